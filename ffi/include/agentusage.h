@@ -0,0 +1,31 @@
+/* C interface to agentusage (built from the agentusage-ffi crate).
+ *
+ * Every call blocks while provider CLIs run (up to the "timeout" option),
+ * so call from a background thread.  Returned strings are JSON, never NULL,
+ * and must be released with agentusage_free.
+ *
+ * options_json may be NULL for defaults, or an object with any of:
+ *   timeout, prompt_timeout (seconds), approval_policy ("fail" | "accept" |
+ *   "onboard"), directory, mock, safe_launch, isolate_env, preseed_trust,
+ *   defer_updates, allow_partial.
+ */
+#ifndef AGENTUSAGE_H
+#define AGENTUSAGE_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* {"success": bool, "results": [...], "warnings": {"<provider>": "<message>"}} */
+char *agentusage_check_all(const char *options_json);
+
+/* {"success": true, "result": {...}} or {"success": false, "error": "..."} */
+char *agentusage_check(const char *provider, const char *options_json);
+
+void agentusage_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AGENTUSAGE_H */
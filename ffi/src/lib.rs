@@ -0,0 +1,273 @@
+#![deny(warnings)]
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use agentusage::{ApprovalPolicy, UsageConfig};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// The options JSON accepted by every entry point.  Omitted fields keep the
+/// library defaults; unknown fields are rejected so typos don't pass
+/// silently.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Options {
+    timeout: u64,
+    prompt_timeout: Option<u64>,
+    approval_policy: Policy,
+    directory: Option<String>,
+    mock: bool,
+    safe_launch: bool,
+    isolate_env: bool,
+    preseed_trust: bool,
+    defer_updates: bool,
+    allow_partial: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        let config = UsageConfig::default();
+        Self {
+            timeout: config.timeout,
+            prompt_timeout: config.prompt_timeout,
+            approval_policy: Policy::Fail,
+            directory: config.directory,
+            mock: config.mock,
+            safe_launch: config.safe_launch,
+            isolate_env: config.isolate_env,
+            preseed_trust: config.preseed_trust,
+            defer_updates: config.defer_updates,
+            allow_partial: config.allow_partial,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Policy {
+    Fail,
+    Accept,
+    Onboard,
+}
+
+impl Options {
+    fn into_config(self) -> UsageConfig {
+        UsageConfig {
+            timeout: self.timeout,
+            prompt_timeout: self.prompt_timeout,
+            approval_policy: match self.approval_policy {
+                Policy::Fail => ApprovalPolicy::Fail,
+                Policy::Accept => ApprovalPolicy::Accept,
+                Policy::Onboard => ApprovalPolicy::Onboard,
+            },
+            directory: self.directory,
+            mock: self.mock,
+            safe_launch: self.safe_launch,
+            isolate_env: self.isolate_env,
+            preseed_trust: self.preseed_trust,
+            defer_updates: self.defer_updates,
+            allow_partial: self.allow_partial,
+            ..UsageConfig::default()
+        }
+    }
+}
+
+/// Read a nullable C string argument.  NULL reads as `None`.
+///
+/// # Safety
+/// `ptr` must be NULL or a valid NUL-terminated string.
+unsafe fn optional_str<'a>(ptr: *const c_char, what: &str) -> Result<Option<&'a str>, String> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(Some)
+        .map_err(|_| format!("{} is not valid UTF-8", what))
+}
+
+fn parse_options(text: Option<&str>) -> Result<UsageConfig, String> {
+    match text.map(str::trim).filter(|t| !t.is_empty()) {
+        None => Ok(UsageConfig::default()),
+        Some(text) => serde_json::from_str::<Options>(text)
+            .map(Options::into_config)
+            .map_err(|e| format!("Invalid options: {}", e)),
+    }
+}
+
+fn error_document(message: &str) -> Value {
+    json!({ "success": false, "error": message })
+}
+
+fn check_document(provider: &str, config: &UsageConfig) -> Value {
+    match agentusage::run_provider(config, provider) {
+        Ok(data) => json!({ "success": true, "result": data }),
+        Err(e) => error_document(&format!("{:#}", e)),
+    }
+}
+
+fn check_all_document(config: &UsageConfig) -> Value {
+    let all = agentusage::run_all(config);
+    json!({
+        "success": !all.results.is_empty(),
+        "results": all.results,
+        "warnings": all.warnings,
+    })
+}
+
+/// Run `body`, turning a panic into an error document (unwinding across the
+/// C boundary is undefined behaviour) and the result into an owned C string.
+fn respond(body: impl FnOnce() -> Value) -> *mut c_char {
+    let document = catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|_| error_document("agentusage panicked"));
+    // serde_json escapes control characters, so the text has no interior NUL.
+    CString::new(document.to_string())
+        .expect("JSON has no interior NUL")
+        .into_raw()
+}
+
+/// Check every provider and return a JSON document:
+/// `{"success": bool, "results": [UsageData...], "warnings": {provider: message}}`.
+/// `success` is false only when no provider produced data.
+///
+/// Blocks until all providers finish (bounded by the `timeout` option), so
+/// call it off the UI thread.  `options_json` may be NULL for defaults.
+/// The returned string is never NULL and must be released with
+/// `agentusage_free`.
+///
+/// # Safety
+/// `options_json` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn agentusage_check_all(options_json: *const c_char) -> *mut c_char {
+    respond(|| {
+        let config = match optional_str(options_json, "options").and_then(parse_options) {
+            Ok(config) => config,
+            Err(e) => return error_document(&e),
+        };
+        check_all_document(&config)
+    })
+}
+
+/// Check one provider (`"claude"`, `"codex"` or `"gemini"`) and return
+/// `{"success": true, "result": UsageData}` or
+/// `{"success": false, "error": message}`.  Same blocking and ownership
+/// rules as `agentusage_check_all`.
+///
+/// # Safety
+/// `provider` must be a valid NUL-terminated string; `options_json` must be
+/// NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn agentusage_check(
+    provider: *const c_char,
+    options_json: *const c_char,
+) -> *mut c_char {
+    respond(|| {
+        let provider = match optional_str(provider, "provider") {
+            Ok(Some(provider)) => provider,
+            Ok(None) => return error_document("provider is NULL"),
+            Err(e) => return error_document(&e),
+        };
+        let config = match optional_str(options_json, "options").and_then(parse_options) {
+            Ok(config) => config,
+            Err(e) => return error_document(&e),
+        };
+        check_document(provider, &config)
+    })
+}
+
+/// Release a string returned by this library.  NULL is ignored.
+///
+/// # Safety
+/// `s` must be NULL or a pointer returned by `agentusage_check_all` or
+/// `agentusage_check` that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn agentusage_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Call an entry point the way a C caller would and parse the result.
+    fn call(f: impl FnOnce(*const c_char) -> *mut c_char, options: Option<&str>) -> Value {
+        let options = options.map(|o| CString::new(o).unwrap());
+        let ptr = f(options.as_ref().map_or(std::ptr::null(), |o| o.as_ptr()));
+        assert!(!ptr.is_null());
+        let text = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { agentusage_free(ptr) };
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn test_check_all_mock_returns_every_provider() {
+        let doc = call(
+            |o| unsafe { agentusage_check_all(o) },
+            Some(r#"{"mock": true}"#),
+        );
+        assert_eq!(doc["success"], true);
+        let providers: Vec<&str> = doc["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["provider"].as_str().unwrap())
+            .collect();
+        assert_eq!(providers, agentusage::ALL_PROVIDERS);
+        assert!(doc["results"][0]["entries"][0]["percent_used"].is_u64());
+        assert_eq!(doc["warnings"], json!({}));
+    }
+
+    #[test]
+    fn test_check_single_provider() {
+        let provider = CString::new("codex").unwrap();
+        let doc = call(
+            |o| unsafe { agentusage_check(provider.as_ptr(), o) },
+            Some(r#"{"mock": true, "approval_policy": "accept"}"#),
+        );
+        assert_eq!(doc["success"], true);
+        assert_eq!(doc["result"]["provider"], "codex");
+
+        let unknown = CString::new("bogus").unwrap();
+        let doc = call(
+            |o| unsafe { agentusage_check(unknown.as_ptr(), o) },
+            Some(r#"{"mock": true}"#),
+        );
+        assert_eq!(doc["success"], false);
+        assert!(doc["error"].as_str().unwrap().contains("bogus"));
+    }
+
+    #[test]
+    fn test_bad_arguments_return_error_documents() {
+        let doc = call(
+            |o| unsafe { agentusage_check_all(o) },
+            Some(r#"{"timout": 10}"#),
+        );
+        assert_eq!(doc["success"], false);
+        assert!(doc["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid options"));
+
+        let doc = call(|o| unsafe { agentusage_check(std::ptr::null(), o) }, None);
+        assert_eq!(doc["error"], "provider is NULL");
+    }
+
+    #[test]
+    fn test_options_default_to_library_defaults() {
+        let config = parse_options(None).unwrap();
+        assert_eq!(config.timeout, agentusage::DEFAULT_TIMEOUT_SECS);
+        assert!(config.safe_launch);
+        assert!(!config.mock);
+        let config = parse_options(Some(r#"{"timeout": 10}"#)).unwrap();
+        assert_eq!(config.timeout, 10);
+        assert!(config.safe_launch);
+    }
+
+    #[test]
+    fn test_free_ignores_null() {
+        unsafe { agentusage_free(std::ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,100 @@
+#![deny(warnings)]
+
+use agentusage::{ApprovalPolicy, UsageConfig, ALL_PROVIDERS};
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Error, JsUnknown, Result, Task};
+use napi_derive::napi;
+use serde_json::{json, Value};
+
+/// Options for `checkUsage`.  Omitted fields keep the library defaults.
+#[napi(object)]
+#[derive(Default)]
+pub struct CheckOptions {
+    /// Providers to check; defaults to every built-in provider (`ALL_PROVIDERS`).
+    pub providers: Option<Vec<String>>,
+    /// Seconds to wait for usage data.
+    pub timeout: Option<u32>,
+    /// Seconds to wait for each CLI's prompt.
+    pub prompt_timeout: Option<u32>,
+    /// `"fail"` (default), `"accept"`, or `"onboard"`.
+    pub approval_policy: Option<String>,
+    /// Working directory for the CLIs; defaults to a fresh temp directory.
+    pub directory: Option<String>,
+    /// Return built-in fixture data instead of launching any CLI.
+    pub mock: Option<bool>,
+    pub safe_launch: Option<bool>,
+    pub isolate_env: Option<bool>,
+    pub preseed_trust: Option<bool>,
+    pub defer_updates: Option<bool>,
+    /// Return entries parsed before a data timeout instead of failing.
+    pub allow_partial: Option<bool>,
+}
+
+fn approval_policy(name: &str) -> Result<ApprovalPolicy> {
+    match name {
+        "fail" => Ok(ApprovalPolicy::Fail),
+        "accept" => Ok(ApprovalPolicy::Accept),
+        "onboard" => Ok(ApprovalPolicy::Onboard),
+        other => Err(Error::from_reason(format!(
+            "Unknown approvalPolicy '{}' (expected fail, accept, or onboard)",
+            other
+        ))),
+    }
+}
+
+fn usage_config(options: &CheckOptions) -> Result<UsageConfig> {
+    let defaults = UsageConfig::default();
+    Ok(UsageConfig {
+        timeout: options.timeout.map_or(defaults.timeout, u64::from),
+        prompt_timeout: options.prompt_timeout.map(u64::from),
+        approval_policy: match &options.approval_policy {
+            Some(name) => approval_policy(name)?,
+            None => defaults.approval_policy,
+        },
+        directory: options.directory.clone(),
+        mock: options.mock.unwrap_or(defaults.mock),
+        safe_launch: options.safe_launch.unwrap_or(defaults.safe_launch),
+        isolate_env: options.isolate_env.unwrap_or(defaults.isolate_env),
+        preseed_trust: options.preseed_trust.unwrap_or(defaults.preseed_trust),
+        defer_updates: options.defer_updates.unwrap_or(defaults.defer_updates),
+        allow_partial: options.allow_partial.unwrap_or(defaults.allow_partial),
+        ..defaults
+    })
+}
+
+/// Runs the checks on libuv's thread pool so the event loop stays free.
+pub struct CheckTask {
+    providers: Vec<String>,
+    config: UsageConfig,
+}
+
+impl Task for CheckTask {
+    type Output = Value;
+    type JsValue = JsUnknown;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let providers: Vec<&str> = self.providers.iter().map(String::as_str).collect();
+        let all = agentusage::collect_results(
+            &providers,
+            agentusage::run_streaming(&self.config, &providers),
+        );
+        Ok(json!({ "results": all.results, "warnings": all.warnings }))
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        env.to_js_value(&output)
+    }
+}
+
+/// Check provider usage limits.  Resolves to
+/// `{ results: UsageData[], warnings: { [provider]: string } }`; a provider
+/// that fails lands in `warnings` rather than rejecting the promise.
+#[napi(ts_return_type = "Promise<{ results: any[]; warnings: Record<string, string> }>")]
+pub fn check_usage(options: Option<CheckOptions>) -> Result<AsyncTask<CheckTask>> {
+    let options = options.unwrap_or_default();
+    let config = usage_config(&options)?;
+    let providers = options
+        .providers
+        .unwrap_or_else(|| ALL_PROVIDERS.iter().map(|p| p.to_string()).collect());
+    Ok(AsyncTask::new(CheckTask { providers, config }))
+}
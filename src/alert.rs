@@ -0,0 +1,318 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::thresholds::Thresholds;
+use crate::types::UsageData;
+
+/// `$XDG_STATE_HOME/agentusage/alerts.json` (default
+/// `~/.local/state/agentusage/alerts.json`), where notifications keep which
+/// limits they have already raised between runs.
+pub fn default_path() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    let state_home = var("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state_home.join("agentusage").join("alerts.json"))
+}
+
+/// When a usage alert is raised and cleared.  An alert fires once a limit
+/// reaches `trigger` percent used and clears only after usage falls below
+/// `clear`, so a limit hovering around the boundary doesn't flap; a limit
+/// that re-triggers within `cooldown` of its last alert stays quiet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlertPolicy {
+    pub trigger: u32,
+    /// Values above `trigger` are treated as `trigger` (no hysteresis).
+    pub clear: u32,
+    pub cooldown: Duration,
+}
+
+impl Default for AlertPolicy {
+    fn default() -> Self {
+        Self {
+            trigger: 90,
+            clear: 80,
+            cooldown: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertTransition {
+    Raised,
+    Cleared,
+}
+
+/// A change worth notifying about for one limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub provider: String,
+    pub label: String,
+    pub percent_used: u32,
+    pub transition: AlertTransition,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LimitState {
+    /// Above `trigger` and not yet back below `clear`.
+    active: bool,
+    /// The current activation was announced (not swallowed by the cooldown).
+    announced: bool,
+    last_raised: Option<DateTime<Utc>>,
+}
+
+/// Turns repeated usage readings into raise/clear alerts under an
+/// `AlertPolicy`, for callers that poll on an interval.  `load` and `save`
+/// carry the state across runs, e.g. from cron.
+#[derive(Debug)]
+pub struct AlertTracker {
+    policy: AlertPolicy,
+    thresholds: Option<Thresholds>,
+    /// "provider/label@trigger" → state, so the same limit watched at two
+    /// levels (notification routes with different `below`) is tracked
+    /// once per level.
+    limits: BTreeMap<String, LimitState>,
+}
+
+impl AlertTracker {
+    pub fn new(policy: AlertPolicy) -> Self {
+        Self {
+            policy,
+            thresholds: None,
+            limits: BTreeMap::new(),
+        }
+    }
+
+    /// A tracker resuming from the state `save` wrote to `path`, or a
+    /// fresh one when there is no file yet.
+    pub fn load(policy: AlertPolicy, path: &Path) -> Result<Self> {
+        let mut tracker = Self::new(policy);
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                tracker.limits = serde_json::from_str(&text)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        }
+        Ok(tracker)
+    }
+
+    /// Write the limits' state to `path` for the next `load`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".tmp");
+        let temp = path.with_file_name(file_name);
+        std::fs::write(&temp, serde_json::to_string_pretty(&self.limits)?)
+            .and_then(|()| std::fs::rename(&temp, path))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Trigger at each limit's window threshold instead of
+    /// `policy.trigger`, keeping the policy's gap between trigger and clear.
+    pub fn with_thresholds(mut self, thresholds: Thresholds) -> Self {
+        self.thresholds = Some(thresholds);
+        self
+    }
+
+    /// Feed one provider's reading taken at `now`; returns the alerts it
+    /// causes.
+    pub fn observe(&mut self, data: &UsageData, now: DateTime<Utc>) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for entry in &data.entries {
+            let trigger = match &self.thresholds {
+                Some(thresholds) => thresholds.warn_at(entry.window),
+                None => self.policy.trigger,
+            };
+            let used = entry.percent_used;
+            let transition = self.observe_limit(&data.provider, &entry.label, used, trigger, now);
+            if let Some(transition) = transition {
+                alerts.push(Alert {
+                    provider: data.provider.clone(),
+                    label: entry.label.clone(),
+                    percent_used: used,
+                    transition,
+                });
+            }
+        }
+        alerts
+    }
+
+    /// Feed one limit's `level` (percent used) against `trigger`, which
+    /// replaces `policy.trigger` and keeps its gap to the clear level.
+    pub fn observe_limit(
+        &mut self,
+        provider: &str,
+        label: &str,
+        level: u32,
+        trigger: u32,
+        now: DateTime<Utc>,
+    ) -> Option<AlertTransition> {
+        let gap = self.policy.trigger - self.policy.clear.min(self.policy.trigger);
+        let clear = trigger.saturating_sub(gap);
+        let state = self
+            .limits
+            .entry(format!("{}/{}@{}", provider, label, trigger))
+            .or_default();
+        if !state.active && level >= trigger {
+            state.active = true;
+            let cooling = state.last_raised.is_some_and(|at| {
+                (now - at)
+                    .to_std()
+                    .is_ok_and(|since| since < self.policy.cooldown)
+            });
+            state.announced = !cooling;
+            if cooling {
+                None
+            } else {
+                state.last_raised = Some(now);
+                Some(AlertTransition::Raised)
+            }
+        } else if state.active && level < clear {
+            state.active = false;
+            std::mem::take(&mut state.announced).then_some(AlertTransition::Cleared)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CheckStats, Confidence, LimitScope, LimitWindow, PercentKind, UsageEntry};
+
+    fn reading(used: u32) -> UsageData {
+        UsageData {
+            provider: "codex".into(),
+            entries: vec![UsageEntry {
+                label: "5h limit".into(),
+                percent_used: used,
+                percent_remaining: 100 - used,
+                percent_kind: PercentKind::Used,
+                reset_info: String::new(),
+                reset_minutes: None,
+                spent: None,
+                requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Session,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
+            }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
+        }
+    }
+
+    fn transitions(
+        tracker: &mut AlertTracker,
+        readings: &[(u32, u64)],
+        start: DateTime<Utc>,
+    ) -> Vec<(u32, AlertTransition)> {
+        readings
+            .iter()
+            .flat_map(|&(used, minute)| {
+                let now = start + chrono::Duration::minutes(minute as i64);
+                tracker.observe(&reading(used), now)
+            })
+            .map(|alert| (alert.percent_used, alert.transition))
+            .collect()
+    }
+
+    #[test]
+    fn test_hovering_at_trigger_alerts_once() {
+        let mut tracker = AlertTracker::new(AlertPolicy::default());
+        let got = transitions(
+            &mut tracker,
+            &[(89, 0), (90, 1), (88, 2), (91, 3), (85, 4), (79, 5)],
+            Utc::now(),
+        );
+        assert_eq!(
+            got,
+            vec![
+                (90, AlertTransition::Raised),
+                (79, AlertTransition::Cleared)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cooldown_suppresses_repeat_alerts() {
+        let mut tracker = AlertTracker::new(AlertPolicy::default());
+        let got = transitions(
+            &mut tracker,
+            // Raised, cleared, re-triggered inside 30 minutes (quiet, and so
+            // is its clear), then re-triggered after the cooldown.
+            &[(95, 0), (50, 5), (95, 10), (50, 15), (95, 45)],
+            Utc::now(),
+        );
+        assert_eq!(
+            got,
+            vec![
+                (95, AlertTransition::Raised),
+                (50, AlertTransition::Cleared),
+                (95, AlertTransition::Raised),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_window_thresholds_move_trigger_and_clear() {
+        let mut thresholds = Thresholds::default();
+        thresholds.set(Some(LimitWindow::Session), 70);
+        let mut tracker = AlertTracker::new(AlertPolicy::default()).with_thresholds(thresholds);
+        let got = transitions(
+            &mut tracker,
+            &[(69, 0), (72, 1), (65, 2), (59, 3)],
+            Utc::now(),
+        );
+        assert_eq!(
+            got,
+            vec![
+                (72, AlertTransition::Raised),
+                (59, AlertTransition::Cleared)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_limits_are_tracked_separately() {
+        let mut tracker = AlertTracker::new(AlertPolicy::default());
+        let now = Utc::now();
+        assert_eq!(tracker.observe(&reading(95), now).len(), 1);
+        let mut other = reading(95);
+        other.provider = "claude".into();
+        assert_eq!(tracker.observe(&other, now).len(), 1);
+    }
+
+    #[test]
+    fn test_state_survives_save_and_load() {
+        let dir =
+            std::env::temp_dir().join(format!("agentusage-alert-test-{}", std::process::id()));
+        let path = dir.join("state/alerts.json");
+        let start = Utc::now();
+        let mut tracker = AlertTracker::load(AlertPolicy::default(), &path).unwrap();
+        assert_eq!(transitions(&mut tracker, &[(95, 0)], start).len(), 1);
+        tracker.save(&path).unwrap();
+
+        // The next run still sees the limit as raised, then clears it.
+        let mut tracker = AlertTracker::load(AlertPolicy::default(), &path).unwrap();
+        let got = transitions(&mut tracker, &[(96, 5), (50, 10)], start);
+        assert_eq!(got, vec![(50, AlertTransition::Cleared)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
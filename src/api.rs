@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::UsageConfig;
+
+pub use crate::types::{
+    ApprovalPolicy, Attempt, AttemptKind, Capabilities, CheckFailure, CheckStats, DataSource,
+    LimitScope, LimitWindow, PercentKind, SourceMode, TokenUsage, UsageData, UsageEntry,
+};
+pub use crate::AllResults;
+
+/// What a check may do and how long it may take.  New fields are added
+/// with defaults that keep today's behaviour, so start from `default()`
+/// and set the ones you need.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Options {
+    /// How long each provider may take to produce usage data.
+    pub timeout: Duration,
+    /// What to do when a provider shows a dialog (trust, update, ...).
+    pub approval_policy: ApprovalPolicy,
+    /// Working directory for the provider CLIs.  `None` uses a fresh, empty
+    /// temp directory.
+    pub directory: Option<PathBuf>,
+    /// Launch providers read-only so a check can never run tools.
+    pub safe_launch: bool,
+    /// Give each provider private cache/state/tmp directories.
+    pub isolate_env: bool,
+    /// Return built-in fixture data instead of launching any CLI.
+    pub mock: bool,
+    /// When the wait for usage data times out after some limits were
+    /// parsed, return those with `check.partial` set instead of an error.
+    pub allow_partial: bool,
+    /// Where usage is read from; `check.source` on each result says which
+    /// source in the chain produced it.
+    pub source: SourceMode,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        let config = UsageConfig::default();
+        Self {
+            timeout: Duration::from_secs(config.timeout),
+            approval_policy: config.approval_policy,
+            directory: None,
+            safe_launch: config.safe_launch,
+            isolate_env: config.isolate_env,
+            mock: config.mock,
+            allow_partial: config.allow_partial,
+            source: config.source,
+        }
+    }
+}
+
+impl Options {
+    fn to_config(&self) -> UsageConfig {
+        UsageConfig {
+            timeout: self.timeout.as_secs().max(1),
+            approval_policy: self.approval_policy,
+            directory: self
+                .directory
+                .as_ref()
+                .map(|dir| dir.to_string_lossy().into_owned()),
+            safe_launch: self.safe_launch,
+            isolate_env: self.isolate_env,
+            mock: self.mock,
+            allow_partial: self.allow_partial,
+            source: self.source,
+            ..UsageConfig::default()
+        }
+    }
+}
+
+/// Providers `check` accepts, in the order `check_all` reports them.
+pub fn providers() -> &'static [&'static str] {
+    &crate::ALL_PROVIDERS
+}
+
+/// Check one provider by name.
+pub fn check(provider: &str, options: &Options) -> Result<UsageData> {
+    crate::run_provider(&options.to_config(), provider)
+}
+
+/// Check every provider in parallel.  A provider that fails is reported in
+/// `warnings` rather than failing the whole call.
+pub fn check_all(options: &Options) -> AllResults {
+    crate::run_all(&options.to_config())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock() -> Options {
+        Options {
+            mock: true,
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn test_check_and_check_all_with_mock_data() {
+        let data = check("gemini", &mock()).unwrap();
+        assert_eq!(data.provider, "gemini");
+        assert!(check("bogus", &mock()).is_err());
+
+        let all = check_all(&mock());
+        let names: Vec<&str> = all.results.iter().map(|d| d.provider.as_str()).collect();
+        assert_eq!(names, providers());
+    }
+
+    #[test]
+    fn test_options_map_onto_config() {
+        let options = Options {
+            timeout: Duration::from_millis(1500),
+            directory: Some(PathBuf::from("/work")),
+            ..Options::default()
+        };
+        let config = options.to_config();
+        assert_eq!(config.timeout, 1);
+        assert_eq!(config.directory.as_deref(), Some("/work"));
+        assert!(config.safe_launch);
+        assert_eq!(
+            Options::default().to_config().timeout,
+            UsageConfig::default().timeout
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{run_provider, CheckEvent, CheckPhase, UsageConfig};
+
+/// Timings from one benchmark iteration.
+#[derive(Debug, Clone)]
+pub struct BenchSample {
+    /// Launch until the usage command was sent (the prompt was ready).
+    pub to_prompt: Option<Duration>,
+    /// Launch until usage data was parsed, or the check failed.
+    pub to_data: Duration,
+    pub error: Option<String>,
+}
+
+/// Summary of a set of durations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchStats {
+    pub min: Duration,
+    pub median: Duration,
+    pub mean: Duration,
+    pub max: Duration,
+}
+
+impl BenchStats {
+    /// `None` when there are no samples.
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let mid = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[mid - 1] + sorted[mid]) / 2
+        } else {
+            sorted[mid]
+        };
+        let total: Duration = sorted.iter().sum();
+        Some(Self {
+            min: sorted[0],
+            median,
+            mean: total / sorted.len() as u32,
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// Time one full check of `provider`.
+pub fn bench_once(config: &UsageConfig, provider: &str) -> BenchSample {
+    let started = Instant::now();
+    let prompt_at = Arc::new(Mutex::new(None));
+    let mark = Arc::clone(&prompt_at);
+    let mut config = config.clone();
+    config
+        .observers
+        .push(Arc::new(move |_: &str, event: &CheckEvent| {
+            if *event == CheckEvent::Phase(CheckPhase::FetchingUsage) {
+                if let Ok(mut at) = mark.lock() {
+                    at.get_or_insert_with(|| started.elapsed());
+                }
+            }
+        }));
+    let result = run_provider(&config, provider);
+    let to_data = started.elapsed();
+    let to_prompt = prompt_at.lock().ok().and_then(|at| *at);
+    BenchSample {
+        to_prompt,
+        to_data,
+        error: result.err().map(|e| format!("{:#}", e)),
+    }
+}
+
+/// Run `iterations` sequential checks of `provider`.  Runs are sequential so
+/// providers don't compete for CPU and skew each other's numbers.
+pub fn bench_provider(config: &UsageConfig, provider: &str, iterations: u32) -> Vec<BenchSample> {
+    (0..iterations)
+        .map(|_| bench_once(config, provider))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_stats_odd_count() {
+        let stats = BenchStats::from_samples(&[ms(300), ms(100), ms(200)]).unwrap();
+        assert_eq!(stats.min, ms(100));
+        assert_eq!(stats.median, ms(200));
+        assert_eq!(stats.mean, ms(200));
+        assert_eq!(stats.max, ms(300));
+    }
+
+    #[test]
+    fn test_stats_even_count_median_averages() {
+        let stats = BenchStats::from_samples(&[ms(100), ms(400), ms(200), ms(300)]).unwrap();
+        assert_eq!(stats.median, ms(250));
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        assert!(BenchStats::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn test_bench_mock_records_prompt_and_data() {
+        let config = UsageConfig {
+            mock: true,
+            ..UsageConfig::default()
+        };
+        let samples = bench_provider(&config, "codex", 2);
+        assert_eq!(samples.len(), 2);
+        for sample in &samples {
+            assert!(sample.error.is_none());
+            assert!(sample.to_prompt.unwrap() <= sample.to_data);
+        }
+    }
+}
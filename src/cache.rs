@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::types::UsageData;
+
+/// A provider's last successful result, shared between agentusage runs.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedResult {
+    pub checked_at: DateTime<Utc>,
+    pub data: UsageData,
+}
+
+impl CachedResult {
+    /// The data with every entry marked as of `checked_at`, for serving a
+    /// value that may be out of date.
+    pub fn into_stale(mut self) -> UsageData {
+        for entry in &mut self.data.entries {
+            entry.as_of.get_or_insert(self.checked_at);
+        }
+        self.data
+    }
+}
+
+/// `$XDG_CACHE_HOME/agentusage` (default `~/.cache/agentusage`).
+pub fn cache_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    let cache_home = var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache_home.join("agentusage"))
+}
+
+fn path_in(dir: &Path, provider: &str) -> PathBuf {
+    dir.join(format!("{}.json", provider))
+}
+
+/// Replace `data.provider`'s cached result, stamped now.
+pub fn store(data: &UsageData) -> Result<()> {
+    let dir = cache_dir().context("No cache directory: set HOME or XDG_CACHE_HOME")?;
+    store_in(&dir, data, Utc::now())
+}
+
+fn store_in(dir: &Path, data: &UsageData, at: DateTime<Utc>) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = path_in(dir, &data.provider);
+    // Write aside and rename, so a concurrent reader never sees half a file.
+    let partial = path.with_extension(format!("json.{}", std::process::id()));
+    let json = serde_json::to_vec(&serde_json::json!({ "checked_at": at, "data": data }))?;
+    std::fs::write(&partial, json)
+        .with_context(|| format!("Failed to write {}", partial.display()))?;
+    std::fs::rename(&partial, &path)
+        .with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// `provider`'s cached result, if there is a readable one.
+pub fn load(provider: &str) -> Option<CachedResult> {
+    load_in(&cache_dir()?, provider)
+}
+
+fn load_in(dir: &Path, provider: &str) -> Option<CachedResult> {
+    let bytes = std::fs::read(path_in(dir, provider)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Every readable cached result, by provider name.
+pub fn list() -> Vec<CachedResult> {
+    cache_dir().map(|dir| list_in(&dir)).unwrap_or_default()
+}
+
+fn list_in(dir: &Path) -> Vec<CachedResult> {
+    let Ok(read) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut results: Vec<CachedResult> = read
+        .flatten()
+        .filter_map(|file| {
+            let name = file.file_name().into_string().ok()?;
+            load_in(dir, name.strip_suffix(".json")?)
+        })
+        .collect();
+    results.sort_by(|a, b| a.data.provider.cmp(&b.data.provider));
+    results
+}
+
+/// Delete the cached result of `provider` (every provider when `None`) and
+/// forget when it was last launched, so the next check under `--lock` or
+/// `--min-interval` launches it instead of serving old values.  Returns
+/// the providers whose results were deleted.
+pub fn clear(provider: Option<&str>) -> Result<Vec<String>> {
+    let Some(dir) = cache_dir() else {
+        return Ok(Vec::new());
+    };
+    let cleared = clear_in(&dir, provider)?;
+    #[cfg(feature = "runner")]
+    crate::throttle::forget(provider);
+    Ok(cleared)
+}
+
+fn clear_in(dir: &Path, provider: Option<&str>) -> Result<Vec<String>> {
+    let mut cleared = Vec::new();
+    for cached in list_in(dir) {
+        let name = cached.data.provider;
+        if provider.is_some_and(|p| p != name) {
+            continue;
+        }
+        let path = path_in(dir, &name);
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove {}", path.display()))?;
+        cleared.push(name);
+    }
+    Ok(cleared)
+}
+
+/// How long cached values keep being served for one provider.
+#[cfg(feature = "runner")]
+#[derive(Debug, Serialize)]
+pub struct Freshness {
+    pub provider: String,
+    /// When the cached result was checked, if there is one.
+    pub checked_at: Option<DateTime<Utc>>,
+    /// When any run last launched the provider for a real check.
+    pub last_launch: Option<DateTime<Utc>>,
+    /// Seconds until `--min-interval` lets a run launch the provider again;
+    /// 0 once it may.  `None` without an interval.
+    pub serve_for_secs: Option<u64>,
+}
+
+/// `Freshness` for `providers` under a `--min-interval` of `interval`.
+#[cfg(feature = "runner")]
+pub fn freshness(providers: &[&str], interval: Option<std::time::Duration>) -> Vec<Freshness> {
+    let now = Utc::now();
+    providers
+        .iter()
+        .map(|&provider| {
+            let last_launch = crate::throttle::last_launch(provider);
+            let serve_for_secs = interval.map(|interval| {
+                last_launch
+                    .and_then(|last| (now - last).to_std().ok())
+                    .map_or(0, |since| interval.saturating_sub(since).as_secs())
+            });
+            Freshness {
+                provider: provider.to_string(),
+                checked_at: load(provider).map(|cached| cached.checked_at),
+                last_launch,
+                serve_for_secs,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_load_round_trip() {
+        let dir =
+            std::env::temp_dir().join(format!("agentusage-cache-test-{}", std::process::id()));
+        let data = crate::fixtures::mock_usage("codex").unwrap();
+        let at = "2026-03-01T12:00:00Z".parse().unwrap();
+        store_in(&dir, &data, at).unwrap();
+
+        let cached = load_in(&dir, "codex").unwrap();
+        assert_eq!(cached.checked_at, at);
+        assert_eq!(cached.data.entries.len(), data.entries.len());
+        assert_eq!(cached.data.entries[0].label, data.entries[0].label);
+        assert!(load_in(&dir, "gemini").is_none());
+
+        let stale = cached.into_stale();
+        assert!(stale.entries.iter().all(|entry| entry.as_of == Some(at)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_and_clear() {
+        let dir = std::env::temp_dir().join(format!(
+            "agentusage-cache-clear-test-{}",
+            std::process::id()
+        ));
+        let at = "2026-03-01T12:00:00Z".parse().unwrap();
+        for provider in ["gemini", "claude"] {
+            store_in(&dir, &crate::fixtures::mock_usage(provider).unwrap(), at).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "not a cached result").unwrap();
+        let providers = |dir: &Path| -> Vec<String> {
+            list_in(dir).into_iter().map(|c| c.data.provider).collect()
+        };
+        assert_eq!(providers(&dir), ["claude", "gemini"]);
+
+        assert_eq!(clear_in(&dir, Some("gemini")).unwrap(), ["gemini"]);
+        assert_eq!(providers(&dir), ["claude"]);
+        assert!(clear_in(&dir, Some("codex")).unwrap().is_empty());
+        assert_eq!(clear_in(&dir, None).unwrap(), ["claude"]);
+        assert!(list_in(&dir).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Fixed-capacity byte buffer that drops the oldest bytes once full.
+pub(crate) struct ByteRing {
+    data: VecDeque<u8>,
+    capacity: usize,
+    /// Bytes ever appended, trimmed or not.
+    total: u64,
+}
+
+impl ByteRing {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            data: VecDeque::with_capacity(capacity.min(64 * 1024)),
+            capacity,
+            total: 0,
+        }
+    }
+
+    /// Append `bytes`, dropping the oldest ones past capacity.  A codepoint
+    /// cut in half by the drop is removed whole, so the text still starts
+    /// on a character boundary.
+    pub(crate) fn extend(&mut self, bytes: &[u8]) {
+        self.total += bytes.len() as u64;
+        if bytes.len() >= self.capacity {
+            self.data.clear();
+            self.data.extend(&bytes[bytes.len() - self.capacity..]);
+        } else {
+            let overflow = (self.data.len() + bytes.len()).saturating_sub(self.capacity);
+            if overflow == 0 {
+                self.data.extend(bytes);
+                return;
+            }
+            self.data.drain(..overflow);
+            self.data.extend(bytes);
+        }
+        while self.data.front().is_some_and(|&b| is_continuation(b)) {
+            self.data.pop_front();
+        }
+    }
+
+    /// Contents as text; invalid UTF-8 the child wrote itself is replaced
+    /// rather than rejected.
+    pub(crate) fn text_lossy(&mut self) -> String {
+        String::from_utf8_lossy(self.data.make_contiguous()).into_owned()
+    }
+
+    /// Running count of bytes appended, to pass to `text_since` later.
+    /// Unlike an index into the contents it stays meaningful after a trim.
+    pub(crate) fn mark(&self) -> u64 {
+        self.total
+    }
+
+    /// Text appended after `mark`; everything still held if the oldest of
+    /// it has already been dropped.
+    pub(crate) fn text_since(&mut self, mark: u64) -> String {
+        let fresh = self.total.saturating_sub(mark);
+        let start = self
+            .data
+            .len()
+            .saturating_sub(fresh.try_into().unwrap_or(usize::MAX));
+        String::from_utf8_lossy(&self.data.make_contiguous()[start..]).into_owned()
+    }
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+/// Length of the UTF-8 sequence `bytes` ends partway through (0 if it ends
+/// on a character boundary).
+fn incomplete_utf8_tail(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(4) {
+        let byte = bytes[bytes.len() - back];
+        if is_continuation(byte) {
+            continue;
+        }
+        let needed = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return 0,
+        };
+        return if back < needed { back } else { 0 };
+    }
+    0
+}
+
+/// Write sink shared with the streaming ANSI stripper, which only exposes
+/// its inner writer by value.
+#[derive(Clone, Default)]
+struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+impl SharedSink {
+    fn take(&self) -> Vec<u8> {
+        self.0
+            .lock()
+            .map(|mut buf| std::mem::take(&mut *buf))
+            .unwrap_or_default()
+    }
+}
+
+impl Write for SharedSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.extend_from_slice(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// PTY output captured as it arrives.  ANSI escapes are stripped
+/// incrementally (parser state carries across chunks), so each capture only
+/// pays for the bytes read since the last one instead of re-stripping the
+/// whole history.  A read can end partway through a multi-byte character
+/// (the TUIs' `❯`, `│`, `─`); those bytes wait for the next read so the
+/// stripper and the decoder only ever see whole characters.
+pub(crate) struct CaptureBuffer {
+    text: ByteRing,
+    stripper: strip_ansi_escapes::Writer<SharedSink>,
+    sink: SharedSink,
+    /// Start of a character split by the last read.
+    pending: Vec<u8>,
+}
+
+impl CaptureBuffer {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let sink = SharedSink::default();
+        Self {
+            text: ByteRing::new(capacity),
+            stripper: strip_ansi_escapes::Writer::new(sink.clone()),
+            sink,
+            pending: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        let mut bytes = std::mem::take(&mut self.pending);
+        bytes.extend_from_slice(chunk);
+        let complete = bytes.len() - incomplete_utf8_tail(&bytes);
+        self.pending = bytes.split_off(complete);
+        // Writing into a Vec-backed sink cannot fail.
+        let _ = self.stripper.write_all(&bytes);
+        let _ = self.stripper.flush();
+        let stripped = self.sink.take();
+        self.text.extend(&stripped);
+    }
+
+    /// Stripped text captured so far.
+    pub(crate) fn text(&mut self) -> String {
+        self.text.text_lossy()
+    }
+
+    /// Position in the stripped output, for `text_since`.
+    pub(crate) fn mark(&self) -> u64 {
+        self.text.mark()
+    }
+
+    /// Stripped text captured after `mark`.
+    pub(crate) fn text_since(&mut self, mark: u64) -> String {
+        self.text.text_since(mark)
+    }
+}
+
+/// Cheap fingerprint of a capture, so wait loops can skip re-matching a
+/// screen that hasn't changed since the last poll.
+pub(crate) fn content_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash_tracks_changes() {
+        assert_eq!(content_hash("5% used"), content_hash("5% used"));
+        assert_ne!(content_hash("5% used"), content_hash("6% used"));
+    }
+
+    #[test]
+    fn test_ring_drops_oldest_bytes() {
+        let mut ring = ByteRing::new(5);
+        ring.extend(b"abc");
+        ring.extend(b"def");
+        assert_eq!(ring.text_lossy(), "bcdef");
+    }
+
+    #[test]
+    fn test_ring_oversized_chunk_keeps_tail() {
+        let mut ring = ByteRing::new(3);
+        ring.extend(b"abcdefg");
+        assert_eq!(ring.text_lossy(), "efg");
+    }
+
+    #[test]
+    fn test_capture_matches_full_strip() {
+        let input: &[u8] = b"\x1b[1mBold\x1b[0m line\r\n\x1b[32m42% used\x1b[0m\r\nno newline";
+        let mut capture = CaptureBuffer::new(1024);
+        for chunk in input.chunks(3) {
+            capture.push(chunk);
+        }
+        let expected = String::from_utf8_lossy(&strip_ansi_escapes::strip(input)).to_string();
+        assert_eq!(capture.text(), expected);
+    }
+
+    #[test]
+    fn test_capture_escape_split_across_chunks() {
+        let mut capture = CaptureBuffer::new(1024);
+        capture.push(b"before\x1b[3");
+        capture.push(b"1mafter");
+        assert_eq!(capture.text(), "beforeafter");
+    }
+
+    #[test]
+    fn test_capture_reassembles_characters_split_across_reads() {
+        let input = "\x1b[2m│\x1b[0m ❯ 42% used ─── 𝐱\r\n".as_bytes();
+        for size in 1..=4 {
+            let mut capture = CaptureBuffer::new(1024);
+            for chunk in input.chunks(size) {
+                capture.push(chunk);
+            }
+            assert_eq!(capture.text(), "│ ❯ 42% used ─── 𝐱\n", "chunks of {}", size);
+        }
+    }
+
+    #[test]
+    fn test_incomplete_utf8_tail() {
+        let bar = "│".as_bytes();
+        assert_eq!(incomplete_utf8_tail(b"plain"), 0);
+        assert_eq!(incomplete_utf8_tail(bar), 0);
+        assert_eq!(incomplete_utf8_tail(&bar[..1]), 1);
+        assert_eq!(incomplete_utf8_tail(&bar[..2]), 2);
+        assert_eq!(
+            incomplete_utf8_tail(b"\x80\x80"),
+            0,
+            "stray continuation bytes"
+        );
+    }
+
+    #[test]
+    fn test_ring_trim_keeps_character_boundary() {
+        let mut ring = ByteRing::new(4);
+        ring.extend("ab│".as_bytes());
+        ring.extend(b"cd");
+        assert_eq!(
+            ring.text_lossy(),
+            "cd",
+            "half a `│` is dropped, not garbled"
+        );
+        let mut ring = ByteRing::new(5);
+        ring.extend("x──".as_bytes());
+        assert_eq!(ring.text_lossy(), "─");
+    }
+
+    #[test]
+    fn test_text_since_survives_trim() {
+        let mut capture = CaptureBuffer::new(8);
+        capture.push(b"012345");
+        let mark = capture.mark();
+        assert_eq!(capture.text_since(mark), "");
+        capture.push(b"abc");
+        assert_eq!(capture.text(), "12345abc");
+        assert_eq!(capture.text_since(mark), "abc");
+        capture.push(b"defghijk");
+        assert_eq!(capture.text_since(mark), "defghijk", "all of it is new");
+    }
+
+    #[test]
+    fn test_capture_is_bounded() {
+        let mut capture = CaptureBuffer::new(8);
+        capture.push(b"0123456789abcdef");
+        assert_eq!(capture.text(), "89abcdef");
+    }
+}
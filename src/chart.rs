@@ -0,0 +1,229 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::history::HistoryRecord;
+
+/// Points chart rendering at a TrueType font for its labels.
+pub const FONT_PATH_VAR: &str = "AGENTUSAGE_FONT";
+
+/// Fonts tried when `AGENTUSAGE_FONT` isn't set.
+const FONT_CANDIDATES: [&str; 6] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+];
+
+const SIZE: (u32, u32) = (1000, 500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartFormat {
+    Svg,
+    Png,
+}
+
+impl ChartFormat {
+    /// The format named by `path`'s extension.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match ext.as_deref() {
+            Some("svg") => Ok(ChartFormat::Svg),
+            Some("png") => Ok(ChartFormat::Png),
+            _ => bail!("{}: chart output must end in .svg or .png", path.display()),
+        }
+    }
+}
+
+/// Percent used over time, one line per provider limit, written to `path`
+/// as SVG or PNG by extension.
+pub fn render(records: &[HistoryRecord], path: &Path) -> Result<()> {
+    let format = ChartFormat::from_path(path)?;
+    if records.is_empty() {
+        bail!("No usage history to chart");
+    }
+    register_font()?;
+    match format {
+        ChartFormat::Svg => draw(SVGBackend::new(path, SIZE).into_drawing_area(), records),
+        ChartFormat::Png => draw(BitMapBackend::new(path, SIZE).into_drawing_area(), records),
+    }
+    .with_context(|| format!("Failed to render {}", path.display()))
+}
+
+fn register_font() -> Result<()> {
+    let path = std::env::var_os(FONT_PATH_VAR)
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            FONT_CANDIDATES
+                .iter()
+                .map(PathBuf::from)
+                .find(|p| p.is_file())
+        })
+        .with_context(|| {
+            format!(
+                "No TrueType font found for chart labels; set {} to a .ttf file",
+                FONT_PATH_VAR
+            )
+        })?;
+    let bytes =
+        std::fs::read(&path).with_context(|| format!("Failed to read font {}", path.display()))?;
+    // plotters keeps registered fonts for the life of the process.
+    let bytes: &'static [u8] = Box::leak(bytes.into_boxed_slice());
+    plotters::style::register_font("sans-serif", FontStyle::Normal, bytes)
+        .map_err(|_| anyhow!("{} is not a usable TrueType font", path.display()))
+}
+
+/// `provider · label` (`host/provider · label` for another machine's) →
+/// readings in time order.
+fn series(records: &[HistoryRecord]) -> BTreeMap<String, Vec<(DateTime<Utc>, u32)>> {
+    let mut lines: BTreeMap<String, Vec<(DateTime<Utc>, u32)>> = BTreeMap::new();
+    for record in records {
+        lines
+            .entry(match &record.host {
+                Some(host) => format!("{}/{} · {}", host, record.provider, record.label),
+                None => format!("{} · {}", record.provider, record.label),
+            })
+            .or_default()
+            .push((record.timestamp, record.percent_used));
+    }
+    for points in lines.values_mut() {
+        points.sort_by_key(|(at, _)| *at);
+    }
+    lines
+}
+
+fn draw<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, records: &[HistoryRecord]) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let start = records
+        .iter()
+        .map(|r| r.timestamp)
+        .min()
+        .unwrap_or_default();
+    let end = records
+        .iter()
+        .map(|r| r.timestamp)
+        .max()
+        .unwrap_or_default();
+    // x is hours since `start`; a single reading still gets an hour of width.
+    let hours = |at: DateTime<Utc>| (at - start).num_seconds() as f64 / 3600.0;
+    let span = hours(end).max(1.0);
+
+    root.fill(&WHITE).map_err(|e| anyhow!("{}", e))?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Usage over time (% used)", ("sans-serif", 20))
+        .margin(15)
+        .margin_right(40)
+        .x_label_area_size(35)
+        .y_label_area_size(45)
+        .build_cartesian_2d(0f64..span, 0u32..100u32)
+        .map_err(|e| anyhow!("{}", e))?;
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|h| {
+            let at = start + chrono::Duration::seconds((h * 3600.0) as i64);
+            at.with_timezone(&chrono::Local)
+                .format("%b %d %H:%M")
+                .to_string()
+        })
+        .y_label_formatter(&|pct| format!("{}%", pct))
+        .draw()
+        .map_err(|e| anyhow!("{}", e))?;
+
+    for (i, (name, points)) in series(records).into_iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart
+            .draw_series(LineSeries::new(
+                points.iter().map(|(at, used)| (hours(*at), *used)),
+                color.stroke_width(2),
+            ))
+            .map_err(|e| anyhow!("{}", e))?
+            .label(name)
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], color.stroke_width(2)));
+        // Markers keep sparse histories (a reading or two) visible.
+        chart
+            .draw_series(
+                points
+                    .iter()
+                    .map(|(at, used)| Circle::new((hours(*at), *used), 3, color.filled())),
+            )
+            .map_err(|e| anyhow!("{}", e))?;
+    }
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperLeft)
+        .draw()
+        .map_err(|e| anyhow!("{}", e))?;
+    root.present().map_err(|e| anyhow!("{}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LimitWindow;
+
+    fn record(at: &str, label: &str, used: u32) -> HistoryRecord {
+        HistoryRecord {
+            timestamp: at.parse().unwrap(),
+            provider: "codex".into(),
+            label: label.into(),
+            window: LimitWindow::Session,
+            percent_used: used,
+            reset_minutes: None,
+            spent: None,
+            project: None,
+            snapshot: None,
+            host: None,
+            estimated: false,
+        }
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(
+            ChartFormat::from_path(Path::new("usage.svg")).unwrap(),
+            ChartFormat::Svg
+        );
+        assert_eq!(
+            ChartFormat::from_path(Path::new("usage.PNG")).unwrap(),
+            ChartFormat::Png
+        );
+        assert!(ChartFormat::from_path(Path::new("usage.pdf")).is_err());
+        assert!(ChartFormat::from_path(Path::new("usage")).is_err());
+    }
+
+    #[test]
+    fn test_series_groups_and_sorts_readings() {
+        let lines = series(&[
+            record("2026-03-01T12:00:00Z", "5h limit", 30),
+            record("2026-03-01T10:00:00Z", "5h limit", 10),
+            record("2026-03-01T10:00:00Z", "Weekly limit", 40),
+        ]);
+        assert_eq!(
+            lines.keys().collect::<Vec<_>>(),
+            vec!["codex · 5h limit", "codex · Weekly limit"]
+        );
+        let used: Vec<u32> = lines["codex · 5h limit"].iter().map(|p| p.1).collect();
+        assert_eq!(used, vec![10, 30]);
+    }
+
+    #[test]
+    fn test_render_rejects_empty_history() {
+        let err = render(&[], Path::new("/tmp/agentusage-empty.svg")).unwrap_err();
+        assert!(err.to_string().contains("No usage history"));
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, TimeZone, Timelike, Utc};
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::parser::{scope_for_label, window_for_label};
+use crate::types::{CheckStats, Confidence, DataSource, PercentKind, UsageData, UsageEntry};
+
+/// The endpoint Claude Code's `/usage` screen is drawn from.
+const USAGE_URL: &str = "https://api.anthropic.com/api/oauth/usage";
+
+#[derive(Debug, Deserialize)]
+struct CredentialsFile {
+    #[serde(rename = "claudeAiOauth", default)]
+    oauth: Option<OauthCredentials>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OauthCredentials {
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    /// Milliseconds since the epoch.
+    #[serde(rename = "expiresAt", default)]
+    expires_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageResponse {
+    #[serde(default)]
+    five_hour: Option<UsageWindow>,
+    #[serde(default)]
+    seven_day: Option<UsageWindow>,
+    #[serde(default)]
+    seven_day_sonnet: Option<UsageWindow>,
+    #[serde(default)]
+    seven_day_opus: Option<UsageWindow>,
+    #[serde(default)]
+    extra_usage: Option<ExtraUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageWindow {
+    utilization: f64,
+    #[serde(default)]
+    resets_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtraUsage {
+    is_enabled: bool,
+    /// Cents.
+    #[serde(default)]
+    monthly_limit: Option<f64>,
+    /// Cents.
+    #[serde(default)]
+    used_credits: Option<f64>,
+    #[serde(default)]
+    utilization: Option<f64>,
+}
+
+/// The OAuth login Claude Code keeps in `~/.claude/.credentials.json`
+/// (`$CLAUDE_CONFIG_DIR` when set), or on macOS in the login keychain.
+fn read_credentials() -> Result<String> {
+    let path = crate::trust::claude_home()?.join(".credentials.json");
+    match std::fs::read_to_string(&path) {
+        Ok(text) => return Ok(text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+    if cfg!(target_os = "macos") {
+        let output = std::process::Command::new("security")
+            .args([
+                "find-generic-password",
+                "-s",
+                "Claude Code-credentials",
+                "-w",
+            ])
+            .output()
+            .context("Failed to run 'security'")?;
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+    bail!(
+        "No Claude login found in {} or the keychain",
+        path.display()
+    )
+}
+
+/// Read Claude's subscription usage from the OAuth usage endpoint with
+/// Claude Code's login, giving up after `timeout`.
+pub fn fetch(timeout: Duration) -> Result<UsageData> {
+    let credentials: CredentialsFile =
+        serde_json::from_str(&read_credentials()?).context("Failed to parse Claude credentials")?;
+    let Some(oauth) = credentials.oauth else {
+        bail!("Claude credentials have no subscription login (API-key logins have no usage limits to read)");
+    };
+    if oauth
+        .expires_at
+        .is_some_and(|at| at <= Utc::now().timestamp_millis())
+    {
+        bail!("Claude login has expired; run 'claude' to refresh it");
+    }
+    let bearer = format!("Bearer {}", oauth.access_token);
+    let headers = [
+        ("Authorization", bearer.as_str()),
+        ("anthropic-beta", "oauth-2025-04-20"),
+    ];
+    let body = crate::http::get_usage(USAGE_URL, &headers, timeout, "Claude", "claude")?;
+    parse_usage_response(&body, Utc::now())
+}
+
+/// Parse the usage endpoint's JSON into the entries `/usage` shows, as of
+/// `now`.
+pub fn parse_usage_response(body: &str, now: DateTime<Utc>) -> Result<UsageData> {
+    let response: UsageResponse = serde_json::from_str(body)
+        .map_err(|e| anyhow::anyhow!("[parse-failure] Invalid Claude usage response: {}", e))?;
+    let windows = [
+        ("Current session", response.five_hour),
+        ("Current week (all models)", response.seven_day),
+        ("Current week (Sonnet only)", response.seven_day_sonnet),
+        ("Current week (Opus only)", response.seven_day_opus),
+    ];
+    let mut entries: Vec<UsageEntry> = windows
+        .into_iter()
+        .filter_map(|(label, window)| {
+            let window = window?;
+            let reset_minutes = window
+                .resets_at
+                .map(|at| ((at - now).num_seconds().max(0) + 59) / 60);
+            let reset_info = window
+                .resets_at
+                .map(|at| reset_text(at.with_timezone(&Local), now.with_timezone(&Local)))
+                .unwrap_or_default();
+            Some(usage_entry(
+                label,
+                window.utilization,
+                reset_info,
+                reset_minutes,
+                None,
+            ))
+        })
+        .collect();
+    let extra_usage_enabled = response.extra_usage.as_ref().map(|extra| extra.is_enabled);
+    if let Some(extra) = response.extra_usage.filter(|extra| extra.is_enabled) {
+        let spent = match (extra.used_credits, extra.monthly_limit) {
+            (Some(used), Some(limit)) => Some(format!(
+                "${:.2} / ${:.2} spent",
+                used / 100.0,
+                limit / 100.0
+            )),
+            (Some(used), None) => Some(format!("${:.2} spent", used / 100.0)),
+            _ => None,
+        };
+        let percent = extra.utilization.unwrap_or_default();
+        entries.push(usage_entry(
+            "Extra usage",
+            percent,
+            String::new(),
+            None,
+            spent,
+        ));
+    }
+    if entries.is_empty() {
+        bail!("[parse-failure] Claude usage response has no limits");
+    }
+    Ok(UsageData {
+        provider: "claude".to_string(),
+        entries,
+        extra_usage_enabled,
+        tokens: Vec::new(),
+        check: CheckStats {
+            source: DataSource::Api,
+            ..CheckStats::default()
+        },
+    })
+}
+
+fn usage_entry(
+    label: &str,
+    percent: f64,
+    reset_info: String,
+    reset_minutes: Option<i64>,
+    spent: Option<String>,
+) -> UsageEntry {
+    let used = (percent.round().max(0.0) as u32).min(100);
+    UsageEntry {
+        label: label.to_string(),
+        percent_used: used,
+        percent_remaining: 100 - used,
+        percent_kind: PercentKind::Used,
+        reset_info,
+        reset_minutes,
+        spent,
+        requests: None,
+        requests_used: None,
+        requests_limit: None,
+        scope: scope_for_label(label),
+        window: window_for_label(label),
+        confidence: Confidence::Exact,
+        inconsistent: false,
+        as_of: None,
+    }
+}
+
+/// "Resets 2pm" today, "Resets Feb 20 at 9:30am" on a later day, as the
+/// TUI words it (without its time zone suffix).
+fn reset_text<Tz: TimeZone>(at: DateTime<Tz>, now: DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let time = if at.minute() == 0 {
+        at.format("%-I%P").to_string()
+    } else {
+        at.format("%-I:%M%P").to_string()
+    };
+    if at.date_naive() == now.date_naive() {
+        format!("Resets {}", time)
+    } else {
+        format!("Resets {} at {}", at.format("%b %-d"), time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LimitWindow;
+
+    #[test]
+    fn test_parse_usage_response() {
+        let now = Utc.with_ymd_and_hms(2026, 2, 14, 9, 0, 0).unwrap();
+        let data = parse_usage_response(
+            r#"{
+                "five_hour": {"utilization": 12.4, "resets_at": "2026-02-14T13:59:59.771647+00:00"},
+                "seven_day": {"utilization": 35.0, "resets_at": "2026-02-20T09:00:00+00:00"},
+                "seven_day_oauth_apps": null,
+                "seven_day_opus": null,
+                "seven_day_sonnet": {"utilization": 2.0, "resets_at": "2026-02-15T11:00:00+00:00"},
+                "extra_usage": {"is_enabled": true, "monthly_limit": 50000, "used_credits": 7733, "utilization": 15.466}
+            }"#,
+            now,
+        )
+        .unwrap();
+        assert_eq!(data.provider, "claude");
+        assert_eq!(data.check.source, DataSource::Api);
+        assert_eq!(data.extra_usage_enabled, Some(true));
+        let labels: Vec<&str> = data.entries.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "Current session",
+                "Current week (all models)",
+                "Current week (Sonnet only)",
+                "Extra usage"
+            ]
+        );
+        let session = &data.entries[0];
+        assert_eq!((session.percent_used, session.percent_remaining), (12, 88));
+        assert_eq!(session.window, LimitWindow::Session);
+        assert_eq!(session.reset_minutes, Some(300));
+        assert_eq!(data.entries[1].window, LimitWindow::Weekly);
+        assert_eq!(data.entries[1].reset_minutes, Some(8640));
+        let extra = &data.entries[3];
+        assert_eq!(extra.percent_used, 15);
+        assert_eq!(extra.spent.as_deref(), Some("$77.33 / $500.00 spent"));
+
+        let off = parse_usage_response(
+            r#"{"five_hour": {"utilization": 0, "resets_at": null}, "extra_usage": {"is_enabled": false}}"#,
+            now,
+        )
+        .unwrap();
+        assert_eq!(off.entries.len(), 1);
+        assert_eq!(off.entries[0].reset_info, "");
+        assert_eq!(off.extra_usage_enabled, Some(false));
+
+        let err = parse_usage_response("{}", now).unwrap_err();
+        assert!(err.to_string().starts_with("[parse-failure]"));
+    }
+
+    #[test]
+    fn test_reset_text() {
+        let now = Utc.with_ymd_and_hms(2026, 2, 14, 9, 0, 0).unwrap();
+        let today = Utc.with_ymd_and_hms(2026, 2, 14, 14, 0, 0).unwrap();
+        assert_eq!(reset_text(today, now), "Resets 2pm");
+        let later = Utc.with_ymd_and_hms(2026, 2, 20, 9, 30, 0).unwrap();
+        assert_eq!(reset_text(later, now), "Resets Feb 20 at 9:30am");
+    }
+}
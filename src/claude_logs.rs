@@ -0,0 +1,261 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::types::{CheckStats, DataSource, TokenUsage, UsageData};
+
+/// `projects/` in Claude Code's state directory, where each session's
+/// transcript is written as `<project>/<session>.jsonl`.
+pub fn projects_dir() -> Result<PathBuf> {
+    Ok(crate::trust::claude_home()?.join("projects"))
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptLine {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
+    #[serde(rename = "requestId", default)]
+    request_id: Option<String>,
+    #[serde(default)]
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    usage: Option<Tokens>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Tokens {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+}
+
+/// API list prices in dollars per million tokens: input, output.  Cache
+/// writes cost 1.25× input and cache reads 0.1×.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4-6", 5.0, 25.0),
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4-1", 15.0, 75.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4-6", 3.0, 15.0),
+    ("claude-sonnet-4-5", 3.0, 15.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-haiku-4-5", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+];
+
+/// `usage`'s estimated cost in dollars, if `model`'s price is known.  A
+/// model matches its alias or a dated snapshot of it
+/// (`claude-sonnet-4-5-20250929`).
+fn estimate_cost(model: &str, usage: &TokenUsage) -> Option<f64> {
+    let (_, input, output) = PRICES.iter().find(|(name, _, _)| {
+        model == *name
+            || model
+                .strip_prefix(name)
+                .is_some_and(|rest| rest.starts_with("-20"))
+    })?;
+    let dollars = (usage.input_tokens as f64 * input
+        + usage.cache_creation_tokens as f64 * input * 1.25
+        + usage.cache_read_tokens as f64 * input * 0.1
+        + usage.output_tokens as f64 * output)
+        / 1_000_000.0;
+    Some((dollars * 100.0).round() / 100.0)
+}
+
+/// Claude's token usage today (since local midnight), per model, from its
+/// session transcripts.
+pub fn read() -> Result<UsageData> {
+    let midnight = Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|t| t.and_local_timezone(Local).earliest())
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    read_from(&projects_dir()?, midnight)
+}
+
+/// `read` the transcripts under `dir`, counting messages sent at or after
+/// `since`.  Fails when there are no transcripts at all.
+pub fn read_from(dir: &Path, since: DateTime<Utc>) -> Result<UsageData> {
+    let files = transcripts(dir)?;
+    if files.is_empty() {
+        bail!("No Claude transcripts in {}", dir.display());
+    }
+    let mut seen = HashSet::new();
+    let mut totals: BTreeMap<String, TokenUsage> = BTreeMap::new();
+    for (path, modified) in files {
+        // A transcript last written before `since` has nothing after it.
+        if modified.is_some_and(|at| DateTime::<Utc>::from(at) < since) {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        for line in text.lines().filter(|line| line.contains("\"usage\"")) {
+            let Ok(line) = serde_json::from_str::<TranscriptLine>(line) else {
+                continue;
+            };
+            if line.kind != "assistant" || line.timestamp.is_none_or(|at| at < since) {
+                continue;
+            }
+            let Some(message) = line.message else {
+                continue;
+            };
+            let (Some(model), Some(tokens)) = (message.model, message.usage) else {
+                continue;
+            };
+            if model == "<synthetic>" {
+                continue;
+            }
+            // Streamed and resumed sessions log the same response more
+            // than once.
+            if let (Some(id), Some(request)) = (message.id, line.request_id) {
+                if !seen.insert((id, request)) {
+                    continue;
+                }
+            }
+            let total = totals.entry(model.clone()).or_insert_with(|| TokenUsage {
+                model,
+                ..TokenUsage::default()
+            });
+            total.input_tokens += tokens.input_tokens;
+            total.output_tokens += tokens.output_tokens;
+            total.cache_creation_tokens += tokens.cache_creation_input_tokens;
+            total.cache_read_tokens += tokens.cache_read_input_tokens;
+        }
+    }
+    let tokens = totals
+        .into_values()
+        .map(|mut usage| {
+            usage.cost_usd = estimate_cost(&usage.model, &usage);
+            usage
+        })
+        .collect();
+    Ok(UsageData {
+        provider: "claude".to_string(),
+        entries: Vec::new(),
+        extra_usage_enabled: None,
+        tokens,
+        check: CheckStats {
+            source: DataSource::Local,
+            ..CheckStats::default()
+        },
+    })
+}
+
+/// Every `.jsonl` under `dir` (subagent transcripts are nested a level
+/// deeper), with when it was last written.
+fn transcripts(dir: &Path) -> Result<Vec<(PathBuf, Option<SystemTime>)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "jsonl") {
+                let modified = entry.metadata().and_then(|m| m.modified()).ok();
+                files.push((path, modified));
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::TempDir;
+    use chrono::TimeZone;
+
+    fn assistant(at: &str, id: &str, model: &str, input: u64, output: u64) -> String {
+        format!(
+            r#"{{"type":"assistant","timestamp":"{}","requestId":"req_{}","message":{{"id":"msg_{}","model":"{}","role":"assistant","usage":{{"input_tokens":{},"cache_creation_input_tokens":1000,"cache_read_input_tokens":20000,"output_tokens":{}}}}}}}"#,
+            at, id, id, model, input, output
+        )
+    }
+
+    #[test]
+    fn test_read_tokens_per_model() {
+        let dir = TempDir::create("claude-logs").unwrap();
+        let project = dir.path().join("-home-me-app");
+        std::fs::create_dir_all(&project).unwrap();
+        let lines = [
+            r#"{"type":"user","timestamp":"2026-02-14T09:00:00Z","message":{"role":"user","content":"hi"}}"#.to_string(),
+            assistant("2026-02-14T09:00:05Z", "a", "claude-sonnet-4-5-20250929", 100, 500),
+            // The same response logged again.
+            assistant("2026-02-14T09:00:05Z", "a", "claude-sonnet-4-5-20250929", 100, 500),
+            assistant("2026-02-14T09:05:00Z", "b", "claude-sonnet-4-5-20250929", 50, 250),
+            assistant("2026-02-14T09:06:00Z", "c", "claude-opus-4-1-20250805", 10, 100),
+            assistant("2026-02-14T09:07:00Z", "d", "<synthetic>", 0, 0),
+            assistant("2026-02-14T09:08:00Z", "e", "claude-next", 1, 1),
+            // Yesterday's.
+            assistant("2026-02-13T23:00:00Z", "f", "claude-sonnet-4-5-20250929", 9999, 9999),
+        ];
+        std::fs::write(project.join("s1.jsonl"), lines.join("\n")).unwrap();
+
+        let since = Utc.with_ymd_and_hms(2026, 2, 14, 0, 0, 0).unwrap();
+        let data = read_from(dir.path(), since).unwrap();
+        assert_eq!(data.provider, "claude");
+        assert!(data.entries.is_empty());
+        assert_eq!(data.check.source, DataSource::Local);
+        let models: Vec<&str> = data.tokens.iter().map(|t| t.model.as_str()).collect();
+        assert_eq!(
+            models,
+            [
+                "claude-next",
+                "claude-opus-4-1-20250805",
+                "claude-sonnet-4-5-20250929"
+            ]
+        );
+        let sonnet = &data.tokens[2];
+        assert_eq!((sonnet.input_tokens, sonnet.output_tokens), (150, 750));
+        assert_eq!(sonnet.cache_creation_tokens, 2000);
+        assert_eq!(sonnet.cache_read_tokens, 40000);
+        // 150×3 + 2000×3.75 + 40000×0.3 + 750×15 = 31,200 per million.
+        assert_eq!(sonnet.cost_usd, Some(0.03));
+        assert_eq!(data.tokens[0].cost_usd, None);
+
+        assert!(read_from(&dir.path().join("missing"), since).is_err());
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 1_000_000,
+            ..TokenUsage::default()
+        };
+        assert_eq!(estimate_cost("claude-opus-4-5", &usage), Some(30.0));
+        assert_eq!(estimate_cost("claude-opus-4-20250514", &usage), Some(90.0));
+        assert_eq!(
+            estimate_cost("claude-3-5-haiku-20241022", &usage),
+            Some(4.8)
+        );
+        // Not Opus 4 at Opus 4 prices.
+        assert_eq!(estimate_cost("claude-opus-4-7", &usage), None);
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::codex_logs::limit_entry;
+use crate::types::{CheckStats, DataSource, UsageData, UsageEntry};
+
+/// The endpoint the Codex TUI reads its `/status` limits from.
+const USAGE_URL: &str = "https://chatgpt.com/backend-api/wham/usage";
+
+/// `auth.json` in Codex's state directory, written by `codex login`.
+pub fn auth_path() -> Result<PathBuf> {
+    Ok(crate::trust::codex_home()?.join("auth.json"))
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthFile {
+    #[serde(default)]
+    tokens: Option<AuthTokens>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthTokens {
+    access_token: String,
+    #[serde(default)]
+    account_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageResponse {
+    #[serde(default)]
+    rate_limit: Option<RateLimit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimit {
+    #[serde(default)]
+    primary_window: Option<RateWindow>,
+    #[serde(default)]
+    secondary_window: Option<RateWindow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateWindow {
+    used_percent: f64,
+    limit_window_seconds: i64,
+    #[serde(default)]
+    reset_after_seconds: Option<i64>,
+    #[serde(default)]
+    reset_at: Option<i64>,
+}
+
+/// Read Codex's rate limits from the usage endpoint with the ChatGPT login
+/// in `auth.json`, giving up after `timeout`.
+pub fn fetch(timeout: Duration) -> Result<UsageData> {
+    let path = auth_path()?;
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let auth: AuthFile = serde_json::from_str(&text)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    let Some(tokens) = auth.tokens else {
+        bail!(
+            "{} has no ChatGPT login (API-key logins have no rate limits to read)",
+            path.display()
+        );
+    };
+
+    let bearer = format!("Bearer {}", tokens.access_token);
+    let mut headers = vec![("Authorization", bearer.as_str())];
+    if let Some(account) = &tokens.account_id {
+        headers.push(("ChatGPT-Account-Id", account));
+    }
+    let body = crate::http::get_usage(USAGE_URL, &headers, timeout, "Codex", "codex")?;
+    parse_usage_response(&body, Utc::now())
+}
+
+/// Parse the usage endpoint's JSON into the entries `/status` shows, as of
+/// `now`.
+pub fn parse_usage_response(body: &str, now: DateTime<Utc>) -> Result<UsageData> {
+    let response: UsageResponse = serde_json::from_str(body)
+        .map_err(|e| anyhow::anyhow!("[parse-failure] Invalid Codex usage response: {}", e))?;
+    let windows = response
+        .rate_limit
+        .map(|limits| [limits.primary_window, limits.secondary_window])
+        .unwrap_or_default();
+    let entries: Vec<UsageEntry> = windows
+        .into_iter()
+        .flatten()
+        .map(|window| usage_entry(&window, now))
+        .collect();
+    if entries.is_empty() {
+        bail!("[parse-failure] Codex usage response has no rate limits");
+    }
+    Ok(UsageData {
+        provider: "codex".to_string(),
+        entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats {
+            source: DataSource::Api,
+            ..CheckStats::default()
+        },
+    })
+}
+
+fn usage_entry(window: &RateWindow, now: DateTime<Utc>) -> UsageEntry {
+    let reset_at = window
+        .reset_at
+        .and_then(|at| Utc.timestamp_opt(at, 0).single())
+        .or_else(|| {
+            window
+                .reset_after_seconds
+                .map(|after| now + chrono::Duration::seconds(after))
+        });
+    limit_entry(
+        window.limit_window_seconds,
+        window.used_percent,
+        reset_at,
+        now,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LimitWindow;
+
+    #[test]
+    fn test_parse_usage_response() {
+        let now = Utc.with_ymd_and_hms(2026, 2, 14, 9, 0, 0).unwrap();
+        let data = parse_usage_response(
+            r#"{"plan_type": "plus", "rate_limit": {
+                "allowed": true,
+                "primary_window": {"used_percent": 3, "limit_window_seconds": 18000, "reset_after_seconds": 7620},
+                "secondary_window": {"used_percent": 28.6, "limit_window_seconds": 604800, "reset_at": 1771243620}
+            }}"#,
+            now,
+        )
+        .unwrap();
+        assert_eq!(data.provider, "codex");
+        assert_eq!(data.check.source, DataSource::Api);
+        let session = &data.entries[0];
+        assert_eq!(session.label, "5h limit");
+        assert_eq!(session.window, LimitWindow::Session);
+        assert_eq!((session.percent_used, session.percent_remaining), (3, 97));
+        assert_eq!(session.reset_minutes, Some(127));
+        assert!(session.reset_info.starts_with("resets "));
+        let weekly = &data.entries[1];
+        assert_eq!(weekly.label, "Weekly limit");
+        assert_eq!(weekly.window, LimitWindow::Weekly);
+        assert_eq!(weekly.percent_used, 29);
+        assert_eq!(weekly.reset_minutes, Some(3067));
+
+        let err = parse_usage_response(r#"{"rate_limit": null}"#, now).unwrap_err();
+        assert!(err.to_string().starts_with("[parse-failure]"));
+        let err = parse_usage_response("<html>", now).unwrap_err();
+        assert!(err.to_string().starts_with("[parse-failure]"));
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::parser::{scope_for_label, window_for_label};
+use crate::types::{CheckStats, Confidence, DataSource, PercentKind, UsageData, UsageEntry};
+
+/// Session rollouts searched, newest first, for a rate-limit snapshot.
+const MAX_ROLLOUTS: usize = 20;
+
+/// `sessions/` in Codex's state directory, where each session's events
+/// are written as `YYYY/MM/DD/rollout-*.jsonl`.
+pub fn sessions_dir() -> Result<PathBuf> {
+    Ok(crate::trust::codex_home()?.join("sessions"))
+}
+
+#[derive(Debug, Deserialize)]
+struct LogLine {
+    timestamp: DateTime<Utc>,
+    payload: Payload,
+}
+
+#[derive(Debug, Deserialize)]
+struct Payload {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    rate_limits: Option<RateLimits>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimits {
+    #[serde(default)]
+    primary: Option<LoggedWindow>,
+    #[serde(default)]
+    secondary: Option<LoggedWindow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoggedWindow {
+    used_percent: f64,
+    #[serde(default)]
+    window_minutes: Option<i64>,
+    #[serde(default)]
+    resets_in_seconds: Option<i64>,
+    /// Unix seconds; newer Codex builds log this instead of
+    /// `resets_in_seconds`.
+    #[serde(default)]
+    resets_at: Option<i64>,
+}
+
+/// Read Codex's rate limits from the last snapshot its sessions logged.
+pub fn read() -> Result<UsageData> {
+    read_from(&sessions_dir()?, Utc::now())
+}
+
+/// `read` from the sessions under `dir`, as of `now`.  Fails when no
+/// session logged rate limits, or when a window has reset since the last
+/// snapshot (the logged values no longer apply).
+pub fn read_from(dir: &Path, now: DateTime<Utc>) -> Result<UsageData> {
+    for path in rollouts(dir)?.into_iter().take(MAX_ROLLOUTS) {
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if let Some(data) = parse_rollout(&text, now)? {
+            return Ok(data);
+        }
+    }
+    bail!("No Codex session in {} logged rate limits", dir.display())
+}
+
+/// The rollout files under `dir`, newest first.  Their names start with
+/// the session's start time, so name order is time order.
+fn rollouts(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("rollout-") && name.ends_with(".jsonl"))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+    Ok(files)
+}
+
+/// The last rate-limit snapshot in one rollout, if it logged any.
+fn parse_rollout(text: &str, now: DateTime<Utc>) -> Result<Option<UsageData>> {
+    let snapshot = text.lines().rev().find_map(|line| {
+        let line: LogLine = serde_json::from_str(line).ok()?;
+        if line.payload.kind != "token_count" {
+            return None;
+        }
+        Some((line.timestamp, line.payload.rate_limits?))
+    });
+    let Some((logged_at, limits)) = snapshot else {
+        return Ok(None);
+    };
+    let mut entries = Vec::new();
+    for window in [limits.primary, limits.secondary].into_iter().flatten() {
+        let reset_at = window
+            .resets_at
+            .and_then(|at| Utc.timestamp_opt(at, 0).single())
+            .or_else(|| {
+                window
+                    .resets_in_seconds
+                    .map(|secs| logged_at + chrono::Duration::seconds(secs))
+            });
+        if reset_at.is_some_and(|at| at <= now) {
+            bail!(
+                "Codex's last logged rate limits ({}) predate a reset",
+                logged_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            );
+        }
+        let Some(minutes) = window.window_minutes else {
+            continue;
+        };
+        entries.push(limit_entry(
+            minutes * 60,
+            window.used_percent,
+            reset_at,
+            now,
+        ));
+    }
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(UsageData {
+        provider: "codex".to_string(),
+        entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats {
+            source: DataSource::Local,
+            ..CheckStats::default()
+        },
+    }))
+}
+
+/// A Codex rate-limit window as the entry `/status` would show for it.
+pub(crate) fn limit_entry(
+    window_seconds: i64,
+    used_percent: f64,
+    reset_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> UsageEntry {
+    let label = window_label(window_seconds);
+    let used = (used_percent.round().max(0.0) as u32).min(100);
+    let reset_minutes = reset_at.map(|at| ((at - now).num_seconds().max(0) + 59) / 60);
+    UsageEntry {
+        scope: scope_for_label(&label),
+        window: window_for_label(&label),
+        percent_used: used,
+        percent_remaining: 100 - used,
+        percent_kind: PercentKind::Used,
+        reset_info: reset_at
+            .map(|at| reset_text(at.with_timezone(&Local), now.with_timezone(&Local)))
+            .unwrap_or_default(),
+        reset_minutes,
+        spent: None,
+        requests: None,
+        requests_used: None,
+        requests_limit: None,
+        confidence: Confidence::Exact,
+        inconsistent: false,
+        as_of: None,
+        label,
+    }
+}
+
+/// The label `/status` gives a window of this length: "5h limit",
+/// "Weekly limit".
+fn window_label(seconds: i64) -> String {
+    const HOUR: i64 = 3600;
+    const DAY: i64 = 24 * HOUR;
+    match seconds {
+        s if s == 7 * DAY => "Weekly limit".to_string(),
+        s if s >= DAY && s % DAY == 0 => format!("{}d limit", s / DAY),
+        s => format!("{}h limit", ((s + HOUR / 2) / HOUR).max(1)),
+    }
+}
+
+/// "resets 11:07" today, "resets 12:07 on 16 Feb" on a later day, as the
+/// TUI words it.
+fn reset_text<Tz: TimeZone>(at: DateTime<Tz>, now: DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    if at.date_naive() == now.date_naive() {
+        format!("resets {}", at.format("%H:%M"))
+    } else {
+        format!("resets {}", at.format("%H:%M on %-d %b"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::TempDir;
+    use crate::types::LimitWindow;
+
+    const SNAPSHOT: &str = r#"{"timestamp":"2026-02-14T08:30:00.000Z","type":"event_msg","payload":{"type":"token_count","info":null,"rate_limits":{"primary":{"used_percent":12.0,"window_minutes":300,"resets_in_seconds":9000},"secondary":{"used_percent":40.5,"window_minutes":10080,"resets_at":1771243620}}}}"#;
+
+    fn write_rollout(dir: &Path, day: &str, name: &str, lines: &[&str]) {
+        let day_dir = dir.join(day);
+        std::fs::create_dir_all(&day_dir).unwrap();
+        std::fs::write(day_dir.join(name), lines.join("\n")).unwrap();
+    }
+
+    #[test]
+    fn test_read_latest_snapshot() {
+        let dir = TempDir::create("codex-logs").unwrap();
+        let older = SNAPSHOT.replace("12.0", "5.0");
+        write_rollout(
+            dir.path(),
+            "2026/02/13",
+            "rollout-2026-02-13T10-00-00-a.jsonl",
+            &[&older],
+        );
+        write_rollout(
+            dir.path(),
+            "2026/02/14",
+            "rollout-2026-02-14T08-00-00-b.jsonl",
+            &[
+                r#"{"timestamp":"2026-02-14T08:00:00.000Z","type":"session_meta","payload":{"type":"session_meta"}}"#,
+                SNAPSHOT,
+                r#"{"timestamp":"2026-02-14T08:31:00.000Z","type":"event_msg","payload":{"type":"token_count","rate_limits":null}}"#,
+            ],
+        );
+        // The newest session hasn't logged limits yet.
+        write_rollout(
+            dir.path(),
+            "2026/02/14",
+            "rollout-2026-02-14T08-45-00-c.jsonl",
+            &[
+                r#"{"timestamp":"2026-02-14T08:45:00.000Z","type":"session_meta","payload":{"type":"session_meta"}}"#,
+            ],
+        );
+
+        let now = Utc.with_ymd_and_hms(2026, 2, 14, 9, 0, 0).unwrap();
+        let data = read_from(dir.path(), now).unwrap();
+        assert_eq!(data.check.source, DataSource::Local);
+        let session = &data.entries[0];
+        assert_eq!(session.label, "5h limit");
+        assert_eq!(session.window, LimitWindow::Session);
+        assert_eq!(session.percent_used, 12);
+        assert_eq!(session.reset_minutes, Some(120));
+        let weekly = &data.entries[1];
+        assert_eq!(weekly.label, "Weekly limit");
+        assert_eq!(weekly.percent_used, 41);
+        assert_eq!(weekly.reset_minutes, Some(3067));
+
+        let after_reset = Utc.with_ymd_and_hms(2026, 2, 14, 11, 0, 0).unwrap();
+        let err = read_from(dir.path(), after_reset).unwrap_err();
+        assert!(err.to_string().contains("predate a reset"), "{}", err);
+        assert!(read_from(&dir.path().join("missing"), now).is_err());
+    }
+
+    #[test]
+    fn test_window_label_and_reset_text() {
+        assert_eq!(window_label(18000), "5h limit");
+        assert_eq!(window_label(604800), "Weekly limit");
+        assert_eq!(window_label(86400), "1d limit");
+        let now = Utc.with_ymd_and_hms(2026, 2, 14, 9, 0, 0).unwrap();
+        let later = Utc.with_ymd_and_hms(2026, 2, 14, 11, 7, 0).unwrap();
+        assert_eq!(reset_text(later, now), "resets 11:07");
+        let week = Utc.with_ymd_and_hms(2026, 2, 16, 12, 7, 0).unwrap();
+        assert_eq!(reset_text(week, now), "resets 12:07 on 16 Feb");
+    }
+}
@@ -0,0 +1,400 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::parser::{parse_reset_minutes, scope_for_label, window_for_label};
+use crate::provider::{Provider, ProviderRegistry};
+use crate::types::{
+    Capabilities, CheckStats, Confidence, LimitScope, LimitWindow, PercentKind, UsageData,
+    UsageEntry,
+};
+use crate::UsageConfig;
+
+/// `$XDG_CONFIG_HOME/agentusage/providers.toml` (default
+/// `~/.config/agentusage/providers.toml`).
+pub fn default_path() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    let config_home = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("agentusage").join("providers.toml"))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProvidersFile {
+    #[serde(default)]
+    provider: Vec<ProviderDef>,
+}
+
+/// One `[[provider]]` table.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProviderDef {
+    name: String,
+    binary: String,
+    #[serde(default)]
+    args: Vec<String>,
+    /// Matches the screen once the CLI is ready for input.
+    prompt: String,
+    /// Typed at the prompt to show usage.
+    command: String,
+    /// One usage row, with named groups `percent` and, unless `label` is
+    /// set, `label`; `reset` is optional.
+    row: String,
+    /// Matches the screen once usage has rendered; defaults to `row`.
+    #[serde(default)]
+    ready: Option<String>,
+    /// Label for every row, when `row` has no `label` group.
+    #[serde(default)]
+    label: Option<String>,
+    /// Whether `percent` counts what is used (the default) or what is left.
+    #[serde(default)]
+    percent: PercentMeaning,
+    /// A built-in provider whose reset wording the `reset` group follows,
+    /// used to work out minutes until reset.
+    #[serde(default)]
+    reset_format: Option<String>,
+    #[serde(default)]
+    window: Option<LimitWindow>,
+    #[serde(default)]
+    scope: Option<LimitScope>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PercentMeaning {
+    #[default]
+    Used,
+    Left,
+}
+
+/// A provider defined in `providers.toml`: a CLI that follows the usual
+/// TUI pattern (prompt, slash command, usage rows), checked with the
+/// default PTY flow and parsed with the row regex from its definition.
+#[derive(Debug, Clone)]
+pub struct CustomProvider {
+    name: String,
+    binary: String,
+    args: Vec<String>,
+    prompt: Regex,
+    command: String,
+    row: Regex,
+    ready: Regex,
+    label: Option<String>,
+    percent: PercentMeaning,
+    reset_format: Option<String>,
+    window: Option<LimitWindow>,
+    scope: Option<LimitScope>,
+}
+
+/// Compile `pattern` so `^` and `$` match at line boundaries, as screens
+/// are matched whole.
+fn compile(name: &str, key: &str, pattern: &str) -> Result<Regex> {
+    Regex::new(&format!("(?m){}", pattern))
+        .with_context(|| format!("Provider '{}': invalid `{}` regex", name, key))
+}
+
+impl CustomProvider {
+    fn from_def(def: ProviderDef) -> Result<Self> {
+        let name = def.name;
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid_name {
+            bail!(
+                "Provider name '{}' must be lowercase letters, digits, '-', and '_'",
+                name
+            );
+        }
+        let row = compile(&name, "row", &def.row)?;
+        let groups: Vec<&str> = row.capture_names().flatten().collect();
+        if !groups.contains(&"percent") {
+            bail!("Provider '{}': `row` needs a (?P<percent>...) group", name);
+        }
+        if def.label.is_none() && !groups.contains(&"label") {
+            bail!(
+                "Provider '{}': `row` needs a (?P<label>...) group, or set `label`",
+                name
+            );
+        }
+        if let Some(format) = &def.reset_format {
+            if !crate::ALL_PROVIDERS.contains(&format.as_str()) {
+                bail!(
+                    "Provider '{}': unknown `reset_format` '{}' (expected one of: {})",
+                    name,
+                    format,
+                    crate::ALL_PROVIDERS.join(", ")
+                );
+            }
+        }
+        let ready = match &def.ready {
+            Some(pattern) => compile(&name, "ready", pattern)?,
+            None => row.clone(),
+        };
+        Ok(Self {
+            prompt: compile(&name, "prompt", &def.prompt)?,
+            binary: def.binary,
+            args: def.args,
+            command: def.command,
+            row,
+            ready,
+            label: def.label,
+            percent: def.percent,
+            reset_format: def.reset_format,
+            window: def.window,
+            scope: def.scope,
+            name,
+        })
+    }
+}
+
+impl Provider for CustomProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn binary(&self) -> &str {
+        &self.binary
+    }
+
+    fn launch_args(&self, _config: &UsageConfig) -> Vec<String> {
+        self.args.clone()
+    }
+
+    fn prompt_ready(&self, screen: &str) -> bool {
+        self.prompt.is_match(screen)
+    }
+
+    fn usage_command(&self) -> &str {
+        &self.command
+    }
+
+    fn usage_ready(&self, screen: &str) -> bool {
+        self.ready.is_match(screen)
+    }
+
+    /// Rows carry only percentages and resets, and minutes until the reset
+    /// only with a `reset` group and a `reset_format` to read it by.
+    fn capabilities(&self) -> Capabilities {
+        let has_reset = self.row.capture_names().any(|name| name == Some("reset"));
+        Capabilities {
+            supports_reset_minutes: has_reset && self.reset_format.is_some(),
+            ..Capabilities::default()
+        }
+    }
+
+    /// Match `row` against each line, with box-drawing borders trimmed.
+    /// Rows whose label was already seen are skipped.
+    fn parse(&self, output: &str) -> Result<UsageData> {
+        let mut entries: Vec<UsageEntry> = Vec::new();
+        for raw_line in output.lines() {
+            let line = raw_line.trim().trim_matches('│').trim();
+            let Some(caps) = self.row.captures(line) else {
+                continue;
+            };
+            let label = match (caps.name("label"), &self.label) {
+                (Some(label), _) => label.as_str().trim().to_string(),
+                (None, Some(label)) => label.clone(),
+                (None, None) => continue,
+            };
+            if label.is_empty() || entries.iter().any(|e| e.label == label) {
+                continue;
+            }
+            let Some(percent) = caps
+                .name("percent")
+                .and_then(|m| m.as_str().trim().trim_end_matches('%').parse::<f64>().ok())
+            else {
+                continue;
+            };
+            let clamped = (percent.round().max(0.0) as u32).min(100);
+            let (percent_used, percent_kind) = match self.percent {
+                PercentMeaning::Used => (clamped, PercentKind::Used),
+                PercentMeaning::Left => (100 - clamped, PercentKind::Left),
+            };
+            let reset_info = match caps.name("reset").map(|m| m.as_str().trim()) {
+                Some(reset) if reset.to_lowercase().starts_with("reset") => reset.to_string(),
+                Some(reset) if !reset.is_empty() => format!("Resets {}", reset),
+                _ => String::new(),
+            };
+            let reset_minutes = self
+                .reset_format
+                .as_deref()
+                .and_then(|format| parse_reset_minutes(&reset_info, format));
+            entries.push(UsageEntry {
+                percent_used,
+                percent_remaining: 100 - percent_used,
+                percent_kind,
+                reset_info,
+                reset_minutes,
+                spent: None,
+                requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: self.scope.unwrap_or_else(|| scope_for_label(&label)),
+                window: self.window.unwrap_or_else(|| window_for_label(&label)),
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
+                label,
+            });
+        }
+        Ok(UsageData {
+            provider: self.name.clone(),
+            entries,
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
+        })
+    }
+}
+
+/// Parse the `[[provider]]` tables of a providers file.
+pub fn parse_providers(text: &str) -> Result<Vec<CustomProvider>> {
+    let file: ProvidersFile = toml::from_str(text)?;
+    file.provider
+        .into_iter()
+        .map(CustomProvider::from_def)
+        .collect()
+}
+
+/// Register the providers defined in the file at `path` on `registry`,
+/// after the providers already there.  A definition never replaces a
+/// registered provider of the same name.  A missing file defines none.
+/// Returns the names registered.
+pub fn register_from(registry: &mut ProviderRegistry, path: &Path) -> Result<Vec<String>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let providers =
+        parse_providers(&text).with_context(|| format!("Invalid {}", path.display()))?;
+    let mut registered = Vec::new();
+    for provider in providers {
+        if registry.get(&provider.name).is_some() {
+            continue;
+        }
+        registered.push(provider.name.clone());
+        registry.register(Arc::new(provider));
+    }
+    Ok(registered)
+}
+
+/// `register_from` the default providers file.
+pub fn register_all(registry: &mut ProviderRegistry) -> Result<Vec<String>> {
+    match default_path() {
+        Some(path) => register_from(registry, &path),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::TempDir;
+
+    const DEFS: &str = r#"
+[[provider]]
+name = "acme"
+binary = "sh"
+args = ["-c", "printf 'acme> '; read cmd; printf 'Daily quota: 37%% used (resets in 3h 20m)\nWeekly quota: 12%% used\n'; sleep 5"]
+prompt = 'acme> $'
+command = "/quota"
+row = '^(?P<label>\w+ quota):\s*(?P<percent>\d+)% used(?:\s*\((?P<reset>[^)]+)\))?'
+reset_format = "gemini"
+
+[[provider]]
+name = "fork"
+binary = "fork"
+prompt = '^> '
+command = "/usage"
+row = 'credits\s+(?P<percent>\d+(?:\.\d+)?)% left'
+label = "Credits"
+percent = "left"
+window = "monthly"
+"#;
+
+    #[test]
+    fn test_parse_providers_and_rows() {
+        let providers = parse_providers(DEFS).unwrap();
+        let names: Vec<&str> = providers.iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["acme", "fork"]);
+
+        let acme = &providers[0];
+        assert!(acme.prompt_ready("starting\nacme> "));
+        assert!(!acme.usage_ready("acme> /quota"));
+        let data = acme
+            .parse("│ Daily quota: 37% used (resets in 3h 20m) │\nWeekly quota: 12% used\n")
+            .unwrap();
+        assert_eq!(data.provider, "acme");
+        let daily = &data.entries[0];
+        assert_eq!(
+            (daily.label.as_str(), daily.percent_used),
+            ("Daily quota", 37)
+        );
+        assert_eq!(daily.reset_info, "resets in 3h 20m");
+        assert_eq!(daily.reset_minutes, Some(200));
+        assert_eq!(daily.window, LimitWindow::Daily);
+        assert_eq!(data.entries[1].window, LimitWindow::Weekly);
+        assert_eq!(data.entries[1].reset_minutes, None);
+        assert!(acme.capabilities().supports_reset_minutes);
+
+        let fork = &providers[1];
+        let data = fork.parse("Plan credits   80% left\n").unwrap();
+        assert!(!fork.capabilities().supports_reset_minutes);
+        assert_eq!(data.entries[0].label, "Credits");
+        assert_eq!(data.entries[0].percent_used, 20);
+        assert_eq!(data.entries[0].window, LimitWindow::Monthly);
+    }
+
+    #[test]
+    fn test_parse_providers_rejects_bad_definitions() {
+        let def = |extra: &str| {
+            format!(
+                "[[provider]]\nname = \"acme\"\nbinary = \"acme\"\nprompt = '> '\n\
+                 command = \"/usage\"\n{}\n",
+                extra
+            )
+        };
+        let err = |extra: &str| format!("{:#}", parse_providers(&def(extra)).unwrap_err());
+        assert!(err("row = '(?P<label>.+) used'").contains("percent"));
+        assert!(err("row = '(?P<percent>\\d+)%'").contains("label"));
+        assert!(err("row = '(?P<percent>\\d+'").contains("invalid `row` regex"));
+        assert!(
+            err("row = '(?P<label>.+) (?P<percent>\\d+)%'\nreset_format = \"bogus\"")
+                .contains("bogus")
+        );
+        assert!(err("row = '(?P<label>.+) (?P<percent>\\d+)%'\ncolour = 1").contains("colour"));
+        assert!(parse_providers(&def("row = '(?P<label>.+) (?P<percent>\\d+)%'")).is_ok());
+    }
+
+    #[test]
+    fn test_registered_custom_provider_runs_through_the_default_check() {
+        let dir = TempDir::create("custom").unwrap();
+        let path = dir.path().join("providers.toml");
+        std::fs::write(&path, DEFS).unwrap();
+        let mut config = UsageConfig {
+            timeout: 10,
+            poll_interval_ms: 50,
+            ..UsageConfig::default()
+        };
+        let registered = register_from(&mut config.providers, &path).unwrap();
+        assert_eq!(registered, ["acme", "fork"]);
+        assert!(register_from(&mut config.providers, &path)
+            .unwrap()
+            .is_empty());
+
+        let data = crate::run_provider(&config, "acme").unwrap();
+        assert_eq!(data.entries.len(), 2);
+        assert_eq!(data.entries[0].percent_used, 37);
+
+        let missing = dir.path().join("missing.toml");
+        assert!(register_from(&mut config.providers, &missing)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,382 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use crate::runner::strip_error_tags;
+use crate::thresholds::Thresholds;
+use crate::types::{UsageData, UsageEntry};
+use crate::{run_provider, UsageConfig};
+
+/// How often the screen is redrawn, so countdowns tick.
+const TICK: Duration = Duration::from_millis(250);
+
+/// A finished check: the panel it belongs to and its result.
+type Finished = (usize, Result<UsageData, String>);
+
+/// One provider's panel: its last good result and how its last check went.
+struct Panel {
+    provider: String,
+    data: Option<UsageData>,
+    /// When `data` was read; reset countdowns run from here.
+    refreshed: Option<(Instant, DateTime<Local>)>,
+    /// The last check's failure, cleared by the next success.
+    error: Option<String>,
+    checking: bool,
+    last_started: Option<Instant>,
+}
+
+/// The state behind `agentusage dashboard`.
+struct Dashboard {
+    panels: Vec<Panel>,
+    selected: usize,
+    thresholds: Thresholds,
+    refresh: Duration,
+}
+
+/// Run the full-screen dashboard for `providers` until the user quits:
+/// a gauge per limit with its reset counting down, each provider checked
+/// again every `refresh` or on demand.
+pub fn run(
+    config: &UsageConfig,
+    providers: &[&str],
+    thresholds: Thresholds,
+    refresh: Duration,
+) -> Result<()> {
+    let mut dashboard = Dashboard::new(providers, thresholds, refresh);
+    let (tx, rx) = mpsc::channel();
+    let mut terminal = ratatui::try_init()?;
+    let result = dashboard.event_loop(&mut terminal, config, &tx, &rx);
+    ratatui::restore();
+    // Checks still running would otherwise outlive the dashboard.
+    crate::pty::request_shutdown();
+    crate::session::Session::kill_registered_sessions();
+    result
+}
+
+impl Dashboard {
+    fn new(providers: &[&str], thresholds: Thresholds, refresh: Duration) -> Self {
+        Self {
+            panels: providers
+                .iter()
+                .map(|provider| Panel {
+                    provider: provider.to_string(),
+                    data: None,
+                    refreshed: None,
+                    error: None,
+                    checking: false,
+                    last_started: None,
+                })
+                .collect(),
+            selected: 0,
+            thresholds,
+            refresh,
+        }
+    }
+
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        config: &UsageConfig,
+        tx: &Sender<Finished>,
+        rx: &Receiver<Finished>,
+    ) -> Result<()> {
+        loop {
+            let now = Instant::now();
+            for index in 0..self.panels.len() {
+                let due = self.panels[index]
+                    .last_started
+                    .is_none_or(|at| now.duration_since(at) >= self.refresh);
+                if due {
+                    self.start(index, config, tx);
+                }
+            }
+            while let Ok((index, result)) = rx.try_recv() {
+                self.finish(index, result, Instant::now(), Local::now());
+            }
+            terminal.draw(|frame| self.draw(frame, Instant::now()))?;
+
+            if !event::poll(TICK)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Up | KeyCode::Char('k') | KeyCode::BackTab => self.select(-1),
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => self.select(1),
+                KeyCode::Char('r') | KeyCode::Enter => self.start(self.selected, config, tx),
+                KeyCode::Char('a') => {
+                    for index in 0..self.panels.len() {
+                        self.start(index, config, tx);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn select(&mut self, step: isize) {
+        let count = self.panels.len() as isize;
+        if count > 0 {
+            self.selected = (self.selected as isize + step).rem_euclid(count) as usize;
+        }
+    }
+
+    /// Check panel `index`'s provider in the background, unless a check of
+    /// it is already running.
+    fn start(&mut self, index: usize, config: &UsageConfig, tx: &Sender<Finished>) {
+        let Some(panel) = self.panels.get_mut(index) else {
+            return;
+        };
+        if panel.checking {
+            return;
+        }
+        panel.checking = true;
+        panel.last_started = Some(Instant::now());
+        let config = config.clone();
+        let provider = panel.provider.clone();
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                run_provider(&config, &provider)
+            }))
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("Provider thread panicked")))
+            .map_err(|e| strip_error_tags(&format!("{:#}", e)));
+            let _ = tx.send((index, result));
+        });
+    }
+
+    fn finish(
+        &mut self,
+        index: usize,
+        result: Result<UsageData, String>,
+        now: Instant,
+        local: DateTime<Local>,
+    ) {
+        let Some(panel) = self.panels.get_mut(index) else {
+            return;
+        };
+        panel.checking = false;
+        match result {
+            Ok(data) => {
+                panel.data = Some(data);
+                panel.refreshed = Some((now, local));
+                panel.error = None;
+            }
+            Err(e) => panel.error = Some(e),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame, now: Instant) {
+        let heights = self.panels.iter().map(|panel| {
+            let entries = panel.data.as_ref().map_or(0, |d| d.entries.len());
+            let lines = entries + usize::from(panel.error.is_some());
+            Constraint::Length(lines.max(1) as u16 + 2)
+        });
+        let rows = Layout::vertical(
+            heights
+                .chain([Constraint::Fill(1), Constraint::Length(1)])
+                .collect::<Vec<_>>(),
+        )
+        .split(frame.area());
+        for (index, panel) in self.panels.iter().enumerate() {
+            self.draw_panel(frame, rows[index], panel, index == self.selected, now);
+        }
+        let help = format!(
+            "↑/↓ select · r refresh · a refresh all · q quit · auto-refresh every {}",
+            format_countdown(self.refresh.as_secs() as i64)
+        );
+        frame.render_widget(
+            Paragraph::new(help).style(Style::new().fg(Color::DarkGray)),
+            rows[rows.len() - 1],
+        );
+    }
+
+    fn draw_panel(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        panel: &Panel,
+        selected: bool,
+        now: Instant,
+    ) {
+        let status = if panel.checking {
+            "checking…".to_string()
+        } else if let Some((_, at)) = &panel.refreshed {
+            format!("refreshed {}", at.format("%H:%M:%S"))
+        } else if panel.error.is_some() {
+            "failed".to_string()
+        } else {
+            String::new()
+        };
+        let border = if selected {
+            Style::new().fg(Color::Yellow)
+        } else {
+            Style::new()
+        };
+        let block = Block::bordered()
+            .border_style(border)
+            .title(
+                Line::from(format!(" {} ", provider_title(&panel.provider)))
+                    .style(Style::new().add_modifier(Modifier::BOLD)),
+            )
+            .title(Line::from(format!(" {} ", status)).right_aligned());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let entries = panel.data.as_ref().map_or(&[][..], |d| &d.entries[..]);
+        let lines =
+            Layout::vertical(vec![Constraint::Length(1); inner.height as usize]).split(inner);
+        let elapsed = panel
+            .refreshed
+            .map_or(0, |(at, _)| now.duration_since(at).as_secs() as i64);
+        for (entry, line) in entries.iter().zip(lines.iter()) {
+            self.draw_entry(frame, *line, entry, elapsed);
+        }
+        if let Some(line) = lines.get(entries.len()) {
+            let message = match &panel.error {
+                Some(error) => {
+                    Paragraph::new(format!("✗ {}", error)).style(Style::new().fg(Color::Red))
+                }
+                None if panel.checking => Paragraph::new("Checking…"),
+                None => Paragraph::new("No limits reported"),
+            };
+            if panel.error.is_some() || entries.is_empty() {
+                frame.render_widget(message, *line);
+            }
+        }
+    }
+
+    fn draw_entry(&self, frame: &mut Frame, area: Rect, entry: &UsageEntry, elapsed: i64) {
+        let [label, gauge, reset] = Layout::horizontal([
+            Constraint::Length(28),
+            Constraint::Fill(1),
+            Constraint::Length(22),
+        ])
+        .spacing(1)
+        .areas(area);
+        let color = if self.thresholds.is_over(entry) {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        frame.render_widget(Paragraph::new(entry.label.as_str()), label);
+        frame.render_widget(
+            Gauge::default()
+                .ratio(f64::from(entry.percent_used.min(100)) / 100.0)
+                .label(format!("{}% used", entry.percent_used))
+                .gauge_style(Style::new().fg(color).bg(Color::DarkGray)),
+            gauge,
+        );
+        let countdown = match entry.reset_minutes {
+            Some(minutes) => match minutes * 60 - elapsed {
+                left if left > 0 => format!("resets in {}", format_countdown(left)),
+                _ => "reset due".to_string(),
+            },
+            None => entry.reset_info.clone(),
+        };
+        frame.render_widget(
+            Paragraph::new(countdown)
+                .right_aligned()
+                .style(Style::new().add_modifier(Modifier::DIM)),
+            reset,
+        );
+    }
+}
+
+fn provider_title(provider: &str) -> String {
+    crate::discovery::display_name(provider)
+        .map(str::to_string)
+        .unwrap_or_else(|| provider.to_string())
+}
+
+/// `secs` as `4d 3h 12m`, `2h 05m 09s`, or `4m 09s`.
+fn format_countdown(secs: i64) -> String {
+    let (days, hours, minutes, secs) = (
+        secs / 86400,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, secs)
+    } else {
+        format!("{}m {:02}s", minutes, secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::mock_usage;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn screen(dashboard: &Dashboard, now: Instant) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+        terminal.draw(|frame| dashboard.draw(frame, now)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_dashboard_shows_limits_countdowns_and_errors() {
+        let mut dashboard = Dashboard::new(
+            &["codex", "claude"],
+            Thresholds::default(),
+            Duration::from_secs(300),
+        );
+        let refreshed = Instant::now();
+        let data = mock_usage("codex").unwrap();
+        let reset = data.entries[0].reset_minutes.unwrap();
+        dashboard.finish(0, Ok(data), refreshed, Local::now());
+        dashboard.finish(
+            1,
+            Err("No usage data found".into()),
+            refreshed,
+            Local::now(),
+        );
+
+        let text = screen(&dashboard, refreshed + Duration::from_secs(65));
+        assert!(text.contains("5h limit"), "{}", text);
+        assert!(text.contains("23% used"), "{}", text);
+        assert!(
+            text.contains(&format!("resets in {}", format_countdown(reset * 60 - 65))),
+            "{}",
+            text
+        );
+        assert!(text.contains("✗ No usage data found"), "{}", text);
+        assert!(text.contains("r refresh"), "{}", text);
+
+        dashboard.select(-1);
+        assert_eq!(dashboard.selected, 1);
+    }
+
+    #[test]
+    fn test_format_countdown() {
+        assert_eq!(format_countdown(249), "4m 09s");
+        assert_eq!(format_countdown(7509), "2h 05m 09s");
+        assert_eq!(format_countdown(4 * 86400 + 3 * 3600 + 720), "4d 3h 12m");
+    }
+}
@@ -1,9 +1,16 @@
 use crate::session::Session;
-use crate::types::DialogKind;
+use crate::types::{ApprovalPolicy, DialogKind};
 use anyhow::Result;
+use regex::Regex;
+use std::mem::discriminant;
+use std::sync::LazyLock;
 use std::thread;
 use std::time::Duration;
 
+/// Alternative key sequences tried, in order, when the primary dismissal
+/// left the dialog on screen.  Different CLI versions bind different keys.
+const ESCALATION_STRATEGIES: &[&[&str]] = &[&["Esc"], &["n"], &["Down", "Enter"]];
+
 fn is_auth_required_prompt(lower: &str) -> bool {
     const AUTH_PHRASES: &[&str] = &[
         "sign in required",
@@ -40,6 +47,39 @@ fn has_numbered_skip_option(content: &str) -> bool {
     compact.contains("2.skip")
 }
 
+/// Whether the Codex composer is ready for input.  Most builds show a
+/// "? for shortcuts" footer; themes and builds without it still draw the
+/// composer's "›" (or older "▌") input line near the bottom of the screen.
+/// Must NOT match ">_" in the Codex banner header, which appears early, or
+/// the "› 1." cursor of a selection dialog.
+pub(crate) fn codex_prompt_ready(content: &str) -> bool {
+    if content.contains("? for shortcuts") {
+        return true;
+    }
+    if detect_codex_dialog(content).is_some() {
+        return false;
+    }
+    content
+        .lines()
+        .rev()
+        .filter(|line| !line.trim().is_empty())
+        .take(6)
+        .any(is_codex_composer_line)
+}
+
+fn is_codex_composer_line(line: &str) -> bool {
+    // The composer may sit inside a box: "│ › Ask Codex to do anything │".
+    let inner = line.trim().trim_start_matches(['│', '┃']).trim_start();
+    let Some(rest) = inner.strip_prefix('›').or_else(|| inner.strip_prefix('▌')) else {
+        return false;
+    };
+    let numbered_option = rest
+        .trim_start()
+        .split_once('.')
+        .is_some_and(|(n, _)| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    !numbered_option
+}
+
 fn dismiss_codex_update_prompt(session: &mut Session) -> Result<bool> {
     // Never accept updates on behalf of the user.
     // Try escape first, then explicit skip selection for numbered menus.
@@ -47,7 +87,7 @@ fn dismiss_codex_update_prompt(session: &mut Session) -> Result<bool> {
     thread::sleep(Duration::from_millis(250));
 
     let mut content = session.capture_pane()?;
-    if content.contains("? for shortcuts") {
+    if codex_prompt_ready(&content) {
         return Ok(true);
     }
 
@@ -58,7 +98,7 @@ fn dismiss_codex_update_prompt(session: &mut Session) -> Result<bool> {
         thread::sleep(Duration::from_millis(400));
 
         content = session.capture_pane()?;
-        if content.contains("? for shortcuts") {
+        if codex_prompt_ready(&content) {
             return Ok(true);
         }
     }
@@ -72,7 +112,134 @@ fn dismiss_codex_update_prompt(session: &mut Session) -> Result<bool> {
     Ok(true)
 }
 
+/// Number of trailing non-empty lines inspected by the generic modal heuristic.
+const GENERIC_MODAL_TAIL_LINES: usize = 12;
+/// Upper bound on the captured text carried by `DialogKind::Unknown`.
+const GENERIC_MODAL_MAX_CHARS: usize = 400;
+
+fn is_selected_numbered_option(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let Some(rest) = trimmed
+        .strip_prefix('❯')
+        .or_else(|| trimmed.strip_prefix('›'))
+    else {
+        return false;
+    };
+    let rest = rest.trim_start();
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && rest[digits..].starts_with('.')
+}
+
+fn is_yes_no_prompt(lower_line: &str) -> bool {
+    lower_line.contains("(y/n)")
+        || lower_line.contains("[y/n]")
+        || lower_line.contains("(yes/no)")
+        || lower_line.contains("[yes/no]")
+}
+
+/// Recognize generic modal layouts that matched no known dialog kind:
+/// a cursor-selected numbered menu, a y/n question, or a "press enter to
+/// continue" gate.  Only the tail of the capture is inspected so history
+/// scrolled above the current screen cannot trigger it.
+///
+/// Returns `DialogKind::Unknown` carrying the modal's lines so unfamiliar
+/// dialogs are reported verbatim instead of surfacing as a bare timeout.
+pub fn detect_generic_dialog(content: &str) -> Option<DialogKind> {
+    let mut tail: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .rev()
+        .take(GENERIC_MODAL_TAIL_LINES)
+        .collect();
+    tail.reverse();
+
+    let modal = tail.iter().any(|line| {
+        let lower = line.to_lowercase();
+        is_selected_numbered_option(line)
+            || is_yes_no_prompt(&lower)
+            || lower.contains("press enter to continue")
+    });
+    if !modal {
+        return None;
+    }
+
+    let summary: String = tail
+        .join(" | ")
+        .chars()
+        .take(GENERIC_MODAL_MAX_CHARS)
+        .collect();
+    Some(DialogKind::Unknown(summary))
+}
+
+fn looks_like_codex_model_picker(lower: &str) -> bool {
+    lower.contains("select model")
+        || lower.contains("choose a model")
+        || lower.contains("try new model")
+        || lower.contains("reasoning effort")
+        || lower.contains("select reasoning")
+}
+
+/// Find the numbered option that keeps the current model, e.g.
+/// `2. Use existing model` or `1. gpt-5-codex (current)`.
+fn current_model_option(content: &str) -> Option<String> {
+    static CURRENT_OPTION_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?i)(\d+)\.\s*[^\n]*?(?:\(current\)|use existing|keep current)").unwrap()
+    });
+    CURRENT_OPTION_RE
+        .captures(content)
+        .map(|caps| caps[1].to_string())
+}
+
+fn dismiss_codex_model_picker(session: &mut Session) -> Result<bool> {
+    // Never switch models on behalf of the user.
+    // Esc closes the picker on most versions and keeps the current selection.
+    session.send_keys("Esc")?;
+    thread::sleep(Duration::from_millis(250));
+
+    let content = session.capture_pane()?;
+    if !looks_like_codex_model_picker(&content.to_lowercase()) {
+        return Ok(true);
+    }
+
+    // Startup pickers cannot be escaped: explicitly select the current model.
+    if let Some(option) = current_model_option(&content) {
+        session.send_keys_literal(&option)?;
+        thread::sleep(Duration::from_millis(100));
+    }
+    session.send_keys("Enter")?;
+    thread::sleep(Duration::from_millis(400));
+
+    Ok(true)
+}
+
+fn looks_like_claude_theme_picker(lower: &str) -> bool {
+    lower.contains("choose the text style")
+        || (lower.contains("dark mode") && lower.contains("light mode"))
+}
+
+fn looks_like_claude_terminal_setup(lower: &str) -> bool {
+    lower.contains("terminal setup?")
+}
+
+fn looks_like_claude_onboarding_step(lower: &str) -> bool {
+    lower.contains("security notes") || looks_like_claude_terminal_setup(lower)
+}
+
+fn dismiss_claude_onboarding_step(session: &mut Session) -> Result<bool> {
+    let lower = session.capture_pane()?.to_lowercase();
+    if looks_like_claude_terminal_setup(&lower) {
+        // Never change the user's terminal configuration: pick "No, maybe later".
+        session.send_keys("Down")?;
+        thread::sleep(Duration::from_millis(120));
+    }
+    session.send_keys("Enter")?;
+    thread::sleep(Duration::from_secs(1));
+    Ok(true)
+}
+
 /// Detect Claude-specific dialogs in screen content.
+/// Priority: update > auth > theme > onboarding > first-run.
 pub fn detect_claude_dialog(content: &str) -> Option<DialogKind> {
     let lower = content.to_lowercase();
 
@@ -82,11 +249,22 @@ pub fn detect_claude_dialog(content: &str) -> Option<DialogKind> {
     if is_auth_required_prompt(&lower) {
         return Some(DialogKind::AuthRequired);
     }
+    // The theme picker is shown under the "Welcome to Claude Code" banner,
+    // so it must be checked before the generic first-run match.
+    if looks_like_claude_theme_picker(&lower) {
+        return Some(DialogKind::ThemeSelection);
+    }
+    if looks_like_claude_onboarding_step(&lower) {
+        return Some(DialogKind::OnboardingStep);
+    }
+    if lower.contains("do you trust the files in this folder") {
+        return Some(DialogKind::TrustFolder);
+    }
     if lower.contains("welcome to claude") || lower.contains("first time") {
         return Some(DialogKind::FirstRunSetup);
     }
 
-    None
+    detect_generic_dialog(content)
 }
 
 /// Detect Codex-specific dialogs in screen content.
@@ -96,6 +274,9 @@ pub fn detect_codex_dialog(content: &str) -> Option<DialogKind> {
     if lower.contains("update available") && lower.contains("codex") {
         return Some(DialogKind::UpdatePrompt);
     }
+    if looks_like_codex_model_picker(&lower) {
+        return Some(DialogKind::ModelSelection);
+    }
     if lower.contains("terms") && lower.contains("accept") {
         return Some(DialogKind::TermsAcceptance);
     }
@@ -111,7 +292,7 @@ pub fn detect_codex_dialog(content: &str) -> Option<DialogKind> {
         return Some(DialogKind::AuthRequired);
     }
 
-    None
+    detect_generic_dialog(content)
 }
 
 /// Detect Gemini-specific dialogs in screen content.
@@ -145,8 +326,106 @@ pub fn detect_gemini_dialog(content: &str) -> Option<DialogKind> {
     if is_auth_required_prompt(&lower) {
         return Some(DialogKind::AuthRequired);
     }
+    // Priority 6: Anything else that looks modal
+    detect_generic_dialog(content)
+}
+
+/// Detect Qwen Code dialogs in screen content.  Qwen Code is a Gemini CLI
+/// fork with the same trust, theme, update and terms screens; its first-run
+/// auth picker ("Qwen OAuth" or an OpenAI-compatible key) replaces Google
+/// sign-in.
+pub fn detect_qwen_dialog(content: &str) -> Option<DialogKind> {
+    let lower = content.to_lowercase();
+    if !lower.contains("do you trust this folder")
+        && (lower.contains("select authentication method") || lower.contains("qwen oauth"))
+    {
+        return Some(DialogKind::AuthRequired);
+    }
+    detect_gemini_dialog(content)
+}
+
+/// Detect Amazon Q Developer CLI dialogs in screen content.
+/// Priority: update > auth.
+pub fn detect_amazonq_dialog(content: &str) -> Option<DialogKind> {
+    let lower = content.to_lowercase();
+
+    if looks_like_update_prompt(content)
+        && (lower.contains("amazon q") || lower.contains("q update"))
+    {
+        return Some(DialogKind::UpdatePrompt);
+    }
+    if is_auth_required_prompt(&lower)
+        || lower.contains("not logged in")
+        || lower.contains("q login")
+    {
+        return Some(DialogKind::AuthRequired);
+    }
+    detect_generic_dialog(content)
+}
+
+/// Detect aider's startup questions in screen content.  Aider asks yes/no
+/// questions inline; the ones that would change the repository are never
+/// answered for the user.
+/// Priority: auth > repository setup > update.
+pub fn detect_aider_dialog(content: &str) -> Option<DialogKind> {
+    let lower = content.to_lowercase();
+
+    if lower.contains("no api keys were provided")
+        || lower.contains("login to openrouter")
+        || is_auth_required_prompt(&lower)
+    {
+        return Some(DialogKind::AuthRequired);
+    }
+    if lower.contains("no git repo found")
+        || lower.contains("to .gitignore")
+        || lower.contains("collection of anonymous analytics")
+    {
+        return Some(DialogKind::FirstRunSetup);
+    }
+    if lower.contains("newer aider version") {
+        return Some(DialogKind::UpdatePrompt);
+    }
+    detect_generic_dialog(content)
+}
+
+/// Detect Copilot CLI dialogs in screen content.
+/// Priority: trust > update > auth.
+pub fn detect_copilot_dialog(content: &str) -> Option<DialogKind> {
+    let lower = content.to_lowercase();
+
+    if lower.contains("do you trust the files in this folder")
+        || lower.contains("confirm folder trust")
+    {
+        return Some(DialogKind::TrustFolder);
+    }
+    if looks_like_update_prompt(content) && lower.contains("copilot") {
+        return Some(DialogKind::UpdatePrompt);
+    }
+    // Copilot asks for `/login` instead of opening a browser itself.
+    if is_auth_required_prompt(&lower) || lower.contains("use /login") {
+        return Some(DialogKind::AuthRequired);
+    }
+    detect_generic_dialog(content)
+}
 
-    None
+/// Detect `cursor-agent` dialogs in screen content.
+/// Priority: trust > update > auth.
+pub fn detect_cursor_dialog(content: &str) -> Option<DialogKind> {
+    let lower = content.to_lowercase();
+
+    if lower.contains("workspace trust") || lower.contains("do you trust the contents") {
+        return Some(DialogKind::TrustFolder);
+    }
+    if looks_like_update_prompt(content) && lower.contains("cursor") {
+        return Some(DialogKind::UpdatePrompt);
+    }
+    if is_auth_required_prompt(&lower)
+        || lower.contains("not logged in")
+        || lower.contains("cursor-agent login")
+    {
+        return Some(DialogKind::AuthRequired);
+    }
+    detect_generic_dialog(content)
 }
 
 /// Return a user-facing error message for a detected dialog.
@@ -177,6 +456,21 @@ pub fn dialog_error_message(kind: &DialogKind, provider: &str) -> String {
              Run '{0}' manually to complete setup first.",
             provider
         ),
+        DialogKind::ThemeSelection => format!(
+            "{} is showing the first-run theme selection. \
+             Run '{0}' manually to pick a theme, or use --approval-policy onboard.",
+            provider
+        ),
+        DialogKind::OnboardingStep => format!(
+            "{} is showing a first-run onboarding screen. \
+             Run '{0}' manually to finish onboarding, or use --approval-policy onboard.",
+            provider
+        ),
+        DialogKind::ModelSelection => format!(
+            "{} is showing a model selection dialog. \
+             Run '{0}' manually to choose a model, or use --approval-policy accept.",
+            provider
+        ),
         DialogKind::SandboxTrust => format!(
             "{} is showing a sandbox trust dialog. \
              Run '{0}' manually to trust, or use --approval-policy accept.",
@@ -184,7 +478,7 @@ pub fn dialog_error_message(kind: &DialogKind, provider: &str) -> String {
         ),
         DialogKind::Unknown(msg) => format!(
             "{} is showing an unexpected dialog: {}. \
-             Run '{0}' manually to resolve.",
+             Run '{0}' manually to resolve, and please report this screen.",
             provider, msg
         ),
     }
@@ -192,10 +486,30 @@ pub fn dialog_error_message(kind: &DialogKind, provider: &str) -> String {
 
 /// Attempt to dismiss a dialog by sending Enter.
 /// Returns Ok(true) if the dialog is dismissible (Enter sent),
-/// Ok(false) if it requires manual intervention (auth, first-run).
-pub fn dismiss_dialog(kind: &DialogKind, provider: &str, session: &mut Session) -> Result<bool> {
+/// Ok(false) if it requires manual intervention (auth, first-run, unknown) or
+/// the policy does not allow it (onboarding screens need `onboard`).
+pub fn dismiss_dialog(
+    kind: &DialogKind,
+    provider: &str,
+    policy: ApprovalPolicy,
+    session: &mut Session,
+) -> Result<bool> {
     match kind {
-        DialogKind::AuthRequired | DialogKind::FirstRunSetup => Ok(false),
+        // Never guess keys for a dialog we don't understand.
+        DialogKind::AuthRequired | DialogKind::FirstRunSetup | DialogKind::Unknown(_) => Ok(false),
+        DialogKind::ThemeSelection | DialogKind::OnboardingStep
+            if policy != ApprovalPolicy::Onboard =>
+        {
+            Ok(false)
+        }
+        // The default theme is pre-selected; Enter confirms it.
+        DialogKind::ThemeSelection => {
+            session.send_keys("Enter")?;
+            thread::sleep(Duration::from_secs(1));
+            Ok(true)
+        }
+        DialogKind::OnboardingStep => dismiss_claude_onboarding_step(session),
+        DialogKind::ModelSelection => dismiss_codex_model_picker(session),
         DialogKind::UpdatePrompt => {
             if provider == "codex" {
                 dismiss_codex_update_prompt(session)
@@ -213,6 +527,54 @@ pub fn dismiss_dialog(kind: &DialogKind, provider: &str, session: &mut Session)
     }
 }
 
+/// Check whether the dialog is gone, looking only at `fresh`, the output
+/// produced since the keypress.  No redraw at all means it was ignored.
+fn dialog_cleared<F>(kind: &DialogKind, fresh: &str, detect_fn: &F) -> bool
+where
+    F: Fn(&str) -> Option<DialogKind>,
+{
+    if fresh.trim().is_empty() {
+        return false;
+    }
+    !matches!(detect_fn(fresh), Some(ref k) if discriminant(k) == discriminant(kind))
+}
+
+/// Dismiss a dialog and verify it actually disappeared.  If the dialog is
+/// still showing, escalate through `ESCALATION_STRATEGIES` before giving up.
+/// Returns Ok(false) if the dialog is not dismissible or survived every strategy.
+pub fn dismiss_and_verify<F>(
+    kind: &DialogKind,
+    provider: &str,
+    policy: ApprovalPolicy,
+    session: &mut Session,
+    detect_fn: &F,
+) -> Result<bool>
+where
+    F: Fn(&str) -> Option<DialogKind>,
+{
+    let mut mark = session.output_mark()?;
+    if !dismiss_dialog(kind, provider, policy, session)? {
+        return Ok(false);
+    }
+    if dialog_cleared(kind, &session.capture_since(mark)?, detect_fn) {
+        return Ok(true);
+    }
+
+    for strategy in ESCALATION_STRATEGIES {
+        mark = session.output_mark()?;
+        for key in *strategy {
+            session.send_keys(key)?;
+            thread::sleep(Duration::from_millis(120));
+        }
+        thread::sleep(Duration::from_millis(500));
+        if dialog_cleared(kind, &session.capture_since(mark)?, detect_fn) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -246,12 +608,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_detect_claude_trust_folder() {
+        let content = "Do you trust the files in this folder?\n\n/home/me/.local/state/agentusage/launch\n\n\
+                       Claude Code may read files in this folder.\n❯ 1. Yes, proceed\n  2. No, exit";
+        assert_eq!(detect_claude_dialog(content), Some(DialogKind::TrustFolder));
+    }
+
     #[test]
     fn test_detect_claude_none() {
         let content = "❯ Ready for input\nTips: use /help for commands";
         assert_eq!(detect_claude_dialog(content), None);
     }
 
+    #[test]
+    fn test_detect_claude_theme_picker() {
+        let content = "Welcome to Claude Code\nLet's get started.\n\
+                       Choose the text style that looks best with your terminal:\n\
+                       ❯ 1. Dark mode ✔\n  2. Light mode";
+        assert_eq!(
+            detect_claude_dialog(content),
+            Some(DialogKind::ThemeSelection)
+        );
+    }
+
+    #[test]
+    fn test_detect_claude_security_notes_onboarding() {
+        let content = "Security notes:\n1. Claude can make mistakes\nPress Enter to continue…";
+        assert_eq!(
+            detect_claude_dialog(content),
+            Some(DialogKind::OnboardingStep)
+        );
+    }
+
+    #[test]
+    fn test_detect_claude_terminal_setup_onboarding() {
+        let content = "Use Claude Code's terminal setup?\n❯ 1. Yes, use recommended settings\n  2. No, maybe later";
+        assert_eq!(
+            detect_claude_dialog(content),
+            Some(DialogKind::OnboardingStep)
+        );
+    }
+
+    #[test]
+    fn test_detect_claude_auth_before_theme() {
+        let content = "Dark mode / Light mode\nPlease sign in to continue.";
+        assert_eq!(
+            detect_claude_dialog(content),
+            Some(DialogKind::AuthRequired)
+        );
+    }
+
     // ── Codex dialog detection ──────────────────────────────────────
 
     #[test]
@@ -293,6 +700,50 @@ mod tests {
         assert_eq!(detect_codex_dialog(content), Some(DialogKind::TrustFolder));
     }
 
+    #[test]
+    fn test_detect_codex_model_picker() {
+        let content = "Select Model and Effort\n› 1. gpt-5-codex (current)\n  2. gpt-5";
+        assert_eq!(
+            detect_codex_dialog(content),
+            Some(DialogKind::ModelSelection)
+        );
+    }
+
+    #[test]
+    fn test_detect_codex_reasoning_picker() {
+        let content = "Select Reasoning Level for gpt-5-codex\n› 1. Low\n  2. Medium (current)";
+        assert_eq!(
+            detect_codex_dialog(content),
+            Some(DialogKind::ModelSelection)
+        );
+    }
+
+    #[test]
+    fn test_detect_codex_new_model_announcement() {
+        let content = "Introducing GPT-5.1-Codex\n› 1. Try new model\n  2. Use existing model";
+        assert_eq!(
+            detect_codex_dialog(content),
+            Some(DialogKind::ModelSelection)
+        );
+    }
+
+    #[test]
+    fn test_current_model_option_marked_current() {
+        let content = "› 1. gpt-5-codex\n  2. gpt-5 (current)\n  3. o3";
+        assert_eq!(current_model_option(content), Some("2".to_string()));
+    }
+
+    #[test]
+    fn test_current_model_option_use_existing() {
+        let content = "› 1. Try new model\n  2. Use existing model";
+        assert_eq!(current_model_option(content), Some("2".to_string()));
+    }
+
+    #[test]
+    fn test_current_model_option_absent() {
+        assert_eq!(current_model_option("› 1. Low\n  2. High"), None);
+    }
+
     #[test]
     fn test_detect_codex_none() {
         let content = ">_ OpenAI Codex\n? for shortcuts";
@@ -316,6 +767,87 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_detect_copilot_dialogs() {
+        let trust = "Confirm folder trust\n\nDo you trust the files in this folder?\n\
+                     ❯ 1. Yes\n  2. Yes, and remember this folder\n  3. No (Esc)";
+        assert_eq!(detect_copilot_dialog(trust), Some(DialogKind::TrustFolder));
+        assert_eq!(
+            detect_copilot_dialog("You are not logged in. Please use /login to sign in."),
+            Some(DialogKind::AuthRequired)
+        );
+        assert_eq!(
+            detect_copilot_dialog("Welcome to GitHub Copilot CLI\n> Enter @ to mention files"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detect_cursor_dialogs() {
+        let trust = "⚠ Workspace Trust Required\n\
+                     Do you trust the contents of this directory?\n\
+                     ▶ [a] Trust this workspace\n  [q] Quit";
+        assert_eq!(detect_cursor_dialog(trust), Some(DialogKind::TrustFolder));
+        assert_eq!(
+            detect_cursor_dialog("Not logged in. Run `cursor-agent login` to continue."),
+            Some(DialogKind::AuthRequired)
+        );
+        assert_eq!(
+            detect_cursor_dialog("Cursor Agent\n→ Plan, search, build anything"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detect_qwen_dialogs() {
+        assert_eq!(
+            detect_qwen_dialog("Do you trust this folder and allow Qwen Code to run?"),
+            Some(DialogKind::TrustFolder)
+        );
+        assert_eq!(
+            detect_qwen_dialog("Select a theme for Qwen Code"),
+            Some(DialogKind::FirstRunSetup)
+        );
+        assert_eq!(
+            detect_qwen_dialog("Select Authentication Method\n● Qwen OAuth\n  OpenAI"),
+            Some(DialogKind::AuthRequired)
+        );
+        assert_eq!(
+            detect_qwen_dialog("Loaded QWEN.md\n> Type your message"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detect_amazonq_dialogs() {
+        assert_eq!(
+            detect_amazonq_dialog("You are not logged in, please log in with q login"),
+            Some(DialogKind::AuthRequired)
+        );
+        assert_eq!(
+            detect_amazonq_dialog("/help all commands  •  ctrl + j new lines\n>"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detect_aider_dialogs() {
+        assert_eq!(
+            detect_aider_dialog(
+                "No git repo found, create one to track aider's changes (recommended)? (Y)es/(N)o [Yes]:"
+            ),
+            Some(DialogKind::FirstRunSetup)
+        );
+        assert_eq!(
+            detect_aider_dialog("No LLM model was specified and no API keys were provided."),
+            Some(DialogKind::AuthRequired)
+        );
+        assert_eq!(
+            detect_aider_dialog("Aider v0.86.1\nMain model: gpt-4o with diff edit format\n>"),
+            None
+        );
+    }
+
     #[test]
     fn test_detect_gemini_none() {
         let content = "Loaded GEMINI.md\nFound 3 MCP servers\ngemini >";
@@ -487,6 +1019,27 @@ mod tests {
         assert!(msg.contains("setup"));
     }
 
+    #[test]
+    fn test_error_message_theme_selection_mentions_onboard() {
+        let msg = dialog_error_message(&DialogKind::ThemeSelection, "claude");
+        assert!(msg.contains("theme"));
+        assert!(msg.contains("--approval-policy onboard"));
+    }
+
+    #[test]
+    fn test_error_message_onboarding_step_mentions_onboard() {
+        let msg = dialog_error_message(&DialogKind::OnboardingStep, "claude");
+        assert!(msg.contains("onboarding"));
+        assert!(msg.contains("--approval-policy onboard"));
+    }
+
+    #[test]
+    fn test_error_message_model_selection() {
+        let msg = dialog_error_message(&DialogKind::ModelSelection, "codex");
+        assert!(msg.contains("model selection"));
+        assert!(msg.contains("--approval-policy accept"));
+    }
+
     #[test]
     fn test_error_message_sandbox_trust() {
         let msg = dialog_error_message(&DialogKind::SandboxTrust, "codex");
@@ -703,6 +1256,133 @@ mod tests {
         );
     }
 
+    // ── Generic modal heuristic ─────────────────────────────────────
+
+    #[test]
+    fn test_generic_dialog_selected_numbered_menu() {
+        let content = "Something new happened\n❯ 1. Continue\n  2. Cancel";
+        match detect_generic_dialog(content) {
+            Some(DialogKind::Unknown(text)) => {
+                assert!(text.contains("Something new happened"));
+                assert!(text.contains("2. Cancel"));
+            }
+            other => panic!("expected Unknown, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_generic_dialog_yes_no() {
+        let content = "Enable telemetry? (y/n)";
+        assert!(matches!(
+            detect_generic_dialog(content),
+            Some(DialogKind::Unknown(_))
+        ));
+    }
+
+    #[test]
+    fn test_generic_dialog_press_enter() {
+        let content = "Release notes for v9\nPress Enter to continue";
+        assert!(matches!(
+            detect_generic_dialog(content),
+            Some(DialogKind::Unknown(_))
+        ));
+    }
+
+    #[test]
+    fn test_generic_dialog_ignores_unselected_numbered_tips() {
+        let content = "Tips for getting started:\n1. Ask questions\n2. Be specific\n> ";
+        assert_eq!(detect_generic_dialog(content), None);
+    }
+
+    #[test]
+    fn test_generic_dialog_only_inspects_tail() {
+        let mut content = String::from("Old prompt (y/n)\n");
+        for i in 0..GENERIC_MODAL_TAIL_LINES {
+            content.push_str(&format!("line {}\n", i));
+        }
+        assert_eq!(detect_generic_dialog(&content), None);
+    }
+
+    #[test]
+    fn test_generic_dialog_text_is_bounded() {
+        let content = format!("{}\n❯ 1. Ok", "x".repeat(2000));
+        match detect_generic_dialog(&content) {
+            Some(DialogKind::Unknown(text)) => {
+                assert_eq!(text.chars().count(), GENERIC_MODAL_MAX_CHARS)
+            }
+            other => panic!("expected Unknown, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_known_dialog_wins_over_generic() {
+        let content = "Do you trust this folder?\n❯ 1. Trust folder\n  2. Don't trust";
+        assert_eq!(detect_gemini_dialog(content), Some(DialogKind::TrustFolder));
+    }
+
+    #[test]
+    fn test_detect_codex_falls_back_to_generic() {
+        let content = ">_ OpenAI Codex\nTry the new agent mode?\n› 1. Yes\n  2. No";
+        assert!(matches!(
+            detect_codex_dialog(content),
+            Some(DialogKind::Unknown(_))
+        ));
+    }
+
+    // ── Dismissal verification ──────────────────────────────────────
+
+    #[test]
+    fn test_dialog_cleared_when_prompt_redrawn() {
+        assert!(dialog_cleared(
+            &DialogKind::TrustFolder,
+            "\n> ",
+            &detect_gemini_dialog
+        ));
+    }
+
+    #[test]
+    fn test_dialog_not_cleared_without_redraw() {
+        assert!(!dialog_cleared(
+            &DialogKind::TrustFolder,
+            "",
+            &detect_gemini_dialog
+        ));
+    }
+
+    #[test]
+    fn test_dialog_not_cleared_when_redrawn_again() {
+        assert!(!dialog_cleared(
+            &DialogKind::TrustFolder,
+            "\nDo you trust this folder?",
+            &detect_gemini_dialog
+        ));
+    }
+
+    #[test]
+    fn test_dialog_cleared_when_different_dialog_follows() {
+        assert!(dialog_cleared(
+            &DialogKind::TrustFolder,
+            "\nUpdate available: v0.29.0",
+            &detect_gemini_dialog
+        ));
+    }
+
+    #[test]
+    fn test_dialog_cleared_when_buffer_trims_mid_dismissal() {
+        let mut capture = crate::capture::CaptureBuffer::new(72);
+        capture.push(b"Gemini CLI\nDo you trust this folder?\n");
+        let mark = capture.mark();
+        // The redraw pushes the start of the capture out of the buffer, so
+        // the old screen is no longer a prefix of the new one.
+        capture.push(b"\n> Type your message or @path/to/file\n");
+        assert!(capture.text().contains("Do you trust this folder?"));
+        assert!(dialog_cleared(
+            &DialogKind::TrustFolder,
+            &capture.text_since(mark),
+            &detect_gemini_dialog
+        ));
+    }
+
     // ── Dismissibility (logic only) ─────────────────────────────────
 
     #[test]
@@ -734,4 +1414,36 @@ mod tests {
             DialogKind::AuthRequired | DialogKind::FirstRunSetup
         ));
     }
+
+    #[test]
+    fn test_codex_prompt_ready_footer() {
+        assert!(codex_prompt_ready(
+            ">_ OpenAI Codex\n\n› Ask Codex to do anything\n\n? for shortcuts"
+        ));
+    }
+
+    #[test]
+    fn test_codex_prompt_ready_without_footer() {
+        let boxed = "╭────────────────╮\n│ >_ OpenAI Codex │\n╰────────────────╯\n\n\
+                     ╭──────────────────────────────╮\n\
+                     │ › Ask Codex to do anything   │\n\
+                     ╰──────────────────────────────╯\n  100% context left";
+        assert!(codex_prompt_ready(boxed));
+        assert!(codex_prompt_ready(
+            ">_ OpenAI Codex\n\n▌ Improve documentation\n\n⏎ send"
+        ));
+        assert!(codex_prompt_ready("› \n"));
+    }
+
+    #[test]
+    fn test_codex_prompt_ready_not_banner_or_dialog() {
+        assert!(!codex_prompt_ready(
+            "╭────────────────────╮\n│ >_ OpenAI Codex (v0.101.0) │\n╰────────────────────╯"
+        ));
+        assert!(!codex_prompt_ready(
+            "Select Model and Effort\n› 1. gpt-5-codex (current)\n  2. gpt-5"
+        ));
+        assert!(!codex_prompt_ready("› 12. Try new model"));
+        assert!(!codex_prompt_ready(""));
+    }
 }
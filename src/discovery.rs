@@ -0,0 +1,204 @@
+use regex::Regex;
+use serde::Serialize;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::LazyLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::plan::find_on_path;
+
+/// How long a CLI gets to answer `--version` before it's reported without one.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+static VERSION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d+\.\d+(?:\.\d+)?(?:-[0-9A-Za-z.]+)?").unwrap());
+
+/// An agent CLI agentusage knows how to find, whether or not it can check it.
+struct KnownCli {
+    name: &'static str,
+    display_name: &'static str,
+    binary: &'static str,
+}
+
+const KNOWN_CLIS: [KnownCli; 8] = [
+    KnownCli {
+        name: "claude",
+        display_name: "Claude Code",
+        binary: "claude",
+    },
+    KnownCli {
+        name: "codex",
+        display_name: "Codex",
+        binary: "codex",
+    },
+    KnownCli {
+        name: "gemini",
+        display_name: "Gemini CLI",
+        binary: "gemini",
+    },
+    KnownCli {
+        name: "copilot",
+        display_name: "GitHub Copilot CLI",
+        binary: "copilot",
+    },
+    KnownCli {
+        name: "cursor",
+        display_name: "Cursor Agent",
+        binary: "cursor-agent",
+    },
+    KnownCli {
+        name: "qwen",
+        display_name: "Qwen Code",
+        binary: "qwen",
+    },
+    KnownCli {
+        name: "amazonq",
+        display_name: "Amazon Q Developer CLI",
+        binary: "q",
+    },
+    KnownCli {
+        name: "aider",
+        display_name: "Aider",
+        binary: "aider",
+    },
+];
+
+/// One agent CLI as found on this machine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProviderInfo {
+    /// Provider name as accepted by `run_provider`.
+    pub name: &'static str,
+    pub display_name: &'static str,
+    pub binary: &'static str,
+    /// agentusage can check this provider's usage.
+    pub supported: bool,
+    pub installed: bool,
+    /// Where `binary` resolves on PATH.
+    pub path: Option<String>,
+    /// Parsed from `binary --version`; `None` if it printed nothing usable
+    /// or took longer than a few seconds.
+    pub version: Option<String>,
+}
+
+/// Report which agent CLIs are installed, their versions, and which of them
+/// agentusage supports.  Version probes run in parallel.
+pub fn list_providers() -> Vec<ProviderInfo> {
+    thread::scope(|scope| {
+        let probes: Vec<_> = KNOWN_CLIS
+            .iter()
+            .map(|cli| scope.spawn(move || probe(cli)))
+            .collect();
+        probes
+            .into_iter()
+            .zip(&KNOWN_CLIS)
+            .map(|(probe, cli)| probe.join().unwrap_or_else(|_| not_installed(cli)))
+            .collect()
+    })
+}
+
+/// `provider`'s product name, e.g. "Gemini CLI", if agentusage knows it.
+#[cfg(feature = "dashboard")]
+pub(crate) fn display_name(provider: &str) -> Option<&'static str> {
+    KNOWN_CLIS
+        .iter()
+        .find(|cli| cli.name == provider)
+        .map(|cli| cli.display_name)
+}
+
+fn not_installed(cli: &KnownCli) -> ProviderInfo {
+    ProviderInfo {
+        name: cli.name,
+        display_name: cli.display_name,
+        binary: cli.binary,
+        supported: crate::ALL_PROVIDERS.contains(&cli.name),
+        installed: false,
+        path: None,
+        version: None,
+    }
+}
+
+fn probe(cli: &KnownCli) -> ProviderInfo {
+    let mut info = not_installed(cli);
+    if let Some(path) = find_on_path(cli.binary) {
+        info.installed = true;
+        info.version = probe_version(&path);
+        info.path = Some(path.display().to_string());
+    }
+    info
+}
+
+fn probe_version(path: &Path) -> Option<String> {
+    let mut child = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let deadline = Instant::now() + VERSION_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(20)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    parse_version(&output)
+}
+
+/// The version number in `--version` output, else its first non-empty line.
+fn parse_version(output: &str) -> Option<String> {
+    if let Some(found) = VERSION_RE.find(output) {
+        return Some(found.as_str().to_string());
+    }
+    output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version_formats() {
+        assert_eq!(
+            parse_version("2.0.14 (Claude Code)\n").as_deref(),
+            Some("2.0.14")
+        );
+        assert_eq!(
+            parse_version("codex-cli 0.46.0\n").as_deref(),
+            Some("0.46.0")
+        );
+        assert_eq!(
+            parse_version("0.9.0-nightly.20251001\n").as_deref(),
+            Some("0.9.0-nightly.20251001")
+        );
+        assert_eq!(
+            parse_version("\n  dev build\n").as_deref(),
+            Some("dev build")
+        );
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn test_list_providers_covers_known_clis() {
+        let found = list_providers();
+        let names: Vec<&str> = found.iter().map(|info| info.name).collect();
+        assert_eq!(&names[..crate::ALL_PROVIDERS.len()], &crate::ALL_PROVIDERS);
+        for info in &found {
+            assert_eq!(info.supported, crate::ALL_PROVIDERS.contains(&info.name));
+            assert_eq!(info.installed, info.path.is_some());
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::collections::HashMap;
+
+use crate::history::ENCRYPTED_PREFIX as PREFIX;
+
+/// The passphrase that encrypts the history.
+pub const PASSPHRASE_VAR: &str = "AGENTUSAGE_HISTORY_PASSPHRASE";
+/// A command that prints the passphrase, e.g. one reading the OS keyring.
+pub const PASSPHRASE_COMMAND_VAR: &str = "AGENTUSAGE_HISTORY_PASSPHRASE_COMMAND";
+
+const SALT_LEN: usize = 16;
+
+/// scrypt cost: 2^15 iterations, about 50ms, paid once per salt.  Tests
+/// use a cheap one; unoptimized, the real cost takes seconds.
+const SCRYPT_LOG_N: u8 = if cfg!(test) { 4 } else { 15 };
+
+/// `$AGENTUSAGE_HISTORY_PASSPHRASE`, else what
+/// `$AGENTUSAGE_HISTORY_PASSPHRASE_COMMAND` prints; `None` when neither is
+/// set, so the history stays plaintext.
+pub fn passphrase() -> Result<Option<String>> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    if let Some(passphrase) = var(PASSPHRASE_VAR) {
+        return Ok(Some(passphrase));
+    }
+    let Some(command) = var(PASSPHRASE_COMMAND_VAR) else {
+        return Ok(None);
+    };
+    let output = std::process::Command::new("sh")
+        .args(["-c", &command])
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .with_context(|| format!("Failed to run {}", PASSPHRASE_COMMAND_VAR))?;
+    if !output.status.success() {
+        bail!("{} failed ({})", PASSPHRASE_COMMAND_VAR, output.status);
+    }
+    let passphrase = String::from_utf8(output.stdout)
+        .map_err(|_| anyhow!("{} printed invalid UTF-8", PASSPHRASE_COMMAND_VAR))?;
+    let passphrase = passphrase.trim_end_matches(['\r', '\n']);
+    if passphrase.is_empty() {
+        bail!("{} printed nothing", PASSPHRASE_COMMAND_VAR);
+    }
+    Ok(Some(passphrase.to_string()))
+}
+
+/// Seals and opens history lines under one passphrase, deriving each
+/// salt's key once.
+pub(crate) struct Crypter {
+    passphrase: String,
+    keys: HashMap<Vec<u8>, Key>,
+}
+
+impl Crypter {
+    pub(crate) fn new(passphrase: &str) -> Self {
+        Self {
+            passphrase: passphrase.to_string(),
+            keys: HashMap::new(),
+        }
+    }
+
+    fn key(&mut self, salt: &[u8]) -> Result<Key> {
+        if let Some(key) = self.keys.get(salt) {
+            return Ok(*key);
+        }
+        let params = scrypt::Params::new(SCRYPT_LOG_N, 8, 1, 32)
+            .map_err(|e| anyhow!("scrypt parameters: {}", e))?;
+        let mut key = Key::default();
+        scrypt::scrypt(self.passphrase.as_bytes(), salt, &params, &mut key)
+            .map_err(|e| anyhow!("scrypt: {}", e))?;
+        self.keys.insert(salt.to_vec(), key);
+        Ok(key)
+    }
+
+    /// Encrypt `line` with the key for `salt`.
+    pub(crate) fn seal(&mut self, line: &str, salt: &[u8]) -> Result<String> {
+        let cipher = ChaCha20Poly1305::new(&self.key(salt)?);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = cipher
+            .encrypt(&nonce, line.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt a history record"))?;
+        Ok(format!(
+            "{}{}:{}:{}",
+            PREFIX,
+            BASE64.encode(salt),
+            BASE64.encode(nonce),
+            BASE64.encode(sealed)
+        ))
+    }
+
+    /// Decrypt a line `seal` wrote.  Fails on a wrong passphrase.
+    pub(crate) fn open(&mut self, line: &str) -> Result<String> {
+        let (salt, nonce, sealed) = parts(line)?;
+        if nonce.len() != 12 {
+            bail!("Malformed encrypted history record");
+        }
+        let cipher = ChaCha20Poly1305::new(&self.key(&salt)?);
+        let plain = cipher
+            .decrypt(Nonce::from_slice(&nonce), sealed.as_slice())
+            .map_err(|_| anyhow!("Failed to decrypt the history; wrong passphrase?"))?;
+        String::from_utf8(plain).map_err(|_| anyhow!("Malformed encrypted history record"))
+    }
+}
+
+/// A random salt for a history that has none yet.
+pub(crate) fn new_salt() -> Vec<u8> {
+    let mut salt = vec![0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// The salt an encrypted `line` was sealed under.
+pub(crate) fn salt(line: &str) -> Option<Vec<u8>> {
+    parts(line).ok().map(|(salt, _, _)| salt)
+}
+
+fn parts(line: &str) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+    let malformed = || anyhow!("Malformed encrypted history record");
+    let mut parts = line.strip_prefix(PREFIX).ok_or_else(malformed)?.split(':');
+    let mut next = || -> Result<Vec<u8>> {
+        let part = parts.next().ok_or_else(malformed)?;
+        BASE64.decode(part.trim()).map_err(|_| malformed())
+    };
+    Ok((next()?, next()?, next()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let mut crypter = Crypter::new("correct horse");
+        let line = r#"{"provider":"claude","label":"Current week (all models)"}"#;
+        let salt = new_salt();
+        let sealed = crypter.seal(line, &salt).unwrap();
+        assert!(sealed.starts_with(PREFIX));
+        assert!(!sealed.contains("claude"));
+        assert_eq!(crypter.open(&sealed).unwrap(), line);
+
+        // Lines sealed under one salt share its key but not their nonce.
+        assert_eq!(super::salt(&sealed), Some(salt.clone()));
+        let again = crypter.seal(line, &salt).unwrap();
+        assert_ne!(again, sealed);
+        assert_eq!(crypter.keys.len(), 1);
+
+        let err = Crypter::new("wrong").open(&sealed).unwrap_err();
+        assert!(err.to_string().contains("wrong passphrase"));
+        assert!(crypter.open("enc1:nope").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+
+/// The terminal and host details capture bugs most often hinge on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Environment {
+    pub term: Option<String>,
+    pub colorterm: Option<String>,
+    pub lang: Option<String>,
+    pub shell: Option<String>,
+    pub os: String,
+    pub arch: String,
+    pub ssh: bool,
+    pub ci: bool,
+    /// Running in a Docker, Podman, or Kubernetes container.
+    pub container: bool,
+}
+
+impl Environment {
+    /// Snapshot the current process environment.
+    pub fn capture() -> Self {
+        let mut env = Self::from_vars(|name| std::env::var(name).ok());
+        env.container |= ["/.dockerenv", "/run/.containerenv"]
+            .iter()
+            .any(|marker| std::path::Path::new(marker).exists());
+        env
+    }
+
+    fn from_vars<F: Fn(&str) -> Option<String>>(get: F) -> Self {
+        let non_empty = |name: &str| get(name).filter(|v| !v.is_empty());
+        Self {
+            term: non_empty("TERM"),
+            colorterm: non_empty("COLORTERM"),
+            // LC_ALL overrides LANG for every locale category.
+            lang: non_empty("LC_ALL").or_else(|| non_empty("LANG")),
+            shell: non_empty("SHELL"),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            ssh: non_empty("SSH_CONNECTION").is_some() || non_empty("SSH_TTY").is_some(),
+            ci: non_empty("CI").is_some_and(|v| v != "0" && v != "false"),
+            // Podman and systemd-nspawn set `container`; Kubernetes sets the
+            // service host in every pod.
+            container: non_empty("container").is_some()
+                || non_empty("KUBERNETES_SERVICE_HOST").is_some(),
+        }
+    }
+
+    /// `(label, value)` pairs for display; unset variables show as "(unset)".
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let show = |v: &Option<String>| v.clone().unwrap_or_else(|| "(unset)".into());
+        let yes_no = |b: bool| if b { "yes" } else { "no" }.to_string();
+        vec![
+            ("TERM", show(&self.term)),
+            ("COLORTERM", show(&self.colorterm)),
+            ("LANG", show(&self.lang)),
+            ("SHELL", show(&self.shell)),
+            ("OS", format!("{} ({})", self.os, self.arch)),
+            ("SSH", yes_no(self.ssh)),
+            ("CI", yes_no(self.ci)),
+            ("Container", yes_no(self.container)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env_from(pairs: &[(&str, &str)]) -> Environment {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Environment::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_environment_reads_terminal_vars() {
+        let env = env_from(&[
+            ("TERM", "xterm-256color"),
+            ("COLORTERM", "truecolor"),
+            ("LANG", "en_US.UTF-8"),
+            ("SHELL", "/bin/zsh"),
+        ]);
+        assert_eq!(env.term.as_deref(), Some("xterm-256color"));
+        assert_eq!(env.colorterm.as_deref(), Some("truecolor"));
+        assert_eq!(env.lang.as_deref(), Some("en_US.UTF-8"));
+        assert_eq!(env.shell.as_deref(), Some("/bin/zsh"));
+        assert!(!env.ssh);
+        assert!(!env.ci);
+    }
+
+    #[test]
+    fn test_environment_lc_all_wins_over_lang() {
+        let env = env_from(&[("LANG", "en_US.UTF-8"), ("LC_ALL", "C")]);
+        assert_eq!(env.lang.as_deref(), Some("C"));
+    }
+
+    #[test]
+    fn test_environment_detects_ssh_and_ci() {
+        let env = env_from(&[
+            ("SSH_CONNECTION", "10.0.0.1 5000 10.0.0.2 22"),
+            ("CI", "true"),
+        ]);
+        assert!(env.ssh);
+        assert!(env.ci);
+        assert!(!env.container);
+        assert!(env_from(&[("container", "podman")]).container);
+        let env = env_from(&[("CI", "false"), ("TERM", "")]);
+        assert!(!env.ci);
+        assert_eq!(env.term, None);
+    }
+
+    #[test]
+    fn test_environment_rows_mark_unset() {
+        let rows = env_from(&[]).rows();
+        assert!(rows.contains(&("TERM", "(unset)".to_string())));
+        assert!(rows.contains(&("CI", "no".to_string())));
+    }
+}
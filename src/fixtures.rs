@@ -0,0 +1,372 @@
+use crate::parser::window_for_label;
+use crate::types::{
+    CheckStats, Confidence, LimitScope, LimitWindow, PercentKind, UsageData, UsageEntry,
+};
+
+/// Captured Claude `/usage` screen.
+pub(crate) const CLAUDE_CAPTURE: &str = "\
+Settings:   Status    Config   [Usage]
+
+Current session
+████████░░░░░░░░  1% used
+Resets 2pm (America/Chicago)
+
+Current week (all models)
+░░░░░░░░░░░░░░░░  0% used
+Resets Feb 20 at 9am (America/Chicago)
+
+Current week (Sonnet only)
+░░░░░░░░░░░░░░░░  0% used
+Resets Feb 15 at 11am (America/Chicago)
+
+Extra usage
+██░░░░░░░░░░░░░░  15% used
+$77.33 / $500.00 spent · Resets Mar 1 (America/Chicago)
+";
+
+/// Claude `/usage` on a Team seat: personal rows plus a shared org limit.
+pub(crate) const CLAUDE_TEAM_CAPTURE: &str = "\
+Settings:   Status    Config   [Usage]
+
+Current session
+██░░░░░░░░░░░░░░  12% used
+Resets 2pm (America/Chicago)
+
+Current week (all models)
+█████░░░░░░░░░░░  30% used
+Resets Feb 20 at 9am (America/Chicago)
+
+Organization usage (shared)
+███████░░░░░░░░░  45% of your organization's limit used
+Resets Mar 1 (America/Chicago)
+";
+
+/// Claude `/usage` on an Enterprise seat: a per-seat row and a pooled
+/// monthly spend limit, with the reset on the same line as the percentage.
+pub(crate) const CLAUDE_ENTERPRISE_CAPTURE: &str = "\
+Settings:   Status    Config   [Usage]
+
+Your seat
+█░░░░░░░░░░░░░░░  8% used · Resets 2pm (America/Chicago)
+
+Team monthly limit
+██████████░░░░░░  62% used
+$3,100.00 / $5,000.00 spent · Resets Mar 1 (America/Chicago)
+";
+
+/// Captured Codex `/status` screen.
+pub(crate) const CODEX_CAPTURE: &str = "\
+│  >_ OpenAI Codex (v0.101.0)                                                             │
+│                                                                                         │
+│  Model:                       gpt-5.3-codex (reasoning xhigh, summaries auto)           │
+│  Account:                     user@example.com (Pro)                                    │
+│                                                                                         │
+│  5h limit:                    [███████████████████░] 97% left (resets 11:07)            │
+│  Weekly limit:                [██████████████░░░░░░] 71% left (resets 12:07 on 16 Feb)  │
+";
+
+/// Codex `/status` on a Business workspace: personal rows plus pooled
+/// workspace rows under their own section.
+pub(crate) const CODEX_BUSINESS_CAPTURE: &str = "\
+│  >_ OpenAI Codex (v0.101.0)                                                             │
+│                                                                                         │
+│  Model:                       gpt-5.3-codex (reasoning xhigh, summaries auto)           │
+│  Account:                     user@acme.example (Business)                              │
+│                                                                                         │
+│  5h limit:                    [███████████████████░] 97% left (resets 11:07)            │
+│  Weekly limit:                [██████████████░░░░░░] 71% left (resets 12:07 on 16 Feb)  │
+│  Workspace limits:                                                                      │
+│  Shared credits:              [██████░░░░░░░░░░░░░░] 30% left (resets 00:00 on 1 Mar)   │
+│  Weekly limit:                [██████████░░░░░░░░░░] 52% used (resets 09:00 on 20 Feb)  │
+";
+
+/// Captured Gemini `/stats session` screen.
+pub(crate) const GEMINI_CAPTURE: &str = "\
+│  Model Usage                 Reqs                  Usage left
+│  ────────────────────────────────────────────────────────────
+│  gemini-2.5-flash-lite          2   99.9% (Resets in 23h 58m)
+│  gemini-2.5-flash               6    99.3% (Resets in 4h 49m)
+│  gemini-2.5-pro                 -    98.1% (Resets in 2h 35m)
+";
+
+/// Gemini `/stats session` under a Code Assist Standard license: request
+/// counts against a daily quota rather than a percentage left.
+pub(crate) const GEMINI_CODE_ASSIST_CAPTURE: &str = "\
+│  Gemini Code Assist Standard
+│  Model Usage                 Requests today             Daily quota
+│  ────────────────────────────────────────────────────────────────────
+│  gemini-2.5-pro            120 / 1,500 requests   (Resets in 11h 20m)
+│  gemini-2.5-flash           40 / 2,000 requests   (Resets in 11h 20m)
+";
+
+/// Captured Copilot CLI `/usage` screen on a Pro plan: premium requests
+/// against the monthly allowance, chat and completions unlimited.
+pub(crate) const COPILOT_CAPTURE: &str = "\
+● Usage
+
+  Premium requests     ███████░░░░░░░░░  132 / 300 (44%)
+  Chat messages        Unlimited
+  Code completions     Unlimited
+
+  Allowance resets on Nov 1, 2026 (UTC)
+";
+
+/// Copilot CLI `/usage` on the Free plan, where chat and completions have
+/// monthly allowances too.
+pub(crate) const COPILOT_FREE_CAPTURE: &str = "\
+● Usage
+
+  Premium requests     ███░░░░░░░░░░░░░  12 / 50 (24%)
+  Chat messages        ████████████░░░░  38 / 50 (76%)
+  Code completions     ███░░░░░░░░░░░░░  420 / 2,000 (21%)
+
+  Allowance resets in 13 days
+";
+
+/// Captured `cursor-agent` `/status` screen on a Pro plan: included and
+/// on-demand spend for the billing cycle.
+pub(crate) const CURSOR_CAPTURE: &str = "\
+│  Cursor Agent                                                     │
+│  Account        user@example.com (Pro)                            │
+│                                                                   │
+│  Usage this billing cycle                                         │
+│  Included usage     ██████████░░░░░░  $12.40 / $20.00 (62%)        │
+│  On-demand usage    █░░░░░░░░░░░░░░░  $3.10 / $50.00 (6%)          │
+│                                                                   │
+│  Resets on Nov 1                                                  │
+";
+
+/// Captured Qwen Code `/stats` screen on the free Qwen OAuth tier: request
+/// counts against the daily quota.
+pub(crate) const QWEN_CAPTURE: &str = "\
+│  Model Usage                 Requests today             Daily quota
+│  ────────────────────────────────────────────────────────────────────
+│  qwen3-coder-plus          312 / 2,000 requests   (Resets in 9h 41m)
+│  qwen3-coder-flash          18 / 2,000 requests   (Resets in 9h 41m)
+";
+
+/// Captured Amazon Q Developer CLI `/usage` screen on a Pro subscription:
+/// the conversation's context window, then the monthly allowances.
+pub(crate) const AMAZONQ_CAPTURE: &str = "\
+> /usage
+
+Current context window (12,340 of 200k tokens used)
+██░░░░░░░░░░░░░░░░░░░░░░░░░░░░ 6.17%
+
+Monthly usage (Amazon Q Developer Pro)
+  Agentic requests        212 / 1,000 (21%)
+  Code transformation     1,200 / 4,000 lines (30%)
+  Inline suggestions      Unlimited
+
+Limits reset on Nov 1, 2026 (UTC)
+";
+
+/// Captured aider `/tokens` report: what the next message would send and
+/// what it would cost.
+pub(crate) const AIDER_CAPTURE: &str = "\
+> /tokens
+
+Approximate context window usage for gpt-4o, in tokens:
+
+$ 0.0012      412 system messages
+$ 0.0003      106 chat history                     use /clear to clear
+$ 0.0054    1,812 repository map                   use --map-tokens to resize
+$ 0.0021      700 src/main.py                      /drop to remove
+==================
+$ 0.0090    3,030 tokens total
+          124,970 tokens remaining in context window
+          128,000 tokens max context window size
+";
+
+fn entry(
+    label: &str,
+    percent_used: u32,
+    percent_kind: PercentKind,
+    reset_info: &str,
+    reset_minutes: i64,
+) -> UsageEntry {
+    UsageEntry {
+        label: label.into(),
+        percent_used,
+        percent_remaining: 100 - percent_used,
+        percent_kind,
+        reset_info: reset_info.into(),
+        reset_minutes: Some(reset_minutes),
+        spent: None,
+        requests: None,
+        requests_used: None,
+        requests_limit: None,
+        scope: LimitScope::Personal,
+        window: window_for_label(label),
+        confidence: Confidence::Exact,
+        inconsistent: false,
+        as_of: None,
+    }
+}
+
+/// Fixed, realistic usage for `provider` ("claude", "codex", "gemini",
+/// "copilot", "cursor", "qwen", "amazonq" or "aider").
+/// The same values every time, so output built on it can be snapshot-tested.
+pub fn mock_usage(provider: &str) -> Option<UsageData> {
+    let entries = match provider {
+        "claude" => vec![
+            entry(
+                "Current session",
+                12,
+                PercentKind::Used,
+                "Resets 2pm (America/Chicago)",
+                180,
+            ),
+            entry(
+                "Current week (all models)",
+                34,
+                PercentKind::Used,
+                "Resets Feb 20 at 9am (America/Chicago)",
+                4 * 24 * 60,
+            ),
+            entry(
+                "Current week (Sonnet only)",
+                5,
+                PercentKind::Used,
+                "Resets Feb 20 at 9am (America/Chicago)",
+                4 * 24 * 60,
+            ),
+            UsageEntry {
+                spent: Some("$77.33 / $500.00 spent".into()),
+                ..entry(
+                    "Extra usage",
+                    15,
+                    PercentKind::Used,
+                    "Resets Mar 1 (America/Chicago)",
+                    13 * 24 * 60,
+                )
+            },
+        ],
+        "codex" => vec![
+            entry("5h limit", 23, PercentKind::Left, "resets 16:20", 135),
+            entry(
+                "Weekly limit",
+                41,
+                PercentKind::Left,
+                "resets 09:00 on 20 Feb",
+                3 * 24 * 60 + 600,
+            ),
+        ],
+        "gemini" => vec![
+            UsageEntry {
+                requests: Some("4".into()),
+                requests_used: None,
+                requests_limit: None,
+                window: LimitWindow::Daily,
+                ..entry(
+                    "gemini-2.5-flash",
+                    1,
+                    PercentKind::Left,
+                    "Resets in 4h 49m",
+                    289,
+                )
+            },
+            UsageEntry {
+                window: LimitWindow::Daily,
+                ..entry(
+                    "gemini-2.5-pro",
+                    2,
+                    PercentKind::Left,
+                    "Resets in 2h 35m",
+                    155,
+                )
+            },
+        ],
+        "copilot" => vec![UsageEntry {
+            requests: Some("132 / 300".into()),
+            requests_used: None,
+            requests_limit: None,
+            window: LimitWindow::Monthly,
+            ..entry(
+                "Premium requests",
+                44,
+                PercentKind::Used,
+                "Resets on Mar 1",
+                15 * 24 * 60 + 12 * 60,
+            )
+        }],
+        "cursor" => vec![UsageEntry {
+            spent: Some("$12.40 / $20.00 spent".into()),
+            window: LimitWindow::Monthly,
+            ..entry(
+                "Included usage",
+                62,
+                PercentKind::Used,
+                "Resets on Mar 1",
+                15 * 24 * 60 + 12 * 60,
+            )
+        }],
+        "qwen" => vec![UsageEntry {
+            requests: Some("312 / 2,000".into()),
+            requests_used: None,
+            requests_limit: None,
+            window: LimitWindow::Daily,
+            ..entry(
+                "qwen3-coder-plus",
+                16,
+                PercentKind::Used,
+                "Resets in 9h 41m",
+                581,
+            )
+        }],
+        "amazonq" => vec![UsageEntry {
+            requests: Some("212 / 1,000".into()),
+            requests_used: None,
+            requests_limit: None,
+            window: LimitWindow::Monthly,
+            ..entry(
+                "Agentic requests",
+                21,
+                PercentKind::Used,
+                "Resets on Mar 1",
+                15 * 24 * 60 + 12 * 60,
+            )
+        }],
+        "aider" => vec![UsageEntry {
+            requests: Some("3,030 / 128,000 tokens (~$0.0090 estimated)".into()),
+            requests_used: None,
+            requests_limit: None,
+            reset_minutes: None,
+            window: LimitWindow::Other,
+            ..entry("Context window", 2, PercentKind::Used, "", 0)
+        }],
+        _ => return None,
+    };
+    Some(UsageData {
+        extra_usage_enabled: (provider == "claude").then_some(true),
+        tokens: Vec::new(),
+        provider: provider.into(),
+        entries,
+        check: CheckStats::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_usage_covers_every_provider() {
+        for provider in crate::ALL_PROVIDERS {
+            let data = mock_usage(provider).unwrap();
+            assert_eq!(data.provider, provider);
+            assert!(!data.entries.is_empty());
+            for entry in &data.entries {
+                assert_eq!(entry.percent_used + entry.percent_remaining, 100);
+            }
+        }
+        assert!(mock_usage("bogus").is_none());
+    }
+
+    #[test]
+    fn test_mock_usage_is_deterministic() {
+        let a = serde_json::to_string(&mock_usage("claude").unwrap()).unwrap();
+        let b = serde_json::to_string(&mock_usage("claude").unwrap()).unwrap();
+        assert_eq!(a, b);
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{bail, Context, Result};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::isolation::{launch_dir, IsolatedEnv};
+use crate::plan::ProviderSpec;
+use crate::UsageConfig;
+
+/// Run `spec`'s CLI once without a terminal, with `args`, and return what it
+/// printed (stdout, then stderr) with escape sequences removed.  The working
+/// directory and environment isolation follow the PTY session's rules, so a
+/// fallback run sees the same project (or the same empty temp dir).
+pub(crate) fn run(
+    spec: &ProviderSpec,
+    args: &[&str],
+    config: &UsageConfig,
+    timeout: Duration,
+) -> Result<String> {
+    let Output {
+        status,
+        mut stdout,
+        stderr,
+    } = execute(spec.binary, args, config, timeout)?;
+    stdout.extend(stderr);
+    let text = String::from_utf8_lossy(&strip_ansi_escapes::strip(stdout)).into_owned();
+    if !status.success() && text.trim().is_empty() {
+        bail!("{} exited with {}", spec.binary, status);
+    }
+    Ok(text)
+}
+
+/// Run `binary` with `args` the way `run` does and return its exit status
+/// and raw output, killing it with a `[timeout]` error after `timeout`.
+pub(crate) fn execute(
+    binary: &str,
+    args: &[&str],
+    config: &UsageConfig,
+    timeout: Duration,
+) -> Result<Output> {
+    let isolation = config.isolate_env.then(IsolatedEnv::create).transpose()?;
+    let workdir = match &config.directory {
+        Some(dir) => PathBuf::from(dir),
+        None => launch_dir()?,
+    };
+    let mut command = Command::new(binary);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .env("NO_COLOR", "1")
+        .env("TERM", "dumb");
+    command.current_dir(workdir);
+    if let Some(isolation) = &isolation {
+        command.envs(isolation.vars());
+    }
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to run {} non-interactively", binary))?;
+
+    // Drain both pipes while waiting so a chatty CLI can't block on a full one.
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(
+                "[timeout] {} did not finish within {}s",
+                binary,
+                timeout.as_secs()
+            );
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(binary: &'static str) -> ProviderSpec {
+        ProviderSpec {
+            name: "test",
+            binary,
+            args: &[],
+            safe_args: &[],
+            no_update_args: &[],
+            command: "",
+            fallback_command: None,
+            headless_args: None,
+        }
+    }
+
+    #[test]
+    fn test_run_captures_output_without_escapes() {
+        let text = run(
+            &spec("sh"),
+            &[
+                "-c",
+                "printf '\\033[1m42%% used\\033[0m\\n'; echo oops >&2; pwd",
+            ],
+            &UsageConfig::default(),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "42% used");
+        assert!(lines[1].ends_with("agentusage/launch"));
+        assert_eq!(lines[2], "oops");
+    }
+
+    #[test]
+    fn test_run_times_out() {
+        let err = run(
+            &spec("sleep"),
+            &["5"],
+            &UsageConfig::default(),
+            Duration::from_millis(200),
+        )
+        .unwrap_err();
+        assert!(err.to_string().starts_with("[timeout]"));
+    }
+}
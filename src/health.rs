@@ -0,0 +1,194 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// When a provider's scrapes count as broken rather than unlucky.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthPolicy {
+    /// Failures in a row before a provider is failing.
+    pub failures: u32,
+    /// A provider whose last successful scrape is older than this is
+    /// failing, however few of its recent scrapes failed.
+    pub max_age: Duration,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            failures: 3,
+            max_age: Duration::hours(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// No scrape has finished yet.
+    Pending,
+    /// The last scrape succeeded.
+    Ok,
+    /// The last scrape failed, but not often or long enough to be failing.
+    Degraded,
+    /// Scrapes keep failing, e.g. after the provider's UI changed.
+    Failing,
+}
+
+/// One provider's scrape record.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub status: HealthStatus,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    pub consecutive_failures: u32,
+    /// The last failure's message, while the provider isn't `Ok`.
+    pub last_error: Option<String>,
+}
+
+/// What `/healthz` and `/readyz` answer with.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    /// No provider is failing: `/healthz` answers 200, else 503.
+    pub healthy: bool,
+    /// Every provider has a recent good result to serve: `/readyz`
+    /// answers 200, else 503.
+    pub ready: bool,
+    pub providers: Vec<ProviderHealth>,
+}
+
+#[derive(Debug, Default)]
+struct Record {
+    last_success: Option<DateTime<Utc>>,
+    last_failure: Option<DateTime<Utc>>,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+}
+
+/// Tracks whether each provider's recent scrapes succeed, for a
+/// long-running host to report more than "the server is up".
+#[derive(Debug)]
+pub struct HealthTracker {
+    policy: HealthPolicy,
+    providers: BTreeMap<String, Record>,
+}
+
+impl HealthTracker {
+    /// Track `providers`, all pending until their first scrape finishes.
+    pub fn new<'a>(policy: HealthPolicy, providers: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            policy,
+            providers: providers
+                .into_iter()
+                .map(|p| (p.to_string(), Record::default()))
+                .collect(),
+        }
+    }
+
+    /// A scrape of `provider` succeeded at `at`.
+    pub fn succeeded(&mut self, provider: &str, at: DateTime<Utc>) {
+        let record = self.providers.entry(provider.to_string()).or_default();
+        record.last_success = Some(at);
+        record.consecutive_failures = 0;
+        record.last_error = None;
+    }
+
+    /// A scrape of `provider` failed at `at` with `error`.
+    pub fn failed(&mut self, provider: &str, error: &str, at: DateTime<Utc>) {
+        let record = self.providers.entry(provider.to_string()).or_default();
+        record.last_failure = Some(at);
+        record.consecutive_failures += 1;
+        record.last_error = Some(error.to_string());
+    }
+
+    /// Every provider's health as of `now`.
+    pub fn report(&self, now: DateTime<Utc>) -> HealthReport {
+        let providers: Vec<ProviderHealth> = self
+            .providers
+            .iter()
+            .map(|(provider, record)| ProviderHealth {
+                provider: provider.clone(),
+                status: self.status(record, now),
+                last_success: record.last_success,
+                last_failure: record.last_failure,
+                consecutive_failures: record.consecutive_failures,
+                last_error: record.last_error.clone(),
+            })
+            .collect();
+        HealthReport {
+            healthy: providers.iter().all(|p| p.status != HealthStatus::Failing),
+            ready: providers
+                .iter()
+                .all(|p| matches!(p.status, HealthStatus::Ok | HealthStatus::Degraded)),
+            providers,
+        }
+    }
+
+    fn status(&self, record: &Record, now: DateTime<Utc>) -> HealthStatus {
+        if record.consecutive_failures == 0 {
+            return match record.last_success {
+                Some(_) => HealthStatus::Ok,
+                None => HealthStatus::Pending,
+            };
+        }
+        let recent = record
+            .last_success
+            .is_some_and(|at| now - at <= self.policy.max_age);
+        if recent && record.consecutive_failures < self.policy.failures {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Failing
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_follows_recent_scrapes() {
+        let start: DateTime<Utc> = "2026-03-01T10:00:00Z".parse().unwrap();
+        let minutes = |m| start + Duration::minutes(m);
+        let mut tracker = HealthTracker::new(HealthPolicy::default(), ["claude", "codex"]);
+        let report = tracker.report(start);
+        assert!(report.healthy);
+        assert!(!report.ready);
+        assert_eq!(report.providers[0].status, HealthStatus::Pending);
+
+        tracker.succeeded("claude", minutes(1));
+        tracker.succeeded("codex", minutes(1));
+        assert!(tracker.report(minutes(1)).ready);
+
+        // A UI change breaks Codex scraping.
+        tracker.failed("codex", "No usage data found", minutes(6));
+        let report = tracker.report(minutes(6));
+        assert!(report.healthy && report.ready);
+        assert_eq!(report.providers[1].status, HealthStatus::Degraded);
+        tracker.failed("codex", "No usage data found", minutes(11));
+        tracker.failed("codex", "No usage data found", minutes(16));
+        let report = tracker.report(minutes(16));
+        assert!(!report.healthy && !report.ready);
+        assert_eq!(report.providers[1].status, HealthStatus::Failing);
+        assert_eq!(report.providers[1].consecutive_failures, 3);
+        assert_eq!(
+            report.providers[1].last_error.as_deref(),
+            Some("No usage data found")
+        );
+        assert_eq!(report.providers[0].status, HealthStatus::Ok);
+
+        tracker.succeeded("codex", minutes(21));
+        assert!(tracker.report(minutes(21)).healthy);
+    }
+
+    #[test]
+    fn test_one_failure_after_a_long_gap_is_failing() {
+        let start: DateTime<Utc> = "2026-03-01T10:00:00Z".parse().unwrap();
+        let mut tracker = HealthTracker::new(HealthPolicy::default(), ["claude"]);
+        tracker.succeeded("claude", start);
+        tracker.failed("claude", "timed out", start + Duration::hours(2));
+        let report = tracker.report(start + Duration::hours(2));
+        assert_eq!(report.providers[0].status, HealthStatus::Failing);
+        assert!(!report.healthy);
+    }
+}
@@ -0,0 +1,848 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::parser::scope_for_label;
+use crate::types::{CheckStats, Confidence, LimitWindow, PercentKind, UsageData, UsageEntry};
+
+/// Overrides where the history file lives.
+pub const HISTORY_PATH_VAR: &str = "AGENTUSAGE_HISTORY";
+
+/// Starts a record encrypted under `AGENTUSAGE_HISTORY_PASSPHRASE`:
+/// `enc1:SALT:NONCE:CIPHERTEXT`, each part base64.  The key is
+/// scrypt(passphrase, SALT) and the record is sealed with
+/// ChaCha20-Poly1305.
+pub(crate) const ENCRYPTED_PREFIX: &str = "enc1:";
+
+/// One limit's reading at one point in time.  The history file holds one
+/// record per line, in the order they were taken.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub timestamp: DateTime<Utc>,
+    pub provider: String,
+    pub label: String,
+    pub window: LimitWindow,
+    pub percent_used: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_minutes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spent: Option<String>,
+    /// The `--directory` the check ran in, for attributing usage to a
+    /// project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// The name it was saved under with `agentusage snapshot save`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    /// The machine that sent it to `agentusage serve --ingest`; `None` for
+    /// checks run here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Worked out from another tool's data by `history import` rather than
+    /// read off the provider, so only roughly right: charts show it, but
+    /// stale fallbacks and threshold counts leave it out.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub estimated: bool,
+}
+
+/// A named baseline: the records of the last check saved under `name`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub taken_at: DateTime<Utc>,
+    pub records: Vec<HistoryRecord>,
+}
+
+/// `$AGENTUSAGE_HISTORY`, else `history.jsonl` under
+/// `$XDG_DATA_HOME/agentusage` (default `~/.local/share/agentusage`).
+pub fn default_path() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    if let Some(path) = var(HISTORY_PATH_VAR) {
+        return Some(PathBuf::from(path));
+    }
+    let data_home = var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+    Some(data_home.join("agentusage").join("history.jsonl"))
+}
+
+/// One record per freshly checked entry of `data`, stamped `at` and tagged
+/// with `project`.
+pub fn records_for(
+    data: &UsageData,
+    at: DateTime<Utc>,
+    project: Option<&str>,
+) -> Vec<HistoryRecord> {
+    // Stale stand-ins are already in the history.
+    data.entries
+        .iter()
+        .filter(|entry| entry.as_of.is_none())
+        .map(|entry| HistoryRecord {
+            timestamp: at,
+            provider: data.provider.clone(),
+            label: entry.label.clone(),
+            window: entry.window,
+            percent_used: entry.percent_used,
+            reset_minutes: entry.reset_minutes,
+            spent: entry.spent.clone(),
+            project: project.map(str::to_string),
+            snapshot: None,
+            host: None,
+            estimated: false,
+        })
+        .collect()
+}
+
+/// The history passphrase, if one is configured (see `encryption`).
+#[cfg(feature = "encrypt")]
+fn passphrase() -> Result<Option<String>> {
+    crate::encryption::passphrase()
+}
+
+#[cfg(not(feature = "encrypt"))]
+fn passphrase() -> Result<Option<String>> {
+    if std::env::var_os("AGENTUSAGE_HISTORY_PASSPHRASE").is_some()
+        || std::env::var_os("AGENTUSAGE_HISTORY_PASSPHRASE_COMMAND").is_some()
+    {
+        bail!("History encryption needs agentusage built with the `encrypt` feature");
+    }
+    Ok(None)
+}
+
+/// Append `records` to the history file at `path`, creating it (and its
+/// directory) on first use.  With a history passphrase set, each record is
+/// encrypted, and so are any plaintext records from before it was set.
+pub fn append(path: &Path, records: &[HistoryRecord]) -> Result<()> {
+    append_with(path, records, passphrase()?.as_deref())
+}
+
+fn append_with(path: &Path, records: &[HistoryRecord], passphrase: Option<&str>) -> Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let _lock = lock(path)?;
+    if passphrase.is_some() {
+        if let Some(old) = read(path)?.filter(|text| has_plaintext(text)) {
+            // Encryption was just turned on: seal the whole history rather
+            // than leave the older records readable.
+            let mut all = parse_records(path, old, passphrase)?;
+            all.extend_from_slice(records);
+            return rewrite(path, history_text(path, &all, passphrase)?);
+        }
+    }
+    let text = history_text(path, records, passphrase)?;
+    // One write per batch so concurrent runs don't interleave lines.
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Take the lock on the history at `path` (a `.lock` file beside it),
+/// held until the file is dropped.  Appends and rewrites both hold it, so
+/// no record lands between a rewrite's read and its rename.
+fn lock(path: &Path) -> Result<std::fs::File> {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".lock");
+    let lock_path = path.with_file_name(file_name);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    file.lock()
+        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+    Ok(file)
+}
+
+/// `records` as lines to write to the history at `path`, encrypted when it
+/// is.
+fn history_text(
+    path: &Path,
+    records: &[HistoryRecord],
+    passphrase: Option<&str>,
+) -> Result<String> {
+    let encrypted = first_encrypted_line(path);
+    if passphrase.is_none() && encrypted.is_some() {
+        // Don't leave plaintext records in a history kept encrypted.
+        bail!(
+            "{} has encrypted records; set AGENTUSAGE_HISTORY_PASSPHRASE to add to it",
+            path.display()
+        );
+    }
+    let lines = record_lines(records, passphrase, encrypted.as_deref())
+        .with_context(|| format!("Failed to add to {}", path.display()))?;
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(&line);
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// The first encrypted line in the history at `path`, if any.
+fn first_encrypted_line(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .find(|line| line.starts_with(ENCRYPTED_PREFIX))
+}
+
+/// Whether `text` has a record that isn't encrypted.
+fn has_plaintext(text: &str) -> bool {
+    text.lines()
+        .any(|line| !line.trim().is_empty() && !line.starts_with(ENCRYPTED_PREFIX))
+}
+
+/// `records` as history lines, encrypted under `passphrase` with the salt
+/// of `encrypted`, a line already in the file, so reading it back derives
+/// one key.  Fails if `passphrase` can't open `encrypted`: records sealed
+/// under another passphrase would make the history unreadable.
+#[cfg(feature = "encrypt")]
+fn record_lines(
+    records: &[HistoryRecord],
+    passphrase: Option<&str>,
+    encrypted: Option<&str>,
+) -> Result<Vec<String>> {
+    use crate::encryption::{self, Crypter};
+    let lines = records
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<serde_json::Result<Vec<_>>>()?;
+    let Some(passphrase) = passphrase else {
+        return Ok(lines);
+    };
+    let salt = encrypted
+        .and_then(encryption::salt)
+        .unwrap_or_else(encryption::new_salt);
+    let mut crypter = Crypter::new(passphrase);
+    if let Some(line) = encrypted {
+        crypter.open(line)?;
+    }
+    lines.iter().map(|line| crypter.seal(line, &salt)).collect()
+}
+
+#[cfg(not(feature = "encrypt"))]
+fn record_lines(
+    records: &[HistoryRecord],
+    _passphrase: Option<&str>,
+    _encrypted: Option<&str>,
+) -> Result<Vec<String>> {
+    Ok(records
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<serde_json::Result<Vec<_>>>()?)
+}
+
+/// Records at or after `since` (all of them for `None`).  A missing file
+/// is an empty history; lines that don't parse (a write cut short, say)
+/// are skipped.  Encrypted records need the history passphrase.
+pub fn load(path: &Path, since: Option<DateTime<Utc>>) -> Result<Vec<HistoryRecord>> {
+    load_with(path, since, passphrase()?.as_deref())
+}
+
+fn load_with(
+    path: &Path,
+    since: Option<DateTime<Utc>>,
+    passphrase: Option<&str>,
+) -> Result<Vec<HistoryRecord>> {
+    let Some(text) = read(path)? else {
+        return Ok(Vec::new());
+    };
+    let records = parse_records(path, text, passphrase)?;
+    Ok(records
+        .into_iter()
+        .filter(|record| since.is_none_or(|since| record.timestamp >= since))
+        .collect())
+}
+
+/// The history file's text; `None` when there is no history yet.
+fn read(path: &Path) -> Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn parse_records(
+    path: &Path,
+    text: String,
+    passphrase: Option<&str>,
+) -> Result<Vec<HistoryRecord>> {
+    let text = decrypt_lines(path, text, passphrase)?;
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str::<HistoryRecord>(line).ok())
+        .collect())
+}
+
+/// How long `prune` keeps history at each resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    /// Records this recent are kept as they were taken.
+    pub raw: chrono::Duration,
+    /// Past `raw`, each limit keeps one record per hour for this long,
+    /// then none; `None` keeps the hourly records for good.
+    pub hourly: Option<chrono::Duration>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            raw: chrono::Duration::days(30),
+            hourly: None,
+        }
+    }
+}
+
+/// The history left after `compact`, and what went.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Compacted {
+    pub records: Vec<HistoryRecord>,
+    /// Records folded into another of the same hour.
+    pub downsampled: usize,
+    /// Records older than the retention keeps.
+    pub expired: usize,
+}
+
+/// `records` thinned to `retention` as of `now`.  Past the raw period, the
+/// reading with the highest percent used in each hour stands for its limit
+/// (per project), so peaks, resets, and growth still show in reports.
+/// Snapshot records are kept as they are.
+pub fn compact(records: &[HistoryRecord], retention: &Retention, now: DateTime<Utc>) -> Compacted {
+    let raw_from = now - retention.raw;
+    let keep_from = retention.hourly.map(|hourly| raw_from - hourly);
+    let mut compacted = Compacted::default();
+    type Hour<'a> = (Option<&'a str>, &'a str, &'a str, Option<&'a str>, i64);
+    let mut hours: HashMap<Hour, usize> = HashMap::new();
+    for record in records {
+        if record.timestamp >= raw_from || record.snapshot.is_some() {
+            compacted.records.push(record.clone());
+            continue;
+        }
+        if keep_from.is_some_and(|from| record.timestamp < from) {
+            compacted.expired += 1;
+            continue;
+        }
+        let hour = (
+            record.host.as_deref(),
+            record.provider.as_str(),
+            record.label.as_str(),
+            record.project.as_deref(),
+            record.timestamp.timestamp().div_euclid(3600),
+        );
+        match hours.get(&hour) {
+            Some(&i) => {
+                compacted.downsampled += 1;
+                if record.percent_used >= compacted.records[i].percent_used {
+                    compacted.records[i] = record.clone();
+                }
+            }
+            None => {
+                hours.insert(hour, compacted.records.len());
+                compacted.records.push(record.clone());
+            }
+        }
+    }
+    compacted
+}
+
+/// `compact` the history at `path` in place.  A check that finishes
+/// meanwhile waits to append until the rewrite is done.
+pub fn prune(path: &Path, retention: &Retention, now: DateTime<Utc>) -> Result<Compacted> {
+    prune_with(path, retention, now, passphrase()?.as_deref())
+}
+
+fn prune_with(
+    path: &Path,
+    retention: &Retention,
+    now: DateTime<Utc>,
+    passphrase: Option<&str>,
+) -> Result<Compacted> {
+    if !path.exists() {
+        return Ok(Compacted::default());
+    }
+    let _lock = lock(path)?;
+    let Some(text) = read(path)? else {
+        return Ok(Compacted::default());
+    };
+    let compacted = compact(&parse_records(path, text, passphrase)?, retention, now);
+    if compacted.downsampled + compacted.expired == 0 {
+        return Ok(compacted);
+    }
+    rewrite(path, history_text(path, &compacted.records, passphrase)?)?;
+    Ok(compacted)
+}
+
+/// Replace the history at `path` with `text` by renaming a temp file over
+/// it, so readers never see it half written.  Callers hold its `lock`.
+fn rewrite(path: &Path, text: String) -> Result<()> {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".prune");
+    let temp = path.with_file_name(file_name);
+    std::fs::write(&temp, text)
+        .and_then(|()| std::fs::rename(&temp, path))
+        .with_context(|| format!("Failed to rewrite {}", path.display()))
+}
+
+/// `text` with its encrypted lines decrypted.  A line that won't decrypt
+/// is an error, not skipped: records sealed under another passphrase
+/// would otherwise drop out of reports unnoticed.
+#[cfg(feature = "encrypt")]
+fn decrypt_lines(path: &Path, text: String, passphrase: Option<&str>) -> Result<String> {
+    if !text.contains(ENCRYPTED_PREFIX) {
+        return Ok(text);
+    }
+    let Some(passphrase) = passphrase else {
+        bail!(
+            "{} has encrypted records; set {} or {}",
+            path.display(),
+            crate::encryption::PASSPHRASE_VAR,
+            crate::encryption::PASSPHRASE_COMMAND_VAR
+        );
+    };
+    let mut crypter = crate::encryption::Crypter::new(passphrase);
+    let mut plain = String::with_capacity(text.len());
+    for (i, line) in text.lines().enumerate() {
+        if line.starts_with(ENCRYPTED_PREFIX) {
+            let line = crypter
+                .open(line)
+                .with_context(|| format!("Failed to read {}, line {}", path.display(), i + 1))?;
+            plain.push_str(&line);
+        } else {
+            plain.push_str(line);
+        }
+        plain.push('\n');
+    }
+    Ok(plain)
+}
+
+#[cfg(not(feature = "encrypt"))]
+fn decrypt_lines(path: &Path, text: String, _passphrase: Option<&str>) -> Result<String> {
+    if text.contains(ENCRYPTED_PREFIX) {
+        bail!(
+            "{} has encrypted records, which need agentusage built with the `encrypt` feature",
+            path.display()
+        );
+    }
+    Ok(text)
+}
+
+/// Append the `records` the history at `path` doesn't already have (same
+/// time, machine, provider, and limit), oldest first, e.g. ones imported from
+/// another tool; so importing twice adds nothing.  Returns how many were
+/// added.
+pub fn import(path: &Path, records: &[HistoryRecord]) -> Result<usize> {
+    let existing = load(path, None)?;
+    let key = |r: &HistoryRecord| {
+        (
+            r.timestamp,
+            r.host.clone(),
+            r.provider.clone(),
+            r.label.clone(),
+        )
+    };
+    let mut seen: HashSet<_> = existing.iter().map(key).collect();
+    let mut new: Vec<HistoryRecord> = records
+        .iter()
+        .filter(|r| seen.insert(key(r)))
+        .cloned()
+        .collect();
+    new.sort_by_key(|r| r.timestamp);
+    append(path, &new)?;
+    Ok(new.len())
+}
+
+/// The snapshot saved as `name`; saving a name again replaces it.
+pub fn snapshot(records: &[HistoryRecord], name: &str) -> Option<Snapshot> {
+    let taken_at = records
+        .iter()
+        .filter(|r| r.snapshot.as_deref() == Some(name))
+        .map(|r| r.timestamp)
+        .max()?;
+    Some(Snapshot {
+        name: name.to_string(),
+        taken_at,
+        records: records
+            .iter()
+            .filter(|r| r.snapshot.as_deref() == Some(name) && r.timestamp == taken_at)
+            .cloned()
+            .collect(),
+    })
+}
+
+/// Every saved snapshot, oldest first.
+pub fn snapshots(records: &[HistoryRecord]) -> Vec<Snapshot> {
+    let names: std::collections::BTreeSet<&str> = records
+        .iter()
+        .filter_map(|r| r.snapshot.as_deref())
+        .collect();
+    let mut all: Vec<Snapshot> = names
+        .into_iter()
+        .filter_map(|name| snapshot(records, name))
+        .collect();
+    all.sort_by_key(|s| s.taken_at);
+    all
+}
+
+/// `provider`'s most recent recorded check, as usage data whose entries
+/// are marked stale (`as_of` set).  Reset times are counted down to `now`;
+/// one that has already passed is dropped, since the limit has refilled
+/// since.
+pub fn last_known(
+    records: &[HistoryRecord],
+    provider: &str,
+    now: DateTime<Utc>,
+) -> Option<UsageData> {
+    // Only this machine's checks; another's account may differ, and an
+    // imported estimate isn't a reading to fall back on.
+    let records: Vec<&HistoryRecord> = records
+        .iter()
+        .filter(|r| r.provider == provider && r.host.is_none() && !r.estimated)
+        .collect();
+    let latest = records.iter().map(|r| r.timestamp).max()?;
+    let elapsed = (now - latest).num_minutes().max(0);
+    let entries = records
+        .iter()
+        .filter(|r| r.timestamp == latest)
+        .map(|r| UsageEntry {
+            label: r.label.clone(),
+            percent_used: r.percent_used,
+            percent_remaining: 100u32.saturating_sub(r.percent_used),
+            percent_kind: PercentKind::Used,
+            reset_info: String::new(),
+            reset_minutes: r
+                .reset_minutes
+                .map(|mins| mins - elapsed)
+                .filter(|mins| *mins >= 0),
+            spent: r.spent.clone(),
+            requests: None,
+            requests_used: None,
+            requests_limit: None,
+            scope: scope_for_label(&r.label),
+            window: r.window,
+            confidence: Confidence::Exact,
+            inconsistent: false,
+            as_of: Some(latest),
+        })
+        .collect();
+    Some(UsageData {
+        provider: provider.to_string(),
+        entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats::default(),
+    })
+}
+
+/// Parse a look-back period such as `30m`, `24h`, `7d`, or `2w`.
+pub fn parse_period(s: &str) -> Result<chrono::Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let Ok(n) = number.parse::<i64>() else {
+        bail!("'{}' is not a period like 7d, 24h, or 30m", s);
+    };
+    let period = match unit {
+        "m" => chrono::Duration::try_minutes(n),
+        "h" => chrono::Duration::try_hours(n),
+        "d" => chrono::Duration::try_days(n),
+        "w" => chrono::Duration::try_weeks(n),
+        _ => bail!("unknown unit in '{}' (expected m, h, d, or w)", s),
+    };
+    period.with_context(|| format!("'{}' is too long", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::mock_usage;
+
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "agentusage-history-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("history.jsonl")
+    }
+
+    #[test]
+    fn test_append_and_load_round_trip() {
+        let path = temp_file("round-trip");
+        let data = mock_usage("codex").unwrap();
+        let earlier = "2026-03-01T10:00:00Z".parse().unwrap();
+        let later = "2026-03-02T10:00:00Z".parse().unwrap();
+        append(&path, &records_for(&data, earlier, None)).unwrap();
+        append(&path, &records_for(&data, later, Some("/work/acme"))).unwrap();
+
+        let all = load(&path, None).unwrap();
+        assert_eq!(all.len(), data.entries.len() * 2);
+        assert_eq!(all[0].provider, "codex");
+        assert_eq!(all[0].label, data.entries[0].label);
+        assert_eq!(all[0].window, data.entries[0].window);
+        assert_eq!(all[0].project, None);
+        assert_eq!(all.last().unwrap().project.as_deref(), Some("/work/acme"));
+        assert_eq!(load(&path, Some(later)).unwrap().len(), data.entries.len());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_load_skips_bad_lines_and_missing_file() {
+        let path = temp_file("bad-lines");
+        assert!(load(&path, None).unwrap().is_empty());
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            "{\"timestamp\":\"2026-03-01T10:00:00Z\",\"provider\":\"claude\",\
+             \"label\":\"Current session\",\"window\":\"session\",\"percent_used\":12}\n\
+             {\"timestamp\":\"2026-03-0",
+        )
+        .unwrap();
+        let records = load(&path, None).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].percent_used, 12);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn test_encrypted_history_round_trip() {
+        let path = temp_file("encrypted");
+        let data = mock_usage("codex").unwrap();
+        let at = "2026-03-01T10:00:00Z".parse().unwrap();
+        append_with(&path, &records_for(&data, at, None), None).unwrap();
+        // Plaintext from before encryption was turned on is sealed too.
+        append_with(&path, &records_for(&data, at, None), Some("hunter2")).unwrap();
+        append_with(&path, &records_for(&data, at, None), Some("hunter2")).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let sealed: Vec<&str> = text.lines().collect();
+        assert_eq!(sealed.len(), data.entries.len() * 3);
+        assert!(sealed.iter().all(|l| l.starts_with(ENCRYPTED_PREFIX)));
+        assert!(!text.contains("codex"));
+        // Every append reuses the file's salt.
+        let salt = crate::encryption::salt(sealed[0]);
+        assert!(sealed.iter().all(|l| crate::encryption::salt(l) == salt));
+
+        let records = load_with(&path, None, Some("hunter2")).unwrap();
+        assert_eq!(records.len(), data.entries.len() * 3);
+        assert_eq!(records.last().unwrap().provider, "codex");
+        let err = load_with(&path, None, None).unwrap_err();
+        assert!(err.to_string().contains("has encrypted records"));
+        assert!(append_with(&path, &records_for(&data, at, None), None).is_err());
+        let err = load_with(&path, None, Some("wrong")).unwrap_err();
+        assert!(format!("{:#}", err).contains("wrong passphrase"));
+
+        // A wrong passphrase can't add records nobody could read back.
+        let err = append_with(&path, &records_for(&data, at, None), Some("wrong")).unwrap_err();
+        assert!(format!("{:#}", err).contains("wrong passphrase"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+
+        // One record sealed under another passphrase fails the whole read.
+        let mut crypter = crate::encryption::Crypter::new("other");
+        let stray = crypter
+            .seal(sealed[0], &crate::encryption::new_salt())
+            .unwrap();
+        std::fs::write(&path, format!("{}{}\n", text, stray)).unwrap();
+        let err = load_with(&path, None, Some("hunter2")).unwrap_err();
+        assert!(format!("{:#}", err).contains(&format!("line {}", sealed.len() + 1)));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_compact_downsamples_old_records_to_hourly() {
+        let now: DateTime<Utc> = "2026-04-01T00:00:00Z".parse().unwrap();
+        let record = |at: &str, percent_used: u32| HistoryRecord {
+            timestamp: at.parse().unwrap(),
+            provider: "claude".to_string(),
+            label: "Current session".to_string(),
+            window: LimitWindow::Session,
+            percent_used,
+            reset_minutes: None,
+            spent: None,
+            project: None,
+            snapshot: None,
+            host: None,
+            estimated: false,
+        };
+        let records = vec![
+            record("2026-01-10T09:00:00Z", 5),
+            record("2026-02-10T09:05:00Z", 40),
+            record("2026-02-10T09:20:00Z", 55),
+            record("2026-02-10T09:50:00Z", 3),
+            HistoryRecord {
+                snapshot: Some("before-refactor".to_string()),
+                ..record("2026-02-10T09:55:00Z", 4)
+            },
+            record("2026-02-10T10:10:00Z", 9),
+            record("2026-03-20T09:05:00Z", 20),
+            record("2026-03-20T09:20:00Z", 25),
+        ];
+
+        let compacted = compact(&records, &Retention::default(), now);
+        let kept: Vec<u32> = compacted.records.iter().map(|r| r.percent_used).collect();
+        // The 09:00 hour keeps its peak; the last 30 days stay raw.
+        assert_eq!(kept, [5, 55, 4, 9, 20, 25]);
+        assert_eq!(compacted.records[1].timestamp, records[2].timestamp);
+        assert_eq!((compacted.downsampled, compacted.expired), (2, 0));
+
+        let retention = Retention {
+            hourly: Some(chrono::Duration::days(40)),
+            ..Retention::default()
+        };
+        let compacted = compact(&records, &retention, now);
+        assert_eq!(compacted.records.len(), 5);
+        assert_eq!(compacted.expired, 1);
+    }
+
+    #[test]
+    fn test_prune_rewrites_the_history() {
+        let path = temp_file("prune");
+        let data = mock_usage("codex").unwrap();
+        let old: DateTime<Utc> = "2026-01-01T10:00:00Z".parse().unwrap();
+        let now = "2026-03-01T10:00:00Z".parse().unwrap();
+        for minutes in [0, 10, 20] {
+            let at = old + chrono::Duration::minutes(minutes);
+            append(&path, &records_for(&data, at, None)).unwrap();
+        }
+        append(&path, &records_for(&data, now, None)).unwrap();
+
+        let compacted = prune(&path, &Retention::default(), now).unwrap();
+        assert_eq!(compacted.downsampled, data.entries.len() * 2);
+        assert_eq!(load(&path, None).unwrap(), compacted.records);
+        assert_eq!(compacted.records.len(), data.entries.len() * 2);
+        // Nothing left to do the second time.
+        let again = prune(&path, &Retention::default(), now).unwrap();
+        assert_eq!(again.downsampled + again.expired, 0);
+
+        // A check finishing mid-prune waits for the lock prune holds.
+        let held = lock(&path).unwrap();
+        let appending = std::thread::spawn({
+            let (path, records) = (path.clone(), records_for(&data, now, None));
+            move || append(&path, &records)
+        });
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(load(&path, None).unwrap(), compacted.records);
+        drop(held);
+        appending.join().unwrap().unwrap();
+        assert_eq!(load(&path, None).unwrap().len(), data.entries.len() * 3);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_import_skips_records_already_in_the_history() {
+        let path = temp_file("import");
+        let data = mock_usage("codex").unwrap();
+        let at = "2026-03-01T10:00:00Z".parse().unwrap();
+        let later = "2026-03-01T11:00:00Z".parse().unwrap();
+        append(&path, &records_for(&data, at, None)).unwrap();
+
+        let mut imported = records_for(&data, later, None);
+        imported.extend(records_for(&data, at, None));
+        assert_eq!(import(&path, &imported).unwrap(), data.entries.len());
+        assert_eq!(import(&path, &imported).unwrap(), 0);
+        assert_eq!(load(&path, None).unwrap().len(), data.entries.len() * 2);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_snapshots_keep_the_latest_save_of_a_name() {
+        let data = mock_usage("codex").unwrap();
+        let tagged = |at: &str, name: Option<&str>| -> Vec<HistoryRecord> {
+            records_for(&data, at.parse().unwrap(), None)
+                .into_iter()
+                .map(|r| HistoryRecord {
+                    snapshot: name.map(str::to_string),
+                    ..r
+                })
+                .collect()
+        };
+        let records: Vec<HistoryRecord> = [
+            tagged("2026-03-01T10:00:00Z", Some("before-refactor")),
+            tagged("2026-03-01T11:00:00Z", None),
+            tagged("2026-03-01T12:00:00Z", Some("lunch")),
+            tagged("2026-03-01T13:00:00Z", Some("before-refactor")),
+        ]
+        .concat();
+        let saved = snapshot(&records, "before-refactor").unwrap();
+        assert_eq!(
+            saved.taken_at,
+            "2026-03-01T13:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(saved.records.len(), data.entries.len());
+        assert!(snapshot(&records, "missing").is_none());
+        let names: Vec<String> = snapshots(&records).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["lunch", "before-refactor"]);
+    }
+
+    #[test]
+    fn test_last_known_marks_entries_stale() {
+        let record = |at: &str, label: &str, used, reset| HistoryRecord {
+            timestamp: at.parse().unwrap(),
+            provider: "claude".into(),
+            label: String::from(label),
+            window: LimitWindow::Session,
+            percent_used: used,
+            reset_minutes: reset,
+            spent: None,
+            project: None,
+            snapshot: None,
+            host: None,
+            estimated: false,
+        };
+        let records = [
+            record("2026-03-01T10:00:00Z", "Current session", 10, Some(200)),
+            record("2026-03-01T12:00:00Z", "Current session", 40, Some(90)),
+            record("2026-03-01T12:00:00Z", "Team weekly limit", 20, Some(30)),
+        ];
+        let now = "2026-03-01T13:00:00Z".parse().unwrap();
+        let data = last_known(&records, "claude", now).unwrap();
+        assert_eq!(data.entries.len(), 2);
+        let session = &data.entries[0];
+        assert_eq!(session.percent_used, 40);
+        assert_eq!(session.percent_remaining, 60);
+        assert_eq!(session.reset_minutes, Some(30));
+        assert_eq!(session.as_of, Some("2026-03-01T12:00:00Z".parse().unwrap()));
+        // Reset 30 minutes after the reading, so it has passed by now.
+        assert_eq!(data.entries[1].reset_minutes, None);
+        assert_eq!(data.entries[1].scope, crate::types::LimitScope::Workspace);
+        assert!(last_known(&records, "codex", now).is_none());
+
+        // Stale entries are never written back.
+        assert!(records_for(&data, now, None).is_empty());
+
+        // A later imported estimate is no fallback.
+        let mut imported = record("2026-03-01T12:30:00Z", "Current session", 70, Some(60));
+        imported.estimated = true;
+        let mut with_import = records.to_vec();
+        with_import.push(imported);
+        let data = last_known(&with_import, "claude", now).unwrap();
+        assert_eq!(data.entries[0].percent_used, 40);
+    }
+
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(parse_period("7d").unwrap(), chrono::Duration::days(7));
+        assert_eq!(parse_period("24h").unwrap(), chrono::Duration::hours(24));
+        assert_eq!(parse_period("30m").unwrap(), chrono::Duration::minutes(30));
+        assert_eq!(parse_period("2w").unwrap(), chrono::Duration::weeks(2));
+        assert!(parse_period("7").is_err());
+        assert!(parse_period("d").is_err());
+        assert!(parse_period("7y").is_err());
+    }
+}
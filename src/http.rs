@@ -0,0 +1,62 @@
+use anyhow::{bail, Context, Result};
+use std::time::Duration;
+
+/// GET a provider's usage endpoint with `headers` and return the body.
+/// `provider` names it in errors ("Codex"); `cli` is the command that
+/// refreshes an expired login.
+pub(crate) fn get_usage(
+    url: &str,
+    headers: &[(&str, &str)],
+    timeout: Duration,
+    provider: &str,
+    cli: &str,
+) -> Result<String> {
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    let mut request = agent.get(url).set(
+        "User-Agent",
+        concat!("agentusage/", env!("CARGO_PKG_VERSION")),
+    );
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    match request.call() {
+        Ok(response) => response
+            .into_string()
+            .with_context(|| format!("Failed to read the {} usage response", provider)),
+        Err(ureq::Error::Status(401 | 403, _)) => {
+            bail!(
+                "{} login has expired; run '{}' to refresh it",
+                provider,
+                cli
+            )
+        }
+        Err(ureq::Error::Status(code, _)) => {
+            bail!("{} usage endpoint returned HTTP {}", provider, code)
+        }
+        Err(e) => {
+            Err(e).with_context(|| format!("Failed to reach the {} usage endpoint", provider))
+        }
+    }
+}
+
+/// POST `body` to `url` with `headers`, failing on a non-2xx response.
+pub(crate) fn post(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+    timeout: Duration,
+) -> Result<()> {
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    let mut request = agent.post(url).set(
+        "User-Agent",
+        concat!("agentusage/", env!("CARGO_PKG_VERSION")),
+    );
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    match request.send_string(body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, _)) => bail!("{} returned HTTP {}", url, code),
+        Err(e) => Err(e).with_context(|| format!("Failed to reach {}", url)),
+    }
+}
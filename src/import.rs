@@ -0,0 +1,166 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::history::HistoryRecord;
+use crate::types::LimitWindow;
+
+/// The limit ccusage's 5-hour billing blocks line up with.
+const CLAUDE_SESSION_LABEL: &str = "Current session";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CcusageReport {
+    blocks: Option<Vec<CcusageBlock>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CcusageBlock {
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    /// The block's last activity; absent on gaps.
+    actual_end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    is_gap: bool,
+    total_tokens: Option<u64>,
+    #[serde(default)]
+    token_counts: CcusageTokens,
+    /// Present when ccusage ran with `--token-limit`.
+    token_limit_status: Option<CcusageLimit>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct CcusageTokens {
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_input_tokens: u64,
+    cache_read_input_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CcusageLimit {
+    limit: u64,
+}
+
+impl CcusageBlock {
+    fn tokens(&self) -> u64 {
+        self.total_tokens.unwrap_or(
+            self.token_counts.input_tokens
+                + self.token_counts.output_tokens
+                + self.token_counts.cache_creation_input_tokens
+                + self.token_counts.cache_read_input_tokens,
+        )
+    }
+}
+
+/// History records from `ccusage blocks --json`: each 5-hour billing block
+/// becomes Claude's session limit, empty at the block's start and at its
+/// peak at the last activity.  Percent used is of the `--token-limit`
+/// ccusage ran with, else of the busiest block, as `--token-limit max`
+/// would.  The records are marked `estimated`.  ccusage's daily, monthly,
+/// and session reports have no window to chart and are rejected.
+pub fn ccusage(text: &str) -> Result<Vec<HistoryRecord>> {
+    let report: CcusageReport = serde_json::from_str(text).context("Not a ccusage JSON report")?;
+    let Some(blocks) = report.blocks else {
+        bail!(
+            "Expected `ccusage blocks --json` output; daily, monthly, and \
+             session reports have no usage windows"
+        );
+    };
+    let blocks: Vec<CcusageBlock> = blocks
+        .into_iter()
+        .filter(|block| !block.is_gap && block.tokens() > 0)
+        .collect();
+    let limit = blocks
+        .iter()
+        .find_map(|block| block.token_limit_status.as_ref())
+        .map(|status| status.limit)
+        .or_else(|| blocks.iter().map(CcusageBlock::tokens).max())
+        .filter(|limit| *limit > 0);
+    let Some(limit) = limit else {
+        return Ok(Vec::new());
+    };
+    let record = |timestamp: DateTime<Utc>, percent_used: u32, end: DateTime<Utc>| HistoryRecord {
+        timestamp,
+        provider: "claude".to_string(),
+        label: CLAUDE_SESSION_LABEL.to_string(),
+        window: LimitWindow::Session,
+        percent_used,
+        reset_minutes: Some((end - timestamp).num_minutes().max(0)),
+        spent: None,
+        project: None,
+        snapshot: None,
+        host: None,
+        estimated: true,
+    };
+    let mut records = Vec::new();
+    for block in &blocks {
+        let percent = (block.tokens() as f64 * 100.0 / limit as f64).round() as u32;
+        records.push(record(block.start_time, 0, block.end_time));
+        let last = block.actual_end_time.unwrap_or(block.end_time);
+        records.push(record(last, percent, block.end_time));
+    }
+    records.sort_by_key(|r| r.timestamp);
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ccusage_blocks() {
+        let text = r#"{"blocks": [
+            {"id": "2026-03-01T09:00:00.000Z", "startTime": "2026-03-01T09:00:00.000Z",
+             "endTime": "2026-03-01T14:00:00.000Z", "actualEndTime": "2026-03-01T12:30:00.000Z",
+             "isActive": false, "isGap": false, "entries": 40,
+             "tokenCounts": {"inputTokens": 1000, "outputTokens": 4000,
+                             "cacheCreationInputTokens": 0, "cacheReadInputTokens": 5000},
+             "totalTokens": 10000, "costUSD": 1.5, "models": ["claude-sonnet-4"]},
+            {"id": "gap-2026-03-01T14:00:00.000Z", "startTime": "2026-03-01T14:00:00.000Z",
+             "endTime": "2026-03-01T18:00:00.000Z", "isActive": false, "isGap": true,
+             "entries": 0, "tokenCounts": {}, "costUSD": 0, "models": []},
+            {"id": "2026-03-01T18:00:00.000Z", "startTime": "2026-03-01T18:00:00.000Z",
+             "endTime": "2026-03-01T23:00:00.000Z", "actualEndTime": "2026-03-01T19:00:00.000Z",
+             "isActive": false, "isGap": false, "entries": 9,
+             "tokenCounts": {"inputTokens": 500, "outputTokens": 2000,
+                             "cacheCreationInputTokens": 0, "cacheReadInputTokens": 0},
+             "costUSD": 0.4, "models": ["claude-sonnet-4"]}
+        ]}"#;
+        let records = ccusage(text).unwrap();
+        let used: Vec<u32> = records.iter().map(|r| r.percent_used).collect();
+        // The busiest block is the limit.
+        assert_eq!(used, [0, 100, 0, 25]);
+        assert_eq!(
+            records[1].timestamp.to_rfc3339(),
+            "2026-03-01T12:30:00+00:00"
+        );
+        assert_eq!(records[1].reset_minutes, Some(90));
+        assert_eq!(records[1].label, "Current session");
+        assert_eq!(records[1].window, LimitWindow::Session);
+        assert!(records.iter().all(|r| r.estimated));
+
+        let with_limit = text.replacen(
+            r#""totalTokens": 10000,"#,
+            r#""totalTokens": 10000, "tokenLimitStatus": {"limit": 20000, "projectedUsage": 12000, "percentUsed": 50, "status": "ok"},"#,
+            1,
+        );
+        let used: Vec<u32> = ccusage(&with_limit)
+            .unwrap()
+            .iter()
+            .map(|r| r.percent_used)
+            .collect();
+        assert_eq!(used, [0, 50, 0, 13]);
+    }
+
+    #[test]
+    fn test_ccusage_rejects_other_reports() {
+        let daily = r#"{"daily": [{"date": "2026-03-01", "totalTokens": 10}], "totals": {}}"#;
+        let err = ccusage(daily).unwrap_err();
+        assert!(err.to_string().contains("ccusage blocks --json"));
+        assert!(ccusage("not json").is_err());
+    }
+}
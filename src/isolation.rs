@@ -0,0 +1,223 @@
+use anyhow::{bail, Context, Result};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+/// Variables pointed at a private directory under `--isolate-env`.  Only
+/// cache, state, and scratch space: HOME and config dirs hold credentials,
+/// so they stay shared with the user's real sessions.
+const ISOLATED_VARS: [(&str, &str); 3] = [
+    ("XDG_CACHE_HOME", "cache"),
+    ("XDG_STATE_HOME", "state"),
+    ("TMPDIR", "tmp"),
+];
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+/// Live temp dirs, so the Ctrl+C handler can remove them before exiting
+/// (Drop doesn't run on `process::exit`).
+static LIVE_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Remove every temp dir this process still holds.  Call after the sessions
+/// using them are killed.
+pub(crate) fn remove_registered_dirs() {
+    let dirs = LIVE_DIRS
+        .lock()
+        .map(|mut dirs| std::mem::take(&mut *dirs))
+        .unwrap_or_default();
+    for dir in dirs {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+/// Unguessable names before giving up on finding a free one.
+const CREATE_ATTEMPTS: u32 = 16;
+
+/// A random hex suffix for a temp dir name.
+fn random_suffix() -> String {
+    // RandomState is seeded from the OS's randomness.
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    format!("{:016x}", hasher.finish())
+}
+
+/// A fresh directory under the system temp dir, removed on drop.
+#[derive(Debug)]
+pub(crate) struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Create `agentusage-<kind>-<pid>-<random>`, readable only by this
+    /// user.  Sessions run in these and Gemini reads settings from one, so
+    /// a directory that already exists (someone else may own it) is never
+    /// reused.
+    pub fn create(kind: &str) -> Result<Self> {
+        let mut attempts = 0;
+        let path = loop {
+            let path = std::env::temp_dir().join(format!(
+                "agentusage-{}-{}-{}",
+                kind,
+                std::process::id(),
+                random_suffix()
+            ));
+            match std::fs::DirBuilder::new().mode(0o700).create(&path) {
+                Ok(()) => break path,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    attempts += 1;
+                    if attempts == CREATE_ATTEMPTS {
+                        bail!("Failed to create a temp dir: {}", path.display());
+                    }
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create {}", path.display()))
+                }
+            }
+        };
+        if let Ok(mut dirs) = LIVE_DIRS.lock() {
+            dirs.push(path.clone());
+        }
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Ok(mut dirs) = LIVE_DIRS.lock() {
+            dirs.retain(|dir| dir != &self.path);
+        }
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Private cache/state/tmp directories for one provider session, so a check
+/// doesn't write history files or take lockfiles the user's live CLI is
+/// using.  Removed on drop.
+#[derive(Debug)]
+pub(crate) struct IsolatedEnv {
+    /// Held so the directories are removed with the environment.
+    _root: TempDir,
+    vars: Vec<(&'static str, PathBuf)>,
+}
+
+impl IsolatedEnv {
+    pub fn create() -> Result<Self> {
+        let root = TempDir::create("env")?;
+        let mut vars = Vec::with_capacity(ISOLATED_VARS.len());
+        for (name, sub) in ISOLATED_VARS {
+            let dir = root.path().join(sub);
+            std::fs::create_dir(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            vars.push((name, dir));
+        }
+        Ok(Self { _root: root, vars })
+    }
+
+    /// `(name, value)` overrides for the child environment.
+    pub fn vars(&self) -> Vec<(&'static str, &Path)> {
+        self.vars
+            .iter()
+            .map(|(name, dir)| (*name, dir.as_path()))
+            .collect()
+    }
+}
+
+/// The directory checks launch in without `-C`: `launch/` under the state
+/// home, empty and private to this user.  One stable path, so a provider
+/// that remembers trusted folders asks about it once, not on every run.
+pub(crate) fn launch_dir() -> Result<PathBuf> {
+    let dir = crate::registry::state_home()
+        .context("No state directory to launch in: set HOME or XDG_STATE_HOME")?
+        .join("launch");
+    private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Create `dir` readable only by this user, or check that it already is.
+/// Project files in it (hooks, `.mcp.json`, `GEMINI.md`) would run with
+/// trust granted, so a directory someone else owns or can write to is
+/// refused.
+fn private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    if let Some(parent) = dir.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to create {}", dir.display())),
+    }
+    let meta = std::fs::symlink_metadata(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?;
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    if !meta.is_dir() || meta.uid() != uid || meta.permissions().mode() & 0o077 != 0 {
+        bail!(
+            "Refusing to launch in {}: it must be a directory owned by you with no group or other permissions",
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_dir_refuses_shared_dirs() {
+        use std::os::unix::fs::PermissionsExt;
+        let root = TempDir::create("launch-test").unwrap();
+        let dir = root.path().join("state/launch");
+        private_dir(&dir).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        // Reusing our own private dir is fine.
+        private_dir(&dir).unwrap();
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+        let err = private_dir(&dir).unwrap_err();
+        assert!(err.to_string().contains("Refusing to launch"));
+    }
+
+    #[test]
+    fn test_isolated_env_creates_and_removes_dirs() {
+        let env = IsolatedEnv::create().unwrap();
+        let vars = env.vars();
+        assert_eq!(
+            vars.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+            vec!["XDG_CACHE_HOME", "XDG_STATE_HOME", "TMPDIR"]
+        );
+        assert!(vars.iter().all(|(_, dir)| dir.is_dir()));
+        let root = env._root.path().to_path_buf();
+        drop(env);
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn test_isolated_envs_do_not_share_dirs() {
+        let a = IsolatedEnv::create().unwrap();
+        let b = IsolatedEnv::create().unwrap();
+        assert_ne!(a._root.path(), b._root.path());
+    }
+
+    #[test]
+    fn test_temp_dir_is_empty_and_removed() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::create("cwd").unwrap();
+        let path = dir.path().to_path_buf();
+        assert_eq!(std::fs::read_dir(&path).unwrap().count(), 0);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        std::fs::write(path.join("scratch"), "x").unwrap();
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
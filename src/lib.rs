@@ -1,1124 +1,432 @@
 #![deny(warnings)]
 
+pub mod alert;
+#[cfg(feature = "runner")]
+pub mod api;
+#[cfg(feature = "runner")]
+pub mod bench;
+pub mod cache;
+#[cfg(feature = "runner")]
+mod capture;
+#[cfg(feature = "chart")]
+pub mod chart;
+#[cfg(feature = "http")]
+pub mod claude_api;
+#[cfg(feature = "runner")]
+pub mod claude_logs;
+#[cfg(feature = "http")]
+pub mod codex_api;
+#[cfg(feature = "runner")]
+pub mod codex_logs;
+#[cfg(feature = "runner")]
+pub mod custom;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+#[cfg(feature = "runner")]
 pub mod dialog;
+#[cfg(feature = "runner")]
+pub mod discovery;
+#[cfg(feature = "encrypt")]
+pub mod encryption;
+pub mod environment;
+pub mod fixtures;
+#[cfg(feature = "runner")]
+mod headless;
+pub mod health;
+pub mod history;
+#[cfg(feature = "http")]
+mod http;
+pub mod import;
+#[cfg(feature = "runner")]
+mod isolation;
+pub mod locale;
+#[cfg(feature = "runner")]
+mod lock;
+pub mod metrics;
+#[cfg(feature = "runner")]
+pub mod notify;
+pub mod observer;
 pub mod parser;
+#[cfg(feature = "runner")]
+pub mod plan;
+#[cfg(feature = "runner")]
+pub mod plugin;
+#[cfg(feature = "runner")]
+pub mod pool;
+#[cfg(feature = "runner")]
+pub mod provider;
+#[cfg(feature = "runner")]
 pub mod pty;
+pub mod query;
+#[cfg(feature = "runner")]
+pub mod registry;
+pub mod report;
+#[cfg(feature = "runner")]
+mod runner;
+pub mod selftest;
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(feature = "runner")]
 pub mod session;
+#[cfg(feature = "runner")]
+pub mod singleflight;
+pub mod thresholds;
+#[cfg(feature = "runner")]
+mod throttle;
+#[cfg(feature = "runner")]
+mod trust;
+pub mod tuning;
 pub mod types;
+#[cfg(feature = "runner")]
+mod updates;
 
-use anyhow::{bail, Context, Result};
+use anyhow::Result;
 use std::collections::BTreeMap;
-use std::process::Command;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use dialog::{
-    detect_claude_dialog, detect_codex_dialog, detect_gemini_dialog, dialog_error_message,
-    dismiss_dialog,
+pub use types::{
+    ApprovalPolicy, AttachMode, Attempt, AttemptKind, Capabilities, CheckEvent, CheckFailure,
+    CheckPhase, CheckStats, Confidence, DataSource, DialogKind, LimitScope, LimitWindow,
+    PercentKind, SourceMode, TokenUsage, UsageData, UsageEntry, WaitStats,
 };
-use parser::{parse_claude_output, parse_codex_output, parse_gemini_output};
-use session::{Session, SessionLaunch};
-use types::DialogKind;
 
-pub use types::{ApprovalPolicy, PercentKind, UsageData, UsageEntry};
+#[cfg(feature = "runner")]
+pub use discovery::{list_providers, ProviderInfo};
+pub use environment::Environment;
+pub use observer::CheckObserver;
+#[cfg(feature = "runner")]
+pub use plan::{plan, CheckPlan};
+#[cfg(feature = "runner")]
+pub use provider::{Provider, ProviderRegistry};
+#[cfg(feature = "runner")]
+pub use runner::{
+    capture_usage_screen, check_command_exists, run_aider, run_all, run_amazonq, run_claude,
+    run_codex, run_copilot, run_cursor, run_gemini, run_provider, run_qwen, run_streaming,
+    strip_error_tags,
+};
+
+/// Default seconds to wait for usage data.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 45;
+/// Default seconds Claude and Codex get to show their input prompt.
+pub const DEFAULT_PROMPT_TIMEOUT_SECS: u64 = 30;
+/// Default delay between PTY captures while waiting.
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 500;
+
+/// Per-provider overrides for the timing knobs on `UsageConfig`.
+/// `None` falls back to the config-wide value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProviderTimings {
+    pub timeout: Option<u64>,
+    pub prompt_timeout: Option<u64>,
+    pub poll_interval_ms: Option<u64>,
+}
 
 /// Library-friendly configuration for running usage checks.
+#[derive(Clone)]
 pub struct UsageConfig {
+    /// Seconds to wait for usage data.
     pub timeout: u64,
+    /// Seconds to wait for the CLI prompt.  `None` uses the provider default:
+    /// 30s for Claude and Codex, `timeout` for Gemini (slow auth start-up).
+    pub prompt_timeout: Option<u64>,
+    /// Milliseconds between PTY captures while waiting.
+    pub poll_interval_ms: u64,
+    /// Provider name → timing overrides.
+    pub provider_timings: BTreeMap<String, ProviderTimings>,
+    /// Wall-clock limit for the whole invocation.  Checks still running when
+    /// it passes are abandoned (CLI killed) and fail with a `[timeout]` error.
+    pub deadline: Option<Instant>,
+    /// When the wait for usage data times out after some entries were
+    /// already parsed, return those (marked `check.partial`) instead of
+    /// failing the provider.
+    pub allow_partial: bool,
+    /// Take a per-user lock on each provider before launching it, so
+    /// overlapping runs (cron, a status bar, a manual check) don't each
+    /// launch a copy.  A run that finds the lock held waits up to this long
+    /// and then uses the result the other run cached; if the other run is
+    /// still going, it serves the last cached result (marked stale) or
+    /// fails with a `[timeout]` error.  `None` (the default) doesn't lock.
+    pub lock_wait: Option<Duration>,
+    /// Minimum time between real launches of each provider, across runs.
+    /// A check asked for sooner serves the provider's cached result (marked
+    /// stale if the last launch failed), or fails if nothing is cached.
+    /// Independent of `lock_wait`; `None` (the default) never throttles.
+    pub min_interval: Option<Duration>,
+    /// Dump the last screen when a PTY wait times out.  Other diagnostics
+    /// go to `observers`.
     pub verbose: bool,
     pub approval_policy: ApprovalPolicy,
+    /// Hand the terminal to the user when a dialog can't be dismissed
+    /// automatically, then resume once they press Ctrl-].
+    pub interactive: bool,
+    /// Mirror the provider session to the user's terminal while it runs.
+    /// Meant for single-provider runs; parallel sessions would interleave.
+    pub attach: Option<AttachMode>,
+    /// Return built-in fixture data instead of launching any CLI.
+    pub mock: bool,
+    /// Working directory for the CLIs.  `None` runs each one in a fresh,
+    /// empty temp directory that is removed afterwards.
     pub directory: Option<String>,
+    /// Launch providers with their read-only/sandbox flags (Codex
+    /// `-s read-only -a untrusted`, Claude plan mode, Gemini default
+    /// approvals) so a check can never run tools or write files.
+    pub safe_launch: bool,
+    /// Give each provider session private `XDG_CACHE_HOME`, `XDG_STATE_HOME`
+    /// and `TMPDIR` directories (removed afterwards), so checks don't touch
+    /// history files or lockfiles of CLI sessions the user has open.
+    pub isolate_env: bool,
+    /// Record the launch directory as trusted in each provider's config
+    /// before starting it, so the folder-trust dialog never shows.  Without
+    /// `directory`, launches from one stable empty directory instead of a
+    /// fresh temp dir per run.
+    pub preseed_trust: bool,
+    /// Switch off each provider's startup update check for the launched
+    /// session only (Claude `DISABLE_AUTOUPDATER`, a Gemini system settings
+    /// override, Codex `check_for_update_on_startup`), so update prompts
+    /// rarely need dismissing.  The user's settings files are not touched.
+    pub defer_updates: bool,
+    /// Check providers one at a time instead of in parallel, so only one
+    /// CLI is running at once (small CI runners and containers).  Results
+    /// still stream as each provider finishes.
+    pub sequential: bool,
+    /// Where usage is read from: the TUI, or first the provider's usage
+    /// API (Claude, Codex) or local session logs (Codex), falling back along
+    /// the mode's chain.  Only Claude and Codex have sources besides the
+    /// TUI; the API needs the `http` feature.
+    pub source: SourceMode,
+    /// Provider name → `source` override.
+    pub provider_sources: BTreeMap<String, SourceMode>,
+    /// Daily request cap per Gemini model, for `/stats` rows that show a
+    /// request count without a quota; matching rows get `requests_used` and
+    /// `requests_limit`.  Empty by default, since caps depend on the
+    /// account's tier; `parser::GEMINI_DAILY_REQUEST_CAPS` has the free
+    /// tier's.
+    pub gemini_request_caps: BTreeMap<String, u64>,
+    /// Progress and diagnostics hooks, invoked from the provider's worker
+    /// thread.
+    pub observers: Vec<Arc<dyn CheckObserver>>,
+    /// Providers checks can run by name; `run_all` checks each of them.
+    /// Register a `Provider` here to check another agent CLI.
+    #[cfg(feature = "runner")]
+    pub providers: ProviderRegistry,
+    /// Warm sessions to check on instead of launching, for long-running
+    /// hosts (`serve::UsageServer` keeps one).  Only Claude's TUI check
+    /// uses it so far; `None` (the default) launches a CLI per check.
+    #[cfg(feature = "runner")]
+    pub pool: Option<Arc<pool::SessionPool>>,
 }
 
-/// Results from checking all providers.
-pub struct AllResults {
-    pub results: Vec<UsageData>,
-    /// Provider name → error message (raw, may contain internal tags like `[timeout]`).
-    pub warnings: BTreeMap<String, String>,
-}
-
-pub fn check_command_exists(cmd: &str) -> Result<()> {
-    match Command::new(cmd).arg("--version").output() {
-        Ok(_) => Ok(()),
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            bail!(
-                "[tool-missing] {} CLI not found. Make sure it is installed and on your PATH.",
-                cmd
-            );
-        }
-        Err(_) => {
-            // Binary exists but --version might not be supported; that's fine
-            Ok(())
-        }
-    }
-}
-
-/// Handle dialog detection and policy for a provider.
-/// Returns Ok(true) if a dialog was found and dismissed (caller should retry wait),
-/// Ok(false) if no dialog found, or Err if dialog found and policy is Fail / not dismissible.
-fn handle_dialog_check<F>(
-    session: &mut Session,
-    detect_fn: F,
-    provider: &str,
-    policy: ApprovalPolicy,
-    verbose: bool,
-) -> Result<bool>
-where
-    F: Fn(&str) -> Option<DialogKind>,
-{
-    let content = session.capture_pane()?;
-    if let Some(kind) = detect_fn(&content) {
-        if verbose {
-            eprintln!("[verbose] Dialog detected: {:?}", kind);
-        }
-
-        match policy {
-            ApprovalPolicy::Fail => {
-                bail!("[timeout] {}", dialog_error_message(&kind, provider));
-            }
-            ApprovalPolicy::Accept => {
-                let dismissed = dismiss_dialog(&kind, provider, session)?;
-                if !dismissed {
-                    bail!("[timeout] {}", dialog_error_message(&kind, provider));
-                }
-                if verbose {
-                    eprintln!("[verbose] Dialog dismissed, retrying...");
-                }
-                Ok(true)
-            }
-        }
-    } else {
-        Ok(false)
-    }
-}
-
-/// Return whichever UsageData has more entries.
-fn pick_richer(a: UsageData, b: UsageData) -> UsageData {
-    if a.entries.len() >= b.entries.len() {
-        a
-    } else {
-        b
-    }
-}
-
-fn looks_like_codex_update_prompt(content: &str) -> bool {
-    let lower = content.to_lowercase();
-    lower.contains("update available") && lower.contains("codex")
-}
-
-fn content_tail(content: &str, max_chars: usize) -> String {
-    let mut chars: Vec<char> = content.chars().rev().take(max_chars).collect();
-    chars.reverse();
-    chars.into_iter().collect()
-}
-
-fn normalized_no_whitespace_lower(content: &str) -> String {
-    content
-        .chars()
-        .filter(|c| !c.is_whitespace())
-        .flat_map(|c| c.to_lowercase())
-        .collect()
-}
-
-/// Check whether the Gemini CLI pane content indicates the prompt is
-/// actually ready for input.  Only matches patterns that appear once the
-/// CLI is interactive — startup-only text (identity headers, dialog
-/// screens, banners) is intentionally excluded and handled separately by
-/// the dialog-checking poll loop in `run_gemini`.
-fn gemini_prompt_ready(content: &str) -> bool {
-    // Legacy patterns (case-sensitive originals)
-    if content.contains("GEMINI.md")
-        || content.contains("MCP servers")
-        || content.contains("gemini >")
-    {
-        return true;
-    }
-
-    let lower = content.to_lowercase();
-
-    // Legacy patterns (case-insensitive variants)
-    if lower.contains("gemini.md") || lower.contains("mcp servers") {
-        return true;
-    }
-
-    // Ready indicator
-    if lower.contains("what can i help") {
-        return true;
-    }
-
-    // Bare `>` at line start (strict: entire trimmed line or `> ` prefix)
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if trimmed == ">" || trimmed.starts_with("> ") {
-            return true;
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT_SECS,
+            prompt_timeout: None,
+            poll_interval_ms: DEFAULT_POLL_INTERVAL_MS,
+            provider_timings: BTreeMap::new(),
+            deadline: None,
+            allow_partial: false,
+            lock_wait: None,
+            min_interval: None,
+            verbose: false,
+            approval_policy: ApprovalPolicy::Fail,
+            interactive: false,
+            attach: None,
+            mock: false,
+            directory: None,
+            safe_launch: true,
+            isolate_env: false,
+            preseed_trust: false,
+            defer_updates: false,
+            sequential: false,
+            source: SourceMode::Tui,
+            provider_sources: BTreeMap::new(),
+            gemini_request_caps: BTreeMap::new(),
+            observers: Vec::new(),
+            #[cfg(feature = "runner")]
+            providers: ProviderRegistry::builtin(),
+            #[cfg(feature = "runner")]
+            pool: None,
         }
     }
-
-    false
 }
 
-pub fn run_claude(config: &UsageConfig) -> Result<UsageData> {
-    check_command_exists("claude")?;
-
-    let mut session = Session::new(
-        config.directory.as_deref(),
-        config.verbose,
-        SessionLaunch {
-            binary: "claude",
-            args: &["--allowed-tools", ""],
-        },
-    )?;
-    let poll_interval = Duration::from_millis(500);
-    let prompt_timeout = Duration::from_secs(30);
-    let data_timeout = Duration::from_secs(config.timeout);
-
-    if config.verbose {
-        eprintln!(
-            "[verbose] Created {} session for claude",
-            session.backend_name()
-        );
-    }
-
-    if config.verbose {
-        eprintln!("[verbose] Launched claude, waiting for prompt...");
-    }
-
-    let prompt_result = session.wait_for(
-        |content| {
-            let t = content.trim();
-            t.contains('>') || t.contains('❯') || t.contains("Tips")
-        },
-        prompt_timeout,
-        poll_interval,
-        true,
-        config.verbose,
-    );
-
-    if let Err(e) = prompt_result {
-        // Check for dialogs before giving up
-        if handle_dialog_check(
-            &mut session,
-            detect_claude_dialog,
-            "claude",
-            config.approval_policy,
-            config.verbose,
-        )? {
-            // Dialog dismissed, retry waiting for prompt
-            session
-                .wait_for(
-                    |content| {
-                        let t = content.trim();
-                        t.contains('>') || t.contains('❯') || t.contains("Tips")
-                    },
-                    prompt_timeout,
-                    poll_interval,
-                    true,
-                    config.verbose,
-                )
-                .context(
-                    "[timeout] Timed out waiting for Claude prompt after dismissing dialog.",
-                )?;
-        } else {
-            return Err(e.context(
-                "Timed out waiting for Claude prompt. Is claude authenticated? Try running 'claude' manually."
-            ));
-        }
-    }
-
-    // Wait for TUI to stabilize instead of fixed sleep
-    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
-
-    if config.verbose {
-        let content = session.capture_pane()?;
-        eprintln!("[verbose] Prompt detected. Current pane:\n{}", content);
-    }
-
-    // Claude's newer UI is most stable via `/usage`; `/status` now opens a tabbed screen
-    // where `Config` may be selected first.
-    session.send_keys("Esc")?;
-    std::thread::sleep(Duration::from_millis(120));
-    session.send_keys_literal("/usage")?;
-    std::thread::sleep(Duration::from_millis(250));
-    session.send_keys("Enter")?;
-
-    if config.verbose {
-        eprintln!("[verbose] Sent /usage + Enter, waiting for usage data...");
-    }
-
-    let pct_re = regex::Regex::new(r"\d+(?:\.\d+)?%\s*used")?;
-    let usage_start = std::time::Instant::now();
-    let mut last_enter = usage_start
-        .checked_sub(Duration::from_secs(1))
-        .unwrap_or(usage_start);
-    let mut content = String::new();
-    let mut usage_ready = false;
-
-    while usage_start.elapsed() < data_timeout {
-        content = session.capture_pane()?;
-        let normalized = normalized_no_whitespace_lower(&content);
-
-        if pct_re.is_match(&content) {
-            usage_ready = true;
-            break;
-        }
-
-        // If Claude opened a prompt/menu (update/auth/etc), handle it and keep going.
-        if handle_dialog_check(
-            &mut session,
-            detect_claude_dialog,
-            "claude",
-            config.approval_policy,
-            config.verbose,
-        )? {
-            std::thread::sleep(Duration::from_millis(250));
-            continue;
-        }
-
-        // Command palette hint rows sometimes require one more Enter to execute `/usage`.
-        if normalized.contains("showplanusagelimits")
-            || normalized.contains("showplan")
-            || normalized.contains("/usage")
-        {
-            session.send_keys("Enter")?;
-            last_enter = std::time::Instant::now();
-            std::thread::sleep(Duration::from_millis(180));
-            continue;
-        }
-
-        // Nudge the TUI occasionally while waiting for usage panels to render.
-        if !pct_re.is_match(&content) && last_enter.elapsed() >= Duration::from_millis(850) {
-            session.send_keys("Enter")?;
-            last_enter = std::time::Instant::now();
-        }
-
-        std::thread::sleep(poll_interval);
-    }
-
-    if !usage_ready {
-        if config.verbose {
-            eprintln!(
-                "[verbose] /usage did not render in time; falling back to /status usage tab navigation"
-            );
-        }
-        session.send_keys("Esc")?;
-        std::thread::sleep(Duration::from_millis(120));
-        session.send_keys_literal("/status")?;
-        std::thread::sleep(Duration::from_millis(300));
-        session.send_keys("Enter")?;
-
-        // Wait for the status screen tab bar and then move right toward Usage.
-        session
-            .wait_for(
-                |content| {
-                    let tail = content_tail(content, 4000);
-                    tail.contains("Status") && tail.contains("Config") && tail.contains("Usage")
-                },
-                Duration::from_secs(15),
-                poll_interval,
-                false,
-                config.verbose,
-            )
-            .context("[timeout] Timed out waiting for status screen")?;
-
-        for _ in 0..4 {
-            let screen = session.capture_pane()?;
-            if pct_re.is_match(&screen) {
-                content = screen;
-                usage_ready = true;
-                break;
-            }
-            session.send_keys("Right")?;
-            std::thread::sleep(Duration::from_millis(250));
-        }
-
-        if !usage_ready {
-            content = session
-                .wait_for(
-                    |screen| pct_re.is_match(screen),
-                    data_timeout,
-                    poll_interval,
-                    false,
-                    config.verbose,
-                )
-                .context(
-                    "[timeout] Timed out waiting for usage data. Check your internet connection.",
-                )?;
-        }
-    }
-
-    // Wait for TUI to stabilize instead of fixed sleep
-    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
-
-    let final_content = session.capture_pane()?;
-
-    if config.verbose {
-        eprintln!("[verbose] Raw captured text:\n{}", final_content);
-    }
-
-    let data_final = parse_claude_output(&final_content)?;
-    let data_early = parse_claude_output(&content)?;
-    let data = pick_richer(data_final, data_early);
-
-    if data.entries.is_empty() {
-        bail!("[parse-failure] No usage data found in captured output. Run with --verbose to see raw text.");
-    }
-
-    Ok(data)
-}
-
-pub fn run_codex(config: &UsageConfig) -> Result<UsageData> {
-    check_command_exists("codex")?;
-
-    let mut session = Session::new(
-        config.directory.as_deref(),
-        config.verbose,
-        SessionLaunch {
-            binary: "codex",
-            args: &["-s", "read-only", "-a", "untrusted"],
-        },
-    )?;
-    let poll_interval = Duration::from_millis(500);
-    let prompt_timeout = Duration::from_secs(30);
-    let data_timeout = Duration::from_secs(config.timeout);
-
-    if config.verbose {
-        eprintln!(
-            "[verbose] Created {} session for codex",
-            session.backend_name()
-        );
-    }
-
-    if config.verbose {
-        eprintln!("[verbose] Launched codex, waiting for prompt...");
-    }
-
-    // Codex prompt shows "› ..." and "? for shortcuts" at the bottom.
-    // Must NOT match ">_" in the Codex banner header which appears early.
-    let prompt_result = session.wait_for(
-        |content| content.contains("? for shortcuts"),
-        prompt_timeout,
-        poll_interval,
-        false,
-        config.verbose,
-    );
-
-    if let Err(e) = prompt_result {
-        // Check for dialogs before giving up
-        if handle_dialog_check(
-            &mut session,
-            detect_codex_dialog,
-            "codex",
-            config.approval_policy,
-            config.verbose,
-        )? {
-            // Dialog dismissed, retry waiting for prompt
-            session
-                .wait_for(
-                    |content| content.contains("? for shortcuts"),
-                    prompt_timeout,
-                    poll_interval,
-                    false,
-                    config.verbose,
-                )
-                .context("[timeout] Timed out waiting for Codex prompt after dismissing dialog.")?;
-        } else {
-            return Err(e.context(
-                "Timed out waiting for Codex prompt. Is codex authenticated? Try running 'codex' manually."
-            ));
-        }
-    }
-
-    // Wait for TUI to stabilize instead of fixed sleep
-    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
-
-    if config.verbose {
-        let content = session.capture_pane()?;
-        eprintln!("[verbose] Prompt detected. Current pane:\n{}", content);
-    }
-
-    // Codex /status prints inline — no autocomplete, no tabs
-    session.send_keys_literal("/status")?;
-    std::thread::sleep(Duration::from_millis(500));
-    session.send_keys("Enter")?;
-
-    if config.verbose {
-        eprintln!("[verbose] Sent /status + Enter, waiting for usage data...");
-    }
-
-    // Wait for limit data to appear
-    let limit_re = regex::Regex::new(r"\d+%\s*(left|used)")?;
-    let mut content = session
-        .wait_for(
-            |content| limit_re.is_match(content) || looks_like_codex_update_prompt(content),
-            data_timeout,
-            poll_interval,
-            false,
-            config.verbose,
+impl UsageConfig {
+    fn timings(&self, provider: &str) -> ProviderTimings {
+        self.provider_timings
+            .get(provider)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The sources `provider` may be read from.
+    pub fn source_for(&self, provider: &str) -> SourceMode {
+        self.provider_sources
+            .get(provider)
+            .copied()
+            .unwrap_or(self.source)
+    }
+
+    /// How long `provider` may take to produce usage data.
+    pub fn data_timeout(&self, provider: &str) -> Duration {
+        Duration::from_secs(self.timings(provider).timeout.unwrap_or(self.timeout))
+    }
+
+    /// How long `provider` may take to show its input prompt.
+    pub fn prompt_timeout(&self, provider: &str) -> Duration {
+        let secs = self
+            .timings(provider)
+            .prompt_timeout
+            .or(self.prompt_timeout)
+            .unwrap_or_else(|| match provider {
+                // Slow starters: auth checks, extension loading, repo maps.
+                "gemini" | "qwen" | "aider" => self.data_timeout(provider).as_secs(),
+                _ => DEFAULT_PROMPT_TIMEOUT_SECS,
+            });
+        Duration::from_secs(secs)
+    }
+
+    /// Delay between PTY captures for `provider`.
+    pub fn poll_interval(&self, provider: &str) -> Duration {
+        Duration::from_millis(
+            self.timings(provider)
+                .poll_interval_ms
+                .unwrap_or(self.poll_interval_ms),
         )
-        .context("[timeout] Timed out waiting for Codex usage data.")?;
-
-    if looks_like_codex_update_prompt(&content) && !limit_re.is_match(&content) {
-        if config.verbose {
-            eprintln!(
-                "[verbose] Codex update prompt detected, selecting Skip and retrying /status"
-            );
-        }
-        session.send_keys("Down")?;
-        std::thread::sleep(Duration::from_millis(120));
-        session.send_keys("Enter")?;
-        std::thread::sleep(Duration::from_millis(150));
-        session.send_keys("Enter")?;
-        std::thread::sleep(Duration::from_millis(200));
-        session.send_keys_literal("/status")?;
-        std::thread::sleep(Duration::from_millis(200));
-        session.send_keys("Enter")?;
-
-        content = session
-            .wait_for(
-                |content| limit_re.is_match(content),
-                data_timeout,
-                poll_interval,
-                false,
-                config.verbose,
-            )
-            .context(
-                "[timeout] Timed out waiting for Codex usage data after dismissing update prompt.",
-            )?;
-    }
-
-    // Wait for all data to render
-    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
-
-    let final_content = session.capture_pane()?;
-
-    if config.verbose {
-        eprintln!("[verbose] Raw captured text:\n{}", final_content);
     }
-
-    let data_final = parse_codex_output(&final_content)?;
-    let data_early = parse_codex_output(&content)?;
-    let data = pick_richer(data_final, data_early);
-
-    if data.entries.is_empty() {
-        bail!("[parse-failure] No usage data found in captured output. Run with --verbose to see raw text.");
-    }
-
-    Ok(data)
 }
 
-pub fn run_gemini(config: &UsageConfig) -> Result<UsageData> {
-    check_command_exists("gemini")?;
-
-    let mut session = Session::new(
-        config.directory.as_deref(),
-        config.verbose,
-        SessionLaunch {
-            binary: "gemini",
-            args: &[],
-        },
-    )?;
-    let poll_interval = Duration::from_millis(500);
-    // Faster polling during the first few seconds of startup.  Ink-based
-    // TUIs (Gemini) may send terminal capability queries (Device Attributes,
-    // cursor position, etc.) early and block until they receive a response.
-    // Polling at 100ms ensures we answer those queries promptly.
-    let fast_poll_interval = Duration::from_millis(100);
-    let fast_poll_duration = Duration::from_secs(5);
-    // Gemini v0.28+ has a long auth validation phase (spinners, loading
-    // extensions, etc.) that can easily exceed 30 seconds.  We use the
-    // user-configurable data timeout as the hard ceiling and separately
-    // track "idle time" (no output changes) — if nothing happens for 45s
-    // the CLI is likely stuck, even if the wall-clock timeout hasn't hit.
-    let idle_timeout = Duration::from_secs(45);
-    let max_prompt_timeout = Duration::from_secs(config.timeout);
-    let data_timeout = Duration::from_secs(config.timeout);
-
-    if config.verbose {
-        eprintln!(
-            "[verbose] Created {} session for gemini",
-            session.backend_name()
-        );
-    }
-
-    // Pump the PTY briefly to answer any immediate terminal queries
-    // (DA1, cursor position, DSR) the Ink TUI sends on startup.
-    for _ in 0..10 {
-        let _ = session.capture_pane();
-        std::thread::sleep(Duration::from_millis(50));
-    }
-
-    if config.verbose {
-        eprintln!("[verbose] Launched gemini, waiting for prompt...");
-    }
-
-    // Poll for prompt readiness, handling dialogs as they appear.
-    // Track content changes to distinguish "still starting up" from "stuck".
-    let prompt_start = std::time::Instant::now();
-    let mut last_activity = std::time::Instant::now();
-    let mut prev_content = String::new();
-
-    loop {
-        let wall_elapsed = prompt_start.elapsed();
-        let idle_elapsed = last_activity.elapsed();
-
-        if wall_elapsed >= max_prompt_timeout || idle_elapsed >= idle_timeout {
-            let pane = session.capture_pane().unwrap_or_default();
-            let tail = content_tail(&pane, 500);
-            bail!(
-                "[timeout] Timed out waiting for Gemini prompt. Is gemini authenticated? \
-                 Try running 'gemini' manually.\nLast captured output:\n{}",
-                tail
-            );
-        }
-
-        let content = session.capture_pane()?;
-
-        // Track activity: reset idle timer when content changes
-        if content != prev_content {
-            if config.verbose && !prev_content.is_empty() {
-                eprintln!("[verbose] Gemini startup activity detected, resetting idle timer");
-            }
-            last_activity = std::time::Instant::now();
-            prev_content = content.clone();
-        }
-
-        // Check if the actual prompt is visible
-        if gemini_prompt_ready(&content) {
-            break;
-        }
-
-        // Check for dialogs during startup
-        if let Some(kind) = detect_gemini_dialog(&content) {
-            if config.verbose {
-                eprintln!("[verbose] Dialog detected during prompt wait: {:?}", kind);
-            }
-            match config.approval_policy {
-                ApprovalPolicy::Fail => {
-                    bail!("[timeout] {}", dialog_error_message(&kind, "gemini"));
-                }
-                ApprovalPolicy::Accept => {
-                    let dismissed = dismiss_dialog(&kind, "gemini", &mut session)?;
-                    if !dismissed {
-                        bail!("[timeout] {}", dialog_error_message(&kind, "gemini"));
-                    }
-                    if config.verbose {
-                        eprintln!("[verbose] Dialog dismissed, continuing...");
-                    }
-                    last_activity = std::time::Instant::now();
-                    prev_content.clear();
-                    continue;
-                }
-            }
-        }
-
-        // Use faster polling during the initial startup phase to respond
-        // to terminal capability queries quickly.
-        let effective_poll = if prompt_start.elapsed() < fast_poll_duration {
-            fast_poll_interval
-        } else {
-            poll_interval
-        };
-        std::thread::sleep(effective_poll);
-    }
-
-    // Gemini v0.28+ shows a "Waiting for auth..." spinner overlay while
-    // re-validating credentials.  The TUI renders the `> ` prompt even
-    // while the overlay is active, so prompt detection fires early.
-    // The spinner animates continuously (changing the captured output), but
-    // once auth completes the TUI becomes static.  Use content stability to
-    // detect auth completion before sending any commands.
-    {
-        let content = session.capture_pane()?;
-        if content.to_lowercase().contains("waiting for auth") {
-            if config.verbose {
-                eprintln!("[verbose] Auth spinner detected, waiting for completion...");
-            }
-            session
-                .wait_for_stable(max_prompt_timeout, poll_interval, config.verbose)
-                .context(
-                    "[timeout] Gemini auth did not complete in time. \
-                     Try running 'gemini' manually to check authentication.",
-                )?;
-            if config.verbose {
-                eprintln!("[verbose] Auth completed (content stabilized)");
-            }
-        } else {
-            // No auth spinner — wait for the TUI to fully settle.
-            let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
-        }
-    }
-
-    if config.verbose {
-        let content = session.capture_pane()?;
-        eprintln!("[verbose] Prompt detected. Current pane:\n{}", content);
-    }
-
-    // Type /stats session — Gemini uses this command, not /status.
-    session.send_keys_literal("/stats session")?;
-    std::thread::sleep(Duration::from_millis(500));
-    session.send_keys("Enter")?;
-
-    if config.verbose {
-        eprintln!("[verbose] Sent /stats session + Enter, waiting for usage data...");
-    }
-
-    // Wait for usage data to appear, checking for dialogs.
-    let pct_re = regex::Regex::new(r"(?i)\d+(?:\.\d+)?%\s*\(?resets?\b")?;
-    let data_start = std::time::Instant::now();
-    let mut content = String::new();
-    let mut data_ready = false;
-
-    while data_start.elapsed() < data_timeout {
-        content = session.capture_pane()?;
-        if pct_re.is_match(&content) {
-            data_ready = true;
-            break;
-        }
-
-        // Check for dialogs that may have appeared during data wait
-        if handle_dialog_check(
-            &mut session,
-            detect_gemini_dialog,
-            "gemini",
-            config.approval_policy,
-            config.verbose,
-        )? {
-            // Dialog dismissed, re-send the command
-            session.send_keys_literal("/stats session")?;
-            std::thread::sleep(Duration::from_millis(500));
-            session.send_keys("Enter")?;
-            std::thread::sleep(Duration::from_millis(250));
-            continue;
-        }
-
-        std::thread::sleep(poll_interval);
-    }
-
-    if !data_ready {
-        let tail = content_tail(&content, 500);
-        bail!(
-            "[timeout] Timed out waiting for Gemini usage data.\nLast captured output:\n{}",
-            tail
-        );
-    }
-
-    // Wait for all data to render
-    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
-
-    let final_content = session.capture_pane()?;
-
-    if config.verbose {
-        eprintln!("[verbose] Raw captured text:\n{}", final_content);
-    }
-
-    let data_final = parse_gemini_output(&final_content)?;
-    let data_early = parse_gemini_output(&content)?;
-    let data = pick_richer(data_final, data_early);
-
-    if data.entries.is_empty() {
-        bail!("[parse-failure] No usage data found in captured output. Run with --verbose to see raw text.");
-    }
+/// Results from checking all providers.
+pub struct AllResults {
+    pub results: Vec<UsageData>,
+    /// Provider name → error message (raw, may contain internal tags like `[timeout]`).
+    pub warnings: BTreeMap<String, String>,
+    /// Provider name → the attempts a failed check made, in order.
+    pub attempts: BTreeMap<String, Vec<Attempt>>,
+}
 
-    Ok(data)
+/// The built-in providers, in the order `run_all` checks them.
+pub const ALL_PROVIDERS: [&str; 8] = [
+    "claude", "codex", "gemini", "copilot", "cursor", "qwen", "amazonq", "aider",
+];
+
+/// One provider's outcome from `run_streaming`.
+pub struct ProviderResult {
+    pub provider: String,
+    pub result: Result<UsageData>,
+    /// Time from the start of the run until this provider finished.
+    pub elapsed: Duration,
 }
 
-pub fn run_all(config: &UsageConfig) -> AllResults {
+/// Gather streamed results into an `AllResults` ordered like `providers`.
+pub fn collect_results(
+    providers: &[&str],
+    stream: impl Iterator<Item = ProviderResult>,
+) -> AllResults {
     let mut results = Vec::new();
     let mut warnings = BTreeMap::new();
-
-    std::thread::scope(|s| {
-        let claude = s.spawn(|| run_claude(config));
-        let codex = s.spawn(|| run_codex(config));
-        let gemini = s.spawn(|| run_gemini(config));
-
-        for (name, handle) in [("claude", claude), ("codex", codex), ("gemini", gemini)] {
-            match handle.join() {
-                Ok(Ok(data)) => results.push(data),
-                Ok(Err(e)) => {
-                    warnings.insert(name.into(), format!("{:#}", e));
-                }
-                Err(_) => {
-                    warnings.insert(name.into(), "Provider thread panicked".into());
+    let mut attempts = BTreeMap::new();
+    for item in stream {
+        match item.result {
+            Ok(data) => results.push(data),
+            Err(e) => {
+                if let Some(failure) = e.downcast_ref::<CheckFailure>() {
+                    attempts.insert(item.provider.clone(), failure.attempts.clone());
                 }
+                warnings.insert(item.provider, format!("{:#}", e));
             }
         }
-    });
-
-    AllResults { results, warnings }
+    }
+    let rank = |name: &str| providers.iter().position(|p| *p == name);
+    results.sort_by_key(|data| rank(&data.provider));
+    AllResults {
+        results,
+        warnings,
+        attempts,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    // ── pick_richer ─────────────────────────────────────────────────
+    // ── CheckStats ──────────────────────────────────────────────────
 
     #[test]
-    fn test_pick_richer_first_has_more() {
-        let a = UsageData {
-            provider: "claude".into(),
-            entries: vec![
-                UsageEntry {
-                    label: "session".into(),
-                    percent_used: 5,
-                    percent_kind: PercentKind::Used,
-                    reset_info: "Resets 2pm".into(),
-                    percent_remaining: 95,
-                    reset_minutes: None,
-                    spent: None,
-                    requests: None,
-                },
-                UsageEntry {
-                    label: "week".into(),
-                    percent_used: 10,
-                    percent_kind: PercentKind::Used,
-                    reset_info: "Resets Feb 20".into(),
-                    percent_remaining: 90,
-                    reset_minutes: None,
-                    spent: None,
-                    requests: None,
-                },
-            ],
-        };
-        let b = UsageData {
-            provider: "claude".into(),
-            entries: vec![UsageEntry {
-                label: "session".into(),
-                percent_used: 5,
-                percent_kind: PercentKind::Used,
-                reset_info: "Resets 2pm".into(),
-                percent_remaining: 95,
-                reset_minutes: None,
-                spent: None,
-                requests: None,
-            }],
-        };
-        let result = pick_richer(a, b);
-        assert_eq!(result.entries.len(), 2);
+    fn test_check_stats_tallies_events() {
+        let mut stats = CheckStats::default();
+        for event in [
+            CheckEvent::Phase(CheckPhase::Launching),
+            CheckEvent::DialogDetected(DialogKind::TrustFolder),
+            CheckEvent::DialogDismissed(DialogKind::TrustFolder),
+            CheckEvent::Fallback("usage_fallback_status"),
+            CheckEvent::Retry("recapture_wider"),
+        ] {
+            stats.record(&event);
+        }
+        assert_eq!(
+            stats,
+            CheckStats {
+                attempts: 3,
+                fallbacks: 1,
+                dialogs: 1,
+                partial: false,
+                log: Vec::new(),
+                source: DataSource::Tui,
+            }
+        );
     }
 
-    #[test]
-    fn test_pick_richer_second_has_more() {
-        let a = UsageData {
-            provider: "claude".into(),
-            entries: vec![],
-        };
-        let b = UsageData {
-            provider: "claude".into(),
-            entries: vec![UsageEntry {
-                label: "session".into(),
-                percent_used: 5,
-                percent_kind: PercentKind::Used,
-                reset_info: "Resets 2pm".into(),
-                percent_remaining: 95,
-                reset_minutes: None,
-                spent: None,
-                requests: None,
-            }],
-        };
-        let result = pick_richer(a, b);
-        assert_eq!(result.entries.len(), 1);
-    }
+    // ── UsageConfig timings ─────────────────────────────────────────
 
     #[test]
-    fn test_pick_richer_equal_prefers_first() {
-        let a = UsageData {
-            provider: "claude".into(),
-            entries: vec![UsageEntry {
-                label: "from_a".into(),
-                percent_used: 5,
-                percent_kind: PercentKind::Used,
-                reset_info: String::new(),
-                percent_remaining: 95,
-                reset_minutes: None,
-                spent: None,
-                requests: None,
-            }],
-        };
-        let b = UsageData {
-            provider: "claude".into(),
-            entries: vec![UsageEntry {
-                label: "from_b".into(),
-                percent_used: 10,
-                percent_kind: PercentKind::Used,
-                reset_info: String::new(),
-                percent_remaining: 90,
-                reset_minutes: None,
-                spent: None,
-                requests: None,
-            }],
-        };
-        let result = pick_richer(a, b);
-        assert_eq!(result.entries[0].label, "from_a");
+    fn test_config_default_timings() {
+        let config = UsageConfig::default();
+        assert_eq!(config.data_timeout("claude"), Duration::from_secs(45));
+        assert_eq!(config.prompt_timeout("claude"), Duration::from_secs(30));
+        assert_eq!(config.prompt_timeout("codex"), Duration::from_secs(30));
+        assert_eq!(config.poll_interval("codex"), Duration::from_millis(500));
     }
 
     #[test]
-    fn test_pick_richer_both_empty() {
-        let a = UsageData {
-            provider: "claude".into(),
-            entries: vec![],
+    fn test_config_gemini_prompt_timeout_follows_data_timeout() {
+        let config = UsageConfig {
+            timeout: 90,
+            ..UsageConfig::default()
         };
-        let b = UsageData {
-            provider: "claude".into(),
-            entries: vec![],
-        };
-        let result = pick_richer(a, b);
-        assert!(result.entries.is_empty());
-    }
-
-    // ── check_command_exists ────────────────────────────────────────
-
-    #[test]
-    fn test_check_command_exists_valid() {
-        // "ls" exists on all unix systems
-        assert!(check_command_exists("ls").is_ok());
-    }
-
-    #[test]
-    fn test_check_command_exists_missing() {
-        let result = check_command_exists("nonexistent_tool_xyz_12345");
-        assert!(result.is_err());
-        let err = format!("{:#}", result.unwrap_err());
-        assert!(err.contains("[tool-missing]"));
-    }
-
-    // ── gemini_prompt_ready: legacy path ────────────────────────────
-
-    #[test]
-    fn test_gemini_prompt_ready_legacy_gemini_md() {
-        assert!(gemini_prompt_ready("Loaded GEMINI.md"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_legacy_mcp_servers() {
-        assert!(gemini_prompt_ready("Found 3 MCP servers"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_legacy_gemini_prompt() {
-        assert!(gemini_prompt_ready("gemini > type here"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_not_banner_only() {
-        // Banner text alone doesn't mean the prompt is ready
-        assert!(!gemini_prompt_ready("Welcome to Gemini CLI v0.28.0"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_not_trust_dialog() {
-        // Dialog screens are handled separately, not by prompt readiness
-        assert!(!gemini_prompt_ready("Do you trust this folder"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_legacy_full_startup() {
-        assert!(gemini_prompt_ready(
-            "Loaded GEMINI.md\nFound 3 MCP servers\ngemini >"
-        ));
-    }
-
-    // ── gemini_prompt_ready: new path ───────────────────────────────
-
-    #[test]
-    fn test_gemini_prompt_ready_bare_gt_entire_line() {
-        assert!(gemini_prompt_ready("some header\n>\nmore text"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_bare_gt_with_space() {
-        assert!(gemini_prompt_ready("header\n> \nfooter"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_gt_with_trailing_content() {
-        assert!(gemini_prompt_ready("header\n> type your message"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_no_false_positive_gt_in_text() {
-        assert!(!gemini_prompt_ready("value > 5"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_no_false_positive_arrow() {
-        assert!(!gemini_prompt_ready("use -> arrow"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_no_false_positive_comparison() {
-        assert!(!gemini_prompt_ready("5 > 3"));
-    }
-
-    // ── gemini_prompt_ready: startup text must NOT match ───────────
-    // These appear before the CLI is ready; handled by dialog detection.
-
-    #[test]
-    fn test_gemini_prompt_ready_not_signed_in() {
-        assert!(!gemini_prompt_ready("Signed in as user@gmail.com"));
+        assert_eq!(config.prompt_timeout("gemini"), Duration::from_secs(90));
     }
 
     #[test]
-    fn test_gemini_prompt_ready_not_logged_in() {
-        assert!(!gemini_prompt_ready("Logged in as user@gmail.com"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_not_logged_in_with() {
-        assert!(!gemini_prompt_ready(
-            "Logged in with Google: user@gmail.com"
-        ));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_not_model_indicator() {
-        assert!(!gemini_prompt_ready("Model: gemini-2.5-pro"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_not_select_theme() {
-        assert!(!gemini_prompt_ready("Select a theme"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_not_update_available() {
-        assert!(!gemini_prompt_ready("Update available: v0.29.0"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_not_terms() {
-        assert!(!gemini_prompt_ready("Please accept the terms of service"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_what_can_i_help() {
-        assert!(gemini_prompt_ready("What can I help you with?"));
-    }
-
-    // ── gemini_prompt_ready: case insensitivity ─────────────────────
-
-    #[test]
-    fn test_gemini_prompt_ready_lowercase_gemini_md() {
-        assert!(gemini_prompt_ready("loaded gemini.md from disk"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_uppercase_mcp_servers() {
-        assert!(gemini_prompt_ready("Found 3 MCP SERVERS configured"));
-    }
-
-    // ── gemini_prompt_ready: negative tests ─────────────────────────
-
-    #[test]
-    fn test_gemini_prompt_ready_empty() {
-        assert!(!gemini_prompt_ready(""));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_loading() {
-        assert!(!gemini_prompt_ready("Loading..."));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_processing() {
-        assert!(!gemini_prompt_ready("Processing request..."));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_random_text() {
-        assert!(!gemini_prompt_ready(
-            "The quick brown fox jumps over the lazy dog"
-        ));
-    }
-
-    // ── gemini_prompt_ready: additional edge cases ──────────────────
-
-    #[test]
-    fn test_gemini_prompt_ready_not_google_account() {
-        assert!(!gemini_prompt_ready("Using Google Account user@gmail.com"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_not_color_theme() {
-        assert!(!gemini_prompt_ready("Pick a color theme for the CLI"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_no_false_positive_gt_no_space() {
-        // ">foo" without space after > should NOT match
-        assert!(!gemini_prompt_ready(">foo"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_bare_gt_only_content() {
-        assert!(gemini_prompt_ready(">"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_gt_in_multiline_no_match() {
-        // > embedded in text across multiple lines, never at line start
-        assert!(!gemini_prompt_ready("line1\nvalue > 5\nline3"));
-    }
-
-    #[test]
-    fn test_gemini_prompt_ready_what_can_i_help_uppercase() {
-        assert!(gemini_prompt_ready("WHAT CAN I HELP you with today?"));
-    }
-
-    // NOTE: The "Waiting for auth..." spinner is NOT handled in
-    // gemini_prompt_ready itself — the startup loop in run_gemini requires
-    // content stability (3 consecutive stable polls) before accepting
-    // prompt readiness, which naturally filters out the active spinner.
-
-    // ── gemini_prompt_ready: data regex ─────────────────────────────
-
-    #[test]
-    fn test_gemini_data_regex_case_insensitive() {
-        let re = regex::Regex::new(r"(?i)\d+(?:\.\d+)?%\s*\(?resets?\b").unwrap();
-        // Old format with parentheses
-        assert!(re.is_match("45.2% (Resets in 3 hours)"));
-        assert!(re.is_match("45.2% (resets in 3 hours)"));
-        assert!(re.is_match("45.2% (RESETS IN 3 HOURS)"));
-        assert!(re.is_match("100% (Reset tomorrow)"));
-        assert!(re.is_match("100% (reset tomorrow)"));
-        // New format without parentheses
-        assert!(re.is_match("99.0% resets in 23h 19m"));
-        assert!(re.is_match("97.1% resets in 1h 13m"));
-        assert!(re.is_match("99.0% Resets in 23h 19m"));
-    }
-
-    #[test]
-    fn test_gemini_data_regex_no_false_positive() {
-        let re = regex::Regex::new(r"(?i)\d+(?:\.\d+)?%\s*\(?resets?\b").unwrap();
-        assert!(!re.is_match("45% (Resetting)"));
-        assert!(!re.is_match("45% used"));
-        assert!(!re.is_match("no percentage here"));
-    }
-
-    // ── content_tail ────────────────────────────────────────────────
-
-    #[test]
-    fn test_content_tail_shorter_than_max() {
-        assert_eq!(content_tail("hello", 500), "hello");
-    }
-
-    #[test]
-    fn test_content_tail_exact_max() {
-        assert_eq!(content_tail("abc", 3), "abc");
-    }
-
-    #[test]
-    fn test_content_tail_longer_than_max() {
-        assert_eq!(content_tail("hello world", 5), "world");
-    }
-
-    #[test]
-    fn test_content_tail_empty() {
-        assert_eq!(content_tail("", 500), "");
+    fn test_config_global_prompt_timeout_applies_to_all() {
+        let config = UsageConfig {
+            prompt_timeout: Some(60),
+            ..UsageConfig::default()
+        };
+        assert_eq!(config.prompt_timeout("claude"), Duration::from_secs(60));
+        assert_eq!(config.prompt_timeout("gemini"), Duration::from_secs(60));
     }
 
     #[test]
-    fn test_content_tail_unicode() {
-        // Ensure char-based truncation doesn't split codepoints
-        assert_eq!(content_tail("héllo wörld", 5), "wörld");
+    fn test_config_provider_overrides_win() {
+        let mut config = UsageConfig {
+            prompt_timeout: Some(60),
+            ..UsageConfig::default()
+        };
+        config.provider_timings.insert(
+            "codex".into(),
+            ProviderTimings {
+                timeout: Some(120),
+                prompt_timeout: Some(90),
+                poll_interval_ms: Some(200),
+            },
+        );
+        assert_eq!(config.data_timeout("codex"), Duration::from_secs(120));
+        assert_eq!(config.prompt_timeout("codex"), Duration::from_secs(90));
+        assert_eq!(config.poll_interval("codex"), Duration::from_millis(200));
+        // Other providers keep the config-wide values
+        assert_eq!(config.data_timeout("claude"), Duration::from_secs(45));
+        assert_eq!(config.prompt_timeout("claude"), Duration::from_secs(60));
+        assert_eq!(config.poll_interval("claude"), Duration::from_millis(500));
     }
 }
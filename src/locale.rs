@@ -0,0 +1,171 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// How numbers are written for a locale.  Only affects human output; JSON
+/// always uses canonical numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub decimal: char,
+    /// Thousands separator; empty for no grouping.
+    pub group: &'static str,
+    /// Between a number and `%` ("" in English, a no-break space in most of
+    /// continental Europe).
+    pub percent_gap: &'static str,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            decimal: '.',
+            group: ",",
+            percent_gap: "",
+        }
+    }
+}
+
+const NBSP: &str = "\u{a0}";
+const NARROW_NBSP: &str = "\u{202f}";
+
+impl NumberFormat {
+    /// Format for a locale tag such as `de_DE.UTF-8`, `fr-CA` or `C`.
+    /// Unknown languages fall back to English conventions.
+    pub fn for_locale(tag: &str) -> Self {
+        let tag = tag.split(['.', '@']).next().unwrap_or("");
+        let mut parts = tag.split(['_', '-']);
+        let language = parts.next().unwrap_or("").to_ascii_lowercase();
+        let region = parts.next().unwrap_or("").to_ascii_uppercase();
+        let format = |decimal, group, percent_gap| Self {
+            decimal,
+            group,
+            percent_gap,
+        };
+        match (language.as_str(), region.as_str()) {
+            ("de", "CH") => format('.', "’", ""),
+            ("de", _) => format(',', ".", NBSP),
+            ("fr", "CH") => format(',', NARROW_NBSP, NBSP),
+            ("fr", _) => format(',', NARROW_NBSP, NARROW_NBSP),
+            ("es" | "it" | "pt" | "nl" | "da" | "id" | "tr", _) => format(',', ".", ""),
+            ("sv" | "nb" | "no" | "fi" | "cs" | "sk" | "pl" | "ru" | "uk", _) => {
+                format(',', NBSP, NBSP)
+            }
+            _ => Self::default(),
+        }
+    }
+
+    /// Format for the user's numeric locale (`LC_ALL`, then `LC_NUMERIC`,
+    /// then `LANG`).
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map(|tag| Self::for_locale(&tag))
+            .unwrap_or_default()
+    }
+
+    /// An integer with thousands grouping.
+    pub fn integer(&self, value: i64) -> String {
+        let digits = value.unsigned_abs().to_string();
+        let sign = if value < 0 { "-" } else { "" };
+        format!("{}{}", sign, self.group_digits(&digits))
+    }
+
+    /// A number with exactly `places` decimals.
+    pub fn decimal(&self, value: f64, places: usize) -> String {
+        let fixed = format!("{:.*}", places, value.abs());
+        let (int_part, frac_part) = fixed.split_once('.').unwrap_or((&fixed, ""));
+        let sign = if value < 0.0 && fixed.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            "-"
+        } else {
+            ""
+        };
+        let mut out = format!("{}{}", sign, self.group_digits(int_part));
+        if !frac_part.is_empty() {
+            out.push(self.decimal);
+            out.push_str(frac_part);
+        }
+        out
+    }
+
+    pub fn percent(&self, value: u32) -> String {
+        format!("{}{}%", self.integer(value as i64), self.percent_gap)
+    }
+
+    /// Reformat dollar amounts inside provider text, e.g.
+    /// `$1,077.33 / $500.00 spent`.  Other text is left alone.
+    pub fn money_text(&self, text: &str) -> String {
+        static AMOUNT_RE: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"\$(\d[\d,]*)(?:\.(\d+))?").unwrap());
+        AMOUNT_RE
+            .replace_all(text, |caps: &regex::Captures| {
+                let digits = caps[1].replace(',', "");
+                let mut out = format!("${}", self.group_digits(&digits));
+                if let Some(cents) = caps.get(2) {
+                    out.push(self.decimal);
+                    out.push_str(cents.as_str());
+                }
+                out
+            })
+            .into_owned()
+    }
+
+    fn group_digits(&self, digits: &str) -> String {
+        let mut out = String::with_capacity(digits.len() + digits.len() / 3 * 3);
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push_str(self.group);
+            }
+            out.push(c);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_default() {
+        let fmt = NumberFormat::for_locale("en_US.UTF-8");
+        assert_eq!(fmt, NumberFormat::default());
+        assert_eq!(fmt.integer(1234567), "1,234,567");
+        assert_eq!(fmt.decimal(1234.5, 2), "1,234.50");
+        assert_eq!(fmt.percent(12), "12%");
+        assert_eq!(NumberFormat::for_locale("C"), NumberFormat::default());
+    }
+
+    #[test]
+    fn test_german_and_french() {
+        let de = NumberFormat::for_locale("de_DE.UTF-8");
+        assert_eq!(de.decimal(1234.5, 2), "1.234,50");
+        assert_eq!(de.percent(12), "12\u{a0}%");
+        let fr = NumberFormat::for_locale("fr-FR");
+        assert_eq!(fr.integer(4920), "4\u{202f}920");
+        assert_eq!(fr.decimal(0.09, 2), "0,09");
+        let ch = NumberFormat::for_locale("de_CH");
+        assert_eq!(ch.decimal(1234.5, 2), "1’234.50");
+    }
+
+    #[test]
+    fn test_negative_and_small_numbers() {
+        let fmt = NumberFormat::default();
+        assert_eq!(fmt.integer(-1234), "-1,234");
+        assert_eq!(fmt.integer(999), "999");
+        assert_eq!(fmt.decimal(-0.001, 2), "0.00");
+        assert_eq!(fmt.decimal(-2.5, 1), "-2.5");
+    }
+
+    #[test]
+    fn test_money_text() {
+        let de = NumberFormat::for_locale("de_DE");
+        assert_eq!(
+            de.money_text("$1,077.33 / $500.00 spent"),
+            "$1.077,33 / $500,00 spent"
+        );
+        assert_eq!(
+            NumberFormat::default().money_text("$77.33 / $500.00 spent"),
+            "$77.33 / $500.00 spent"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::registry;
+
+/// Poll interval while another run holds a provider's lock.
+const LOCK_POLL: Duration = Duration::from_millis(100);
+
+/// An exclusive, per-user lock on checking one provider; released when
+/// dropped (or when the process dies, so a crash never leaves it held).
+#[derive(Debug)]
+pub(crate) struct ProviderLock {
+    _file: File,
+}
+
+/// How taking a provider's lock went.
+#[derive(Debug)]
+pub(crate) enum Acquired {
+    /// Nobody else was checking.
+    Free(ProviderLock),
+    /// Another run was checking and has finished; its result may be in
+    /// the cache.
+    AfterWait(ProviderLock),
+    /// Another run is still checking after the whole wait.
+    TimedOut,
+}
+
+/// Take `provider`'s lock under the state home, waiting up to `wait` for
+/// another run to release it.
+pub(crate) fn acquire(provider: &str, wait: Duration) -> Result<Acquired> {
+    let dir = registry::state_home()
+        .context("No state directory for locks: set HOME or XDG_STATE_HOME")?
+        .join("locks");
+    acquire_in(&dir, provider, wait)
+}
+
+fn acquire_in(dir: &Path, provider: &str, wait: Duration) -> Result<Acquired> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!("{}.lock", provider));
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let deadline = Instant::now() + wait;
+    let mut waited = false;
+    loop {
+        // SAFETY: flock on a file descriptor we own.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+            let lock = ProviderLock { _file: file };
+            return Ok(if waited {
+                Acquired::AfterWait(lock)
+            } else {
+                Acquired::Free(lock)
+            });
+        }
+        if Instant::now() >= deadline {
+            return Ok(Acquired::TimedOut);
+        }
+        waited = true;
+        thread::sleep(LOCK_POLL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::TempDir;
+
+    #[test]
+    fn test_second_caller_waits_for_the_lock() {
+        let dir = TempDir::create("lock-test").unwrap();
+        let first = acquire_in(dir.path(), "claude", Duration::ZERO).unwrap();
+        assert!(matches!(first, Acquired::Free(_)));
+        let second = acquire_in(dir.path(), "claude", Duration::from_millis(150)).unwrap();
+        assert!(matches!(second, Acquired::TimedOut));
+        // Other providers aren't blocked.
+        let other = acquire_in(dir.path(), "codex", Duration::ZERO).unwrap();
+        assert!(matches!(other, Acquired::Free(_)));
+
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            drop(first);
+        });
+        let third = acquire_in(dir.path(), "claude", Duration::from_secs(5)).unwrap();
+        assert!(matches!(third, Acquired::AfterWait(_)));
+        release.join().unwrap();
+    }
+}
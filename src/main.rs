@@ -1,40 +1,406 @@
 #![deny(warnings)]
 
 use anyhow::Result;
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use comfy_table::{presets::ASCII_BORDERS_ONLY_CONDENSED, Cell, Color, Table};
 use std::collections::BTreeMap;
 use std::io::Write;
 use std::process::Command;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use agentusage::alert;
+use agentusage::bench::{BenchSample, BenchStats};
+use agentusage::cache;
+use agentusage::custom;
+use agentusage::history;
+use agentusage::import;
+use agentusage::locale::NumberFormat;
+use agentusage::notify;
+use agentusage::parser::{self, canonical_keys};
+use agentusage::plugin;
+use agentusage::query::Query;
+use agentusage::registry::{self, LeftoverState};
+use agentusage::thresholds::{self, Thresholds};
+use agentusage::tuning;
 use agentusage::{
-    run_all, run_claude, run_codex, run_gemini, AllResults, ApprovalPolicy, PercentKind,
-    UsageConfig, UsageData, UsageEntry,
+    collect_results, run_all, run_provider, run_streaming, strip_error_tags, AllResults,
+    ApprovalPolicy, AttachMode, CheckEvent, CheckFailure, CheckObserver, CheckPhase, CheckPlan,
+    DialogKind, Environment, LimitWindow, PercentKind, ProviderRegistry, ProviderResult,
+    ProviderTimings, SourceMode, UsageConfig, UsageData, UsageEntry, WaitStats, ALL_PROVIDERS,
 };
 
+/// Minimum accepted `--poll-interval`; tighter loops just burn CPU.
+const MIN_POLL_INTERVAL_MS: u64 = 10;
+
+/// A timing flag value: `N` applies to every provider, `PROVIDER=N` to one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TimingArg {
+    provider: Option<String>,
+    value: u64,
+}
+
+fn parse_timing_arg(s: &str) -> Result<TimingArg, String> {
+    let (provider, value) = match s.split_once('=') {
+        Some((provider, value)) => {
+            let provider = provider.trim().to_lowercase();
+            if !ALL_PROVIDERS.contains(&provider.as_str()) {
+                return Err(format!(
+                    "unknown provider '{}' (expected one of: {})",
+                    provider,
+                    ALL_PROVIDERS.join(", ")
+                ));
+            }
+            (Some(provider), value)
+        }
+        None => (None, s),
+    };
+    let value = value
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("'{}' is not a whole number", value.trim()))?;
+    Ok(TimingArg { provider, value })
+}
+
+/// A `--source` value: `MODE` applies to every provider, `PROVIDER=MODE`
+/// to one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SourceArg {
+    provider: Option<String>,
+    mode: SourceMode,
+}
+
+fn parse_source_arg(s: &str) -> Result<SourceArg, String> {
+    let (provider, mode) = match s.split_once('=') {
+        Some((provider, mode)) => {
+            let provider = provider.trim().to_lowercase();
+            if !ALL_PROVIDERS.contains(&provider.as_str()) {
+                return Err(format!(
+                    "unknown provider '{}' (expected one of: {})",
+                    provider,
+                    ALL_PROVIDERS.join(", ")
+                ));
+            }
+            (Some(provider), mode)
+        }
+        None => (None, s),
+    };
+    Ok(SourceArg {
+        provider,
+        mode: mode.parse()?,
+    })
+}
+
+/// `--gemini-cap MODEL=N`: a daily request cap for one Gemini model.
+fn parse_request_cap_arg(s: &str) -> Result<(String, u64), String> {
+    let Some((model, cap)) = s.split_once('=') else {
+        return Err(format!("expected MODEL=N, got '{}'", s));
+    };
+    let model = model.trim().to_lowercase();
+    if model.is_empty() {
+        return Err("model name is empty".to_string());
+    }
+    let cap = cap
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|cap| *cap > 0)
+        .ok_or_else(|| format!("'{}' is not a positive whole number", cap.trim()))?;
+    Ok((model, cap))
+}
+
+fn parse_threshold_arg(s: &str) -> Result<(Option<LimitWindow>, u32), String> {
+    let (window, percent) = match s.split_once('=') {
+        Some((window, percent)) => (Some(window.parse::<LimitWindow>()?), percent),
+        None => (None, s),
+    };
+    let percent = percent
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|p| *p <= 100)
+        .ok_or_else(|| format!("'{}' is not a percentage (0-100)", percent.trim()))?;
+    Ok((window, percent))
+}
+
+fn parse_poll_interval_arg(s: &str) -> Result<TimingArg, String> {
+    let arg = parse_timing_arg(s)?;
+    if arg.value < MIN_POLL_INTERVAL_MS {
+        return Err(format!(
+            "poll interval must be at least {}ms",
+            MIN_POLL_INTERVAL_MS
+        ));
+    }
+    Ok(arg)
+}
+
+/// How results are written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// Human-readable table
+    Table,
+    /// Same as --json
+    Json,
+    /// Render --template over the JSON document
+    Template,
+}
+
+/// How diagnostics (warnings, errors, --verbose output) are written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum DiagnosticsMode {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// How `--bell` gets attention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum BellMode {
+    /// Ring the terminal bell
+    Terminal,
+    /// Play the system alert sound (falls back to the terminal bell)
+    Sound,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, clap::Subcommand)]
+enum Commands {
+    /// List agent CLIs found on PATH, their versions, and which ones
+    /// agentusage can check
+    List,
+    /// Chart percent used over time from the history that --record keeps
+    #[cfg(feature = "chart")]
+    Chart {
+        /// How far back to chart, e.g. 24h, 7d, 2w
+        #[arg(long, value_name = "PERIOD", default_value = "7d", value_parser = parse_period_arg)]
+        since: chrono::Duration,
+        /// Output file; .svg or .png
+        #[arg(long, short = 'o', value_name = "FILE")]
+        output: std::path::PathBuf,
+        /// Only chart this provider
+        #[arg(long, value_name = "NAME")]
+        provider: Option<String>,
+    },
+    /// Summarize the history that --record keeps: peaks, time spent at or
+    /// above each limit's threshold, usage at reset, and spend change
+    Report {
+        /// Cover the last 7 days (the default)
+        #[arg(long)]
+        weekly: bool,
+        /// Cover a different period instead, e.g. 24h, 30d
+        #[arg(long, value_name = "PERIOD", conflicts_with = "weekly", value_parser = parse_period_arg)]
+        since: Option<chrono::Duration>,
+        /// Only report on this provider
+        #[arg(long, value_name = "NAME")]
+        provider: Option<String>,
+        /// Show usage growth by weekday and hour instead, with the hours
+        /// limits usually reset
+        #[arg(long)]
+        heatmap: bool,
+    },
+    /// Inspect or reset the results --lock and --min-interval serve from
+    /// the cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Save a check as a named baseline, to see later with --compare-to
+    /// how much each limit was used since
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+    /// Maintain the history that --record keeps
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+    /// Full-screen live view: a gauge per limit, resets counting down, and
+    /// each provider checked again on an interval or on demand
+    #[cfg(feature = "dashboard")]
+    Dashboard {
+        /// Seconds between automatic checks of each provider
+        #[arg(long, value_name = "SECS", default_value_t = 300, value_parser = clap::value_parser!(u64).range(10..))]
+        refresh: u64,
+    },
+    /// Serve usage and provider health as JSON over HTTP, caching results so
+    /// polling doesn't launch a CLI per request
+    #[cfg(feature = "serve")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:7788")]
+        listen: String,
+        /// Seconds a result is served before a request checks again
+        #[arg(long, value_name = "SECS", default_value_t = 60)]
+        ttl: u64,
+        /// Accept other machines' --json output POSTed to /ingest/HOST with
+        /// the bearer token in $AGENTUSAGE_INGEST_TOKEN, and add it to the
+        /// history
+        #[arg(long)]
+        ingest: bool,
+    },
+    /// Check usage, run COMMAND, check again, and show how much of each
+    /// limit it used
+    Measure {
+        /// Command to run and its arguments, after --
+        #[arg(required = true, last = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+    /// Run a saved capture through a parser and show the result and which
+    /// patterns each line matched
+    Parse {
+        /// Parser to use; every parser is tried when omitted
+        #[arg(long, value_name = "NAME")]
+        provider: Option<String>,
+        /// Capture file, e.g. from `agentusage capture`; - for stdin
+        file: std::path::PathBuf,
+    },
+    /// Run a provider up to its usage screen and save the screen text
+    /// without parsing it, to contribute as a parser fixture
+    Capture {
+        /// Provider to capture, e.g. claude
+        #[arg(long, value_name = "NAME")]
+        provider: String,
+        /// File to write; stdout when omitted
+        #[arg(long, short = 'o', value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, clap::Subcommand)]
+enum CacheAction {
+    /// Show each provider's cached result and its age
+    Show {
+        /// Only show this provider
+        #[arg(long, value_name = "NAME")]
+        provider: Option<String>,
+    },
+    /// Delete cached results and launch stamps, e.g. after switching
+    /// accounts, so the next check launches the provider
+    Clear {
+        /// Only clear this provider
+        #[arg(long, value_name = "NAME")]
+        provider: Option<String>,
+    },
+    /// Print the cache directory
+    Path,
+    /// Show when each provider was last launched and, for a
+    /// --min-interval, how much longer its cached result is served
+    Ttl {
+        /// The --min-interval your integration runs with
+        #[arg(long, value_name = "SECS")]
+        min_interval: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, clap::Subcommand)]
+enum SnapshotAction {
+    /// Check usage and save the results in the history as NAME; saving a
+    /// name again replaces it
+    Save {
+        /// Name to save under, e.g. before-refactor
+        name: String,
+    },
+    /// List saved snapshots
+    List,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, clap::Subcommand)]
+enum HistoryAction {
+    /// Keep recent records as taken and older ones at one per limit per
+    /// hour, so the history doesn't grow without bound
+    Prune {
+        /// How long records are kept as taken, e.g. 30d
+        #[arg(long, value_name = "PERIOD", default_value = "30d", value_parser = parse_period_arg)]
+        keep_raw: chrono::Duration,
+        /// How long hourly records are kept after that; kept for good when
+        /// omitted
+        #[arg(long, value_name = "PERIOD", value_parser = parse_period_arg)]
+        keep_hourly: Option<chrono::Duration>,
+        /// Show what would be removed without rewriting the history
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Add usage recorded by another tool to the history, skipping records
+    /// it already has
+    Import {
+        /// What wrote FILE
+        #[arg(long, value_enum)]
+        format: ImportFormat,
+        /// The tool's JSON output; - for stdin
+        file: std::path::PathBuf,
+    },
+}
+
+/// Tools `agentusage history import` reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ImportFormat {
+    /// `ccusage blocks --json`: Claude's 5-hour session windows
+    Ccusage,
+}
+
+fn parse_period_arg(s: &str) -> Result<chrono::Duration, String> {
+    history::parse_period(s).map_err(|e| e.to_string())
+}
+
+/// How check progress is reported on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ProgressMode {
+    /// Spinners, unless --json or --verbose is set
+    Auto,
+    /// One JSON event per line (phases, dialogs, retries, completion)
+    Ndjson,
+    /// No progress output
+    Off,
+}
+
 #[derive(Parser)]
+#[command(group(
+    clap::ArgGroup::new("provider").args(["claude", "codex", "gemini", "copilot", "cursor", "qwen", "amazonq", "aider"])
+))]
 #[command(
     name = "agentusage",
     version,
-    about = "Check Claude Code, Codex, and Gemini CLI usage limits",
-    long_about = "Check Claude Code, Codex, and Gemini CLI usage limits.\n\n\
+    about = "Check Claude Code, Codex, Gemini CLI, Copilot CLI, Cursor, Qwen Code, Amazon Q, and aider usage",
+    long_about = "Check Claude Code, Codex, Gemini CLI, Copilot CLI, Cursor, Qwen Code, Amazon Q, and aider usage.\n\n\
         Launches each CLI tool in an isolated pseudo-terminal (openpty), then\n\
         runs its usage/status command,\n\
         parses the output, and reports usage percentages, reset times, and spend.\n\n\
-        By default, checks all installed providers. Use --claude, --codex, or\n\
-        --gemini to check a single provider.",
+        By default, checks all installed providers. Use --claude, --codex,\n\
+        --gemini, --copilot, --cursor, --qwen, --amazonq, or --aider to check\n\
+        a single provider.",
     after_help = "\
 Examples:
   agentusage                  Check all installed providers
   agentusage --claude         Check only Claude Code
   agentusage --json           Output as machine-readable JSON
   agentusage --claude --json  Single provider, JSON output
+  agentusage --query results.claude.session.percent_used
+                              Print a single value for scripts
+  agentusage --codex --template '{{ results.codex.session.percent_remaining }}%'
+                              Render a custom status line
   agentusage --timeout 60     Wait up to 60s for data
+  agentusage --prompt-timeout codex=90
+                              Give Codex 90s to show its prompt
+  agentusage --max-total-time 20
+                              Give up on anything still running after 20s
   agentusage -C ~/project     Run CLI sessions in ~/project
-  agentusage --cleanup        Kill tracked PTY child sessions and exit
+  agentusage --claude --interactive
+                              Log in inside Claude's UI if needed, then check
+  agentusage --codex --attach Watch the Codex session while it is checked
+  agentusage --mock --json    Fixture output for offline development
+  agentusage --self-test      Verify parsers against bundled fixtures
+  agentusage list --json      Installed agent CLIs and their versions
+  agentusage --record         Check and append the results to the history
+  agentusage chart --since 7d -o usage.svg
+                              Chart the recorded history
+  agentusage report --weekly  Summarize the last week of recorded history
+  agentusage report --heatmap Which hours of the week usage grows in
+  agentusage --bench --iterations 5
+                              Time five checks of each provider
+  agentusage --cleanup        Kill provider processes left by crashed runs and exit
 
 Exit codes:
   0  Success
@@ -44,80 +410,526 @@ Exit codes:
   4  Failed to parse provider output"
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Check only Claude Code usage
-    #[arg(long, help_heading = "Providers", conflicts_with_all = ["codex", "gemini"])]
+    #[arg(
+        long,
+        help_heading = "Providers",
+        conflicts_with_all = ["codex", "gemini", "copilot", "cursor", "qwen", "amazonq", "aider"]
+    )]
     claude: bool,
 
     /// Check only Codex usage
-    #[arg(long, help_heading = "Providers", conflicts_with_all = ["claude", "gemini"])]
+    #[arg(
+        long,
+        help_heading = "Providers",
+        conflicts_with_all = ["claude", "gemini", "copilot", "cursor", "qwen", "amazonq", "aider"]
+    )]
     codex: bool,
 
     /// Check only Gemini CLI usage
-    #[arg(long, help_heading = "Providers", conflicts_with_all = ["claude", "codex"])]
+    #[arg(
+        long,
+        help_heading = "Providers",
+        conflicts_with_all = ["claude", "codex", "copilot", "cursor", "qwen", "amazonq", "aider"]
+    )]
     gemini: bool,
 
+    /// Check only GitHub Copilot CLI usage (premium requests)
+    #[arg(
+        long,
+        help_heading = "Providers",
+        conflicts_with_all = ["claude", "codex", "gemini", "cursor", "qwen", "amazonq", "aider"]
+    )]
+    copilot: bool,
+
+    /// Check only Cursor usage (cursor-agent plan usage)
+    #[arg(
+        long,
+        help_heading = "Providers",
+        conflicts_with_all = ["claude", "codex", "gemini", "copilot", "qwen", "amazonq", "aider"]
+    )]
+    cursor: bool,
+
+    /// Check only Qwen Code usage
+    #[arg(
+        long,
+        help_heading = "Providers",
+        conflicts_with_all = ["claude", "codex", "gemini", "copilot", "cursor", "amazonq", "aider"]
+    )]
+    qwen: bool,
+
+    /// Check only Amazon Q Developer CLI usage (monthly allowances)
+    #[arg(
+        long,
+        help_heading = "Providers",
+        conflicts_with_all = ["claude", "codex", "gemini", "copilot", "cursor", "qwen", "aider"]
+    )]
+    amazonq: bool,
+
+    /// Check only aider (context window tokens and their estimated cost)
+    #[arg(
+        long,
+        help_heading = "Providers",
+        conflicts_with_all = ["claude", "codex", "gemini", "copilot", "cursor", "qwen", "amazonq"]
+    )]
+    aider: bool,
+
     /// Output as JSON
-    #[arg(long)]
+    #[arg(long, global = true)]
     json: bool,
 
-    /// Max seconds to wait for data [default: 45]
-    #[arg(long, default_value = "45", hide_default_value = true)]
-    timeout: u64,
+    /// Output format: table, json, or template [default: table]
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["json", "query"])]
+    format: Option<OutputFormat>,
+
+    /// Jinja-style template rendered over the JSON output, e.g.
+    /// '{{ results.codex.session.percent_remaining }}%' [implies --format template]
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["json", "query"])]
+    template: Option<String>,
+
+    /// Locale for numbers in table output, e.g. de_DE [default: LC_ALL,
+    /// LC_NUMERIC, or LANG]; JSON is unaffected
+    #[arg(long, value_name = "LOCALE")]
+    locale: Option<String>,
+
+    /// Print each provider's result as a JSON line as soon as it finishes,
+    /// instead of one document after the slowest provider
+    #[arg(long, conflicts_with_all = ["json", "format", "template", "query", "bench"])]
+    stream: bool,
+
+    /// Print one value from the JSON output, e.g.
+    /// results.claude.session.percent_used
+    #[arg(long, value_name = "PATH", conflicts_with = "json")]
+    query: Option<Query>,
+
+    /// Max seconds to wait for data; SECS or PROVIDER=SECS, repeatable [default: 45]
+    #[arg(long, value_name = "SECS", value_parser = parse_timing_arg)]
+    timeout: Vec<TimingArg>,
+
+    /// Max seconds to wait for the CLI prompt; SECS or PROVIDER=SECS, repeatable
+    /// [default: 30, Gemini: --timeout]
+    #[arg(long, value_name = "SECS", value_parser = parse_timing_arg)]
+    prompt_timeout: Vec<TimingArg>,
+
+    /// Milliseconds between screen captures; MS or PROVIDER=MS, repeatable [default: 500]
+    #[arg(long, value_name = "MS", value_parser = parse_poll_interval_arg)]
+    poll_interval: Vec<TimingArg>,
+
+    /// Overall deadline in seconds; providers still running are cancelled and reported as timed out
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    max_total_time: Option<u64>,
+
+    /// When the data wait times out after some limits were parsed, report
+    /// those (marked partial) instead of failing the provider
+    #[arg(long)]
+    allow_partial: bool,
+
+    /// Lock each provider per user so overlapping runs don't launch copies;
+    /// wait up to SECS [default: 10] for another run, then use its cached result
+    #[arg(
+        long,
+        value_name = "SECS",
+        num_args = 0..=1,
+        default_missing_value = "10",
+        value_parser = clap::value_parser!(u64)
+    )]
+    lock: Option<u64>,
+
+    /// Launch each provider at most once per SECS across runs; checks asked
+    /// for sooner serve the last cached result instead
+    #[arg(long, value_name = "SECS")]
+    min_interval: Option<u64>,
+
+    /// When a provider fails, report its last values from the `--record`
+    /// history instead, marked stale with the time they were checked
+    #[arg(long)]
+    stale_fallback: bool,
+
+    /// Ring the terminal bell, or play a system sound, when a limit reaches
+    /// its threshold or a provider check fails (alongside any notify.toml
+    /// routes)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KIND",
+        num_args = 0..=1,
+        default_missing_value = "terminal"
+    )]
+    bell: Option<BellMode>,
+
+    /// Percent used at which a limit warns, [WINDOW=]PCT with WINDOW one of
+    /// session, daily, weekly, monthly, other; repeatable, overrides
+    /// thresholds.toml [default: 90]
+    #[arg(long, global = true, value_name = "[WINDOW=]PCT", value_parser = parse_threshold_arg)]
+    threshold: Vec<(Option<LimitWindow>, u32)>,
+
+    /// Exit with code 5 when a limit has reached its threshold
+    #[arg(long)]
+    fail_on_threshold: bool,
+
+    /// Don't send the notifications routed in notify.toml
+    /// [default ~/.config/agentusage/notify.toml]
+    #[arg(long)]
+    no_notify: bool,
+
+    /// Append successful results to the usage history for `agentusage chart`
+    /// [$AGENTUSAGE_HISTORY, default ~/.local/share/agentusage/history.jsonl]
+    #[arg(long)]
+    record: bool,
+
+    /// Show how much of each limit was used since the snapshot saved as
+    /// NAME with `agentusage snapshot save NAME`
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["query", "template", "stream"])]
+    compare_to: Option<String>,
 
     /// Print debug info (raw captured text, timing)
     #[arg(long)]
     verbose: bool,
 
-    /// How to handle interactive dialogs (trust, update, terms) [default: fail]
+    /// Diagnostics format on stderr: text, or json for one object per line
+    /// [default: text]; stdout only ever carries the result
+    #[arg(long, value_enum, default_value = "text", hide_default_value = true)]
+    diagnostics: DiagnosticsMode,
+
+    /// How to handle interactive dialogs (trust, update, terms, onboarding) [default: fail]
     #[arg(long, value_enum, default_value = "fail", hide_default_value = true)]
     approval_policy: ApprovalPolicy,
 
-    /// Working directory for the CLI sessions
+    /// Hand over the terminal for dialogs that can't be dismissed automatically
+    /// (auth, first-run); press Ctrl-] to resume
+    #[arg(long)]
+    interactive: bool,
+
+    /// Mirror the provider's screen to this terminal while the check runs
+    /// (view, or interactive to also type into it) [single provider only]
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "view",
+        requires = "provider",
+        conflicts_with = "interactive"
+    )]
+    attach: Option<AttachMode>,
+
+    /// Launch providers in read-only/plan modes so a check can't run tools or
+    /// write files [default]
+    #[arg(long, overrides_with = "no_safe_launch")]
+    safe_launch: bool,
+
+    /// Launch providers without the --safe-launch hardening flags
+    #[arg(long, overrides_with = "safe_launch")]
+    no_safe_launch: bool,
+
+    /// Give provider sessions private XDG cache/state and temp directories so
+    /// checks don't touch history files or lockfiles of running CLI sessions
+    #[arg(long)]
+    isolate_env: bool,
+
+    /// Record the launch directory as trusted in each provider's config before
+    /// starting it, so folder-trust dialogs don't appear on repeat runs
+    #[arg(long)]
+    preseed_trust: bool,
+
+    /// Switch off provider update checks for the launched sessions so update
+    /// prompts rarely appear (your settings files are not changed)
+    #[arg(long)]
+    defer_updates: bool,
+
+    /// Check providers one at a time instead of in parallel
+    #[arg(long)]
+    sequential: bool,
+
+    /// Keep workstation defaults on CI runners, containers, and SSH hosts
+    /// (otherwise: longer prompt wait, deferred updates, sequential checks,
+    /// no progress animation)
+    #[arg(long)]
+    no_auto_tune: bool,
+
+    /// Don't run the plugin providers in ~/.config/agentusage/providers
+    /// when checking all providers
+    #[arg(long)]
+    no_plugins: bool,
+
+    /// Read Codex rate limits from OpenAI's usage endpoint with the login in
+    /// ~/.codex/auth.json, falling back to the TUI (same as --source codex=api)
+    #[arg(long)]
+    codex_api: bool,
+
+    /// Where to read usage from: auto (API, then session logs, then TUI),
+    /// api, logs, or tui; MODE or PROVIDER=MODE, repeatable [default: tui]
+    #[arg(long, value_name = "MODE", value_parser = parse_source_arg)]
+    source: Vec<SourceArg>,
+
+    /// Daily request cap for a Gemini model, MODEL=N, repeatable [default:
+    /// none, so Gemini rows show only their request count]
+    #[arg(long, value_name = "MODEL=N", value_parser = parse_request_cap_arg)]
+    gemini_cap: Vec<(String, u64)>,
+
+    /// Working directory for the CLI sessions [default: a fresh, empty temp
+    /// directory, removed afterwards]
     #[arg(long, short = 'C')]
     directory: Option<String>,
 
-    /// Kill tracked agentusage PTY child sessions and exit
+    /// Progress reporting: auto, ndjson, or off [default: auto]
+    #[arg(long, value_enum, default_value = "auto", hide_default_value = true)]
+    progress: ProgressMode,
+
+    /// Write --progress ndjson events to this file descriptor instead of stderr
+    #[arg(long, value_name = "FD")]
+    progress_fd: Option<i32>,
+
+    /// Kill provider processes that crashed agentusage runs left behind, and exit
     #[arg(long)]
     cleanup: bool,
 
+    /// Before checking, kill provider processes that crashed runs left
+    /// running, instead of only warning about them
+    #[arg(long)]
+    reap_orphans: bool,
+
     /// Check if provider CLIs are installed
     #[arg(long)]
     doctor: bool,
+
+    /// Return built-in fixture data instead of launching any CLI (for building
+    /// against agentusage output offline)
+    #[arg(long)]
+    mock: bool,
+
+    /// Run the bundled capture fixtures through the parsers and check
+    /// reset-time math against a fixed clock
+    #[arg(long)]
+    self_test: bool,
+
+    /// Print what each provider check would launch and type, with resolved
+    /// timeouts and policies, without running anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Run each provider check repeatedly and report time-to-prompt and
+    /// time-to-data statistics instead of usage
+    #[arg(long)]
+    bench: bool,
+
+    /// Checks per provider for --bench
+    #[arg(
+        long,
+        value_name = "N",
+        default_value = "3",
+        requires = "bench",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    iterations: u32,
 }
 
 impl Cli {
+    /// Fold `--format` into the flags the rest of main reads.
+    fn resolve_format(&mut self) -> Result<(), String> {
+        match self.format {
+            Some(OutputFormat::Json) => self.json = true,
+            Some(OutputFormat::Template) if self.template.is_none() => {
+                return Err("--format template requires --template".into())
+            }
+            Some(OutputFormat::Table) if self.template.is_some() => {
+                return Err("--template cannot be used with --format table".into())
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Number formatting for human output: `--locale`, else the environment.
+    fn number_format(&self) -> NumberFormat {
+        self.locale
+            .as_deref()
+            .map(NumberFormat::for_locale)
+            .unwrap_or_else(NumberFormat::from_env)
+    }
+
+    fn diagnostics(&self) -> Diagnostics {
+        Diagnostics(self.diagnostics)
+    }
+
+    /// The warning thresholds: thresholds.toml, then `--threshold`.
+    fn thresholds(&self) -> Thresholds {
+        let mut thresholds = thresholds::load_default().unwrap_or_else(|e| {
+            self.diagnostics()
+                .warning("thresholds.toml", &format!("{:#}", e));
+            Thresholds::default()
+        });
+        for (window, percent) in &self.threshold {
+            thresholds.set(*window, *percent);
+        }
+        thresholds
+    }
+
+    /// The name `agentusage snapshot save` saves this check under.
+    fn snapshot_name(&self) -> Option<&str> {
+        match &self.command {
+            Some(Commands::Snapshot {
+                action: SnapshotAction::Save { name },
+            }) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The provider picked with `--claude`, `--codex`, `--gemini`,
+    /// `--copilot`, `--cursor`, `--qwen`, `--amazonq`, or `--aider`, if any.
+    fn selected_provider(&self) -> Option<&'static str> {
+        if self.claude {
+            Some("claude")
+        } else if self.codex {
+            Some("codex")
+        } else if self.gemini {
+            Some("gemini")
+        } else if self.copilot {
+            Some("copilot")
+        } else if self.cursor {
+            Some("cursor")
+        } else if self.qwen {
+            Some("qwen")
+        } else if self.amazonq {
+            Some("amazonq")
+        } else if self.aider {
+            Some("aider")
+        } else {
+            None
+        }
+    }
+
+    /// Providers this invocation checks.
+    fn providers(&self) -> Vec<&'static str> {
+        match self.selected_provider() {
+            Some(provider) => vec![provider],
+            None => ALL_PROVIDERS.to_vec(),
+        }
+    }
+
     fn to_config(&self) -> UsageConfig {
-        UsageConfig {
-            timeout: self.timeout,
+        let mut config = UsageConfig {
             verbose: self.verbose,
             approval_policy: self.approval_policy,
+            interactive: self.interactive,
+            attach: self.attach,
+            mock: self.mock,
             directory: self.directory.clone(),
+            safe_launch: !self.no_safe_launch,
+            isolate_env: self.isolate_env,
+            preseed_trust: self.preseed_trust,
+            defer_updates: self.defer_updates,
+            sequential: self.sequential,
+            allow_partial: self.allow_partial,
+            lock_wait: self.lock.map(Duration::from_secs),
+            min_interval: self.min_interval.map(Duration::from_secs),
+            deadline: self
+                .max_total_time
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+            ..UsageConfig::default()
+        };
+        // Later flags win, so `--timeout 60 --timeout 90` behaves like 90.
+        let mut overrides: BTreeMap<String, ProviderTimings> = BTreeMap::new();
+        for arg in &self.timeout {
+            match &arg.provider {
+                Some(p) => overrides.entry(p.clone()).or_default().timeout = Some(arg.value),
+                None => config.timeout = arg.value,
+            }
+        }
+        for arg in &self.prompt_timeout {
+            match &arg.provider {
+                Some(p) => overrides.entry(p.clone()).or_default().prompt_timeout = Some(arg.value),
+                None => config.prompt_timeout = Some(arg.value),
+            }
+        }
+        for arg in &self.poll_interval {
+            match &arg.provider {
+                Some(p) => {
+                    overrides.entry(p.clone()).or_default().poll_interval_ms = Some(arg.value)
+                }
+                None => config.poll_interval_ms = arg.value,
+            }
+        }
+        config.provider_timings = overrides;
+        if self.codex_api {
+            config
+                .provider_sources
+                .insert("codex".to_string(), SourceMode::Api);
+        }
+        for arg in &self.source {
+            match &arg.provider {
+                Some(p) => {
+                    config.provider_sources.insert(p.clone(), arg.mode);
+                }
+                None => config.source = arg.mode,
+            }
         }
+        config
+            .gemini_request_caps
+            .extend(self.gemini_cap.iter().cloned());
+        config
     }
 }
 
-fn run_doctor() {
+fn run_doctor(json: bool) {
     let mut all_ok = true;
+    let mut providers = serde_json::Map::new();
 
-    // Check providers
-    for (cmd, name) in [
-        ("claude", "Claude Code"),
-        ("codex", "Codex"),
-        ("gemini", "Gemini CLI"),
+    // Check providers; the ones added later than the original three are
+    // reported but not required.
+    for (provider, cmd, name, required) in [
+        ("claude", "claude", "Claude Code", true),
+        ("codex", "codex", "Codex", true),
+        ("gemini", "gemini", "Gemini CLI", true),
+        ("copilot", "copilot", "GitHub Copilot CLI", false),
+        ("cursor", "cursor-agent", "Cursor Agent", false),
+        ("qwen", "qwen", "Qwen Code", false),
+        ("amazonq", "q", "Amazon Q Developer CLI", false),
+        ("aider", "aider", "Aider", false),
     ] {
-        match Command::new(cmd).arg("--version").output() {
-            Ok(output) if output.status.success() => {
-                let version = String::from_utf8_lossy(&output.stdout);
-                println!("  {}: {}", name, version.trim());
-            }
-            Ok(_) => {
-                println!("  {}: installed (unknown version)", name);
-            }
-            Err(_) => {
-                println!("  {}: not found", name);
-                all_ok = false;
-            }
+        let (installed, version) = match Command::new(cmd).arg("--version").output() {
+            Ok(output) if output.status.success() => (
+                true,
+                Some(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+            ),
+            Ok(_) => (true, None),
+            Err(_) => (false, None),
+        };
+        all_ok &= installed || !required;
+        if json {
+            providers.insert(
+                provider.into(),
+                serde_json::json!({"installed": installed, "version": version}),
+            );
+            continue;
+        }
+        match (installed, version) {
+            (true, Some(version)) => println!("  {}: {}", name, version),
+            (true, None) => println!("  {}: installed (unknown version)", name),
+            (false, _) if required => println!("  {}: not found", name),
+            (false, _) => println!("  {}: not found (optional)", name),
+        }
+    }
+
+    if json {
+        let doc = serde_json::json!({
+            "success": all_ok,
+            "providers": providers,
+            "environment": Environment::capture(),
+        });
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+        if !all_ok {
+            std::process::exit(1);
         }
+        return;
+    }
+
+    println!("\nEnvironment:");
+    for (label, value) in Environment::capture().rows() {
+        println!("  {}: {}", label, value);
     }
 
     if all_ok {
@@ -128,55 +940,632 @@ fn run_doctor() {
     }
 }
 
-struct Spinner {
-    stop: Arc<AtomicBool>,
-    handle: Option<std::thread::JoinHandle<()>>,
+fn run_list(json: bool) {
+    let providers = agentusage::list_providers();
+    if json {
+        let doc = serde_json::json!({ "providers": providers });
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+        return;
+    }
+    let mut table = Table::new();
+    table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
+    table.set_header(vec!["Provider", "CLI", "Version", "Path", "Supported"]);
+    for info in &providers {
+        let version = match (&info.version, info.installed) {
+            (Some(version), _) => version.clone(),
+            (None, true) => "unknown".into(),
+            (None, false) => "not installed".into(),
+        };
+        table.add_row(vec![
+            info.display_name.to_string(),
+            info.binary.to_string(),
+            version,
+            info.path.clone().unwrap_or_default(),
+            if info.supported { "yes" } else { "no" }.to_string(),
+        ]);
+    }
+    println!("{}", table);
 }
 
-impl Spinner {
-    fn start(message: &str) -> Self {
-        let stop = Arc::new(AtomicBool::new(false));
-        let stop_clone = stop.clone();
-        let msg = message.to_string();
+#[cfg(feature = "chart")]
+fn run_chart(since: chrono::Duration, output: &std::path::Path, provider: Option<&str>) {
+    let Some(path) = history::default_path() else {
+        eprintln!("Error: no history file; set HOME or AGENTUSAGE_HISTORY");
+        std::process::exit(1);
+    };
+    let records = match history::load(&path, Some(chrono::Utc::now() - since)) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let records: Vec<_> = records
+        .into_iter()
+        .filter(|r| provider.is_none_or(|p| r.provider == p))
+        .collect();
+    if records.is_empty() {
+        eprintln!(
+            "Error: no usage history in {} for that period; run checks with --record first",
+            path.display()
+        );
+        std::process::exit(1);
+    }
+    if let Err(e) = agentusage::chart::render(&records, output) {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
 
-        let handle = std::thread::spawn(move || {
-            let frames = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
-            let mut i = 0;
-            let mut stderr = std::io::stderr();
-            while !stop_clone.load(Ordering::Relaxed) {
-                let _ = write!(stderr, "\r{} {}", frames[i % frames.len()], msg);
-                let _ = stderr.flush();
-                std::thread::sleep(Duration::from_millis(80));
-                i += 1;
-            }
-            // Clear the spinner line
-            let _ = write!(stderr, "\r{}\r", " ".repeat(msg.len() + 4));
-            let _ = stderr.flush();
-        });
+fn run_report(
+    since: chrono::Duration,
+    provider: Option<&str>,
+    heatmap: bool,
+    json: bool,
+    thresholds: &Thresholds,
+) {
+    let Some(path) = history::default_path() else {
+        eprintln!("Error: no history file; set HOME or AGENTUSAGE_HISTORY");
+        std::process::exit(1);
+    };
+    let to = chrono::Utc::now();
+    let from = to - since;
+    let records = match history::load(&path, Some(from)) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let records: Vec<_> = records
+        .into_iter()
+        .filter(|r| provider.is_none_or(|p| r.provider == p))
+        .collect();
+    if heatmap {
+        let map = agentusage::report::heatmap(&records, from, to, &chrono::Local);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&map).unwrap());
+        } else {
+            print!("{}", agentusage::report::heatmap_to_text(&map));
+        }
+        return;
+    }
+    let report = agentusage::report::summarize(&records, from, to, thresholds);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print!("{}", agentusage::report::to_markdown(&report));
+    }
+}
 
-        Spinner {
-            stop,
-            handle: Some(handle),
+fn run_cache(action: &CacheAction, json: bool) {
+    match action {
+        CacheAction::Show { provider } => {
+            let cached: Vec<_> = cache::list()
+                .into_iter()
+                .filter(|c| provider.as_ref().is_none_or(|p| &c.data.provider == p))
+                .collect();
+            if json {
+                let results: serde_json::Map<_, _> = cached
+                    .iter()
+                    .map(|c| {
+                        let mut doc = build_provider_json(&c.data);
+                        doc["checked_at"] = serde_json::json!(c.checked_at);
+                        (c.data.provider.clone(), doc)
+                    })
+                    .collect();
+                let doc = serde_json::json!({ "results": results });
+                println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+                return;
+            }
+            if cached.is_empty() {
+                println!("No cached results");
+                return;
+            }
+            let now = chrono::Utc::now();
+            let mut table = Table::new();
+            table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
+            table.set_header(vec!["Provider", "Checked", "Age", "Limits"]);
+            for c in &cached {
+                let limits: Vec<String> = c
+                    .data
+                    .entries
+                    .iter()
+                    .map(|e| format!("{} {}% used", e.label, e.percent_used))
+                    .collect();
+                table.add_row(vec![
+                    provider_label(&c.data.provider).to_string(),
+                    c.checked_at
+                        .with_timezone(&chrono::Local)
+                        .format("%b %-d %H:%M")
+                        .to_string(),
+                    format_age(now - c.checked_at),
+                    limits.join("\n"),
+                ]);
+            }
+            println!("{}", table);
+        }
+        CacheAction::Clear { provider } => match cache::clear(provider.as_deref()) {
+            Ok(cleared) if json => {
+                println!("{}", serde_json::json!({ "cleared": cleared }));
+            }
+            Ok(cleared) if cleared.is_empty() => println!("No cached results to clear"),
+            Ok(cleared) => println!("Cleared cached results for {}", cleared.join(", ")),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        },
+        CacheAction::Path => {
+            let Some(dir) = cache::cache_dir() else {
+                eprintln!("Error: no cache directory; set HOME or XDG_CACHE_HOME");
+                std::process::exit(1);
+            };
+            if json {
+                println!("{}", serde_json::json!({ "path": dir }));
+            } else {
+                println!("{}", dir.display());
+            }
+        }
+        CacheAction::Ttl { min_interval } => {
+            let freshness = cache::freshness(
+                &agentusage::ALL_PROVIDERS,
+                min_interval.map(Duration::from_secs),
+            );
+            if json {
+                let doc = serde_json::json!({ "min_interval_secs": min_interval, "providers": freshness });
+                println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+                return;
+            }
+            let now = chrono::Utc::now();
+            let age = |at: Option<chrono::DateTime<chrono::Utc>>| {
+                at.map_or("-".to_string(), |at| {
+                    format!("{} ago", format_age(now - at))
+                })
+            };
+            let mut table = Table::new();
+            table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
+            let mut header = vec!["Provider", "Last launch", "Cached"];
+            if min_interval.is_some() {
+                header.push("Next launch");
+            }
+            table.set_header(header);
+            for f in &freshness {
+                let mut row = vec![
+                    provider_label(&f.provider).to_string(),
+                    age(f.last_launch),
+                    age(f.checked_at),
+                ];
+                if let Some(secs) = f.serve_for_secs {
+                    row.push(match secs {
+                        0 => "next check".to_string(),
+                        secs => {
+                            format!("in {}", format_age(chrono::Duration::seconds(secs as i64)))
+                        }
+                    });
+                }
+                table.add_row(row);
+            }
+            println!("{}", table);
         }
     }
 }
 
-impl Drop for Spinner {
-    fn drop(&mut self) {
-        self.stop.store(true, Ordering::Relaxed);
-        if let Some(h) = self.handle.take() {
-            h.join().ok();
+fn run_capture(config: &UsageConfig, provider: &str, output: Option<&std::path::Path>) {
+    let screen = match agentusage::capture_usage_screen(config, provider) {
+        Ok(screen) => screen,
+        Err(e) => {
+            let msg = format!("{:#}", e);
+            eprintln!("Error: {}", strip_error_tags(&msg));
+            std::process::exit(exit_code_from_error(&msg));
         }
+    };
+    let Some(path) = output else {
+        print!("{}", screen);
+        return;
+    };
+    if let Err(e) = std::fs::write(path, &screen) {
+        eprintln!("Error: failed to write {}: {}", path.display(), e);
+        std::process::exit(1);
     }
+    eprintln!(
+        "Saved {} lines to {}. Please check it for personal details before sharing.",
+        screen.lines().count(),
+        path.display()
+    );
 }
 
-// ── Multi-provider progress display ──────────────────────────────
+/// "45s", "12m", "3h 5m", or "2d 4h".
+fn format_age(age: chrono::Duration) -> String {
+    let secs = age.num_seconds().max(0);
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+fn run_parse(provider: Option<&str>, file: &std::path::Path, json: bool) {
+    let text = if file == std::path::Path::new("-") {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(file)
+    };
+    let text = match text {
+        Ok(text) => strip_ansi_escapes::strip_str(&text),
+        Err(e) => {
+            eprintln!("Error: failed to read {}: {}", file.display(), e);
+            std::process::exit(1);
+        }
+    };
+    // Without --provider, the parser that finds the most rows wins.
+    let tried: Vec<(&str, usize)> = match provider {
+        Some(provider) => vec![(provider, 0)],
+        None => ALL_PROVIDERS
+            .iter()
+            .map(|&p| {
+                let found = parser::parse_output(p, &text).map_or(0, |d| d.entries.len());
+                (p, found)
+            })
+            .collect(),
+    };
+    let best = tried
+        .iter()
+        .fold(tried[0], |best, &p| if p.1 > best.1 { p } else { best })
+        .0;
+    let (result, lines) = match (
+        parser::parse_output(best, &text),
+        parser::explain(best, &text),
+    ) {
+        (Ok(data), Ok(lines)) => (data, lines),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if json {
+        let mut doc = serde_json::json!({
+            "provider": best,
+            "success": !result.entries.is_empty(),
+            "results": build_provider_json(&result),
+            "lines": lines,
+        });
+        if provider.is_none() {
+            let counts: serde_json::Map<_, _> = tried
+                .iter()
+                .map(|(p, n)| (p.to_string(), serde_json::json!(n)))
+                .collect();
+            doc["tried"] = counts.into();
+        }
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+    } else {
+        if provider.is_none() {
+            let counts: Vec<String> = tried.iter().map(|(p, n)| format!("{} {}", p, n)).collect();
+            println!("Rows found by each parser: {}", counts.join(", "));
+        }
+        println!(
+            "Parsed {} {} as {}",
+            result.entries.len(),
+            if result.entries.len() == 1 {
+                "entry"
+            } else {
+                "entries"
+            },
+            best
+        );
+        for entry in &result.entries {
+            let reset = match entry.reset_minutes {
+                Some(minutes) => format!("{} ({} min)", entry.reset_info, minutes),
+                None if entry.reset_info.is_empty() => "no reset".to_string(),
+                None => format!("{} (not understood)", entry.reset_info),
+            };
+            println!("  {}: {}% used, {}", entry.label, entry.percent_used, reset);
+        }
+        println!("\nLines:");
+        for line in &lines {
+            let matched = if line.matched.is_empty() {
+                "-".to_string()
+            } else {
+                line.matched.join(",")
+            };
+            println!("{:>4}  {:<28} {}", line.line, matched, line.text);
+        }
+    }
+    if result.entries.is_empty() {
+        std::process::exit(4);
+    }
+}
+
+fn run_self_test(json: bool) {
+    let results = agentusage::selftest::run_self_test();
+    let passed = results.iter().filter(|r| r.passed()).count();
+    if json {
+        let checks: Vec<_> = results
+            .iter()
+            .map(
+                |r| serde_json::json!({"name": r.name, "passed": r.passed(), "failure": r.failure}),
+            )
+            .collect();
+        let doc = serde_json::json!({
+            "success": passed == results.len(),
+            "passed": passed,
+            "total": results.len(),
+            "checks": checks,
+        });
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+        if passed != results.len() {
+            std::process::exit(1);
+        }
+        return;
+    }
+    for result in &results {
+        match &result.failure {
+            None => println!("  PASS  {}", result.name),
+            Some(failure) => println!("  FAIL  {}: {}", result.name, failure),
+        }
+    }
+    println!("\n{}/{} checks passed.", passed, results.len());
+    if passed != results.len() {
+        std::process::exit(1);
+    }
+}
+
+/// Per-provider benchmark summary. Failed runs count toward `failures` but
+/// are left out of the time-to-data statistics so a timeout doesn't skew them.
+struct BenchReport {
+    provider: &'static str,
+    iterations: u32,
+    failures: u32,
+    time_to_prompt: Option<BenchStats>,
+    time_to_data: Option<BenchStats>,
+    errors: Vec<String>,
+}
+
+fn bench_report(provider: &'static str, samples: &[BenchSample]) -> BenchReport {
+    let prompts: Vec<Duration> = samples.iter().filter_map(|s| s.to_prompt).collect();
+    let data: Vec<Duration> = samples
+        .iter()
+        .filter(|s| s.error.is_none())
+        .map(|s| s.to_data)
+        .collect();
+    let errors: Vec<String> = samples.iter().filter_map(|s| s.error.clone()).collect();
+    BenchReport {
+        provider,
+        iterations: samples.len() as u32,
+        failures: errors.len() as u32,
+        time_to_prompt: BenchStats::from_samples(&prompts),
+        time_to_data: BenchStats::from_samples(&data),
+        errors,
+    }
+}
+
+fn bench_stats_json(stats: Option<BenchStats>) -> serde_json::Value {
+    let ms = |d: Duration| d.as_millis() as u64;
+    match stats {
+        Some(s) => serde_json::json!({
+            "min": ms(s.min),
+            "median": ms(s.median),
+            "mean": ms(s.mean),
+            "max": ms(s.max),
+        }),
+        None => serde_json::Value::Null,
+    }
+}
+
+fn bench_report_json(report: &BenchReport) -> serde_json::Value {
+    serde_json::json!({
+        "provider": report.provider,
+        "iterations": report.iterations,
+        "failures": report.failures,
+        "time_to_prompt_ms": bench_stats_json(report.time_to_prompt),
+        "time_to_data_ms": bench_stats_json(report.time_to_data),
+        "errors": report.errors,
+    })
+}
+
+fn format_secs(d: Duration) -> String {
+    format!("{:.2}s", d.as_secs_f64())
+}
+
+fn run_bench(cli: &Cli, config: &UsageConfig) {
+    let mut reports = Vec::new();
+    for provider in cli.providers() {
+        if !cli.json {
+            eprintln!(
+                "Benchmarking {} ({} runs)...",
+                provider_label(provider),
+                cli.iterations
+            );
+        }
+        let samples = agentusage::bench::bench_provider(config, provider, cli.iterations);
+        reports.push(bench_report(provider, &samples));
+    }
+
+    if cli.json {
+        let wrapper = serde_json::json!({
+            "bench": {
+                "iterations": cli.iterations,
+                "providers": reports.iter().map(bench_report_json).collect::<Vec<_>>(),
+            }
+        });
+        println!("{}", serde_json::to_string_pretty(&wrapper).unwrap());
+    } else {
+        let mut table = Table::new();
+        table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
+        table.set_header(vec![
+            "Provider", "Metric", "Min", "Median", "Mean", "Max", "Failed",
+        ]);
+        for report in &reports {
+            for (metric, stats) in [
+                ("To prompt", report.time_to_prompt),
+                ("To data", report.time_to_data),
+            ] {
+                let mut row = vec![
+                    provider_label(report.provider).to_string(),
+                    metric.to_string(),
+                ];
+                match stats {
+                    Some(s) => row.extend([s.min, s.median, s.mean, s.max].map(format_secs)),
+                    None => row.extend(["-"; 4].map(String::from)),
+                }
+                row.push(format!("{}/{}", report.failures, report.iterations));
+                table.add_row(row);
+            }
+        }
+        println!("{}", table);
+        for report in &reports {
+            for error in &report.errors {
+                eprintln!("{}: {}", provider_label(report.provider), error);
+            }
+        }
+    }
+
+    if reports.iter().any(|r| r.failures == r.iterations) {
+        std::process::exit(1);
+    }
+}
+
+fn dry_run_plans(cli: &Cli) -> Vec<CheckPlan> {
+    let config = cli.to_config();
+    cli.providers()
+        .into_iter()
+        .filter_map(|provider| agentusage::plan(&config, provider))
+        .collect()
+}
+
+fn print_dry_run(cli: &Cli) {
+    let plans = dry_run_plans(cli);
+    if cli.json {
+        let wrapper = serde_json::json!({
+            "dry_run": true,
+            "providers": plans,
+            "max_total_time_secs": cli.max_total_time,
+            "interactive": cli.interactive,
+        });
+        println!("{}", serde_json::to_string_pretty(&wrapper).unwrap());
+        return;
+    }
+
+    for plan in &plans {
+        println!("{}", plan.provider);
+        let location = plan
+            .binary_path
+            .clone()
+            .unwrap_or_else(|| "not found on PATH".into());
+        println!("  binary:          {} ({})", plan.binary, location);
+        let args: Vec<String> = plan.args.iter().map(|a| shell_quote(a)).collect();
+        println!("  args:            {}", args.join(" "));
+        match plan.fallback_command {
+            Some(fallback) => println!(
+                "  command:         {} (fallback: {})",
+                plan.command, fallback
+            ),
+            None => println!("  command:         {}", plan.command),
+        }
+        println!(
+            "  directory:       {}",
+            plan.directory.as_deref().unwrap_or("(current)")
+        );
+        println!("  approval policy: {}", plan.approval_policy);
+        println!(
+            "  timeouts:        prompt {}s, data {}s, poll {}ms",
+            plan.prompt_timeout_secs, plan.data_timeout_secs, plan.poll_interval_ms
+        );
+        println!();
+    }
+    if let Some(secs) = cli.max_total_time {
+        println!("Overall deadline: {}s", secs);
+    }
+    println!("Dry run: nothing was launched.");
+}
+
+/// Quote an argument for display when it would be ambiguous unquoted.
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        arg.to_string()
+    } else {
+        format!("{:?}", arg)
+    }
+}
+
+struct Spinner {
+    stop: Arc<AtomicBool>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Spinner {
+    fn start(message: &str) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = stop.clone();
+        let msg = message.to_string();
+
+        let handle = std::thread::spawn(move || {
+            let frames = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+            let mut i = 0;
+            let mut stderr = std::io::stderr();
+            while !stop_clone.load(Ordering::Relaxed) {
+                let _ = write!(stderr, "\r{} {}", frames[i % frames.len()], msg);
+                let _ = stderr.flush();
+                std::thread::sleep(Duration::from_millis(80));
+                i += 1;
+            }
+            // Clear the spinner line
+            let _ = write!(stderr, "\r{}\r", " ".repeat(msg.len() + 4));
+            let _ = stderr.flush();
+        });
+
+        Spinner {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(h) = self.handle.take() {
+            h.join().ok();
+        }
+    }
+}
+
+// ── Multi-provider progress display ──────────────────────────────
 
 #[derive(Clone, Copy, PartialEq)]
 enum ProviderStatus {
-    Waiting,
-    Done,
-    Failed,
+    Running(CheckPhase),
+    Done(Duration),
+    Failed(Duration),
+}
+
+/// Render one provider's status line (without the spinner frame for finished checks).
+fn status_line(name: &str, status: ProviderStatus, frame: char, elapsed: Duration) -> String {
+    match status {
+        ProviderStatus::Running(phase) => format!(
+            "{} {}: {} ({:.0}s)",
+            frame,
+            name,
+            phase.as_str(),
+            elapsed.as_secs_f64()
+        ),
+        ProviderStatus::Done(took) => {
+            format!(
+                "\x1b[32m✓\x1b[0m {}: done ({:.1}s)",
+                name,
+                took.as_secs_f64()
+            )
+        }
+        ProviderStatus::Failed(took) => {
+            format!(
+                "\x1b[33m✗\x1b[0m {}: failed ({:.1}s)",
+                name,
+                took.as_secs_f64()
+            )
+        }
+    }
 }
 
 struct MultiSpinner {
@@ -185,10 +1574,10 @@ struct MultiSpinner {
 }
 
 impl MultiSpinner {
-    fn start(names: &[&str], states: Arc<Mutex<Vec<ProviderStatus>>>) -> Self {
+    fn start(names: &[String], states: Arc<Mutex<Vec<ProviderStatus>>>, started: Instant) -> Self {
         let stop = Arc::new(AtomicBool::new(false));
         let stop_clone = stop.clone();
-        let names: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+        let names = names.to_vec();
 
         let handle = std::thread::spawn(move || {
             let frames = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
@@ -207,18 +1596,9 @@ impl MultiSpinner {
                 let st = states.lock().unwrap();
                 for (j, name) in names.iter().enumerate() {
                     let _ = write!(stderr, "\r\x1b[2K");
-                    match st[j] {
-                        ProviderStatus::Waiting => {
-                            let _ =
-                                write!(stderr, "{} Checking {}...", frames[i % frames.len()], name);
-                        }
-                        ProviderStatus::Done => {
-                            let _ = write!(stderr, "\x1b[32m✓\x1b[0m {}", name);
-                        }
-                        ProviderStatus::Failed => {
-                            let _ = write!(stderr, "\x1b[33m✗\x1b[0m {}", name);
-                        }
-                    }
+                    let line =
+                        status_line(name, st[j], frames[i % frames.len()], started.elapsed());
+                    let _ = write!(stderr, "{}", line);
                     if j < n - 1 {
                         let _ = writeln!(stderr);
                     }
@@ -269,74 +1649,354 @@ impl Drop for MultiSpinner {
     }
 }
 
+/// Run all providers in parallel, calling `on_finish` as soon as each
+/// provider completes.
+fn run_all_tracked<F>(config: &UsageConfig, on_finish: F) -> AllResults
+where
+    F: Fn(&str, &Result<UsageData>, Duration),
+{
+    let providers = config.providers.names();
+    let stream = run_streaming(config, &providers).inspect(|item| {
+        on_finish(&item.provider, &item.result, item.elapsed);
+    });
+    collect_results(&providers, stream)
+}
+
+/// One `--stream` line: a provider's result or error, as soon as it is known.
+fn stream_line_json(item: &ProviderResult, providers: &ProviderRegistry) -> serde_json::Value {
+    match &item.result {
+        Ok(data) => serde_json::json!({
+            "provider": item.provider,
+            "success": true,
+            "result": build_provider_json(data),
+            "check": data.check,
+            "capabilities": data.capabilities(providers),
+        }),
+        Err(e) => {
+            let mut line = serde_json::json!({
+                "provider": item.provider,
+                "success": false,
+                "error": strip_error_tags(&format!("{:#}", e)),
+            });
+            if let Some(failure) = e.downcast_ref::<CheckFailure>() {
+                line["attempts"] = serde_json::json!(failure.attempts);
+            }
+            line
+        }
+    }
+}
+
+/// Print each provider's result as one JSON line the moment it finishes.
+/// Exits 1 when every provider failed.
+fn run_stream(
+    cli: &Cli,
+    config: &UsageConfig,
+    progress: Option<&NdjsonProgress>,
+    thresholds: &Thresholds,
+) {
+    let providers = match cli.selected_provider() {
+        Some(provider) => vec![provider],
+        None => config.providers.names(),
+    };
+    let mut results = Vec::new();
+    let mut failures = BTreeMap::new();
+    for item in run_streaming(config, &providers) {
+        if let Some(progress) = progress {
+            progress.emit_finish(&item.provider, &item.result, item.elapsed);
+        }
+        let mut line = stream_line_json(&item, &config.providers);
+        if item.result.is_err() {
+            if let Some(stale) = stale_results(cli, [item.provider.as_str()]).pop() {
+                line["result"] = build_provider_json(&stale);
+            }
+        }
+        println!("{}", line);
+        let _ = std::io::stdout().flush();
+        match item.result {
+            Ok(data) => results.push(data),
+            Err(e) => {
+                failures.insert(item.provider, format!("{:#}", e));
+            }
+        }
+    }
+    record_history(cli, &results);
+    send_notifications(cli, &results, &failures, thresholds);
+    if results.is_empty() {
+        std::process::exit(1);
+    }
+    exit_if_over_threshold(cli, &results, thresholds);
+}
+
+/// Writes warnings, errors, and verbose output to stderr, as text or as one
+/// JSON object per line, so stdout only ever carries the result document.
+#[derive(Debug, Clone, Copy)]
+struct Diagnostics(DiagnosticsMode);
+
+impl Diagnostics {
+    fn emit(&self, level: &str, provider: Option<&str>, text: String, extra: serde_json::Value) {
+        match self.0 {
+            DiagnosticsMode::Text => {
+                let prefix = match (level, provider) {
+                    ("verbose", Some(p)) => format!("[verbose] {}: ", p),
+                    ("verbose", None) => "[verbose] ".to_string(),
+                    ("warning", Some(p)) => format!("Warning ({}): ", p),
+                    ("warning", None) => "Warning: ".to_string(),
+                    _ => "Error: ".to_string(),
+                };
+                eprintln!("{}{}", prefix, text);
+            }
+            DiagnosticsMode::Json => {
+                eprintln!("{}", diagnostic_json(level, provider, &text, extra))
+            }
+        }
+    }
+
+    fn verbose(&self, provider: Option<&str>, message: String) {
+        self.emit("verbose", provider, message, serde_json::Value::Null);
+    }
+
+    fn warning(&self, provider: &str, message: &str) {
+        self.emit(
+            "warning",
+            Some(provider),
+            strip_error_tags(message),
+            serde_json::Value::Null,
+        );
+    }
+
+    fn error(&self, message: &str) {
+        self.emit(
+            "error",
+            None,
+            strip_error_tags(message),
+            serde_json::Value::Null,
+        );
+    }
+}
+
+fn diagnostic_json(
+    level: &str,
+    provider: Option<&str>,
+    message: &str,
+    extra: serde_json::Value,
+) -> serde_json::Value {
+    let mut obj = serde_json::json!({"level": level, "message": message});
+    if let Some(provider) = provider {
+        obj["provider"] = serde_json::json!(provider);
+    }
+    if let serde_json::Value::Object(fields) = extra {
+        for (key, value) in fields {
+            obj[key] = value;
+        }
+    }
+    obj
+}
+
+/// `--verbose` diagnostics on stderr.
+struct VerboseObserver(Diagnostics);
+
+impl CheckObserver for VerboseObserver {
+    fn on_phase(&self, provider: &str, phase: CheckPhase) {
+        self.0.verbose(Some(provider), phase.as_str().to_string());
+    }
+
+    fn on_dialog(&self, provider: &str, kind: &DialogKind, dismissed: bool) {
+        let action = if dismissed { "dismissed" } else { "detected" };
+        self.0
+            .verbose(Some(provider), format!("dialog {}: {:?}", action, kind));
+    }
+
+    fn on_retry(&self, provider: &str, reason: &'static str, fallback: bool) {
+        let kind = if fallback { "fallback" } else { "retry" };
+        self.0
+            .verbose(Some(provider), format!("{} ({})", kind, reason));
+    }
+
+    fn on_capture(&self, provider: &str, label: &str, text: &str) {
+        match self.0 .0 {
+            DiagnosticsMode::Text => self
+                .0
+                .verbose(Some(provider), format!("{} screen:\n{}", label, text)),
+            DiagnosticsMode::Json => self.0.emit(
+                "verbose",
+                Some(provider),
+                format!("{} screen", label),
+                serde_json::json!({"screen": label, "text": text}),
+            ),
+        }
+    }
+
+    fn on_warning(&self, provider: &str, message: &str) {
+        self.0.verbose(Some(provider), message.to_string());
+    }
+
+    fn on_wait(&self, provider: &str, wait: &WaitStats) {
+        let elapsed = wait.elapsed.as_secs_f64();
+        self.0.emit(
+            "verbose",
+            Some(provider),
+            format!(
+                "wait_for {} after {:.1}s: {} polls, {} parsed, capture {:016x}",
+                wait.outcome, elapsed, wait.polls, wait.parses, wait.capture_hash
+            ),
+            serde_json::json!({
+                "wait": wait.outcome,
+                "elapsed_secs": elapsed,
+                "polls": wait.polls,
+                "parses": wait.parses,
+            }),
+        );
+    }
+}
+
 /// Run all providers in parallel with per-provider progress display.
 fn run_all_with_progress(config: &UsageConfig) -> AllResults {
-    let names = ["claude", "codex", "gemini"];
-    let states = Arc::new(Mutex::new(vec![ProviderStatus::Waiting; 3]));
-    let spinner = MultiSpinner::start(&names, states.clone());
+    let names: Vec<String> = config
+        .providers
+        .names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+    let started = Instant::now();
+    let states = Arc::new(Mutex::new(vec![
+        ProviderStatus::Running(
+            CheckPhase::Launching
+        );
+        names.len()
+    ]));
 
-    let mut results = Vec::new();
-    let mut warnings = BTreeMap::new();
-
-    std::thread::scope(|s| {
-        let st0 = states.clone();
-        let h0 = s.spawn(move || {
-            let r = run_claude(config);
-            st0.lock().unwrap()[0] = if r.is_ok() {
-                ProviderStatus::Done
-            } else {
-                ProviderStatus::Failed
-            };
-            r
-        });
+    let mut config = config.clone();
+    let phase_states = states.clone();
+    let phase_names = names.clone();
+    config
+        .observers
+        .push(Arc::new(move |provider: &str, event: &CheckEvent| {
+            if let CheckEvent::Phase(phase) = event {
+                if let Some(idx) = phase_names.iter().position(|n| n == provider) {
+                    phase_states.lock().unwrap()[idx] = ProviderStatus::Running(*phase);
+                }
+            }
+        }));
 
-        let st1 = states.clone();
-        let h1 = s.spawn(move || {
-            let r = run_codex(config);
-            st1.lock().unwrap()[1] = if r.is_ok() {
-                ProviderStatus::Done
-            } else {
-                ProviderStatus::Failed
-            };
-            r
-        });
+    let spinner = MultiSpinner::start(&names, states.clone(), started);
 
-        let st2 = states.clone();
-        let h2 = s.spawn(move || {
-            let r = run_gemini(config);
-            st2.lock().unwrap()[2] = if r.is_ok() {
-                ProviderStatus::Done
+    let all = run_all_tracked(&config, |provider, result, took| {
+        if let Some(idx) = names.iter().position(|n| n == provider) {
+            states.lock().unwrap()[idx] = if result.is_ok() {
+                ProviderStatus::Done(took)
             } else {
-                ProviderStatus::Failed
+                ProviderStatus::Failed(took)
             };
-            r
-        });
+        }
+    });
 
-        for (name, handle) in [("claude", h0), ("codex", h1), ("gemini", h2)] {
-            match handle.join() {
-                Ok(Ok(data)) => results.push(data),
-                Ok(Err(e)) => {
-                    warnings.insert(name.into(), format!("{:#}", e));
-                }
-                Err(_) => {
-                    warnings.insert(name.into(), "Provider thread panicked".into());
+    drop(spinner);
+
+    all
+}
+
+// ── NDJSON progress events ───────────────────────────────────────
+
+/// Line-oriented JSON event writer shared by all provider threads.
+#[derive(Clone)]
+struct NdjsonProgress {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+    started: Instant,
+}
+
+impl NdjsonProgress {
+    /// Write to stderr, or to a duplicate of `fd` when given.
+    fn open(fd: Option<i32>) -> Result<Self> {
+        let out: Box<dyn Write + Send> = match fd {
+            Some(fd) => {
+                use std::os::fd::FromRawFd;
+                // SAFETY: dup() on a caller-supplied descriptor; ownership of
+                // the duplicate moves into File so the original stays open.
+                let dup = unsafe { libc::dup(fd) };
+                if dup < 0 {
+                    anyhow::bail!("--progress-fd {}: {}", fd, std::io::Error::last_os_error());
                 }
+                // SAFETY: `dup` is a fresh, valid descriptor owned by nobody else.
+                Box::new(unsafe { std::fs::File::from_raw_fd(dup) })
             }
+            None => Box::new(std::io::stderr()),
+        };
+        Ok(Self {
+            out: Arc::new(Mutex::new(out)),
+            started: Instant::now(),
+        })
+    }
+
+    fn emit(&self, provider: &str, mut fields: serde_json::Value) {
+        fields["ts"] = serde_json::json!(chrono::Utc::now().to_rfc3339());
+        fields["elapsed_ms"] = serde_json::json!(self.started.elapsed().as_millis() as u64);
+        fields["provider"] = serde_json::json!(provider);
+        if let Ok(mut out) = self.out.lock() {
+            let _ = writeln!(out, "{}", fields);
+            let _ = out.flush();
         }
-    });
+    }
 
-    drop(spinner);
+    fn observer(&self) -> Arc<dyn CheckObserver> {
+        let sink = self.clone();
+        Arc::new(move |provider: &str, event: &CheckEvent| {
+            sink.emit(provider, check_event_json(event));
+        })
+    }
+
+    fn emit_finish(&self, provider: &str, result: &Result<UsageData>, took: Duration) {
+        self.emit(provider, finish_event_json(result, took));
+    }
+}
+
+fn check_event_json(event: &CheckEvent) -> serde_json::Value {
+    match event {
+        CheckEvent::Phase(phase) => serde_json::json!({"event": "phase", "phase": phase.id()}),
+        CheckEvent::DialogDetected(kind) => {
+            serde_json::json!({"event": "dialog_detected", "dialog": kind.id()})
+        }
+        CheckEvent::DialogDismissed(kind) => {
+            serde_json::json!({"event": "dialog_dismissed", "dialog": kind.id()})
+        }
+        CheckEvent::Retry(reason) => serde_json::json!({"event": "retry", "reason": reason}),
+        CheckEvent::Fallback(reason) => {
+            serde_json::json!({"event": "fallback", "reason": reason})
+        }
+    }
+}
 
-    AllResults { results, warnings }
+fn finish_event_json(result: &Result<UsageData>, took: Duration) -> serde_json::Value {
+    let duration_ms = took.as_millis() as u64;
+    match result {
+        Ok(data) => serde_json::json!({
+            "event": "done",
+            "duration_ms": duration_ms,
+            "entries": data.entries.len(),
+        }),
+        Err(e) => serde_json::json!({
+            "event": "failed",
+            "duration_ms": duration_ms,
+            "error": strip_error_tags(&format!("{:#}", e)),
+        }),
+    }
 }
 
-fn print_human(data: &UsageData) {
+fn print_human(data: &UsageData, numbers: &NumberFormat, thresholds: &Thresholds) {
     let title = match data.provider.as_str() {
         "codex" => "Codex Usage",
         "gemini" => "Gemini Usage",
+        "copilot" => "Copilot Usage",
+        "cursor" => "Cursor Usage",
+        "qwen" => "Qwen Usage",
+        "amazonq" => "Amazon Q Usage",
+        "aider" => "Aider Usage",
         _ => "Claude Code Usage",
     };
-    println!("{}", title);
+    if data.check.partial {
+        println!("{} (partial)", title);
+    } else {
+        println!("{}", title);
+    }
     let mut table = Table::new();
     table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
     table.set_header(vec![
@@ -349,47 +2009,94 @@ fn print_human(data: &UsageData) {
     ]);
 
     for entry in &data.entries {
-        let low = entry.percent_remaining < LOW_THRESHOLD;
+        let low = thresholds.is_over(entry);
         table.add_row(vec![
-            make_cell(entry.label.clone(), low),
-            make_cell(remaining_pct_cell(entry), low),
-            make_cell(reset_days_cell(entry), low),
-            make_cell(reset_minutes_cell(entry), low),
-            make_cell(reset_hours_cell(entry), low),
-            make_cell(spent_cell(entry), low),
+            make_cell(label_cell(entry), low),
+            make_cell(remaining_pct_cell(entry, numbers), low),
+            make_cell(reset_days_cell(entry, numbers), low),
+            make_cell(reset_minutes_cell(entry, numbers), low),
+            make_cell(reset_hours_cell(entry, numbers), low),
+            make_cell(spent_cell(entry, numbers), low),
         ]);
     }
 
     println!("{}", table);
+    if let Some(note) = extra_usage_note(data) {
+        println!("{}", note);
+    }
+    print_token_usage(data, numbers);
 }
 
-fn print_human_multi(results: &[UsageData]) {
+/// The per-model token counts read from a provider's transcripts, if any.
+fn print_token_usage(data: &UsageData, numbers: &NumberFormat) {
+    if data.tokens.is_empty() {
+        return;
+    }
     let mut table = Table::new();
     table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
     table.set_header(vec![
-        "Provider",
-        "Limit",
-        "Remaining",
-        "Days",
-        "Minutes",
-        "Hours",
-        "Spend",
+        "Model",
+        "Input",
+        "Output",
+        "Cache write",
+        "Cache read",
+        "Est. cost",
     ]);
-
-    let mut boundaries = Vec::new();
-    let mut row_count = 0usize;
+    for usage in &data.tokens {
+        let count = |tokens: u64| numbers.integer(tokens as i64);
+        table.add_row(vec![
+            usage.model.clone(),
+            count(usage.input_tokens),
+            count(usage.output_tokens),
+            count(usage.cache_creation_tokens),
+            count(usage.cache_read_tokens),
+            usage
+                .cost_usd
+                .map(|cost| format!("${}", numbers.decimal(cost, 2)))
+                .unwrap_or_default(),
+        ]);
+    }
+    println!("{} tokens today", provider_label(&data.provider));
+    println!("{}", table);
+}
+
+/// "Claude extra usage: on" under the table, when the screen said.
+fn extra_usage_note(data: &UsageData) -> Option<String> {
+    let enabled = data.extra_usage_enabled?;
+    Some(format!(
+        "{} extra usage: {}",
+        provider_label(&data.provider),
+        if enabled { "on" } else { "off" }
+    ))
+}
+
+fn print_human_multi(results: &[UsageData], numbers: &NumberFormat, thresholds: &Thresholds) {
+    let mut table = Table::new();
+    table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
+    table.set_header(vec![
+        "Provider",
+        "Limit",
+        "Remaining",
+        "Days",
+        "Minutes",
+        "Hours",
+        "Spend",
+    ]);
+
+    let mut boundaries = Vec::new();
+    let mut row_count = 0usize;
     for (idx, data) in results.iter().enumerate() {
         let mut added_for_provider = 0usize;
         for entry in &data.entries {
-            let low = entry.percent_remaining < LOW_THRESHOLD;
+            let low = thresholds.is_over(entry);
             table.add_row(vec![
                 make_cell(provider_label(&data.provider).to_string(), low),
-                make_cell(entry.label.clone(), low),
-                make_cell(remaining_pct_cell(entry), low),
-                make_cell(reset_days_cell(entry), low),
-                make_cell(reset_minutes_cell(entry), low),
-                make_cell(reset_hours_cell(entry), low),
-                make_cell(spent_cell(entry), low),
+                make_cell(label_cell(entry), low),
+                make_cell(remaining_pct_cell(entry, numbers), low),
+                make_cell(reset_days_cell(entry, numbers), low),
+                make_cell(reset_minutes_cell(entry, numbers), low),
+                make_cell(reset_hours_cell(entry, numbers), low),
+                make_cell(spent_cell(entry, numbers), low),
             ]);
             row_count += 1;
             added_for_provider += 1;
@@ -415,6 +2122,12 @@ fn print_human_multi(results: &[UsageData]) {
 
     println!("Usage");
     println!("{}", lines.join("\n"));
+    for note in results.iter().filter_map(extra_usage_note) {
+        println!("{}", note);
+    }
+    for data in results {
+        print_token_usage(data, numbers);
+    }
 }
 
 fn provider_label(provider: &str) -> &str {
@@ -422,11 +2135,549 @@ fn provider_label(provider: &str) -> &str {
         "claude" => "Claude",
         "codex" => "Codex",
         "gemini" => "Gemini",
+        "copilot" => "Copilot",
+        "cursor" => "Cursor",
+        "qwen" => "Qwen",
+        "amazonq" => "Amazon Q",
+        "aider" => "Aider",
         _ => provider,
     }
 }
 
-const LOW_THRESHOLD: u32 = 10;
+/// `--cleanup`: reap leftovers from crashed runs and say what was done.
+fn run_cleanup(json: bool) {
+    let reaped = agentusage::session::Session::kill_all_stale_sessions();
+    if json {
+        let doc = serde_json::json!({ "success": true, "cleaned": reaped });
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+        return;
+    }
+    if reaped.is_empty() {
+        println!("No leftover sessions found.");
+    }
+    for item in &reaped {
+        let action = match item.state {
+            LeftoverState::Killed => "Killed",
+            _ => "Removed stale record of",
+        };
+        println!(
+            "{} {} (process group {}, from agentusage pid {})",
+            action, item.record.binary, item.record.pgid, item.record.owner
+        );
+    }
+}
+
+/// Before checking, note (or, under `--reap-orphans`, kill) provider
+/// processes that crashed runs left running.
+fn sweep_leftovers(cli: &Cli) {
+    for item in registry::sweep(cli.reap_orphans) {
+        let record = &item.record;
+        let message = match item.state {
+            LeftoverState::Exited => continue,
+            LeftoverState::Killed => format!(
+                "killed process group {} left running by a crashed run (agentusage pid {})",
+                record.pgid, record.owner
+            ),
+            LeftoverState::Running => format!(
+                "process group {} was left running by a crashed run (agentusage pid {}); \
+                 --cleanup or --reap-orphans kills it",
+                record.pgid, record.owner
+            ),
+        };
+        cli.diagnostics().warning(&record.binary, &message);
+    }
+}
+
+/// Append `results` to the history file under `--record`, or as a named
+/// snapshot under `agentusage snapshot save`.
+fn record_history(cli: &Cli, results: &[UsageData]) {
+    let snapshot = cli.snapshot_name();
+    if !(cli.record || snapshot.is_some()) || results.is_empty() {
+        return;
+    }
+    let Some(path) = history::default_path() else {
+        cli.diagnostics().emit(
+            "warning",
+            None,
+            "not recording history: set HOME or AGENTUSAGE_HISTORY".into(),
+            serde_json::Value::Null,
+        );
+        return;
+    };
+    let now = chrono::Utc::now();
+    // Absolute, so `-C .` and `-C ~/client` from elsewhere land on one project.
+    let project = cli.directory.as_deref().map(|dir| {
+        std::fs::canonicalize(dir)
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| dir.to_string())
+    });
+    let records: Vec<_> = results
+        .iter()
+        .flat_map(|data| history::records_for(data, now, project.as_deref()))
+        .map(|record| history::HistoryRecord {
+            snapshot: snapshot.map(str::to_string),
+            ..record
+        })
+        .collect();
+    if let Err(e) = history::append(&path, &records) {
+        cli.diagnostics().emit(
+            "warning",
+            None,
+            format!("history: {:#}", e),
+            serde_json::Value::Null,
+        );
+        return;
+    }
+    if let Some(name) = snapshot {
+        if records.is_empty() {
+            cli.diagnostics().emit(
+                "warning",
+                None,
+                format!("snapshot '{}' not saved: no freshly checked limits", name),
+                serde_json::Value::Null,
+            );
+        } else {
+            eprintln!("Saved snapshot '{}' ({} limits)", name, records.len());
+        }
+    }
+}
+
+/// The snapshot `--compare-to` names, from the history file; exits when
+/// there is none.
+fn load_snapshot(name: &str) -> history::Snapshot {
+    let Some(path) = history::default_path() else {
+        eprintln!("Error: no history file; set HOME or AGENTUSAGE_HISTORY");
+        std::process::exit(1);
+    };
+    let records = match history::load(&path, None) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    history::snapshot(&records, name).unwrap_or_else(|| {
+        eprintln!(
+            "Error: no snapshot named '{}'; save one with `agentusage snapshot save {}`",
+            name, name
+        );
+        std::process::exit(1);
+    })
+}
+
+/// How each limit in `results` moved since `snapshot`, as a table or JSON.
+fn print_comparison(cli: &Cli, snapshot: &history::Snapshot, results: &[UsageData]) {
+    let now = chrono::Utc::now();
+    let current: Vec<_> = results
+        .iter()
+        .flat_map(|data| history::records_for(data, now, None))
+        .collect();
+    let changes = agentusage::report::compare(&snapshot.records, &current);
+    if cli.json {
+        let doc = serde_json::json!({
+            "success": true,
+            "snapshot": snapshot.name,
+            "taken_at": snapshot.taken_at,
+            "changes": changes,
+        });
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+        return;
+    }
+    println!(
+        "Usage since '{}' ({})",
+        snapshot.name,
+        snapshot
+            .taken_at
+            .with_timezone(&chrono::Local)
+            .format("%b %-d %H:%M")
+    );
+    if changes.is_empty() {
+        println!("No limits in common with the snapshot");
+    } else {
+        print_changes(&changes);
+    }
+}
+
+/// A table of how each limit moved: percent used then and now, and the
+/// points used in between.
+fn print_changes(changes: &[agentusage::report::LimitChange]) {
+    let mut table = Table::new();
+    table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
+    table.set_header(vec!["Provider", "Limit", "Then", "Now", "Used", "Spend"]);
+    for change in changes {
+        let used = if change.reset {
+            format!("+{} pts (reset)", change.used)
+        } else {
+            format!("+{} pts", change.used)
+        };
+        table.add_row(vec![
+            provider_label(&change.provider).to_string(),
+            change.label.clone(),
+            format!("{}%", change.before),
+            format!("{}%", change.after),
+            used,
+            change
+                .spend
+                .map(|spend| format!("+${:.2}", spend))
+                .unwrap_or_default(),
+        ]);
+    }
+    println!("{}", table);
+}
+
+/// One check of the selected provider, or of all of them, for `measure`.
+/// Failed providers are reported as warnings and left out.
+fn check_usage(cli: &Cli, config: &UsageConfig) -> Vec<UsageData> {
+    let all = match cli.selected_provider() {
+        Some(provider) => match run_provider(config, provider) {
+            Ok(data) => return vec![data],
+            Err(e) => {
+                cli.diagnostics().warning(provider, &format!("{:#}", e));
+                return Vec::new();
+            }
+        },
+        None => run_all(config),
+    };
+    for (provider, msg) in &all.warnings {
+        cli.diagnostics().warning(provider, msg);
+    }
+    all.results
+}
+
+/// `agentusage measure -- COMMAND`: check usage, run `command`, check
+/// again, and show how much of each limit the command used.  Exits with
+/// the command's status.
+fn run_measure(cli: &Cli, config: &UsageConfig, command: &[String]) {
+    let before = check_usage(cli, config);
+    if before.iter().all(|data| data.entries.is_empty()) {
+        cli.diagnostics()
+            .error("no usage to measure from; every provider failed before the command ran");
+        std::process::exit(1);
+    }
+    record_history(cli, &before);
+    let baseline: Vec<_> = before
+        .iter()
+        .flat_map(|data| history::records_for(data, chrono::Utc::now(), None))
+        .collect();
+
+    let started = chrono::Utc::now();
+    let mut child = Command::new(&command[0]);
+    child.args(&command[1..]);
+    // Keep stdout for the JSON document.
+    if cli.json {
+        child.stdout(std::io::stderr());
+    }
+    let status = match child.status() {
+        Ok(status) => status,
+        Err(e) => {
+            cli.diagnostics()
+                .error(&format!("failed to run {}: {}", command[0], e));
+            std::process::exit(127);
+        }
+    };
+    let finished = chrono::Utc::now();
+
+    let after = check_usage(cli, config);
+    record_history(cli, &after);
+    let current: Vec<_> = after
+        .iter()
+        .flat_map(|data| history::records_for(data, finished, None))
+        .collect();
+    let changes = agentusage::report::compare(&baseline, &current);
+    if cli.json {
+        let doc = serde_json::json!({
+            "success": !changes.is_empty(),
+            "command": command,
+            "exit_code": status.code(),
+            "started_at": started,
+            "finished_at": finished,
+            "changes": changes,
+        });
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+    } else {
+        println!(
+            "Usage during `{}` ({})",
+            command.join(" "),
+            format_age(finished - started)
+        );
+        if changes.is_empty() {
+            println!("No usage to compare; every provider failed after the command ran");
+        } else {
+            print_changes(&changes);
+        }
+    }
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    if changes.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// `agentusage snapshot list`.
+fn run_snapshot_list(json: bool) {
+    let Some(path) = history::default_path() else {
+        eprintln!("Error: no history file; set HOME or AGENTUSAGE_HISTORY");
+        std::process::exit(1);
+    };
+    let snapshots = match history::load(&path, None) {
+        Ok(records) => history::snapshots(&records),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    if json {
+        let doc: Vec<_> = snapshots
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "name": s.name,
+                    "taken_at": s.taken_at,
+                    "limits": s.records.len(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+        return;
+    }
+    if snapshots.is_empty() {
+        println!("No snapshots; save one with `agentusage snapshot save NAME`");
+        return;
+    }
+    let mut table = Table::new();
+    table.load_preset(ASCII_BORDERS_ONLY_CONDENSED);
+    table.set_header(vec!["Name", "Saved", "Limits"]);
+    for s in &snapshots {
+        table.add_row(vec![
+            s.name.clone(),
+            s.taken_at
+                .with_timezone(&chrono::Local)
+                .format("%b %-d %H:%M")
+                .to_string(),
+            s.records.len().to_string(),
+        ]);
+    }
+    println!("{}", table);
+}
+
+fn run_history(action: &HistoryAction, json: bool) {
+    let Some(path) = history::default_path() else {
+        eprintln!("Error: no history file; set HOME or AGENTUSAGE_HISTORY");
+        std::process::exit(1);
+    };
+    match action {
+        HistoryAction::Prune {
+            keep_raw,
+            keep_hourly,
+            dry_run,
+        } => {
+            let retention = history::Retention {
+                raw: *keep_raw,
+                hourly: *keep_hourly,
+            };
+            let now = chrono::Utc::now();
+            let compacted = if *dry_run {
+                history::load(&path, None)
+                    .map(|records| history::compact(&records, &retention, now))
+            } else {
+                history::prune(&path, &retention, now)
+            };
+            let compacted = match compacted {
+                Ok(compacted) => compacted,
+                Err(e) => {
+                    eprintln!("Error: {:#}", e);
+                    std::process::exit(1);
+                }
+            };
+            let kept = compacted.records.len();
+            if json {
+                let doc = serde_json::json!({
+                    "path": path,
+                    "dry_run": dry_run,
+                    "kept": kept,
+                    "downsampled": compacted.downsampled,
+                    "expired": compacted.expired,
+                });
+                println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+                return;
+            }
+            let removed = compacted.downsampled + compacted.expired;
+            println!(
+                "{} {} of {} records from {} ({} downsampled to hourly, {} expired)",
+                if *dry_run { "Would remove" } else { "Removed" },
+                removed,
+                kept + removed,
+                path.display(),
+                compacted.downsampled,
+                compacted.expired
+            );
+        }
+        HistoryAction::Import { format, file } => {
+            let text = if file == std::path::Path::new("-") {
+                std::io::read_to_string(std::io::stdin())
+            } else {
+                std::fs::read_to_string(file)
+            };
+            let text = match text {
+                Ok(text) => text,
+                Err(e) => {
+                    eprintln!("Error: failed to read {}: {}", file.display(), e);
+                    std::process::exit(1);
+                }
+            };
+            let records = match format {
+                ImportFormat::Ccusage => import::ccusage(&text),
+            };
+            let imported = records.and_then(|records| {
+                let added = history::import(&path, &records)?;
+                Ok((records.len(), added))
+            });
+            let (read, added) = match imported {
+                Ok(counts) => counts,
+                Err(e) => {
+                    eprintln!("Error: {}: {:#}", file.display(), e);
+                    std::process::exit(1);
+                }
+            };
+            if json {
+                let doc = serde_json::json!({
+                    "path": path,
+                    "read": read,
+                    "added": added,
+                });
+                println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+                return;
+            }
+            println!(
+                "Added {} of {} records to {} ({} already there)",
+                added,
+                read,
+                path.display(),
+                read - added
+            );
+        }
+    }
+}
+
+/// Under `--stale-fallback`, the last recorded values for each of `failed`
+/// that has any history.
+fn stale_results<'a>(cli: &Cli, failed: impl IntoIterator<Item = &'a str>) -> Vec<UsageData> {
+    if !cli.stale_fallback {
+        return Vec::new();
+    }
+    let Some(path) = history::default_path() else {
+        return Vec::new();
+    };
+    let records = match history::load(&path, None) {
+        Ok(records) => records,
+        Err(e) => {
+            cli.diagnostics().emit(
+                "warning",
+                None,
+                format!("history: {:#}", e),
+                serde_json::Value::Null,
+            );
+            return Vec::new();
+        }
+    };
+    let now = chrono::Utc::now();
+    failed
+        .into_iter()
+        .filter_map(|provider| history::last_known(&records, provider, now))
+        .collect()
+}
+
+/// The notification routes for this run: those in notify.toml (unless
+/// `--no-notify`), and `--bell`'s.
+fn notification_router(cli: &Cli, thresholds: &Thresholds) -> notify::Router {
+    let mut router = notify::Router::new();
+    if !cli.no_notify {
+        match notify::load_default() {
+            Ok(loaded) => router = loaded,
+            Err(e) => cli
+                .diagnostics()
+                .warning("notify.toml", &format!("{:#}", e)),
+        }
+    }
+    if let Some(mode) = cli.bell {
+        let sound = mode == BellMode::Sound;
+        router.add_sink("--bell", Arc::new(notify::Bell { sound }));
+        router
+            .add_route(notify::Route::to(&["--bell"]))
+            .expect("the --bell sink was just added");
+    }
+    router.set_thresholds(thresholds.clone());
+    router
+}
+
+/// `--fail-on-threshold`: exit 5 when a fresh limit has reached its
+/// threshold.
+fn exit_if_over_threshold(cli: &Cli, results: &[UsageData], thresholds: &Thresholds) {
+    let over = results
+        .iter()
+        .flat_map(|data| &data.entries)
+        .any(|entry| entry.as_of.is_none() && thresholds.is_over(entry));
+    if cli.fail_on_threshold && over {
+        std::process::exit(5);
+    }
+}
+
+/// Send this run's failed checks (provider → error), and the limits that
+/// just became low, to the sinks routed to them.  Which limits are already
+/// low is kept in `alert::default_path()` between runs, so a limit that
+/// stays low is notified once rather than on every run.
+fn send_notifications(
+    cli: &Cli,
+    results: &[UsageData],
+    failures: &BTreeMap<String, String>,
+    thresholds: &Thresholds,
+) {
+    let router = notification_router(cli, thresholds);
+    if router.is_empty() {
+        return;
+    }
+    let failures: BTreeMap<String, String> = failures
+        .iter()
+        .map(|(provider, error)| (provider.clone(), strip_error_tags(error)))
+        .collect();
+    let policy = alert::AlertPolicy::default();
+    let path = alert::default_path();
+    let mut tracker = match path
+        .as_deref()
+        .map(|path| alert::AlertTracker::load(policy, path))
+    {
+        Some(Ok(tracker)) => tracker,
+        Some(Err(e)) => {
+            cli.diagnostics().warning("notify", &format!("{:#}", e));
+            alert::AlertTracker::new(policy)
+        }
+        None => alert::AlertTracker::new(policy),
+    };
+    let now = chrono::Utc::now();
+    for (sink, e) in router.dispatch_tracked(results, &failures, &mut tracker, now) {
+        cli.diagnostics()
+            .warning("notify", &format!("sink '{}' failed: {:#}", sink, e));
+    }
+    if let Some(Err(e)) = path.map(|path| tracker.save(&path)) {
+        cli.diagnostics().warning("notify", &format!("{:#}", e));
+    }
+}
+
+/// The entry's label, with the time it was checked when it is a stale
+/// stand-in for a failed check.
+fn label_cell(entry: &UsageEntry) -> String {
+    let Some(as_of) = entry.as_of else {
+        return entry.label.clone();
+    };
+    let local = as_of.with_timezone(&chrono::Local);
+    let when = if local.date_naive() == chrono::Local::now().date_naive() {
+        local.format("%H:%M")
+    } else {
+        local.format("%b %-d %H:%M")
+    };
+    format!("{} (as of {})", entry.label, when)
+}
 
 fn make_cell(text: String, low: bool) -> Cell {
     let cell = Cell::new(text);
@@ -437,48 +2688,54 @@ fn make_cell(text: String, low: bool) -> Cell {
     }
 }
 
-fn remaining_pct_cell(entry: &UsageEntry) -> String {
+fn remaining_pct_cell(entry: &UsageEntry, numbers: &NumberFormat) -> String {
     let remaining = match entry.percent_kind {
         PercentKind::Used => entry.percent_remaining,
         PercentKind::Left => entry.percent_remaining,
     };
-    format!("{}%", remaining)
+    numbers.percent(remaining)
 }
 
-fn spent_cell(entry: &UsageEntry) -> String {
-    entry.spent.clone().unwrap_or_default()
+fn spent_cell(entry: &UsageEntry, numbers: &NumberFormat) -> String {
+    entry
+        .spent
+        .as_deref()
+        .map(|spent| numbers.money_text(spent))
+        .unwrap_or_default()
 }
 
-fn reset_days_cell(entry: &UsageEntry) -> String {
+fn reset_days_cell(entry: &UsageEntry, numbers: &NumberFormat) -> String {
     entry
         .reset_minutes
-        .map(|mins| format!("{:.2}", mins as f64 / (24.0 * 60.0)))
+        .map(|mins| numbers.decimal(mins as f64 / (24.0 * 60.0), 2))
         .unwrap_or_default()
 }
 
-fn reset_minutes_cell(entry: &UsageEntry) -> String {
+fn reset_minutes_cell(entry: &UsageEntry, numbers: &NumberFormat) -> String {
     entry
         .reset_minutes
-        .map(|mins| mins.to_string())
+        .map(|mins| numbers.integer(mins))
         .unwrap_or_default()
 }
 
-fn reset_hours_cell(entry: &UsageEntry) -> String {
+fn reset_hours_cell(entry: &UsageEntry, numbers: &NumberFormat) -> String {
     entry
         .reset_minutes
-        .map(|mins| format!("{:.2}", mins as f64 / 60.0))
+        .map(|mins| numbers.decimal(mins as f64 / 60.0, 2))
         .unwrap_or_default()
 }
 
-/// Build a JSON object for a single provider: { label: { ...fields }, ... }
+/// Build a JSON object for a single provider: { key: { label, ...fields }, ... },
+/// keyed by each entry's canonical key.
 fn build_provider_json(data: &UsageData) -> serde_json::Value {
     fn round2(v: f64) -> f64 {
         (v * 100.0).round() / 100.0
     }
 
     let mut entries = serde_json::Map::new();
-    for entry in &data.entries {
+    for (key, entry) in canonical_keys(data).into_iter().zip(&data.entries) {
         let mut obj = serde_json::Map::new();
+        obj.insert("label".into(), serde_json::json!(entry.label));
         obj.insert("percent_used".into(), serde_json::json!(entry.percent_used));
         obj.insert(
             "percent_remaining".into(),
@@ -502,313 +2759,1675 @@ fn build_provider_json(data: &UsageData) -> serde_json::Value {
         if let Some(ref requests) = entry.requests {
             obj.insert("requests".into(), serde_json::json!(requests));
         }
-        entries.insert(entry.label.clone(), serde_json::Value::Object(obj));
+        if let (Some(used), Some(limit)) = (entry.requests_used, entry.requests_limit) {
+            obj.insert("requests_used".into(), serde_json::json!(used));
+            obj.insert("requests_limit".into(), serde_json::json!(limit));
+        }
+        obj.insert("scope".into(), serde_json::json!(entry.scope.as_str()));
+        obj.insert("window".into(), serde_json::json!(entry.window.as_str()));
+        obj.insert(
+            "confidence".into(),
+            serde_json::json!(entry.confidence.as_str()),
+        );
+        if entry.inconsistent {
+            obj.insert("inconsistent".into(), serde_json::json!(true));
+        }
+        if let Some(as_of) = entry.as_of {
+            obj.insert("stale".into(), serde_json::json!(true));
+            obj.insert("as_of".into(), serde_json::json!(as_of.to_rfc3339()));
+        }
+        entries.insert(key, serde_json::Value::Object(obj));
     }
     serde_json::Value::Object(entries)
 }
 
-fn print_json(data: &UsageData) -> Result<()> {
+fn json_document(data: &UsageData, providers: &ProviderRegistry) -> serde_json::Value {
     let mut results = serde_json::Map::new();
     results.insert(data.provider.clone(), build_provider_json(data));
+    let mut check = serde_json::Map::new();
+    check.insert(data.provider.clone(), serde_json::json!(data.check));
+    let mut capabilities = serde_json::Map::new();
+    capabilities.insert(
+        data.provider.clone(),
+        serde_json::json!(data.capabilities(providers)),
+    );
 
-    let wrapper = serde_json::json!({
+    let mut document = serde_json::json!({
         "success": true,
         "results": serde_json::Value::Object(results),
+        "check": serde_json::Value::Object(check),
+        "capabilities": serde_json::Value::Object(capabilities),
     });
-    println!("{}", serde_json::to_string_pretty(&wrapper)?);
+    add_extra_usage(&mut document, std::slice::from_ref(data));
+    add_tokens(&mut document, std::slice::from_ref(data));
+    document
+}
+
+/// List under `extra_usage_enabled` the providers whose usage screen said
+/// whether extra usage is on.
+fn add_extra_usage(document: &mut serde_json::Value, results: &[UsageData]) {
+    for data in results {
+        if let Some(enabled) = data.extra_usage_enabled {
+            document["extra_usage_enabled"][&data.provider] = serde_json::json!(enabled);
+        }
+    }
+}
+
+/// List under `tokens` the per-model token counts of the providers read
+/// from their transcripts.
+fn add_tokens(document: &mut serde_json::Value, results: &[UsageData]) {
+    for data in results.iter().filter(|data| !data.tokens.is_empty()) {
+        document["tokens"][&data.provider] = serde_json::json!(data.tokens);
+    }
+}
+
+/// Under `--verbose`, list each successful check's attempts under
+/// `attempts` alongside any failed ones'.
+fn add_attempt_logs(document: &mut serde_json::Value, results: &[UsageData]) {
+    for data in results.iter().filter(|data| !data.check.log.is_empty()) {
+        document["attempts"][&data.provider] = serde_json::json!(data.check.log);
+    }
+}
+
+fn print_json(data: &UsageData, providers: &ProviderRegistry, verbose: bool) -> Result<()> {
+    let mut document = json_document(data, providers);
+    if verbose {
+        add_attempt_logs(&mut document, std::slice::from_ref(data));
+    }
+    println!("{}", serde_json::to_string_pretty(&document)?);
     Ok(())
 }
 
-fn print_json_multi(all: &AllResults) -> Result<()> {
+fn json_document_multi(all: &AllResults, providers: &ProviderRegistry) -> serde_json::Value {
     let mut results = serde_json::Map::new();
+    let mut check = serde_json::Map::new();
+    let mut capabilities = serde_json::Map::new();
     for data in &all.results {
         results.insert(data.provider.clone(), build_provider_json(data));
+        check.insert(data.provider.clone(), serde_json::json!(data.check));
+        capabilities.insert(
+            data.provider.clone(),
+            serde_json::json!(data.capabilities(providers)),
+        );
+    }
+
+    // Strip internal tags from warnings for user-facing JSON output
+    let stripped_warnings: BTreeMap<String, String> = all
+        .warnings
+        .iter()
+        .map(|(k, v)| (k.clone(), strip_error_tags(v)))
+        .collect();
+
+    let mut wrapper = serde_json::json!({
+        "success": true,
+        "results": serde_json::Value::Object(results),
+        "check": serde_json::Value::Object(check),
+        "capabilities": serde_json::Value::Object(capabilities),
+    });
+    add_extra_usage(&mut wrapper, &all.results);
+    add_tokens(&mut wrapper, &all.results);
+    if !stripped_warnings.is_empty() {
+        wrapper["warnings"] = serde_json::json!(stripped_warnings);
+        if !all.attempts.is_empty() {
+            wrapper["attempts"] = serde_json::json!(all.attempts);
+        }
+        wrapper["environment"] = serde_json::json!(Environment::capture());
+    }
+    wrapper
+}
+
+fn print_json_multi(all: &AllResults, providers: &ProviderRegistry, verbose: bool) -> Result<()> {
+    let mut document = json_document_multi(all, providers);
+    if verbose {
+        add_attempt_logs(&mut document, &all.results);
+    }
+    println!("{}", serde_json::to_string_pretty(&document)?);
+    Ok(())
+}
+
+/// Render `--template` over the `--json` document.  Missing values render
+/// empty rather than failing, so one provider being unavailable doesn't
+/// break a status bar line.
+fn render_template(template: &str, document: &serde_json::Value) -> Result<String> {
+    let mut env = minijinja::Environment::new();
+    env.set_undefined_behavior(minijinja::UndefinedBehavior::Chainable);
+    let rendered = env.render_str(template, document)?;
+    Ok(rendered)
+}
+
+fn print_template(template: &str, document: &serde_json::Value) {
+    match render_template(template, document) {
+        Ok(rendered) => println!("{}", rendered),
+        Err(e) => {
+            eprintln!("Error: template: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print the value `--query` selects from the `--json` document; a path
+/// that matches nothing is an error so scripts can tell it from a value.
+fn print_query(document: &serde_json::Value, query: &Query) {
+    match query.select(document) {
+        Some(value) => println!("{}", agentusage::query::render(value)),
+        None => {
+            eprintln!("Error: --query matched nothing");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Environment details for triaging a failed check.
+fn print_environment_verbose(diagnostics: Diagnostics) {
+    match diagnostics.0 {
+        DiagnosticsMode::Text => {
+            let rows: Vec<String> = Environment::capture()
+                .rows()
+                .into_iter()
+                .map(|(label, value)| format!("{}={}", label, value))
+                .collect();
+            diagnostics.verbose(None, format!("Environment: {}", rows.join(" ")));
+        }
+        DiagnosticsMode::Json => diagnostics.emit(
+            "verbose",
+            None,
+            "Environment".into(),
+            serde_json::json!({"environment": Environment::capture()}),
+        ),
+    }
+}
+
+/// Determine exit code from error message tags.
+fn exit_code_from_error(err: &str) -> i32 {
+    if err.contains("[tool-missing]") {
+        2
+    } else if err.contains("[timeout]") {
+        3
+    } else if err.contains("[parse-failure]") {
+        4
+    } else {
+        1
+    }
+}
+
+fn main() {
+    let mut cli = Cli::parse();
+    if let Err(e) = cli.resolve_format() {
+        Cli::command().error(ErrorKind::ArgumentConflict, e).exit();
+    }
+    // Catch template syntax errors before launching anything.
+    if let Some(template) = &cli.template {
+        if let Err(e) = minijinja::Environment::new().template_from_str(template) {
+            eprintln!("Error: template: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+
+    match &cli.command {
+        Some(Commands::List) => {
+            run_list(cli.json);
+            return;
+        }
+        #[cfg(feature = "chart")]
+        Some(Commands::Chart {
+            since,
+            output,
+            provider,
+        }) => {
+            run_chart(*since, output, provider.as_deref());
+            return;
+        }
+        Some(Commands::Report {
+            weekly: _,
+            since,
+            provider,
+            heatmap,
+        }) => {
+            run_report(
+                since.unwrap_or_else(|| chrono::Duration::days(7)),
+                provider.as_deref(),
+                *heatmap,
+                cli.json,
+                &cli.thresholds(),
+            );
+            return;
+        }
+        Some(Commands::Cache { action }) => {
+            run_cache(action, cli.json);
+            return;
+        }
+        Some(Commands::Parse { provider, file }) => {
+            run_parse(provider.as_deref(), file, cli.json);
+            return;
+        }
+        Some(Commands::Snapshot {
+            action: SnapshotAction::List,
+        }) => {
+            run_snapshot_list(cli.json);
+            return;
+        }
+        Some(Commands::History { action }) => {
+            run_history(action, cli.json);
+            return;
+        }
+        #[cfg(feature = "dashboard")]
+        Some(Commands::Dashboard { .. }) => {}
+        #[cfg(feature = "serve")]
+        Some(Commands::Serve { .. }) => {}
+        Some(Commands::Snapshot { .. })
+        | Some(Commands::Measure { .. })
+        | Some(Commands::Capture { .. })
+        | None => {}
+    }
+
+    // Handle --cleanup
+    if cli.cleanup {
+        run_cleanup(cli.json);
+        return;
+    }
+
+    // Handle --doctor
+    if cli.doctor {
+        run_doctor(cli.json);
+        return;
+    }
+
+    if cli.self_test {
+        run_self_test(cli.json);
+        return;
+    }
+
+    if cli.dry_run {
+        print_dry_run(&cli);
+        return;
+    }
+
+    agentusage::pty::clear_shutdown();
+
+    // Set up Ctrl+C handler
+    ctrlc::set_handler(|| {
+        agentusage::pty::request_shutdown();
+        agentusage::session::Session::kill_registered_sessions();
+        std::process::exit(130);
+    })
+    .expect("Failed to set Ctrl+C handler");
+
+    let mut config = cli.to_config();
+    if cli.selected_provider().is_none() && !cli.mock {
+        match custom::register_all(&mut config.providers) {
+            Ok(names) if cli.verbose && !names.is_empty() => cli.diagnostics().verbose(
+                None,
+                format!("Providers from providers.toml: {}", names.join(", ")),
+            ),
+            Ok(_) => {}
+            Err(e) => cli
+                .diagnostics()
+                .warning("providers.toml", &format!("{:#}", e)),
+        }
+    }
+    if cli.selected_provider().is_none() && !cli.mock && !cli.no_plugins {
+        match plugin::register_all(&mut config.providers) {
+            Ok(names) if cli.verbose && !names.is_empty() => cli
+                .diagnostics()
+                .verbose(None, format!("Plugin providers: {}", names.join(", "))),
+            Ok(_) => {}
+            Err(e) => cli.diagnostics().warning("plugins", &format!("{:#}", e)),
+        }
+    }
+    let headless = (!cli.no_auto_tune)
+        .then(Environment::capture)
+        .filter(|env| tuning::headless_kind(env).is_some());
+    if let Some(env) = &headless {
+        let mut tunings = tuning::auto_tune(&mut config, env);
+        // Spinner frames and colored status lines only clutter CI logs.
+        if cli.progress == ProgressMode::Auto {
+            tunings.push(tuning::Tuning {
+                setting: "progress",
+                value: "off".into(),
+                reason: format!("{} environment", tuning::headless_kind(env).unwrap_or("")),
+            });
+        }
+        if cli.verbose {
+            for tuned in tunings {
+                cli.diagnostics().verbose(
+                    None,
+                    format!(
+                        "Auto-tuned {} = {} ({}; --no-auto-tune to disable)",
+                        tuned.setting, tuned.value, tuned.reason
+                    ),
+                );
+            }
+        }
+    }
+    if cli.verbose {
+        config
+            .observers
+            .push(Arc::new(VerboseObserver(cli.diagnostics())));
+    }
+    if cli.bench {
+        run_bench(&cli, &config);
+        return;
+    }
+    if let Some(Commands::Capture { provider, output }) = &cli.command {
+        run_capture(&config, provider, output.as_deref());
+        return;
+    }
+    // Spinners would draw over a provider's screen during takeover or --attach.
+    let show_progress = cli.progress == ProgressMode::Auto
+        && !cli.json
+        && cli.query.is_none()
+        && cli.template.is_none()
+        && !cli.verbose
+        && cli.diagnostics == DiagnosticsMode::Text
+        && !cli.stream
+        && !cli.interactive
+        && cli.attach.is_none()
+        && headless.is_none();
+    let ndjson = if cli.progress == ProgressMode::Ndjson {
+        match NdjsonProgress::open(cli.progress_fd) {
+            Ok(progress) => {
+                config.observers.push(progress.observer());
+                Some(progress)
+            }
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    if !cli.mock {
+        sweep_leftovers(&cli);
+    }
+
+    if let Some(Commands::Measure { command }) = &cli.command {
+        run_measure(&cli, &config, command);
+        return;
+    }
+    #[cfg(feature = "dashboard")]
+    if let Some(Commands::Dashboard { refresh }) = &cli.command {
+        let providers = match cli.selected_provider() {
+            Some(provider) => vec![provider],
+            None => config.providers.names(),
+        };
+        let refresh = Duration::from_secs(*refresh);
+        if let Err(e) = agentusage::dashboard::run(&config, &providers, cli.thresholds(), refresh) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    #[cfg(feature = "serve")]
+    if let Some(Commands::Serve {
+        listen,
+        ttl,
+        ingest,
+    }) = &cli.command
+    {
+        let providers = match cli.selected_provider() {
+            Some(provider) => vec![provider],
+            None => config.providers.names(),
+        };
+        let server = agentusage::serve::UsageServer::new(
+            config.clone(),
+            &providers,
+            Duration::from_secs(*ttl),
+        )
+        .with_render(build_provider_json);
+        let server = if *ingest {
+            let var = agentusage::serve::INGEST_TOKEN_VAR;
+            let Some(token) = std::env::var(var).ok().filter(|t| !t.trim().is_empty()) else {
+                eprintln!("Error: --ingest needs a token in {}", var);
+                std::process::exit(1);
+            };
+            eprintln!("Accepting usage at http://{}/ingest/HOST", listen);
+            server.with_ingest(token.trim(), history::default_path())
+        } else {
+            server
+        };
+        eprintln!("Serving usage on http://{}", listen);
+        if let Err(e) = server.serve(listen) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let thresholds = cli.thresholds();
+    let baseline = cli.compare_to.as_deref().map(load_snapshot);
+    if cli.stream {
+        run_stream(&cli, &config, ndjson.as_ref(), &thresholds);
+        return;
+    }
+
+    if let Some(provider_name) = cli.selected_provider() {
+        // Single provider mode
+        let spinner =
+            show_progress.then(|| Spinner::start(&format!("Checking {}...", provider_name)));
+
+        let started = Instant::now();
+        let result = run_provider(&config, provider_name);
+
+        drop(spinner);
+        if let Some(progress) = &ndjson {
+            progress.emit_finish(provider_name, &result, started.elapsed());
+        }
+        let mut failures = BTreeMap::new();
+        let result = match result {
+            Err(e) if cli.stale_fallback => match stale_results(&cli, [provider_name]).pop() {
+                Some(stale) => {
+                    let msg = format!("{:#}", e);
+                    cli.diagnostics().warning(provider_name, &msg);
+                    failures.insert(provider_name.to_string(), msg);
+                    Ok(stale)
+                }
+                None => Err(e),
+            },
+            result => result,
+        };
+
+        match result {
+            Ok(data) => {
+                if let Some(snapshot) = &baseline {
+                    print_comparison(&cli, snapshot, std::slice::from_ref(&data));
+                } else if let Some(path) = &cli.query {
+                    print_query(&json_document(&data, &config.providers), path);
+                } else if let Some(template) = &cli.template {
+                    print_template(template, &json_document(&data, &config.providers));
+                } else if cli.json {
+                    if let Err(e) = print_json(&data, &config.providers, cli.verbose) {
+                        eprintln!("Error formatting JSON: {}", e);
+                        std::process::exit(1);
+                    }
+                } else {
+                    print_human(&data, &cli.number_format(), &thresholds);
+                }
+                record_history(&cli, std::slice::from_ref(&data));
+                send_notifications(&cli, std::slice::from_ref(&data), &failures, &thresholds);
+                exit_if_over_threshold(&cli, std::slice::from_ref(&data), &thresholds);
+            }
+            Err(e) => {
+                let msg = format!("{:#}", e);
+                failures.insert(provider_name.to_string(), msg.clone());
+                send_notifications(&cli, &[], &failures, &thresholds);
+                let code = exit_code_from_error(&msg);
+                if cli.json {
+                    let mut wrapper = serde_json::json!({
+                        "success": false,
+                        "error": strip_error_tags(&msg),
+                        "environment": Environment::capture(),
+                    });
+                    if let Some(failure) = e.downcast_ref::<CheckFailure>() {
+                        wrapper["attempts"] = serde_json::json!(failure.attempts);
+                    }
+                    println!("{}", serde_json::to_string_pretty(&wrapper).unwrap());
+                } else {
+                    cli.diagnostics().error(&msg);
+                    if cli.verbose {
+                        print_environment_verbose(cli.diagnostics());
+                    }
+                }
+                std::process::exit(code);
+            }
+        }
+    } else {
+        // All providers mode (parallel)
+        let mut all = if let Some(progress) = &ndjson {
+            run_all_tracked(&config, |provider, result, took| {
+                progress.emit_finish(provider, result, took)
+            })
+        } else if show_progress {
+            run_all_with_progress(&config)
+        } else {
+            run_all(&config)
+        };
+        let failed: Vec<&str> = all.warnings.keys().map(String::as_str).collect();
+        let stale = stale_results(&cli, failed);
+        if !stale.is_empty() {
+            all.results.extend(stale);
+            let order = config.providers.names();
+            let rank = |name: &str| order.iter().position(|p| *p == name);
+            all.results.sort_by_key(|data| rank(&data.provider));
+        }
+
+        if all.results.is_empty() {
+            send_notifications(&cli, &[], &all.warnings, &thresholds);
+            if cli.json {
+                let stripped_warnings: BTreeMap<String, String> = all
+                    .warnings
+                    .iter()
+                    .map(|(k, v)| (k.clone(), strip_error_tags(v)))
+                    .collect();
+                let wrapper = serde_json::json!({
+                    "success": false,
+                    "results": {},
+                    "warnings": stripped_warnings,
+                    "error": "All providers failed.",
+                    "attempts": all.attempts,
+                    "environment": Environment::capture(),
+                });
+                println!("{}", serde_json::to_string_pretty(&wrapper).unwrap());
+            } else {
+                for (provider, msg) in &all.warnings {
+                    cli.diagnostics().warning(provider, msg);
+                }
+                cli.diagnostics().error("All providers failed.");
+                if cli.verbose {
+                    print_environment_verbose(cli.diagnostics());
+                }
+            }
+            std::process::exit(1);
+        }
+
+        if let Some(snapshot) = &baseline {
+            for (provider, msg) in &all.warnings {
+                cli.diagnostics().warning(provider, msg);
+            }
+            print_comparison(&cli, snapshot, &all.results);
+        } else if let Some(path) = &cli.query {
+            for (provider, msg) in &all.warnings {
+                cli.diagnostics().warning(provider, msg);
+            }
+            print_query(&json_document_multi(&all, &config.providers), path);
+        } else if let Some(template) = &cli.template {
+            for (provider, msg) in &all.warnings {
+                cli.diagnostics().warning(provider, msg);
+            }
+            print_template(template, &json_document_multi(&all, &config.providers));
+        } else if cli.json {
+            if let Err(e) = print_json_multi(&all, &config.providers, cli.verbose) {
+                eprintln!("Error formatting JSON: {}", e);
+                std::process::exit(1);
+            }
+        } else {
+            for (provider, msg) in &all.warnings {
+                cli.diagnostics().warning(provider, msg);
+            }
+            print_human_multi(&all.results, &cli.number_format(), &thresholds);
+        }
+        record_history(&cli, &all.results);
+        send_notifications(&cli, &all.results, &all.warnings, &thresholds);
+        exit_if_over_threshold(&cli, &all.results, &thresholds);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentusage::{CheckStats, Confidence, LimitScope, LimitWindow, UsageEntry};
+
+    // ── exit_code_from_error ────────────────────────────────────────
+
+    #[test]
+    fn test_exit_code_tool_missing() {
+        assert_eq!(
+            exit_code_from_error("[tool-missing] claude CLI not found"),
+            2
+        );
+    }
+
+    #[test]
+    fn test_exit_code_timeout() {
+        assert_eq!(exit_code_from_error("[timeout] Timed out after 45s"), 3);
+    }
+
+    #[test]
+    fn test_exit_code_parse_failure() {
+        assert_eq!(
+            exit_code_from_error("[parse-failure] No usage data found"),
+            4
+        );
+    }
+
+    #[test]
+    fn test_exit_code_general() {
+        assert_eq!(exit_code_from_error("something else went wrong"), 1);
+    }
+
+    #[test]
+    fn test_exit_code_empty_string() {
+        assert_eq!(exit_code_from_error(""), 1);
+    }
+
+    #[test]
+    fn test_exit_code_tag_embedded_in_context() {
+        // anyhow context wrapping: "outer: [timeout] inner"
+        assert_eq!(
+            exit_code_from_error("Timed out waiting for prompt: [timeout] Timed out after 30s"),
+            3
+        );
+    }
+
+    // ── strip_error_tags ────────────────────────────────────────────
+
+    #[test]
+    fn test_strip_tool_missing_tag() {
+        assert_eq!(
+            strip_error_tags("[tool-missing] claude CLI not found"),
+            "claude CLI not found"
+        );
+    }
+
+    #[test]
+    fn test_strip_timeout_tag() {
+        assert_eq!(
+            strip_error_tags("[timeout] Timed out after 45s"),
+            "Timed out after 45s"
+        );
+    }
+
+    #[test]
+    fn test_strip_parse_failure_tag() {
+        assert_eq!(
+            strip_error_tags("[parse-failure] No usage data found"),
+            "No usage data found"
+        );
+    }
+
+    #[test]
+    fn test_strip_no_tags() {
+        assert_eq!(strip_error_tags("plain error"), "plain error");
+    }
+
+    #[test]
+    fn test_strip_multiple_tags_in_chained_error() {
+        // anyhow can chain errors: "context: [timeout] inner message"
+        let msg = "Waiting failed: [timeout] Timed out after 30s";
+        let stripped = strip_error_tags(msg);
+        assert_eq!(stripped, "Waiting failed: Timed out after 30s");
+    }
+
+    // ── CLI flag parsing ──────────────────────────────────────────
+
+    #[test]
+    fn test_cli_default_no_flags() {
+        let cli = Cli::try_parse_from(["agentusage"]).unwrap();
+        assert!(!cli.claude);
+        assert!(!cli.codex);
+        assert!(!cli.gemini);
+        assert!(!cli.no_plugins);
+        assert!(
+            Cli::try_parse_from(["agentusage", "--no-plugins"])
+                .unwrap()
+                .no_plugins
+        );
+    }
+
+    #[test]
+    fn test_cli_codex_api_flag() {
+        let config = Cli::try_parse_from(["agentusage"]).unwrap().to_config();
+        assert_eq!(config.source_for("codex"), SourceMode::Tui);
+        let cli = Cli::try_parse_from(["agentusage", "--codex", "--codex-api"]).unwrap();
+        assert!(cli.codex);
+        let config = cli.to_config();
+        assert_eq!(config.source_for("codex"), SourceMode::Api);
+        assert_eq!(config.source_for("claude"), SourceMode::Tui);
+    }
+
+    #[test]
+    fn test_cli_gemini_cap_flag() {
+        let config = Cli::try_parse_from(["agentusage"]).unwrap().to_config();
+        assert!(config.gemini_request_caps.is_empty());
+        let cli =
+            Cli::try_parse_from(["agentusage", "--gemini-cap", "Gemini-2.5-Pro=1500"]).unwrap();
+        let config = cli.to_config();
+        assert_eq!(
+            config.gemini_request_caps,
+            BTreeMap::from([("gemini-2.5-pro".to_string(), 1500)])
+        );
+        assert!(Cli::try_parse_from(["agentusage", "--gemini-cap", "gemini-2.5-flash=0"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--gemini-cap", "100"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--gemini-cap", "pro=lots"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_threshold_flag() {
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "--threshold",
+            "80",
+            "--threshold",
+            "Weekly=70",
+            "--fail-on-threshold",
+        ])
+        .unwrap();
+        assert_eq!(cli.threshold, [(None, 80), (Some(LimitWindow::Weekly), 70)]);
+        assert!(cli.fail_on_threshold);
+        let cli =
+            Cli::try_parse_from(["agentusage", "report", "--threshold", "session=95"]).unwrap();
+        assert_eq!(cli.threshold, [(Some(LimitWindow::Session), 95)]);
+        assert!(Cli::try_parse_from(["agentusage", "--threshold", "101"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--threshold", "hourly=50"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_source_flag() {
+        let cli = Cli::try_parse_from(["agentusage", "--source", "auto", "--source", "gemini=tui"])
+            .unwrap();
+        let config = cli.to_config();
+        assert_eq!(config.source, SourceMode::Auto);
+        assert_eq!(config.source_for("claude"), SourceMode::Auto);
+        assert_eq!(config.source_for("gemini"), SourceMode::Tui);
+        let config = Cli::try_parse_from(["agentusage", "--source", "Codex=LOGS"])
+            .unwrap()
+            .to_config();
+        assert_eq!(config.source_for("codex"), SourceMode::Logs);
+        assert_eq!(config.source_for("claude"), SourceMode::Tui);
+        assert!(Cli::try_parse_from(["agentusage", "--source", "local"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--source", "acme=api"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_claude_flag() {
+        let cli = Cli::try_parse_from(["agentusage", "--claude"]).unwrap();
+        assert!(cli.claude);
+        assert!(!cli.codex);
+        assert!(!cli.gemini);
+    }
+
+    #[test]
+    fn test_cli_codex_flag() {
+        let cli = Cli::try_parse_from(["agentusage", "--codex"]).unwrap();
+        assert!(!cli.claude);
+        assert!(cli.codex);
+    }
+
+    #[test]
+    fn test_cli_gemini_flag() {
+        let cli = Cli::try_parse_from(["agentusage", "--gemini"]).unwrap();
+        assert!(!cli.claude);
+        assert!(cli.gemini);
+    }
+
+    #[test]
+    fn test_cli_copilot_flag() {
+        let cli = Cli::try_parse_from(["agentusage", "--copilot"]).unwrap();
+        assert!(cli.copilot);
+        assert_eq!(cli.providers(), ["copilot"]);
+        assert!(Cli::try_parse_from(["agentusage", "--copilot", "--gemini"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--timeout", "copilot=60"]).is_ok());
+    }
+
+    #[test]
+    fn test_cli_cursor_flag() {
+        let cli = Cli::try_parse_from(["agentusage", "--cursor"]).unwrap();
+        assert_eq!(cli.providers(), ["cursor"]);
+        assert!(Cli::try_parse_from(["agentusage", "--cursor", "--copilot"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_qwen_flag() {
+        let cli = Cli::try_parse_from(["agentusage", "--qwen"]).unwrap();
+        assert_eq!(cli.providers(), ["qwen"]);
+        assert!(Cli::try_parse_from(["agentusage", "--qwen", "--gemini"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_amazonq_flag() {
+        let cli = Cli::try_parse_from(["agentusage", "--amazonq"]).unwrap();
+        assert_eq!(cli.providers(), ["amazonq"]);
+        assert!(Cli::try_parse_from(["agentusage", "--amazonq", "--claude"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_aider_flag() {
+        let cli = Cli::try_parse_from(["agentusage", "--aider"]).unwrap();
+        assert_eq!(cli.providers(), ["aider"]);
+        assert!(Cli::try_parse_from(["agentusage", "--aider", "--amazonq"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_conflicting_provider_flags_error() {
+        // Multiple provider flags should produce a clap error
+        assert!(Cli::try_parse_from(["agentusage", "--claude", "--codex"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--claude", "--gemini"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--codex", "--gemini"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--claude", "--codex", "--gemini"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_list_subcommand() {
+        let cli = Cli::try_parse_from(["agentusage", "list", "--json"]).unwrap();
+        assert_eq!(cli.command, Some(Commands::List));
+        assert!(cli.json);
+        let cli = Cli::try_parse_from(["agentusage", "--json", "list"]).unwrap();
+        assert!(cli.json);
+        assert_eq!(Cli::try_parse_from(["agentusage"]).unwrap().command, None);
+    }
+
+    #[test]
+    #[cfg(feature = "chart")]
+    fn test_cli_chart_subcommand() {
+        let cli =
+            Cli::try_parse_from(["agentusage", "chart", "--since", "24h", "-o", "u.png"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Chart {
+                since: chrono::Duration::hours(24),
+                output: "u.png".into(),
+                provider: None,
+            })
+        );
+        assert!(
+            Cli::try_parse_from(["agentusage", "chart", "-o", "u.svg", "--since", "7"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["agentusage", "chart"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_cache_subcommand() {
+        let cli =
+            Cli::try_parse_from(["agentusage", "cache", "clear", "--provider", "codex"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Cache {
+                action: CacheAction::Clear {
+                    provider: Some("codex".into())
+                }
+            })
+        );
+        let cli =
+            Cli::try_parse_from(["agentusage", "cache", "ttl", "--min-interval", "300"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Cache {
+                action: CacheAction::Ttl {
+                    min_interval: Some(300)
+                }
+            })
+        );
+        assert!(Cli::try_parse_from(["agentusage", "cache"]).is_err());
+    }
+
+    #[cfg(feature = "dashboard")]
+    #[test]
+    fn test_cli_dashboard_subcommand() {
+        let cli = Cli::try_parse_from(["agentusage", "dashboard"]).unwrap();
+        assert_eq!(cli.command, Some(Commands::Dashboard { refresh: 300 }));
+        let cli =
+            Cli::try_parse_from(["agentusage", "--codex", "dashboard", "--refresh", "60"]).unwrap();
+        assert_eq!(cli.command, Some(Commands::Dashboard { refresh: 60 }));
+        assert!(Cli::try_parse_from(["agentusage", "dashboard", "--refresh", "1"]).is_err());
+    }
+
+    #[cfg(feature = "serve")]
+    #[test]
+    fn test_cli_serve_subcommand() {
+        let cli = Cli::try_parse_from(["agentusage", "serve"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Serve {
+                listen: "127.0.0.1:7788".into(),
+                ttl: 60,
+                ingest: false,
+            })
+        );
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "serve",
+            "--listen",
+            "0.0.0.0:9000",
+            "--ttl",
+            "300",
+            "--ingest",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Serve {
+                listen: "0.0.0.0:9000".into(),
+                ttl: 300,
+                ingest: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_cli_measure_subcommand() {
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "--claude",
+            "measure",
+            "--",
+            "claude",
+            "-p",
+            "fix the tests",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Measure {
+                command: vec!["claude".into(), "-p".into(), "fix the tests".into()]
+            })
+        );
+        assert!(Cli::try_parse_from(["agentusage", "measure"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "measure", "--"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_snapshot_subcommand() {
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "--codex",
+            "snapshot",
+            "save",
+            "before-refactor",
+        ])
+        .unwrap();
+        assert_eq!(cli.snapshot_name(), Some("before-refactor"));
+        assert_eq!(cli.selected_provider(), Some("codex"));
+        let cli = Cli::try_parse_from(["agentusage", "snapshot", "list"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Snapshot {
+                action: SnapshotAction::List
+            })
+        );
+        assert_eq!(cli.snapshot_name(), None);
+        assert!(Cli::try_parse_from(["agentusage", "snapshot", "save"]).is_err());
+
+        let cli = Cli::try_parse_from(["agentusage", "--compare-to", "before-refactor"]).unwrap();
+        assert_eq!(cli.compare_to.as_deref(), Some("before-refactor"));
+        assert!(Cli::try_parse_from(["agentusage", "--compare-to", "x", "--stream"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_history_subcommand() {
+        let cli = Cli::try_parse_from(["agentusage", "history", "prune"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::History {
+                action: HistoryAction::Prune {
+                    keep_raw: chrono::Duration::days(30),
+                    keep_hourly: None,
+                    dry_run: false,
+                }
+            })
+        );
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "history",
+            "prune",
+            "--keep-raw",
+            "2w",
+            "--keep-hourly",
+            "52w",
+            "--dry-run",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::History {
+                action: HistoryAction::Prune {
+                    keep_raw: chrono::Duration::weeks(2),
+                    keep_hourly: Some(chrono::Duration::weeks(52)),
+                    dry_run: true,
+                }
+            })
+        );
+        assert!(
+            Cli::try_parse_from(["agentusage", "history", "prune", "--keep-raw", "x"]).is_err()
+        );
+
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "history",
+            "import",
+            "--format",
+            "ccusage",
+            "blocks.json",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::History {
+                action: HistoryAction::Import {
+                    format: ImportFormat::Ccusage,
+                    file: "blocks.json".into(),
+                }
+            })
+        );
+        assert!(Cli::try_parse_from(["agentusage", "history", "import", "blocks.json"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_capture_subcommand() {
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "capture",
+            "--provider",
+            "claude",
+            "-o",
+            "f.txt",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Capture {
+                provider: "claude".into(),
+                output: Some("f.txt".into()),
+            })
+        );
+        assert!(Cli::try_parse_from(["agentusage", "capture"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_subcommand() {
+        let cli =
+            Cli::try_parse_from(["agentusage", "parse", "--provider", "codex", "f.txt"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Parse {
+                provider: Some("codex".into()),
+                file: "f.txt".into(),
+            })
+        );
+        let cli = Cli::try_parse_from(["agentusage", "parse", "-"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Commands::Parse {
+                provider: None,
+                file: "-".into(),
+            })
+        );
+        assert!(Cli::try_parse_from(["agentusage", "parse"]).is_err());
+    }
+
+    #[test]
+    fn test_format_age() {
+        let age = |secs| format_age(chrono::Duration::seconds(secs));
+        assert_eq!(age(-5), "0s");
+        assert_eq!(age(45), "45s");
+        assert_eq!(age(12 * 60 + 30), "12m");
+        assert_eq!(age(3 * 3600 + 5 * 60), "3h 5m");
+        assert_eq!(age(2 * 86400 + 4 * 3600), "2d 4h");
+    }
+
+    #[test]
+    fn test_cli_report_subcommand() {
+        let cli = Cli::try_parse_from(["agentusage", "report", "--weekly", "--json"]).unwrap();
+        assert!(cli.json);
+        assert_eq!(
+            cli.command,
+            Some(Commands::Report {
+                weekly: true,
+                since: None,
+                provider: None,
+                heatmap: false,
+            })
+        );
+        let cli =
+            Cli::try_parse_from(["agentusage", "report", "--heatmap", "--since", "30d"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Report {
+                heatmap: true,
+                since: Some(_),
+                ..
+            })
+        ));
+        assert!(
+            Cli::try_parse_from(["agentusage", "report", "--weekly", "--since", "30d"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_cli_progress_modes() {
+        let cli = Cli::try_parse_from(["agentusage"]).unwrap();
+        assert_eq!(cli.progress, ProgressMode::Auto);
+        let cli = Cli::try_parse_from(["agentusage", "--progress", "ndjson"]).unwrap();
+        assert_eq!(cli.progress, ProgressMode::Ndjson);
+        assert!(Cli::try_parse_from(["agentusage", "--progress", "bogus"]).is_err());
+    }
+
+    // ── Timing flags ──────────────────────────────────────────────
+
+    #[test]
+    fn test_parse_timing_arg() {
+        assert_eq!(
+            parse_timing_arg("60").unwrap(),
+            TimingArg {
+                provider: None,
+                value: 60
+            }
+        );
+        assert_eq!(
+            parse_timing_arg("Codex=90").unwrap(),
+            TimingArg {
+                provider: Some("codex".into()),
+                value: 90
+            }
+        );
+        assert!(parse_timing_arg("bogus=90").is_err());
+        assert!(parse_timing_arg("codex=soon").is_err());
+        assert!(parse_timing_arg("-5").is_err());
+    }
+
+    #[test]
+    fn test_parse_poll_interval_minimum() {
+        assert!(parse_poll_interval_arg("5").is_err());
+        assert_eq!(parse_poll_interval_arg("250").unwrap().value, 250);
+    }
+
+    #[test]
+    fn test_cli_timing_defaults() {
+        let config = Cli::try_parse_from(["agentusage"]).unwrap().to_config();
+        assert_eq!(config.timeout, 45);
+        assert_eq!(config.prompt_timeout, None);
+        assert_eq!(config.poll_interval_ms, 500);
+        assert!(config.provider_timings.is_empty());
+    }
+
+    #[test]
+    fn test_cli_timing_per_provider() {
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "--timeout",
+            "60",
+            "--prompt-timeout",
+            "codex=90",
+            "--poll-interval",
+            "200",
+            "--poll-interval",
+            "gemini=100",
+        ])
+        .unwrap();
+        let config = cli.to_config();
+        assert_eq!(config.data_timeout("claude"), Duration::from_secs(60));
+        assert_eq!(config.prompt_timeout("codex"), Duration::from_secs(90));
+        assert_eq!(config.prompt_timeout("claude"), Duration::from_secs(30));
+        assert_eq!(config.poll_interval("claude"), Duration::from_millis(200));
+        assert_eq!(config.poll_interval("gemini"), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_cli_interactive_flag() {
+        let config = Cli::try_parse_from(["agentusage"]).unwrap().to_config();
+        assert!(!config.interactive);
+        let config = Cli::try_parse_from(["agentusage", "--interactive"])
+            .unwrap()
+            .to_config();
+        assert!(config.interactive);
+    }
+
+    #[test]
+    fn test_cli_safe_launch_default_and_opt_out() {
+        let config = |args: &[&str]| {
+            let mut argv = vec!["agentusage"];
+            argv.extend_from_slice(args);
+            Cli::try_parse_from(argv).unwrap().to_config()
+        };
+        assert!(config(&[]).safe_launch);
+        assert!(!config(&["--no-safe-launch"]).safe_launch);
+        assert!(config(&["--no-safe-launch", "--safe-launch"]).safe_launch);
+    }
+
+    #[test]
+    fn test_cli_attach_modes() {
+        let cli = Cli::try_parse_from(["agentusage", "--codex", "--attach"]).unwrap();
+        assert_eq!(cli.attach, Some(AttachMode::View));
+        let cli =
+            Cli::try_parse_from(["agentusage", "--claude", "--attach", "interactive"]).unwrap();
+        assert_eq!(cli.to_config().attach, Some(AttachMode::Interactive));
+        // Parallel sessions would interleave on one terminal
+        assert!(Cli::try_parse_from(["agentusage", "--attach"]).is_err());
+        assert!(
+            Cli::try_parse_from(["agentusage", "--gemini", "--attach", "--interactive"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_dry_run_plans_follow_provider_flags() {
+        let cli = Cli::try_parse_from(["agentusage", "--dry-run"]).unwrap();
+        let providers: Vec<_> = dry_run_plans(&cli).iter().map(|p| p.provider).collect();
+        assert_eq!(providers, ALL_PROVIDERS);
+
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "--gemini",
+            "--dry-run",
+            "--timeout",
+            "gemini=120",
+        ])
+        .unwrap();
+        let plans = dry_run_plans(&cli);
+        assert_eq!(plans.len(), 1);
+        assert_eq!(plans[0].command, "/stats session");
+        assert_eq!(plans[0].data_timeout_secs, 120);
+        // Gemini's prompt wait follows its data timeout by default
+        assert_eq!(plans[0].prompt_timeout_secs, 120);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("--allowed-tools"), "--allowed-tools");
+        assert_eq!(shell_quote(""), "\"\"");
+        assert_eq!(shell_quote("a b"), "\"a b\"");
+    }
+
+    #[test]
+    fn test_cli_bench_iterations() {
+        let cli = Cli::try_parse_from(["agentusage", "--bench"]).unwrap();
+        assert!(cli.bench);
+        assert_eq!(cli.iterations, 3);
+        let cli = Cli::try_parse_from(["agentusage", "--bench", "--iterations", "5"]).unwrap();
+        assert_eq!(cli.iterations, 5);
+        assert!(Cli::try_parse_from(["agentusage", "--iterations", "5"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--bench", "--iterations", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_bench_report_excludes_failures_from_data_stats() {
+        let sample = |prompt: Option<u64>, data: u64, error: Option<&str>| BenchSample {
+            to_prompt: prompt.map(Duration::from_millis),
+            to_data: Duration::from_millis(data),
+            error: error.map(String::from),
+        };
+        let report = bench_report(
+            "codex",
+            &[
+                sample(Some(1000), 3000, None),
+                sample(Some(2000), 5000, None),
+                sample(None, 45000, Some("timed out")),
+            ],
+        );
+        assert_eq!(report.iterations, 3);
+        assert_eq!(report.failures, 1);
+        assert_eq!(
+            report.time_to_data.unwrap().max,
+            Duration::from_millis(5000)
+        );
+        let json = bench_report_json(&report);
+        assert_eq!(json["time_to_prompt_ms"]["median"], 1500);
+        assert_eq!(json["time_to_data_ms"]["mean"], 4000);
+        assert_eq!(json["errors"][0], "timed out");
+    }
+
+    #[test]
+    fn test_query_selects_from_json_document() {
+        let data = agentusage::fixtures::mock_usage("claude").unwrap();
+        let cli = Cli::try_parse_from([
+            "agentusage",
+            "--query",
+            "results.claude.session.percent_used",
+        ])
+        .unwrap();
+        let value = cli
+            .query
+            .unwrap()
+            .select(&json_document(&data, &ProviderRegistry::builtin()))
+            .cloned();
+        assert_eq!(value, Some(serde_json::json!(12)));
+        assert!(Cli::try_parse_from(["agentusage", "--query", "a", "--json"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--query", "a..b"]).is_err());
+    }
+
+    #[test]
+    fn test_render_template_over_json_document() {
+        let data = agentusage::fixtures::mock_usage("codex").unwrap();
+        let document = json_document(&data, &ProviderRegistry::builtin());
+        let rendered = render_template(
+            r#"{{ results.codex.session.percent_remaining }}%{% if results.claude %} claude{% endif %}"#,
+            &document,
+        )
+        .unwrap();
+        assert_eq!(rendered, "77%");
+        // Providers missing from the run render empty instead of failing.
+        let rendered =
+            render_template(r#"[{{ results.gemini["x"].percent_used }}]"#, &document).unwrap();
+        assert_eq!(rendered, "[]");
+        assert!(render_template("{{ oops", &document).is_err());
+    }
+
+    #[test]
+    fn test_cli_format_flags() {
+        let mut cli = Cli::try_parse_from(["agentusage", "--format", "json"]).unwrap();
+        cli.resolve_format().unwrap();
+        assert!(cli.json);
+        let mut cli = Cli::try_parse_from(["agentusage", "--format", "template"]).unwrap();
+        assert!(cli.resolve_format().is_err());
+        let mut cli =
+            Cli::try_parse_from(["agentusage", "--format", "table", "--template", "x"]).unwrap();
+        assert!(cli.resolve_format().is_err());
+        let mut cli = Cli::try_parse_from(["agentusage", "--template", "{{ success }}"]).unwrap();
+        cli.resolve_format().unwrap();
+        assert!(!cli.json);
+        assert!(Cli::try_parse_from(["agentusage", "--template", "x", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_human_cells_follow_locale() {
+        let data = agentusage::fixtures::mock_usage("claude").unwrap();
+        let extra = &data.entries[3];
+        let de = NumberFormat::for_locale("de_DE.UTF-8");
+        assert_eq!(remaining_pct_cell(extra, &de), "85\u{a0}%");
+        assert_eq!(reset_minutes_cell(extra, &de), "18.720");
+        assert_eq!(reset_hours_cell(extra, &de), "312,00");
+        assert_eq!(spent_cell(extra, &de), "$77,33 / $500,00 spent");
+        let en = NumberFormat::default();
+        assert_eq!(remaining_pct_cell(extra, &en), "85%");
+        assert_eq!(reset_minutes_cell(extra, &en), "18,720");
+        assert_eq!(reset_days_cell(extra, &en), "13.00");
     }
 
-    // Strip internal tags from warnings for user-facing JSON output
-    let stripped_warnings: BTreeMap<String, String> = all
-        .warnings
-        .iter()
-        .map(|(k, v)| (k.clone(), strip_error_tags(v)))
-        .collect();
+    #[test]
+    fn test_cli_locale_overrides_environment() {
+        let cli = Cli::try_parse_from(["agentusage", "--locale", "fr_FR"]).unwrap();
+        assert_eq!(cli.number_format(), NumberFormat::for_locale("fr"));
+    }
 
-    let mut wrapper = serde_json::json!({
-        "success": true,
-        "results": serde_json::Value::Object(results),
-    });
-    if !stripped_warnings.is_empty() {
-        wrapper["warnings"] = serde_json::json!(stripped_warnings);
+    #[test]
+    fn test_cli_mock_flag() {
+        assert!(
+            !Cli::try_parse_from(["agentusage"])
+                .unwrap()
+                .to_config()
+                .mock
+        );
+        let config = Cli::try_parse_from(["agentusage", "--mock", "--json"])
+            .unwrap()
+            .to_config();
+        assert!(config.mock);
     }
-    println!("{}", serde_json::to_string_pretty(&wrapper)?);
-    Ok(())
-}
 
-/// Determine exit code from error message tags.
-fn exit_code_from_error(err: &str) -> i32 {
-    if err.contains("[tool-missing]") {
-        2
-    } else if err.contains("[timeout]") {
-        3
-    } else if err.contains("[parse-failure]") {
-        4
-    } else {
-        1
+    #[test]
+    fn test_cli_allow_partial() {
+        assert!(
+            !Cli::try_parse_from(["agentusage"])
+                .unwrap()
+                .to_config()
+                .allow_partial
+        );
+        let cli = Cli::try_parse_from(["agentusage", "--allow-partial"]).unwrap();
+        assert!(cli.to_config().allow_partial);
     }
-}
 
-/// Strip internal error tags from user-facing message.
-fn strip_error_tags(msg: &str) -> String {
-    msg.replace("[tool-missing] ", "")
-        .replace("[timeout] ", "")
-        .replace("[parse-failure] ", "")
-}
+    #[test]
+    fn test_cli_lock() {
+        let config = Cli::try_parse_from(["agentusage"]).unwrap().to_config();
+        assert_eq!(config.lock_wait, None);
+        let config = Cli::try_parse_from(["agentusage", "--lock"])
+            .unwrap()
+            .to_config();
+        assert_eq!(config.lock_wait, Some(Duration::from_secs(10)));
+        let config = Cli::try_parse_from(["agentusage", "--lock", "3"])
+            .unwrap()
+            .to_config();
+        assert_eq!(config.lock_wait, Some(Duration::from_secs(3)));
+    }
 
-fn main() {
-    let cli = Cli::parse();
+    #[test]
+    fn test_cli_min_interval() {
+        let config = Cli::try_parse_from(["agentusage"]).unwrap().to_config();
+        assert_eq!(config.min_interval, None);
+        let config = Cli::try_parse_from(["agentusage", "--min-interval", "300"])
+            .unwrap()
+            .to_config();
+        assert_eq!(config.min_interval, Some(Duration::from_secs(300)));
+    }
 
-    // Handle --cleanup
-    if cli.cleanup {
-        agentusage::session::Session::kill_all_stale_sessions();
-        return;
+    #[test]
+    fn test_cli_reap_orphans() {
+        assert!(!Cli::try_parse_from(["agentusage"]).unwrap().reap_orphans);
+        assert!(
+            Cli::try_parse_from(["agentusage", "--reap-orphans"])
+                .unwrap()
+                .reap_orphans
+        );
     }
 
-    // Handle --doctor
-    if cli.doctor {
-        run_doctor();
-        return;
+    #[test]
+    fn test_cli_stale_fallback() {
+        assert!(!Cli::try_parse_from(["agentusage"]).unwrap().stale_fallback);
+        let cli = Cli::try_parse_from(["agentusage", "--codex", "--stale-fallback"]).unwrap();
+        assert!(cli.stale_fallback);
     }
 
-    agentusage::pty::clear_shutdown();
+    #[test]
+    fn test_cli_max_total_time_sets_deadline() {
+        let config = Cli::try_parse_from(["agentusage"]).unwrap().to_config();
+        assert!(config.deadline.is_none());
 
-    // Set up Ctrl+C handler
-    ctrlc::set_handler(|| {
-        agentusage::pty::request_shutdown();
-        agentusage::session::Session::kill_registered_sessions();
-        std::process::exit(130);
-    })
-    .expect("Failed to set Ctrl+C handler");
+        let before = Instant::now();
+        let config = Cli::try_parse_from(["agentusage", "--max-total-time", "20"])
+            .unwrap()
+            .to_config();
+        let deadline = config.deadline.expect("deadline set");
+        assert!(deadline >= before + Duration::from_secs(20));
+        assert!(deadline <= Instant::now() + Duration::from_secs(20));
 
-    let config = cli.to_config();
-    let show_progress = !cli.json && !cli.verbose;
+        assert!(Cli::try_parse_from(["agentusage", "--max-total-time", "0"]).is_err());
+    }
 
-    if cli.claude || cli.codex || cli.gemini {
-        // Single provider mode
-        let provider_name = if cli.claude {
-            "claude"
-        } else if cli.codex {
-            "codex"
-        } else {
-            "gemini"
-        };
-        let spinner =
-            show_progress.then(|| Spinner::start(&format!("Checking {}...", provider_name)));
+    #[test]
+    fn test_cli_bell_modes() {
+        let cli = Cli::try_parse_from(["agentusage"]).unwrap();
+        assert_eq!(cli.bell, None);
+        let cli = Cli::try_parse_from(["agentusage", "--bell"]).unwrap();
+        assert_eq!(cli.bell, Some(BellMode::Terminal));
+        let cli = Cli::try_parse_from(["agentusage", "--bell", "sound"]).unwrap();
+        assert_eq!(cli.bell, Some(BellMode::Sound));
+    }
 
-        let result = if cli.claude {
-            run_claude(&config)
-        } else if cli.codex {
-            run_codex(&config)
-        } else {
-            run_gemini(&config)
-        };
+    #[test]
+    fn test_notification_router() {
+        let cli = Cli::try_parse_from(["agentusage", "--no-notify"]).unwrap();
+        assert!(notification_router(&cli, &Thresholds::default()).is_empty());
+        let cli = Cli::try_parse_from(["agentusage", "--no-notify", "--bell"]).unwrap();
+        assert!(!notification_router(&cli, &Thresholds::default()).is_empty());
+    }
 
-        drop(spinner);
+    // ── --stream ──────────────────────────────────────────────────
 
-        match result {
-            Ok(data) => {
-                if cli.json {
-                    if let Err(e) = print_json(&data) {
-                        eprintln!("Error formatting JSON: {}", e);
-                        std::process::exit(1);
-                    }
-                } else {
-                    print_human(&data);
-                }
-            }
-            Err(e) => {
-                let msg = format!("{:#}", e);
-                let code = exit_code_from_error(&msg);
-                if cli.json {
-                    let wrapper = serde_json::json!({
-                        "success": false,
-                        "error": strip_error_tags(&msg),
-                    });
-                    println!("{}", serde_json::to_string_pretty(&wrapper).unwrap());
-                } else {
-                    eprintln!("Error: {}", strip_error_tags(&msg));
-                }
-                std::process::exit(code);
-            }
-        }
-    } else {
-        // All providers mode (parallel)
-        let all = if show_progress {
-            run_all_with_progress(&config)
-        } else {
-            run_all(&config)
+    #[test]
+    fn test_stream_line_json() {
+        let ok = ProviderResult {
+            provider: "codex".into(),
+            result: Ok(agentusage::fixtures::mock_usage("codex").unwrap()),
+            elapsed: Duration::from_secs(1),
         };
+        let line = stream_line_json(&ok, &ProviderRegistry::builtin());
+        assert_eq!(line["provider"], "codex");
+        assert_eq!(line["success"], true);
+        assert_eq!(line["result"]["session"]["percent_used"], 23);
+        assert_eq!(line["result"]["session"]["label"], "5h limit");
+        assert_eq!(line["check"]["attempts"], 1);
 
-        if all.results.is_empty() {
-            if cli.json {
-                let stripped_warnings: BTreeMap<String, String> = all
-                    .warnings
-                    .iter()
-                    .map(|(k, v)| (k.clone(), strip_error_tags(v)))
-                    .collect();
-                let wrapper = serde_json::json!({
-                    "success": false,
-                    "results": {},
-                    "warnings": stripped_warnings,
-                    "error": "All providers failed.",
-                });
-                println!("{}", serde_json::to_string_pretty(&wrapper).unwrap());
-            } else {
-                for (provider, msg) in &all.warnings {
-                    eprintln!("Warning ({}): {}", provider, strip_error_tags(msg));
-                }
-                eprintln!("Error: All providers failed.");
-            }
-            std::process::exit(1);
-        }
-
-        if cli.json {
-            if let Err(e) = print_json_multi(&all) {
-                eprintln!("Error formatting JSON: {}", e);
-                std::process::exit(1);
-            }
-        } else {
-            for (provider, msg) in &all.warnings {
-                eprintln!("Warning ({}): {}", provider, strip_error_tags(msg));
-            }
-            print_human_multi(&all.results);
-        }
+        let failed = ProviderResult {
+            provider: "gemini".into(),
+            result: Err(anyhow::anyhow!("[timeout] Timed out")),
+            elapsed: Duration::from_secs(45),
+        };
+        let line = stream_line_json(&failed, &ProviderRegistry::builtin());
+        assert_eq!(line["success"], false);
+        assert_eq!(line["error"], "Timed out");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use agentusage::UsageEntry;
+    #[test]
+    fn test_cli_stream_conflicts_with_json() {
+        assert!(Cli::try_parse_from(["agentusage", "--stream"]).is_ok());
+        assert!(Cli::try_parse_from(["agentusage", "--stream", "--json"]).is_err());
+        assert!(Cli::try_parse_from(["agentusage", "--stream", "--query", "success"]).is_err());
+    }
 
-    // ── exit_code_from_error ────────────────────────────────────────
+    // ── Diagnostics ───────────────────────────────────────────────
 
     #[test]
-    fn test_exit_code_tool_missing() {
+    fn test_diagnostic_json_shape() {
+        let json = diagnostic_json(
+            "warning",
+            Some("codex"),
+            "timed out",
+            serde_json::Value::Null,
+        );
         assert_eq!(
-            exit_code_from_error("[tool-missing] claude CLI not found"),
-            2
+            json,
+            serde_json::json!({"level": "warning", "provider": "codex", "message": "timed out"})
         );
+        let json = diagnostic_json(
+            "verbose",
+            Some("claude"),
+            "final screen",
+            serde_json::json!({"screen": "final", "text": "45% used"}),
+        );
+        assert_eq!(json["screen"], "final");
+        assert_eq!(json["text"], "45% used");
+        assert!(diagnostic_json("error", None, "x", serde_json::Value::Null)
+            .get("provider")
+            .is_none());
     }
 
     #[test]
-    fn test_exit_code_timeout() {
-        assert_eq!(exit_code_from_error("[timeout] Timed out after 45s"), 3);
+    fn test_cli_diagnostics_mode() {
+        let cli = Cli::try_parse_from(["agentusage"]).unwrap();
+        assert_eq!(cli.diagnostics, DiagnosticsMode::Text);
+        let cli = Cli::try_parse_from(["agentusage", "--json", "--diagnostics", "json"]).unwrap();
+        assert_eq!(cli.diagnostics, DiagnosticsMode::Json);
     }
 
+    // ── NDJSON events ─────────────────────────────────────────────
+
     #[test]
-    fn test_exit_code_parse_failure() {
-        assert_eq!(
-            exit_code_from_error("[parse-failure] No usage data found"),
-            4
-        );
+    fn test_check_event_json_phase() {
+        let json = check_event_json(&CheckEvent::Phase(CheckPhase::WaitingForPrompt));
+        assert_eq!(json["event"], "phase");
+        assert_eq!(json["phase"], "waiting_for_prompt");
     }
 
     #[test]
-    fn test_exit_code_general() {
-        assert_eq!(exit_code_from_error("something else went wrong"), 1);
+    fn test_check_event_json_dialog() {
+        let json = check_event_json(&CheckEvent::DialogDismissed(
+            agentusage::DialogKind::TrustFolder,
+        ));
+        assert_eq!(json["event"], "dialog_dismissed");
+        assert_eq!(json["dialog"], "trust_folder");
     }
 
     #[test]
-    fn test_exit_code_empty_string() {
-        assert_eq!(exit_code_from_error(""), 1);
+    fn test_check_event_json_retry() {
+        let json = check_event_json(&CheckEvent::Retry("recapture_wider"));
+        assert_eq!(json["event"], "retry");
+        assert_eq!(json["reason"], "recapture_wider");
     }
 
     #[test]
-    fn test_exit_code_tag_embedded_in_context() {
-        // anyhow context wrapping: "outer: [timeout] inner"
-        assert_eq!(
-            exit_code_from_error("Timed out waiting for prompt: [timeout] Timed out after 30s"),
-            3
-        );
+    fn test_check_event_json_fallback() {
+        let json = check_event_json(&CheckEvent::Fallback("usage_fallback_status"));
+        assert_eq!(json["event"], "fallback");
+        assert_eq!(json["reason"], "usage_fallback_status");
     }
 
-    // ── strip_error_tags ────────────────────────────────────────────
-
     #[test]
-    fn test_strip_tool_missing_tag() {
+    fn test_check_stats_json_shape() {
+        let stats = CheckStats {
+            attempts: 2,
+            fallbacks: 1,
+            dialogs: 3,
+            partial: false,
+            log: Vec::new(),
+            source: agentusage::DataSource::Tui,
+        };
         assert_eq!(
-            strip_error_tags("[tool-missing] claude CLI not found"),
-            "claude CLI not found"
+            serde_json::json!(stats),
+            serde_json::json!({"attempts": 2, "fallbacks": 1, "dialogs": 3})
         );
+        let partial = CheckStats {
+            partial: true,
+            ..stats
+        };
+        assert_eq!(serde_json::json!(partial)["partial"], true);
     }
 
     #[test]
-    fn test_strip_timeout_tag() {
+    fn test_json_document_includes_capabilities() {
+        let document = json_document(&sample_usage("gemini"), &ProviderRegistry::builtin());
         assert_eq!(
-            strip_error_tags("[timeout] Timed out after 45s"),
-            "Timed out after 45s"
+            document["capabilities"]["gemini"],
+            serde_json::json!({
+                "supports_spend": false,
+                "supports_requests": true,
+                "supports_reset_minutes": true,
+                "source": "tui",
+            })
         );
+        let all = AllResults {
+            results: vec![sample_usage("claude")],
+            warnings: BTreeMap::new(),
+            attempts: BTreeMap::new(),
+        };
+        let document = json_document_multi(&all, &ProviderRegistry::builtin());
+        assert_eq!(document["capabilities"]["claude"]["supports_spend"], true);
     }
 
     #[test]
-    fn test_strip_parse_failure_tag() {
+    fn test_extra_usage_note() {
+        let mut data = sample_usage("claude");
+        assert_eq!(extra_usage_note(&data), None);
+        data.extra_usage_enabled = Some(true);
         assert_eq!(
-            strip_error_tags("[parse-failure] No usage data found"),
-            "No usage data found"
+            extra_usage_note(&data).as_deref(),
+            Some("Claude extra usage: on")
         );
     }
 
     #[test]
-    fn test_strip_no_tags() {
-        assert_eq!(strip_error_tags("plain error"), "plain error");
-    }
-
-    #[test]
-    fn test_strip_multiple_tags_in_chained_error() {
-        // anyhow can chain errors: "context: [timeout] inner message"
-        let msg = "Waiting failed: [timeout] Timed out after 30s";
-        let stripped = strip_error_tags(msg);
-        assert_eq!(stripped, "Waiting failed: Timed out after 30s");
-    }
-
-    // ── CLI flag parsing ──────────────────────────────────────────
-
-    #[test]
-    fn test_cli_default_no_flags() {
-        let cli = Cli::try_parse_from(["agentusage"]).unwrap();
-        assert!(!cli.claude);
-        assert!(!cli.codex);
-        assert!(!cli.gemini);
-    }
-
-    #[test]
-    fn test_cli_claude_flag() {
-        let cli = Cli::try_parse_from(["agentusage", "--claude"]).unwrap();
-        assert!(cli.claude);
-        assert!(!cli.codex);
-        assert!(!cli.gemini);
+    fn test_json_document_reports_tokens() {
+        let mut data = sample_usage("claude");
+        assert!(json_document(&data, &ProviderRegistry::builtin())
+            .get("tokens")
+            .is_none());
+        data.tokens = vec![agentusage::TokenUsage {
+            model: "claude-sonnet-4-5".into(),
+            input_tokens: 150,
+            output_tokens: 750,
+            cost_usd: Some(0.03),
+            ..Default::default()
+        }];
+        let doc = json_document(&data, &ProviderRegistry::builtin());
+        assert!(doc["results"]["claude"]["session"].is_object());
+        assert_eq!(doc["tokens"]["claude"][0]["output_tokens"], 750);
+        assert_eq!(doc["tokens"]["claude"][0]["cost_usd"], 0.03);
     }
 
     #[test]
-    fn test_cli_codex_flag() {
-        let cli = Cli::try_parse_from(["agentusage", "--codex"]).unwrap();
-        assert!(!cli.claude);
-        assert!(cli.codex);
+    fn test_json_document_reports_extra_usage_state() {
+        let mut data = sample_usage("claude");
+        assert!(json_document(&data, &ProviderRegistry::builtin())
+            .get("extra_usage_enabled")
+            .is_none());
+        data.extra_usage_enabled = Some(false);
+        let all = AllResults {
+            results: vec![data.clone(), sample_usage("codex")],
+            warnings: BTreeMap::new(),
+            attempts: BTreeMap::new(),
+        };
+        assert_eq!(
+            json_document(&data, &ProviderRegistry::builtin())["extra_usage_enabled"],
+            serde_json::json!({ "claude": false })
+        );
+        assert_eq!(
+            json_document_multi(&all, &ProviderRegistry::builtin())["extra_usage_enabled"],
+            serde_json::json!({ "claude": false })
+        );
     }
 
     #[test]
-    fn test_cli_gemini_flag() {
-        let cli = Cli::try_parse_from(["agentusage", "--gemini"]).unwrap();
-        assert!(!cli.claude);
-        assert!(cli.gemini);
+    fn test_finish_event_json_failed_strips_tags() {
+        let result: Result<UsageData> = Err(anyhow::anyhow!("[timeout] Timed out after 45s"));
+        let json = finish_event_json(&result, Duration::from_millis(1500));
+        assert_eq!(json["event"], "failed");
+        assert_eq!(json["duration_ms"], 1500);
+        assert_eq!(json["error"], "Timed out after 45s");
     }
 
     #[test]
-    fn test_cli_conflicting_provider_flags_error() {
-        // Multiple provider flags should produce a clap error
-        assert!(Cli::try_parse_from(["agentusage", "--claude", "--codex"]).is_err());
-        assert!(Cli::try_parse_from(["agentusage", "--claude", "--gemini"]).is_err());
-        assert!(Cli::try_parse_from(["agentusage", "--codex", "--gemini"]).is_err());
-        assert!(Cli::try_parse_from(["agentusage", "--claude", "--codex", "--gemini"]).is_err());
+    fn test_finish_event_json_done_counts_entries() {
+        let result: Result<UsageData> = Ok(sample_usage("claude"));
+        let json = finish_event_json(&result, Duration::from_secs(2));
+        assert_eq!(json["event"], "done");
+        assert_eq!(json["entries"], 1);
     }
 
     #[test]
@@ -832,15 +4451,61 @@ mod tests {
                 reset_minutes: None,
                 spent: None,
                 requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Session,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
         }
     }
 
+    // ── Status lines ──────────────────────────────────────────────
+
+    #[test]
+    fn test_status_line_running_shows_phase_and_elapsed() {
+        let line = status_line(
+            "codex",
+            ProviderStatus::Running(CheckPhase::WaitingForPrompt),
+            '⠋',
+            Duration::from_secs(12),
+        );
+        assert_eq!(line, "⠋ codex: waiting for prompt (12s)");
+    }
+
+    #[test]
+    fn test_status_line_done_shows_duration() {
+        let line = status_line(
+            "claude",
+            ProviderStatus::Done(Duration::from_millis(23_400)),
+            '⠋',
+            Duration::from_secs(30),
+        );
+        assert!(line.contains("claude: done (23.4s)"));
+    }
+
+    #[test]
+    fn test_status_line_failed_shows_duration() {
+        let line = status_line(
+            "gemini",
+            ProviderStatus::Failed(Duration::from_secs(45)),
+            '⠋',
+            Duration::from_secs(45),
+        );
+        assert!(line.contains("gemini: failed (45.0s)"));
+    }
+
     #[test]
     fn test_json_multi_structure_no_warnings() {
         let all = AllResults {
             results: vec![sample_usage("claude")],
             warnings: BTreeMap::new(),
+            attempts: BTreeMap::new(),
         };
         let mut results = serde_json::Map::new();
         for data in &all.results {
@@ -866,6 +4531,7 @@ mod tests {
         let all = AllResults {
             results: vec![sample_usage("claude")],
             warnings,
+            attempts: BTreeMap::new(),
         };
         let mut results = serde_json::Map::new();
         for data in &all.results {
@@ -886,6 +4552,36 @@ mod tests {
         assert_eq!(warnings["codex"], "tool not found");
     }
 
+    #[test]
+    fn test_json_multi_lists_attempts() {
+        let attempt = |kind, reason| agentusage::Attempt {
+            kind,
+            reason,
+            started_ms: 0,
+            warnings: vec![],
+        };
+        let mut claude = sample_usage("claude");
+        claude.check.log = vec![attempt(agentusage::AttemptKind::Initial, "initial")];
+        let all = AllResults {
+            results: vec![claude],
+            warnings: BTreeMap::from([("codex".to_string(), "timed out".to_string())]),
+            attempts: BTreeMap::from([(
+                "codex".to_string(),
+                vec![
+                    attempt(agentusage::AttemptKind::Initial, "initial"),
+                    attempt(agentusage::AttemptKind::Retry, "esc_probe"),
+                ],
+            )]),
+        };
+        let mut document = json_document_multi(&all, &ProviderRegistry::builtin());
+        assert_eq!(document["attempts"]["codex"][1]["kind"], "retry");
+        assert_eq!(document["attempts"]["codex"][1]["reason"], "esc_probe");
+        assert!(document["attempts"].get("claude").is_none());
+
+        add_attempt_logs(&mut document, &all.results);
+        assert_eq!(document["attempts"]["claude"][0]["kind"], "initial");
+    }
+
     #[test]
     fn test_json_multi_multiple_results() {
         let mut warnings = BTreeMap::new();
@@ -893,6 +4589,7 @@ mod tests {
         let all = AllResults {
             results: vec![sample_usage("claude"), sample_usage("gemini")],
             warnings,
+            attempts: BTreeMap::new(),
         };
         let mut results = serde_json::Map::new();
         for data in &all.results {
@@ -921,6 +4618,7 @@ mod tests {
         let all = AllResults {
             results: vec![],
             warnings,
+            attempts: BTreeMap::new(),
         };
         assert!(all.results.is_empty());
         assert_eq!(all.warnings.len(), 3);
@@ -943,6 +4641,34 @@ mod tests {
         assert!(!entry.contains_key("reset_days"));
         // spent is None, should be absent
         assert!(!entry.contains_key("spent"));
+        assert_eq!(entry["confidence"], "exact");
+    }
+
+    #[test]
+    fn test_build_provider_json_marks_stale_entries() {
+        let mut data = sample_usage("claude");
+        let json = build_provider_json(&data);
+        assert!(!json["session"].as_object().unwrap().contains_key("stale"));
+
+        data.entries[0].as_of = Some("2026-03-01T12:00:00Z".parse().unwrap());
+        let json = build_provider_json(&data);
+        assert_eq!(json["session"]["stale"], true);
+        assert_eq!(json["session"]["as_of"], "2026-03-01T12:00:00+00:00");
+        assert!(label_cell(&data.entries[0]).starts_with("session (as of "));
+    }
+
+    #[test]
+    fn test_build_provider_json_marks_inconsistent_entries() {
+        let mut data = sample_usage("claude");
+        let json = build_provider_json(&data);
+        assert!(!json["session"]
+            .as_object()
+            .unwrap()
+            .contains_key("inconsistent"));
+
+        data.entries[0].inconsistent = true;
+        let json = build_provider_json(&data);
+        assert_eq!(json["session"]["inconsistent"], true);
     }
 
     #[test]
@@ -958,7 +4684,17 @@ mod tests {
                 reset_minutes: Some(90),
                 spent: None,
                 requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Session,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
         };
 
         let json = build_provider_json(&data);
@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::observer::CheckObserver;
+use crate::types::{DialogKind, UsageData};
+
+/// Upper bounds, in seconds, of the scrape duration histogram's buckets.
+const DURATION_BUCKETS: [f64; 9] = [1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 45.0, 60.0, 120.0];
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Observations at or under each of `DURATION_BUCKETS`.
+    buckets: [u64; DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    durations: BTreeMap<String, Histogram>,
+    /// (provider, "success" or "failure") → scrapes.
+    scrapes: BTreeMap<(String, &'static str), u64>,
+    cache_hits: u64,
+    cache_misses: u64,
+    /// (provider, dialog id) → dialogs seen.
+    dialogs: BTreeMap<(String, &'static str), u64>,
+}
+
+/// A long-running host's own operational metrics, kept apart from the
+/// usage it reads: how long scrapes take and how often they fail, how many
+/// requests the cache answers, and which dialogs providers showed.  As a
+/// `CheckObserver` in `UsageConfig::observers` it counts dialogs itself.
+#[derive(Debug, Default)]
+pub struct SelfMetrics {
+    counters: Mutex<Counters>,
+}
+
+impl SelfMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A check of `provider` finished after `took`.
+    pub fn record_scrape(&self, provider: &str, took: Duration, success: bool) {
+        let mut counters = self.lock();
+        let outcome = if success { "success" } else { "failure" };
+        *counters
+            .scrapes
+            .entry((provider.to_string(), outcome))
+            .or_default() += 1;
+        let histogram = counters.durations.entry(provider.to_string()).or_default();
+        let secs = took.as_secs_f64();
+        for (bucket, le) in histogram.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if secs <= le {
+                *bucket += 1;
+            }
+        }
+        histogram.count += 1;
+        histogram.sum += secs;
+    }
+
+    /// A request was answered from the cache (`hit`) or needed a check.
+    pub fn record_cache(&self, hit: bool) {
+        let mut counters = self.lock();
+        if hit {
+            counters.cache_hits += 1;
+        } else {
+            counters.cache_misses += 1;
+        }
+    }
+
+    /// The metrics in the Prometheus text format.
+    pub fn to_prometheus(&self) -> String {
+        let counters = self.lock();
+        let mut out = String::new();
+        header(
+            &mut out,
+            "agentusage_scrape_duration_seconds",
+            "histogram",
+            "How long provider checks took.",
+        );
+        for (provider, histogram) in &counters.durations {
+            let provider = escape(provider);
+            for (count, le) in histogram.buckets.iter().zip(DURATION_BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "agentusage_scrape_duration_seconds_bucket{{provider=\"{provider}\",le=\"{le}\"}} {count}"
+                );
+            }
+            let (count, sum) = (histogram.count, histogram.sum);
+            let _ = writeln!(
+                out,
+                "agentusage_scrape_duration_seconds_bucket{{provider=\"{provider}\",le=\"+Inf\"}} {count}"
+            );
+            let _ = writeln!(
+                out,
+                "agentusage_scrape_duration_seconds_sum{{provider=\"{provider}\"}} {sum}"
+            );
+            let _ = writeln!(
+                out,
+                "agentusage_scrape_duration_seconds_count{{provider=\"{provider}\"}} {count}"
+            );
+        }
+        header(
+            &mut out,
+            "agentusage_scrapes_total",
+            "counter",
+            "Provider checks run, by outcome.",
+        );
+        for ((provider, outcome), count) in &counters.scrapes {
+            let provider = escape(provider);
+            let _ = writeln!(
+                out,
+                "agentusage_scrapes_total{{provider=\"{provider}\",outcome=\"{outcome}\"}} {count}"
+            );
+        }
+        header(
+            &mut out,
+            "agentusage_cache_requests_total",
+            "counter",
+            "Requests for a provider's usage, by whether the cache answered.",
+        );
+        let (hits, misses) = (counters.cache_hits, counters.cache_misses);
+        let _ = writeln!(
+            out,
+            "agentusage_cache_requests_total{{result=\"hit\"}} {hits}"
+        );
+        let _ = writeln!(
+            out,
+            "agentusage_cache_requests_total{{result=\"miss\"}} {misses}"
+        );
+        header(
+            &mut out,
+            "agentusage_dialogs_total",
+            "counter",
+            "Dialogs providers showed during checks, by kind.",
+        );
+        for ((provider, kind), count) in &counters.dialogs {
+            let provider = escape(provider);
+            let _ = writeln!(
+                out,
+                "agentusage_dialogs_total{{provider=\"{provider}\",kind=\"{kind}\"}} {count}"
+            );
+        }
+        out
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Counters> {
+        self.counters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CheckObserver for SelfMetrics {
+    fn on_dialog(&self, provider: &str, kind: &DialogKind, dismissed: bool) {
+        // Count each dialog once, when it is detected.
+        if !dismissed {
+            *self
+                .lock()
+                .dialogs
+                .entry((provider.to_string(), kind.id()))
+                .or_default() += 1;
+        }
+    }
+}
+
+/// Each limit in `results` as Prometheus gauges: percent used and seconds
+/// until it resets.
+pub fn usage_gauges(results: &[UsageData]) -> String {
+    let mut out = String::new();
+    header(
+        &mut out,
+        "agentusage_percent_used",
+        "gauge",
+        "Percent of the limit used.",
+    );
+    for data in results {
+        for entry in &data.entries {
+            let _ = writeln!(
+                out,
+                "agentusage_percent_used{{provider=\"{}\",limit=\"{}\",window=\"{}\"}} {}",
+                escape(&data.provider),
+                escape(&entry.label),
+                entry.window.as_str(),
+                entry.percent_used
+            );
+        }
+    }
+    header(
+        &mut out,
+        "agentusage_reset_seconds",
+        "gauge",
+        "Seconds until the limit resets.",
+    );
+    for data in results {
+        for entry in &data.entries {
+            let Some(minutes) = entry.reset_minutes else {
+                continue;
+            };
+            let _ = writeln!(
+                out,
+                "agentusage_reset_seconds{{provider=\"{}\",limit=\"{}\",window=\"{}\"}} {}",
+                escape(&data.provider),
+                escape(&entry.label),
+                entry.window.as_str(),
+                minutes * 60
+            );
+        }
+    }
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// `value` escaped for a label: backslash, double quote, and newline.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::mock_usage;
+
+    #[test]
+    fn test_self_metrics() {
+        let metrics = SelfMetrics::new();
+        metrics.record_scrape("claude", Duration::from_secs_f64(7.5), true);
+        metrics.record_scrape("claude", Duration::from_secs(50), false);
+        metrics.record_cache(true);
+        metrics.record_cache(true);
+        metrics.record_cache(false);
+        metrics.on_dialog("codex", &DialogKind::TrustFolder, false);
+        metrics.on_dialog("codex", &DialogKind::TrustFolder, true);
+
+        let text = metrics.to_prometheus();
+        for line in [
+            "# TYPE agentusage_scrape_duration_seconds histogram",
+            "agentusage_scrape_duration_seconds_bucket{provider=\"claude\",le=\"5\"} 0",
+            "agentusage_scrape_duration_seconds_bucket{provider=\"claude\",le=\"10\"} 1",
+            "agentusage_scrape_duration_seconds_bucket{provider=\"claude\",le=\"60\"} 2",
+            "agentusage_scrape_duration_seconds_bucket{provider=\"claude\",le=\"+Inf\"} 2",
+            "agentusage_scrape_duration_seconds_sum{provider=\"claude\"} 57.5",
+            "agentusage_scrapes_total{provider=\"claude\",outcome=\"failure\"} 1",
+            "agentusage_scrapes_total{provider=\"claude\",outcome=\"success\"} 1",
+            "agentusage_cache_requests_total{result=\"hit\"} 2",
+            "agentusage_cache_requests_total{result=\"miss\"} 1",
+            "agentusage_dialogs_total{provider=\"codex\",kind=\"trust_folder\"} 1",
+        ] {
+            assert!(text.lines().any(|l| l == line), "{}\n{}", line, text);
+        }
+    }
+
+    #[test]
+    fn test_usage_gauges() {
+        let text = usage_gauges(&[mock_usage("codex").unwrap()]);
+        assert!(text.contains(
+            "agentusage_percent_used{provider=\"codex\",limit=\"5h limit\",window=\"session\"} 23"
+        ));
+        assert!(text.contains(
+            "agentusage_reset_seconds{provider=\"codex\",limit=\"5h limit\",window=\"session\"} 8100"
+        ));
+        assert_eq!(escape("a \"b\"\n"), "a \\\"b\\\"\\n");
+    }
+}
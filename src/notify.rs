@@ -0,0 +1,733 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use crate::alert::{AlertTracker, AlertTransition};
+use crate::thresholds::Thresholds;
+use crate::types::{UsageData, UsageEntry};
+
+/// How long a webhook may take to accept a notification.
+#[cfg(feature = "http")]
+const WEBHOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// `$XDG_CONFIG_HOME/agentusage/notify.toml` (default
+/// `~/.config/agentusage/notify.toml`).
+pub fn default_path() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    let config_home = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("agentusage").join("notify.toml"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Low,
+    Failed,
+}
+
+/// Something a route can send to its sinks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    /// A limit reached its threshold, or has less than the route's
+    /// `below` percent remaining.
+    Low {
+        provider: String,
+        label: String,
+        percent_used: u32,
+        percent_remaining: u32,
+        reset_info: String,
+    },
+    /// A provider's check failed.
+    Failed { provider: String, error: String },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::Low { .. } => EventKind::Low,
+            Event::Failed { .. } => EventKind::Failed,
+        }
+    }
+
+    pub fn provider(&self) -> &str {
+        match self {
+            Event::Low { provider, .. } | Event::Failed { provider, .. } => provider,
+        }
+    }
+
+    /// One line for people: "claude: Current session 92% used (Resets 2pm)".
+    pub fn summary(&self) -> String {
+        match self {
+            Event::Low {
+                provider,
+                label,
+                percent_used,
+                reset_info,
+                ..
+            } if reset_info.is_empty() => format!("{}: {} {}% used", provider, label, percent_used),
+            Event::Low {
+                provider,
+                label,
+                percent_used,
+                reset_info,
+                ..
+            } => format!(
+                "{}: {} {}% used ({})",
+                provider, label, percent_used, reset_info
+            ),
+            Event::Failed { provider, error } => {
+                let first = error.lines().next().unwrap_or_default();
+                format!("{}: check failed: {}", provider, first)
+            }
+        }
+    }
+}
+
+fn summaries(events: &[Event]) -> String {
+    events
+        .iter()
+        .map(Event::summary)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Somewhere notifications are delivered: a terminal bell, the desktop, a
+/// chat webhook.
+pub trait Notifier: Send + Sync {
+    /// Deliver `events` (never empty) as one notification.
+    fn notify(&self, events: &[Event]) -> Result<()>;
+}
+
+/// Ring the terminal bell, or play the system alert sound.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bell {
+    /// Play a sound, falling back to the terminal bell when no player works.
+    pub sound: bool,
+}
+
+impl Notifier for Bell {
+    fn notify(&self, _events: &[Event]) -> Result<()> {
+        if self.sound {
+            let players: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+                &[("afplay", &["/System/Library/Sounds/Glass.aiff"])]
+            } else {
+                &[
+                    ("canberra-gtk-play", &["-i", "bell"]),
+                    ("paplay", &["/usr/share/sounds/freedesktop/stereo/bell.oga"]),
+                ]
+            };
+            for (player, args) in players {
+                let played = Command::new(player)
+                    .args(*args)
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .is_ok_and(|status| status.success());
+                if played {
+                    return Ok(());
+                }
+            }
+        }
+        // stderr, so JSON on stdout stays clean.
+        eprint!("\x07");
+        let _ = std::io::stderr().flush();
+        Ok(())
+    }
+}
+
+/// A desktop notification (`notify-send`, or `osascript` on macOS).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Desktop;
+
+impl Notifier for Desktop {
+    fn notify(&self, events: &[Event]) -> Result<()> {
+        let body = summaries(events);
+        let status = if cfg!(target_os = "macos") {
+            let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+            let script = format!(
+                "display notification \"{}\" with title \"agentusage\"",
+                quote(&body)
+            );
+            Command::new("osascript").args(["-e", &script]).status()
+        } else {
+            Command::new("notify-send")
+                .args(["agentusage", &body])
+                .status()
+        };
+        match status {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => bail!("desktop notification failed ({})", status),
+            Err(e) => Err(e).context("Failed to show a desktop notification"),
+        }
+    }
+}
+
+/// Run a shell command with the events as JSON (`{"events": [...]}`) on
+/// stdin and their summaries in `AGENTUSAGE_SUMMARY`.
+#[derive(Debug, Clone)]
+pub struct CommandNotifier {
+    pub command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn notify(&self, events: &[Event]) -> Result<()> {
+        let mut child = Command::new("sh")
+            .args(["-c", &self.command])
+            .env("AGENTUSAGE_SUMMARY", summaries(events))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to run '{}'", self.command))?;
+        let payload = serde_json::json!({ "events": events }).to_string();
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that ignores its input may exit before reading it.
+            let _ = stdin.write_all(payload.as_bytes());
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            bail!("'{}' exited with {}", self.command, status);
+        }
+        Ok(())
+    }
+}
+
+/// How a webhook wants its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// `{"events": [...], "summary": "..."}`
+    Json,
+    /// A Slack incoming webhook: `{"text": "..."}`
+    Slack,
+    /// An ntfy topic URL: the summaries as the message body.
+    Ntfy,
+}
+
+/// POST the events to a URL.  Needs the `http` feature.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    pub format: WebhookFormat,
+}
+
+impl Notifier for Webhook {
+    #[cfg(feature = "http")]
+    fn notify(&self, events: &[Event]) -> Result<()> {
+        let summary = summaries(events);
+        let (headers, body): (&[(&str, &str)], String) = match self.format {
+            WebhookFormat::Json => (
+                &[("Content-Type", "application/json")],
+                serde_json::json!({ "events": events, "summary": summary }).to_string(),
+            ),
+            WebhookFormat::Slack => (
+                &[("Content-Type", "application/json")],
+                serde_json::json!({ "text": summary }).to_string(),
+            ),
+            WebhookFormat::Ntfy => (&[("Title", "agentusage")], summary),
+        };
+        crate::http::post(&self.url, headers, &body, WEBHOOK_TIMEOUT)
+    }
+
+    #[cfg(not(feature = "http"))]
+    fn notify(&self, _events: &[Event]) -> Result<()> {
+        bail!(
+            "built without the `http` feature; can't POST to {}",
+            self.url
+        )
+    }
+}
+
+/// Which events go to which sinks.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    /// Names of the sinks to notify.
+    pub sinks: Vec<String>,
+    /// Providers this route covers; empty covers every provider.
+    #[serde(default)]
+    pub providers: Vec<String>,
+    /// Event kinds this route sends; empty sends every kind.
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// A limit is low below this percent remaining; without it, once it
+    /// reaches its window's threshold.
+    #[serde(default)]
+    pub below: Option<u32>,
+}
+
+impl Route {
+    /// Every event kind, from every provider, to `sinks`.
+    pub fn to(sinks: &[&str]) -> Self {
+        Self {
+            sinks: sinks.iter().map(|sink| sink.to_string()).collect(),
+            providers: Vec::new(),
+            events: Vec::new(),
+            below: None,
+        }
+    }
+
+    fn wants(&self, kind: EventKind, provider: &str) -> bool {
+        (self.events.is_empty() || self.events.contains(&kind))
+            && (self.providers.is_empty() || self.providers.iter().any(|p| p == provider))
+    }
+
+    /// This route's events for one run: `results`, and `failures`
+    /// (provider → error).  Stale stand-ins from history aren't reported
+    /// as low; their failure is.
+    pub fn events(
+        &self,
+        results: &[UsageData],
+        failures: &BTreeMap<String, String>,
+        thresholds: &Thresholds,
+    ) -> Vec<Event> {
+        self.events_where(results, failures, thresholds, |_, _| true)
+    }
+
+    /// `events`, keeping only the low limits `keep` accepts.
+    fn events_where(
+        &self,
+        results: &[UsageData],
+        failures: &BTreeMap<String, String>,
+        thresholds: &Thresholds,
+        keep: impl Fn(&str, &UsageEntry) -> bool,
+    ) -> Vec<Event> {
+        let mut events = Vec::new();
+        for data in results {
+            if !self.wants(EventKind::Low, &data.provider) {
+                continue;
+            }
+            for entry in &data.entries {
+                let low = match self.below {
+                    Some(below) => entry.percent_remaining < below,
+                    None => thresholds.is_over(entry),
+                };
+                if entry.as_of.is_none() && low && keep(&data.provider, entry) {
+                    events.push(Event::Low {
+                        provider: data.provider.clone(),
+                        label: entry.label.clone(),
+                        percent_used: entry.percent_used,
+                        percent_remaining: entry.percent_remaining,
+                        reset_info: entry.reset_info.clone(),
+                    });
+                }
+            }
+        }
+        for (provider, error) in failures {
+            if self.wants(EventKind::Failed, provider) {
+                events.push(Event::Failed {
+                    provider: provider.clone(),
+                    error: error.clone(),
+                });
+            }
+        }
+        events
+    }
+
+    /// Where `entry` stands against the level it is low at, as the percent
+    /// used an `AlertTracker` compares with a trigger: the window's
+    /// threshold, or with `below`, the percent used that leaves less than
+    /// `below` remaining.
+    fn level(&self, entry: &UsageEntry, thresholds: &Thresholds) -> (u32, u32) {
+        match self.below {
+            Some(below) => (
+                100u32.saturating_sub(entry.percent_remaining),
+                101u32.saturating_sub(below),
+            ),
+            None => (entry.percent_used, thresholds.warn_at(entry.window)),
+        }
+    }
+}
+
+/// Named sinks and the routes that feed them.
+#[derive(Clone, Default)]
+pub struct Router {
+    sinks: BTreeMap<String, Arc<dyn Notifier>>,
+    routes: Vec<Route>,
+    thresholds: Thresholds,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sink, replacing any of the same name.
+    pub fn add_sink(&mut self, name: impl Into<String>, sink: Arc<dyn Notifier>) {
+        self.sinks.insert(name.into(), sink);
+    }
+
+    /// Add a route.  Fails if it names a sink that hasn't been added.
+    pub fn add_route(&mut self, route: Route) -> Result<()> {
+        if let Some(missing) = route.sinks.iter().find(|s| !self.sinks.contains_key(*s)) {
+            bail!("route sends to unknown sink '{}'", missing);
+        }
+        self.routes.push(route);
+        Ok(())
+    }
+
+    /// The thresholds routes without `below` report low limits at.
+    pub fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Send each route's events to its sinks.  A sink that several routes
+    /// feed is notified once, with their events combined.  Returns the
+    /// sinks that failed, with why.
+    pub fn dispatch(
+        &self,
+        results: &[UsageData],
+        failures: &BTreeMap<String, String>,
+    ) -> Vec<(String, anyhow::Error)> {
+        self.send(|route| route.events(results, failures, &self.thresholds))
+    }
+
+    /// `dispatch`, sending a low limit only when `tracker` raises it at
+    /// the route's level: once as it crosses, then not again until it has
+    /// cleared and the cool-down has passed.  Failed checks are always
+    /// sent.
+    pub fn dispatch_tracked(
+        &self,
+        results: &[UsageData],
+        failures: &BTreeMap<String, String>,
+        tracker: &mut AlertTracker,
+        now: DateTime<Utc>,
+    ) -> Vec<(String, anyhow::Error)> {
+        // Each limit is observed once per level, however many routes
+        // watch it there.
+        let mut seen = BTreeSet::new();
+        let mut raised = BTreeSet::new();
+        for route in &self.routes {
+            for data in results
+                .iter()
+                .filter(|data| route.wants(EventKind::Low, &data.provider))
+            {
+                for entry in data.entries.iter().filter(|entry| entry.as_of.is_none()) {
+                    let (level, trigger) = route.level(entry, &self.thresholds);
+                    let key = (data.provider.as_str(), entry.label.as_str(), trigger);
+                    if seen.insert(key)
+                        && tracker.observe_limit(key.0, key.1, level, trigger, now)
+                            == Some(AlertTransition::Raised)
+                    {
+                        raised.insert(key);
+                    }
+                }
+            }
+        }
+        self.send(|route| {
+            route.events_where(results, failures, &self.thresholds, |provider, entry| {
+                let (_, trigger) = route.level(entry, &self.thresholds);
+                raised.contains(&(provider, entry.label.as_str(), trigger))
+            })
+        })
+    }
+
+    /// Send each route's `events` to its sinks, once per sink.
+    fn send(&self, events: impl Fn(&Route) -> Vec<Event>) -> Vec<(String, anyhow::Error)> {
+        let mut outbox: BTreeMap<&str, Vec<Event>> = BTreeMap::new();
+        for route in &self.routes {
+            let events = events(route);
+            if events.is_empty() {
+                continue;
+            }
+            for sink in &route.sinks {
+                let queued = outbox.entry(sink.as_str()).or_default();
+                for event in &events {
+                    if !queued.contains(event) {
+                        queued.push(event.clone());
+                    }
+                }
+            }
+        }
+        outbox
+            .into_iter()
+            .filter_map(|(name, events)| {
+                let sink = self.sinks.get(name)?;
+                sink.notify(&events).err().map(|e| (name.to_string(), e))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NotifyFile {
+    #[serde(default)]
+    sink: Vec<SinkEntry>,
+    #[serde(default)]
+    route: Vec<Route>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SinkKind {
+    Bell,
+    Sound,
+    Desktop,
+    Command,
+    Webhook,
+    Slack,
+    Ntfy,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SinkEntry {
+    name: String,
+    #[serde(rename = "type")]
+    kind: SinkKind,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    command: Option<String>,
+}
+
+impl SinkEntry {
+    fn build(self) -> Result<Arc<dyn Notifier>> {
+        let name = self.name;
+        let url = |format| match self.url.clone() {
+            Some(url) => Ok(Arc::new(Webhook { url, format }) as Arc<dyn Notifier>),
+            None => bail!("sink '{}' needs a url", name),
+        };
+        Ok(match self.kind {
+            SinkKind::Bell => Arc::new(Bell { sound: false }),
+            SinkKind::Sound => Arc::new(Bell { sound: true }),
+            SinkKind::Desktop => Arc::new(Desktop),
+            SinkKind::Command => match self.command {
+                Some(command) => Arc::new(CommandNotifier { command }),
+                None => bail!("sink '{}' needs a command", name),
+            },
+            SinkKind::Webhook => url(WebhookFormat::Json)?,
+            SinkKind::Slack => url(WebhookFormat::Slack)?,
+            SinkKind::Ntfy => url(WebhookFormat::Ntfy)?,
+        })
+    }
+}
+
+/// Parse a `notify.toml`: `[[sink]]` tables (`name`, `type`, and `url` or
+/// `command`) and `[[route]]` tables.
+pub fn parse_config(text: &str) -> Result<Router> {
+    let file: NotifyFile = toml::from_str(text).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let mut router = Router::new();
+    for entry in file.sink {
+        let name = entry.name.clone();
+        router.add_sink(name, entry.build()?);
+    }
+    for route in file.route {
+        router.add_route(route)?;
+    }
+    Ok(router)
+}
+
+/// Load the routing rules at `path`; a missing file has none.
+pub fn load(path: &Path) -> Result<Router> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => parse_config(&text).with_context(|| format!("Invalid {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Router::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// `load` the default `notify.toml`.
+pub fn load_default() -> Result<Router> {
+    match default_path() {
+        Some(path) => load(&path),
+        None => Ok(Router::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::TempDir;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Vec<Event>>>);
+
+    impl Notifier for Recorder {
+        fn notify(&self, events: &[Event]) -> Result<()> {
+            self.0.lock().unwrap().push(events.to_vec());
+            Ok(())
+        }
+    }
+
+    fn usage(provider: &str, remaining: &[u32]) -> UsageData {
+        let mut data = crate::fixtures::mock_usage(provider).unwrap();
+        data.entries.truncate(remaining.len());
+        for (entry, remaining) in data.entries.iter_mut().zip(remaining) {
+            entry.percent_remaining = *remaining;
+            entry.percent_used = 100 - remaining;
+        }
+        data
+    }
+
+    #[test]
+    fn test_routes_pick_events_per_sink() {
+        let phone = Arc::new(Recorder::default());
+        let team = Arc::new(Recorder::default());
+        let mut router = Router::new();
+        router.add_sink("phone", phone.clone());
+        router.add_sink("team", team.clone());
+        router.add_route(Route::to(&["phone"])).unwrap();
+        router
+            .add_route(Route {
+                providers: vec!["claude".into()],
+                events: vec![EventKind::Low],
+                below: Some(30),
+                ..Route::to(&["team", "phone"])
+            })
+            .unwrap();
+        assert!(router.add_route(Route::to(&["pager"])).is_err());
+
+        let results = [usage("claude", &[25, 5]), usage("codex", &[50])];
+        let failures = BTreeMap::from([("gemini".to_string(), "[timeout] no prompt".to_string())]);
+        assert!(router.dispatch(&results, &failures).is_empty());
+
+        let team = team.0.lock().unwrap();
+        assert_eq!(team.len(), 1);
+        let kinds: Vec<(&str, u32)> = team[0]
+            .iter()
+            .map(|e| match e {
+                Event::Low {
+                    provider,
+                    percent_remaining,
+                    ..
+                } => (provider.as_str(), *percent_remaining),
+                Event::Failed { .. } => panic!("team only gets low limits"),
+            })
+            .collect();
+        assert_eq!(kinds, [("claude", 25), ("claude", 5)]);
+
+        // Both routes feed the phone: one notification, each event once.
+        let phone = phone.0.lock().unwrap();
+        assert_eq!(phone.len(), 1);
+        assert_eq!(phone[0].len(), 3);
+        assert_eq!(phone[0][1].kind(), EventKind::Failed);
+        assert_eq!(
+            phone[0][1].summary(),
+            "gemini: check failed: [timeout] no prompt"
+        );
+    }
+
+    #[test]
+    fn test_nothing_to_report_sends_nothing() {
+        let sink = Arc::new(Recorder::default());
+        let mut router = Router::new();
+        router.add_sink("bell", sink.clone());
+        router.add_route(Route::to(&["bell"])).unwrap();
+        let mut stale = usage("claude", &[1]);
+        stale.entries[0].as_of = Some(chrono::Utc::now());
+        router.dispatch(&[usage("codex", &[50]), stale], &BTreeMap::new());
+        assert!(sink.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_routes_without_below_use_window_thresholds() {
+        let sink = Arc::new(Recorder::default());
+        let mut router = Router::new();
+        router.add_sink("bell", sink.clone());
+        router.add_route(Route::to(&["bell"])).unwrap();
+        let results = [usage("codex", &[50])];
+        router.dispatch(&results, &BTreeMap::new());
+        assert!(sink.0.lock().unwrap().is_empty());
+
+        let mut thresholds = Thresholds::default();
+        thresholds.set(Some(crate::types::LimitWindow::Session), 50);
+        router.set_thresholds(thresholds);
+        router.dispatch(&results, &BTreeMap::new());
+        assert_eq!(sink.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_tracked_dispatch_sends_each_crossing_once() {
+        let sink = Arc::new(Recorder::default());
+        let mut router = Router::new();
+        router.add_sink("bell", sink.clone());
+        router.add_route(Route::to(&["bell"])).unwrap();
+        router
+            .add_route(Route {
+                below: Some(30),
+                ..Route::to(&["bell"])
+            })
+            .unwrap();
+        let mut tracker = AlertTracker::new(crate::alert::AlertPolicy::default());
+        let start = chrono::Utc::now();
+        let mut run = |remaining: u32, minute: i64| {
+            let now = start + chrono::Duration::minutes(minute);
+            router.dispatch_tracked(
+                &[usage("codex", &[remaining])],
+                &BTreeMap::new(),
+                &mut tracker,
+                now,
+            );
+            let sent = sink.0.lock().unwrap().drain(..).collect::<Vec<_>>();
+            sent.concat().len()
+        };
+        // Low for `below`, then for the threshold too, then still low:
+        // each level notifies once as it is crossed.
+        assert_eq!(run(25, 0), 1);
+        assert_eq!(run(5, 1), 1);
+        assert_eq!(run(4, 2), 0);
+        // Cleared below both levels, and low again after the cool-down.
+        assert_eq!(run(90, 3), 0);
+        assert_eq!(run(5, 60), 1);
+    }
+
+    #[test]
+    fn test_parse_config_and_command_sink() {
+        let dir = TempDir::create("notify").unwrap();
+        let out = dir.path().join("out.json");
+        let text = format!(
+            r#"
+[[sink]]
+name = "log"
+type = "command"
+command = "cat > '{}'"
+
+[[sink]]
+name = "phone"
+type = "ntfy"
+url = "https://ntfy.example/agentusage"
+
+[[route]]
+sinks = ["log"]
+events = ["failed"]
+providers = ["codex"]
+"#,
+            out.display()
+        );
+        let router = parse_config(&text).unwrap();
+        let failures = BTreeMap::from([
+            ("codex".to_string(), "not signed in".to_string()),
+            ("claude".to_string(), "not signed in".to_string()),
+        ]);
+        assert!(router.dispatch(&[], &failures).is_empty());
+        let sent: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(
+            sent,
+            serde_json::json!({"events": [
+                {"event": "failed", "provider": "codex", "error": "not signed in"}
+            ]})
+        );
+
+        let err = parse_config("[[sink]]\nname = \"x\"\ntype = \"slack\"\n")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("needs a url"), "{}", err);
+        assert!(parse_config("[[route]]\nsinks = [\"x\"]\n").is_err());
+        assert!(parse_config("[[sink]]\nname = \"x\"\ntype = \"pager\"\n").is_err());
+        assert!(load(&dir.path().join("missing.toml")).unwrap().is_empty());
+    }
+}
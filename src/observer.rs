@@ -0,0 +1,109 @@
+use crate::types::{CheckEvent, CheckPhase, DialogKind, WaitStats};
+
+/// Receives progress and diagnostics from provider checks.
+///
+/// Every method defaults to a no-op, so implementors only override what they
+/// need. Closures taking `(provider, &CheckEvent)` are observers too: they
+/// receive phases, dialogs, retries and fallbacks as `CheckEvent`s.
+pub trait CheckObserver: Send + Sync {
+    /// The check moved to a new phase.
+    fn on_phase(&self, _provider: &str, _phase: CheckPhase) {}
+
+    /// A dialog was detected (`dismissed == false`) or dismissed.
+    fn on_dialog(&self, _provider: &str, _kind: &DialogKind, _dismissed: bool) {}
+
+    /// A step was retried, or a fallback path taken; `reason` is a short id.
+    fn on_retry(&self, _provider: &str, _reason: &'static str, _fallback: bool) {}
+
+    /// Screen text at a notable point; `label` says which ("prompt", "final").
+    fn on_capture(&self, _provider: &str, _label: &str, _text: &str) {}
+
+    /// Something worth knowing that does not fail the check (slow start-up,
+    /// truncated rows, an unexpected screen being worked around).
+    fn on_warning(&self, _provider: &str, _message: &str) {}
+
+    /// A wait for screen content ended; only waits run with `verbose`
+    /// report, as the PTY layer's trace.
+    fn on_wait(&self, _provider: &str, _wait: &WaitStats) {}
+}
+
+impl<F> CheckObserver for F
+where
+    F: Fn(&str, &CheckEvent) + Send + Sync,
+{
+    fn on_phase(&self, provider: &str, phase: CheckPhase) {
+        self(provider, &CheckEvent::Phase(phase));
+    }
+
+    fn on_dialog(&self, provider: &str, kind: &DialogKind, dismissed: bool) {
+        let event = if dismissed {
+            CheckEvent::DialogDismissed(kind.clone())
+        } else {
+            CheckEvent::DialogDetected(kind.clone())
+        };
+        self(provider, &event);
+    }
+
+    fn on_retry(&self, provider: &str, reason: &'static str, fallback: bool) {
+        let event = if fallback {
+            CheckEvent::Fallback(reason)
+        } else {
+            CheckEvent::Retry(reason)
+        };
+        self(provider, &event);
+    }
+}
+
+/// Route a `CheckEvent` to the matching observer method.
+#[cfg_attr(not(feature = "runner"), allow(dead_code))]
+pub(crate) fn dispatch(observer: &dyn CheckObserver, provider: &str, event: &CheckEvent) {
+    match event {
+        CheckEvent::Phase(phase) => observer.on_phase(provider, *phase),
+        CheckEvent::DialogDetected(kind) => observer.on_dialog(provider, kind, false),
+        CheckEvent::DialogDismissed(kind) => observer.on_dialog(provider, kind, true),
+        CheckEvent::Retry(reason) => observer.on_retry(provider, reason, false),
+        CheckEvent::Fallback(reason) => observer.on_retry(provider, reason, true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_closure_observer_round_trips_events() {
+        let seen = Mutex::new(Vec::new());
+        let observer = |provider: &str, event: &CheckEvent| {
+            seen.lock()
+                .unwrap()
+                .push((provider.to_string(), event.clone()));
+        };
+        let events = [
+            CheckEvent::Phase(CheckPhase::FetchingUsage),
+            CheckEvent::DialogDetected(DialogKind::TrustFolder),
+            CheckEvent::DialogDismissed(DialogKind::TrustFolder),
+            CheckEvent::Retry("recapture_wider"),
+            CheckEvent::Fallback("usage_fallback_status"),
+        ];
+        for event in &events {
+            dispatch(&observer, "claude", event);
+        }
+        let seen: Vec<CheckEvent> = seen
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|(_, e)| e)
+            .collect();
+        assert_eq!(seen, events);
+    }
+
+    #[test]
+    fn test_closure_observer_ignores_captures_and_warnings() {
+        let calls = Mutex::new(0);
+        let observer = |_: &str, _: &CheckEvent| *calls.lock().unwrap() += 1;
+        observer.on_capture("codex", "final", "text");
+        observer.on_warning("codex", "slow");
+        assert_eq!(*calls.lock().unwrap(), 0);
+    }
+}
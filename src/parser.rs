@@ -1,15 +1,170 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, Utc};
 use chrono_tz::Tz;
 use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use crate::types::{
+    CheckStats, Confidence, LimitScope, LimitWindow, PercentKind, UsageData, UsageEntry,
+};
+
+// Compiled once: the parsers run on every poll while waiting for data.
+// Team/Enterprise seats word shared limits as "45% of your organization's
+// limit used", so allow a short "of …" between the percentage and "used".
+static CLAUDE_PCT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(\d+(?:\.\d+)?)\s*%\s*(?:of\s+[\w\s'’]{1,40}?\s+)?used").unwrap()
+});
+// "Extra usage not enabled • /extra-usage to enable", "Extra usage is off".
+static CLAUDE_EXTRA_OFF_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)extra[ \t]*usage[ \t]*(?:is[ \t]*)?(?:not[ \t]*enabled|disabled|off\b)|/extra-usage[ \t]*to[ \t]*(?:enable|turn[ \t]*on)",
+    )
+    .unwrap()
+});
+static CLAUDE_EXTRA_ON_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)extra[ \t]*usage[ \t]*(?:is[ \t]*)?(?:enabled|turned[ \t]*on)\b").unwrap()
+});
+// Weekly limits for a model we don't list by name: "Current week (Opus only)".
+static CLAUDE_MODEL_WEEK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^Current week \([\w .-]+\)$").unwrap());
+// Section titles beyond the personal-plan ones: seat and shared org limits.
+static CLAUDE_SEAT_HEADER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(?:your\s+seat|seat\s+(?:usage|limit)|(?:organi[sz]ation|org|team|workspace|enterprise|shared)\b[\w\s()-]*?\b(?:usage|limit))\b[\w\s()-]*:?$",
+    )
+    .unwrap()
+});
+static CLAUDE_MONEY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\$[\d.,]+\s*/\s*\$[\d.,]+\s*spent)").unwrap());
+static CLAUDE_RESET_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"((?:Resets?|Reses)\s*.+)").unwrap());
+// Business/Enterprise workspaces add pooled rows such as "Shared credits:".
+static CODEX_LIMIT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*([\w][\w\s.-]*?)\s*(limit|credits|usage):\s+\[.*?\]\s+(\d+(?:\.\d+)?)\s*%\s*(left|used)\s+\(resets?\s+(.+?)\)",
+    )
+    .unwrap()
+});
+// Section header: "Something limit:" or "Workspace limits:" on its own line
+// (no progress bar)
+static CODEX_SECTION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*([\w][\w\s.-]+?)\s*limits?:\s*$").unwrap());
+// Labels naming a pooled quota rather than the user's own.
+static WORKSPACE_SCOPE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:workspace|shared|team|organi[sz]ation|org|pooled|business|enterprise)\b")
+        .unwrap()
+});
+
+pub(crate) fn scope_for_label(label: &str) -> LimitScope {
+    if WORKSPACE_SCOPE_RE.is_match(label) {
+        LimitScope::Workspace
+    } else {
+        LimitScope::Personal
+    }
+}
 
-use crate::types::{PercentKind, UsageData, UsageEntry};
+/// Window a Claude or Codex limit is measured over, from its label.
+pub(crate) fn window_for_label(label: &str) -> LimitWindow {
+    let label = label.to_ascii_lowercase();
+    if label.contains("session") || label.contains("5h") {
+        LimitWindow::Session
+    } else if label.contains("week") {
+        LimitWindow::Weekly
+    } else if label.contains("daily") || label.contains("today") {
+        LimitWindow::Daily
+    } else if label.contains("extra usage") || label.contains("month") {
+        LimitWindow::Monthly
+    } else {
+        LimitWindow::Other
+    }
+}
+static GEMINI_MODEL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^\s*(gemini-[\w.-]+|qwen[\w.-]*)\s+(\d+|-)\s+(\d+(?:\.\d+)?)\s*%\s*\(?resets?\s+in\s+(.+?)\)?\s*$",
+    )
+    .unwrap()
+});
+// Code Assist (Standard/Enterprise) licenses show request counts against a
+// daily quota instead of a percentage: "gemini-2.5-pro  120 / 1,500  (Resets in 11h)".
+static GEMINI_QUOTA_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^\s*(gemini-[\w.-]+|qwen[\w.-]*)\s+([\d,]+)\s*/\s*([\d,]+)(?:\s+(?:requests?|reqs?))?(?:\s+today)?\s+\(?resets?\s+in\s+(.+?)\)?\s*$",
+    )
+    .unwrap()
+});
+// Copilot `/usage` quota rows: "Premium requests  ████░░  132 / 300 (44%)",
+// "Premium requests: 44% used", or "Chat messages  Unlimited".
+static COPILOT_ROW_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(premium requests?|chat(?: messages)?|(?:code )?completions)\b[\s:]*(.*)$")
+        .unwrap()
+});
+// Cursor status rows: "Included usage  ███░░  $12.40 / $20.00 (62%)",
+// "Fast requests  312 / 500", or "On-demand usage: 6% used".
+static CURSOR_ROW_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^((?:[\w-]+\s+){0,2}(?:usage|requests))\b[\s:]*(.*)$").unwrap()
+});
+static CURSOR_SPEND_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$([\d,]+(?:\.\d+)?)\s*/\s*\$([\d,]+(?:\.\d+)?)").unwrap());
+// Amazon Q `/usage` allowance rows: "Agentic requests  212 / 1,000 (21%)",
+// "Code transformation  1,200 / 4,000 lines", or "Inline suggestions  Unlimited".
+static AMAZONQ_ROW_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(agentic (?:requests?|chat)|code transformations?|inline (?:suggestions?|chat))\b[\s:]*(.*)$",
+    )
+    .unwrap()
+});
+// Aider `/tokens` summary: "$ 0.0090    3,030 tokens total" and
+// "128,000 tokens max context window size".
+static AIDER_TOTAL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^\$\s*([\d,]+(?:\.\d+)?)\s+([\d,]+)\s+tokens\s+total").unwrap()
+});
+static AIDER_MAX_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^([\d,]+)\s+tokens\s+max\s+context\s+window").unwrap());
+// Monthly allowance rows and resets shared by Copilot, Cursor, and Amazon Q.
+static ALLOWANCE_COUNT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"([\d,]+)\s*/\s*([\d,]+)").unwrap());
+static ALLOWANCE_PERCENT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(\d+(?:\.\d+)?)\s*%(?:\s*(used|left|remaining))?").unwrap());
+static ALLOWANCE_RESET_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\bresets?\s+((?:on|in)\s+[^()]+?)\s*(?:\(UTC\))?\)?\s*$").unwrap()
+});
+static RESET_DAYS_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)in\s+(\d+)\s+days?").unwrap());
+static RESET_ON_DATE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)on\s+([A-Za-z]+)\s+(\d{1,2})(?:,?\s+(\d{4}))?").unwrap());
+static PERCENT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)?\s*%").unwrap());
+static TIME_12H_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(\d{1,2})(?::(\d{2}))?\s*(am|pm)").unwrap());
+static HOURS_MINUTES_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d+)h\s*(\d+)m").unwrap());
+static HOURS_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+)h").unwrap());
+static MINUTES_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+)m").unwrap());
+static CODEX_RESET_DATE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)resets?\s+(\d{1,2}):(\d{2})\s+on\s+(\d{1,2})\s+(\w+)").unwrap()
+});
+static CODEX_RESET_TIME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)resets?\s+(\d{1,2}):(\d{2})").unwrap());
+// Newer Codex builds: "resets in 3h 20m", "resets in 2d 4h", "resets in 45m".
+static CODEX_RESET_RELATIVE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)resets?\s+in\s+(?:(\d+)\s*d\w*\s*)?(?:(\d+)\s*h\w*\s*)?(?:(\d+)\s*m\w*)?")
+        .unwrap()
+});
+static PAREN_TZ_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\(([^)]+)\)").unwrap());
+static CLAUDE_RESET_DATE_TIME_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)Resets?\s*([A-Za-z]+)\s*(\d{1,2})\s*at\s*(.+?)\s*\(").unwrap()
+});
+static CLAUDE_RESET_TIME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)Resets?\s*(\d{1,2}(?::\d{2})?\s*(?:am|pm))\s*\(").unwrap());
+static CLAUDE_RESET_DATE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)Resets?\s*([A-Za-z]+)\s*(\d{1,2})\s*\(").unwrap());
 
 /// Parse Claude Code `/status` Usage tab output.
 pub fn parse_claude_output(text: &str) -> Result<UsageData> {
-    let pct_re = Regex::new(r"(\d+(?:\.\d+)?)\s*%\s*used")?;
-    let money_re = Regex::new(r"(\$[\d.,]+\s*/\s*\$[\d.,]+\s*spent)")?;
-    let reset_re = Regex::new(r"((?:Resets?|Reses)\s*.+)")?;
+    let pct_re = &*CLAUDE_PCT_RE;
+    let money_re = &*CLAUDE_MONEY_RE;
+    let reset_re = &*CLAUDE_RESET_RE;
 
     fn normalize_reset_text(raw: &str) -> String {
         let trimmed = raw.trim();
@@ -40,17 +195,29 @@ pub fn parse_claude_output(text: &str) -> Result<UsageData> {
         let matched_header = known_headers
             .iter()
             .find(|h| trimmed.starts_with(*h))
-            .map(|h| h.to_string());
+            .map(|h| (h.to_string(), Confidence::Exact));
 
         let header = matched_header.or_else(|| {
             if trimmed.starts_with("Current week") || trimmed.starts_with("Current session") {
-                Some(trimmed.to_string())
+                // Anything but a well-formed model qualifier means part of
+                // the header was overwritten.
+                let confidence = if CLAUDE_MODEL_WEEK_RE.is_match(trimmed) {
+                    Confidence::Exact
+                } else {
+                    Confidence::Fallback
+                };
+                Some((trimmed.to_string(), confidence))
+            } else if CLAUDE_SEAT_HEADER_RE.is_match(trimmed) {
+                Some((
+                    trimmed.trim_end_matches(':').trim_end().to_string(),
+                    Confidence::Exact,
+                ))
             } else {
                 None
             }
         });
 
-        if let Some(label) = header {
+        if let Some((label, confidence)) = header {
             let mut percent = None;
             let mut reset_info = String::new();
             let mut spent = None;
@@ -61,15 +228,7 @@ pub fn parse_claude_output(text: &str) -> Result<UsageData> {
 
                 if percent.is_none() {
                     if let Some(caps) = pct_re.captures(line) {
-                        match caps[1].parse::<f64>() {
-                            Ok(v) => percent = Some(v),
-                            Err(e) => {
-                                eprintln!(
-                                    "Warning: skipping unparseable percentage '{}': {}",
-                                    &caps[1], e
-                                );
-                            }
-                        }
+                        percent = caps[1].parse::<f64>().ok();
                     }
                 }
 
@@ -88,6 +247,8 @@ pub fn parse_claude_output(text: &str) -> Result<UsageData> {
 
             if let Some(pct) = percent {
                 let reset_minutes = parse_reset_minutes(&reset_info, "claude");
+                let scope = scope_for_label(&label);
+                let window = window_for_label(&label);
                 let used = (pct.round() as u32).min(100);
                 entries.push(UsageEntry {
                     label,
@@ -98,6 +259,13 @@ pub fn parse_claude_output(text: &str) -> Result<UsageData> {
                     reset_minutes,
                     spent,
                     requests: None,
+                    requests_used: None,
+                    requests_limit: None,
+                    scope,
+                    window,
+                    confidence,
+                    inconsistent: false,
+                    as_of: None,
                 });
             }
         }
@@ -138,13 +306,35 @@ pub fn parse_claude_output(text: &str) -> Result<UsageData> {
                 reset_info,
                 spent: if idx == 3 { spent.clone() } else { None },
                 requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: window_for_label(labels[idx]),
+                confidence: Confidence::Inferred,
+                inconsistent: false,
+                as_of: None,
             });
         }
     }
 
+    // The extra usage section either shows spend against a monthly cap or
+    // says it is off.
+    let extra_usage_enabled = if CLAUDE_EXTRA_OFF_RE.is_match(text) {
+        Some(false)
+    } else if CLAUDE_EXTRA_ON_RE.is_match(text)
+        || entries.iter().any(|e| e.label.starts_with("Extra usage"))
+    {
+        Some(true)
+    } else {
+        None
+    };
+
     Ok(UsageData {
         provider: "claude".to_string(),
         entries,
+        extra_usage_enabled,
+        tokens: Vec::new(),
+        check: CheckStats::default(),
     })
 }
 
@@ -159,11 +349,8 @@ pub fn parse_claude_output(text: &str) -> Result<UsageData> {
 /// Weekly limit:       [████████████████] 100% left (resets 10:16 on 20 Feb)
 /// ```
 pub fn parse_codex_output(text: &str) -> Result<UsageData> {
-    let limit_re = Regex::new(
-        r"^\s*([\w][\w\s.-]*?)\s*limit:\s+\[.*?\]\s+(\d+(?:\.\d+)?)\s*%\s*(left|used)\s+\(resets?\s+(.+?)\)",
-    )?;
-    // Section header: "Something limit:" on its own line (no progress bar)
-    let section_re = Regex::new(r"^\s*([\w][\w\s.-]+?)\s*limit:\s*$")?;
+    let limit_re = &*CODEX_LIMIT_RE;
+    let section_re = &*CODEX_SECTION_RE;
 
     let mut entries = Vec::new();
     let mut current_section: Option<String> = None;
@@ -188,27 +375,20 @@ pub fn parse_codex_output(text: &str) -> Result<UsageData> {
 
         // Check for limit line with progress bar
         if let Some(caps) = limit_re.captures(line) {
-            let raw_label = caps[1].trim();
+            let raw_label = format!("{} {}", caps[1].trim(), &caps[2]);
             let label = match &current_section {
-                Some(section) => format!("{} {} limit", section, raw_label),
-                None => format!("{} limit", raw_label),
+                Some(section) => format!("{} {}", section, raw_label),
+                None => raw_label,
             };
-            let percent = match caps[2].parse::<f64>() {
-                Ok(v) => v,
-                Err(e) => {
-                    eprintln!(
-                        "Warning: skipping unparseable Codex percentage '{}': {}",
-                        &caps[2], e
-                    );
-                    continue;
-                }
+            let Ok(percent) = caps[3].parse::<f64>() else {
+                continue;
             };
-            let percent_kind = if &caps[3] == "left" {
+            let percent_kind = if &caps[4] == "left" {
                 PercentKind::Left
             } else {
                 PercentKind::Used
             };
-            let reset_info = format!("resets {}", &caps[4]);
+            let reset_info = format!("resets {}", &caps[5]);
 
             let clamped = (percent.round() as u32).min(100);
             let (percent_used, percent_remaining) = match percent_kind {
@@ -216,6 +396,9 @@ pub fn parse_codex_output(text: &str) -> Result<UsageData> {
                 PercentKind::Left => (100 - clamped, clamped),
             };
             let reset_minutes = parse_reset_minutes(&reset_info, "codex");
+            // The label carries any section prefix ("Workspace weekly limit").
+            let scope = scope_for_label(&label);
+            let window = window_for_label(&label);
             entries.push(UsageEntry {
                 label,
                 percent_used,
@@ -225,6 +408,13 @@ pub fn parse_codex_output(text: &str) -> Result<UsageData> {
                 reset_minutes,
                 spent: None,
                 requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope,
+                window,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             });
             continue;
         }
@@ -244,6 +434,9 @@ pub fn parse_codex_output(text: &str) -> Result<UsageData> {
     Ok(UsageData {
         provider: "codex".to_string(),
         entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats::default(),
     })
 }
 
@@ -255,10 +448,29 @@ pub fn parse_codex_output(text: &str) -> Result<UsageData> {
 /// │  gemini-2.5-pro                 -    98.1% (Resets in 2h 35m)
 /// │  gemini-2.5-pro                 -     99.0% resets in 23h 19m
 /// ```
+///
+/// and the Code Assist layout, which counts requests against a daily quota:
+/// ```text
+/// │  gemini-2.5-pro            120 / 1,500 requests   (Resets in 11h 20m)
+/// ```
 pub fn parse_gemini_output(text: &str) -> Result<UsageData> {
-    let model_re = Regex::new(
-        r"(?i)^\s*(gemini-[\w.-]+)\s+(\d+|-)\s+(\d+(?:\.\d+)?)\s*%\s*\(?resets?\s+in\s+(.+?)\)?\s*$",
-    )?;
+    parse_model_stats(text, "gemini")
+}
+
+/// Parse Qwen Code `/stats` output.  Qwen Code is a Gemini CLI fork and
+/// prints the same per-model table, with Qwen model names and, on the
+/// free Qwen OAuth tier, a daily request quota:
+/// ```text
+/// │  qwen3-coder-plus            120 / 2,000 requests   (Resets in 11h 20m)
+/// ```
+pub fn parse_qwen_output(text: &str) -> Result<UsageData> {
+    parse_model_stats(text, "qwen")
+}
+
+/// The per-model stats table Gemini CLI and its forks print.
+fn parse_model_stats(text: &str, provider: &str) -> Result<UsageData> {
+    let model_re = &*GEMINI_MODEL_RE;
+    let quota_re = &*GEMINI_QUOTA_RE;
 
     let mut entries = Vec::new();
 
@@ -282,19 +494,12 @@ pub fn parse_gemini_output(text: &str) -> Result<UsageData> {
             } else {
                 Some(requests_raw)
             };
-            let percent = match caps[3].parse::<f64>() {
-                Ok(v) => v,
-                Err(e) => {
-                    eprintln!(
-                        "Warning: skipping unparseable Gemini percentage '{}': {}",
-                        &caps[3], e
-                    );
-                    continue;
-                }
+            let Ok(percent) = caps[3].parse::<f64>() else {
+                continue;
             };
             let reset_info = format!("Resets in {}", &caps[4]);
 
-            let reset_minutes = parse_reset_minutes(&reset_info, "gemini");
+            let reset_minutes = parse_reset_minutes(&reset_info, provider);
             let clamped = (percent.round() as u32).min(100);
             entries.push(UsageEntry {
                 label,
@@ -305,16 +510,583 @@ pub fn parse_gemini_output(text: &str) -> Result<UsageData> {
                 reset_minutes,
                 spent: None,
                 requests,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Daily,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
+            });
+        } else if let Some(caps) = quota_re.captures(line) {
+            let count = |raw: &str| raw.replace(',', "").parse::<u64>();
+            let (used, limit) = match (count(&caps[2]), count(&caps[3])) {
+                (Ok(used), Ok(limit)) if limit > 0 => (used, limit),
+                // No quota to speak of (`0 / 0`) or a count past u64.
+                _ => continue,
+            };
+            let reset_info = format!("Resets in {}", &caps[4]);
+            let reset_minutes = parse_reset_minutes(&reset_info, provider);
+            let percent_used = ((used as f64 / limit as f64 * 100.0).round() as u32).min(100);
+            entries.push(UsageEntry {
+                label: caps[1].to_string(),
+                percent_used,
+                percent_remaining: 100 - percent_used,
+                percent_kind: PercentKind::Used,
+                reset_info,
+                reset_minutes,
+                spent: None,
+                requests: Some(format!("{} / {}", &caps[2], &caps[3])),
+                requests_used: Some(used),
+                requests_limit: Some(limit),
+                scope: LimitScope::Personal,
+                window: LimitWindow::Daily,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             });
         }
     }
 
     Ok(UsageData {
-        provider: "gemini".to_string(),
+        provider: provider.to_string(),
+        entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats::default(),
+    })
+}
+
+/// Daily request caps per Gemini model on the free tier.  Not applied
+/// unless copied into `UsageConfig::gemini_request_caps`: paid tiers have
+/// higher caps, and nothing on screen says which tier an account is on.
+pub const GEMINI_DAILY_REQUEST_CAPS: &[(&str, u64)] = &[
+    ("gemini-2.5-pro", 100),
+    ("gemini-2.5-flash", 250),
+    ("gemini-2.5-flash-lite", 1000),
+];
+
+/// Fill in `requests_used` and `requests_limit` on rows that show a plain
+/// request count for a model in `caps` (model name → daily cap).  A count
+/// above the cap means the cap doesn't fit this account, so that row is
+/// left alone.
+pub fn apply_request_caps(data: &mut UsageData, caps: &BTreeMap<String, u64>) {
+    for entry in &mut data.entries {
+        if entry.requests_limit.is_some() {
+            continue;
+        }
+        let Some(&limit) = caps.get(&entry.label.to_lowercase()) else {
+            continue;
+        };
+        let used = entry
+            .requests
+            .as_deref()
+            .and_then(|raw| raw.replace(',', "").parse::<u64>().ok());
+        if let Some(used) = used.filter(|used| *used <= limit) {
+            entry.requests_used = Some(used);
+            entry.requests_limit = Some(limit);
+        }
+    }
+}
+
+/// Parse Copilot CLI's `/usage` screen: monthly premium requests and, on
+/// the Free plan, chat and completion allowances.  Unlimited rows are
+/// skipped.  A "resets ..." line below the rows applies to every row that
+/// doesn't carry its own.
+pub fn parse_copilot_output(text: &str) -> Result<UsageData> {
+    let mut entries: Vec<UsageEntry> = Vec::new();
+    let mut shared_reset = None;
+
+    for raw_line in text.lines() {
+        let line = raw_line
+            .trim()
+            .trim_start_matches(['│', '●', '•'])
+            .trim_end_matches('│')
+            .trim();
+        let reset = allowance_reset(line);
+
+        let Some(caps) = COPILOT_ROW_RE.captures(line) else {
+            if reset.is_some() {
+                shared_reset = reset;
+            }
+            continue;
+        };
+        let lower = caps[1].to_lowercase();
+        let label = if lower.starts_with("premium") {
+            "Premium requests"
+        } else if lower.starts_with("chat") {
+            "Chat messages"
+        } else {
+            "Code completions"
+        };
+        let Some((percent_used, requests)) = allowance_usage(&caps[2]) else {
+            // "Unlimited", a row still rendering, or a "Premium requests
+            // reset on ..." note.
+            if reset.is_some() {
+                shared_reset = reset;
+            }
+            continue;
+        };
+        entries.push(UsageEntry {
+            label: label.to_string(),
+            percent_used,
+            percent_remaining: 100 - percent_used,
+            percent_kind: PercentKind::Used,
+            reset_info: reset.unwrap_or_default(),
+            reset_minutes: None,
+            spent: None,
+            requests,
+            requests_used: None,
+            requests_limit: None,
+            scope: LimitScope::Personal,
+            window: LimitWindow::Monthly,
+            confidence: Confidence::Exact,
+            inconsistent: false,
+            as_of: None,
+        });
+    }
+
+    apply_shared_reset(&mut entries, shared_reset, "copilot");
+    Ok(UsageData {
+        provider: "copilot".to_string(),
+        entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats::default(),
+    })
+}
+
+/// Parse `cursor-agent`'s `/status` screen: plan usage for the billing
+/// cycle, as included and on-demand spend or, on request-based plans, as
+/// request counts.  A "resets ..." line applies to every row that doesn't
+/// carry its own.
+pub fn parse_cursor_output(text: &str) -> Result<UsageData> {
+    let mut entries: Vec<UsageEntry> = Vec::new();
+    let mut shared_reset = None;
+
+    for raw_line in text.lines() {
+        let line = raw_line.trim().trim_matches('│').trim();
+        let reset = allowance_reset(line);
+
+        let Some(caps) = CURSOR_ROW_RE.captures(line) else {
+            if reset.is_some() {
+                shared_reset = reset;
+            }
+            continue;
+        };
+        let rest = &caps[2];
+        let (percent_used, spent, requests) = if let Some(amounts) = CURSOR_SPEND_RE.captures(rest)
+        {
+            let amount = |raw: &str| raw.replace(',', "").parse::<f64>().ok();
+            let Some(percent) = amount(&amounts[1])
+                .zip(amount(&amounts[2]))
+                .and_then(|(used, limit)| quota_percent(used, limit))
+            else {
+                continue;
+            };
+            let spent = format!("${} / ${} spent", &amounts[1], &amounts[2]);
+            (percent, Some(spent), None)
+        } else if let Some((percent, requests)) = allowance_usage(rest) {
+            (percent, None, requests)
+        } else {
+            // A section header ("Usage this billing cycle") or a note.
+            if reset.is_some() {
+                shared_reset = reset;
+            }
+            continue;
+        };
+        entries.push(UsageEntry {
+            label: caps[1].trim().to_string(),
+            percent_used,
+            percent_remaining: 100 - percent_used,
+            percent_kind: PercentKind::Used,
+            reset_info: reset.unwrap_or_default(),
+            reset_minutes: None,
+            spent,
+            requests,
+            requests_used: None,
+            requests_limit: None,
+            scope: LimitScope::Personal,
+            window: LimitWindow::Monthly,
+            confidence: Confidence::Exact,
+            inconsistent: false,
+            as_of: None,
+        });
+    }
+
+    apply_shared_reset(&mut entries, shared_reset, "cursor");
+    Ok(UsageData {
+        provider: "cursor".to_string(),
+        entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats::default(),
+    })
+}
+
+/// Parse the Amazon Q Developer CLI's `/usage` screen: the monthly
+/// allowances of the Free tier or a Pro subscription.  The context window
+/// gauge above them is a per-conversation limit and is skipped, as are
+/// unlimited rows.  A "reset ..." line applies to every row that doesn't
+/// carry its own.
+pub fn parse_amazonq_output(text: &str) -> Result<UsageData> {
+    let mut entries: Vec<UsageEntry> = Vec::new();
+    let mut shared_reset = None;
+
+    for raw_line in text.lines() {
+        let line = raw_line.trim().trim_matches('│').trim();
+        let reset = allowance_reset(line);
+
+        let usage = AMAZONQ_ROW_RE
+            .captures(line)
+            .and_then(|caps| Some((caps[1].to_string(), allowance_usage(&caps[2])?)));
+        let Some((label, (percent_used, requests))) = usage else {
+            if reset.is_some() {
+                shared_reset = reset;
+            }
+            continue;
+        };
+        entries.push(UsageEntry {
+            label,
+            percent_used,
+            percent_remaining: 100 - percent_used,
+            percent_kind: PercentKind::Used,
+            reset_info: reset.unwrap_or_default(),
+            reset_minutes: None,
+            spent: None,
+            requests,
+            requests_used: None,
+            requests_limit: None,
+            scope: LimitScope::Personal,
+            window: LimitWindow::Monthly,
+            confidence: Confidence::Exact,
+            inconsistent: false,
+            as_of: None,
+        });
+    }
+
+    apply_shared_reset(&mut entries, shared_reset, "amazonq");
+    Ok(UsageData {
+        provider: "amazonq".to_string(),
+        entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats::default(),
+    })
+}
+
+/// Parse aider's `/tokens` report into one "Context window" entry: the
+/// tokens the chat would send against the model's context window, and
+/// aider's estimate of what sending them costs, both in `requests`.  The
+/// cost is not money spent, so `spent` stays empty.  Aider has no account
+/// limits, so there is no reset and the window is `Other`.
+pub fn parse_aider_output(text: &str) -> Result<UsageData> {
+    let mut total = None;
+    let mut max = None;
+    for raw_line in text.lines() {
+        let line = raw_line.trim();
+        if let Some(caps) = AIDER_TOTAL_RE.captures(line) {
+            total = Some((caps[1].to_string(), caps[2].to_string()));
+        } else if let Some(caps) = AIDER_MAX_RE.captures(line) {
+            max = Some(caps[1].to_string());
+        }
+    }
+
+    let mut entries = Vec::new();
+    // The last report on screen is the current one.
+    if let Some((cost, tokens)) = total {
+        let count = |raw: &str| raw.replace(',', "").parse::<f64>().ok();
+        let percent_used = max
+            .as_deref()
+            .and_then(|max| quota_percent(count(&tokens)?, count(max)?))
+            .unwrap_or(0);
+        let requests = match &max {
+            Some(max) => format!("{} / {} tokens (~${} estimated)", tokens, max, cost),
+            None => format!("{} tokens (~${} estimated)", tokens, cost),
+        };
+        entries.push(UsageEntry {
+            label: "Context window".to_string(),
+            percent_used,
+            percent_remaining: 100 - percent_used,
+            percent_kind: PercentKind::Used,
+            reset_info: String::new(),
+            reset_minutes: None,
+            spent: None,
+            requests: Some(requests),
+            requests_used: None,
+            requests_limit: None,
+            scope: LimitScope::Personal,
+            window: LimitWindow::Other,
+            confidence: Confidence::Exact,
+            inconsistent: false,
+            as_of: None,
+        });
+    }
+
+    Ok(UsageData {
+        provider: "aider".to_string(),
         entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats::default(),
     })
 }
 
+/// "Resets on Nov 1" / "resets in 13 days" on an allowance line.
+fn allowance_reset(line: &str) -> Option<String> {
+    ALLOWANCE_RESET_RE
+        .captures(line)
+        .map(|caps| format!("Resets {}", caps[1].trim()))
+}
+
+/// Percent used from "132 / 300" (kept as `requests`) or "44% used" /
+/// "56% left".
+fn allowance_usage(rest: &str) -> Option<(u32, Option<String>)> {
+    if let Some(counts) = ALLOWANCE_COUNT_RE.captures(rest) {
+        let count = |raw: &str| raw.replace(',', "").parse::<f64>().ok();
+        let percent = quota_percent(count(&counts[1])?, count(&counts[2])?)?;
+        return Some((percent, Some(format!("{} / {}", &counts[1], &counts[2]))));
+    }
+    let pct = ALLOWANCE_PERCENT_RE.captures(rest)?;
+    let value = (pct[1].parse::<f64>().ok()?.round() as u32).min(100);
+    let left = pct
+        .get(2)
+        .is_some_and(|word| !word.as_str().eq_ignore_ascii_case("used"));
+    Some((if left { 100 - value } else { value }, None))
+}
+
+fn quota_percent(used: f64, limit: f64) -> Option<u32> {
+    (limit > 0.0).then(|| ((used / limit * 100.0).round() as u32).min(100))
+}
+
+fn apply_shared_reset(entries: &mut [UsageEntry], shared_reset: Option<String>, provider: &str) {
+    for entry in entries {
+        if entry.reset_info.is_empty() {
+            if let Some(reset) = &shared_reset {
+                entry.reset_info = reset.clone();
+            }
+        }
+        entry.reset_minutes = parse_reset_minutes(&entry.reset_info, provider);
+    }
+}
+
+/// Parse a usage screen captured from `provider` ("claude", "codex",
+/// "gemini", "copilot", "cursor", "qwen", "amazonq" or "aider").
+pub fn parse_output(provider: &str, text: &str) -> Result<UsageData> {
+    match provider {
+        "claude" => parse_claude_output(text),
+        "codex" => parse_codex_output(text),
+        "gemini" => parse_gemini_output(text),
+        "copilot" => parse_copilot_output(text),
+        "cursor" => parse_cursor_output(text),
+        "qwen" => parse_qwen_output(text),
+        "amazonq" => parse_amazonq_output(text),
+        "aider" => parse_aider_output(text),
+        other => bail!("Unknown provider '{}'", other),
+    }
+}
+
+/// One non-blank line of a capture and which of the parser's line patterns
+/// it matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LineMatch {
+    /// 1-based line number in the capture.
+    pub line: usize,
+    pub text: String,
+    /// Names of the patterns that matched; empty when none did.
+    pub matched: Vec<&'static str>,
+}
+
+/// The patterns `provider`'s parser looks for on a line, by name.
+fn line_patterns(provider: &str) -> Option<Vec<(&'static str, &'static Regex)>> {
+    let allowance = [
+        ("allowance_count", &*ALLOWANCE_COUNT_RE),
+        ("allowance_percent", &*ALLOWANCE_PERCENT_RE),
+        ("allowance_reset", &*ALLOWANCE_RESET_RE),
+    ];
+    let mut patterns = match provider {
+        "claude" => vec![
+            ("percent", &*CLAUDE_PCT_RE),
+            ("spend", &*CLAUDE_MONEY_RE),
+            ("reset", &*CLAUDE_RESET_RE),
+            ("seat_header", &*CLAUDE_SEAT_HEADER_RE),
+        ],
+        "codex" => vec![("limit", &*CODEX_LIMIT_RE), ("section", &*CODEX_SECTION_RE)],
+        "gemini" | "qwen" => vec![("model", &*GEMINI_MODEL_RE), ("quota", &*GEMINI_QUOTA_RE)],
+        "copilot" => vec![("row", &*COPILOT_ROW_RE)],
+        "cursor" => vec![("row", &*CURSOR_ROW_RE), ("spend", &*CURSOR_SPEND_RE)],
+        "amazonq" => vec![("row", &*AMAZONQ_ROW_RE)],
+        "aider" => return Some(vec![("total", &*AIDER_TOTAL_RE), ("max", &*AIDER_MAX_RE)]),
+        _ => return None,
+    };
+    if matches!(provider, "copilot" | "cursor" | "amazonq") {
+        patterns.extend(allowance);
+    }
+    Some(patterns)
+}
+
+/// Run every non-blank line of `text` past `provider`'s line patterns, with
+/// the box-drawing borders and bullets the parsers strip removed first.
+/// For working out why a capture parses to fewer entries than it shows.
+pub fn explain(provider: &str, text: &str) -> Result<Vec<LineMatch>> {
+    let Some(patterns) = line_patterns(provider) else {
+        bail!("Unknown provider '{}'", provider);
+    };
+    Ok(text
+        .lines()
+        .enumerate()
+        .filter_map(|(i, raw)| {
+            let line = raw.trim().trim_matches(['│', '●', '•']).trim();
+            (!line.is_empty()).then(|| LineMatch {
+                line: i + 1,
+                text: line.to_string(),
+                matched: patterns
+                    .iter()
+                    .filter(|(_, re)| re.is_match(line))
+                    .map(|(name, _)| *name)
+                    .collect(),
+            })
+        })
+        .collect())
+}
+
+/// Words that name a limit's window or scope rather than what it covers;
+/// whatever is left of a label (a model name) qualifies its canonical key.
+const KEY_FILLER_WORDS: &[&str] = &[
+    "current",
+    "session",
+    "5h",
+    "week",
+    "weekly",
+    "daily",
+    "today",
+    "monthly",
+    "month",
+    "limit",
+    "limits",
+    "all",
+    "models",
+    "only",
+    "usage",
+    "your",
+    "workspace",
+    "shared",
+    "team",
+    "organization",
+    "organisation",
+    "org",
+    "pooled",
+    "business",
+    "enterprise",
+];
+
+/// Stable JSON key for each of `data`'s entries, in order, so scripts don't
+/// break when a provider rewords a header: `session`, `weekly`,
+/// `weekly_opus`, `workspace_weekly`, `extra_usage`, a Gemini or Qwen model name, or
+/// a Copilot or Cursor allowance (`premium_requests`, `included_usage`, ...).
+/// Labels that name no window fall back to a slug of the label; a key that
+/// would repeat gets a `_2`, `_3`, ... suffix.
+pub fn canonical_keys(data: &UsageData) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::with_capacity(data.entries.len());
+    for entry in &data.entries {
+        let key = canonical_key(&data.provider, entry);
+        let mut unique = key.clone();
+        let mut n = 2;
+        while keys.contains(&unique) {
+            unique = format!("{}_{}", key, n);
+            n += 1;
+        }
+        keys.push(unique);
+    }
+    keys
+}
+
+fn canonical_key(provider: &str, entry: &UsageEntry) -> String {
+    match provider {
+        "gemini" | "qwen" => return entry.label.trim().to_lowercase(),
+        "copilot" | "cursor" | "amazonq" | "aider" => return slug(&entry.label),
+        _ => {}
+    }
+    let base = match entry.window {
+        LimitWindow::Session => "session",
+        LimitWindow::Weekly => "weekly",
+        LimitWindow::Daily => "daily",
+        LimitWindow::Monthly if entry.label.to_lowercase().contains("extra usage") => {
+            return "extra_usage".to_string();
+        }
+        LimitWindow::Monthly => "monthly",
+        LimitWindow::Other => return slug(&entry.label),
+    };
+    let mut key = match entry.scope {
+        LimitScope::Workspace => format!("workspace_{}", base),
+        LimitScope::Personal => base.to_string(),
+    };
+    let qualifier = slug(&entry.label)
+        .split('_')
+        .filter(|word| !KEY_FILLER_WORDS.contains(word))
+        .collect::<Vec<_>>()
+        .join("_");
+    if !qualifier.is_empty() {
+        key.push('_');
+        key.push_str(&qualifier);
+    }
+    key
+}
+
+/// Lowercase `label` with each run of other characters turned into `_`.
+fn slug(label: &str) -> String {
+    label
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Team/Enterprise seats whose limits are administered centrally can show
+/// a notice instead of any usage rows.
+pub fn claude_usage_managed_by_org(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    !CLAUDE_PCT_RE.is_match(text)
+        && (lower.contains("managed by your organization")
+            || lower.contains("managed by your organisation")
+            || lower.contains("contact your admin")
+            || lower.contains("contact your organization's admin"))
+}
+
+/// Heuristic: does the capture contain a usage row that was cut off at the
+/// terminal edge?  Such rows show a percentage but lack the trailing reset
+/// text the parsers need, or end in a TUI ellipsis.
+pub fn has_truncated_rows(text: &str, provider: &str) -> bool {
+    let pct_re = &*PERCENT_RE;
+    text.lines()
+        .map(|raw| {
+            raw.trim()
+                .trim_start_matches('│')
+                .trim_end_matches('│')
+                .trim()
+        })
+        .filter(|line| pct_re.is_match(line))
+        .any(|line| {
+            if line.ends_with('…') {
+                return true;
+            }
+            let lower = line.to_lowercase();
+            match provider {
+                // Codex rows end with "(resets …)"; a bar without it was clipped.
+                "codex" => {
+                    line.contains('[')
+                        && (lower.contains("limit:") || lower.contains("credits:"))
+                        && !(lower.contains("(reset") && line.ends_with(')'))
+                }
+                // Gemini rows always carry "resets in …" after the percentage.
+                "gemini" => lower.starts_with("gemini-") && !lower.contains("reset"),
+                "qwen" => lower.starts_with("qwen") && !lower.contains("reset"),
+                _ => false,
+            }
+        })
+}
+
 // ── Reset time parsing ──────────────────────────────────────────
 
 fn parse_month(s: &str) -> Option<u32> {
@@ -336,8 +1108,7 @@ fn parse_month(s: &str) -> Option<u32> {
 }
 
 fn parse_12h_time(s: &str) -> Option<(u32, u32)> {
-    let re = Regex::new(r"(?i)(\d{1,2})(?::(\d{2}))?\s*(am|pm)").ok()?;
-    let caps = re.captures(s)?;
+    let caps = TIME_12H_RE.captures(s)?;
     let mut hour: u32 = caps[1].parse().ok()?;
     let min: u32 = caps
         .get(2)
@@ -360,32 +1131,63 @@ fn parse_12h_time(s: &str) -> Option<(u32, u32)> {
 
 fn parse_gemini_reset(reset_info: &str) -> Option<i64> {
     // "Resets in 3h 3m"
-    let re_hm = Regex::new(r"(\d+)h\s*(\d+)m").ok()?;
-    if let Some(caps) = re_hm.captures(reset_info) {
+    if let Some(caps) = HOURS_MINUTES_RE.captures(reset_info) {
         let hours: i64 = caps[1].parse().ok()?;
         let minutes: i64 = caps[2].parse().ok()?;
         return Some(hours * 60 + minutes);
     }
     // "Resets in 3h"
-    let re_h = Regex::new(r"(\d+)h").ok()?;
-    if let Some(caps) = re_h.captures(reset_info) {
+    if let Some(caps) = HOURS_RE.captures(reset_info) {
         let hours: i64 = caps[1].parse().ok()?;
         return Some(hours * 60);
     }
     // "Resets in 45m"
-    let re_m = Regex::new(r"(\d+)m").ok()?;
-    if let Some(caps) = re_m.captures(reset_info) {
+    if let Some(caps) = MINUTES_RE.captures(reset_info) {
         let minutes: i64 = caps[1].parse().ok()?;
         return Some(minutes);
     }
     None
 }
 
+/// Monthly allowances (Copilot, Cursor, Amazon Q) reset at 00:00 UTC: "Resets on
+/// Nov 1, 2026", "Resets on Nov 1", or "Resets in 13 days".
+fn parse_allowance_reset(reset_info: &str, now_utc: DateTime<Utc>) -> Option<i64> {
+    if let Some(caps) = RESET_DAYS_RE.captures(reset_info) {
+        let days: i64 = caps[1].parse().ok()?;
+        return Some(days * 24 * 60);
+    }
+    if let Some(caps) = RESET_ON_DATE_RE.captures(reset_info) {
+        let month = parse_month(&caps[1])?;
+        let day: u32 = caps[2].parse().ok()?;
+        let today = now_utc.date_naive();
+        let mut date = match caps.get(3) {
+            Some(year) => NaiveDate::from_ymd_opt(year.as_str().parse().ok()?, month, day)?,
+            None => NaiveDate::from_ymd_opt(today.year(), month, day)?,
+        };
+        if caps.get(3).is_none() && date <= today {
+            date = NaiveDate::from_ymd_opt(today.year() + 1, month, day)?;
+        }
+        let reset = date.and_time(NaiveTime::MIN).and_utc();
+        let minutes = reset.signed_duration_since(now_utc).num_minutes();
+        return (minutes >= 0).then_some(minutes);
+    }
+    // "Resets in 5h 10m"
+    parse_gemini_reset(reset_info)
+}
+
 fn parse_codex_reset(reset_info: &str, now_utc: DateTime<Utc>) -> Option<i64> {
+    // "resets in 3h 20m"
+    if let Some(caps) = CODEX_RESET_RELATIVE_RE.captures(reset_info) {
+        let part = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<i64>().ok());
+        if (1..=3).any(|i| caps.get(i).is_some()) {
+            let days = part(1).unwrap_or(0);
+            let hours = part(2).unwrap_or(0);
+            return Some((days * 24 + hours) * 60 + part(3).unwrap_or(0));
+        }
+    }
+
     // "resets 12:07 on 16 Feb"
-    let re_with_date =
-        Regex::new(r"(?i)resets?\s+(\d{1,2}):(\d{2})\s+on\s+(\d{1,2})\s+(\w+)").ok()?;
-    if let Some(caps) = re_with_date.captures(reset_info) {
+    if let Some(caps) = CODEX_RESET_DATE_RE.captures(reset_info) {
         let hour: u32 = caps[1].parse().ok()?;
         let min: u32 = caps[2].parse().ok()?;
         let day: u32 = caps[3].parse().ok()?;
@@ -411,8 +1213,7 @@ fn parse_codex_reset(reset_info: &str, now_utc: DateTime<Utc>) -> Option<i64> {
     }
 
     // "resets 16:25"
-    let re_time = Regex::new(r"(?i)resets?\s+(\d{1,2}):(\d{2})").ok()?;
-    if let Some(caps) = re_time.captures(reset_info) {
+    if let Some(caps) = CODEX_RESET_TIME_RE.captures(reset_info) {
         let hour: u32 = caps[1].parse().ok()?;
         let min: u32 = caps[2].parse().ok()?;
 
@@ -439,16 +1240,13 @@ fn parse_codex_reset(reset_info: &str, now_utc: DateTime<Utc>) -> Option<i64> {
 
 fn parse_claude_reset(reset_info: &str, now_utc: DateTime<Utc>) -> Option<i64> {
     // Extract timezone from parentheses
-    let tz_re = Regex::new(r"\(([^)]+)\)").ok()?;
-    let tz_str = tz_re.captures(reset_info)?.get(1)?.as_str();
+    let tz_str = PAREN_TZ_RE.captures(reset_info)?.get(1)?.as_str();
     let tz: Tz = tz_str.parse().ok()?;
 
     let now_tz = now_utc.with_timezone(&tz);
 
     // "Resets Feb 20 at 9am (...)" or compact "ResetsFeb20at9am(...)"
-    let date_time_re =
-        Regex::new(r"(?i)Resets?\s*([A-Za-z]+)\s*(\d{1,2})\s*at\s*(.+?)\s*\(").ok()?;
-    if let Some(caps) = date_time_re.captures(reset_info) {
+    if let Some(caps) = CLAUDE_RESET_DATE_TIME_RE.captures(reset_info) {
         let month = parse_month(&caps[1])?;
         let day: u32 = caps[2].parse().ok()?;
         let (hour, min) = parse_12h_time(&caps[3])?;
@@ -472,8 +1270,7 @@ fn parse_claude_reset(reset_info: &str, now_utc: DateTime<Utc>) -> Option<i64> {
 
     // "Resets 2pm (...)" or compact "Resets10pm(...)".
     // Time only: assume today in provider TZ and wrap to tomorrow if already past.
-    let time_re = Regex::new(r"(?i)Resets?\s*(\d{1,2}(?::\d{2})?\s*(?:am|pm))\s*\(").ok()?;
-    if let Some(caps) = time_re.captures(reset_info) {
+    if let Some(caps) = CLAUDE_RESET_TIME_RE.captures(reset_info) {
         let (hour, min) = parse_12h_time(&caps[1])?;
 
         let today = now_tz.date_naive();
@@ -493,8 +1290,7 @@ fn parse_claude_reset(reset_info: &str, now_utc: DateTime<Utc>) -> Option<i64> {
     }
 
     // "Resets Mar 1 (...)" or compact "ResetsMar1(...)" - date only
-    let date_re = Regex::new(r"(?i)Resets?\s*([A-Za-z]+)\s*(\d{1,2})\s*\(").ok()?;
-    if let Some(caps) = date_re.captures(reset_info) {
+    if let Some(caps) = CLAUDE_RESET_DATE_RE.captures(reset_info) {
         let month = parse_month(&caps[1])?;
         let day: u32 = caps[2].parse().ok()?;
 
@@ -519,14 +1315,19 @@ fn parse_claude_reset(reset_info: &str, now_utc: DateTime<Utc>) -> Option<i64> {
 }
 
 /// Parse reset_info into minutes until reset. Testable variant that accepts a controlled "now".
-fn parse_reset_minutes_at(reset_info: &str, provider: &str, now_utc: DateTime<Utc>) -> Option<i64> {
+pub(crate) fn parse_reset_minutes_at(
+    reset_info: &str,
+    provider: &str,
+    now_utc: DateTime<Utc>,
+) -> Option<i64> {
     if reset_info.is_empty() {
         return None;
     }
     match provider {
-        "gemini" => parse_gemini_reset(reset_info),
+        "gemini" | "qwen" => parse_gemini_reset(reset_info),
         "codex" => parse_codex_reset(reset_info, now_utc),
         "claude" => parse_claude_reset(reset_info, now_utc),
+        "copilot" | "cursor" | "amazonq" => parse_allowance_reset(reset_info, now_utc),
         _ => None,
     }
 }
@@ -540,6 +1341,41 @@ pub fn parse_reset_minutes(reset_info: &str, provider: &str) -> Option<i64> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_output_dispatches_by_provider() {
+        let data = parse_output("codex", crate::fixtures::CODEX_BUSINESS_CAPTURE).unwrap();
+        assert_eq!(data.provider, "codex");
+        assert!(parse_output("bogus", "").is_err());
+    }
+
+    #[test]
+    fn test_static_regexes_compile() {
+        for re in [
+            &CLAUDE_PCT_RE,
+            &CLAUDE_MONEY_RE,
+            &CLAUDE_RESET_RE,
+            &CLAUDE_SEAT_HEADER_RE,
+            &CODEX_LIMIT_RE,
+            &CODEX_SECTION_RE,
+            &WORKSPACE_SCOPE_RE,
+            &GEMINI_MODEL_RE,
+            &GEMINI_QUOTA_RE,
+            &PERCENT_RE,
+            &TIME_12H_RE,
+            &HOURS_MINUTES_RE,
+            &HOURS_RE,
+            &MINUTES_RE,
+            &CODEX_RESET_DATE_RE,
+            &CODEX_RESET_TIME_RE,
+            &PAREN_TZ_RE,
+            &CLAUDE_RESET_DATE_TIME_RE,
+            &CLAUDE_RESET_TIME_RE,
+            &CLAUDE_RESET_DATE_RE,
+        ] {
+            LazyLock::force(re);
+        }
+    }
+
     // ── Claude parser tests ─────────────────────────────────────────
 
     #[test]
@@ -671,6 +1507,43 @@ $77.33/$500.00spent·ResetsMar1(America/Chicago)
         assert_eq!(data.entries[2].percent_used, 0);
         assert_eq!(data.entries[3].label, "Extra usage");
         assert_eq!(data.entries[3].percent_used, 15);
+        assert!(data
+            .entries
+            .iter()
+            .all(|e| e.confidence == Confidence::Inferred));
+    }
+
+    #[test]
+    fn test_claude_confidence_per_header() {
+        let text = "Current session\n██  5% used\n\n\
+                    Current week (Opus only)\n░░  3% used\n\n\
+                    Current weekallmod█ls)\n██  16% used\n";
+        let data = parse_claude_output(text).unwrap();
+        let confidence: Vec<_> = data.entries.iter().map(|e| e.confidence).collect();
+        assert_eq!(
+            confidence,
+            [Confidence::Exact, Confidence::Exact, Confidence::Fallback]
+        );
+    }
+
+    #[test]
+    fn test_claude_extra_usage_enabled() {
+        let data = parse_claude_output(crate::fixtures::CLAUDE_CAPTURE).unwrap();
+        assert_eq!(data.extra_usage_enabled, Some(true));
+
+        let off = "Current session\n██  5% used\n\nExtra usage\n\
+                   Extra usage not enabled • /extra-usage to enable\n";
+        let data = parse_claude_output(off).unwrap();
+        assert_eq!(data.entries.len(), 1);
+        assert_eq!(data.extra_usage_enabled, Some(false));
+        let noisy = "Currentsession██5%used\nExtrausagenotenabled•/extra-usagetoenable\n";
+        assert_eq!(
+            parse_claude_output(noisy).unwrap().extra_usage_enabled,
+            Some(false)
+        );
+
+        let data = parse_claude_output("Current session\n██  5% used\n").unwrap();
+        assert_eq!(data.extra_usage_enabled, None);
     }
 
     #[test]
@@ -709,6 +1582,58 @@ Resets Feb 20
         assert_eq!(data.entries.len(), 2);
     }
 
+    #[test]
+    fn test_claude_team_seat_shared_limit() {
+        let data = parse_claude_output(crate::fixtures::CLAUDE_TEAM_CAPTURE).unwrap();
+        let labels: Vec<&str> = data.entries.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Current session",
+                "Current week (all models)",
+                "Organization usage (shared)"
+            ]
+        );
+        assert_eq!(data.entries[2].percent_used, 45);
+        assert_eq!(data.entries[2].reset_info, "Resets Mar 1 (America/Chicago)");
+    }
+
+    #[test]
+    fn test_claude_enterprise_seat_and_pooled_spend() {
+        let data = parse_claude_output(crate::fixtures::CLAUDE_ENTERPRISE_CAPTURE).unwrap();
+        assert_eq!(data.entries.len(), 2);
+        assert_eq!(data.entries[0].label, "Your seat");
+        assert_eq!(data.entries[0].percent_used, 8);
+        assert_eq!(data.entries[0].reset_info, "Resets 2pm (America/Chicago)");
+        assert_eq!(data.entries[1].label, "Team monthly limit");
+        assert_eq!(
+            data.entries[1].spent.as_deref(),
+            Some("$3,100.00 / $5,000.00 spent")
+        );
+    }
+
+    #[test]
+    fn test_claude_seat_headers_need_usage_wording() {
+        assert!(CLAUDE_SEAT_HEADER_RE.is_match("Workspace limit:"));
+        assert!(CLAUDE_SEAT_HEADER_RE.is_match("Seat usage"));
+        // Chrome mentioning the team plan isn't a section title.
+        assert!(!CLAUDE_SEAT_HEADER_RE.is_match("Team plan · Acme Corp"));
+        assert!(!CLAUDE_SEAT_HEADER_RE.is_match("Organization: Acme Corp"));
+    }
+
+    #[test]
+    fn test_claude_usage_managed_by_org() {
+        assert!(claude_usage_managed_by_org(
+            "Usage limits for your seat are managed by your organization.\nContact your admin."
+        ));
+        assert!(!claude_usage_managed_by_org(
+            "Managed by your organization\nCurrent session\n██ 5% used"
+        ));
+        assert!(!claude_usage_managed_by_org(
+            crate::fixtures::CLAUDE_CAPTURE
+        ));
+    }
+
     #[test]
     fn test_claude_json_serialization_skips_none_spent() {
         let data = crate::types::UsageData {
@@ -722,7 +1647,17 @@ Resets Feb 20
                 reset_minutes: None,
                 spent: None,
                 requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Session,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
         };
         let json = serde_json::to_string(&data).unwrap();
         assert!(!json.contains("spent"));
@@ -741,7 +1676,17 @@ Resets Feb 20
                 reset_minutes: None,
                 spent: Some("$77.33 / $500.00 spent".to_string()),
                 requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Monthly,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
         };
         let json = serde_json::to_string(&data).unwrap();
         assert!(json.contains("$77.33"));
@@ -885,7 +1830,17 @@ Some-Model limit:
                 reset_minutes: None,
                 spent: None,
                 requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Session,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
         };
         let json = serde_json::to_string(&data).unwrap();
         assert!(json.contains("\"codex\""));
@@ -893,6 +1848,41 @@ Some-Model limit:
         assert!(!json.contains("spent"));
     }
 
+    #[test]
+    fn test_codex_business_workspace_limits() {
+        let data = parse_codex_output(crate::fixtures::CODEX_BUSINESS_CAPTURE).unwrap();
+        let rows: Vec<(&str, LimitScope, u32)> = data
+            .entries
+            .iter()
+            .map(|e| (e.label.as_str(), e.scope, e.percent_remaining))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("5h limit", LimitScope::Personal, 97),
+                ("Weekly limit", LimitScope::Personal, 71),
+                ("Workspace Shared credits", LimitScope::Workspace, 30),
+                ("Workspace Weekly limit", LimitScope::Workspace, 48),
+            ]
+        );
+        assert_eq!(data.entries[3].percent_kind, PercentKind::Used);
+    }
+
+    #[test]
+    fn test_codex_pooled_row_outside_section_is_workspace() {
+        let text = "Shared weekly limit:  [████░░░░] 40% left (resets 09:00 on 20 Feb)";
+        let data = parse_codex_output(text).unwrap();
+        assert_eq!(data.entries[0].label, "Shared weekly limit");
+        assert_eq!(data.entries[0].scope, LimitScope::Workspace);
+    }
+
+    #[test]
+    fn test_claude_shared_rows_are_workspace_scoped() {
+        let data = parse_claude_output(crate::fixtures::CLAUDE_ENTERPRISE_CAPTURE).unwrap();
+        assert_eq!(data.entries[0].scope, LimitScope::Personal);
+        assert_eq!(data.entries[1].scope, LimitScope::Workspace);
+    }
+
     #[test]
     fn test_codex_multiple_sections() {
         let text = "\
@@ -971,6 +1961,301 @@ Model-B limit:
         assert_eq!(data.entries[4].reset_info, "Resets in 1h 13m");
     }
 
+    #[test]
+    fn test_gemini_code_assist_daily_quota() {
+        let data = parse_gemini_output(crate::fixtures::GEMINI_CODE_ASSIST_CAPTURE).unwrap();
+        assert_eq!(data.entries.len(), 2);
+        let pro = &data.entries[0];
+        assert_eq!(pro.label, "gemini-2.5-pro");
+        assert_eq!(pro.percent_used, 8);
+        assert_eq!(pro.percent_kind, PercentKind::Used);
+        assert_eq!(pro.requests.as_deref(), Some("120 / 1,500"));
+        assert_eq!(
+            (pro.requests_used, pro.requests_limit),
+            (Some(120), Some(1500))
+        );
+        assert_eq!(pro.reset_minutes, Some(11 * 60 + 20));
+        assert!(data.entries.iter().all(|e| e.window == LimitWindow::Daily));
+    }
+
+    #[test]
+    fn test_gemini_request_caps() {
+        let text = "\
+│  gemini-2.5-pro                 42   58.0% (Resets in 2h 35m)
+│  gemini-2.5-flash               -    99.0% (Resets in 2h 35m)
+│  gemini-2.5-flash-lite        1200   99.9% (Resets in 2h 35m)
+│  gemini-3-pro-preview            3   97.0% (Resets in 2h 35m)";
+        let mut data = parse_gemini_output(text).unwrap();
+        assert!(data.entries.iter().all(|e| e.requests_used.is_none()));
+        let mut caps: BTreeMap<String, u64> = GEMINI_DAILY_REQUEST_CAPS
+            .iter()
+            .map(|(model, cap)| (model.to_string(), *cap))
+            .collect();
+        caps.insert("gemini-3-pro-preview".into(), 50);
+        apply_request_caps(&mut data, &caps);
+        let counted: Vec<_> = data
+            .entries
+            .iter()
+            .map(|e| (e.requests_used, e.requests_limit))
+            .collect();
+        assert_eq!(
+            counted,
+            [
+                (Some(42), Some(100)),
+                (None, None),
+                (None, None),
+                (Some(3), Some(50))
+            ]
+        );
+        let json = serde_json::to_value(&data.entries[0]).unwrap();
+        assert_eq!(json["requests_used"], 42);
+        assert_eq!(json["requests_limit"], 100);
+    }
+
+    #[test]
+    fn test_gemini_code_assist_zero_quota_skipped() {
+        let text = "│  gemini-2.5-pro   0 / 0 requests   (Resets in 1h)";
+        assert!(parse_gemini_output(text).unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn test_qwen_daily_quota() {
+        let data = parse_output("qwen", crate::fixtures::QWEN_CAPTURE).unwrap();
+        assert_eq!(data.provider, "qwen");
+        assert_eq!(data.entries.len(), 2);
+        let plus = &data.entries[0];
+        assert_eq!(plus.label, "qwen3-coder-plus");
+        assert_eq!(plus.percent_used, 16);
+        assert_eq!(plus.requests.as_deref(), Some("312 / 2,000"));
+        assert_eq!(plus.reset_minutes, Some(9 * 60 + 41));
+        assert_eq!(
+            canonical_keys(&data),
+            ["qwen3-coder-plus", "qwen3-coder-flash"]
+        );
+    }
+
+    #[test]
+    fn test_copilot_premium_requests() {
+        let data = parse_copilot_output(crate::fixtures::COPILOT_CAPTURE).unwrap();
+        assert_eq!(data.provider, "copilot");
+        assert_eq!(data.entries.len(), 1, "unlimited rows are skipped");
+        let premium = &data.entries[0];
+        assert_eq!(premium.label, "Premium requests");
+        assert_eq!(premium.percent_used, 44);
+        assert_eq!(premium.percent_remaining, 56);
+        assert_eq!(premium.requests.as_deref(), Some("132 / 300"));
+        assert_eq!(premium.reset_info, "Resets on Nov 1, 2026");
+        assert_eq!(premium.window, LimitWindow::Monthly);
+    }
+
+    #[test]
+    fn test_copilot_free_plan_and_percent_rows() {
+        let data = parse_copilot_output(crate::fixtures::COPILOT_FREE_CAPTURE).unwrap();
+        let used: Vec<u32> = data.entries.iter().map(|e| e.percent_used).collect();
+        assert_eq!(used, [24, 76, 21]);
+        assert_eq!(data.entries[2].requests.as_deref(), Some("420 / 2,000"));
+        assert!(data
+            .entries
+            .iter()
+            .all(|e| e.reset_minutes == Some(13 * 24 * 60)));
+
+        let text = "Premium requests: 62.5% left (resets in 3 days)\nChat: 10% used";
+        let data = parse_copilot_output(text).unwrap();
+        assert_eq!(data.entries[0].percent_used, 37);
+        assert_eq!(data.entries[0].reset_info, "Resets in 3 days");
+        assert_eq!(data.entries[1].label, "Chat messages");
+        assert_eq!(data.entries[1].percent_used, 10);
+        assert_eq!(data.entries[1].reset_info, "");
+    }
+
+    #[test]
+    fn test_cursor_included_and_on_demand_usage() {
+        let data = parse_cursor_output(crate::fixtures::CURSOR_CAPTURE).unwrap();
+        assert_eq!(data.provider, "cursor");
+        let labels: Vec<&str> = data.entries.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(labels, ["Included usage", "On-demand usage"]);
+        let included = &data.entries[0];
+        assert_eq!(included.percent_used, 62);
+        assert_eq!(included.spent.as_deref(), Some("$12.40 / $20.00 spent"));
+        assert_eq!(included.reset_info, "Resets on Nov 1");
+        assert_eq!(included.window, LimitWindow::Monthly);
+        assert_eq!(data.entries[1].percent_used, 6);
+    }
+
+    #[test]
+    fn test_cursor_request_based_plan() {
+        let text = "│  Fast requests      312 / 500   (resets in 9 days)\n\
+                    │  Slow requests      Unlimited\n";
+        let data = parse_cursor_output(text).unwrap();
+        assert_eq!(data.entries.len(), 1);
+        assert_eq!(data.entries[0].label, "Fast requests");
+        assert_eq!(data.entries[0].percent_used, 62);
+        assert_eq!(data.entries[0].requests.as_deref(), Some("312 / 500"));
+        assert_eq!(data.entries[0].reset_minutes, Some(9 * 24 * 60));
+    }
+
+    #[test]
+    fn test_amazonq_monthly_allowances() {
+        let data = parse_output("amazonq", crate::fixtures::AMAZONQ_CAPTURE).unwrap();
+        assert_eq!(data.provider, "amazonq");
+        let labels: Vec<&str> = data.entries.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            ["Agentic requests", "Code transformation"],
+            "the context window and unlimited rows are skipped"
+        );
+        assert_eq!(data.entries[0].percent_used, 21);
+        assert_eq!(data.entries[0].requests.as_deref(), Some("212 / 1,000"));
+        assert_eq!(data.entries[1].percent_used, 30);
+        assert!(data
+            .entries
+            .iter()
+            .all(|e| e.reset_info == "Resets on Nov 1, 2026" && e.window == LimitWindow::Monthly));
+        assert_eq!(
+            canonical_keys(&data),
+            ["agentic_requests", "code_transformation"]
+        );
+    }
+
+    #[test]
+    fn test_aider_tokens_and_cost() {
+        let data = parse_output("aider", crate::fixtures::AIDER_CAPTURE).unwrap();
+        assert_eq!(data.provider, "aider");
+        assert_eq!(data.entries.len(), 1);
+        let context = &data.entries[0];
+        assert_eq!(context.label, "Context window");
+        assert_eq!(context.percent_used, 2);
+        assert_eq!(
+            context.requests.as_deref(),
+            Some("3,030 / 128,000 tokens (~$0.0090 estimated)")
+        );
+        assert_eq!(context.spent, None);
+        assert_eq!(context.window, LimitWindow::Other);
+        assert_eq!(context.reset_minutes, None);
+        assert_eq!(canonical_keys(&data), ["context_window"]);
+
+        let no_max = "$ 0.0021      700 tokens total\n";
+        let data = parse_aider_output(no_max).unwrap();
+        assert_eq!(data.entries[0].percent_used, 0);
+        assert_eq!(
+            data.entries[0].requests.as_deref(),
+            Some("700 tokens (~$0.0021 estimated)")
+        );
+        assert!(parse_aider_output("> /tokens\n")
+            .unwrap()
+            .entries
+            .is_empty());
+    }
+
+    #[test]
+    fn test_explain_marks_matching_lines() {
+        let lines = explain("codex", crate::fixtures::CODEX_CAPTURE).unwrap();
+        let limits: Vec<&LineMatch> = lines
+            .iter()
+            .filter(|l| l.matched.contains(&"limit"))
+            .collect();
+        assert_eq!(
+            limits.len(),
+            parse_codex_output(crate::fixtures::CODEX_CAPTURE)
+                .unwrap()
+                .entries
+                .len()
+        );
+        assert!(lines.iter().any(|l| l.matched.is_empty()));
+        assert!(lines.iter().all(|l| !l.text.is_empty()));
+
+        let copilot = explain("copilot", "│ Premium requests  132 / 300 (44%) │").unwrap();
+        assert_eq!(copilot[0].line, 1);
+        assert_eq!(
+            copilot[0].matched,
+            ["row", "allowance_count", "allowance_percent"]
+        );
+        for provider in crate::ALL_PROVIDERS {
+            assert!(explain(provider, "").is_ok(), "{} has patterns", provider);
+        }
+        assert!(explain("bogus", "").is_err());
+    }
+
+    #[test]
+    fn test_copilot_reset_dates() {
+        use chrono::TimeZone;
+        let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        let minutes = |text: &str| parse_allowance_reset(text, now);
+        assert_eq!(minutes("Resets on Nov 1"), Some(13 * 24 * 60 + 12 * 60));
+        assert_eq!(
+            minutes("Resets on Oct 1"),
+            Some((347 * 24 + 12) * 60),
+            "a date already past is next year's"
+        );
+        assert_eq!(minutes("Resets on Oct 1, 2026"), None);
+        assert_eq!(minutes("Resets in 5h 10m"), Some(5 * 60 + 10));
+    }
+
+    #[test]
+    fn test_window_for_label() {
+        assert_eq!(window_for_label("Current session"), LimitWindow::Session);
+        assert_eq!(window_for_label("Model-A 5h limit"), LimitWindow::Session);
+        assert_eq!(
+            window_for_label("Workspace Weekly limit"),
+            LimitWindow::Weekly
+        );
+        assert_eq!(window_for_label("Extra usage"), LimitWindow::Monthly);
+        assert_eq!(window_for_label("Team monthly limit"), LimitWindow::Monthly);
+        assert_eq!(
+            window_for_label("Workspace Shared credits"),
+            LimitWindow::Other
+        );
+    }
+
+    #[test]
+    fn test_canonical_keys() {
+        let keys =
+            |provider: &str, text: &str| canonical_keys(&parse_output(provider, text).unwrap());
+        assert_eq!(
+            keys("claude", crate::fixtures::CLAUDE_CAPTURE),
+            ["session", "weekly", "weekly_sonnet", "extra_usage"]
+        );
+        assert_eq!(
+            keys("claude", crate::fixtures::CLAUDE_ENTERPRISE_CAPTURE),
+            ["your_seat", "workspace_monthly"]
+        );
+        assert_eq!(
+            keys("codex", crate::fixtures::CODEX_BUSINESS_CAPTURE),
+            [
+                "session",
+                "weekly",
+                "workspace_shared_credits",
+                "workspace_weekly"
+            ]
+        );
+        assert_eq!(
+            keys("copilot", crate::fixtures::COPILOT_FREE_CAPTURE),
+            ["premium_requests", "chat_messages", "code_completions"]
+        );
+        assert_eq!(
+            keys("cursor", crate::fixtures::CURSOR_CAPTURE),
+            ["included_usage", "on_demand_usage"]
+        );
+        assert_eq!(
+            keys("gemini", crate::fixtures::GEMINI_CAPTURE),
+            [
+                "gemini-2.5-flash-lite",
+                "gemini-2.5-flash",
+                "gemini-2.5-pro"
+            ]
+        );
+
+        let text = "Current week (Opus)\n██░░  12% used\nResets Feb 20 at 9am\n\n\
+                    Current week (Sonnet only)\n██░░  20% used\nResets Feb 20 at 9am\n";
+        assert_eq!(keys("claude", text), ["weekly_opus", "weekly_sonnet"]);
+        let text = "Model-A 5h limit:  [███░] 40% left (resets 11:07)\n\
+                    Model-A 5h limit:  [███░] 30% left (resets 11:07)\n";
+        assert_eq!(
+            keys("codex", text),
+            ["session_model_a", "session_model_a_2"]
+        );
+    }
+
     #[test]
     fn test_gemini_empty_output() {
         let data = parse_gemini_output("").unwrap();
@@ -1034,7 +2319,17 @@ Model-B limit:
                 reset_minutes: Some(289),
                 spent: None,
                 requests: Some("6".to_string()),
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Daily,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
         };
         let json = serde_json::to_string(&data).unwrap();
         assert!(json.contains("\"gemini\""));
@@ -1056,7 +2351,17 @@ Model-B limit:
                 reset_minutes: Some(155),
                 spent: None,
                 requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Daily,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
         };
         let json = serde_json::to_string(&data).unwrap();
         assert!(!json.contains("requests"));
@@ -1101,6 +2406,42 @@ Model-B limit:
         assert_eq!(data.entries[0].percent_used, 0);
     }
 
+    // ── Truncation detection tests ──────────────────────────────────
+
+    #[test]
+    fn test_truncated_codex_row_missing_reset() {
+        let text = "│  5h limit:  [████████        ] 97% left (rese│";
+        assert!(has_truncated_rows(text, "codex"));
+    }
+
+    #[test]
+    fn test_truncated_codex_complete_rows() {
+        let text = "│  5h limit:  [████████        ] 97% left (resets 11:07)  │\n\
+                    │  Weekly limit:  [████████    ] 71% left (resets 12:07 on 16 Feb) │";
+        assert!(!has_truncated_rows(text, "codex"));
+    }
+
+    #[test]
+    fn test_truncated_gemini_row_missing_reset() {
+        let text = "│  gemini-2.5-pro                 -    98.1%";
+        assert!(has_truncated_rows(text, "gemini"));
+    }
+
+    #[test]
+    fn test_truncated_gemini_complete_row() {
+        let text = "│  gemini-2.5-pro                 -    98.1% (Resets in 2h 35m) │";
+        assert!(!has_truncated_rows(text, "gemini"));
+    }
+
+    #[test]
+    fn test_truncated_ellipsis_any_provider() {
+        assert!(has_truncated_rows(
+            "Current week (all mod… 3% used…",
+            "claude"
+        ));
+        assert!(!has_truncated_rows("Current session\n 3% used", "claude"));
+    }
+
     // ── Year rollover tests ─────────────────────────────────────────
 
     #[test]
@@ -1326,6 +2667,36 @@ Weekly limit:  [████] 80% left (resets 12:00 on 20 Feb)
         assert!((118..=122).contains(&mins), "Expected ~120, got {}", mins);
     }
 
+    #[test]
+    fn test_codex_reset_minutes_relative() {
+        let now = Utc::now();
+        for (text, minutes) in [
+            ("resets in 3h 20m", 200),
+            ("resets in 45m", 45),
+            ("resets in 5h", 300),
+            ("resets in 2d 4h", 52 * 60),
+            ("Resets in 1 day 2 hours", 26 * 60),
+        ] {
+            assert_eq!(
+                parse_reset_minutes_at(text, "codex", now),
+                Some(minutes),
+                "{}",
+                text
+            );
+        }
+        assert_eq!(
+            parse_reset_minutes_at("resets in a while", "codex", now),
+            None
+        );
+
+        let text = "5h limit:  [████████        ] 64% left (resets in 3h 20m)\n\
+                    Weekly limit:  [████████        ] 71% left (resets in 2d 4h)";
+        let data = parse_codex_output(text).unwrap();
+        assert_eq!(data.entries[0].reset_info, "resets in 3h 20m");
+        assert_eq!(data.entries[0].reset_minutes, Some(200));
+        assert_eq!(data.entries[1].reset_minutes, Some(52 * 60));
+    }
+
     #[test]
     fn test_codex_reset_minutes_with_date() {
         use chrono::TimeZone;
@@ -1364,7 +2735,17 @@ Weekly limit:  [████] 80% left (resets 12:00 on 20 Feb)
                 reset_minutes: Some(289),
                 spent: None,
                 requests: Some("6".to_string()),
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Daily,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
         };
         let json = serde_json::to_string(&data).unwrap();
         assert!(json.contains("\"percent_remaining\":99"));
@@ -1384,7 +2765,17 @@ Weekly limit:  [████] 80% left (resets 12:00 on 20 Feb)
                 reset_minutes: None,
                 spent: None,
                 requests: None,
+                requests_used: None,
+                requests_limit: None,
+                scope: LimitScope::Personal,
+                window: LimitWindow::Session,
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
             }],
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
         };
         let json = serde_json::to_string(&data).unwrap();
         assert!(json.contains("\"percent_remaining\":95"));
@@ -0,0 +1,291 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::{ApprovalPolicy, UsageConfig};
+
+/// What a provider check launches and types.
+pub(crate) struct ProviderSpec {
+    pub name: &'static str,
+    pub binary: &'static str,
+    pub args: &'static [&'static str],
+    /// Hardening flags added under `UsageConfig::safe_launch`: read-only or
+    /// plan modes so a usage check can't run tools or write files.
+    pub safe_args: &'static [&'static str],
+    /// Flags that skip the startup update check under
+    /// `UsageConfig::defer_updates` (others use the environment instead).
+    pub no_update_args: &'static [&'static str],
+    /// Slash command that shows usage.
+    pub command: &'static str,
+    /// Command tried when `command` doesn't render usage in time.
+    pub fallback_command: Option<&'static str>,
+    /// Arguments that print usage without the TUI, tried when the
+    /// interactive session can't start.
+    pub headless_args: Option<&'static [&'static str]>,
+}
+
+pub(crate) const CLAUDE: ProviderSpec = ProviderSpec {
+    name: "claude",
+    binary: "claude",
+    args: &["--allowed-tools", ""],
+    safe_args: &["--permission-mode", "plan"],
+    no_update_args: &[],
+    command: "/usage",
+    fallback_command: Some("/status"),
+    headless_args: None,
+};
+
+pub(crate) const CODEX: ProviderSpec = ProviderSpec {
+    name: "codex",
+    binary: "codex",
+    args: &[],
+    safe_args: &["-s", "read-only", "-a", "untrusted"],
+    no_update_args: &["-c", "check_for_update_on_startup=false"],
+    command: "/status",
+    fallback_command: None,
+    headless_args: None,
+};
+
+pub(crate) const GEMINI: ProviderSpec = ProviderSpec {
+    name: "gemini",
+    binary: "gemini",
+    args: &[],
+    safe_args: &["--approval-mode", "default"],
+    no_update_args: &[],
+    command: "/stats session",
+    fallback_command: None,
+    headless_args: None,
+};
+
+pub(crate) const COPILOT: ProviderSpec = ProviderSpec {
+    name: "copilot",
+    binary: "copilot",
+    args: &[],
+    safe_args: &["--deny-tool", "shell", "--deny-tool", "write"],
+    no_update_args: &[],
+    command: "/usage",
+    fallback_command: None,
+    headless_args: None,
+};
+
+pub(crate) const CURSOR: ProviderSpec = ProviderSpec {
+    name: "cursor",
+    binary: "cursor-agent",
+    args: &[],
+    // cursor-agent asks before running commands or editing unless told
+    // otherwise, so the default launch is already safe.
+    safe_args: &[],
+    no_update_args: &[],
+    command: "/status",
+    fallback_command: None,
+    headless_args: None,
+};
+
+pub(crate) const QWEN: ProviderSpec = ProviderSpec {
+    name: "qwen",
+    binary: "qwen",
+    args: &[],
+    safe_args: &["--approval-mode", "default"],
+    no_update_args: &[],
+    command: "/stats",
+    fallback_command: None,
+    headless_args: None,
+};
+
+pub(crate) const AMAZONQ: ProviderSpec = ProviderSpec {
+    name: "amazonq",
+    binary: "q",
+    args: &["chat"],
+    // An empty list trusts no tools, so every tool use needs confirmation.
+    safe_args: &["--trust-tools="],
+    no_update_args: &[],
+    command: "/usage",
+    fallback_command: None,
+    headless_args: None,
+};
+
+pub(crate) const AIDER: ProviderSpec = ProviderSpec {
+    name: "aider",
+    binary: "aider",
+    // A usage check shouldn't stop at aider's pip-upgrade or analytics
+    // opt-in questions.
+    args: &[
+        "--no-check-update",
+        "--no-show-release-notes",
+        "--no-analytics",
+    ],
+    // No repo creation, .gitignore edits, or commits.
+    safe_args: &["--no-git"],
+    no_update_args: &[],
+    command: "/tokens",
+    fallback_command: None,
+    headless_args: None,
+};
+
+const PROVIDERS: [&ProviderSpec; 8] = [
+    &CLAUDE, &CODEX, &GEMINI, &COPILOT, &CURSOR, &QWEN, &AMAZONQ, &AIDER,
+];
+
+impl ProviderSpec {
+    /// Arguments the CLI is launched with under `config`.
+    pub fn launch_args(&self, config: &UsageConfig) -> Vec<&'static str> {
+        let mut args = self.args.to_vec();
+        if config.safe_launch {
+            args.extend_from_slice(self.safe_args);
+        }
+        if config.defer_updates {
+            args.extend_from_slice(self.no_update_args);
+        }
+        args
+    }
+}
+
+/// What a check would do under a given config, resolved without launching
+/// anything.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckPlan {
+    pub provider: &'static str,
+    pub binary: &'static str,
+    /// Where `binary` resolves on PATH, if it does.
+    pub binary_path: Option<String>,
+    pub args: Vec<&'static str>,
+    pub command: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_command: Option<&'static str>,
+    pub directory: Option<String>,
+    /// Private cache/state/tmp directories (`--isolate-env`).
+    pub isolate_env: bool,
+    /// Folder trust recorded before launch (`--preseed-trust`).
+    pub preseed_trust: bool,
+    /// Provider update checks switched off for the session (`--defer-updates`).
+    pub defer_updates: bool,
+    pub approval_policy: &'static str,
+    pub prompt_timeout_secs: u64,
+    pub data_timeout_secs: u64,
+    pub poll_interval_ms: u64,
+}
+
+/// Resolve the plan for `provider` ("claude", "codex", "gemini", "copilot",
+/// "cursor", "qwen", "amazonq" or "aider").
+pub fn plan(config: &UsageConfig, provider: &str) -> Option<CheckPlan> {
+    let spec = PROVIDERS.iter().find(|spec| spec.name == provider)?;
+    Some(CheckPlan {
+        provider: spec.name,
+        binary: spec.binary,
+        binary_path: find_on_path(spec.binary).map(|p| p.display().to_string()),
+        args: spec.launch_args(config),
+        command: spec.command,
+        fallback_command: spec.fallback_command,
+        directory: config.directory.clone(),
+        isolate_env: config.isolate_env,
+        preseed_trust: config.preseed_trust,
+        defer_updates: config.defer_updates,
+        approval_policy: match config.approval_policy {
+            ApprovalPolicy::Fail => "fail",
+            ApprovalPolicy::Accept => "accept",
+            ApprovalPolicy::Onboard => "onboard",
+        },
+        prompt_timeout_secs: config.prompt_timeout(provider).as_secs(),
+        data_timeout_secs: config.data_timeout(provider).as_secs(),
+        poll_interval_ms: config.poll_interval(provider).as_millis() as u64,
+    })
+}
+
+/// First executable named `binary` on PATH.
+pub(crate) fn find_on_path(binary: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderTimings;
+
+    #[test]
+    fn test_plan_resolves_config() {
+        let mut config = UsageConfig {
+            approval_policy: ApprovalPolicy::Accept,
+            directory: Some("/tmp".into()),
+            ..UsageConfig::default()
+        };
+        config.provider_timings.insert(
+            "codex".into(),
+            ProviderTimings {
+                prompt_timeout: Some(90),
+                ..ProviderTimings::default()
+            },
+        );
+        let plan = plan(&config, "codex").unwrap();
+        assert_eq!(plan.binary, "codex");
+        assert_eq!(plan.args, vec!["-s", "read-only", "-a", "untrusted"]);
+        assert_eq!(plan.command, "/status");
+        assert_eq!(plan.approval_policy, "accept");
+        assert_eq!(plan.directory.as_deref(), Some("/tmp"));
+        assert_eq!(plan.prompt_timeout_secs, 90);
+        assert_eq!(plan.data_timeout_secs, 45);
+        assert_eq!(plan.poll_interval_ms, 500);
+    }
+
+    #[test]
+    fn test_plan_claude_has_fallback() {
+        let plan = plan(&UsageConfig::default(), "claude").unwrap();
+        assert_eq!(plan.command, "/usage");
+        assert_eq!(plan.fallback_command, Some("/status"));
+    }
+
+    #[test]
+    fn test_plan_safe_launch_args() {
+        let safe = UsageConfig::default();
+        let unsafe_config = UsageConfig {
+            safe_launch: false,
+            ..UsageConfig::default()
+        };
+        assert_eq!(
+            plan(&safe, "claude").unwrap().args,
+            vec!["--allowed-tools", "", "--permission-mode", "plan"]
+        );
+        assert_eq!(
+            plan(&safe, "gemini").unwrap().args,
+            vec!["--approval-mode", "default"]
+        );
+        assert_eq!(
+            plan(&unsafe_config, "claude").unwrap().args,
+            vec!["--allowed-tools", ""]
+        );
+        assert!(plan(&unsafe_config, "codex").unwrap().args.is_empty());
+    }
+
+    #[test]
+    fn test_plan_defer_updates_args() {
+        let config = UsageConfig {
+            defer_updates: true,
+            safe_launch: false,
+            ..UsageConfig::default()
+        };
+        assert_eq!(
+            plan(&config, "codex").unwrap().args,
+            vec!["-c", "check_for_update_on_startup=false"]
+        );
+        assert!(plan(&config, "gemini").unwrap().args.is_empty());
+    }
+
+    #[test]
+    fn test_plan_unknown_provider() {
+        assert!(plan(&UsageConfig::default(), "bogus").is_none());
+    }
+
+    #[test]
+    fn test_find_on_path() {
+        assert!(find_on_path("sh").is_some());
+        assert!(find_on_path("nonexistent_tool_xyz_12345").is_none());
+    }
+}
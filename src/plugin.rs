@@ -0,0 +1,320 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::parser::{scope_for_label, window_for_label};
+use crate::provider::{Provider, ProviderRegistry};
+use crate::types::{
+    Capabilities, CheckStats, Confidence, LimitScope, LimitWindow, PercentKind, UsageData,
+    UsageEntry,
+};
+use crate::UsageConfig;
+
+/// `$XDG_CONFIG_HOME/agentusage/providers` (default
+/// `~/.config/agentusage/providers`).
+pub fn default_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    let config_home = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("agentusage").join("providers"))
+}
+
+/// What a plugin prints on stdout: its limits, or why it has none.
+#[derive(Debug, Deserialize)]
+struct PluginOutput {
+    #[serde(default)]
+    entries: Vec<PluginEntry>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PluginEntry {
+    label: String,
+    percent_used: f64,
+    #[serde(default)]
+    reset_info: String,
+    #[serde(default)]
+    reset_minutes: Option<i64>,
+    #[serde(default)]
+    spent: Option<String>,
+    #[serde(default)]
+    requests: Option<String>,
+    #[serde(default)]
+    scope: Option<LimitScope>,
+    #[serde(default)]
+    window: Option<LimitWindow>,
+}
+
+/// An executable that reports a provider's usage as JSON, checked by
+/// running it instead of driving a TUI.  The provider is named after the
+/// file, without its extension.
+#[derive(Debug, Clone)]
+pub struct PluginProvider {
+    name: String,
+    path: String,
+}
+
+impl PluginProvider {
+    pub fn new(name: impl Into<String>, path: &Path) -> Self {
+        Self {
+            name: name.into(),
+            path: path.to_string_lossy().into_owned(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        Path::new(&self.path)
+    }
+}
+
+impl Provider for PluginProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn binary(&self) -> &str {
+        &self.path
+    }
+
+    fn prompt_ready(&self, _screen: &str) -> bool {
+        true
+    }
+
+    fn usage_command(&self) -> &str {
+        ""
+    }
+
+    fn usage_ready(&self, screen: &str) -> bool {
+        self.parse(screen).is_ok()
+    }
+
+    fn parse(&self, output: &str) -> Result<UsageData> {
+        parse_plugin_output(&self.name, output)
+    }
+
+    /// Every optional field has a place in the plugin's JSON.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            supports_spend: true,
+            supports_requests: true,
+            supports_reset_minutes: true,
+            ..Capabilities::default()
+        }
+    }
+
+    /// Run the plugin with no arguments, in the directory a CLI check
+    /// would use, and parse what it printed.  It is killed after
+    /// `config.timeout` seconds.
+    fn check(&self, config: &UsageConfig) -> Result<UsageData> {
+        let timeout = Duration::from_secs(config.timeout);
+        let output = crate::headless::execute(&self.path, &[], config, timeout)?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            let reported = serde_json::from_str::<PluginOutput>(&stdout)
+                .ok()
+                .and_then(|doc| doc.error);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let detail = reported.or_else(|| {
+                stderr
+                    .lines()
+                    .rev()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .map(String::from)
+            });
+            match detail {
+                Some(detail) => bail!("{} plugin failed: {}", self.name, detail),
+                None => bail!("{} plugin exited with {}", self.name, output.status),
+            }
+        }
+        self.parse(&stdout)
+    }
+}
+
+/// Parse a plugin's JSON report into usage data for `provider`.
+pub fn parse_plugin_output(provider: &str, output: &str) -> Result<UsageData> {
+    let doc: PluginOutput = serde_json::from_str(output.trim()).map_err(|e| {
+        anyhow::anyhow!(
+            "[parse-failure] {} plugin printed invalid JSON: {}",
+            provider,
+            e
+        )
+    })?;
+    if let Some(error) = doc.error {
+        bail!("{} plugin failed: {}", provider, error);
+    }
+    if doc.entries.is_empty() {
+        bail!("[parse-failure] {} plugin reported no limits", provider);
+    }
+    let entries = doc
+        .entries
+        .into_iter()
+        .map(|entry| {
+            let used = (entry.percent_used.round().max(0.0) as u32).min(100);
+            UsageEntry {
+                percent_used: used,
+                percent_remaining: 100 - used,
+                percent_kind: PercentKind::Used,
+                reset_info: entry.reset_info,
+                reset_minutes: entry.reset_minutes,
+                spent: entry.spent,
+                requests: entry.requests,
+                requests_used: None,
+                requests_limit: None,
+                scope: entry.scope.unwrap_or_else(|| scope_for_label(&entry.label)),
+                window: entry
+                    .window
+                    .unwrap_or_else(|| window_for_label(&entry.label)),
+                confidence: Confidence::Exact,
+                inconsistent: false,
+                as_of: None,
+                label: entry.label,
+            }
+        })
+        .collect();
+    Ok(UsageData {
+        provider: provider.to_string(),
+        entries,
+        extra_usage_enabled: None,
+        tokens: Vec::new(),
+        check: CheckStats::default(),
+    })
+}
+
+/// The executables in `dir`, as providers, sorted by name.  Hidden files,
+/// files that aren't executable, and names that aren't lowercase letters,
+/// digits, `-`, and `_` are skipped.  A missing directory has none.
+pub fn discover(dir: &Path) -> Result<Vec<PluginProvider>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    let mut plugins = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        let executable = std::fs::metadata(&path)
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0);
+        if valid_name && executable {
+            plugins.push(PluginProvider::new(name, &path));
+        }
+    }
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins.dedup_by(|a, b| a.name == b.name);
+    Ok(plugins)
+}
+
+/// Register the plugins in `dir` on `registry`, after the providers
+/// already there.  A plugin never replaces a registered provider of the
+/// same name.  Returns the names registered.
+pub fn register_from(registry: &mut ProviderRegistry, dir: &Path) -> Result<Vec<String>> {
+    let mut registered = Vec::new();
+    for plugin in discover(dir)? {
+        if registry.get(&plugin.name).is_some() {
+            continue;
+        }
+        registered.push(plugin.name.clone());
+        registry.register(Arc::new(plugin));
+    }
+    Ok(registered)
+}
+
+/// `register_from` the default plugin directory.
+pub fn register_all(registry: &mut ProviderRegistry) -> Result<Vec<String>> {
+    match default_dir() {
+        Some(dir) => register_from(registry, &dir),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::TempDir;
+
+    fn write_plugin(dir: &Path, file: &str, script: &str, mode: u32) {
+        let path = dir.join(file);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[test]
+    fn test_parse_plugin_output() {
+        let data = parse_plugin_output(
+            "acme",
+            r#"{"entries": [
+                {"label": "Weekly limit", "percent_used": 41.6, "reset_info": "Resets Monday", "reset_minutes": 90},
+                {"label": "Team credits", "percent_used": 120, "scope": "workspace", "window": "monthly", "spent": "$3"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(data.provider, "acme");
+        let weekly = &data.entries[0];
+        assert_eq!((weekly.percent_used, weekly.percent_remaining), (42, 58));
+        assert_eq!(weekly.reset_minutes, Some(90));
+        assert_eq!(weekly.window, LimitWindow::Weekly);
+        assert_eq!(weekly.scope, LimitScope::Personal);
+        let credits = &data.entries[1];
+        assert_eq!(credits.percent_used, 100);
+        assert_eq!(credits.scope, LimitScope::Workspace);
+        assert_eq!(credits.window, LimitWindow::Monthly);
+        assert_eq!(credits.spent.as_deref(), Some("$3"));
+
+        let err = parse_plugin_output("acme", "not json").unwrap_err();
+        assert!(err.to_string().starts_with("[parse-failure]"));
+        let err = parse_plugin_output("acme", r#"{"entries": []}"#).unwrap_err();
+        assert!(err.to_string().starts_with("[parse-failure]"));
+        let err = parse_plugin_output("acme", r#"{"error": "not signed in"}"#).unwrap_err();
+        assert_eq!(err.to_string(), "acme plugin failed: not signed in");
+    }
+
+    #[test]
+    fn test_discover_and_check_plugins() {
+        let dir = TempDir::create("plugins").unwrap();
+        write_plugin(
+            dir.path(),
+            "acme.sh",
+            r#"echo '{"entries": [{"label": "Daily requests", "percent_used": 30}]}'"#,
+            0o755,
+        );
+        write_plugin(
+            dir.path(),
+            "broken",
+            r#"echo 'token expired' >&2; exit 1"#,
+            0o755,
+        );
+        write_plugin(dir.path(), "notes", "exit 0", 0o644);
+        write_plugin(dir.path(), ".hidden", "exit 0", 0o755);
+        write_plugin(dir.path(), "claude", "exit 0", 0o755);
+
+        let mut registry = ProviderRegistry::builtin();
+        let registered = register_from(&mut registry, dir.path()).unwrap();
+        assert_eq!(registered, ["acme", "broken"]);
+        assert_eq!(registry.names().len(), crate::ALL_PROVIDERS.len() + 2);
+
+        let config = UsageConfig {
+            timeout: 5,
+            ..UsageConfig::default()
+        };
+        let data = registry.get("acme").unwrap().check(&config).unwrap();
+        assert_eq!(data.entries[0].label, "Daily requests");
+        assert_eq!(data.entries[0].window, LimitWindow::Daily);
+        let err = registry.get("broken").unwrap().check(&config).unwrap_err();
+        assert_eq!(err.to_string(), "broken plugin failed: token expired");
+
+        let missing = dir.path().join("missing");
+        assert!(discover(&missing).unwrap().is_empty());
+    }
+}
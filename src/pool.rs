@@ -0,0 +1,159 @@
+use anyhow::Result;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::session::Session;
+
+/// A bounded pool of warm provider sessions for long-running hosts, so a
+/// check can skip the CLI's startup (see `UsageConfig::pool`).  Sessions are checked out per
+/// provider and returned afterwards; the least recently returned one is
+/// evicted when the pool is full, sessions idle for longer than the idle
+/// timeout are dropped, and sessions whose CLI has exited are replaced by
+/// a fresh launch on the next checkout.
+pub struct SessionPool {
+    capacity: usize,
+    idle_timeout: Duration,
+    /// Least recently returned first.
+    idle: Mutex<Vec<IdleSession>>,
+}
+
+struct IdleSession {
+    provider: String,
+    session: Session,
+    returned: Instant,
+}
+
+impl SessionPool {
+    pub fn new(capacity: usize, idle_timeout: Duration) -> Self {
+        Self {
+            capacity,
+            idle_timeout,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// A warm, live session for `provider`, or one from `launch` when the
+    /// pool has none.
+    pub fn checkout(
+        &self,
+        provider: &str,
+        launch: impl FnOnce() -> Result<Session>,
+    ) -> Result<Session> {
+        match self.take(provider) {
+            Some(session) => Ok(session),
+            None => launch(),
+        }
+    }
+
+    /// The most recently returned live session for `provider`, if any.
+    /// The screen is as the last check left it, so the caller resets it.
+    pub fn take(&self, provider: &str) -> Option<Session> {
+        self.evict_idle();
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some(pos) = idle.iter().rposition(|s| s.provider == provider) {
+            let mut warm = idle.remove(pos);
+            if warm.session.is_alive() {
+                return Some(warm.session);
+            }
+        }
+        None
+    }
+
+    /// Hand a session back for reuse.  Dead sessions are dropped, and the
+    /// least recently returned session makes room when the pool is full.
+    pub fn checkin(&self, provider: &str, mut session: Session) {
+        if self.capacity == 0 || !session.is_alive() {
+            return;
+        }
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        while idle.len() >= self.capacity {
+            idle.remove(0);
+        }
+        idle.push(IdleSession {
+            provider: provider.to_string(),
+            session,
+            returned: Instant::now(),
+        });
+    }
+
+    /// Drop sessions that have idled past the timeout or whose CLI has
+    /// exited; returns how many were dropped.  Hosts can call this on a
+    /// timer so idle CLIs don't linger between requests.
+    pub fn evict_idle(&self) -> usize {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let before = idle.len();
+        idle.retain_mut(|s| s.returned.elapsed() < self.idle_timeout && s.session.is_alive());
+        before - idle.len()
+    }
+
+    /// Number of idle sessions held.
+    pub fn len(&self) -> usize {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionLaunch;
+    use std::cell::Cell;
+
+    fn launch(script: &str) -> Result<Session> {
+        Session::new(
+            None,
+            false,
+            SessionLaunch {
+                binary: "sh",
+                args: &["-c", script],
+                isolate_env: false,
+                env: &[],
+            },
+        )
+    }
+
+    #[test]
+    fn test_pool_reuses_evicts_and_relaunches() {
+        let pool = SessionPool::new(2, Duration::from_secs(60));
+        let launches = Cell::new(0);
+        let counted = |script: &'static str| {
+            launches.set(launches.get() + 1);
+            launch(script)
+        };
+
+        let claude = pool.checkout("claude", || counted("sleep 30")).unwrap();
+        pool.checkin("claude", claude);
+        let claude = pool.checkout("claude", || counted("sleep 30")).unwrap();
+        assert_eq!(launches.get(), 1, "warm session reused");
+        pool.checkin("claude", claude);
+
+        // Full: returning a third session evicts the least recent (claude).
+        pool.checkin("codex", counted("sleep 30").unwrap());
+        pool.checkin("gemini", counted("sleep 30").unwrap());
+        assert_eq!(pool.len(), 2);
+        pool.checkout("claude", || counted("sleep 30")).unwrap();
+        assert_eq!(launches.get(), 4);
+
+        // A session whose CLI died is replaced transparently.
+        let mut dying = counted("sleep 0.2").unwrap();
+        assert!(dying.is_alive());
+        pool.checkin("codex", dying);
+        std::thread::sleep(Duration::from_millis(500));
+        let mut fresh = pool.checkout("codex", || counted("sleep 30")).unwrap();
+        assert!(fresh.is_alive());
+        assert_eq!(launches.get(), 6);
+    }
+
+    #[test]
+    fn test_idle_sessions_expire() {
+        let pool = SessionPool::new(4, Duration::from_millis(100));
+        pool.checkin("claude", launch("sleep 30").unwrap());
+        assert_eq!(pool.len(), 1);
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(pool.evict_idle(), 1);
+        assert!(pool.is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::types::Capabilities;
+use crate::{DialogKind, UsageConfig, UsageData};
+
+/// An agent CLI that agentusage can check.
+///
+/// Implement the screen hooks and register the provider on
+/// `UsageConfig::providers` to check a CLI agentusage doesn't know about.
+/// The default `check` launches the CLI in a PTY, waits for `prompt_ready`
+/// (answering dialogs from `detect_dialog` under the approval policy),
+/// types `usage_command`, waits for `usage_ready`, and parses the screen
+/// with `parse`.  The built-in providers override `check` with flows
+/// tuned to their TUIs.  Mock data, locks, rate limits, and the
+/// `UsageData::check` tally apply on top of whichever `check` runs.
+pub trait Provider: Send + Sync {
+    /// Name used in results, errors, and per-provider settings.
+    fn name(&self) -> &str;
+
+    /// Executable launched from PATH.
+    fn binary(&self) -> &str;
+
+    /// Arguments the CLI is launched with under `config`.
+    fn launch_args(&self, _config: &UsageConfig) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether the screen shows the CLI ready for input.
+    fn prompt_ready(&self, screen: &str) -> bool;
+
+    /// What to type at the prompt to show usage.
+    fn usage_command(&self) -> &str;
+
+    /// Whether usage data has rendered, so the screen can be parsed.
+    fn usage_ready(&self, screen: &str) -> bool;
+
+    /// A dialog on screen that has to be answered before continuing.
+    fn detect_dialog(&self, _screen: &str) -> Option<DialogKind> {
+        None
+    }
+
+    /// Parse captured output into usage data.
+    fn parse(&self, output: &str) -> Result<UsageData>;
+
+    /// Which optional fields `parse` can fill in.  `source` is taken from
+    /// each result, so it is ignored here.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Run one check against the CLI.
+    fn check(&self, config: &UsageConfig) -> Result<UsageData> {
+        crate::runner::check_tui(self, config)
+    }
+}
+
+/// The providers a check can run, by name, in the order `run_all` checks
+/// them.
+#[derive(Clone)]
+pub struct ProviderRegistry {
+    providers: Vec<Arc<dyn Provider>>,
+}
+
+impl ProviderRegistry {
+    /// Claude, Codex, Gemini, Copilot, Cursor, Qwen Code, Amazon Q, and
+    /// aider.
+    pub fn builtin() -> Self {
+        Self {
+            providers: crate::runner::builtin_providers(),
+        }
+    }
+
+    /// Add `provider`, replacing one registered under the same name.
+    pub fn register(&mut self, provider: Arc<dyn Provider>) {
+        match self
+            .providers
+            .iter_mut()
+            .find(|p| p.name() == provider.name())
+        {
+            Some(slot) => *slot = provider,
+            None => self.providers.push(provider),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Provider>> {
+        self.providers.iter().find(|p| p.name() == name)
+    }
+
+    /// Registered provider names, in check order.
+    pub fn names(&self) -> Vec<&str> {
+        self.providers.iter().map(|p| p.name()).collect()
+    }
+}
+
+impl Default for ProviderRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CheckStats, Confidence, LimitScope, LimitWindow, PercentKind, UsageEntry};
+
+    /// A "CLI" that is a shell script printing a prompt, then a usage row
+    /// once it reads a line.
+    struct Scripted;
+
+    impl Provider for Scripted {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+        fn binary(&self) -> &str {
+            "sh"
+        }
+        fn launch_args(&self, _config: &UsageConfig) -> Vec<String> {
+            vec![
+                "-c".into(),
+                "printf 'ready> '; read cmd; echo \"$cmd\"; \
+                 echo 'Daily limit: 37% used'; sleep 5"
+                    .into(),
+            ]
+        }
+        fn prompt_ready(&self, screen: &str) -> bool {
+            screen.contains("ready>")
+        }
+        fn usage_command(&self) -> &str {
+            "/usage"
+        }
+        fn usage_ready(&self, screen: &str) -> bool {
+            screen.contains("% used")
+        }
+        fn parse(&self, output: &str) -> Result<UsageData> {
+            let entries = output
+                .lines()
+                .filter_map(|line| line.strip_prefix("Daily limit: "))
+                .filter_map(|rest| rest.strip_suffix("% used")?.trim().parse().ok())
+                .map(|used: u32| UsageEntry {
+                    label: "Daily limit".into(),
+                    percent_used: used,
+                    percent_remaining: 100 - used,
+                    percent_kind: PercentKind::Used,
+                    reset_info: String::new(),
+                    reset_minutes: None,
+                    spent: None,
+                    requests: None,
+                    requests_used: None,
+                    requests_limit: None,
+                    scope: LimitScope::default(),
+                    window: LimitWindow::Daily,
+                    confidence: Confidence::Exact,
+                    inconsistent: false,
+                    as_of: None,
+                })
+                .collect();
+            Ok(UsageData {
+                provider: "scripted".into(),
+                entries,
+                extra_usage_enabled: None,
+                tokens: Vec::new(),
+                check: CheckStats::default(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_registry_replaces_by_name_and_keeps_order() {
+        let mut registry = ProviderRegistry::builtin();
+        assert_eq!(registry.names(), crate::ALL_PROVIDERS);
+        registry.register(Arc::new(Scripted));
+        assert_eq!(registry.names().last(), Some(&"scripted"));
+        registry.register(Arc::new(Scripted));
+        assert_eq!(registry.names().len(), crate::ALL_PROVIDERS.len() + 1);
+        assert_eq!(registry.get("scripted").unwrap().binary(), "sh");
+        assert!(registry.get("bogus").is_none());
+    }
+
+    #[test]
+    fn test_capabilities_come_from_the_registered_provider() {
+        let mut registry = ProviderRegistry::builtin();
+        for name in crate::ALL_PROVIDERS {
+            let data = crate::fixtures::mock_usage(name).unwrap();
+            let resets = data.capabilities(&registry).supports_reset_minutes;
+            for entry in &data.entries {
+                assert_eq!(entry.reset_minutes.is_some(), resets, "{}", name);
+            }
+        }
+        let mut data = crate::fixtures::mock_usage("codex").unwrap();
+        data.provider = "scripted".into();
+        data.check.source = crate::DataSource::Local;
+        assert_eq!(
+            data.capabilities(&registry),
+            Capabilities {
+                source: crate::DataSource::Local,
+                ..Capabilities::default()
+            }
+        );
+        registry.register(Arc::new(Scripted));
+        assert!(!data.capabilities(&registry).supports_spend);
+    }
+
+    #[test]
+    fn test_registered_provider_runs_through_the_default_check() {
+        let mut config = UsageConfig {
+            timeout: 10,
+            poll_interval_ms: 50,
+            ..UsageConfig::default()
+        };
+        config.providers.register(Arc::new(Scripted));
+        let data = crate::run_provider(&config, "scripted").unwrap();
+        assert_eq!(data.provider, "scripted");
+        assert_eq!(data.entries.len(), 1);
+        assert_eq!(data.entries[0].percent_used, 37);
+    }
+}
@@ -1,11 +1,16 @@
+use crate::capture::{content_hash, CaptureBuffer};
+use crate::observer::CheckObserver;
+use crate::registry;
+use crate::types::{AttachMode, WaitStats};
 use anyhow::{bail, Context, Result};
-use std::fs::File;
-use std::io;
-use std::os::fd::{FromRawFd, RawFd};
+use std::ffi::OsStr;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
 use std::os::unix::process::CommandExt;
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime};
 
@@ -16,6 +21,21 @@ static SHUTDOWN: AtomicBool = AtomicBool::new(false);
 
 const MAX_BUFFER_BYTES: usize = 1_000_000;
 
+/// Terminal type the child is told it runs in; the PTY answers the xterm
+/// queries TUIs send on startup.
+const CHILD_TERM: &str = "xterm-256color";
+/// UTF-8 locale built into glibc 2.35+ and musl, so it exists even in
+/// minimal containers that have no other locales generated.
+#[cfg(not(target_os = "macos"))]
+const CHILD_UTF8_LOCALE: &str = "C.UTF-8";
+/// macOS has no `C.UTF-8`; `en_US.UTF-8` ships with every install.
+#[cfg(target_os = "macos")]
+const CHILD_UTF8_LOCALE: &str = "en_US.UTF-8";
+
+/// Initial PTY size.  Wide enough for every provider's usage rows today.
+pub const DEFAULT_COLS: u16 = 200;
+pub const DEFAULT_ROWS: u16 = 50;
+
 /// Terminal queries we respond to, enabling Ink-based TUIs (Gemini) to
 /// complete their initialisation handshake without blocking indefinitely.
 const CURSOR_QUERY: &[u8] = b"\x1b[6n";
@@ -26,17 +46,43 @@ const DA1_RESPONSE: &[u8] = b"\x1b[?1;2c"; // VT100 with AVO
 /// Device Status Report (DSR): `\x1b[5n`
 const DSR_QUERY: &[u8] = b"\x1b[5n";
 const DSR_RESPONSE: &[u8] = b"\x1b[0n"; // terminal OK
-
-fn register_group(pgid: i32) {
+/// OSC 10/11 foreground/background colour queries (BEL or ST terminated).
+/// Answer light-on-dark so theme auto-detection picks a dark palette.
+const OSC10_QUERY: &[u8] = b"\x1b]10;?";
+const OSC10_RESPONSE: &[u8] = b"\x1b]10;rgb:ffff/ffff/ffff\x1b\\";
+const OSC11_QUERY: &[u8] = b"\x1b]11;?";
+const OSC11_RESPONSE: &[u8] = b"\x1b]11;rgb:0000/0000/0000\x1b\\";
+/// XTVERSION: `\x1b[>q` or `\x1b[>0q`, answered with a DCS identifying us.
+const XTVERSION_QUERY: &[u8] = b"\x1b[>q";
+const XTVERSION_QUERY_ZERO: &[u8] = b"\x1b[>0q";
+/// Bracketed paste mode (DECSET 2004) toggles emitted by the child, and the
+/// markers we wrap pasted input in while it is enabled.
+const BRACKETED_PASTE_ON: &[u8] = b"\x1b[?2004h";
+const BRACKETED_PASTE_OFF: &[u8] = b"\x1b[?2004l";
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+/// Literals shorter than this (menu digits, single letters) are typed, not pasted.
+const BRACKETED_PASTE_MIN_LEN: usize = 4;
+/// Ctrl-] hands control back from an interactive takeover (as in telnet).
+pub const DETACH_KEY: u8 = 0x1d;
+
+/// How long a send may wait for the PTY to accept any input before failing.
+const WRITE_STALL_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest DECRQM query we recognise: `\x1b[?` + 5 digits + `$p`.
+const DECRQM_MAX_QUERY_LEN: usize = 10;
+
+fn register_group(pgid: i32, binary: &str) {
     if let Ok(mut groups) = PROCESS_GROUPS.lock() {
         groups.push(pgid);
     }
+    registry::record(pgid, binary);
 }
 
 fn unregister_group(pgid: i32) {
     if let Ok(mut groups) = PROCESS_GROUPS.lock() {
         groups.retain(|g| *g != pgid);
     }
+    registry::forget(pgid);
 }
 
 fn kill_group(pgid: i32, signal: i32) {
@@ -60,6 +106,7 @@ pub fn kill_registered_sessions() {
 
     for pgid in &groups {
         kill_group(*pgid, libc::SIGKILL);
+        registry::forget(*pgid);
     }
 }
 
@@ -73,6 +120,163 @@ pub fn clear_shutdown() {
     SHUTDOWN.store(false, Ordering::SeqCst);
 }
 
+/// Puts a terminal in raw mode and restores the previous settings on drop.
+struct RawModeGuard {
+    fd: RawFd,
+    original: libc::termios,
+}
+
+impl RawModeGuard {
+    fn enable(fd: RawFd) -> Result<Self> {
+        // SAFETY: termios is plain data; tcgetattr fills it for a valid tty FD.
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        // SAFETY: `fd` is an open terminal and `original` is writable.
+        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+            bail!("tcgetattr failed: {}", io::Error::last_os_error());
+        }
+        let mut raw = original;
+        // SAFETY: cfmakeraw only edits the struct it is given.
+        unsafe { libc::cfmakeraw(&mut raw) };
+        // SAFETY: applying settings derived from the terminal's own.
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            bail!("tcsetattr failed: {}", io::Error::last_os_error());
+        }
+        Ok(Self { fd, original })
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        // SAFETY: restoring the settings captured in `enable` on the same FD.
+        let _ = unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &self.original) };
+    }
+}
+
+/// A user terminal mirroring a session (`--attach`).  In interactive mode a
+/// background thread forwards keystrokes to the PTY until Ctrl-] or drop.
+struct Attachment {
+    tty: File,
+    stop: Arc<AtomicBool>,
+    input: Option<thread::JoinHandle<()>>,
+    raw: Option<RawModeGuard>,
+}
+
+impl Attachment {
+    fn open(mode: AttachMode, master_fd: RawFd) -> Result<Self> {
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .context("--attach needs a terminal")?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut attachment = Self {
+            tty,
+            stop: stop.clone(),
+            input: None,
+            raw: None,
+        };
+        if mode == AttachMode::Interactive {
+            let tty_fd = attachment.tty.as_raw_fd();
+            attachment.raw = Some(RawModeGuard::enable(tty_fd)?);
+            // A private copy of the master FD, so session cleanup closing the
+            // original can't race a write from this thread.
+            // SAFETY: dup of a valid FD; the thread closes its copy.
+            let master = unsafe { libc::dup(master_fd) };
+            if master < 0 {
+                bail!("dup failed: {}", io::Error::last_os_error());
+            }
+            attachment.input = Some(thread::spawn(move || {
+                forward_keystrokes(tty_fd, master, &stop);
+                // SAFETY: closing the FD dup'ed above, exactly once.
+                let _ = unsafe { libc::close(master) };
+            }));
+        }
+        Ok(attachment)
+    }
+
+    fn mirror(&mut self, chunk: &[u8]) {
+        let _ = self.tty.write_all(chunk);
+        let _ = self.tty.flush();
+    }
+}
+
+impl Drop for Attachment {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.input.take() {
+            let _ = handle.join();
+        }
+        // Restore the terminal only once the input thread is done with it.
+        self.raw.take();
+        let _ = self.tty.write_all(b"\r\n");
+    }
+}
+
+/// Copy keystrokes from the user's terminal to the PTY until `stop` is set
+/// or the user presses Ctrl-].
+fn forward_keystrokes(tty_fd: RawFd, master_fd: RawFd, stop: &AtomicBool) {
+    while !stop.load(Ordering::Relaxed) {
+        let mut fds = [libc::pollfd {
+            fd: tty_fd,
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+        // SAFETY: polling one valid FD.
+        let rc = unsafe { libc::poll(fds.as_mut_ptr(), 1, 100) };
+        if rc <= 0 || fds[0].revents & libc::POLLIN == 0 {
+            continue;
+        }
+        let mut buf = [0u8; 1024];
+        // SAFETY: read from the tty FD into a stack buffer.
+        let n = unsafe { libc::read(tty_fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n <= 0 {
+            return;
+        }
+        let input = &buf[..n as usize];
+        let (input, detach) = match input.iter().position(|b| *b == DETACH_KEY) {
+            Some(pos) => (&input[..pos], true),
+            None => (input, false),
+        };
+        let mut offset = 0;
+        while offset < input.len() {
+            // SAFETY: writing a byte slice to our private master FD copy.
+            let written = unsafe {
+                libc::write(
+                    master_fd,
+                    input[offset..].as_ptr() as *const libc::c_void,
+                    input.len() - offset,
+                )
+            };
+            if written <= 0 {
+                break;
+            }
+            offset += written as usize;
+        }
+        if detach {
+            return;
+        }
+    }
+}
+
+/// Size of the terminal behind `fd`, if it is one.
+fn terminal_size(fd: RawFd) -> Option<(u16, u16)> {
+    let mut win = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ writes a winsize into `win`.
+    let rc = unsafe {
+        libc::ioctl(
+            fd,
+            libc::TIOCGWINSZ as libc::c_ulong,
+            std::ptr::addr_of_mut!(win),
+        )
+    };
+    (rc == 0 && win.ws_col > 0 && win.ws_row > 0).then_some((win.ws_col, win.ws_row))
+}
+
 fn map_special_key(keys: &str) -> &str {
     match keys {
         "Enter" => "\r",
@@ -82,10 +286,103 @@ fn map_special_key(keys: &str) -> &str {
         "Down" => "\u{1b}[B",
         "Right" => "\u{1b}[C",
         "Left" => "\u{1b}[D",
+        "PageDown" => "\u{1b}[6~",
         _ => keys,
     }
 }
 
+fn xtversion_response() -> Vec<u8> {
+    format!("\x1bP>|agentusage({})\x1b\\", env!("CARGO_PKG_VERSION")).into_bytes()
+}
+
+/// DECRQM reply reporting `mode` as not recognised (`Ps = 0`), which tells
+/// the TUI to fall back to its conservative rendering path.
+fn decrqm_response(mode: u32) -> Vec<u8> {
+    format!("\x1b[?{};0$y", mode).into_bytes()
+}
+
+/// Scan for DECRQM private mode queries (`\x1b[?<mode>$p`) in the combined
+/// tail+chunk stream.  Returns the queried modes in order; the tail keeps
+/// any trailing partial query for cross-chunk detection.
+fn detect_decrqm_in_stream(tail: &mut Vec<u8>, chunk: &[u8]) -> Vec<u32> {
+    let mut combined = Vec::with_capacity(tail.len() + chunk.len());
+    combined.extend_from_slice(tail);
+    combined.extend_from_slice(chunk);
+
+    let mut modes = Vec::new();
+    let mut last_end = 0usize;
+    let mut i = 0usize;
+    while i + 2 < combined.len() {
+        if combined[i..].starts_with(b"\x1b[?") {
+            let digits_start = i + 3;
+            let digits_len = combined[digits_start..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            let end = digits_start + digits_len;
+            if digits_len > 0 && combined[end..].starts_with(b"$p") {
+                let mode = std::str::from_utf8(&combined[digits_start..end])
+                    .ok()
+                    .and_then(|d| d.parse().ok());
+                if let Some(mode) = mode {
+                    modes.push(mode);
+                }
+                last_end = end + 2;
+                i = last_end;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    let keep_from = last_end.max(
+        combined
+            .len()
+            .saturating_sub(DECRQM_MAX_QUERY_LEN.saturating_sub(1)),
+    );
+    tail.clear();
+    tail.extend_from_slice(&combined[keep_from..]);
+
+    modes
+}
+
+/// Track a DECSET/DECRST toggle across chunks.  Returns the state implied
+/// by the last `on`/`off` sequence seen, or None if neither appeared.
+fn detect_mode_toggle_in_stream(
+    tail: &mut Vec<u8>,
+    chunk: &[u8],
+    on: &[u8],
+    off: &[u8],
+) -> Option<bool> {
+    let mut combined = Vec::with_capacity(tail.len() + chunk.len());
+    combined.extend_from_slice(tail);
+    combined.extend_from_slice(chunk);
+
+    let last_on = combined.windows(on.len()).rposition(|w| w == on);
+    let last_off = combined.windows(off.len()).rposition(|w| w == off);
+    let state = match (last_on, last_off) {
+        (Some(a), Some(b)) => Some(a > b),
+        (Some(_), None) => Some(true),
+        (None, Some(_)) => Some(false),
+        (None, None) => None,
+    };
+
+    let tail_len = on.len().max(off.len()).saturating_sub(1);
+    let keep_from = combined.len().saturating_sub(tail_len);
+    tail.clear();
+    tail.extend_from_slice(&combined[keep_from..]);
+
+    state
+}
+
+fn bracketed_paste_payload(keys: &str) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(PASTE_START.len() + keys.len() + PASTE_END.len());
+    payload.extend_from_slice(PASTE_START);
+    payload.extend_from_slice(keys.as_bytes());
+    payload.extend_from_slice(PASTE_END);
+    payload
+}
+
 /// Scan for `query` in the combined tail+chunk stream, updating the tail
 /// buffer for cross-chunk detection.  Returns true if the query was found.
 fn detect_query_in_stream(tail: &mut Vec<u8>, chunk: &[u8], query: &[u8]) -> bool {
@@ -113,20 +410,51 @@ pub struct PtySession {
     master_fd: RawFd,
     child: Child,
     process_group: Option<i32>,
-    buffer: Vec<u8>,
+    capture: CaptureBuffer,
     cursor_query_tail: Vec<u8>,
     da1_query_tail: Vec<u8>,
     dsr_query_tail: Vec<u8>,
+    osc10_query_tail: Vec<u8>,
+    osc11_query_tail: Vec<u8>,
+    xtversion_query_tail: Vec<u8>,
+    xtversion_zero_query_tail: Vec<u8>,
+    decrqm_query_tail: Vec<u8>,
+    bracketed_paste_tail: Vec<u8>,
+    /// Whether the child has enabled bracketed paste (DECSET 2004).
+    bracketed_paste: bool,
+    /// Overall budget; captures fail once it passes so every wait loop unwinds.
+    deadline: Option<Instant>,
+    /// User terminal mirroring this session (`--attach`).
+    attachment: Option<Attachment>,
+    /// Input not yet accepted by the PTY.
+    write_queue: Vec<u8>,
+    /// Set while `write_all_to_master` drains the queue, so terminal-query
+    /// answers produced by its reads join the queue instead of recursing.
+    flushing: bool,
     cleaned_up: bool,
+    /// The provider this session checks and who hears about its waits
+    /// (see `observe`).
+    provider: String,
+    observers: Vec<Arc<dyn CheckObserver>>,
 }
 
 impl PtySession {
     pub fn new(directory: Option<&str>, binary: &str, args: &[&str]) -> Result<Self> {
+        Self::new_with_env(directory, binary, args, &[])
+    }
+
+    /// Like `new`, with extra environment variables for the child.
+    pub fn new_with_env(
+        directory: Option<&str>,
+        binary: &str,
+        args: &[&str],
+        env: &[(&str, &OsStr)],
+    ) -> Result<Self> {
         let mut master_fd: libc::c_int = -1;
         let mut slave_fd: libc::c_int = -1;
         let mut win = libc::winsize {
-            ws_row: 50,
-            ws_col: 200,
+            ws_row: DEFAULT_ROWS,
+            ws_col: DEFAULT_COLS,
             ws_xpixel: 0,
             ws_ypixel: 0,
         };
@@ -194,18 +522,11 @@ impl PtySession {
             cmd.current_dir(dir);
             cmd.env("PWD", dir);
         }
-        if std::env::var_os("TERM").is_none() {
-            cmd.env("TERM", "xterm-256color");
-        }
-        if std::env::var_os("COLORTERM").is_none() {
-            cmd.env("COLORTERM", "truecolor");
-        }
-        if std::env::var_os("LANG").is_none() {
-            cmd.env("LANG", "en_US.UTF-8");
-        }
+        cmd.envs(terminal_env(|name| std::env::var(name).ok()));
         if std::env::var_os("CI").is_none() {
             cmd.env("CI", "0");
         }
+        cmd.envs(env.iter().copied());
         let preexec_slave_fd = slave_fd;
         // Make the child a session leader with the slave PTY as controlling terminal.
         // This matches how interactive TUIs expect to be launched.
@@ -248,7 +569,7 @@ impl PtySession {
             }
         }
         if let Some(pgid) = process_group {
-            register_group(pgid);
+            register_group(pgid, binary);
         }
 
         let nanos = SystemTime::now()
@@ -262,31 +583,268 @@ impl PtySession {
             master_fd,
             child,
             process_group,
-            buffer: Vec::with_capacity(64 * 1024),
+            capture: CaptureBuffer::new(MAX_BUFFER_BYTES),
             cursor_query_tail: Vec::new(),
             da1_query_tail: Vec::new(),
             dsr_query_tail: Vec::new(),
+            osc10_query_tail: Vec::new(),
+            osc11_query_tail: Vec::new(),
+            xtversion_query_tail: Vec::new(),
+            xtversion_zero_query_tail: Vec::new(),
+            decrqm_query_tail: Vec::new(),
+            bracketed_paste_tail: Vec::new(),
+            bracketed_paste: false,
+            deadline: None,
+            attachment: None,
+            write_queue: Vec::new(),
+            flushing: false,
             cleaned_up: false,
+            provider: binary.to_string(),
+            observers: Vec::new(),
         })
     }
 
-    pub fn send_keys(&self, keys: &str) -> Result<()> {
+    pub fn send_keys(&mut self, keys: &str) -> Result<()> {
         self.write_all_to_master(map_special_key(keys).as_bytes())
     }
 
-    pub fn send_keys_literal(&self, keys: &str) -> Result<()> {
-        self.write_all_to_master(keys.as_bytes())
+    /// Type `keys` literally.  When the TUI has enabled bracketed paste,
+    /// longer literals are sent as a single paste so autocomplete menus
+    /// can't intercept or drop characters mid-command.
+    pub fn send_keys_literal(&mut self, keys: &str) -> Result<()> {
+        if self.bracketed_paste && keys.chars().count() >= BRACKETED_PASTE_MIN_LEN {
+            self.write_all_to_master(&bracketed_paste_payload(keys))
+        } else {
+            self.write_all_to_master(keys.as_bytes())
+        }
+    }
+
+    /// Resize the PTY and notify the child with SIGWINCH so it re-lays out.
+    pub fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
+        if self.master_fd < 0 {
+            bail!("PTY is not available");
+        }
+
+        let win = libc::winsize {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // SAFETY: TIOCSWINSZ on a valid master FD with a fully initialized winsize.
+        let rc = unsafe {
+            libc::ioctl(
+                self.master_fd,
+                libc::TIOCSWINSZ as libc::c_ulong,
+                std::ptr::addr_of!(win),
+            )
+        };
+        if rc != 0 {
+            let err = std::io::Error::last_os_error();
+            bail!("ioctl(TIOCSWINSZ) failed: {}", err);
+        }
+
+        // The kernel signals the foreground group on a size change, but be
+        // explicit so children that were not in the foreground still notice.
+        if let Some(pgid) = self.process_group {
+            kill_group(pgid, libc::SIGWINCH);
+        } else {
+            // SAFETY: signal child PID directly as fallback.
+            let _ = unsafe { libc::kill(self.child.id() as i32, libc::SIGWINCH) };
+        }
+
+        Ok(())
+    }
+
+    /// Mirror everything the child prints to the user's terminal from now on,
+    /// forwarding their keystrokes too in interactive mode.
+    pub fn attach(&mut self, mode: AttachMode) -> Result<()> {
+        self.attachment = Some(Attachment::open(mode, self.master_fd)?);
+        Ok(())
+    }
+
+    /// Report this session's `verbose` waits to `observers`, as `provider`'s.
+    pub fn observe(&mut self, provider: &str, observers: Vec<Arc<dyn CheckObserver>>) {
+        self.provider = provider.to_string();
+        self.observers = observers;
+    }
+
+    /// Abandon the session (every later capture fails) once `deadline` passes.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Everything captured so far, without reading more output or
+    /// checking the deadline (so it still works after a timeout).
+    pub fn captured_text(&mut self) -> String {
+        self.capture.text()
     }
 
     pub fn capture_pane(&mut self) -> Result<String> {
+        self.read_before_deadline()?;
+        Ok(self.capture.text())
+    }
+
+    /// Read pending output and return a mark for `capture_since`.
+    pub fn output_mark(&mut self) -> Result<u64> {
+        self.read_before_deadline()?;
+        Ok(self.capture.mark())
+    }
+
+    /// Output captured after `mark`, however much of the buffer was
+    /// trimmed in between.
+    pub fn capture_since(&mut self, mark: u64) -> Result<String> {
+        self.read_before_deadline()?;
+        Ok(self.capture.text_since(mark))
+    }
+
+    fn read_before_deadline(&mut self) -> Result<()> {
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            bail!("[timeout] Total time budget exceeded");
+        }
         self.read_available();
-        let stripped = strip_ansi_escapes::strip(&self.buffer);
-        Ok(String::from_utf8_lossy(&stripped).to_string())
+        Ok(())
+    }
+
+    /// Attach the user's terminal (`/dev/tty`) to the session so they can
+    /// answer a screen we can't, until they press Ctrl-] or the child exits.
+    /// `banner` is shown first.  The PTY takes the user's terminal size for
+    /// the duration and everything the child prints is still captured.
+    pub fn interact(&mut self, banner: &str) -> Result<()> {
+        let mut tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .context("Interactive takeover needs a terminal")?;
+        let tty_fd = tty.as_raw_fd();
+
+        write!(tty, "\r\n{}\r\n", banner)?;
+        tty.flush()?;
+        let _raw = RawModeGuard::enable(tty_fd)?;
+        let (cols, rows) = terminal_size(tty_fd).unwrap_or((DEFAULT_COLS, DEFAULT_ROWS));
+        self.resize(cols, rows)?;
+
+        // The takeover owns the terminal; pause any --attach mirror meanwhile.
+        let attachment = self.attachment.take();
+        let result = self.forward_terminal(&mut tty);
+        self.attachment = attachment;
+
+        let _ = self.resize(DEFAULT_COLS, DEFAULT_ROWS);
+        let _ = write!(tty, "\r\n");
+        result
+    }
+
+    fn forward_terminal(&mut self, tty: &mut File) -> Result<()> {
+        let tty_fd = tty.as_raw_fd();
+        loop {
+            if SHUTDOWN.load(Ordering::Relaxed) {
+                bail!("[timeout] Interrupted by shutdown signal");
+            }
+            if self.deadline.is_some_and(|d| Instant::now() >= d) {
+                bail!("[timeout] Total time budget exceeded");
+            }
+
+            let mut fds = [
+                libc::pollfd {
+                    fd: tty_fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: self.master_fd,
+                    events: if self.write_queue.is_empty() {
+                        libc::POLLIN
+                    } else {
+                        libc::POLLIN | libc::POLLOUT
+                    },
+                    revents: 0,
+                },
+            ];
+            // SAFETY: polling two valid FDs with a stack array of pollfd.
+            let rc = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, 100) };
+            if rc < 0 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                bail!("poll failed: {}", err);
+            }
+
+            if fds[1].revents & libc::POLLOUT != 0 {
+                self.write_ready()?;
+            }
+            if fds[1].revents & !libc::POLLOUT != 0 {
+                let mut output = Vec::new();
+                self.read_chunks(false, |chunk| output.extend_from_slice(chunk));
+                tty.write_all(&output)?;
+                tty.flush()?;
+            }
+
+            if fds[0].revents & libc::POLLIN != 0 {
+                let mut buf = [0u8; 1024];
+                // SAFETY: read from the tty FD into a stack buffer.
+                let n =
+                    unsafe { libc::read(tty_fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+                if n <= 0 {
+                    return Ok(());
+                }
+                let input = &buf[..n as usize];
+                if let Some(pos) = input.iter().position(|b| *b == DETACH_KEY) {
+                    self.write_all_to_master(&input[..pos])?;
+                    return Ok(());
+                }
+                // Queued rather than waited on, so a child that is slow to
+                // read doesn't freeze the takeover's output.
+                self.write_queue.extend_from_slice(input);
+                self.write_ready()?;
+            }
+
+            if matches!(self.child.try_wait(), Ok(Some(_))) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Whether the child is still running.
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
     }
 
-    /// Poll capture_pane until matcher returns true or timeout.
+    /// Block until the child writes output or `timeout` elapses, without
+    /// reading it.  Returns true when output is waiting.  After the child
+    /// hangs up this just sleeps, so callers' loops keep their pace.
+    pub fn wait_for_output(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let mut fds = [libc::pollfd {
+                fd: self.master_fd,
+                events: libc::POLLIN,
+                revents: 0,
+            }];
+            // Round up so sub-millisecond waits still block rather than spin.
+            let ms = remaining.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+            // SAFETY: polling one valid FD with a stack array of pollfd.
+            let rc = unsafe { libc::poll(fds.as_mut_ptr(), 1, ms) };
+            if rc < 0 {
+                if io::Error::last_os_error().raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                thread::sleep(remaining);
+                return false;
+            }
+            if fds[0].revents & (libc::POLLHUP | libc::POLLERR) != 0 {
+                thread::sleep(remaining);
+                return false;
+            }
+            return fds[0].revents & libc::POLLIN != 0;
+        }
+    }
+
+    /// Poll capture_pane until matcher returns true or timeout, waking as
+    /// soon as the child writes and otherwise every `interval`.
     /// If `stabilize` is true, requires BOTH the matcher to match AND content to be
-    /// stable for 3 consecutive polls before returning success.
+    /// unchanged for 3 intervals before returning success.
     pub fn wait_for<F: Fn(&str) -> bool>(
         &mut self,
         matcher: F,
@@ -297,8 +855,26 @@ impl PtySession {
     ) -> Result<String> {
         let start = Instant::now();
         let mut last_content = String::new();
-        let mut stable_count = 0;
+        let mut last_hash = None;
+        let mut unchanged_since = start;
         let mut matcher_matched = false;
+        let mut matched_now = false;
+        let (mut polls, mut parses) = (0u32, 0u32);
+        let report = |session: &Self, hash: Option<u64>, polls, parses, outcome| {
+            if !verbose {
+                return;
+            }
+            let wait = WaitStats {
+                outcome,
+                elapsed: start.elapsed(),
+                polls,
+                parses,
+                capture_hash: hash.unwrap_or_default(),
+            };
+            for observer in &session.observers {
+                observer.on_wait(&session.provider, &wait);
+            }
+        };
 
         loop {
             if SHUTDOWN.load(Ordering::Relaxed) {
@@ -306,11 +882,9 @@ impl PtySession {
             }
 
             if start.elapsed() > timeout {
+                report(self, last_hash, polls, parses, "timed out");
                 if verbose {
-                    eprintln!(
-                        "[verbose] Timeout. Last captured content:\n{}",
-                        last_content
-                    );
+                    self.report_capture("timeout", &last_content);
                 }
                 bail!(
                     "[timeout] Timed out after {:.0}s waiting for expected content",
@@ -319,22 +893,34 @@ impl PtySession {
             }
 
             let content = self.capture_pane()?;
-
-            if matcher(&content) {
+            let hash = content_hash(&content);
+            let changed = last_hash != Some(hash);
+            last_hash = Some(hash);
+            polls += 1;
+
+            // Most polls see the same screen; only re-run the matcher on change.
+            if changed {
+                parses += 1;
+                matched_now = matcher(&content);
+            }
+            if matched_now {
                 if !stabilize {
+                    report(self, last_hash, polls, parses, "matched");
                     return Ok(content);
                 }
                 matcher_matched = true;
             }
 
             if stabilize {
-                if content == last_content && !content.trim().is_empty() {
-                    stable_count += 1;
-                    if stable_count >= 3 && matcher_matched {
+                // Measured in time rather than polls: output that doesn't
+                // change the screen (cursor blinks) wakes the loop early.
+                if !changed && !content.trim().is_empty() {
+                    if unchanged_since.elapsed() >= interval * 3 && matcher_matched {
+                        report(self, last_hash, polls, parses, "stable");
                         return Ok(content);
                     }
                 } else {
-                    stable_count = 0;
+                    unchanged_since = Instant::now();
                 }
             }
 
@@ -351,7 +937,7 @@ impl PtySession {
                         exit_content
                     };
                     if verbose && !tail.trim().is_empty() {
-                        eprintln!("[verbose] Process exited. Captured output:\n{}", tail);
+                        self.report_capture("exit", &tail);
                     }
                     bail!(
                         "[timeout] Process exited before expected content (status: {}){}",
@@ -367,7 +953,14 @@ impl PtySession {
             }
 
             last_content = content;
-            thread::sleep(interval);
+            self.wait_for_output(interval.min(timeout.saturating_sub(start.elapsed())));
+        }
+    }
+
+    /// Show `text`, the screen at `label`, to the observers.
+    fn report_capture(&self, label: &str, text: &str) {
+        for observer in &self.observers {
+            observer.on_capture(&self.provider, label, text);
         }
     }
 
@@ -383,6 +976,13 @@ impl PtySession {
     }
 
     fn read_available(&mut self) {
+        self.read_chunks(true, |_| {});
+    }
+
+    /// Drain pending PTY output into the capture buffer, passing each chunk
+    /// to `on_chunk`.  Terminal queries are answered only when `respond` is
+    /// set; during a takeover the user's real terminal answers them.
+    fn read_chunks<F: FnMut(&[u8])>(&mut self, respond: bool, mut on_chunk: F) {
         loop {
             let mut tmp = [0u8; 8192];
             // SAFETY: read from valid master PTY FD into stack buffer.
@@ -395,9 +995,15 @@ impl PtySession {
             };
             if n > 0 {
                 let chunk = &tmp[..n as usize];
-                self.respond_to_terminal_queries(chunk);
-                self.buffer.extend_from_slice(chunk);
-                self.trim_buffer();
+                if respond {
+                    self.respond_to_terminal_queries(chunk);
+                }
+                self.track_terminal_modes(chunk);
+                self.capture.push(chunk);
+                if let Some(attachment) = &mut self.attachment {
+                    attachment.mirror(chunk);
+                }
+                on_chunk(chunk);
                 continue;
             }
             if n == 0 {
@@ -412,10 +1018,14 @@ impl PtySession {
         }
     }
 
-    fn trim_buffer(&mut self) {
-        if self.buffer.len() > MAX_BUFFER_BYTES {
-            let drop_len = self.buffer.len() - MAX_BUFFER_BYTES;
-            self.buffer.drain(..drop_len);
+    fn track_terminal_modes(&mut self, chunk: &[u8]) {
+        if let Some(enabled) = detect_mode_toggle_in_stream(
+            &mut self.bracketed_paste_tail,
+            chunk,
+            BRACKETED_PASTE_ON,
+            BRACKETED_PASTE_OFF,
+        ) {
+            self.bracketed_paste = enabled;
         }
     }
 
@@ -429,49 +1039,115 @@ impl PtySession {
         if detect_query_in_stream(&mut self.dsr_query_tail, chunk, DSR_QUERY) {
             let _ = self.write_all_to_master(DSR_RESPONSE);
         }
+        if detect_query_in_stream(&mut self.osc10_query_tail, chunk, OSC10_QUERY) {
+            let _ = self.write_all_to_master(OSC10_RESPONSE);
+        }
+        if detect_query_in_stream(&mut self.osc11_query_tail, chunk, OSC11_QUERY) {
+            let _ = self.write_all_to_master(OSC11_RESPONSE);
+        }
+        let xtversion =
+            detect_query_in_stream(&mut self.xtversion_query_tail, chunk, XTVERSION_QUERY);
+        let xtversion_zero = detect_query_in_stream(
+            &mut self.xtversion_zero_query_tail,
+            chunk,
+            XTVERSION_QUERY_ZERO,
+        );
+        if xtversion || xtversion_zero {
+            let _ = self.write_all_to_master(&xtversion_response());
+        }
+        for mode in detect_decrqm_in_stream(&mut self.decrqm_query_tail, chunk) {
+            let _ = self.write_all_to_master(&decrqm_response(mode));
+        }
     }
 
-    fn write_all_to_master(&self, data: &[u8]) -> Result<()> {
-        if self.master_fd < 0 {
-            bail!("PTY is not available");
+    /// Queue `data` for the child and write out the whole queue, waiting in
+    /// poll(2) while the PTY is full.  Output is drained meanwhile, so a
+    /// child blocked on printing can get back to reading its input.
+    fn write_all_to_master(&mut self, data: &[u8]) -> Result<()> {
+        self.write_queue.extend_from_slice(data);
+        if self.flushing {
+            // A terminal-query answer from a read inside the flush below;
+            // that flush sends it.
+            return Ok(());
+        }
+        self.flushing = true;
+        let result = self.flush_writes();
+        self.flushing = false;
+        if result.is_err() {
+            self.write_queue.clear();
         }
+        result
+    }
 
-        let mut offset = 0usize;
-        let mut retries = 0u32;
+    fn flush_writes(&mut self) -> Result<()> {
+        let mut last_progress = Instant::now();
+        while !self.write_queue.is_empty() {
+            if self.write_ready()? > 0 {
+                last_progress = Instant::now();
+                continue;
+            }
+            if self.deadline.is_some_and(|d| Instant::now() >= d) {
+                bail!("[timeout] Total time budget exceeded");
+            }
+            if last_progress.elapsed() >= WRITE_STALL_TIMEOUT {
+                bail!(
+                    "[timeout] The PTY accepted no input for {}s",
+                    WRITE_STALL_TIMEOUT.as_secs()
+                );
+            }
+            let mut fds = [libc::pollfd {
+                fd: self.master_fd,
+                events: libc::POLLIN | libc::POLLOUT,
+                revents: 0,
+            }];
+            // SAFETY: polling one valid FD with a stack pollfd.
+            let rc = unsafe { libc::poll(fds.as_mut_ptr(), 1, 50) };
+            if rc < 0 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                bail!("poll failed: {}", err);
+            }
+            if fds[0].revents & libc::POLLIN != 0 {
+                self.read_available();
+            }
+        }
+        Ok(())
+    }
 
-        while offset < data.len() {
+    /// Write as much of the queue as the PTY takes without blocking, and
+    /// return how many bytes that was.
+    fn write_ready(&mut self) -> Result<usize> {
+        if self.master_fd < 0 {
+            bail!("PTY is not available");
+        }
+        let mut total = 0;
+        while !self.write_queue.is_empty() {
             // SAFETY: writing byte slice to valid PTY master FD.
             let written = unsafe {
                 libc::write(
                     self.master_fd,
-                    data[offset..].as_ptr() as *const libc::c_void,
-                    data.len() - offset,
+                    self.write_queue.as_ptr() as *const libc::c_void,
+                    self.write_queue.len(),
                 )
             };
             if written > 0 {
-                offset += written as usize;
-                retries = 0;
+                self.write_queue.drain(..written as usize);
+                total += written as usize;
                 continue;
             }
             if written == 0 {
                 break;
             }
-
-            let err = std::io::Error::last_os_error();
+            let err = io::Error::last_os_error();
             match err.raw_os_error() {
                 Some(libc::EINTR) => continue,
-                Some(code) if code == libc::EAGAIN || code == libc::EWOULDBLOCK => {
-                    retries += 1;
-                    if retries > 200 {
-                        bail!("write to PTY would block");
-                    }
-                    thread::sleep(Duration::from_millis(5));
-                }
+                Some(code) if code == libc::EAGAIN || code == libc::EWOULDBLOCK => break,
                 _ => bail!("write to PTY failed: {}", err),
             }
         }
-
-        Ok(())
+        Ok(total)
     }
 
     fn cleanup(&mut self) {
@@ -479,6 +1155,7 @@ impl PtySession {
             return;
         }
         self.cleaned_up = true;
+        self.attachment = None;
 
         let _ = self.send_keys_literal("/exit\n");
 
@@ -529,10 +1206,100 @@ impl Drop for PtySession {
     }
 }
 
+/// Environment overrides for a child whose output we parse.  The child's
+/// terminal is our PTY, not the user's, so a `TERM=dumb` or ASCII-only
+/// locale inherited from a minimal container or CI runner would only make
+/// the TUI drop the `❯`/`│`/`─` drawing the prompt and parser heuristics
+/// look for (or refuse to start).  Such values are replaced with a capable
+/// terminal and a UTF-8 character type; everything else is inherited.
+fn terminal_env(get: impl Fn(&str) -> Option<String>) -> Vec<(&'static str, &'static str)> {
+    let set = |name: &str| get(name).filter(|v| !v.is_empty());
+    let is_utf8 = |value: &str| {
+        let lower = value.to_lowercase();
+        lower.contains("utf-8") || lower.contains("utf8")
+    };
+    let mut vars = Vec::new();
+    if set("TERM").is_none_or(|term| term == "dumb" || term == "unknown") {
+        vars.push(("TERM", CHILD_TERM));
+    }
+    if set("COLORTERM").is_none() {
+        vars.push(("COLORTERM", "truecolor"));
+    }
+    // LC_ALL overrides LC_CTYPE, which overrides LANG.
+    match set("LC_ALL") {
+        Some(all) if !is_utf8(&all) => vars.push(("LC_ALL", CHILD_UTF8_LOCALE)),
+        Some(_) => {}
+        None => {
+            if set("LANG").is_none() {
+                vars.push(("LANG", CHILD_UTF8_LOCALE));
+            }
+            if set("LC_CTYPE")
+                .or_else(|| set("LANG"))
+                .is_some_and(|ctype| !is_utf8(&ctype))
+            {
+                vars.push(("LC_CTYPE", CHILD_UTF8_LOCALE));
+            }
+        }
+    }
+    vars
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn terminal_env_from(pairs: &[(&str, &str)]) -> Vec<(&'static str, &'static str)> {
+        terminal_env(|name| {
+            pairs
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_terminal_env_keeps_a_capable_terminal() {
+        let vars = terminal_env_from(&[
+            ("TERM", "screen-256color"),
+            ("COLORTERM", "truecolor"),
+            ("LANG", "de_DE.UTF-8"),
+        ]);
+        assert!(vars.is_empty());
+    }
+
+    #[test]
+    fn test_terminal_env_replaces_dumb_terminal_and_ascii_locale() {
+        let vars = terminal_env_from(&[("TERM", "dumb"), ("COLORTERM", "1"), ("LANG", "C")]);
+        assert_eq!(
+            vars,
+            [("TERM", CHILD_TERM), ("LC_CTYPE", CHILD_UTF8_LOCALE)]
+        );
+
+        let vars = terminal_env_from(&[("TERM", "xterm"), ("COLORTERM", "1"), ("LC_ALL", "POSIX")]);
+        assert_eq!(vars, [("LC_ALL", CHILD_UTF8_LOCALE)]);
+
+        // A UTF-8 LC_CTYPE is enough even under a non-UTF-8 LANG.
+        let vars = terminal_env_from(&[
+            ("TERM", "xterm"),
+            ("COLORTERM", "1"),
+            ("LANG", "C"),
+            ("LC_CTYPE", "en_US.utf8"),
+        ]);
+        assert!(vars.is_empty());
+    }
+
+    #[test]
+    fn test_terminal_env_fills_in_a_bare_environment() {
+        assert_eq!(
+            terminal_env_from(&[]),
+            [
+                ("TERM", CHILD_TERM),
+                ("COLORTERM", "truecolor"),
+                ("LANG", CHILD_UTF8_LOCALE)
+            ]
+        );
+    }
+
     struct ShutdownGuard;
 
     impl Drop for ShutdownGuard {
@@ -593,6 +1360,127 @@ mod tests {
         assert!(found);
     }
 
+    #[test]
+    fn test_detect_osc_color_queries() {
+        let mut tail = Vec::new();
+        assert!(detect_query_in_stream(
+            &mut tail,
+            b"\x1b]10;?\x07",
+            OSC10_QUERY
+        ));
+        let mut tail = Vec::new();
+        assert!(detect_query_in_stream(
+            &mut tail,
+            b"\x1b]11;?\x1b\\",
+            OSC11_QUERY
+        ));
+    }
+
+    #[test]
+    fn test_detect_xtversion_variants() {
+        let mut tail = Vec::new();
+        assert!(detect_query_in_stream(
+            &mut tail,
+            b"\x1b[>q",
+            XTVERSION_QUERY
+        ));
+        let mut tail = Vec::new();
+        assert!(detect_query_in_stream(
+            &mut tail,
+            b"\x1b[>0q",
+            XTVERSION_QUERY_ZERO
+        ));
+        // DA2 (`\x1b[>c`) is not XTVERSION
+        let mut tail = Vec::new();
+        assert!(!detect_query_in_stream(
+            &mut tail,
+            b"\x1b[>c",
+            XTVERSION_QUERY
+        ));
+    }
+
+    #[test]
+    fn test_xtversion_response_is_dcs() {
+        let response = xtversion_response();
+        assert!(response.starts_with(b"\x1bP>|agentusage("));
+        assert!(response.ends_with(b"\x1b\\"));
+    }
+
+    #[test]
+    fn test_detect_decrqm_single_chunk() {
+        let mut tail = Vec::new();
+        let modes = detect_decrqm_in_stream(&mut tail, b"a\x1b[?2026$pb\x1b[?2004$p");
+        assert_eq!(modes, vec![2026, 2004]);
+        assert!(tail.is_empty());
+    }
+
+    #[test]
+    fn test_detect_decrqm_split_across_chunks() {
+        let mut tail = Vec::new();
+        assert!(detect_decrqm_in_stream(&mut tail, b"hello\x1b[?20").is_empty());
+        assert_eq!(detect_decrqm_in_stream(&mut tail, b"26$pworld"), vec![2026]);
+        // The answered query must not be reported again.
+        assert!(detect_decrqm_in_stream(&mut tail, b"more").is_empty());
+    }
+
+    #[test]
+    fn test_detect_decrqm_ignores_other_private_modes() {
+        let mut tail = Vec::new();
+        assert!(detect_decrqm_in_stream(&mut tail, b"\x1b[?25h\x1b[?1049l").is_empty());
+    }
+
+    #[test]
+    fn test_decrqm_response_format() {
+        assert_eq!(decrqm_response(2026), b"\x1b[?2026;0$y".to_vec());
+    }
+
+    #[test]
+    fn test_mode_toggle_last_sequence_wins() {
+        let mut tail = Vec::new();
+        let state = detect_mode_toggle_in_stream(
+            &mut tail,
+            b"\x1b[?2004h...\x1b[?2004l",
+            BRACKETED_PASTE_ON,
+            BRACKETED_PASTE_OFF,
+        );
+        assert_eq!(state, Some(false));
+    }
+
+    #[test]
+    fn test_mode_toggle_split_across_chunks() {
+        let mut tail = Vec::new();
+        let first = detect_mode_toggle_in_stream(
+            &mut tail,
+            b"hello\x1b[?20",
+            BRACKETED_PASTE_ON,
+            BRACKETED_PASTE_OFF,
+        );
+        assert_eq!(first, None);
+        let second = detect_mode_toggle_in_stream(
+            &mut tail,
+            b"04hworld",
+            BRACKETED_PASTE_ON,
+            BRACKETED_PASTE_OFF,
+        );
+        assert_eq!(second, Some(true));
+        // Already-seen toggle is not reported again from the tail.
+        let third = detect_mode_toggle_in_stream(
+            &mut tail,
+            b"more",
+            BRACKETED_PASTE_ON,
+            BRACKETED_PASTE_OFF,
+        );
+        assert_eq!(third, None);
+    }
+
+    #[test]
+    fn test_bracketed_paste_payload_wraps_keys() {
+        assert_eq!(
+            bracketed_paste_payload("/stats session"),
+            b"\x1b[200~/stats session\x1b[201~".to_vec()
+        );
+    }
+
     #[test]
     fn test_new_registers_and_drop_unregisters_process_group() -> Result<()> {
         clear_shutdown();
@@ -618,6 +1506,167 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_resize_updates_window_size() -> Result<()> {
+        clear_shutdown();
+        let _guard = ShutdownGuard;
+        let mut session = PtySession::new(None, "sh", &["-c", "sleep 1"])?;
+        session.resize(320, 60)?;
+
+        let mut win = libc::winsize {
+            ws_row: 0,
+            ws_col: 0,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // SAFETY: TIOCGWINSZ on the session's valid master FD.
+        let rc = unsafe {
+            libc::ioctl(
+                session.master_fd,
+                libc::TIOCGWINSZ as libc::c_ulong,
+                std::ptr::addr_of_mut!(win),
+            )
+        };
+        assert_eq!(rc, 0);
+        assert_eq!((win.ws_col, win.ws_row), (320, 60));
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_fails_after_deadline() -> Result<()> {
+        let mut session = PtySession::new(None, "sh", &["-c", "sleep 1"])?;
+        session.set_deadline(Some(Instant::now() + Duration::from_secs(60)));
+        assert!(session.capture_pane().is_ok());
+
+        session.set_deadline(Some(Instant::now()));
+        let err = session.capture_pane().expect_err("deadline has passed");
+        assert!(format!("{:#}", err).contains("[timeout] Total time budget exceeded"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_for_output_wakes_on_output() -> Result<()> {
+        let session = PtySession::new(None, "sh", &["-c", "sleep 0.2; echo ready; sleep 2"])?;
+        let start = Instant::now();
+        assert!(session.wait_for_output(Duration::from_secs(3)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_with_env_sets_child_vars() -> Result<()> {
+        let mut session = PtySession::new_with_env(
+            None,
+            "sh",
+            &["-c", "echo \"cache=$XDG_CACHE_HOME\"; sleep 2"],
+            &[("XDG_CACHE_HOME", OsStr::new("/tmp/isolated-cache"))],
+        )?;
+        session.wait_for(
+            |content| content.contains("cache=/tmp/isolated-cache"),
+            Duration::from_secs(2),
+            Duration::from_millis(20),
+            false,
+            false,
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_for_output_times_out_when_idle() -> Result<()> {
+        let session = PtySession::new(None, "sh", &["-c", "sleep 2"])?;
+        let start = Instant::now();
+        assert!(!session.wait_for_output(Duration::from_millis(150)));
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_for_skips_matcher_on_unchanged_screen() -> Result<()> {
+        let mut session = PtySession::new(None, "sh", &["-c", "echo ready; sleep 2"])?;
+        let calls = std::cell::Cell::new(0);
+        let _ = session.wait_for(
+            |_| {
+                calls.set(calls.get() + 1);
+                false
+            },
+            Duration::from_millis(400),
+            Duration::from_millis(20),
+            false,
+            false,
+        );
+        // Empty screen, then "ready"; every later poll sees the same text.
+        assert!(calls.get() <= 3, "matcher ran {} times", calls.get());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verbose_wait_reports_to_observers() -> Result<()> {
+        #[derive(Default)]
+        struct Seen(Mutex<Vec<(String, WaitStats)>>, Mutex<Vec<String>>);
+        impl CheckObserver for Seen {
+            fn on_wait(&self, provider: &str, wait: &WaitStats) {
+                self.0.lock().unwrap().push((provider.to_string(), *wait));
+            }
+            fn on_capture(&self, _provider: &str, label: &str, text: &str) {
+                self.1
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}: {}", label, text.trim()));
+            }
+        }
+        let observer = Arc::new(Seen::default());
+        let mut session = PtySession::new(None, "sh", &["-c", "echo ready; sleep 2"])?;
+        session.observe("acme", vec![observer.clone()]);
+        let ready = |content: &str| content.contains("ready");
+        let interval = Duration::from_millis(20);
+        session.wait_for(ready, Duration::from_secs(2), interval, false, false)?;
+        assert!(
+            observer.0.lock().unwrap().is_empty(),
+            "quiet unless verbose"
+        );
+        session.wait_for(ready, Duration::from_secs(2), interval, false, true)?;
+        let _ = session.wait_for(|_| false, Duration::from_millis(100), interval, false, true);
+
+        let waits = observer.0.lock().unwrap();
+        let outcomes: Vec<&str> = waits.iter().map(|(_, w)| w.outcome).collect();
+        assert_eq!(outcomes, ["matched", "timed out"]);
+        assert!(waits
+            .iter()
+            .all(|(provider, w)| provider == "acme" && w.polls > 0));
+        drop(waits);
+        // The screen a wait gave up on goes to the observers too.
+        assert_eq!(*observer.1.lock().unwrap(), ["timeout: ready"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_send_waits_for_a_slow_reader() -> Result<()> {
+        // Doesn't read for a while, then echoes everything back, so the
+        // send only completes if our side keeps draining the echo.
+        let mut session = PtySession::new(
+            None,
+            "sh",
+            &["-c", "stty raw -echo; echo ready; sleep 1.5; exec cat"],
+        )?;
+        session.wait_for(
+            |c| c.contains("ready"),
+            Duration::from_secs(5),
+            Duration::from_millis(20),
+            false,
+            false,
+        )?;
+        let mut keys = "x".repeat(64 * 1024);
+        keys.push_str("END");
+        session.send_keys_literal(&keys)?;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !session.captured_text().contains("END") && Instant::now() < deadline {
+            session.wait_for_output(Duration::from_millis(50));
+            session.read_available();
+        }
+        assert!(session.captured_text().contains("END"));
+        Ok(())
+    }
+
     #[test]
     fn test_wait_for_stops_on_shutdown_signal() -> Result<()> {
         clear_shutdown();
@@ -0,0 +1,160 @@
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// One step of a query path: an object key or an array index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A path into the JSON output, such as
+/// `results.claude."Current session".percent_used`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub segments: Vec<Segment>,
+}
+
+impl Query {
+    /// The value at this path, if every segment exists.  Numeric segments
+    /// also match object keys spelled as numbers.
+    pub fn select<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.segments
+            .iter()
+            .try_fold(value, |current, segment| match segment {
+                Segment::Key(key) => current.get(key),
+                Segment::Index(index) => current
+                    .get(index)
+                    .or_else(|| current.get(index.to_string())),
+            })
+    }
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(path: &str) -> Result<Self> {
+        parse_path(path).map(|segments| Query { segments })
+    }
+}
+
+/// Keys containing dots or spaces are double-quoted (`\"` and `\\` escape
+/// inside quotes); bare numbers index arrays.  A leading `.` is accepted
+/// so jq-style paths work unchanged.
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let path = path.strip_prefix('.').unwrap_or(path);
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut segments = Vec::new();
+    let mut chars = path.chars().peekable();
+    loop {
+        let segment = if chars.peek() == Some(&'"') {
+            chars.next();
+            let mut key = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c) => key.push(c),
+                        None => bail!("Unterminated quote in query '{}'", path),
+                    },
+                    Some(c) => key.push(c),
+                    None => bail!("Unterminated quote in query '{}'", path),
+                }
+            }
+            Segment::Key(key)
+        } else {
+            let mut raw = String::new();
+            while let Some(&c) = chars.peek() {
+                if c == '.' {
+                    break;
+                }
+                raw.push(c);
+                chars.next();
+            }
+            if raw.is_empty() {
+                bail!("Empty segment in query '{}'", path);
+            }
+            match raw.parse::<usize>() {
+                Ok(index) => Segment::Index(index),
+                Err(_) => Segment::Key(raw),
+            }
+        };
+        segments.push(segment);
+        match chars.next() {
+            None => return Ok(segments),
+            Some('.') => {}
+            Some(c) => bail!("Expected '.' before '{}' in query '{}'", c, path),
+        }
+    }
+}
+
+/// Render a selected value for the shell: strings without quotes, other
+/// scalars as JSON, objects and arrays as compact JSON.
+pub fn render(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_path_bare_and_quoted() {
+        let path = parse_path(r#"results.claude."Current session".percent_used"#).unwrap();
+        assert_eq!(
+            path,
+            vec![
+                Segment::Key("results".into()),
+                Segment::Key("claude".into()),
+                Segment::Key("Current session".into()),
+                Segment::Key("percent_used".into()),
+            ]
+        );
+        assert_eq!(parse_path(".a.0").unwrap()[1], Segment::Index(0));
+        assert_eq!(
+            parse_path(r#""gemini-2.5-pro""#).unwrap(),
+            vec![Segment::Key("gemini-2.5-pro".into())]
+        );
+        assert_eq!(
+            parse_path(r#""say \"hi\"""#).unwrap(),
+            vec![Segment::Key("say \"hi\"".into())]
+        );
+        assert!(parse_path(".").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_path_rejects_malformed() {
+        assert!(parse_path("a..b").is_err());
+        assert!(parse_path(r#"a."unterminated"#).is_err());
+        assert!(parse_path(r#""a"b"#).is_err());
+    }
+
+    #[test]
+    fn test_select_and_render() {
+        let doc = json!({
+            "results": {
+                "claude": {"Current session": {"percent_used": 12, "reset_info": "Resets 2pm"}}
+            },
+            "list": [1, 2],
+        });
+        let get = |path: &str| path.parse::<Query>().unwrap().select(&doc).map(render);
+        assert_eq!(
+            get(r#"results.claude."Current session".percent_used"#).as_deref(),
+            Some("12")
+        );
+        assert_eq!(
+            get(r#"results.claude."Current session".reset_info"#).as_deref(),
+            Some("Resets 2pm")
+        );
+        assert_eq!(get("list.1").as_deref(), Some("2"));
+        assert_eq!(get("list").as_deref(), Some("[1,2]"));
+        assert_eq!(get("results.codex"), None);
+    }
+}
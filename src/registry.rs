@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// A provider process group launched by an agentusage run, persisted so a
+/// later run can find it if that one crashed before cleaning up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub pgid: i32,
+    /// The provider binary the group was launched from.
+    pub binary: String,
+    /// PID of the agentusage process that launched it.
+    pub owner: u32,
+}
+
+/// What became of a group a crashed run left behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeftoverState {
+    /// The group had already exited; its record was removed.
+    Exited,
+    /// The group was still running and was killed.
+    Killed,
+    /// The group is still running and was left alone.
+    Running,
+}
+
+/// One record left by a crashed run, and what `sweep` did about it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Leftover {
+    #[serde(flatten)]
+    pub record: SessionRecord,
+    pub state: LeftoverState,
+}
+
+/// `$XDG_STATE_HOME/agentusage` (default `~/.local/state/agentusage`).
+pub(crate) fn state_home() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    let state_home = var("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state_home.join("agentusage"))
+}
+
+/// `sessions/` under the state home: one file per live process group.
+pub fn state_dir() -> Option<PathBuf> {
+    Some(state_home()?.join("sessions"))
+}
+
+fn record_path(dir: &Path, owner: u32, pgid: i32) -> PathBuf {
+    dir.join(format!("{}-{}.json", owner, pgid))
+}
+
+/// Persist a newly launched group.  Best effort: a check shouldn't fail
+/// because the state dir is unwritable.
+pub(crate) fn record(pgid: i32, binary: &str) {
+    if let Some(dir) = state_dir() {
+        record_in(&dir, pgid, binary);
+    }
+}
+
+fn record_in(dir: &Path, pgid: i32, binary: &str) {
+    let record = SessionRecord {
+        pgid,
+        binary: binary.to_string(),
+        owner: std::process::id(),
+    };
+    if std::fs::create_dir_all(dir).is_ok() {
+        if let Ok(json) = serde_json::to_vec(&record) {
+            let _ = std::fs::write(record_path(dir, record.owner, pgid), json);
+        }
+    }
+}
+
+/// Drop the record of a group this process has cleaned up.
+pub(crate) fn forget(pgid: i32) {
+    if let Some(dir) = state_dir() {
+        let _ = std::fs::remove_file(record_path(&dir, std::process::id(), pgid));
+    }
+}
+
+fn load(dir: &Path) -> Vec<(PathBuf, SessionRecord)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let record = serde_json::from_slice(&std::fs::read(&path).ok()?).ok()?;
+            Some((path, record))
+        })
+        .collect()
+}
+
+fn process_alive(pid: i32) -> bool {
+    // SAFETY: signal 0 only checks that the process exists.
+    let exists = unsafe { libc::kill(pid, 0) } == 0;
+    exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether `pgid`'s leader still runs `binary`, so a recycled PID is never
+/// mistaken for a leftover provider.
+fn group_runs(pgid: i32, binary: &str) -> bool {
+    let Ok(output) = Command::new("ps")
+        .args(["-o", "args=", "-p", &pgid.to_string()])
+        .output()
+    else {
+        return false;
+    };
+    command_runs(&String::from_utf8_lossy(&output.stdout), binary)
+}
+
+/// Whether the command line `args` runs `binary`: as the executable, or as
+/// the script an interpreter runs (Gemini, and Codex's npm shim, run as
+/// `node .../bin/gemini`).
+fn command_runs(args: &str, binary: &str) -> bool {
+    let stem = |s: &str| {
+        let name = s.rsplit('/').next().unwrap_or(s);
+        name.split('.').next().unwrap_or(name).to_string()
+    };
+    let binary = stem(binary);
+    let mut words = args.split_whitespace();
+    let Some(program) = words.next() else {
+        return false;
+    };
+    let program = stem(program);
+    if program == binary {
+        return true;
+    }
+    // Under an interpreter, the first argument that isn't a flag is the
+    // script.
+    let interpreted = matches!(program.as_str(), "node" | "nodejs" | "bun" | "deno")
+        || program.starts_with("python");
+    interpreted
+        && words
+            .find(|word| !word.starts_with('-'))
+            .is_some_and(|script| stem(script) == binary)
+}
+
+/// Records whose agentusage process has exited without cleaning up.
+pub fn orphans() -> Vec<SessionRecord> {
+    state_dir()
+        .map(|dir| orphans_in(&dir))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, record)| record)
+        .collect()
+}
+
+fn orphans_in(dir: &Path) -> Vec<(PathBuf, SessionRecord)> {
+    load(dir)
+        .into_iter()
+        .filter(|(_, record)| !process_alive(record.owner as i32))
+        .collect()
+}
+
+/// Kill the groups crashed runs left behind (SIGTERM, then SIGKILL) and
+/// remove their records, including records of groups that already exited.
+pub fn reap_orphans() -> Vec<Leftover> {
+    sweep(true)
+}
+
+/// Go through the records crashed runs left: records of groups that have
+/// exited are removed, and groups still running are killed under `kill`
+/// (otherwise left, record and all, for a later `--cleanup`).
+pub fn sweep(kill: bool) -> Vec<Leftover> {
+    state_dir()
+        .map(|dir| sweep_in(&dir, kill))
+        .unwrap_or_default()
+}
+
+fn sweep_in(dir: &Path, kill: bool) -> Vec<Leftover> {
+    let orphans = orphans_in(dir);
+    let live: Vec<i32> = orphans
+        .iter()
+        .filter(|(_, record)| group_runs(record.pgid, &record.binary))
+        .map(|(_, record)| record.pgid)
+        .collect();
+    if kill {
+        for signal in [libc::SIGTERM, libc::SIGKILL] {
+            for pgid in &live {
+                // SAFETY: a negative PID signals the whole process group.
+                let _ = unsafe { libc::kill(-pgid, signal) };
+            }
+            if !live.is_empty() && signal == libc::SIGTERM {
+                thread::sleep(Duration::from_millis(300));
+            }
+        }
+    }
+    orphans
+        .into_iter()
+        .map(|(path, record)| {
+            let state = match (live.contains(&record.pgid), kill) {
+                (false, _) => LeftoverState::Exited,
+                (true, true) => LeftoverState::Killed,
+                (true, false) => LeftoverState::Running,
+            };
+            if state != LeftoverState::Running {
+                let _ = std::fs::remove_file(path);
+            }
+            Leftover { record, state }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::TempDir;
+    use std::os::unix::process::CommandExt;
+
+    #[test]
+    fn test_sweep_kills_orphaned_groups_and_clears_records() {
+        let dir = TempDir::create("registry-test").unwrap();
+        let dir = dir.path();
+
+        // Ours: the owner is alive, so it is left alone.
+        record_in(dir, 999_999, "claude");
+        assert!(orphans_in(dir).is_empty());
+
+        // A crashed run's group that is still running.
+        let mut child = Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pgid = child.id() as i32;
+        let dead_owner = u32::MAX / 2;
+        let write = |pgid: i32, binary: &str| {
+            let record = SessionRecord {
+                pgid,
+                binary: binary.into(),
+                owner: dead_owner,
+            };
+            std::fs::write(
+                record_path(dir, dead_owner, pgid),
+                serde_json::to_vec(&record).unwrap(),
+            )
+            .unwrap();
+        };
+        write(pgid, "sleep");
+        // One whose group exited long ago.
+        write(999_998, "codex");
+
+        let state =
+            |swept: &[Leftover], pgid| swept.iter().find(|r| r.record.pgid == pgid).unwrap().state;
+        // Detecting without killing only clears the exited group's record.
+        let swept = sweep_in(dir, false);
+        assert_eq!(swept.len(), 2);
+        assert_eq!(state(&swept, pgid), LeftoverState::Running);
+        assert_eq!(state(&swept, 999_998), LeftoverState::Exited);
+        assert_eq!(load(dir).len(), 2);
+
+        let swept = sweep_in(dir, true);
+        assert_eq!(swept.len(), 1);
+        assert_eq!(state(&swept, pgid), LeftoverState::Killed);
+        assert!(child.wait().is_ok());
+        assert_eq!(load(dir).len(), 1);
+    }
+
+    #[test]
+    fn test_command_runs_matches_interpreted_providers() {
+        assert!(command_runs(
+            "/usr/local/bin/claude --permission-mode plan",
+            "claude"
+        ));
+        assert!(command_runs(
+            "node /home/me/.npm-global/bin/gemini",
+            "gemini"
+        ));
+        assert!(command_runs(
+            "node --no-warnings /usr/bin/codex.js -s read-only",
+            "codex"
+        ));
+        assert!(command_runs("codex -s read-only", "/opt/codex/bin/codex"));
+        assert!(!command_runs("node /srv/app/server.js", "gemini"));
+        assert!(!command_runs("vim gemini.md", "gemini"));
+        assert!(!command_runs("", "claude"));
+    }
+}
@@ -0,0 +1,699 @@
+use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::history::HistoryRecord;
+use crate::thresholds::Thresholds;
+use crate::types::LimitWindow;
+
+/// A reset is assumed when a limit's reset time moves later by more than
+/// this many minutes between readings.
+const RESET_SLACK_MINUTES: i64 = 60;
+
+/// One provider limit summarized over the report period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LimitSummary {
+    pub provider: String,
+    /// The machine the readings came from, for usage another machine sent
+    /// to `agentusage serve --ingest`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    pub label: String,
+    pub window: LimitWindow,
+    pub readings: usize,
+    pub peak_percent_used: u32,
+    pub peak_at: DateTime<Utc>,
+    /// Percent used this limit's window warns at.
+    pub threshold: u32,
+    /// Separate stretches spent at or above `threshold`.
+    pub times_over_threshold: usize,
+    pub resets_observed: usize,
+    /// Mean of the highest reading in each window that reset during the
+    /// period, i.e. how much of the limit was used before it refilled.
+    pub avg_used_at_reset: Option<f64>,
+    /// Dollars spent at the last reading minus the first, to the cent.
+    pub spend_change: Option<f64>,
+}
+
+/// Usage growth attributed to one project: the increase in each limit
+/// since its previous reading, credited to the project the later reading
+/// was taken in.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProjectSummary {
+    /// `None` for checks run without `--directory`.
+    pub project: Option<String>,
+    pub checks: usize,
+    /// Percentage points gained across all limits.
+    pub usage_growth: u32,
+    /// Extra-usage dollars gained, to the cent.
+    pub spend_growth: Option<f64>,
+}
+
+/// How one limit moved between a snapshot and a later check.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LimitChange {
+    pub provider: String,
+    pub label: String,
+    pub window: LimitWindow,
+    pub before: u32,
+    pub after: u32,
+    /// Percentage points used in between; after a reset, all of `after`.
+    pub used: u32,
+    /// The limit refilled in between, so `before` no longer applies.
+    pub reset: bool,
+    /// Extra-usage dollars spent in between, to the cent.
+    pub spend: Option<f64>,
+}
+
+/// Usage history summarized between `from` and `to`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub limits: Vec<LimitSummary>,
+    /// Sum of every limit's `spend_change`; `None` if none tracked spend.
+    pub total_spend_change: Option<f64>,
+    /// Heaviest usage growth first.
+    pub projects: Vec<ProjectSummary>,
+}
+
+/// Each provider limit's readings in `from`..`to`, in time order.
+fn limit_series(
+    records: &[HistoryRecord],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<Vec<&HistoryRecord>> {
+    let mut groups: BTreeMap<(Option<&str>, &str, &str), Vec<&HistoryRecord>> = BTreeMap::new();
+    for record in records
+        .iter()
+        .filter(|r| r.timestamp >= from && r.timestamp <= to)
+    {
+        groups
+            .entry((
+                record.host.as_deref(),
+                record.provider.as_str(),
+                record.label.as_str(),
+            ))
+            .or_default()
+            .push(record);
+    }
+    let mut series: Vec<_> = groups.into_values().collect();
+    for readings in &mut series {
+        readings.sort_by_key(|r| r.timestamp);
+    }
+    series
+}
+
+/// Summarize `records` per provider limit for the period `from`..`to`,
+/// counting the stretches each limit spent past its `thresholds`.
+pub fn summarize(
+    records: &[HistoryRecord],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    thresholds: &Thresholds,
+) -> Report {
+    let mut projects: BTreeMap<Option<&str>, ProjectTally> = BTreeMap::new();
+    let limits: Vec<LimitSummary> = limit_series(records, from, to)
+        .into_iter()
+        .map(|readings| {
+            for reading in &readings {
+                let tally = projects.entry(reading.project.as_deref()).or_default();
+                tally.checks.insert(reading.timestamp);
+            }
+            for pair in readings.windows(2) {
+                let tally = projects.entry(pair[1].project.as_deref()).or_default();
+                tally.usage_growth += growth(pair[0], pair[1]);
+                if let Some(spend) = spend_growth(pair[0], pair[1]) {
+                    *tally.spend_growth.get_or_insert(0.0) += spend;
+                }
+            }
+            summarize_limit(&readings, thresholds)
+        })
+        .collect();
+    let spend: Vec<f64> = limits.iter().filter_map(|l| l.spend_change).collect();
+    let mut projects: Vec<ProjectSummary> = projects
+        .into_iter()
+        .map(|(project, tally)| ProjectSummary {
+            project: project.map(str::to_string),
+            checks: tally.checks.len(),
+            usage_growth: tally.usage_growth,
+            spend_growth: tally.spend_growth.map(cents),
+        })
+        .collect();
+    projects.sort_by_key(|p| std::cmp::Reverse(p.usage_growth));
+    Report {
+        from,
+        to,
+        total_spend_change: (!spend.is_empty()).then(|| cents(spend.iter().sum())),
+        limits,
+        projects,
+    }
+}
+
+/// How each limit in `current` moved since `baseline` (a snapshot's
+/// records), in `current`'s order.  Limits missing from either side are
+/// left out.
+pub fn compare(baseline: &[HistoryRecord], current: &[HistoryRecord]) -> Vec<LimitChange> {
+    current
+        .iter()
+        .filter_map(|after| {
+            let before = baseline
+                .iter()
+                .find(|b| b.provider == after.provider && b.label == after.label)?;
+            Some(LimitChange {
+                provider: after.provider.clone(),
+                label: after.label.clone(),
+                window: after.window,
+                before: before.percent_used,
+                after: after.percent_used,
+                used: growth(before, after),
+                reset: is_reset(before, after),
+                spend: spend_growth(before, after).map(cents),
+            })
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct ProjectTally {
+    checks: BTreeSet<DateTime<Utc>>,
+    usage_growth: u32,
+    spend_growth: Option<f64>,
+}
+
+/// Percentage points a limit gained between two readings; after a reset
+/// everything now used is new.
+fn growth(before: &HistoryRecord, after: &HistoryRecord) -> u32 {
+    if is_reset(before, after) {
+        after.percent_used
+    } else {
+        after.percent_used.saturating_sub(before.percent_used)
+    }
+}
+
+/// Dollars spent between two readings; a drop means the billing period
+/// rolled over.
+fn spend_growth(before: &HistoryRecord, after: &HistoryRecord) -> Option<f64> {
+    let before = before.spent.as_deref().and_then(spent_dollars)?;
+    let after = after.spent.as_deref().and_then(spent_dollars)?;
+    Some(if after < before {
+        after
+    } else {
+        after - before
+    })
+}
+
+fn cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+fn summarize_limit(readings: &[&HistoryRecord], thresholds: &Thresholds) -> LimitSummary {
+    let first = readings[0];
+    let peak = readings
+        .iter()
+        .max_by(|a, b| {
+            a.percent_used
+                .cmp(&b.percent_used)
+                .then(b.timestamp.cmp(&a.timestamp))
+        })
+        .unwrap_or(&first);
+
+    let threshold = thresholds.warn_at(first.window);
+    let mut times_over_threshold = 0;
+    let mut hot = false;
+    // Imported estimates are too rough to say a limit crossed its threshold.
+    for reading in readings.iter().filter(|r| !r.estimated) {
+        let now_hot = reading.percent_used >= threshold;
+        if now_hot && !hot {
+            times_over_threshold += 1;
+        }
+        hot = now_hot;
+    }
+
+    // Peak of each window that reset before the next reading.
+    let mut reset_peaks = Vec::new();
+    let mut window_peak = first.percent_used;
+    for pair in readings.windows(2) {
+        if is_reset(pair[0], pair[1]) {
+            reset_peaks.push(window_peak);
+            window_peak = pair[1].percent_used;
+        } else {
+            window_peak = window_peak.max(pair[1].percent_used);
+        }
+    }
+
+    let spent: Vec<f64> = readings
+        .iter()
+        .filter_map(|r| r.spent.as_deref().and_then(spent_dollars))
+        .collect();
+
+    LimitSummary {
+        provider: first.provider.clone(),
+        host: first.host.clone(),
+        label: first.label.clone(),
+        window: first.window,
+        readings: readings.len(),
+        peak_percent_used: peak.percent_used,
+        peak_at: peak.timestamp,
+        threshold,
+        times_over_threshold,
+        resets_observed: reset_peaks.len(),
+        avg_used_at_reset: (!reset_peaks.is_empty())
+            .then(|| reset_peaks.iter().map(|&p| p as f64).sum::<f64>() / reset_peaks.len() as f64),
+        spend_change: match (spent.first(), spent.last()) {
+            (Some(start), Some(end)) => Some(cents(end - start)),
+            _ => None,
+        },
+    }
+}
+
+/// Whether the limit refilled between two consecutive readings: its reset
+/// time jumped later, or (with no reset time shown) usage went down.
+fn is_reset(before: &HistoryRecord, after: &HistoryRecord) -> bool {
+    match (before.reset_minutes, after.reset_minutes) {
+        (Some(a), Some(b)) => {
+            let reset_before = before.timestamp + chrono::Duration::minutes(a);
+            let reset_after = after.timestamp + chrono::Duration::minutes(b);
+            (reset_after - reset_before).num_minutes() > RESET_SLACK_MINUTES
+        }
+        _ => after.percent_used < before.percent_used,
+    }
+}
+
+/// The amount spent in an entry's `spent` text, e.g. `$77.33 / $500.00 spent`.
+fn spent_dollars(text: &str) -> Option<f64> {
+    let amount = text.trim_start().strip_prefix('$')?;
+    let end = amount
+        .find(|c: char| !c.is_ascii_digit() && c != '.' && c != ',')
+        .unwrap_or(amount.len());
+    amount[..end].replace(',', "").parse().ok()
+}
+
+/// The report as a markdown document.
+pub fn to_markdown(report: &Report) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# agentusage report\n");
+    let _ = writeln!(
+        out,
+        "{} to {}\n",
+        report
+            .from
+            .with_timezone(&chrono::Local)
+            .format("%b %d %H:%M"),
+        report
+            .to
+            .with_timezone(&chrono::Local)
+            .format("%b %d %H:%M")
+    );
+    if report.limits.is_empty() {
+        let _ = writeln!(out, "No usage history for this period.");
+        return out;
+    }
+    let _ = writeln!(
+        out,
+        "| Provider | Limit | Peak | Peak at | Times ≥ threshold | Resets | Avg used at reset | Spend change |"
+    );
+    let _ = writeln!(out, "|---|---|---:|---|---:|---:|---:|---:|");
+    for limit in &report.limits {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {}% | {} | {} (≥{}%) | {} | {} | {} |",
+            match &limit.host {
+                Some(host) => format!("{}/{}", host, limit.provider),
+                None => limit.provider.clone(),
+            },
+            limit.label,
+            limit.peak_percent_used,
+            limit
+                .peak_at
+                .with_timezone(&chrono::Local)
+                .format("%b %d %H:%M"),
+            limit.times_over_threshold,
+            limit.threshold,
+            limit.resets_observed,
+            limit
+                .avg_used_at_reset
+                .map(|avg| format!("{:.0}%", avg))
+                .unwrap_or_else(|| "–".into()),
+            limit
+                .spend_change
+                .map(format_dollars)
+                .unwrap_or_else(|| "–".into()),
+        );
+    }
+    if let Some(total) = report.total_spend_change {
+        let _ = writeln!(out, "\n**Total spend change:** {}", format_dollars(total));
+    }
+    if report.projects.iter().any(|p| p.project.is_some()) {
+        let _ = writeln!(out, "\n## Usage growth by project\n");
+        let _ = writeln!(out, "| Project | Checks | Usage growth | Spend growth |");
+        let _ = writeln!(out, "|---|---:|---:|---:|");
+        for project in &report.projects {
+            let _ = writeln!(
+                out,
+                "| {} | {} | +{} pts | {} |",
+                project.project.as_deref().unwrap_or("(no --directory)"),
+                project.checks,
+                project.usage_growth,
+                project
+                    .spend_growth
+                    .map(format_dollars)
+                    .unwrap_or_else(|| "–".into()),
+            );
+        }
+    }
+    out
+}
+
+/// Weekday names in heatmap row order.
+pub const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Shades from no growth to the busiest hour.
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// Usage growth bucketed by weekday and hour of day.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Heatmap {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Percentage points gained, summed over every limit; rows follow
+    /// `WEEKDAYS`, columns are hours 0-23.
+    pub growth: [[u32; 24]; 7],
+    /// How many limit resets fell in each hour of the day.  Heavy work
+    /// scheduled just after these hours gets a full window.
+    pub resets_by_hour: [u32; 24],
+}
+
+/// Bucket each limit's growth between readings by when the later reading
+/// was taken, in `tz`.  Resets are placed at the reset time the earlier
+/// reading showed, when it showed one.
+pub fn heatmap<Tz: TimeZone>(
+    records: &[HistoryRecord],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    tz: &Tz,
+) -> Heatmap {
+    let mut map = Heatmap {
+        from,
+        to,
+        growth: [[0; 24]; 7],
+        resets_by_hour: [0; 24],
+    };
+    for readings in limit_series(records, from, to) {
+        for pair in readings.windows(2) {
+            let at = pair[1].timestamp.with_timezone(tz);
+            map.growth[at.weekday().num_days_from_monday() as usize][at.hour() as usize] +=
+                growth(pair[0], pair[1]);
+            if is_reset(pair[0], pair[1]) {
+                let reset_at = pair[0]
+                    .reset_minutes
+                    .map(|mins| pair[0].timestamp + chrono::Duration::minutes(mins))
+                    .unwrap_or(pair[1].timestamp);
+                map.resets_by_hour[reset_at.with_timezone(tz).hour() as usize] += 1;
+            }
+        }
+    }
+    map
+}
+
+/// The heatmap as a terminal grid, one row per weekday and one column per
+/// hour, shaded relative to the busiest hour.
+pub fn heatmap_to_text(map: &Heatmap) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Usage growth by hour, {} to {}\n",
+        map.from.with_timezone(&chrono::Local).format("%b %d"),
+        map.to.with_timezone(&chrono::Local).format("%b %d")
+    );
+    let max = map.growth.iter().flatten().copied().max().unwrap_or(0);
+    if max == 0 {
+        let _ = writeln!(out, "No usage growth recorded for this period.");
+        return out;
+    }
+    let mut header = String::from("    ");
+    for hour in (0..24).step_by(3) {
+        let _ = write!(header, "{:<6}", hour);
+    }
+    let _ = writeln!(out, "{}", header.trim_end());
+    for (day, row) in WEEKDAYS.iter().zip(&map.growth) {
+        let cells: String = row.iter().map(|&pts| shade(pts, max)).collect();
+        let _ = writeln!(out, "{} {}", day, cells);
+    }
+    if map.resets_by_hour.iter().any(|&n| n > 0) {
+        let cells: String = map
+            .resets_by_hour
+            .iter()
+            .map(|&n| match n {
+                0 => "  ".to_string(),
+                1..=99 => format!("{:>2}", n),
+                _ => "++".to_string(),
+            })
+            .collect();
+        let _ = writeln!(out, "\nRst {}", cells.trim_end());
+    }
+    let _ = writeln!(
+        out,
+        "\n{} none  {} low  {} high  ({} pts in the busiest hour)",
+        SHADES[0], SHADES[1], SHADES[4], max
+    );
+    out
+}
+
+fn shade(value: u32, max: u32) -> String {
+    // Any growth at all gets at least the lightest shade.
+    let index = (value * 4).div_ceil(max.max(1)).min(4) as usize;
+    SHADES[index].to_string().repeat(2)
+}
+
+fn format_dollars(amount: f64) -> String {
+    if amount < 0.0 {
+        format!("-${:.2}", -amount)
+    } else {
+        format!("+${:.2}", amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(at: &str, label: &str, used: u32, reset: Option<i64>) -> HistoryRecord {
+        HistoryRecord {
+            timestamp: at.parse().unwrap(),
+            provider: "claude".into(),
+            label: label.into(),
+            window: LimitWindow::Session,
+            percent_used: used,
+            reset_minutes: reset,
+            spent: None,
+            project: None,
+            snapshot: None,
+            host: None,
+            estimated: false,
+        }
+    }
+
+    fn thresholds() -> Thresholds {
+        let mut thresholds = Thresholds::default();
+        thresholds.set(None, 80);
+        thresholds
+    }
+
+    fn period() -> (DateTime<Utc>, DateTime<Utc>) {
+        (
+            "2026-03-01T00:00:00Z".parse().unwrap(),
+            "2026-03-08T00:00:00Z".parse().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_peaks_high_usage_and_resets() {
+        let (from, to) = period();
+        let records = [
+            record("2026-03-01T10:00:00Z", "Current session", 40, Some(180)),
+            record("2026-03-01T11:00:00Z", "Current session", 85, Some(120)),
+            record("2026-03-01T12:00:00Z", "Current session", 90, Some(60)),
+            // Window reset at 13:00; a new 5h window starts.
+            record("2026-03-01T14:00:00Z", "Current session", 10, Some(240)),
+            record("2026-03-01T15:00:00Z", "Current session", 82, Some(180)),
+            // Outside the period.
+            record("2026-02-20T10:00:00Z", "Current session", 100, Some(60)),
+        ];
+        let report = summarize(&records, from, to, &thresholds());
+        assert_eq!(report.limits.len(), 1);
+        let limit = &report.limits[0];
+        assert_eq!(limit.readings, 5);
+        assert_eq!(limit.peak_percent_used, 90);
+        assert_eq!(
+            limit.peak_at,
+            "2026-03-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!((limit.threshold, limit.times_over_threshold), (80, 2));
+        assert_eq!(limit.resets_observed, 1);
+        assert_eq!(limit.avg_used_at_reset, Some(90.0));
+        assert_eq!(limit.spend_change, None);
+        assert_eq!(report.total_spend_change, None);
+
+        let mut session = thresholds();
+        session.set(Some(LimitWindow::Session), 85);
+        let limit = &summarize(&records, from, to, &session).limits[0];
+        assert_eq!((limit.threshold, limit.times_over_threshold), (85, 1));
+
+        // Imported estimates chart, but don't count as crossing.
+        let estimated: Vec<HistoryRecord> = records
+            .iter()
+            .cloned()
+            .map(|r| HistoryRecord {
+                estimated: true,
+                ..r
+            })
+            .collect();
+        let limit = &summarize(&estimated, from, to, &thresholds()).limits[0];
+        assert_eq!((limit.readings, limit.peak_percent_used), (5, 90));
+        assert_eq!(limit.times_over_threshold, 0);
+    }
+
+    #[test]
+    fn test_reset_without_reset_time_is_a_drop_in_usage() {
+        let (from, to) = period();
+        let records = [
+            record("2026-03-01T10:00:00Z", "5h limit", 50, None),
+            record("2026-03-01T11:00:00Z", "5h limit", 70, None),
+            record("2026-03-01T16:00:00Z", "5h limit", 5, None),
+            record("2026-03-01T20:00:00Z", "5h limit", 30, None),
+            record("2026-03-02T02:00:00Z", "5h limit", 20, None),
+        ];
+        let limit = &summarize(&records, from, to, &thresholds()).limits[0];
+        assert_eq!(limit.resets_observed, 2);
+        assert_eq!(limit.avg_used_at_reset, Some(50.0));
+        assert_eq!(limit.times_over_threshold, 0);
+    }
+
+    #[test]
+    fn test_spend_change_and_markdown() {
+        let (from, to) = period();
+        let mut start = record("2026-03-01T10:00:00Z", "Extra usage", 10, None);
+        start.spent = Some("$77.33 / $500.00 spent".into());
+        let mut end = record("2026-03-05T10:00:00Z", "Extra usage", 30, None);
+        end.spent = Some("$1,077.33 / $5,000.00 spent".into());
+        let report = summarize(&[end, start], from, to, &thresholds());
+        assert_eq!(report.limits[0].spend_change, Some(1000.0));
+        assert_eq!(report.total_spend_change, Some(1000.0));
+
+        let markdown = to_markdown(&report);
+        assert!(markdown.contains("| claude | Extra usage | 30% |"));
+        assert!(markdown.contains("**Total spend change:** +$1000.00"));
+        assert!(to_markdown(&summarize(&[], from, to, &thresholds())).contains("No usage history"));
+    }
+
+    #[test]
+    fn test_compare_to_snapshot() {
+        let baseline = [
+            record("2026-03-01T10:00:00Z", "Current session", 40, Some(60)),
+            record(
+                "2026-03-01T10:00:00Z",
+                "Current week (all models)",
+                20,
+                Some(4000),
+            ),
+            record(
+                "2026-03-01T10:00:00Z",
+                "Current week (Opus only)",
+                5,
+                Some(4000),
+            ),
+        ];
+        let current = [
+            // The session reset at 11:00; 25 points used since.
+            record("2026-03-01T12:00:00Z", "Current session", 25, Some(180)),
+            record(
+                "2026-03-01T12:00:00Z",
+                "Current week (all models)",
+                31,
+                Some(3880),
+            ),
+            record("2026-03-01T12:00:00Z", "Extra usage", 3, None),
+        ];
+        let changes = compare(&baseline, &current);
+        let moved: Vec<(&str, u32, u32, u32, bool)> = changes
+            .iter()
+            .map(|c| (c.label.as_str(), c.before, c.after, c.used, c.reset))
+            .collect();
+        assert_eq!(
+            moved,
+            [
+                ("Current session", 40, 25, 25, true),
+                ("Current week (all models)", 20, 31, 11, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_usage_growth_by_project() {
+        let (from, to) = period();
+        let tagged = |at, used, reset, project: Option<&str>| HistoryRecord {
+            project: project.map(str::to_string),
+            ..record(at, "Current session", used, reset)
+        };
+        let records = [
+            tagged("2026-03-01T10:00:00Z", 10, Some(240), Some("/work/acme")),
+            tagged("2026-03-01T11:00:00Z", 50, Some(180), Some("/work/acme")),
+            tagged("2026-03-01T12:00:00Z", 55, Some(120), None),
+            // Reset at 14:00: all 30 points used since count as growth.
+            tagged("2026-03-01T15:00:00Z", 30, Some(240), Some("/work/globex")),
+            tagged("2026-03-01T16:00:00Z", 45, Some(180), Some("/work/acme")),
+        ];
+        let report = summarize(&records, from, to, &thresholds());
+        let growth: Vec<(Option<&str>, usize, u32)> = report
+            .projects
+            .iter()
+            .map(|p| (p.project.as_deref(), p.checks, p.usage_growth))
+            .collect();
+        assert_eq!(
+            growth,
+            vec![
+                (Some("/work/acme"), 3, 55),
+                (Some("/work/globex"), 1, 30),
+                (None, 1, 5),
+            ]
+        );
+        let markdown = to_markdown(&report);
+        assert!(markdown.contains("| /work/acme | 3 | +55 pts | – |"));
+        assert!(markdown.contains("| (no --directory) | 1 | +5 pts | – |"));
+    }
+
+    #[test]
+    fn test_heatmap_buckets_growth_and_resets() {
+        let (from, to) = period();
+        // 2026-03-02 is a Monday.
+        let records = [
+            record("2026-03-02T09:10:00Z", "Current session", 0, Some(300)),
+            record("2026-03-02T10:10:00Z", "Current session", 40, Some(240)),
+            record("2026-03-02T10:50:00Z", "Current session", 60, Some(200)),
+            // Reset at 14:10; growth lands on Tuesday 09:00.
+            record("2026-03-03T09:20:00Z", "Current session", 25, Some(290)),
+        ];
+        let map = heatmap(&records, from, to, &Utc);
+        assert_eq!(map.growth[0][10], 60);
+        assert_eq!(map.growth[1][9], 25);
+        assert_eq!(map.growth.iter().flatten().sum::<u32>(), 85);
+        assert_eq!(map.resets_by_hour[14], 1);
+
+        let text = heatmap_to_text(&map);
+        let monday = text.lines().find(|l| l.starts_with("Mon")).unwrap();
+        assert_eq!(monday.chars().count(), 4 + 48);
+        assert_eq!(&monday[4..].chars().nth(20).unwrap(), &'█');
+        assert!(text.lines().any(|l| l == format!("Rst {:>30}", 1)));
+        assert!(text.contains("(60 pts in the busiest hour)"));
+        assert!(heatmap_to_text(&heatmap(&[], from, to, &Utc)).contains("No usage growth"));
+    }
+
+    #[test]
+    fn test_spent_dollars() {
+        assert_eq!(spent_dollars("$77.33 / $500.00 spent"), Some(77.33));
+        assert_eq!(spent_dollars("$3,100.00 / $5,000.00 spent"), Some(3100.0));
+        assert_eq!(spent_dollars("77.33 spent"), None);
+    }
+}
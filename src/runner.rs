@@ -0,0 +1,2517 @@
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::capture::content_hash;
+use crate::dialog::{
+    codex_prompt_ready, detect_aider_dialog, detect_amazonq_dialog, detect_claude_dialog,
+    detect_codex_dialog, detect_copilot_dialog, detect_cursor_dialog, detect_gemini_dialog,
+    detect_qwen_dialog, dialog_error_message, dismiss_and_verify,
+};
+use crate::lock::{self, Acquired};
+use crate::parser::{
+    claude_usage_managed_by_org, has_truncated_rows, parse_aider_output, parse_amazonq_output,
+    parse_claude_output, parse_codex_output, parse_copilot_output, parse_cursor_output,
+    parse_gemini_output, parse_qwen_output,
+};
+use crate::plan::{ProviderSpec, AIDER, AMAZONQ, CLAUDE, CODEX, COPILOT, CURSOR, GEMINI, QWEN};
+use crate::provider::Provider;
+use crate::session::{Session, SessionLaunch};
+use crate::{
+    cache, collect_results, fixtures, headless, observer, pty, throttle, trust, updates,
+    AllResults, ApprovalPolicy, Attempt, AttemptKind, Capabilities, CheckEvent, CheckFailure,
+    CheckPhase, CheckStats, DataSource, DialogKind, ProviderResult, UsageConfig, UsageData,
+};
+
+impl UsageConfig {
+    fn emit(&self, provider: &str, event: CheckEvent) {
+        for observer in &self.observers {
+            observer::dispatch(observer.as_ref(), provider, &event);
+        }
+    }
+
+    fn capture(&self, provider: &str, label: &str, text: &str) {
+        for observer in &self.observers {
+            observer.on_capture(provider, label, text);
+        }
+    }
+
+    fn warn(&self, provider: &str, message: &str) {
+        for observer in &self.observers {
+            observer.on_warning(provider, message);
+        }
+    }
+
+    fn report_phase(&self, provider: &str, phase: CheckPhase) {
+        self.emit(provider, CheckEvent::Phase(phase));
+    }
+}
+
+pub fn check_command_exists(cmd: &str) -> Result<()> {
+    match Command::new(cmd).arg("--version").output() {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            bail!(
+                "[tool-missing] {} CLI not found. Make sure it is installed and on your PATH.",
+                cmd
+            );
+        }
+        Err(_) => {
+            // Binary exists but --version might not be supported; that's fine
+            Ok(())
+        }
+    }
+}
+
+/// Handle dialog detection and policy for a provider.
+/// Returns Ok(true) if a dialog was found and dismissed (caller should retry wait),
+/// Ok(false) if no dialog found, or Err if dialog found and policy is Fail / not dismissible.
+fn handle_dialog_check<F>(
+    session: &mut Session,
+    detect_fn: F,
+    provider: &str,
+    config: &UsageConfig,
+) -> Result<bool>
+where
+    F: Fn(&str) -> Option<DialogKind>,
+{
+    let policy = config.approval_policy;
+    let content = session.capture_pane()?;
+    if let Some(kind) = detect_fn(&content) {
+        config.emit(provider, CheckEvent::DialogDetected(kind.clone()));
+
+        let dismissed = match policy {
+            ApprovalPolicy::Fail => false,
+            ApprovalPolicy::Accept | ApprovalPolicy::Onboard => {
+                dismiss_and_verify(&kind, provider, policy, session, &detect_fn)?
+            }
+        };
+        if !dismissed {
+            resolve_blocked_dialog(session, &kind, provider, config)?;
+        }
+        config.emit(provider, CheckEvent::DialogDismissed(kind));
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// A dialog we can't (or may not) dismiss: under `interactive`, hand the
+/// terminal to the user to resolve it; otherwise fail with guidance.
+fn resolve_blocked_dialog(
+    session: &mut Session,
+    kind: &DialogKind,
+    provider: &str,
+    config: &UsageConfig,
+) -> Result<()> {
+    if !config.interactive {
+        bail!("[timeout] {}", dialog_error_message(kind, provider));
+    }
+    // Parallel checks share one terminal, so takeovers queue.
+    static TAKEOVER: Mutex<()> = Mutex::new(());
+    let _turn = TAKEOVER.lock().unwrap_or_else(|e| e.into_inner());
+
+    config.warn(
+        provider,
+        &format!("Handing over the terminal for {:?}", kind),
+    );
+    let banner = format!(
+        "agentusage: {} is showing a screen it can't answer automatically ({}).\r\n\
+         Resolve it here, then press Ctrl-] to resume the usage check.",
+        provider,
+        kind.id()
+    );
+    session.interact(&banner)
+}
+
+/// Launch `spec`'s CLI in a new session under `config`.
+fn start_session(spec: &ProviderSpec, config: &UsageConfig) -> Result<Session> {
+    launch_session(spec.name, spec.binary, &spec.launch_args(config), config)
+}
+
+/// Launch `binary` for provider `name` in a new session under `config`.
+fn launch_session(
+    name: &str,
+    binary: &str,
+    args: &[&str],
+    config: &UsageConfig,
+) -> Result<Session> {
+    let deferral = config
+        .defer_updates
+        .then(|| updates::defer(name))
+        .transpose()?;
+    let (env, update_files) = match deferral {
+        Some(d) => (d.env, d.files),
+        None => (Vec::new(), None),
+    };
+    let launch = SessionLaunch {
+        binary,
+        args,
+        isolate_env: config.isolate_env,
+        env: &env,
+    };
+    let mut directory = config.directory.clone();
+    if config.preseed_trust {
+        // A per-run temp dir would need fresh trust every time, so use one
+        // stable empty directory that stays trusted across runs.
+        let dir = match directory {
+            Some(ref dir) => {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir))?;
+                dir
+            }
+            None => directory.insert(crate::isolation::launch_dir()?.display().to_string()),
+        };
+        // Not fatal: the trust dialog is still handled if it shows up.
+        if let Err(e) = trust::preseed(name, Path::new(dir)) {
+            config.warn(name, &format!("Could not pre-seed trust: {:#}", e));
+        }
+    }
+    let mut session = Session::new(directory.as_deref(), config.verbose, launch)?;
+    session.observe(name, config.observers.clone());
+    if let Some(dir) = update_files {
+        session.keep_temp_dir(dir);
+    }
+    Ok(session)
+}
+
+/// Apply the run-wide settings that live on the session itself.
+fn configure_session(session: &mut Session, config: &UsageConfig) -> Result<()> {
+    session.set_deadline(config.deadline);
+    if let Some(mode) = config.attach {
+        session.attach(mode)?;
+    }
+    Ok(())
+}
+
+/// Run one provider check with its progress events tallied into
+/// `UsageData::check` (the caller's observers still see every event).
+/// Under `mock`, fixture data is returned without launching anything; under
+/// `lock_wait`, the check holds the provider's lock and caches its result.
+fn tracked_check(config: &UsageConfig, check: &dyn Provider) -> Result<UsageData> {
+    let provider = check.name();
+    if config.mock {
+        for phase in [
+            CheckPhase::Launching,
+            CheckPhase::WaitingForPrompt,
+            CheckPhase::FetchingUsage,
+        ] {
+            config.report_phase(provider, phase);
+        }
+        return fixtures::mock_usage(provider)
+            .with_context(|| format!("No mock data for provider '{}'", provider));
+    }
+    let Some(wait) = config.lock_wait else {
+        return throttled_check(config, provider, check);
+    };
+    let waiting_since = chrono::Utc::now();
+    let _lock = match lock::acquire(provider, wait)? {
+        Acquired::Free(lock) => lock,
+        Acquired::AfterWait(lock) => {
+            // The run we waited on may have just checked.
+            if let Some(cached) = cache::load(provider) {
+                if cached.checked_at >= waiting_since {
+                    return Ok(cached.data);
+                }
+            }
+            lock
+        }
+        Acquired::TimedOut => {
+            return cache::load(provider)
+                .map(|cached| cached.into_stale())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "[timeout] Another agentusage run is still checking {} after {}s",
+                        provider,
+                        wait.as_secs()
+                    )
+                });
+        }
+    };
+    throttled_check(config, provider, check)
+}
+
+/// Run `check`, unless `min_interval` says `provider` was launched too
+/// recently, in which case serve its cached result instead.
+fn throttled_check(
+    config: &UsageConfig,
+    provider: &str,
+    check: &dyn Provider,
+) -> Result<UsageData> {
+    if let Some(interval) = config.min_interval {
+        if let Some(last) = throttle::last_launch(provider) {
+            // A stamp from the future (the clock moved back) doesn't throttle.
+            let since = (chrono::Utc::now() - last)
+                .to_std()
+                .unwrap_or(Duration::MAX);
+            if since < interval {
+                let cached = cache::load(provider).ok_or_else(|| {
+                    anyhow!(
+                        "{} was launched {}s ago and may be launched again in {}s (minimum interval {}s); no cached result to serve meanwhile",
+                        provider,
+                        since.as_secs(),
+                        (interval - since).as_secs().max(1),
+                        interval.as_secs()
+                    )
+                })?;
+                // Older than the last launch means that launch failed.
+                return Ok(if cached.checked_at >= last {
+                    cached.data
+                } else {
+                    cached.into_stale()
+                });
+            }
+        }
+        throttle::record_launch(provider);
+    }
+    let data = tally_check(config, check)?;
+    if config.lock_wait.is_some() || config.min_interval.is_some() {
+        if let Err(e) = cache::store(&data) {
+            config.warn(provider, &format!("Could not cache the result: {:#}", e));
+        }
+    }
+    Ok(data)
+}
+
+/// Run `check` with its events tallied into `UsageData::check`.
+fn tally_check(config: &UsageConfig, check: &dyn Provider) -> Result<UsageData> {
+    let tally = Arc::new(Tally::new());
+    let mut tracked = config.clone();
+    tracked.observers.push(tally.clone());
+    let result = check.check(&tracked);
+    let stats = tally
+        .stats
+        .lock()
+        .map(|stats| stats.clone())
+        .unwrap_or_default();
+    match result {
+        Ok(mut data) => {
+            let (partial, source) = (data.check.partial, data.check.source);
+            data.check = stats;
+            data.check.partial = partial;
+            data.check.source = source;
+            Ok(data)
+        }
+        Err(error) => Err(CheckFailure {
+            attempts: stats.log,
+            error,
+        }
+        .into()),
+    }
+}
+
+/// Counts a check's events into `CheckStats` and logs each attempt with the
+/// warnings raised during it.
+struct Tally {
+    started: Instant,
+    stats: Mutex<CheckStats>,
+}
+
+impl Tally {
+    fn new() -> Self {
+        let mut stats = CheckStats::default();
+        stats.log.push(Attempt {
+            kind: AttemptKind::Initial,
+            reason: "initial",
+            started_ms: 0,
+            warnings: Vec::new(),
+        });
+        Tally {
+            started: Instant::now(),
+            stats: Mutex::new(stats),
+        }
+    }
+}
+
+impl observer::CheckObserver for Tally {
+    fn on_dialog(&self, _provider: &str, kind: &DialogKind, dismissed: bool) {
+        if let (true, Ok(mut stats)) = (dismissed, self.stats.lock()) {
+            stats.record(&CheckEvent::DialogDismissed(kind.clone()));
+        }
+    }
+
+    fn on_retry(&self, _provider: &str, reason: &'static str, fallback: bool) {
+        let (event, kind) = if fallback {
+            (CheckEvent::Fallback(reason), AttemptKind::Fallback)
+        } else {
+            (CheckEvent::Retry(reason), AttemptKind::Retry)
+        };
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.record(&event);
+            stats.log.push(Attempt {
+                kind,
+                reason,
+                started_ms: self.started.elapsed().as_millis() as u64,
+                warnings: Vec::new(),
+            });
+        }
+    }
+
+    fn on_warning(&self, _provider: &str, message: &str) {
+        if let Ok(mut stats) = self.stats.lock() {
+            if let Some(attempt) = stats.log.last_mut() {
+                if !attempt.warnings.iter().any(|w| w == message) {
+                    attempt.warnings.push(message.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Merge two parses of the same check, keyed by entry label.  Entries in
+/// `fresh` win; entries only `stale` saw (scrolled off, or not yet drawn) are
+/// kept and placed after the entry that preceded them in `stale`.
+fn merge_captures(fresh: UsageData, stale: UsageData) -> UsageData {
+    let mut merged = fresh;
+    merged.extra_usage_enabled = merged.extra_usage_enabled.or(stale.extra_usage_enabled);
+    let mut insert_at = 0;
+    for entry in stale.entries {
+        match merged.entries.iter().position(|e| e.label == entry.label) {
+            Some(idx) => insert_at = idx + 1,
+            None => {
+                merged.entries.insert(insert_at, entry);
+                insert_at += 1;
+            }
+        }
+    }
+    merged
+}
+
+/// How far apart, in percentage points, two captures of a limit may be
+/// before the limit is flagged `inconsistent`.
+const INCONSISTENT_POINTS: u32 = 3;
+
+/// Flag entries of `settled` that `early` read as more than
+/// `INCONSISTENT_POINTS` apart, keeping the settled values.  Returns the
+/// labels flagged.
+fn cross_check(settled: &mut UsageData, early: &UsageData) -> Vec<String> {
+    let mut flagged = Vec::new();
+    for entry in &mut settled.entries {
+        let Some(other) = early.entries.iter().find(|e| e.label == entry.label) else {
+            continue;
+        };
+        if entry.percent_used.abs_diff(other.percent_used) > INCONSISTENT_POINTS {
+            entry.inconsistent = true;
+            flagged.push(format!(
+                "{} ({}% then {}% used)",
+                entry.label, other.percent_used, entry.percent_used
+            ));
+        }
+    }
+    flagged
+}
+
+/// Width used when retrying a capture whose usage rows were cut off.
+const WIDE_COLS: u16 = 320;
+
+// Data-ready checks, matched against every poll while waiting for usage.
+static CLAUDE_DATA_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d+(?:\.\d+)?%\s*(?:of\s+[\w\s'’]{1,40}?\s+)?used").unwrap());
+static CODEX_DATA_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d+%\s*(left|used)").unwrap());
+static GEMINI_DATA_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\d+(?:\.\d+)?%\s*\(?resets?\b").unwrap());
+// Qwen OAuth's free tier counts requests against a daily quota.
+static QWEN_DATA_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:\d+(?:\.\d+)?%|[\d,]+\s*/\s*[\d,]+(?:\s+requests?)?)\s*\(?resets?\b")
+        .unwrap()
+});
+static COPILOT_DATA_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)premium requests?\b.*?(?:[\d,]+\s*/\s*[\d,]+|\d+(?:\.\d+)?\s*%|unlimited)")
+        .unwrap()
+});
+static AMAZONQ_DATA_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:agentic requests?|code transformations?)\b[\s:]*(?:[\d,]+\s*/\s*[\d,]+|\d+(?:\.\d+)?\s*%|unlimited)",
+    )
+    .unwrap()
+});
+static AIDER_DATA_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)[\d,]+\s+tokens\s+total").unwrap());
+static CURSOR_DATA_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:usage|requests)\b[\s:█░]*(?:\$[\d,.]+\s*/|[\d,]+\s*/\s*[\d,]+|\d+(?:\.\d+)?\s*%)",
+    )
+    .unwrap()
+});
+
+/// Widen the PTY and capture again after usage rows were cut off at the
+/// terminal edge.  Full-screen TUIs redraw on SIGWINCH; CLIs that print
+/// inline need `resend` to re-issue the usage command.  Returns only the
+/// output produced after the resize, or None if nothing new arrived.
+fn recapture_wider(
+    session: &mut Session,
+    provider: &str,
+    resend: Option<&str>,
+    config: &UsageConfig,
+) -> Result<Option<String>> {
+    config.warn(
+        provider,
+        &format!(
+            "Usage rows look truncated, retrying at {} columns",
+            WIDE_COLS
+        ),
+    );
+    let poll_interval = config.poll_interval(provider);
+    let verbose = config.verbose;
+    let mark = session.output_mark()?;
+    session.resize(WIDE_COLS, pty::DEFAULT_ROWS)?;
+    std::thread::sleep(Duration::from_millis(250));
+    if let Some(command) = resend {
+        session.send_keys_literal(command)?;
+        std::thread::sleep(Duration::from_millis(500));
+        session.send_keys("Enter")?;
+    }
+    let _ = session.wait_for_stable(Duration::from_secs(3), poll_interval, verbose);
+
+    let fresh = session.capture_since(mark)?;
+    Ok((!fresh.trim().is_empty()).then_some(fresh))
+}
+
+/// Last resort when no prompt heuristic matched: press Esc, which an idle
+/// composer answers with a redraw (its "Esc again" hint) and which can't
+/// submit anything.  A response without a dialog on screen means Codex is
+/// taking input.
+fn codex_esc_probe(session: &mut Session, config: &UsageConfig) -> Result<bool> {
+    config.emit("codex", CheckEvent::Retry("esc_probe"));
+    let before = session.capture_pane()?;
+    session.send_keys("Esc")?;
+    if !session.wait_for_output(Duration::from_secs(2)) {
+        return Ok(false);
+    }
+    let _ = session.wait_for_stable(
+        Duration::from_millis(500),
+        config.poll_interval("codex"),
+        config.verbose,
+    );
+    let after = session.capture_pane()?;
+    let ready = after != before && detect_codex_dialog(&after).is_none();
+    if ready {
+        config.warn(
+            "codex",
+            "No prompt footer found, but Codex answered Esc; continuing",
+        );
+    }
+    Ok(ready)
+}
+
+fn looks_like_codex_update_prompt(content: &str) -> bool {
+    let lower = content.to_lowercase();
+    lower.contains("update available") && lower.contains("codex")
+}
+
+fn content_tail(content: &str, max_chars: usize) -> String {
+    let mut chars: Vec<char> = content.chars().rev().take(max_chars).collect();
+    chars.reverse();
+    chars.into_iter().collect()
+}
+
+fn normalized_no_whitespace_lower(content: &str) -> String {
+    content
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Check whether the Gemini CLI pane content indicates the prompt is
+/// actually ready for input.  Only matches patterns that appear once the
+/// CLI is interactive — startup-only text (identity headers, dialog
+/// screens, banners) is intentionally excluded and handled separately by
+/// the dialog-checking poll loop in `run_gemini`.
+fn gemini_prompt_ready(content: &str) -> bool {
+    // Legacy patterns (case-sensitive originals)
+    if content.contains("GEMINI.md")
+        || content.contains("MCP servers")
+        || content.contains("gemini >")
+    {
+        return true;
+    }
+
+    let lower = content.to_lowercase();
+
+    // Legacy patterns (case-insensitive variants)
+    if lower.contains("gemini.md") || lower.contains("mcp servers") {
+        return true;
+    }
+
+    // Ready indicator
+    if lower.contains("what can i help") {
+        return true;
+    }
+
+    // Bare `>` at line start (strict: entire trimmed line or `> ` prefix)
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed == ">" || trimmed.starts_with("> ") {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Qwen Code keeps Gemini CLI's prompt, loading QWEN.md instead of
+/// GEMINI.md.
+fn qwen_prompt_ready(content: &str) -> bool {
+    content.to_lowercase().contains("qwen.md") || gemini_prompt_ready(content)
+}
+
+/// Copilot's composer shows a "/ for commands" placeholder under a `>`
+/// prompt once it takes input.
+fn copilot_prompt_ready(content: &str) -> bool {
+    let lower = content.to_lowercase();
+    if lower.contains("for commands") || lower.contains("to mention files") {
+        return true;
+    }
+    content.lines().any(|line| {
+        let trimmed = line.trim().trim_matches('│').trim();
+        trimmed == ">" || trimmed == "❯"
+    })
+}
+
+/// `cursor-agent`'s composer shows a "Plan, search, build anything"
+/// placeholder behind a `→` prompt once it takes input.
+fn cursor_prompt_ready(content: &str) -> bool {
+    let lower = content.to_lowercase();
+    if lower.contains("plan, search, build") || lower.contains("add a follow-up") {
+        return true;
+    }
+    content
+        .lines()
+        .any(|line| line.trim().trim_matches('│').trim() == "→")
+}
+
+/// `q chat` draws a `>` prompt under a "/help all commands" hint; with
+/// every tool trusted the prompt is `!>`.
+fn amazonq_prompt_ready(content: &str) -> bool {
+    let lower = content.to_lowercase();
+    if lower.contains("/help all commands") || lower.contains("ctrl + j new lines") {
+        return true;
+    }
+    content.lines().any(|line| {
+        let trimmed = line.trim();
+        trimmed == ">" || trimmed == "!>"
+    })
+}
+
+/// Aider's prompt is a bare `>` (`ask>`, `architect>` in other chat
+/// modes), drawn once the model and repository are loaded.
+fn aider_prompt_ready(content: &str) -> bool {
+    content.lines().any(|line| {
+        line.trim()
+            .strip_suffix('>')
+            .is_some_and(|mode| mode.chars().all(|c| c.is_ascii_alphabetic()))
+    })
+}
+
+fn claude_prompt_ready(content: &str) -> bool {
+    let t = content.trim();
+    t.contains('>') || t.contains('❯') || t.contains("Tips")
+}
+
+pub fn run_claude(config: &UsageConfig) -> Result<UsageData> {
+    tracked_check(config, &CLAUDE_PROVIDER)
+}
+
+fn check_claude(config: &UsageConfig) -> Result<UsageData> {
+    let mut data = check_sources(config, "claude", check_claude_tui)?;
+    // The transcripts hold tokens but no limits, so they ride along with
+    // whichever source found the limits rather than standing in for one.
+    if let Ok(logs) = crate::claude_logs::read() {
+        data.tokens = logs.tokens;
+    }
+    Ok(data)
+}
+
+fn check_claude_tui(config: &UsageConfig) -> Result<UsageData> {
+    config.report_phase("claude", CheckPhase::Launching);
+    check_command_exists(CLAUDE.binary)?;
+
+    // A pooled session that won't come back to the prompt is dropped for
+    // a fresh launch.
+    let warm = config
+        .pool
+        .as_ref()
+        .and_then(|pool| pool.take("claude"))
+        .and_then(|session| reset_claude_screen(session, config).ok());
+    let mut session = match warm {
+        Some(session) => session,
+        None => launch_claude(config)?,
+    };
+    let result = fetch_claude_usage(&mut session, config);
+    let result = salvage_partial(result, &mut session, config, "claude", parse_claude_output);
+    // Only a session that just showed usage is known to be in a usable state.
+    if let (Some(pool), Ok(_)) = (&config.pool, &result) {
+        pool.checkin("claude", session);
+    }
+    result
+}
+
+/// Launch Claude and wait until its prompt is up, dismissing a startup
+/// dialog on the way.
+fn launch_claude(config: &UsageConfig) -> Result<Session> {
+    let mut session = start_session(&CLAUDE, config)?;
+    configure_session(&mut session, config)?;
+    let poll_interval = config.poll_interval("claude");
+    let prompt_timeout = config.prompt_timeout("claude");
+
+    config.report_phase("claude", CheckPhase::WaitingForPrompt);
+
+    let prompt_result = session.wait_for(
+        claude_prompt_ready,
+        prompt_timeout,
+        poll_interval,
+        true,
+        config.verbose,
+    );
+
+    if let Err(e) = prompt_result {
+        // Check for dialogs before giving up
+        if handle_dialog_check(&mut session, detect_claude_dialog, "claude", config)? {
+            // Dialog dismissed, retry waiting for prompt
+            session
+                .wait_for(
+                    claude_prompt_ready,
+                    prompt_timeout,
+                    poll_interval,
+                    true,
+                    config.verbose,
+                )
+                .context(
+                    "[timeout] Timed out waiting for Claude prompt after dismissing dialog.",
+                )?;
+        } else {
+            return Err(e.context(
+                "Timed out waiting for Claude prompt. Is claude authenticated? Try running 'claude' manually."
+            ));
+        }
+    }
+
+    // Wait for TUI to stabilize instead of fixed sleep
+    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
+    Ok(session)
+}
+
+/// Close the usage dialog a pooled Claude session was left on and wait for
+/// the prompt, so the next check doesn't read the previous one's screen.
+fn reset_claude_screen(mut session: Session, config: &UsageConfig) -> Result<Session> {
+    session.set_deadline(config.deadline);
+    session.send_keys("Esc")?;
+    session.wait_for(
+        |content| claude_prompt_ready(content) && !CLAUDE_DATA_RE.is_match(content),
+        config.prompt_timeout("claude"),
+        config.poll_interval("claude"),
+        false,
+        config.verbose,
+    )?;
+    Ok(session)
+}
+
+/// Ask a ready Claude TUI for usage and parse it.
+fn fetch_claude_usage(session: &mut Session, config: &UsageConfig) -> Result<UsageData> {
+    let poll_interval = config.poll_interval("claude");
+    let data_timeout = config.data_timeout("claude");
+
+    config.capture("claude", "prompt", &session.capture_pane()?);
+
+    // Claude's newer UI is most stable via `/usage`; `/status` now opens a tabbed screen
+    // where `Config` may be selected first.
+    session.send_keys("Esc")?;
+    std::thread::sleep(Duration::from_millis(120));
+    session.send_keys_literal(CLAUDE.command)?;
+    std::thread::sleep(Duration::from_millis(250));
+    session.send_keys("Enter")?;
+
+    config.report_phase("claude", CheckPhase::FetchingUsage);
+
+    let pct_re = &*CLAUDE_DATA_RE;
+    let usage_start = std::time::Instant::now();
+    let mut last_enter = usage_start
+        .checked_sub(Duration::from_secs(1))
+        .unwrap_or(usage_start);
+    let mut content = String::new();
+    let mut last_hash = None;
+    let mut usage_ready = false;
+
+    while usage_start.elapsed() < data_timeout {
+        content = session.capture_pane()?;
+        let hash = content_hash(&content);
+
+        // An unchanged screen already failed every check below last poll.
+        if last_hash != Some(hash) {
+            last_hash = Some(hash);
+            let normalized = normalized_no_whitespace_lower(&content);
+
+            if pct_re.is_match(&content) {
+                usage_ready = true;
+                break;
+            }
+
+            if claude_usage_managed_by_org(&content) {
+                bail!(
+                    "[parse-failure] Claude shows no usage limits for this seat; they are \
+                     managed by your organization's admin."
+                );
+            }
+
+            // If Claude opened a prompt/menu (update/auth/etc), handle it and keep going.
+            if handle_dialog_check(session, detect_claude_dialog, "claude", config)? {
+                last_hash = None;
+                std::thread::sleep(Duration::from_millis(250));
+                continue;
+            }
+
+            // Command palette hint rows sometimes require one more Enter to execute `/usage`.
+            if normalized.contains("showplanusagelimits")
+                || normalized.contains("showplan")
+                || normalized.contains("/usage")
+            {
+                session.send_keys("Enter")?;
+                last_enter = std::time::Instant::now();
+                last_hash = None;
+                std::thread::sleep(Duration::from_millis(180));
+                continue;
+            }
+        }
+
+        // Nudge the TUI occasionally while waiting for usage panels to render.
+        if last_enter.elapsed() >= Duration::from_millis(850) {
+            session.send_keys("Enter")?;
+            last_enter = std::time::Instant::now();
+        }
+
+        session.wait_for_output(poll_interval);
+    }
+
+    if !usage_ready {
+        config.warn(
+            "claude",
+            "/usage did not render in time; falling back to /status usage tab navigation",
+        );
+        config.emit("claude", CheckEvent::Fallback("usage_fallback_status"));
+        session.send_keys("Esc")?;
+        std::thread::sleep(Duration::from_millis(120));
+        session.send_keys_literal("/status")?;
+        std::thread::sleep(Duration::from_millis(300));
+        session.send_keys("Enter")?;
+
+        // Wait for the status screen tab bar and then move right toward Usage.
+        session
+            .wait_for(
+                |content| {
+                    let tail = content_tail(content, 4000);
+                    tail.contains("Status") && tail.contains("Config") && tail.contains("Usage")
+                },
+                Duration::from_secs(15),
+                poll_interval,
+                false,
+                config.verbose,
+            )
+            .context("[timeout] Timed out waiting for status screen")?;
+
+        for _ in 0..4 {
+            let screen = session.capture_pane()?;
+            if pct_re.is_match(&screen) {
+                content = screen;
+                usage_ready = true;
+                break;
+            }
+            session.send_keys("Right")?;
+            std::thread::sleep(Duration::from_millis(250));
+        }
+
+        if !usage_ready {
+            content = session
+                .wait_for(
+                    |screen| pct_re.is_match(screen),
+                    data_timeout,
+                    poll_interval,
+                    false,
+                    config.verbose,
+                )
+                .context(
+                    "[timeout] Timed out waiting for usage data. Check your internet connection.",
+                )?;
+        }
+    }
+
+    let data = finish_capture(
+        session,
+        config,
+        "claude",
+        &content,
+        &parse_claude_output,
+        None,
+    )?;
+    require_entries(scroll_claude_usage(session, config, data)?)
+}
+
+/// Once usage has shown up in `early`, let the screen settle, capture it
+/// again, and merge both parses, retrying wider (re-sending `resend`, if
+/// given) when rows were cut off at the terminal edge.  Where the two
+/// parses disagree, the settled capture's value is kept and the entry is
+/// flagged `inconsistent`.
+fn finish_capture(
+    session: &mut Session,
+    config: &UsageConfig,
+    provider: &str,
+    early: &str,
+    parse: &dyn Fn(&str) -> Result<UsageData>,
+    resend: Option<&str>,
+) -> Result<UsageData> {
+    let poll_interval = config.poll_interval(provider);
+    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
+
+    let final_content = session.capture_pane()?;
+
+    config.capture(provider, "final", &final_content);
+
+    let mut settled = parse(&final_content)?;
+    let early = parse(early)?;
+    let flagged = cross_check(&mut settled, &early);
+    if !flagged.is_empty() {
+        config.warn(
+            provider,
+            &format!(
+                "Early and final captures disagree on {}; using the final values",
+                flagged.join(", ")
+            ),
+        );
+    }
+    let mut data = merge_captures(settled, early);
+
+    if has_truncated_rows(&final_content, provider) {
+        config.emit(provider, CheckEvent::Retry("recapture_wider"));
+        if let Some(wide) = recapture_wider(session, provider, resend, config)? {
+            data = merge_captures(parse(&wide)?, data);
+        }
+    }
+    Ok(data)
+}
+
+fn require_entries(data: UsageData) -> Result<UsageData> {
+    if data.entries.is_empty() {
+        bail!("[parse-failure] No usage data found in captured output. Run with --verbose to see raw text.");
+    }
+    Ok(data)
+}
+
+/// Under `allow_partial`, turn a timed-out data wait into the entries
+/// parsed from everything captured so far, marked `check.partial`.  Other
+/// errors, and timeouts with nothing parsed, are returned unchanged.
+fn salvage_partial(
+    result: Result<UsageData>,
+    session: &mut Session,
+    config: &UsageConfig,
+    provider: &str,
+    parse: impl Fn(&str) -> Result<UsageData>,
+) -> Result<UsageData> {
+    let err = match result {
+        Ok(data) => return Ok(data),
+        Err(err) => err,
+    };
+    if !config.allow_partial || !format!("{:#}", err).contains("[timeout]") {
+        return Err(err);
+    }
+    let mut data = match parse(&session.captured_text()) {
+        Ok(data) if !data.entries.is_empty() => data,
+        _ => return Err(err),
+    };
+    config.warn(
+        provider,
+        &format!(
+            "Returning {} partial entries: {}",
+            data.entries.len(),
+            strip_error_tags(&format!("{:#}", err))
+        ),
+    );
+    data.check.partial = true;
+    Ok(data)
+}
+
+/// `message` without the exit-code tags (`[timeout]` and the like), for
+/// showing to users.
+pub fn strip_error_tags(message: &str) -> String {
+    message
+        .replace("[tool-missing] ", "")
+        .replace("[timeout] ", "")
+        .replace("[parse-failure] ", "")
+}
+
+/// Most extra pages read from Claude's usage screen.
+const MAX_USAGE_PAGES: usize = 4;
+
+// The hint line under Claude's settings dialog ("Esc to cancel").
+static CLAUDE_USAGE_FOOTER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^esc to (cancel|close|exit)\b").unwrap());
+
+/// Whether the usage screen already shows its end: the last line is the
+/// dialog's footer hint or its closing border, so there's nothing below.
+fn claude_usage_at_end(screen: &str) -> bool {
+    screen
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .is_some_and(|line| line.starts_with('╰') || CLAUDE_USAGE_FOOTER_RE.is_match(line))
+}
+
+/// Newer Claude builds paginate the usage screen or leave rows below the
+/// fold.  Probe for more with PageDown, then Down, and (when an Opus limit
+/// is mentioned but wasn't parsed) Tab to its tab, adding any new entries.
+/// Stops as soon as no key redraws anything or a page adds nothing.  A
+/// screen that already ends with the footer or closing border skips the
+/// scroll keys, so the usual check pays nothing for the probe.
+fn scroll_claude_usage(
+    session: &mut Session,
+    config: &UsageConfig,
+    mut data: UsageData,
+) -> Result<UsageData> {
+    let poll_interval = config.poll_interval("claude");
+    for _ in 0..MAX_USAGE_PAGES {
+        let before = session.capture_pane()?;
+        let mark = session.output_mark()?;
+        let mut keys = if claude_usage_at_end(&before) {
+            vec![]
+        } else {
+            vec!["PageDown", "Down"]
+        };
+        if before.contains("Opus") && !data.entries.iter().any(|e| e.label.contains("Opus")) {
+            keys.push("Tab");
+        }
+        if keys.is_empty() {
+            break;
+        }
+        let mut after = None;
+        for key in keys {
+            session.send_keys(key)?;
+            if !session.wait_for_output(Duration::from_millis(400)) {
+                continue;
+            }
+            let _ =
+                session.wait_for_stable(Duration::from_millis(600), poll_interval, config.verbose);
+            if !session.capture_since(mark)?.trim().is_empty() {
+                after = Some(session.captured_text());
+                break;
+            }
+        }
+        let Some(after) = after else { break };
+        // Parse the whole capture, not just the new page, so a row split
+        // across the page boundary still parses.
+        let known = data.entries.len();
+        data = merge_captures(data, parse_claude_output(&after)?);
+        if data.entries.len() == known {
+            break;
+        }
+        config.capture("claude", "page", &after);
+        config.emit("claude", CheckEvent::Retry("scroll_usage"));
+    }
+    Ok(data)
+}
+
+pub fn run_codex(config: &UsageConfig) -> Result<UsageData> {
+    tracked_check(config, &CODEX_PROVIDER)
+}
+
+fn check_codex(config: &UsageConfig) -> Result<UsageData> {
+    check_sources(config, "codex", check_codex_tui)
+}
+
+/// Read `provider` from the first source in its chain that works; the
+/// chain ends with `tui`, its usual check.  Sources the provider doesn't
+/// have are skipped, and each one that fails is reported as a warning and
+/// a fallback event before the next is tried.
+fn check_sources(
+    config: &UsageConfig,
+    provider: &str,
+    tui: fn(&UsageConfig) -> Result<UsageData>,
+) -> Result<UsageData> {
+    for source in config.source_for(provider).chain() {
+        let (attempt, what, reason) = match source {
+            DataSource::Tui => return tui(config),
+            DataSource::Api => (
+                fetch_usage_api(config, provider),
+                "Usage API",
+                "api_unavailable",
+            ),
+            DataSource::Local => (
+                read_usage_logs(config, provider),
+                "Session logs",
+                "logs_unavailable",
+            ),
+        };
+        match attempt {
+            None => continue,
+            Some(Ok(data)) => return Ok(data),
+            Some(Err(e)) => {
+                config.warn(
+                    provider,
+                    &format!(
+                        "{} unavailable ({}), trying the next source",
+                        what,
+                        e.to_string().lines().next().unwrap_or_default()
+                    ),
+                );
+                config.emit(provider, CheckEvent::Fallback(reason));
+            }
+        }
+    }
+    tui(config)
+}
+
+/// `provider`'s usage from its HTTP API, or `None` if it has none.
+#[cfg(feature = "http")]
+fn fetch_usage_api(config: &UsageConfig, provider: &str) -> Option<Result<UsageData>> {
+    let fetch = match provider {
+        "claude" => crate::claude_api::fetch,
+        "codex" => crate::codex_api::fetch,
+        _ => return None,
+    };
+    config.report_phase(provider, CheckPhase::FetchingUsage);
+    let mut timeout = config.data_timeout(provider);
+    if let Some(deadline) = config.deadline {
+        timeout = timeout.min(deadline.saturating_duration_since(Instant::now()));
+    }
+    Some(fetch(timeout))
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_usage_api(_config: &UsageConfig, provider: &str) -> Option<Result<UsageData>> {
+    matches!(provider, "claude" | "codex").then(|| Err(anyhow!("built without the `http` feature")))
+}
+
+/// `provider`'s usage from the session logs it writes, or `None` if it
+/// logs none.
+fn read_usage_logs(config: &UsageConfig, provider: &str) -> Option<Result<UsageData>> {
+    let read = match provider {
+        "codex" => crate::codex_logs::read,
+        _ => return None,
+    };
+    config.report_phase(provider, CheckPhase::FetchingUsage);
+    Some(read())
+}
+
+fn check_codex_tui(config: &UsageConfig) -> Result<UsageData> {
+    config.report_phase("codex", CheckPhase::Launching);
+    check_command_exists(CODEX.binary)?;
+
+    let mut session = start_session(&CODEX, config)?;
+    configure_session(&mut session, config)?;
+    let poll_interval = config.poll_interval("codex");
+    let prompt_timeout = config.prompt_timeout("codex");
+
+    config.report_phase("codex", CheckPhase::WaitingForPrompt);
+
+    let prompt_result = session.wait_for(
+        codex_prompt_ready,
+        prompt_timeout,
+        poll_interval,
+        false,
+        config.verbose,
+    );
+
+    if let Err(e) = prompt_result {
+        // Check for dialogs before giving up
+        if handle_dialog_check(&mut session, detect_codex_dialog, "codex", config)? {
+            // Dialog dismissed, retry waiting for prompt
+            let retry = session.wait_for(
+                codex_prompt_ready,
+                prompt_timeout,
+                poll_interval,
+                false,
+                config.verbose,
+            );
+            if retry.is_err() && !codex_esc_probe(&mut session, config)? {
+                bail!("[timeout] Timed out waiting for Codex prompt after dismissing dialog.");
+            }
+        } else if !codex_esc_probe(&mut session, config)? {
+            return Err(e.context(
+                "Timed out waiting for Codex prompt. Is codex authenticated? Try running 'codex' manually."
+            ));
+        }
+    }
+
+    // Wait for TUI to stabilize instead of fixed sleep
+    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
+
+    let result = fetch_codex_usage(&mut session, config);
+    salvage_partial(result, &mut session, config, "codex", parse_codex_output)
+}
+
+/// Ask a ready Codex TUI for usage and parse it.
+fn fetch_codex_usage(session: &mut Session, config: &UsageConfig) -> Result<UsageData> {
+    let poll_interval = config.poll_interval("codex");
+    let data_timeout = config.data_timeout("codex");
+
+    config.capture("codex", "prompt", &session.capture_pane()?);
+
+    // Codex /status prints inline — no autocomplete, no tabs
+    session.send_keys_literal(CODEX.command)?;
+    std::thread::sleep(Duration::from_millis(500));
+    session.send_keys("Enter")?;
+
+    config.report_phase("codex", CheckPhase::FetchingUsage);
+
+    // Wait for limit data to appear
+    let limit_re = &*CODEX_DATA_RE;
+    let mut content = session
+        .wait_for(
+            |content| limit_re.is_match(content) || looks_like_codex_update_prompt(content),
+            data_timeout,
+            poll_interval,
+            false,
+            config.verbose,
+        )
+        .context("[timeout] Timed out waiting for Codex usage data.")?;
+
+    if looks_like_codex_update_prompt(&content) && !limit_re.is_match(&content) {
+        config.warn(
+            "codex",
+            "Codex update prompt detected, selecting Skip and retrying /status",
+        );
+        config.emit("codex", CheckEvent::Retry("update_prompt_resend_status"));
+        session.send_keys("Down")?;
+        std::thread::sleep(Duration::from_millis(120));
+        session.send_keys("Enter")?;
+        std::thread::sleep(Duration::from_millis(150));
+        session.send_keys("Enter")?;
+        std::thread::sleep(Duration::from_millis(200));
+        session.send_keys_literal(CODEX.command)?;
+        std::thread::sleep(Duration::from_millis(200));
+        session.send_keys("Enter")?;
+
+        content = session
+            .wait_for(
+                |content| limit_re.is_match(content),
+                data_timeout,
+                poll_interval,
+                false,
+                config.verbose,
+            )
+            .context(
+                "[timeout] Timed out waiting for Codex usage data after dismissing update prompt.",
+            )?;
+    }
+
+    require_entries(finish_capture(
+        session,
+        config,
+        "codex",
+        &content,
+        &parse_codex_output,
+        Some(CODEX.command),
+    )?)
+}
+
+pub fn run_gemini(config: &UsageConfig) -> Result<UsageData> {
+    tracked_check(config, &GEMINI_PROVIDER)
+}
+
+fn check_gemini(config: &UsageConfig) -> Result<UsageData> {
+    let mut data = check_gemini_cli(&GEMINI_PROVIDER, config)?;
+    crate::parser::apply_request_caps(&mut data, &config.gemini_request_caps);
+    Ok(data)
+}
+
+pub fn run_qwen(config: &UsageConfig) -> Result<UsageData> {
+    tracked_check(config, &QWEN_PROVIDER)
+}
+
+fn check_qwen(config: &UsageConfig) -> Result<UsageData> {
+    check_gemini_cli(&QWEN_PROVIDER, config)
+}
+
+/// The check for Gemini CLI and its forks (Qwen Code): the TUI with a
+/// patient startup wait, falling back to a non-interactive run when the
+/// spec has `headless_args`.
+fn check_gemini_cli(provider: &BuiltinProvider, config: &UsageConfig) -> Result<UsageData> {
+    let name = provider.spec.name;
+    config.report_phase(name, CheckPhase::Launching);
+    check_command_exists(provider.spec.binary)?;
+
+    match start_gemini_tui(provider, config)? {
+        TuiStart::Ready(mut session) => {
+            let result = fetch_usage(provider, &mut session, config);
+            salvage_partial(result, &mut session, config, name, provider.parse)
+        }
+        TuiStart::Unavailable(e) => check_gemini_headless(provider, config, e),
+    }
+}
+
+/// How far the interactive TUI got before usage was requested.
+enum TuiStart {
+    Ready(Box<Session>),
+    /// It never became usable (launch failure, no prompt, stuck startup),
+    /// so a non-interactive run is worth trying.
+    Unavailable(anyhow::Error),
+}
+
+/// Launch a Gemini CLI TUI and wait for its prompt.  Errors that no
+/// fallback can fix, like a dialog the policy won't dismiss, are returned
+/// as `Err`.
+fn start_gemini_tui(provider: &BuiltinProvider, config: &UsageConfig) -> Result<TuiStart> {
+    let name = provider.spec.name;
+    let mut session = match start_session(provider.spec, config) {
+        Ok(session) => session,
+        Err(e) => return Ok(TuiStart::Unavailable(e)),
+    };
+    configure_session(&mut session, config)?;
+    let poll_interval = config.poll_interval(name);
+    // Faster polling during the first few seconds of startup.  Ink-based
+    // TUIs (Gemini) may send terminal capability queries (Device Attributes,
+    // cursor position, etc.) early and block until they receive a response.
+    // Polling at 100ms ensures we answer those queries promptly.
+    let fast_poll_interval = Duration::from_millis(100).min(poll_interval);
+    let fast_poll_duration = Duration::from_secs(5);
+    // Gemini v0.28+ has a long auth validation phase (spinners, loading
+    // extensions, etc.) that can easily exceed 30 seconds.  We use the
+    // prompt timeout (the data timeout unless overridden) as the hard ceiling and separately
+    // track "idle time" (no output changes) — if nothing happens for 45s
+    // the CLI is likely stuck, even if the wall-clock timeout hasn't hit.
+    let idle_timeout = Duration::from_secs(45);
+    let max_prompt_timeout = config.prompt_timeout(name);
+
+    // Pump the PTY briefly to answer any immediate terminal queries
+    // (DA1, cursor position, DSR) the Ink TUI sends on startup.
+    for _ in 0..10 {
+        session.capture_pane()?;
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    config.report_phase(name, CheckPhase::WaitingForPrompt);
+
+    // Poll for prompt readiness, handling dialogs as they appear.
+    // Track content changes to distinguish "still starting up" from "stuck".
+    let mut prompt_start = std::time::Instant::now();
+    let mut last_activity = std::time::Instant::now();
+    let mut prev_hash = None;
+
+    loop {
+        let wall_elapsed = prompt_start.elapsed();
+        let idle_elapsed = last_activity.elapsed();
+
+        if wall_elapsed >= max_prompt_timeout || idle_elapsed >= idle_timeout {
+            let pane = session.capture_pane().unwrap_or_default();
+            let tail = content_tail(&pane, 500);
+            return Ok(TuiStart::Unavailable(anyhow!(
+                "[timeout] Timed out waiting for {} prompt. Is {} authenticated? \
+                 Try running '{}' manually.\nLast captured output:\n{}",
+                display_name(name),
+                name,
+                provider.spec.binary,
+                tail
+            )));
+        }
+
+        let content = session.capture_pane()?;
+        let hash = content_hash(&content);
+
+        // Track activity: reset idle timer when content changes.  An
+        // unchanged screen was already checked for the prompt and dialogs.
+        let changed = prev_hash != Some(hash);
+        if changed {
+            if prev_hash.is_some() {
+                config.warn(name, "Startup activity detected, resetting idle timer");
+            }
+            last_activity = std::time::Instant::now();
+            prev_hash = Some(hash);
+        }
+
+        // Check if the actual prompt is visible
+        if changed && (provider.prompt_ready)(&content) {
+            break;
+        }
+
+        // Check for dialogs during startup
+        if let Some(kind) = changed
+            .then(|| (provider.detect_dialog)(&content))
+            .flatten()
+        {
+            config.emit(name, CheckEvent::DialogDetected(kind.clone()));
+            let dismissed = match config.approval_policy {
+                ApprovalPolicy::Fail => false,
+                ApprovalPolicy::Accept | ApprovalPolicy::Onboard => dismiss_and_verify(
+                    &kind,
+                    name,
+                    config.approval_policy,
+                    &mut session,
+                    &provider.detect_dialog,
+                )?,
+            };
+            if !dismissed {
+                resolve_blocked_dialog(&mut session, &kind, name, config)?;
+                // Time spent with the user doesn't count against start-up.
+                prompt_start = std::time::Instant::now();
+            }
+            config.emit(name, CheckEvent::DialogDismissed(kind));
+            last_activity = std::time::Instant::now();
+            prev_hash = None;
+            continue;
+        }
+
+        // Use faster polling during the initial startup phase to respond
+        // to terminal capability queries quickly.
+        let effective_poll = if prompt_start.elapsed() < fast_poll_duration {
+            fast_poll_interval
+        } else {
+            poll_interval
+        };
+        session.wait_for_output(effective_poll);
+    }
+
+    // Gemini v0.28+ shows a "Waiting for auth..." spinner overlay while
+    // re-validating credentials.  The TUI renders the `> ` prompt even
+    // while the overlay is active, so prompt detection fires early.
+    // The spinner animates continuously (changing the captured output), but
+    // once auth completes the TUI becomes static.  Use content stability to
+    // detect auth completion before sending any commands.
+    {
+        let content = session.capture_pane()?;
+        if content.to_lowercase().contains("waiting for auth") {
+            config.warn(name, "Auth spinner detected, waiting for completion");
+            session
+                .wait_for_stable(max_prompt_timeout, poll_interval, config.verbose)
+                .with_context(|| {
+                    format!(
+                        "[timeout] {} auth did not complete in time. \
+                         Try running '{}' manually to check authentication.",
+                        display_name(name),
+                        provider.spec.binary
+                    )
+                })?;
+            config.warn(name, "Auth completed (content stabilized)");
+        } else {
+            // No auth spinner — wait for the TUI to fully settle.
+            let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
+        }
+    }
+
+    Ok(TuiStart::Ready(Box::new(session)))
+}
+
+/// Type `provider`'s usage command at a ready prompt, wait for usage to
+/// render (answering dialogs and re-sending the command after one), and
+/// parse it.
+fn fetch_usage<P: Provider + ?Sized>(
+    provider: &P,
+    session: &mut Session,
+    config: &UsageConfig,
+) -> Result<UsageData> {
+    let name = provider.name();
+    let command = provider.usage_command();
+    let poll_interval = config.poll_interval(name);
+    let data_timeout = config.data_timeout(name);
+
+    config.capture(name, "prompt", &session.capture_pane()?);
+
+    session.send_keys_literal(command)?;
+    std::thread::sleep(Duration::from_millis(500));
+    session.send_keys("Enter")?;
+
+    config.report_phase(name, CheckPhase::FetchingUsage);
+
+    // Wait for usage data to appear, checking for dialogs.
+    let detect = |screen: &str| provider.detect_dialog(screen);
+    let data_start = std::time::Instant::now();
+    let mut content = String::new();
+    let mut last_hash = None;
+    let mut data_ready = false;
+
+    while data_start.elapsed() < data_timeout {
+        content = session.capture_pane()?;
+        let hash = content_hash(&content);
+        if last_hash == Some(hash) {
+            session.wait_for_output(poll_interval);
+            continue;
+        }
+        last_hash = Some(hash);
+
+        if provider.usage_ready(&content) {
+            data_ready = true;
+            break;
+        }
+
+        // Check for dialogs that may have appeared during data wait
+        if handle_dialog_check(session, detect, name, config)? {
+            // Dialog dismissed, re-send the command
+            config.emit(name, CheckEvent::Retry("resend_usage_after_dialog"));
+            session.send_keys_literal(command)?;
+            std::thread::sleep(Duration::from_millis(500));
+            session.send_keys("Enter")?;
+            last_hash = None;
+            std::thread::sleep(Duration::from_millis(250));
+            continue;
+        }
+
+        session.wait_for_output(poll_interval);
+    }
+
+    if !data_ready {
+        let tail = content_tail(&content, 500);
+        bail!(
+            "[timeout] Timed out waiting for {} usage data.\nLast captured output:\n{}",
+            display_name(name),
+            tail
+        );
+    }
+
+    require_entries(finish_capture(
+        session,
+        config,
+        name,
+        &content,
+        &|output| provider.parse(output),
+        Some(command),
+    )?)
+}
+
+/// "gemini" → "Gemini", for messages.
+fn display_name(name: &str) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// The check a registered `Provider` gets unless it overrides `check`:
+/// launch, wait for the prompt (dismissing dialogs), then `fetch_usage`.
+pub(crate) fn check_tui<P: Provider + ?Sized>(
+    provider: &P,
+    config: &UsageConfig,
+) -> Result<UsageData> {
+    let mut session = start_tui(provider, config)?;
+    let result = fetch_usage(provider, &mut session, config);
+    salvage_partial(result, &mut session, config, provider.name(), |output| {
+        provider.parse(output)
+    })
+}
+
+/// Launch `provider` and wait for its prompt, dismissing dialogs the
+/// approval policy allows.
+fn start_tui<P: Provider + ?Sized>(provider: &P, config: &UsageConfig) -> Result<Session> {
+    let name = provider.name();
+    config.report_phase(name, CheckPhase::Launching);
+    check_command_exists(provider.binary())?;
+
+    let args = provider.launch_args(config);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let mut session = launch_session(name, provider.binary(), &args, config)?;
+    configure_session(&mut session, config)?;
+    let poll_interval = config.poll_interval(name);
+    let prompt_timeout = config.prompt_timeout(name);
+
+    config.report_phase(name, CheckPhase::WaitingForPrompt);
+
+    let ready = |screen: &str| provider.prompt_ready(screen);
+    let prompt_result =
+        session.wait_for(ready, prompt_timeout, poll_interval, true, config.verbose);
+    if let Err(e) = prompt_result {
+        let detect = |screen: &str| provider.detect_dialog(screen);
+        if !handle_dialog_check(&mut session, detect, name, config)? {
+            return Err(e.context(format!(
+                "Timed out waiting for the {} prompt. Try running '{}' manually.",
+                display_name(name),
+                provider.binary()
+            )));
+        }
+        session
+            .wait_for(ready, prompt_timeout, poll_interval, true, config.verbose)
+            .with_context(|| {
+                format!(
+                    "[timeout] Timed out waiting for the {} prompt after dismissing dialog.",
+                    display_name(name)
+                )
+            })?;
+    }
+
+    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
+    Ok(session)
+}
+
+/// Run `provider` up to its usage screen and return the screen text,
+/// without parsing it: the raw material for a new parser fixture.  The
+/// screen is taken once usage data is recognized or, for layouts that
+/// aren't, when the data timeout runs out.
+pub fn capture_usage_screen(config: &UsageConfig, provider: &str) -> Result<String> {
+    let Some(provider) = config.providers.get(provider) else {
+        bail!("Unknown provider '{}'", provider);
+    };
+    let provider = provider.as_ref();
+    let name = provider.name();
+    let mut session = start_tui(provider, config)?;
+    let command = provider.usage_command();
+    session.send_keys_literal(command)?;
+    std::thread::sleep(Duration::from_millis(500));
+    session.send_keys("Enter")?;
+    config.report_phase(name, CheckPhase::FetchingUsage);
+
+    let poll_interval = config.poll_interval(name);
+    let data_start = Instant::now();
+    while data_start.elapsed() < config.data_timeout(name) {
+        if provider.usage_ready(&session.capture_pane()?) {
+            break;
+        }
+        session.wait_for_output(poll_interval);
+    }
+    let _ = session.wait_for_stable(Duration::from_secs(2), poll_interval, config.verbose);
+    let screen = session.capture_pane()?;
+    let lines: Vec<&str> = screen.lines().map(str::trim_end).collect();
+    Ok(format!("{}\n", lines.join("\n").trim_matches('\n')))
+}
+
+pub fn run_copilot(config: &UsageConfig) -> Result<UsageData> {
+    tracked_check(config, &COPILOT_PROVIDER)
+}
+
+fn check_copilot(config: &UsageConfig) -> Result<UsageData> {
+    check_tui(&COPILOT_PROVIDER, config)
+}
+
+pub fn run_cursor(config: &UsageConfig) -> Result<UsageData> {
+    tracked_check(config, &CURSOR_PROVIDER)
+}
+
+fn check_cursor(config: &UsageConfig) -> Result<UsageData> {
+    check_tui(&CURSOR_PROVIDER, config)
+}
+
+pub fn run_amazonq(config: &UsageConfig) -> Result<UsageData> {
+    tracked_check(config, &AMAZONQ_PROVIDER)
+}
+
+fn check_amazonq(config: &UsageConfig) -> Result<UsageData> {
+    check_tui(&AMAZONQ_PROVIDER, config)
+}
+
+pub fn run_aider(config: &UsageConfig) -> Result<UsageData> {
+    tracked_check(config, &AIDER_PROVIDER)
+}
+
+fn check_aider(config: &UsageConfig) -> Result<UsageData> {
+    check_tui(&AIDER_PROVIDER, config)
+}
+
+/// Print a Gemini CLI's stats once without the TUI and parse that instead.
+/// If this fails too, both errors are reported, the TUI's first.
+fn check_gemini_headless(
+    provider: &BuiltinProvider,
+    config: &UsageConfig,
+    tui_error: anyhow::Error,
+) -> Result<UsageData> {
+    let name = provider.spec.name;
+    let Some(args) = provider.spec.headless_args else {
+        return Err(tui_error);
+    };
+    config.warn(
+        name,
+        &format!(
+            "Interactive session unavailable ({}), retrying non-interactively",
+            tui_error.to_string().lines().next().unwrap_or_default()
+        ),
+    );
+    config.emit(name, CheckEvent::Fallback("gemini_non_interactive"));
+    config.report_phase(name, CheckPhase::FetchingUsage);
+
+    let mut timeout = config.data_timeout(name);
+    if let Some(deadline) = config.deadline {
+        timeout = timeout.min(deadline.saturating_duration_since(Instant::now()));
+    }
+    let attempt = headless::run(provider.spec, args, config, timeout).and_then(|output| {
+        config.capture(name, "headless", &output);
+        let data = (provider.parse)(&output)?;
+        if data.entries.is_empty() {
+            bail!(
+                "[parse-failure] No usage data in non-interactive output:\n{}",
+                content_tail(&output, 500)
+            );
+        }
+        Ok(data)
+    });
+    attempt.map_err(|fallback| {
+        tui_error.context(format!(
+            "Non-interactive fallback also failed: {:#}",
+            fallback
+        ))
+    })
+}
+
+/// Run the check for `provider` by name: "claude", "codex", "gemini",
+/// "copilot", "cursor", "qwen", "amazonq", "aider", or one registered on `config.providers`.
+pub fn run_provider(config: &UsageConfig, provider: &str) -> Result<UsageData> {
+    let Some(check) = config.providers.get(provider) else {
+        bail!("Unknown provider '{}'", provider);
+    };
+    tracked_check(config, check.as_ref())
+}
+
+/// A built-in provider: its launch spec, its screen hooks, and the check
+/// flow this module tunes for it.
+struct BuiltinProvider {
+    spec: &'static ProviderSpec,
+    prompt_ready: fn(&str) -> bool,
+    usage_ready: fn(&str) -> bool,
+    detect_dialog: fn(&str) -> Option<DialogKind>,
+    parse: fn(&str) -> Result<UsageData>,
+    check: fn(&UsageConfig) -> Result<UsageData>,
+    capabilities: Capabilities,
+}
+
+/// What a built-in parser can report: `spent`, `requests`, and
+/// `reset_minutes`.
+const fn parses(spend: bool, requests: bool, reset_minutes: bool) -> Capabilities {
+    Capabilities {
+        supports_spend: spend,
+        supports_requests: requests,
+        supports_reset_minutes: reset_minutes,
+        source: DataSource::Tui,
+    }
+}
+
+impl Provider for BuiltinProvider {
+    fn name(&self) -> &str {
+        self.spec.name
+    }
+
+    fn binary(&self) -> &str {
+        self.spec.binary
+    }
+
+    fn launch_args(&self, config: &UsageConfig) -> Vec<String> {
+        self.spec
+            .launch_args(config)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    fn prompt_ready(&self, screen: &str) -> bool {
+        (self.prompt_ready)(screen)
+    }
+
+    fn usage_command(&self) -> &str {
+        self.spec.command
+    }
+
+    fn usage_ready(&self, screen: &str) -> bool {
+        (self.usage_ready)(screen)
+    }
+
+    fn detect_dialog(&self, screen: &str) -> Option<DialogKind> {
+        (self.detect_dialog)(screen)
+    }
+
+    fn parse(&self, output: &str) -> Result<UsageData> {
+        (self.parse)(output)
+    }
+
+    fn check(&self, config: &UsageConfig) -> Result<UsageData> {
+        (self.check)(config)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+}
+
+const CLAUDE_PROVIDER: BuiltinProvider = BuiltinProvider {
+    spec: &CLAUDE,
+    prompt_ready: claude_prompt_ready,
+    usage_ready: |screen| CLAUDE_DATA_RE.is_match(screen),
+    detect_dialog: detect_claude_dialog,
+    parse: parse_claude_output,
+    check: check_claude,
+    capabilities: parses(true, false, true),
+};
+
+const CODEX_PROVIDER: BuiltinProvider = BuiltinProvider {
+    spec: &CODEX,
+    prompt_ready: codex_prompt_ready,
+    usage_ready: |screen| CODEX_DATA_RE.is_match(screen),
+    detect_dialog: detect_codex_dialog,
+    parse: parse_codex_output,
+    check: check_codex,
+    capabilities: parses(false, false, true),
+};
+
+const GEMINI_PROVIDER: BuiltinProvider = BuiltinProvider {
+    spec: &GEMINI,
+    prompt_ready: gemini_prompt_ready,
+    usage_ready: |screen| GEMINI_DATA_RE.is_match(screen),
+    detect_dialog: detect_gemini_dialog,
+    parse: parse_gemini_output,
+    check: check_gemini,
+    capabilities: parses(false, true, true),
+};
+
+const COPILOT_PROVIDER: BuiltinProvider = BuiltinProvider {
+    spec: &COPILOT,
+    prompt_ready: copilot_prompt_ready,
+    usage_ready: |screen| COPILOT_DATA_RE.is_match(screen),
+    detect_dialog: detect_copilot_dialog,
+    parse: parse_copilot_output,
+    check: check_copilot,
+    capabilities: parses(false, true, true),
+};
+
+const CURSOR_PROVIDER: BuiltinProvider = BuiltinProvider {
+    spec: &CURSOR,
+    prompt_ready: cursor_prompt_ready,
+    usage_ready: |screen| CURSOR_DATA_RE.is_match(screen),
+    detect_dialog: detect_cursor_dialog,
+    parse: parse_cursor_output,
+    check: check_cursor,
+    capabilities: parses(true, true, true),
+};
+
+const QWEN_PROVIDER: BuiltinProvider = BuiltinProvider {
+    spec: &QWEN,
+    prompt_ready: qwen_prompt_ready,
+    usage_ready: |screen| QWEN_DATA_RE.is_match(screen),
+    detect_dialog: detect_qwen_dialog,
+    parse: parse_qwen_output,
+    check: check_qwen,
+    capabilities: parses(false, true, true),
+};
+
+const AMAZONQ_PROVIDER: BuiltinProvider = BuiltinProvider {
+    spec: &AMAZONQ,
+    prompt_ready: amazonq_prompt_ready,
+    usage_ready: |screen| AMAZONQ_DATA_RE.is_match(screen),
+    detect_dialog: detect_amazonq_dialog,
+    parse: parse_amazonq_output,
+    check: check_amazonq,
+    capabilities: parses(false, true, true),
+};
+
+const AIDER_PROVIDER: BuiltinProvider = BuiltinProvider {
+    spec: &AIDER,
+    prompt_ready: aider_prompt_ready,
+    usage_ready: |screen| AIDER_DATA_RE.is_match(screen),
+    detect_dialog: detect_aider_dialog,
+    parse: parse_aider_output,
+    check: check_aider,
+    capabilities: parses(false, true, false),
+};
+
+/// The built-in providers, in `ALL_PROVIDERS` order.
+pub(crate) fn builtin_providers() -> Vec<Arc<dyn Provider>> {
+    vec![
+        Arc::new(CLAUDE_PROVIDER),
+        Arc::new(CODEX_PROVIDER),
+        Arc::new(GEMINI_PROVIDER),
+        Arc::new(COPILOT_PROVIDER),
+        Arc::new(CURSOR_PROVIDER),
+        Arc::new(QWEN_PROVIDER),
+        Arc::new(AMAZONQ_PROVIDER),
+        Arc::new(AIDER_PROVIDER),
+    ]
+}
+
+/// Check `providers` in parallel, yielding each result as soon as that
+/// provider finishes (fastest first) instead of waiting for the slowest.
+/// Checks still running when the iterator is dropped finish in the
+/// background.  Under `UsageConfig::sequential` they run one at a time in
+/// the given order, and dropping the iterator stops after the current one.
+pub fn run_streaming(
+    config: &UsageConfig,
+    providers: &[&str],
+) -> impl Iterator<Item = ProviderResult> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let started = Instant::now();
+    let check = move |config: &UsageConfig, provider: String| {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run_provider(config, &provider)
+        }))
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("Provider thread panicked")));
+        ProviderResult {
+            provider,
+            result,
+            elapsed: started.elapsed(),
+        }
+    };
+    if config.sequential {
+        let config = config.clone();
+        let providers: Vec<String> = providers.iter().map(|p| p.to_string()).collect();
+        std::thread::spawn(move || {
+            for provider in providers {
+                if tx.send(check(&config, provider)).is_err() {
+                    break;
+                }
+            }
+        });
+        return rx.into_iter();
+    }
+    for provider in providers {
+        let tx = tx.clone();
+        let config = config.clone();
+        let provider = provider.to_string();
+        std::thread::spawn(move || {
+            let _ = tx.send(check(&config, provider));
+        });
+    }
+    rx.into_iter()
+}
+
+/// Check every provider on `config.providers` in parallel.
+pub fn run_all(config: &UsageConfig) -> AllResults {
+    let providers = config.providers.names();
+    collect_results(&providers, run_streaming(config, &providers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Confidence, LimitScope, LimitWindow, PercentKind, UsageEntry, ALL_PROVIDERS};
+
+    // ── merge_captures ──────────────────────────────────────────────
+
+    fn usage(labels: &[(&str, u32)]) -> UsageData {
+        UsageData {
+            provider: "claude".into(),
+            entries: labels
+                .iter()
+                .map(|(label, used)| UsageEntry {
+                    label: label.to_string(),
+                    percent_used: *used,
+                    percent_kind: PercentKind::Used,
+                    reset_info: String::new(),
+                    percent_remaining: 100 - used,
+                    reset_minutes: None,
+                    spent: None,
+                    requests: None,
+                    requests_used: None,
+                    requests_limit: None,
+                    scope: LimitScope::Personal,
+                    window: LimitWindow::Other,
+                    confidence: Confidence::Exact,
+                    inconsistent: false,
+                    as_of: None,
+                })
+                .collect(),
+            extra_usage_enabled: None,
+            tokens: Vec::new(),
+            check: CheckStats::default(),
+        }
+    }
+
+    fn labels_and_usage(data: &UsageData) -> Vec<(&str, u32)> {
+        data.entries
+            .iter()
+            .map(|e| (e.label.as_str(), e.percent_used))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_captures_keeps_entries_from_both() {
+        let fresh = usage(&[("session", 6)]);
+        let stale = usage(&[("session", 5), ("week", 10)]);
+        let merged = merge_captures(fresh, stale);
+        assert_eq!(
+            labels_and_usage(&merged),
+            vec![("session", 6), ("week", 10)]
+        );
+    }
+
+    #[test]
+    fn test_merge_captures_prefers_fresh_values() {
+        let fresh = usage(&[("session", 7), ("week", 12)]);
+        let stale = usage(&[("session", 5), ("week", 10)]);
+        let merged = merge_captures(fresh, stale);
+        assert_eq!(
+            labels_and_usage(&merged),
+            vec![("session", 7), ("week", 12)]
+        );
+    }
+
+    #[test]
+    fn test_cross_check_flags_disagreeing_entries() {
+        let mut settled = usage(&[("session", 30), ("week", 12), ("opus", 3)]);
+        let early = usage(&[("session", 12), ("week", 10)]);
+        let flagged = cross_check(&mut settled, &early);
+        assert_eq!(flagged, ["session (12% then 30% used)"]);
+        let inconsistent: Vec<bool> = settled.entries.iter().map(|e| e.inconsistent).collect();
+        assert_eq!(inconsistent, [true, false, false]);
+        let merged = merge_captures(settled, early);
+        assert_eq!(
+            labels_and_usage(&merged),
+            vec![("session", 30), ("week", 12), ("opus", 3)]
+        );
+    }
+
+    #[test]
+    fn test_merge_captures_preserves_screen_order() {
+        // The fresh capture lost the top row (scrolled) and the stale one
+        // hadn't drawn the bottom row yet.
+        let fresh = usage(&[("week", 12), ("opus", 3)]);
+        let stale = usage(&[("session", 5), ("week", 10)]);
+        let merged = merge_captures(fresh, stale);
+        assert_eq!(
+            labels_and_usage(&merged),
+            vec![("session", 5), ("week", 12), ("opus", 3)]
+        );
+        let fresh = usage(&[("session", 6), ("opus", 3)]);
+        let stale = usage(&[("session", 5), ("week", 10)]);
+        assert_eq!(
+            labels_and_usage(&merge_captures(fresh, stale)),
+            vec![("session", 6), ("week", 10), ("opus", 3)]
+        );
+    }
+
+    #[test]
+    fn test_claude_usage_at_end() {
+        assert!(!claude_usage_at_end(crate::fixtures::CLAUDE_CAPTURE));
+        let footer = format!("{}\n  Esc to cancel\n\n", crate::fixtures::CLAUDE_CAPTURE);
+        assert!(claude_usage_at_end(&footer));
+        let boxed = "╭──────────╮\n│ Current session │\n│ 1% used │\n╰──────────╯\n";
+        assert!(claude_usage_at_end(boxed));
+        assert!(!claude_usage_at_end(""));
+    }
+
+    #[test]
+    fn test_scroll_claude_usage_skips_the_probe_at_the_end() {
+        // Would show another page on any key; the footer says there's none.
+        let script = "stty -icanon -echo; \
+            printf 'Current session\\n██░░ 10%% used\\nResets 2pm\\nEsc to cancel\\n'; \
+            head -c 1 >/dev/null; \
+            printf '\\nCurrent week (all models)\\n█░░░ 4%% used\\n'; \
+            sleep 5";
+        let mut session = Session::new(
+            None,
+            false,
+            SessionLaunch {
+                binary: "sh",
+                args: &["-c", script],
+                isolate_env: false,
+                env: &[],
+            },
+        )
+        .unwrap();
+        session
+            .wait_for(
+                |c| c.contains("Esc to cancel"),
+                Duration::from_secs(5),
+                Duration::from_millis(50),
+                false,
+                false,
+            )
+            .unwrap();
+        let first = parse_claude_output(&session.capture_pane().unwrap()).unwrap();
+        let started = Instant::now();
+        let data = scroll_claude_usage(&mut session, &UsageConfig::default(), first).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert_eq!(data.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_scroll_claude_usage_collects_rows_below_the_fold() {
+        // Shows one page, then the next on any key.
+        let script = "stty -icanon -echo; \
+            printf 'Current session\\n██░░ 10%% used\\nResets 2pm\\n'; \
+            head -c 1 >/dev/null; \
+            printf '\\nCurrent week (Opus)\\n█░░░ 4%% used\\nResets Mar 1\\n'; \
+            sleep 5";
+        let mut session = Session::new(
+            None,
+            false,
+            SessionLaunch {
+                binary: "sh",
+                args: &["-c", script],
+                isolate_env: false,
+                env: &[],
+            },
+        )
+        .unwrap();
+        session
+            .wait_for(
+                |c| c.contains("Resets 2pm"),
+                Duration::from_secs(5),
+                Duration::from_millis(50),
+                false,
+                false,
+            )
+            .unwrap();
+        let first = parse_claude_output(&session.capture_pane().unwrap()).unwrap();
+        assert_eq!(first.entries.len(), 1);
+
+        let data = scroll_claude_usage(&mut session, &UsageConfig::default(), first).unwrap();
+        let labels: Vec<&str> = data.entries.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(labels, vec!["Current session", "Current week (Opus)"]);
+        assert_eq!(data.entries[1].percent_used, 4);
+    }
+
+    #[test]
+    fn test_salvage_partial_only_for_timeouts_with_entries() {
+        let mut session = Session::new(
+            None,
+            false,
+            SessionLaunch {
+                binary: "sh",
+                args: &[
+                    "-c",
+                    "printf '5h limit: [██░░] 40%% left (resets 11:07)\\n'; sleep 5",
+                ],
+                isolate_env: false,
+                env: &[],
+            },
+        )
+        .unwrap();
+        session
+            .wait_for(
+                |c| c.contains("left"),
+                Duration::from_secs(5),
+                Duration::from_millis(50),
+                false,
+                false,
+            )
+            .unwrap();
+        let timeout = || Err(anyhow!("[timeout] Timed out waiting for Codex usage data."));
+        let allow = UsageConfig {
+            allow_partial: true,
+            ..UsageConfig::default()
+        };
+
+        let err = salvage_partial(
+            timeout(),
+            &mut session,
+            &UsageConfig::default(),
+            "codex",
+            parse_codex_output,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("[timeout]"));
+
+        let data =
+            salvage_partial(timeout(), &mut session, &allow, "codex", parse_codex_output).unwrap();
+        assert!(data.check.partial);
+        assert_eq!(data.entries[0].percent_remaining, 40);
+
+        let other = salvage_partial(
+            Err(anyhow!("[parse-failure] nope")),
+            &mut session,
+            &allow,
+            "codex",
+            parse_codex_output,
+        );
+        assert!(other.is_err());
+        let nothing = salvage_partial(timeout(), &mut session, &allow, "codex", |_| {
+            Ok(UsageData {
+                provider: "codex".into(),
+                entries: vec![],
+                extra_usage_enabled: None,
+                tokens: Vec::new(),
+                check: CheckStats::default(),
+            })
+        });
+        assert!(nothing.is_err());
+    }
+
+    #[test]
+    fn test_merge_captures_empty_sides() {
+        let merged = merge_captures(usage(&[]), usage(&[("session", 5)]));
+        assert_eq!(labels_and_usage(&merged), vec![("session", 5)]);
+        let merged = merge_captures(usage(&[("session", 5)]), usage(&[]));
+        assert_eq!(labels_and_usage(&merged), vec![("session", 5)]);
+        assert!(merge_captures(usage(&[]), usage(&[])).entries.is_empty());
+    }
+
+    /// Codex with its check replaced by `check`.
+    fn codex_with(check: fn(&UsageConfig) -> Result<UsageData>) -> BuiltinProvider {
+        BuiltinProvider {
+            check,
+            ..CODEX_PROVIDER
+        }
+    }
+
+    #[test]
+    fn test_tracked_check_fills_stats_and_forwards_events() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let config = UsageConfig {
+            observers: vec![Arc::new(move |provider: &str, event: &CheckEvent| {
+                sink.lock()
+                    .unwrap()
+                    .push((provider.to_string(), event.clone()));
+            })],
+            ..UsageConfig::default()
+        };
+        let data = tracked_check(
+            &config,
+            &codex_with(|config| {
+                config.emit(
+                    "codex",
+                    CheckEvent::DialogDismissed(DialogKind::ModelSelection),
+                );
+                config.emit("codex", CheckEvent::Retry("update_prompt_resend_status"));
+                Ok(UsageData {
+                    provider: "codex".into(),
+                    entries: vec![],
+                    extra_usage_enabled: None,
+                    tokens: Vec::new(),
+                    check: CheckStats::default(),
+                })
+            }),
+        )
+        .unwrap();
+        assert_eq!(data.check.attempts, 2);
+        assert_eq!(data.check.dialogs, 1);
+        assert_eq!(seen.lock().unwrap().len(), 2);
+        let reasons: Vec<&str> = data.check.log.iter().map(|a| a.reason).collect();
+        assert_eq!(reasons, ["initial", "update_prompt_resend_status"]);
+    }
+
+    #[test]
+    fn test_tracked_check_consolidates_failure() {
+        let err = tracked_check(
+            &UsageConfig::default(),
+            &codex_with(|config| {
+                config.warn("codex", "Usage rows look truncated");
+                config.emit("codex", CheckEvent::Retry("recapture_wider"));
+                config.warn("codex", "Usage rows look truncated");
+                config.warn("codex", "Usage rows look truncated");
+                config.emit("codex", CheckEvent::Fallback("status_command"));
+                bail!("[timeout] Timed out after 45s")
+            }),
+        )
+        .unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "failed after 3 attempts (retry recapture_wider, fallback status_command); \
+             warnings: Usage rows look truncated: [timeout] Timed out after 45s"
+        );
+        let failure = err.downcast_ref::<CheckFailure>().unwrap();
+        assert_eq!(failure.attempts[1].kind, AttemptKind::Retry);
+        assert_eq!(failure.attempts[1].warnings, ["Usage rows look truncated"]);
+        assert_eq!(failure.attempts[2].kind, AttemptKind::Fallback);
+
+        // A plain failure keeps its message.
+        let err = tracked_check(
+            &UsageConfig::default(),
+            &codex_with(|_| bail!("[tool-missing] codex CLI not found")),
+        )
+        .unwrap_err();
+        assert_eq!(format!("{:#}", err), "[tool-missing] codex CLI not found");
+        assert_eq!(
+            err.downcast_ref::<CheckFailure>().unwrap().attempts.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_run_all_mock_returns_fixtures_without_launching() {
+        let config = UsageConfig {
+            mock: true,
+            ..UsageConfig::default()
+        };
+        let all = run_all(&config);
+        assert!(all.warnings.is_empty());
+        let providers: Vec<&str> = all.results.iter().map(|d| d.provider.as_str()).collect();
+        assert_eq!(providers, crate::ALL_PROVIDERS);
+    }
+
+    #[test]
+    fn test_run_streaming_sequential_keeps_order() {
+        let config = UsageConfig {
+            mock: true,
+            sequential: true,
+            ..UsageConfig::default()
+        };
+        let seen: Vec<String> = run_streaming(&config, &["gemini", "bogus", "claude"])
+            .map(|item| item.provider)
+            .collect();
+        assert_eq!(seen, ["gemini", "bogus", "claude"]);
+    }
+
+    #[test]
+    fn test_run_streaming_yields_each_provider() {
+        let config = UsageConfig {
+            mock: true,
+            ..UsageConfig::default()
+        };
+        let mut seen: Vec<String> = run_streaming(&config, &["gemini", "bogus"])
+            .map(|item| {
+                assert_eq!(item.result.is_ok(), item.provider == "gemini");
+                item.provider
+            })
+            .collect();
+        seen.sort();
+        assert_eq!(seen, vec!["bogus", "gemini"]);
+    }
+
+    #[test]
+    fn test_collect_results_orders_by_provider_list() {
+        let config = UsageConfig {
+            mock: true,
+            ..UsageConfig::default()
+        };
+        let stream = ["gemini", "claude", "bogus"].map(|provider| ProviderResult {
+            provider: provider.into(),
+            result: run_provider(&config, provider),
+            elapsed: Duration::ZERO,
+        });
+        let all = collect_results(&ALL_PROVIDERS, stream.into_iter());
+        let providers: Vec<&str> = all.results.iter().map(|d| d.provider.as_str()).collect();
+        assert_eq!(providers, vec!["claude", "gemini"]);
+        assert!(all.warnings.contains_key("bogus"));
+    }
+
+    // ── check_command_exists ────────────────────────────────────────
+
+    #[test]
+    fn test_check_command_exists_valid() {
+        // "ls" exists on all unix systems
+        assert!(check_command_exists("ls").is_ok());
+    }
+
+    #[test]
+    fn test_check_command_exists_missing() {
+        let result = check_command_exists("nonexistent_tool_xyz_12345");
+        assert!(result.is_err());
+        let err = format!("{:#}", result.unwrap_err());
+        assert!(err.contains("[tool-missing]"));
+    }
+
+    // ── gemini_prompt_ready: legacy path ────────────────────────────
+
+    #[test]
+    fn test_gemini_prompt_ready_legacy_gemini_md() {
+        assert!(gemini_prompt_ready("Loaded GEMINI.md"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_legacy_mcp_servers() {
+        assert!(gemini_prompt_ready("Found 3 MCP servers"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_legacy_gemini_prompt() {
+        assert!(gemini_prompt_ready("gemini > type here"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_not_banner_only() {
+        // Banner text alone doesn't mean the prompt is ready
+        assert!(!gemini_prompt_ready("Welcome to Gemini CLI v0.28.0"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_not_trust_dialog() {
+        // Dialog screens are handled separately, not by prompt readiness
+        assert!(!gemini_prompt_ready("Do you trust this folder"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_legacy_full_startup() {
+        assert!(gemini_prompt_ready(
+            "Loaded GEMINI.md\nFound 3 MCP servers\ngemini >"
+        ));
+    }
+
+    // ── gemini_prompt_ready: new path ───────────────────────────────
+
+    #[test]
+    fn test_gemini_prompt_ready_bare_gt_entire_line() {
+        assert!(gemini_prompt_ready("some header\n>\nmore text"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_bare_gt_with_space() {
+        assert!(gemini_prompt_ready("header\n> \nfooter"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_gt_with_trailing_content() {
+        assert!(gemini_prompt_ready("header\n> type your message"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_no_false_positive_gt_in_text() {
+        assert!(!gemini_prompt_ready("value > 5"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_no_false_positive_arrow() {
+        assert!(!gemini_prompt_ready("use -> arrow"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_no_false_positive_comparison() {
+        assert!(!gemini_prompt_ready("5 > 3"));
+    }
+
+    // ── gemini_prompt_ready: startup text must NOT match ───────────
+    // These appear before the CLI is ready; handled by dialog detection.
+
+    #[test]
+    fn test_gemini_prompt_ready_not_signed_in() {
+        assert!(!gemini_prompt_ready("Signed in as user@gmail.com"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_not_logged_in() {
+        assert!(!gemini_prompt_ready("Logged in as user@gmail.com"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_not_logged_in_with() {
+        assert!(!gemini_prompt_ready(
+            "Logged in with Google: user@gmail.com"
+        ));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_not_model_indicator() {
+        assert!(!gemini_prompt_ready("Model: gemini-2.5-pro"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_not_select_theme() {
+        assert!(!gemini_prompt_ready("Select a theme"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_not_update_available() {
+        assert!(!gemini_prompt_ready("Update available: v0.29.0"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_not_terms() {
+        assert!(!gemini_prompt_ready("Please accept the terms of service"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_what_can_i_help() {
+        assert!(gemini_prompt_ready("What can I help you with?"));
+    }
+
+    // ── gemini_prompt_ready: case insensitivity ─────────────────────
+
+    #[test]
+    fn test_gemini_prompt_ready_lowercase_gemini_md() {
+        assert!(gemini_prompt_ready("loaded gemini.md from disk"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_uppercase_mcp_servers() {
+        assert!(gemini_prompt_ready("Found 3 MCP SERVERS configured"));
+    }
+
+    // ── gemini_prompt_ready: negative tests ─────────────────────────
+
+    #[test]
+    fn test_gemini_prompt_ready_empty() {
+        assert!(!gemini_prompt_ready(""));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_loading() {
+        assert!(!gemini_prompt_ready("Loading..."));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_processing() {
+        assert!(!gemini_prompt_ready("Processing request..."));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_random_text() {
+        assert!(!gemini_prompt_ready(
+            "The quick brown fox jumps over the lazy dog"
+        ));
+    }
+
+    // ── gemini_prompt_ready: additional edge cases ──────────────────
+
+    #[test]
+    fn test_gemini_prompt_ready_not_google_account() {
+        assert!(!gemini_prompt_ready("Using Google Account user@gmail.com"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_not_color_theme() {
+        assert!(!gemini_prompt_ready("Pick a color theme for the CLI"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_no_false_positive_gt_no_space() {
+        // ">foo" without space after > should NOT match
+        assert!(!gemini_prompt_ready(">foo"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_bare_gt_only_content() {
+        assert!(gemini_prompt_ready(">"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_gt_in_multiline_no_match() {
+        // > embedded in text across multiple lines, never at line start
+        assert!(!gemini_prompt_ready("line1\nvalue > 5\nline3"));
+    }
+
+    #[test]
+    fn test_gemini_prompt_ready_what_can_i_help_uppercase() {
+        assert!(gemini_prompt_ready("WHAT CAN I HELP you with today?"));
+    }
+
+    // NOTE: The "Waiting for auth..." spinner is NOT handled in
+    // gemini_prompt_ready itself — the startup loop in run_gemini requires
+    // content stability (3 consecutive stable polls) before accepting
+    // prompt readiness, which naturally filters out the active spinner.
+
+    // ── gemini_prompt_ready: data regex ─────────────────────────────
+
+    #[test]
+    fn test_gemini_data_regex_case_insensitive() {
+        let re = &*GEMINI_DATA_RE;
+        // Old format with parentheses
+        assert!(re.is_match("45.2% (Resets in 3 hours)"));
+        assert!(re.is_match("45.2% (resets in 3 hours)"));
+        assert!(re.is_match("45.2% (RESETS IN 3 HOURS)"));
+        assert!(re.is_match("100% (Reset tomorrow)"));
+        assert!(re.is_match("100% (reset tomorrow)"));
+        // New format without parentheses
+        assert!(re.is_match("99.0% resets in 23h 19m"));
+        assert!(re.is_match("97.1% resets in 1h 13m"));
+        assert!(re.is_match("99.0% Resets in 23h 19m"));
+    }
+
+    #[test]
+    fn test_gemini_data_regex_no_false_positive() {
+        let re = &*GEMINI_DATA_RE;
+        assert!(!re.is_match("45% (Resetting)"));
+        assert!(!re.is_match("45% used"));
+        assert!(!re.is_match("no percentage here"));
+    }
+
+    // ── content_tail ────────────────────────────────────────────────
+
+    #[test]
+    fn test_content_tail_shorter_than_max() {
+        assert_eq!(content_tail("hello", 500), "hello");
+    }
+
+    #[test]
+    fn test_content_tail_exact_max() {
+        assert_eq!(content_tail("abc", 3), "abc");
+    }
+
+    #[test]
+    fn test_content_tail_longer_than_max() {
+        assert_eq!(content_tail("hello world", 5), "world");
+    }
+
+    #[test]
+    fn test_content_tail_empty() {
+        assert_eq!(content_tail("", 500), "");
+    }
+
+    #[test]
+    fn test_content_tail_unicode() {
+        // Ensure char-based truncation doesn't split codepoints
+        assert_eq!(content_tail("héllo wörld", 5), "wörld");
+    }
+}
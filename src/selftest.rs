@@ -0,0 +1,232 @@
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::fixtures::{
+    AIDER_CAPTURE, AMAZONQ_CAPTURE, CLAUDE_CAPTURE, CLAUDE_ENTERPRISE_CAPTURE, CLAUDE_TEAM_CAPTURE,
+    CODEX_BUSINESS_CAPTURE, CODEX_CAPTURE, COPILOT_CAPTURE, COPILOT_FREE_CAPTURE, CURSOR_CAPTURE,
+    GEMINI_CAPTURE, GEMINI_CODE_ASSIST_CAPTURE, QWEN_CAPTURE,
+};
+use crate::parser::{
+    parse_aider_output, parse_amazonq_output, parse_claude_output, parse_codex_output,
+    parse_copilot_output, parse_cursor_output, parse_gemini_output, parse_qwen_output,
+    parse_reset_minutes_at,
+};
+use crate::types::UsageData;
+
+/// Outcome of one self-test check.
+#[derive(Debug, Clone)]
+pub struct SelfTestResult {
+    pub name: String,
+    /// `None` on success, otherwise what went wrong.
+    pub failure: Option<String>,
+}
+
+impl SelfTestResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Expected (label, percent remaining) rows for a bundled capture.
+type ParserCase = (
+    &'static str,
+    &'static str,
+    fn(&str) -> anyhow::Result<UsageData>,
+    &'static [(&'static str, u32)],
+);
+
+const PARSER_CASES: &[ParserCase] = &[
+    (
+        "claude",
+        CLAUDE_CAPTURE,
+        parse_claude_output,
+        &[
+            ("Current session", 99),
+            ("Current week (all models)", 100),
+            ("Current week (Sonnet only)", 100),
+            ("Extra usage", 85),
+        ],
+    ),
+    (
+        "claude (team)",
+        CLAUDE_TEAM_CAPTURE,
+        parse_claude_output,
+        &[
+            ("Current session", 88),
+            ("Current week (all models)", 70),
+            ("Organization usage (shared)", 55),
+        ],
+    ),
+    (
+        "claude (enterprise)",
+        CLAUDE_ENTERPRISE_CAPTURE,
+        parse_claude_output,
+        &[("Your seat", 92), ("Team monthly limit", 38)],
+    ),
+    (
+        "codex",
+        CODEX_CAPTURE,
+        parse_codex_output,
+        &[("5h limit", 97), ("Weekly limit", 71)],
+    ),
+    (
+        "codex (business)",
+        CODEX_BUSINESS_CAPTURE,
+        parse_codex_output,
+        &[
+            ("5h limit", 97),
+            ("Weekly limit", 71),
+            ("Workspace Shared credits", 30),
+            ("Workspace Weekly limit", 48),
+        ],
+    ),
+    (
+        "gemini",
+        GEMINI_CAPTURE,
+        parse_gemini_output,
+        &[
+            ("gemini-2.5-flash-lite", 100),
+            ("gemini-2.5-flash", 99),
+            ("gemini-2.5-pro", 98),
+        ],
+    ),
+    (
+        "gemini (code assist)",
+        GEMINI_CODE_ASSIST_CAPTURE,
+        parse_gemini_output,
+        &[("gemini-2.5-pro", 92), ("gemini-2.5-flash", 98)],
+    ),
+    (
+        "copilot",
+        COPILOT_CAPTURE,
+        parse_copilot_output,
+        &[("Premium requests", 56)],
+    ),
+    (
+        "copilot (free)",
+        COPILOT_FREE_CAPTURE,
+        parse_copilot_output,
+        &[
+            ("Premium requests", 76),
+            ("Chat messages", 24),
+            ("Code completions", 79),
+        ],
+    ),
+    (
+        "cursor",
+        CURSOR_CAPTURE,
+        parse_cursor_output,
+        &[("Included usage", 38), ("On-demand usage", 94)],
+    ),
+    (
+        "qwen",
+        QWEN_CAPTURE,
+        parse_qwen_output,
+        &[("qwen3-coder-plus", 84), ("qwen3-coder-flash", 99)],
+    ),
+    (
+        "amazonq",
+        AMAZONQ_CAPTURE,
+        parse_amazonq_output,
+        &[("Agentic requests", 79), ("Code transformation", 70)],
+    ),
+    (
+        "aider",
+        AIDER_CAPTURE,
+        parse_aider_output,
+        &[("Context window", 98)],
+    ),
+];
+
+/// (provider, reset text, expected minutes) at `fixed_clock()`.  Only
+/// formats that carry their own timezone, so results don't depend on the
+/// machine's local zone.
+const RESET_CASES: &[(&str, &str, i64)] = &[
+    ("claude", "Resets 2pm (America/Chicago)", 8 * 60),
+    (
+        "claude",
+        "Resets Feb 20 at 9am (America/Chicago)",
+        7 * 24 * 60 + 3 * 60,
+    ),
+    (
+        "claude",
+        "Resets Mar 1 (America/Chicago)",
+        15 * 24 * 60 + 18 * 60,
+    ),
+    ("claude", "Resets10pm(America/Chicago)", 16 * 60),
+    (
+        "claude",
+        "Resets Jan 5 at 9am (UTC)",
+        325 * 24 * 60 + 21 * 60,
+    ),
+    ("gemini", "Resets in 23h 58m", 23 * 60 + 58),
+    ("gemini", "Resets in 45m", 45),
+    ("copilot", "Resets on Mar 1, 2026", 15 * 24 * 60 + 12 * 60),
+    ("copilot", "Resets in 13 days", 13 * 24 * 60),
+    ("cursor", "Resets on Mar 1", 15 * 24 * 60 + 12 * 60),
+    ("qwen", "Resets in 9h 41m", 9 * 60 + 41),
+    ("amazonq", "Resets on Mar 1", 15 * 24 * 60 + 12 * 60),
+];
+
+/// 12:00 UTC on Feb 13, 2026 (America/Chicago is on CST, UTC-6).
+fn fixed_clock() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 2, 13, 12, 0, 0).unwrap()
+}
+
+fn check_parser(
+    capture: &str,
+    parse: fn(&str) -> anyhow::Result<UsageData>,
+    expected: &[(&str, u32)],
+) -> Option<String> {
+    let data = match parse(capture) {
+        Ok(data) => data,
+        Err(e) => return Some(format!("parse error: {:#}", e)),
+    };
+    let actual: Vec<(&str, u32)> = data
+        .entries
+        .iter()
+        .map(|e| (e.label.as_str(), e.percent_remaining))
+        .collect();
+    (actual != expected).then(|| format!("expected {:?}, got {:?}", expected, actual))
+}
+
+/// Run the bundled captures through the parsers and check reset-time math
+/// against a fixed clock.
+pub fn run_self_test() -> Vec<SelfTestResult> {
+    let mut results = Vec::new();
+    for (provider, capture, parse, expected) in PARSER_CASES {
+        results.push(SelfTestResult {
+            name: format!("{} parser", provider),
+            failure: check_parser(capture, *parse, expected),
+        });
+    }
+    let now = fixed_clock();
+    for (provider, text, minutes) in RESET_CASES {
+        let actual = parse_reset_minutes_at(text, provider, now);
+        results.push(SelfTestResult {
+            name: format!("{} reset \"{}\"", provider, text),
+            failure: (actual != Some(*minutes))
+                .then(|| format!("expected {} minutes, got {:?}", minutes, actual)),
+        });
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        let results = run_self_test();
+        assert_eq!(results.len(), PARSER_CASES.len() + RESET_CASES.len());
+        for result in &results {
+            assert!(result.passed(), "{}: {:?}", result.name, result.failure);
+        }
+    }
+
+    #[test]
+    fn test_check_parser_reports_mismatch() {
+        let failure = check_parser(CODEX_CAPTURE, parse_codex_output, &[("5h limit", 50)]);
+        assert!(failure.unwrap().contains("expected"));
+    }
+}
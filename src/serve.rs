@@ -0,0 +1,642 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::health::{HealthPolicy, HealthTracker};
+use crate::history::{self, HistoryRecord};
+use crate::metrics::{usage_gauges, SelfMetrics};
+use crate::pool::SessionPool;
+use crate::runner::strip_error_tags;
+use crate::singleflight::SingleFlight;
+use crate::types::{LimitWindow, UsageData};
+use crate::{run_provider, UsageConfig};
+
+/// How long a result is served before the next request checks again.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// The bearer token `agentusage serve --ingest` accepts usage with.
+pub const INGEST_TOKEN_VAR: &str = "AGENTUSAGE_INGEST_TOKEN";
+
+/// The largest body `POST /ingest/{host}` reads.
+const MAX_INGEST_BYTES: u64 = 1 << 20;
+
+/// Turns one provider's result into its JSON body.
+type Render = dyn Fn(&UsageData) -> serde_json::Value + Send + Sync;
+
+/// A check's outcome and when it finished.
+#[derive(Clone)]
+struct Cached {
+    at: Instant,
+    checked_at: DateTime<Utc>,
+    result: Result<UsageData, String>,
+}
+
+/// Where POSTed usage goes and the token it must come with.
+struct Ingest {
+    token: String,
+    history: Option<PathBuf>,
+}
+
+/// The usage a machine last POSTed.
+struct Received {
+    received_at: DateTime<Utc>,
+    results: serde_json::Value,
+}
+
+/// The fields of a `--json` entry that a history record keeps.
+#[derive(Deserialize)]
+struct IngestEntry {
+    label: String,
+    percent_used: u32,
+    window: LimitWindow,
+    reset_minutes: Option<i64>,
+    spent: Option<String>,
+    #[serde(default)]
+    stale: bool,
+}
+
+/// An HTTP answer: status code, content type, and body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn new(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_string_pretty(&body).unwrap_or_default(),
+        }
+    }
+}
+
+/// Serves usage over HTTP for dashboards and scripts that poll:
+///
+/// - `GET /usage`: every provider, `{"results": {...}, "warnings": {...}}`
+/// - `GET /usage/{provider}`: one provider, or 502 with its error
+/// - `GET /healthz`: 200 unless a provider's checks keep failing, else 503
+/// - `GET /readyz`: 200 once every provider has a recent good result
+/// - `GET /metrics`: usage gauges and the server's own `SelfMetrics`, in
+///   the Prometheus text format
+/// - `POST /ingest/{host}`: with `with_ingest`, another machine's `--json`
+///   output, added to the history under `host`
+/// - `GET /hosts`: the usage each machine last POSTed
+///
+/// Results are cached for the TTL, failures included, so polling doesn't
+/// launch a CLI per request; concurrent requests for a provider share
+/// one check.  Claude's session is kept open between checks and closed
+/// once it has idled for two TTLs.
+pub struct UsageServer {
+    config: UsageConfig,
+    providers: Vec<String>,
+    ttl: Duration,
+    render: Box<Render>,
+    flights: SingleFlight,
+    cache: Mutex<HashMap<String, Cached>>,
+    health: Mutex<HealthTracker>,
+    metrics: Arc<SelfMetrics>,
+    ingest: Option<Ingest>,
+    hosts: Mutex<BTreeMap<String, Received>>,
+}
+
+impl UsageServer {
+    /// Serve `providers`, checked with `config`, caching for `ttl`.
+    pub fn new(mut config: UsageConfig, providers: &[&str], ttl: Duration) -> Self {
+        let metrics = Arc::new(SelfMetrics::new());
+        config.observers.push(metrics.clone());
+        config.pool = Some(Arc::new(SessionPool::new(providers.len(), ttl * 2)));
+        Self {
+            config,
+            providers: providers.iter().map(|p| p.to_string()).collect(),
+            ttl,
+            render: Box::new(|data| serde_json::to_value(data).unwrap_or_default()),
+            flights: SingleFlight::new(),
+            cache: Mutex::new(HashMap::new()),
+            health: Mutex::new(HealthTracker::new(
+                HealthPolicy::default(),
+                providers.iter().copied(),
+            )),
+            metrics,
+            ingest: None,
+            hosts: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Render each provider's result with `render` instead of serializing
+    /// `UsageData` as is, e.g. to match the CLI's `--json` output.
+    pub fn with_render(
+        mut self,
+        render: impl Fn(&UsageData) -> serde_json::Value + Send + Sync + 'static,
+    ) -> Self {
+        self.render = Box::new(render);
+        self
+    }
+
+    /// Judge `/healthz` and `/readyz` by `policy`.
+    pub fn with_health_policy(self, policy: HealthPolicy) -> Self {
+        let providers: Vec<&str> = self.providers.iter().map(String::as_str).collect();
+        let tracker = HealthTracker::new(policy, providers);
+        *self.health.lock().unwrap_or_else(|e| e.into_inner()) = tracker;
+        self
+    }
+
+    /// Accept usage other machines POST with `Authorization: Bearer
+    /// {token}`, appending it to the history at `history` if given, so one
+    /// server can report on a team's machines.
+    pub fn with_ingest(mut self, token: impl Into<String>, history: Option<PathBuf>) -> Self {
+        self.ingest = Some(Ingest {
+            token: token.into(),
+            history,
+        });
+        self
+    }
+
+    /// Answer `method` `path`.
+    pub fn respond(&self, method: &str, path: &str) -> Response {
+        if method != "GET" {
+            return Response::new(405, json!({ "error": "only GET is supported" }));
+        }
+        let path = path
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('/');
+        match path {
+            "/usage" => self.all_usage(),
+            "/hosts" => {
+                let hosts: serde_json::Map<_, _> = self
+                    .hosts
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .iter()
+                    .map(|(host, received)| {
+                        let body = json!({
+                            "received_at": received.received_at,
+                            "results": received.results,
+                        });
+                        (host.clone(), body)
+                    })
+                    .collect();
+                Response::new(200, json!({ "hosts": hosts }))
+            }
+            "/metrics" => {
+                let results: Vec<UsageData> = self
+                    .check_all()
+                    .into_iter()
+                    .filter_map(|(_, cached)| cached.result.ok())
+                    .collect();
+                Response {
+                    status: 200,
+                    content_type: "text/plain; version=0.0.4",
+                    body: usage_gauges(&results) + &self.metrics.to_prometheus(),
+                }
+            }
+            "/healthz" | "/readyz" => {
+                let report = self
+                    .health
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .report(Utc::now());
+                let ok = if path == "/healthz" {
+                    report.healthy
+                } else {
+                    report.ready
+                };
+                Response::new(if ok { 200 } else { 503 }, json!(report))
+            }
+            _ => match path.strip_prefix("/usage/") {
+                Some(provider) if self.providers.iter().any(|p| p == provider) => {
+                    self.provider_usage(provider)
+                }
+                Some(provider) => Response::new(
+                    404,
+                    json!({ "error": format!("unknown provider '{}'", provider) }),
+                ),
+                None => Response::new(404, json!({ "error": "not found" })),
+            },
+        }
+    }
+
+    /// Answer a POST of `body` to `path`, sent with the `authorization`
+    /// header.
+    pub fn receive(&self, path: &str, authorization: Option<&str>, body: &str) -> Response {
+        let Some(ingest) = &self.ingest else {
+            return Response::new(405, json!({ "error": "only GET is supported" }));
+        };
+        let path = path
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('/');
+        let Some(host) = path
+            .strip_prefix("/ingest/")
+            .filter(|host| valid_host(host))
+        else {
+            return Response::new(404, json!({ "error": "POST usage to /ingest/{host}" }));
+        };
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if !same_token(token.trim(), &ingest.token) {
+            return Response::new(401, json!({ "error": "missing or wrong bearer token" }));
+        }
+        let received_at = Utc::now();
+        let parsed = serde_json::from_str(body)
+            .map_err(|e| format!("body is not JSON: {}", e))
+            .and_then(|doc: serde_json::Value| {
+                let records = ingest_records(&doc, host, received_at)?;
+                Ok((doc, records))
+            });
+        let (doc, records) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => return Response::new(400, json!({ "error": e })),
+        };
+        if let Some(path) = &ingest.history {
+            if let Err(e) = history::append(path, &records) {
+                return Response::new(500, json!({ "error": format!("{:#}", e) }));
+            }
+        }
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            host.to_string(),
+            Received {
+                received_at,
+                results: doc["results"].clone(),
+            },
+        );
+        Response::new(
+            200,
+            json!({ "success": true, "host": host, "records": records.len() }),
+        )
+    }
+
+    fn provider_usage(&self, provider: &str) -> Response {
+        let cached = self.get(provider);
+        match &cached.result {
+            Ok(data) => {
+                let mut body = (self.render)(data);
+                body["checked_at"] = json!(cached.checked_at);
+                Response::new(200, body)
+            }
+            Err(e) => Response::new(
+                502,
+                json!({ "success": false, "error": e, "checked_at": cached.checked_at }),
+            ),
+        }
+    }
+
+    fn all_usage(&self) -> Response {
+        let mut results = serde_json::Map::new();
+        let mut warnings = serde_json::Map::new();
+        let mut checked_at = serde_json::Map::new();
+        for (provider, cached) in &self.check_all() {
+            match &cached.result {
+                Ok(data) => {
+                    results.insert(provider.to_string(), (self.render)(data));
+                }
+                Err(e) => {
+                    warnings.insert(provider.to_string(), json!(e));
+                }
+            }
+            checked_at.insert(provider.to_string(), json!(cached.checked_at));
+        }
+        let success = !results.is_empty();
+        let mut body = json!({
+            "success": success,
+            "results": results,
+            "checked_at": checked_at,
+        });
+        if !warnings.is_empty() {
+            body["warnings"] = json!(warnings);
+        }
+        Response::new(if success { 200 } else { 502 }, body)
+    }
+
+    /// Every provider's result, `get` in parallel.
+    fn check_all(&self) -> Vec<(&str, Cached)> {
+        std::thread::scope(|scope| {
+            let checks: Vec<_> = self
+                .providers
+                .iter()
+                .map(|provider| (provider.as_str(), scope.spawn(move || self.get(provider))))
+                .collect();
+            checks
+                .into_iter()
+                .map(|(provider, check)| {
+                    let cached = check.join().unwrap_or_else(|_| Cached {
+                        at: Instant::now(),
+                        checked_at: Utc::now(),
+                        result: Err("Provider thread panicked".to_string()),
+                    });
+                    (provider, cached)
+                })
+                .collect()
+        })
+    }
+
+    /// `provider`'s result: the cached one while it is fresher than the
+    /// TTL, else a new check's.
+    fn get(&self, provider: &str) -> Cached {
+        let fresh = self
+            .cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(provider)
+            .filter(|cached| cached.at.elapsed() < self.ttl)
+            .cloned();
+        self.metrics.record_cache(fresh.is_some());
+        if let Some(cached) = fresh {
+            return cached;
+        }
+        let result = self
+            .flights
+            .run(provider, || {
+                let started = Instant::now();
+                let result = run_provider(&self.config, provider);
+                self.metrics
+                    .record_scrape(provider, started.elapsed(), result.is_ok());
+                result
+            })
+            .map_err(|e| strip_error_tags(&format!("{:#}", e)));
+        let cached = Cached {
+            at: Instant::now(),
+            checked_at: Utc::now(),
+            result,
+        };
+        {
+            let mut health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+            match &cached.result {
+                Ok(_) => health.succeeded(provider, cached.checked_at),
+                Err(e) => health.failed(provider, e, cached.checked_at),
+            }
+        }
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(provider.to_string(), cached.clone());
+        cached
+    }
+
+    /// Listen on `addr` (e.g. `127.0.0.1:7788`) and answer requests until
+    /// the process exits, each on its own thread.  Every provider is
+    /// checked once at start-up so `/readyz` turns ready without a request.
+    pub fn serve(self, addr: &str) -> Result<()> {
+        let server = tiny_http::Server::http(addr)
+            .map_err(|e| anyhow!("Failed to listen on {}: {}", addr, e))?;
+        let this = Arc::new(self);
+        {
+            let this = Arc::clone(&this);
+            std::thread::spawn(move || this.all_usage());
+        }
+        if let Some(pool) = this.config.pool.clone() {
+            let every = this.ttl;
+            std::thread::spawn(move || loop {
+                std::thread::sleep(every);
+                pool.evict_idle();
+            });
+        }
+        for mut request in server.incoming_requests() {
+            let this = Arc::clone(&this);
+            std::thread::spawn(move || {
+                let response = if *request.method() == tiny_http::Method::Post {
+                    let authorization = request
+                        .headers()
+                        .iter()
+                        .find(|header| header.field.equiv("Authorization"))
+                        .map(|header| header.value.to_string());
+                    let mut body = String::new();
+                    match request
+                        .as_reader()
+                        .take(MAX_INGEST_BYTES + 1)
+                        .read_to_string(&mut body)
+                    {
+                        Ok(read) if read as u64 > MAX_INGEST_BYTES => {
+                            Response::new(413, json!({ "error": "body too large" }))
+                        }
+                        Ok(_) => this.receive(request.url(), authorization.as_deref(), &body),
+                        Err(_) => Response::new(400, json!({ "error": "body is not UTF-8" })),
+                    }
+                } else {
+                    this.respond(request.method().as_str(), request.url())
+                };
+                let content_type =
+                    tiny_http::Header::from_bytes("Content-Type", response.content_type)
+                        .expect("valid header");
+                let _ = request.respond(
+                    tiny_http::Response::from_string(response.body)
+                        .with_status_code(response.status)
+                        .with_header(content_type),
+                );
+            });
+        }
+        Ok(())
+    }
+}
+
+/// History records for each limit in `doc`, an agentusage `--json`
+/// document, as read by `host` at `at` (or at the `checked_at` it gives for
+/// the provider).  Stale entries are skipped; they were recorded already.
+fn ingest_records(
+    doc: &serde_json::Value,
+    host: &str,
+    at: DateTime<Utc>,
+) -> Result<Vec<HistoryRecord>, String> {
+    let Some(results) = doc.get("results").and_then(|r| r.as_object()) else {
+        return Err("expected agentusage --json output, with `results`".to_string());
+    };
+    let mut records = Vec::new();
+    for (provider, entries) in results {
+        let Some(entries) = entries.as_object() else {
+            return Err(format!("results.{} is not an object", provider));
+        };
+        let timestamp = doc["checked_at"][provider]
+            .as_str()
+            .and_then(|at| at.parse().ok())
+            .unwrap_or(at);
+        for (key, entry) in entries {
+            let entry: IngestEntry = serde_json::from_value(entry.clone())
+                .map_err(|e| format!("results.{}.{}: {}", provider, key, e))?;
+            if entry.stale {
+                continue;
+            }
+            records.push(HistoryRecord {
+                timestamp,
+                provider: provider.clone(),
+                label: entry.label,
+                window: entry.window,
+                percent_used: entry.percent_used,
+                reset_minutes: entry.reset_minutes,
+                spent: entry.spent,
+                project: None,
+                snapshot: None,
+                host: Some(host.to_string()),
+                estimated: false,
+            });
+        }
+    }
+    Ok(records)
+}
+
+/// A host name safe to keep in the history: letters, digits, `.`, `_`,
+/// and `-`.
+fn valid_host(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 64
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Compare tokens in time independent of where they differ.
+fn same_token(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(providers: &[&str]) -> UsageServer {
+        let config = UsageConfig {
+            mock: true,
+            ..UsageConfig::default()
+        };
+        UsageServer::new(config, providers, DEFAULT_TTL)
+    }
+
+    fn get(server: &UsageServer, path: &str) -> (u16, serde_json::Value) {
+        let response = server.respond("GET", path);
+        assert_eq!(response.content_type, "application/json");
+        (
+            response.status,
+            serde_json::from_str(&response.body).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_usage_endpoints() {
+        let server = server(&["claude", "codex"]);
+        assert_eq!(get(&server, "/readyz").0, 503);
+
+        let (status, all) = get(&server, "/usage");
+        assert_eq!(status, 200);
+        assert_eq!(all["success"], true);
+        assert_eq!(all["results"]["codex"]["provider"], "codex");
+        assert!(all["checked_at"]["claude"].is_string());
+
+        let (status, one) = get(&server, "/usage/codex?pretty");
+        assert_eq!(status, 200);
+        assert_eq!(one["provider"], "codex");
+        // Served from the cache /usage filled.
+        assert_eq!(one["checked_at"], all["checked_at"]["codex"]);
+
+        assert_eq!(get(&server, "/usage/gemini").0, 404);
+        assert_eq!(get(&server, "/nope").0, 404);
+        assert_eq!(server.respond("POST", "/usage").status, 405);
+    }
+
+    #[test]
+    fn test_health_endpoints_follow_checks() {
+        let server = server(&["codex"]).with_render(|data| json!({ "limits": data.entries.len() }));
+        let (status, health) = get(&server, "/healthz");
+        assert_eq!(status, 200);
+        assert_eq!(health["providers"][0]["status"], "pending");
+
+        assert_eq!(get(&server, "/usage/codex").1["limits"], 2);
+        let (status, ready) = get(&server, "/readyz");
+        assert_eq!(status, 200);
+        assert_eq!(ready["providers"][0]["status"], "ok");
+    }
+
+    #[test]
+    fn test_metrics_endpoint() {
+        let server = server(&["codex"]);
+        get(&server, "/usage/codex");
+        let metrics = server.respond("GET", "/metrics");
+        assert_eq!(metrics.status, 200);
+        assert!(metrics.content_type.starts_with("text/plain"));
+        let text = metrics.body;
+        assert!(text.contains("agentusage_percent_used{provider=\"codex\",limit=\"5h limit\""));
+        assert!(text.contains("agentusage_scrapes_total{provider=\"codex\",outcome=\"success\"} 1"));
+        // One check for /usage/codex, then the cache for /metrics.
+        assert!(text.contains("agentusage_cache_requests_total{result=\"hit\"} 1"));
+        assert!(text.contains("agentusage_cache_requests_total{result=\"miss\"} 1"));
+    }
+
+    #[test]
+    fn test_ingest_endpoint() {
+        let dir =
+            std::env::temp_dir().join(format!("agentusage-serve-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("history.jsonl");
+        assert_eq!(
+            server(&["codex"])
+                .receive("/ingest/laptop", None, "{}")
+                .status,
+            405
+        );
+
+        let server = server(&["codex"]).with_ingest("s3cret", Some(path.clone()));
+        let body = json!({
+            "success": true,
+            "results": {
+                "codex": {
+                    "five_hour": {
+                        "label": "5h limit",
+                        "percent_used": 23,
+                        "window": "session",
+                        "reset_minutes": 135,
+                        "scope": "session",
+                    },
+                    "weekly": {
+                        "label": "Weekly limit",
+                        "percent_used": 41,
+                        "window": "weekly",
+                        "stale": true,
+                    },
+                },
+            },
+        })
+        .to_string();
+        let auth = Some("Bearer s3cret");
+        assert_eq!(server.receive("/ingest/laptop", None, &body).status, 401);
+        assert_eq!(
+            server
+                .receive("/ingest/laptop", Some("Bearer nope"), &body)
+                .status,
+            401
+        );
+        assert_eq!(server.receive("/ingest/../x", auth, &body).status, 404);
+        assert_eq!(server.receive("/ingest/laptop", auth, "{").status, 400);
+        assert_eq!(server.receive("/ingest/laptop", auth, "{}").status, 400);
+
+        let response = server.receive("/ingest/laptop", auth, &body);
+        assert_eq!(response.status, 200);
+        let records = history::load(&path, None).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].host.as_deref(), Some("laptop"));
+        assert_eq!(records[0].percent_used, 23);
+        assert_eq!(records[0].window, LimitWindow::Session);
+
+        let (status, hosts) = get(&server, "/hosts");
+        assert_eq!(status, 200);
+        assert_eq!(
+            hosts["hosts"]["laptop"]["results"]["codex"]["five_hour"]["percent_used"],
+            23
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
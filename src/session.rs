@@ -1,25 +1,79 @@
+use crate::isolation::{launch_dir, IsolatedEnv, TempDir};
+use crate::observer::CheckObserver;
 use crate::pty;
 use crate::pty::PtySession;
-use anyhow::Result;
-use std::time::Duration;
+use crate::types::AttachMode;
+use anyhow::{Context, Result};
+use std::ffi::{OsStr, OsString};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub struct Session {
     inner: PtySession,
+    /// Declared after `inner` so the child is gone before its directories
+    /// are removed.
+    _isolation: Option<IsolatedEnv>,
+    /// Other temp files the child refers to (see `keep_temp_dir`).
+    held: Vec<TempDir>,
 }
 
 pub struct SessionLaunch<'a> {
     pub binary: &'a str,
     pub args: &'a [&'a str],
+    /// Give the child private cache/state/tmp directories.
+    pub isolate_env: bool,
+    /// Extra environment variables for the child.
+    pub env: &'a [(&'a str, OsString)],
 }
 
 impl Session {
-    /// Create a new PTY-backed session.
+    /// Create a new PTY-backed session.  Without a `directory` the CLI runs
+    /// in the empty `launch_dir`, so no project MCP servers or context files
+    /// get involved, and a folder trusted once stays trusted.
     pub fn new(directory: Option<&str>, _verbose: bool, launch: SessionLaunch<'_>) -> Result<Self> {
+        let isolation = launch.isolate_env.then(IsolatedEnv::create).transpose()?;
+        let mut env: Vec<(&str, &OsStr)> = launch
+            .env
+            .iter()
+            .map(|(name, value)| (*name, value.as_os_str()))
+            .collect();
+        if let Some(isolation) = &isolation {
+            env.extend(
+                isolation
+                    .vars()
+                    .into_iter()
+                    .map(|(name, dir)| (name, dir.as_os_str())),
+            );
+        }
+        let workdir = match directory {
+            Some(_) => None,
+            None => Some(launch_dir()?),
+        };
+        let directory = match &workdir {
+            Some(dir) => Some(
+                dir.to_str()
+                    .context("Launch directory path is not valid UTF-8")?,
+            ),
+            None => directory,
+        };
         Ok(Self {
-            inner: PtySession::new(directory, launch.binary, launch.args)?,
+            inner: PtySession::new_with_env(directory, launch.binary, launch.args, &env)?,
+            _isolation: isolation,
+            held: Vec::new(),
         })
     }
 
+    /// Report waits run with `verbose` to `observers`, as `provider`'s.
+    pub fn observe(&mut self, provider: &str, observers: Vec<Arc<dyn CheckObserver>>) {
+        self.inner.observe(provider, observers);
+    }
+
+    /// Keep `dir` until the session ends, for files the child was pointed
+    /// at on launch.
+    pub(crate) fn keep_temp_dir(&mut self, dir: TempDir) {
+        self.held.push(dir);
+    }
+
     pub fn backend_name(&self) -> &'static str {
         "openpty"
     }
@@ -32,10 +86,56 @@ impl Session {
         self.inner.send_keys_literal(keys)
     }
 
+    /// Resize the terminal and signal the child to redraw.
+    pub fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
+        self.inner.resize(cols, rows)
+    }
+
+    /// Fail all captures (and so all waits) once `deadline` passes.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.inner.set_deadline(deadline);
+    }
+
+    /// Mirror the session to the user's terminal.
+    pub fn attach(&mut self, mode: AttachMode) -> Result<()> {
+        self.inner.attach(mode)
+    }
+
+    /// Hand the user's terminal to the session until they press Ctrl-].
+    pub fn interact(&mut self, banner: &str) -> Result<()> {
+        self.inner.interact(banner)
+    }
+
     pub fn capture_pane(&mut self) -> Result<String> {
         self.inner.capture_pane()
     }
 
+    /// Position in the output so far, for `capture_since`.
+    pub fn output_mark(&mut self) -> Result<u64> {
+        self.inner.output_mark()
+    }
+
+    /// Output produced after `mark`, even if the capture buffer has been
+    /// trimmed since.
+    pub fn capture_since(&mut self, mark: u64) -> Result<String> {
+        self.inner.capture_since(mark)
+    }
+
+    /// Everything captured so far, even after the deadline has passed.
+    pub fn captured_text(&mut self) -> String {
+        self.inner.captured_text()
+    }
+
+    /// Whether the provider CLI is still running.
+    pub fn is_alive(&mut self) -> bool {
+        self.inner.is_alive()
+    }
+
+    /// Block until new output arrives or `timeout` elapses.
+    pub fn wait_for_output(&self, timeout: Duration) -> bool {
+        self.inner.wait_for_output(timeout)
+    }
+
     pub fn wait_for<F: Fn(&str) -> bool>(
         &mut self,
         matcher: F,
@@ -57,13 +157,16 @@ impl Session {
         self.inner.wait_for_stable(timeout, interval, verbose)
     }
 
-    /// Kill sessions registered by the current process (used by Ctrl+C handler).
+    /// Kill sessions registered by the current process and remove their temp
+    /// directories (used by Ctrl+C handler).
     pub fn kill_registered_sessions() {
         pty::kill_registered_sessions();
+        crate::isolation::remove_registered_dirs();
     }
 
-    /// Kill any currently registered PTY groups.
-    pub fn kill_all_stale_sessions() {
-        pty::kill_registered_sessions();
+    /// Kill provider process groups that crashed agentusage runs left
+    /// behind (found through the on-disk registry), reporting each one.
+    pub fn kill_all_stale_sessions() -> Vec<crate::registry::Leftover> {
+        crate::registry::reap_orphans()
     }
 }
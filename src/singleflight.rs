@@ -0,0 +1,163 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::types::UsageData;
+
+/// A check in progress, and its result once it lands.
+#[derive(Default)]
+struct Flight {
+    result: Mutex<Option<Result<UsageData, String>>>,
+    done: Condvar,
+}
+
+/// Coalesces concurrent checks of the same provider: the first caller runs
+/// the check and everyone who asks while it is in flight waits for, and
+/// shares, its result.  Meant for long-running hosts (a server or daemon)
+/// where a burst of requests would otherwise launch one CLI each.
+#[derive(Default)]
+pub struct SingleFlight {
+    in_flight: Mutex<HashMap<String, Arc<Flight>>>,
+}
+
+impl SingleFlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `check` for `provider`, unless a check of it is already running,
+    /// in which case wait for that one's result.  Errors are shared by
+    /// message.
+    pub fn run(
+        &self,
+        provider: &str,
+        check: impl FnOnce() -> Result<UsageData>,
+    ) -> Result<UsageData> {
+        let (flight, leader) = {
+            let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+            match in_flight.get(provider) {
+                Some(flight) => (Arc::clone(flight), false),
+                None => {
+                    let flight = Arc::new(Flight::default());
+                    in_flight.insert(provider.to_string(), Arc::clone(&flight));
+                    (flight, true)
+                }
+            }
+        };
+
+        if leader {
+            let mut landing = Landing {
+                flights: self,
+                provider,
+                flight,
+                shared: None,
+            };
+            let result = check();
+            landing.shared = Some(match &result {
+                Ok(data) => Ok(data.clone()),
+                Err(e) => Err(format!("{:#}", e)),
+            });
+            return result;
+        }
+
+        let mut result = flight.result.lock().unwrap_or_else(|e| e.into_inner());
+        while result.is_none() {
+            result = flight.done.wait(result).unwrap_or_else(|e| e.into_inner());
+        }
+        match result.as_ref() {
+            Some(Ok(data)) => Ok(data.clone()),
+            Some(Err(message)) => Err(anyhow!("{}", message)),
+            None => unreachable!("loop exits once a result is set"),
+        }
+    }
+}
+
+/// Lands the leader's flight when it goes out of scope, so a check that
+/// panics still releases its waiters (with an error) instead of leaving
+/// them, and every later caller, blocked on a result that never comes.
+struct Landing<'a> {
+    flights: &'a SingleFlight,
+    provider: &'a str,
+    flight: Arc<Flight>,
+    shared: Option<Result<UsageData, String>>,
+}
+
+impl Drop for Landing<'_> {
+    fn drop(&mut self) {
+        // Later callers start a fresh check rather than reuse this one.
+        self.flights
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(self.provider);
+        let shared = self
+            .shared
+            .take()
+            .unwrap_or_else(|| Err("check panicked".to_string()));
+        *self.flight.result.lock().unwrap_or_else(|e| e.into_inner()) = Some(shared);
+        self.flight.done.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_concurrent_callers_share_one_check() {
+        let flights = Arc::new(SingleFlight::new());
+        let launches = Arc::new(AtomicU32::new(0));
+        let callers: Vec<_> = (0..8)
+            .map(|_| {
+                let flights = Arc::clone(&flights);
+                let launches = Arc::clone(&launches);
+                thread::spawn(move || {
+                    flights.run("codex", || {
+                        launches.fetch_add(1, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(200));
+                        crate::fixtures::mock_usage("codex").ok_or_else(|| anyhow!("no fixture"))
+                    })
+                })
+            })
+            .collect();
+        for caller in callers {
+            assert_eq!(caller.join().unwrap().unwrap().provider, "codex");
+        }
+        assert_eq!(launches.load(Ordering::SeqCst), 1);
+
+        // Once it has landed, the next call checks again; errors are shared too.
+        let err = flights
+            .run("codex", || Err(anyhow!("[timeout] Timed out")))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "[timeout] Timed out");
+    }
+
+    #[test]
+    fn test_leader_panic_releases_waiters() {
+        let flights = Arc::new(SingleFlight::new());
+        let leader = {
+            let flights = Arc::clone(&flights);
+            thread::spawn(move || {
+                flights.run("codex", || {
+                    thread::sleep(Duration::from_millis(200));
+                    panic!("parser bug")
+                })
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        let err = flights
+            .run("codex", || Err(anyhow!("waiter ran its own check")))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "check panicked");
+        assert!(leader.join().is_err());
+
+        // The next call runs a check of its own.
+        assert!(flights
+            .run("codex", || crate::fixtures::mock_usage("codex")
+                .ok_or_else(|| anyhow!("no fixture")))
+            .is_ok());
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::types::{LimitWindow, UsageEntry};
+
+#[cfg(feature = "runner")]
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "runner")]
+use std::path::{Path, PathBuf};
+
+/// Percent used at which a limit warns, for windows without a threshold of
+/// their own.
+pub const DEFAULT_WARN_AT: u32 = 90;
+
+/// How much of a limit may be used before it warns, per window kind: warn
+/// at 70% of the weekly limit but 95% of the session.  The table colours
+/// limits past their threshold, notifications report them as low when
+/// they cross it, and `report` counts the stretches spent there.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Thresholds {
+    /// Percent used, for windows not in `windows`.
+    pub default: u32,
+    pub windows: BTreeMap<LimitWindow, u32>,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            default: DEFAULT_WARN_AT,
+            windows: BTreeMap::new(),
+        }
+    }
+}
+
+impl Thresholds {
+    /// Percent used at which a `window` limit warns.
+    pub fn warn_at(&self, window: LimitWindow) -> u32 {
+        self.windows.get(&window).copied().unwrap_or(self.default)
+    }
+
+    /// Whether `entry` has reached its window's threshold.
+    pub fn is_over(&self, entry: &UsageEntry) -> bool {
+        entry.percent_used >= self.warn_at(entry.window)
+    }
+
+    /// Set `window`'s threshold, or the default with `None`.
+    pub fn set(&mut self, window: Option<LimitWindow>, percent: u32) {
+        match window {
+            Some(window) => {
+                self.windows.insert(window, percent);
+            }
+            None => self.default = percent,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/agentusage/thresholds.toml` (default
+/// `~/.config/agentusage/thresholds.toml`).
+#[cfg(feature = "runner")]
+pub fn default_path() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty());
+    let config_home = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("agentusage").join("thresholds.toml"))
+}
+
+#[cfg(feature = "runner")]
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThresholdsFile {
+    default: Option<u32>,
+    session: Option<u32>,
+    daily: Option<u32>,
+    weekly: Option<u32>,
+    monthly: Option<u32>,
+    other: Option<u32>,
+}
+
+/// Parse a `thresholds.toml`: percent used per window kind (`session`,
+/// `daily`, `weekly`, `monthly`, `other`) and a `default` for the rest.
+#[cfg(feature = "runner")]
+pub fn parse(text: &str) -> Result<Thresholds> {
+    let file: ThresholdsFile = toml::from_str(text).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let mut thresholds = Thresholds::default();
+    let keys = [
+        (None, file.default),
+        (Some(LimitWindow::Session), file.session),
+        (Some(LimitWindow::Daily), file.daily),
+        (Some(LimitWindow::Weekly), file.weekly),
+        (Some(LimitWindow::Monthly), file.monthly),
+        (Some(LimitWindow::Other), file.other),
+    ];
+    for (window, percent) in keys {
+        let Some(percent) = percent else {
+            continue;
+        };
+        if percent > 100 {
+            bail!(
+                "{} threshold is {}%; expected 0-100",
+                window.map_or("default", |w| w.as_str()),
+                percent
+            );
+        }
+        thresholds.set(window, percent);
+    }
+    Ok(thresholds)
+}
+
+/// Load the thresholds at `path`; a missing file leaves the defaults.
+#[cfg(feature = "runner")]
+pub fn load(path: &Path) -> Result<Thresholds> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => parse(&text).with_context(|| format!("Invalid {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Thresholds::default()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// `load` the default `thresholds.toml`.
+#[cfg(feature = "runner")]
+pub fn load_default() -> Result<Thresholds> {
+    match default_path() {
+        Some(path) => load(&path),
+        None => Ok(Thresholds::default()),
+    }
+}
+
+#[cfg(all(test, feature = "runner"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_thresholds() {
+        let thresholds = parse("weekly = 70\nsession = 95\n").unwrap();
+        assert_eq!(thresholds.warn_at(LimitWindow::Weekly), 70);
+        assert_eq!(thresholds.warn_at(LimitWindow::Session), 95);
+        assert_eq!(thresholds.warn_at(LimitWindow::Daily), DEFAULT_WARN_AT);
+
+        let thresholds = parse("default = 80").unwrap();
+        assert_eq!(thresholds.warn_at(LimitWindow::Monthly), 80);
+
+        assert!(parse("weekly = 170").is_err());
+        assert!(parse("hourly = 50").is_err());
+    }
+}
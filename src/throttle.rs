@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+use crate::registry;
+
+/// `launches/` under the state home: the time each provider was last
+/// launched for a real check, by any agentusage run.
+fn launches_dir() -> Option<PathBuf> {
+    Some(registry::state_home()?.join("launches"))
+}
+
+/// When `provider` was last launched, if any run has recorded it.
+pub(crate) fn last_launch(provider: &str) -> Option<DateTime<Utc>> {
+    last_launch_in(&launches_dir()?, provider)
+}
+
+fn last_launch_in(dir: &Path, provider: &str) -> Option<DateTime<Utc>> {
+    std::fs::read_to_string(dir.join(provider))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Stamp `provider` as launched now.  Best effort, like the session
+/// registry: an unwritable state dir only loses the rate limit.
+pub(crate) fn record_launch(provider: &str) {
+    if let Some(dir) = launches_dir() {
+        record_launch_in(&dir, provider, Utc::now());
+    }
+}
+
+fn record_launch_in(dir: &Path, provider: &str, at: DateTime<Utc>) {
+    if std::fs::create_dir_all(dir).is_ok() {
+        let _ = std::fs::write(dir.join(provider), at.to_rfc3339());
+    }
+}
+
+/// Drop the launch stamp of `provider` (every provider when `None`), so
+/// `--min-interval` doesn't hold back its next check.
+pub(crate) fn forget(provider: Option<&str>) {
+    if let Some(dir) = launches_dir() {
+        forget_in(&dir, provider);
+    }
+}
+
+fn forget_in(dir: &Path, provider: Option<&str>) {
+    match provider {
+        Some(provider) => {
+            let _ = std::fs::remove_file(dir.join(provider));
+        }
+        None => {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::TempDir;
+
+    #[test]
+    fn test_launch_stamps_round_trip_per_provider() {
+        let dir = TempDir::create("throttle-test").unwrap();
+        assert_eq!(last_launch_in(dir.path(), "claude"), None);
+        let at: DateTime<Utc> = "2026-03-01T12:00:00Z".parse().unwrap();
+        record_launch_in(dir.path(), "claude", at);
+        assert_eq!(last_launch_in(dir.path(), "claude"), Some(at));
+        assert_eq!(last_launch_in(dir.path(), "codex"), None);
+    }
+
+    #[test]
+    fn test_forget_one_provider_or_all() {
+        let dir = TempDir::create("throttle-forget-test").unwrap();
+        let at: DateTime<Utc> = "2026-03-01T12:00:00Z".parse().unwrap();
+        record_launch_in(dir.path(), "claude", at);
+        record_launch_in(dir.path(), "codex", at);
+        forget_in(dir.path(), Some("claude"));
+        assert_eq!(last_launch_in(dir.path(), "claude"), None);
+        assert_eq!(last_launch_in(dir.path(), "codex"), Some(at));
+        forget_in(dir.path(), None);
+        assert_eq!(last_launch_in(dir.path(), "codex"), None);
+    }
+}
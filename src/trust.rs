@@ -0,0 +1,306 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Adds trust for a directory to a config file's text; `None` if present.
+type Seeder = fn(&str, &str) -> Result<Option<String>>;
+
+/// How many times `preseed` re-reads a config that changed under it.
+const SEED_ATTEMPTS: u32 = 3;
+
+/// Mark `dir` as trusted in `provider`'s config.  Returns the file written,
+/// or `None` when trust was already recorded (or the provider keeps none).
+/// JSON configs keep their key order.  The CLI may be rewriting its config
+/// at the same time, so a file that changed since it was read is read and
+/// seeded again rather than overwritten.
+pub(crate) fn preseed(provider: &str, dir: &Path) -> Result<Option<PathBuf>> {
+    let dir = std::fs::canonicalize(dir)
+        .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+    let dir = dir.to_str().context("Directory path is not valid UTF-8")?;
+    let (path, seed): (PathBuf, Seeder) = match provider {
+        "claude" => (claude_config_path()?, seed_claude),
+        "codex" => (codex_config_path()?, seed_codex),
+        "gemini" => (home()?.join(".gemini/trustedFolders.json"), seed_gemini),
+        _ => return Ok(None),
+    };
+    for _ in 0..SEED_ATTEMPTS {
+        let current = read_config(&path)?;
+        let Some(updated) =
+            seed(&current, dir).with_context(|| format!("Failed to update {}", path.display()))?
+        else {
+            return Ok(None);
+        };
+        if write_atomic(&path, &current, &updated)? {
+            return Ok(Some(path));
+        }
+    }
+    anyhow::bail!(
+        "{} kept changing while trust was being added; left it alone",
+        path.display()
+    )
+}
+
+/// The config's text, or empty when there is none yet.
+fn read_config(path: &Path) -> Result<String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn home() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .map(PathBuf::from)
+        .context("HOME is not set")
+}
+
+/// Claude Code's state directory: `$CLAUDE_CONFIG_DIR`, or `~/.claude`.
+pub(crate) fn claude_home() -> Result<PathBuf> {
+    match std::env::var_os("CLAUDE_CONFIG_DIR").filter(|d| !d.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(home()?.join(".claude")),
+    }
+}
+
+fn claude_config_path() -> Result<PathBuf> {
+    match std::env::var_os("CLAUDE_CONFIG_DIR").filter(|d| !d.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir).join(".claude.json")),
+        None => Ok(home()?.join(".claude.json")),
+    }
+}
+
+/// Codex's state directory: `$CODEX_HOME`, or `~/.codex`.
+pub(crate) fn codex_home() -> Result<PathBuf> {
+    match std::env::var_os("CODEX_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(home()?.join(".codex")),
+    }
+}
+
+fn codex_config_path() -> Result<PathBuf> {
+    Ok(codex_home()?.join("config.toml"))
+}
+
+static NEXT_TEMP: AtomicU32 = AtomicU32::new(0);
+
+/// Replace `path` via a sibling temp file so the CLI never reads a
+/// half-written config.  A symlinked config is written through to its
+/// target, and the file keeps its mode (`~/.claude.json` holds OAuth state);
+/// a new one is private to the user.  Returns `false`, writing nothing,
+/// when the file no longer holds `expected` by the time it would be
+/// replaced.
+fn write_atomic(path: &Path, expected: &str, contents: &str) -> Result<bool> {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let permissions = std::fs::metadata(&path).ok().map(|m| m.permissions());
+    let tmp = path.with_extension(format!(
+        "agentusage-{}-{}.tmp",
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            if let Some(permissions) = permissions {
+                file.set_permissions(permissions)?;
+            }
+            Ok(())
+        })
+        .map_err(anyhow::Error::from)
+        .and_then(|()| {
+            if read_config(&path)? != expected {
+                return Ok(false);
+            }
+            std::fs::rename(&tmp, &path)?;
+            Ok(true)
+        });
+    if !matches!(written, Ok(true)) {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn json_object(text: &str) -> Result<Map<String, Value>> {
+    if text.trim().is_empty() {
+        return Ok(Map::new());
+    }
+    match serde_json::from_str(text)? {
+        Value::Object(map) => Ok(map),
+        _ => anyhow::bail!("expected a JSON object"),
+    }
+}
+
+/// `~/.claude.json`: `projects.<dir>.hasTrustDialogAccepted`.
+fn seed_claude(text: &str, dir: &str) -> Result<Option<String>> {
+    let mut root = json_object(text)?;
+    let projects = root
+        .entry("projects")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .context("\"projects\" is not an object")?;
+    let project = projects
+        .entry(dir)
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .context("project entry is not an object")?;
+    if project.get("hasTrustDialogAccepted") == Some(&Value::Bool(true)) {
+        return Ok(None);
+    }
+    project.insert("hasTrustDialogAccepted".into(), Value::Bool(true));
+    Ok(Some(serde_json::to_string_pretty(&root)?))
+}
+
+/// `~/.gemini/trustedFolders.json`: `{ "<dir>": "TRUST_FOLDER" }`.
+fn seed_gemini(text: &str, dir: &str) -> Result<Option<String>> {
+    let mut root = json_object(text)?;
+    if root.get(dir).and_then(Value::as_str) == Some("TRUST_FOLDER") {
+        return Ok(None);
+    }
+    root.insert(dir.into(), Value::String("TRUST_FOLDER".into()));
+    Ok(Some(serde_json::to_string_pretty(&root)?))
+}
+
+/// `~/.codex/config.toml`: a `[projects."<dir>"]` table with
+/// `trust_level = "trusted"`.  Appended as text so the user's formatting and
+/// comments survive; an existing entry for `dir`, however it is written, is
+/// left alone.
+fn seed_codex(text: &str, dir: &str) -> Result<Option<String>> {
+    let config = parse_toml(text)?;
+    if config
+        .get("projects")
+        .and_then(|projects| projects.get(dir))
+        .is_some()
+    {
+        return Ok(None);
+    }
+    let quoted = format!("\"{}\"", dir.replace('\\', "\\\\").replace('"', "\\\""));
+    let header = format!("[projects.{}]", quoted);
+    let mut out = text.to_string();
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&header);
+    out.push_str("\ntrust_level = \"trusted\"\n");
+    // `projects` written inline can't take another table; never leave a
+    // config Codex would fail to start with.
+    parse_toml(&out).context("an appended table would not fit this file's `projects`")?;
+    Ok(Some(out))
+}
+
+fn parse_toml(text: &str) -> Result<toml::Table> {
+    text.parse::<toml::Table>()
+        .map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_claude_keeps_other_settings() {
+        let text = r#"{"numStartups": 3, "projects": {"/work": {"hasTrustDialogAccepted": true}}}"#;
+        let updated = seed_claude(text, "/tmp/agentusage").unwrap().unwrap();
+        let value: Value = serde_json::from_str(&updated).unwrap();
+        assert_eq!(value["numStartups"], 3);
+        assert_eq!(value["projects"]["/work"]["hasTrustDialogAccepted"], true);
+        assert_eq!(
+            value["projects"]["/tmp/agentusage"]["hasTrustDialogAccepted"],
+            true
+        );
+        assert!(seed_claude(&updated, "/tmp/agentusage").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_seed_gemini_from_missing_file() {
+        let updated = seed_gemini("", "/tmp/agentusage").unwrap().unwrap();
+        let value: Value = serde_json::from_str(&updated).unwrap();
+        assert_eq!(value["/tmp/agentusage"], "TRUST_FOLDER");
+        assert!(seed_gemini(&updated, "/tmp/agentusage").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_seed_codex_appends_table_once() {
+        let text = "model = \"gpt-5\"\n# keep me";
+        let updated = seed_codex(text, "/tmp/agentusage").unwrap().unwrap();
+        assert_eq!(
+            updated,
+            "model = \"gpt-5\"\n# keep me\n\n[projects.\"/tmp/agentusage\"]\ntrust_level = \"trusted\"\n"
+        );
+        assert!(seed_codex(&updated, "/tmp/agentusage").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_seed_codex_finds_entries_written_any_way() {
+        for text in [
+            "[projects.'/tmp/agentusage']\ntrust_level = \"trusted\"\n",
+            "[ projects . \"/tmp/agentusage\" ]\ntrust_level = \"trusted\"\n",
+            "projects.\"/tmp/agentusage\".trust_level = \"trusted\"\n",
+            "projects = { \"/tmp/agentusage\" = { trust_level = \"trusted\" } }\n",
+        ] {
+            assert!(
+                seed_codex(text, "/tmp/agentusage").unwrap().is_none(),
+                "{}",
+                text
+            );
+        }
+        // An inline `projects` can't take a new table.
+        let inline = "projects = { \"/work\" = { trust_level = \"trusted\" } }\n";
+        assert!(seed_codex(inline, "/tmp/agentusage").is_err());
+        assert!(seed_codex("model = ", "/tmp/agentusage").is_err());
+    }
+
+    #[test]
+    fn test_write_atomic_keeps_mode_and_symlink() {
+        use crate::isolation::TempDir;
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::create("trust-test").unwrap();
+        let target = dir.path().join("dotfiles.json");
+        let link = dir.path().join(".claude.json");
+        std::fs::write(&target, "{}").unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o600)).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        assert!(!write_atomic(&link, "{\"b\": 2}", "{\"a\": 1}").unwrap());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "{}");
+        assert!(write_atomic(&link, "{}", "{\"a\": 1}").unwrap());
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "{\"a\": 1}");
+        let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // No temp files left beside it.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_seed_claude_keeps_key_order() {
+        let text = r#"{"numStartups": 3, "autoUpdates": false, "projects": {}}"#;
+        let updated = seed_claude(text, "/tmp/agentusage").unwrap().unwrap();
+        let at = |key: &str| updated.find(key).unwrap();
+        assert!(at("numStartups") < at("autoUpdates"));
+        assert!(at("autoUpdates") < at("projects"));
+    }
+
+    #[test]
+    fn test_seed_rejects_malformed_json() {
+        assert!(seed_claude("[1, 2]", "/tmp").is_err());
+        assert!(seed_gemini("{not json", "/tmp").is_err());
+    }
+}
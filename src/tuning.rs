@@ -0,0 +1,131 @@
+use serde::Serialize;
+
+use crate::{Environment, UsageConfig};
+
+/// Prompt wait, in seconds, on a CI runner, container, or SSH host, where
+/// CLIs start slower than on a workstation.
+pub const HEADLESS_PROMPT_TIMEOUT_SECS: u64 = 60;
+
+/// A default `auto_tune` changed, with the reason, for verbose output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tuning {
+    pub setting: &'static str,
+    pub value: String,
+    pub reason: String,
+}
+
+/// What kind of unattended host `env` is, if any: "CI", "container", or
+/// "SSH", most specific first.
+pub fn headless_kind(env: &Environment) -> Option<&'static str> {
+    if env.ci {
+        Some("CI")
+    } else if env.container {
+        Some("container")
+    } else if env.ssh {
+        Some("SSH")
+    } else {
+        None
+    }
+}
+
+/// Adjust `config`'s defaults for a CI runner, container, or SSH host and
+/// return what changed.  Only settings still at their default are touched,
+/// so explicit options always win:
+///
+/// - the prompt wait grows to `HEADLESS_PROMPT_TIMEOUT_SECS` (or the data
+///   timeout, if longer), since cold starts there are slow;
+/// - provider update checks are deferred (`defer_updates`), as nobody is
+///   there to answer an update prompt;
+/// - on CI and in containers, providers are checked one at a time
+///   (`sequential`), since runners tend to be short on cores and memory.
+pub fn auto_tune(config: &mut UsageConfig, env: &Environment) -> Vec<Tuning> {
+    let Some(kind) = headless_kind(env) else {
+        return Vec::new();
+    };
+    let reason = format!("{} environment", kind);
+    let mut tunings = Vec::new();
+    if config.prompt_timeout.is_none() {
+        let secs = HEADLESS_PROMPT_TIMEOUT_SECS.max(config.timeout);
+        config.prompt_timeout = Some(secs);
+        tunings.push(Tuning {
+            setting: "prompt_timeout",
+            value: format!("{}s", secs),
+            reason: reason.clone(),
+        });
+    }
+    if !config.defer_updates {
+        config.defer_updates = true;
+        tunings.push(Tuning {
+            setting: "defer_updates",
+            value: "on".into(),
+            reason: reason.clone(),
+        });
+    }
+    if kind != "SSH" && !config.sequential {
+        config.sequential = true;
+        tunings.push(Tuning {
+            setting: "sequential",
+            value: "on".into(),
+            reason,
+        });
+    }
+    tunings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(ci: bool, container: bool, ssh: bool) -> Environment {
+        Environment {
+            term: None,
+            colorterm: None,
+            lang: None,
+            shell: None,
+            os: "linux".into(),
+            arch: "x86_64".into(),
+            ssh,
+            ci,
+            container,
+        }
+    }
+
+    #[test]
+    fn test_workstation_is_left_alone() {
+        let mut config = UsageConfig::default();
+        assert!(auto_tune(&mut config, &env(false, false, false)).is_empty());
+        assert_eq!(config.prompt_timeout, None);
+        assert!(!config.sequential);
+    }
+
+    #[test]
+    fn test_ci_tunes_defaults_but_not_explicit_settings() {
+        let mut config = UsageConfig::default();
+        let tunings = auto_tune(&mut config, &env(true, true, false));
+        let settings: Vec<&str> = tunings.iter().map(|t| t.setting).collect();
+        assert_eq!(settings, ["prompt_timeout", "defer_updates", "sequential"]);
+        assert_eq!(tunings[0].reason, "CI environment");
+        assert_eq!(config.prompt_timeout, Some(HEADLESS_PROMPT_TIMEOUT_SECS));
+        assert!(config.defer_updates && config.sequential);
+
+        let mut config = UsageConfig {
+            prompt_timeout: Some(10),
+            timeout: 90,
+            ..UsageConfig::default()
+        };
+        let tunings = auto_tune(&mut config, &env(true, false, false));
+        assert_eq!(config.prompt_timeout, Some(10));
+        assert_eq!(tunings.len(), 2);
+    }
+
+    #[test]
+    fn test_ssh_keeps_parallel_checks() {
+        let mut config = UsageConfig {
+            timeout: 90,
+            ..UsageConfig::default()
+        };
+        auto_tune(&mut config, &env(false, false, true));
+        assert_eq!(config.prompt_timeout, Some(90));
+        assert!(!config.sequential);
+    }
+}
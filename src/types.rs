@@ -1,11 +1,26 @@
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 pub enum ApprovalPolicy {
     /// Fail with an error when a dialog is detected
     Fail,
     /// Automatically accept/dismiss dialogs
     Accept,
+    /// Like accept, and also complete first-run theme/onboarding screens with defaults
+    Onboard,
+}
+
+/// How `--attach` mirrors a provider session to the user's terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum AttachMode {
+    /// Show the session's screen; keystrokes are not forwarded
+    View,
+    /// Show the session and forward keystrokes to it (Ctrl-] stops forwarding)
+    Interactive,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -15,18 +30,427 @@ pub enum DialogKind {
     AuthRequired,
     TermsAcceptance,
     FirstRunSetup,
+    ThemeSelection,
+    OnboardingStep,
+    ModelSelection,
     SandboxTrust,
-    #[allow(dead_code)]
+    /// A modal-looking screen that matched no known kind; carries the captured lines.
     Unknown(String),
 }
 
+impl DialogKind {
+    /// Stable snake_case identifier for machine-readable output.
+    pub fn id(&self) -> &'static str {
+        match self {
+            DialogKind::TrustFolder => "trust_folder",
+            DialogKind::UpdatePrompt => "update_prompt",
+            DialogKind::AuthRequired => "auth_required",
+            DialogKind::TermsAcceptance => "terms_acceptance",
+            DialogKind::FirstRunSetup => "first_run_setup",
+            DialogKind::ThemeSelection => "theme_selection",
+            DialogKind::OnboardingStep => "onboarding_step",
+            DialogKind::ModelSelection => "model_selection",
+            DialogKind::SandboxTrust => "sandbox_trust",
+            DialogKind::Unknown(_) => "unknown",
+        }
+    }
+}
+
+/// Coarse progress of a single provider check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckPhase {
+    /// Starting the CLI in a PTY
+    Launching,
+    /// CLI launched; waiting for its input prompt
+    WaitingForPrompt,
+    /// Usage command sent; waiting for usage data
+    FetchingUsage,
+}
+
+impl CheckPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckPhase::Launching => "launching",
+            CheckPhase::WaitingForPrompt => "waiting for prompt",
+            CheckPhase::FetchingUsage => "fetching usage",
+        }
+    }
+
+    /// Stable snake_case identifier for machine-readable output.
+    pub fn id(&self) -> &'static str {
+        match self {
+            CheckPhase::Launching => "launching",
+            CheckPhase::WaitingForPrompt => "waiting_for_prompt",
+            CheckPhase::FetchingUsage => "fetching_usage",
+        }
+    }
+}
+
+/// Progress event emitted while a provider check runs.
 #[derive(Debug, Clone, PartialEq)]
+pub enum CheckEvent {
+    /// The check moved to a new phase.
+    Phase(CheckPhase),
+    /// A dialog was detected on screen.
+    DialogDetected(DialogKind),
+    /// A detected dialog was dismissed.
+    DialogDismissed(DialogKind),
+    /// A step was retried; carries a short reason id.
+    Retry(&'static str),
+    /// A different command or path was tried after the first one came up
+    /// empty (e.g. Claude `/usage` → `/status`); carries a short reason id.
+    Fallback(&'static str),
+}
+
+/// How one wait for screen content ended, as reported to
+/// `CheckObserver::on_wait` for waits run with `verbose`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitStats {
+    /// `"matched"`, `"stable"`, or `"timed out"`.
+    pub outcome: &'static str,
+    pub elapsed: Duration,
+    /// Screens captured.
+    pub polls: u32,
+    /// Captures that had changed, so the matcher ran on them.
+    pub parses: u32,
+    /// Fingerprint of the last capture, to tell screens apart across waits.
+    pub capture_hash: u64,
+}
+
+/// How a check's attempt at getting usage data came about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttemptKind {
+    /// The first try.
+    Initial,
+    /// A step was retried (`CheckEvent::Retry`).
+    Retry,
+    /// A different path was taken (`CheckEvent::Fallback`).
+    Fallback,
+}
+
+/// One try at getting usage data during a check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Attempt {
+    pub kind: AttemptKind,
+    /// The retry or fallback reason id; `"initial"` for the first try.
+    pub reason: &'static str,
+    /// Milliseconds into the check when this attempt began.
+    pub started_ms: u64,
+    /// Warnings raised during this attempt, each listed once.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// A failed check, with the attempts it made.  Its message leads with a
+/// summary of the retries and warnings (when there were any) before the
+/// final error, so one warning per provider carries the whole story; the
+/// list itself is available by downcasting the `anyhow::Error`.
+#[derive(Debug)]
+pub struct CheckFailure {
+    pub attempts: Vec<Attempt>,
+    pub error: anyhow::Error,
+}
+
+impl CheckFailure {
+    /// Whether there is more to say than the final error.
+    fn eventful(&self) -> bool {
+        self.attempts.len() > 1 || self.attempts.iter().any(|a| !a.warnings.is_empty())
+    }
+}
+
+impl std::fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.eventful() {
+            let steps: Vec<String> = self
+                .attempts
+                .iter()
+                .skip(1)
+                .map(|a| match a.kind {
+                    AttemptKind::Fallback => format!("fallback {}", a.reason),
+                    _ => format!("retry {}", a.reason),
+                })
+                .collect();
+            let plural = if self.attempts.len() == 1 { "" } else { "s" };
+            write!(f, "failed after {} attempt{}", self.attempts.len(), plural)?;
+            if !steps.is_empty() {
+                write!(f, " ({})", steps.join(", "))?;
+            }
+            let mut warnings: Vec<&str> = Vec::new();
+            for warning in self.attempts.iter().flat_map(|a| &a.warnings) {
+                if !warnings.contains(&warning.as_str()) {
+                    warnings.push(warning);
+                }
+            }
+            if !warnings.is_empty() {
+                write!(f, "; warnings: {}", warnings.join("; "))?;
+            }
+            write!(f, ": ")?;
+        }
+        if f.alternate() {
+            write!(f, "{:#}", self.error)
+        } else {
+            write!(f, "{}", self.error)
+        }
+    }
+}
+
+impl std::error::Error for CheckFailure {}
+
+/// How much effort a successful check took, for spotting flaky environments.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckStats {
+    /// Usage requests made, including retries and fallbacks.
+    pub attempts: u32,
+    /// Fallback paths taken.
+    pub fallbacks: u32,
+    /// Dialogs dismissed.
+    pub dialogs: u32,
+    /// The wait for usage data timed out and these are only the entries
+    /// parsed before it did (see `UsageConfig::allow_partial`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// Each attempt in order; empty when the check wasn't run through a
+    /// provider session (mock data, parsed text).
+    #[serde(skip)]
+    pub log: Vec<Attempt>,
+    /// Where the values came from.
+    #[serde(skip)]
+    pub source: DataSource,
+}
+
+impl Default for CheckStats {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            fallbacks: 0,
+            dialogs: 0,
+            partial: false,
+            log: Vec::new(),
+            source: DataSource::Tui,
+        }
+    }
+}
+
+impl CheckStats {
+    /// Tally one progress event.
+    pub fn record(&mut self, event: &CheckEvent) {
+        match event {
+            CheckEvent::Retry(_) => self.attempts += 1,
+            CheckEvent::Fallback(_) => {
+                self.attempts += 1;
+                self.fallbacks += 1;
+            }
+            CheckEvent::DialogDismissed(_) => self.dialogs += 1,
+            CheckEvent::Phase(_) | CheckEvent::DialogDetected(_) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum PercentKind {
+    #[default]
     Used,
     Left,
 }
 
-#[derive(Debug, Serialize)]
+/// Where a provider's usage values were read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataSource {
+    /// Scraped from the provider's own CLI (its TUI, or a non-interactive
+    /// run of it).
+    #[default]
+    Tui,
+    /// Read from the provider's local files.
+    Local,
+    /// Fetched from the provider's API.
+    Api,
+}
+
+impl DataSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataSource::Tui => "tui",
+            DataSource::Local => "local",
+            DataSource::Api => "api",
+        }
+    }
+}
+
+/// Which sources a check may read a provider's usage from.  Each mode is a
+/// chain tried in order that ends with the TUI, so a provider without an
+/// API or logs, or whose API or logs fail, is still checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceMode {
+    /// The API, then local logs, then the TUI.
+    Auto,
+    /// The API, then the TUI.
+    Api,
+    /// Local logs, then the TUI.
+    Logs,
+    /// Only the TUI.
+    #[default]
+    Tui,
+}
+
+impl SourceMode {
+    pub const ALL: [SourceMode; 4] = [
+        SourceMode::Auto,
+        SourceMode::Api,
+        SourceMode::Logs,
+        SourceMode::Tui,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceMode::Auto => "auto",
+            SourceMode::Api => "api",
+            SourceMode::Logs => "logs",
+            SourceMode::Tui => "tui",
+        }
+    }
+
+    /// The sources tried, in order.
+    pub fn chain(&self) -> &'static [DataSource] {
+        match self {
+            SourceMode::Auto => &[DataSource::Api, DataSource::Local, DataSource::Tui],
+            SourceMode::Api => &[DataSource::Api, DataSource::Tui],
+            SourceMode::Logs => &[DataSource::Local, DataSource::Tui],
+            SourceMode::Tui => &[DataSource::Tui],
+        }
+    }
+}
+
+impl std::str::FromStr for SourceMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SourceMode::ALL
+            .into_iter()
+            .find(|mode| mode.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown source '{}' (expected one of: auto, api, logs, tui)",
+                    s.trim()
+                )
+            })
+    }
+}
+
+/// What a provider's results can contain, so consumers can lay out a UI up
+/// front instead of sniffing for absent fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct Capabilities {
+    /// Entries may carry `spent` (extra-usage dollars).
+    pub supports_spend: bool,
+    /// Entries may carry `requests` (request counts or quotas).
+    pub supports_requests: bool,
+    /// Entries carry `reset_minutes` when the reset time is shown.
+    pub supports_reset_minutes: bool,
+    pub source: DataSource,
+}
+
+/// Whose quota a limit draws from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitScope {
+    /// The signed-in user's own allowance.
+    #[default]
+    Personal,
+    /// Pooled across a workspace, team, or organization (Business and
+    /// Enterprise plans).
+    Workspace,
+}
+
+impl LimitScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitScope::Personal => "personal",
+            LimitScope::Workspace => "workspace",
+        }
+    }
+}
+
+/// How directly a parser read an entry off the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    /// The limit's own header or row label was matched.
+    #[default]
+    Exact,
+    /// The header was only partly legible, as in a noisy PTY capture, and
+    /// the values were read from the lines under what was left of it.
+    Fallback,
+    /// No header survived; the label was assigned from the order the
+    /// values appeared in.
+    Inferred,
+}
+
+impl Confidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::Exact => "exact",
+            Confidence::Fallback => "fallback",
+            Confidence::Inferred => "inferred",
+        }
+    }
+}
+
+/// The period a limit is measured over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitWindow {
+    /// A rolling session window (Claude's current session, Codex's 5h limit).
+    Session,
+    /// Resets every day (Gemini request quotas).
+    Daily,
+    Weekly,
+    /// Billing-period allowances such as Claude extra usage.
+    Monthly,
+    /// Anything the label doesn't pin down, e.g. pooled credits.
+    #[default]
+    Other,
+}
+
+impl LimitWindow {
+    pub const ALL: [LimitWindow; 5] = [
+        LimitWindow::Session,
+        LimitWindow::Daily,
+        LimitWindow::Weekly,
+        LimitWindow::Monthly,
+        LimitWindow::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitWindow::Session => "session",
+            LimitWindow::Daily => "daily",
+            LimitWindow::Weekly => "weekly",
+            LimitWindow::Monthly => "monthly",
+            LimitWindow::Other => "other",
+        }
+    }
+}
+
+impl std::str::FromStr for LimitWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LimitWindow::ALL
+            .into_iter()
+            .find(|window| window.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown window '{}' (expected one of: session, daily, weekly, monthly, other)",
+                    s.trim()
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageEntry {
     pub label: String,
     pub percent_used: u32,
@@ -40,10 +464,70 @@ pub struct UsageEntry {
     pub spent: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub requests: Option<String>,
+    /// Requests counted against a daily quota, when the screen shows a
+    /// count and the quota is known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_used: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_limit: Option<u64>,
+    pub scope: LimitScope,
+    pub window: LimitWindow,
+    #[serde(default)]
+    pub confidence: Confidence,
+    /// Set when two captures of the same check disagreed on this limit by
+    /// more than a few points; the value is from the later, settled one.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inconsistent: bool,
+    /// Set on a last-known value from history, standing in for a check
+    /// that failed: when that value was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_of: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsageData {
     pub provider: String,
     pub entries: Vec<UsageEntry>,
+    /// Whether the account pays for usage past its plan limits (Claude's
+    /// extra usage), when the usage screen says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_usage_enabled: Option<bool>,
+    /// Tokens used per model, for providers read from their transcripts
+    /// (Claude's `logs` source).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<TokenUsage>,
+    pub check: CheckStats,
+}
+
+/// One model's token counts over a period, with their estimated cost.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Input written to the prompt cache.
+    pub cache_creation_tokens: u64,
+    /// Input read back from the prompt cache.
+    pub cache_read_tokens: u64,
+    /// Estimated at API list prices; `None` for models without a known
+    /// price.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+impl UsageData {
+    /// What the provider registered under this result's name can report,
+    /// read from this result's source.  Unregistered names promise no
+    /// optional fields.
+    #[cfg(feature = "runner")]
+    pub fn capabilities(&self, registry: &crate::ProviderRegistry) -> Capabilities {
+        let capabilities = registry
+            .get(&self.provider)
+            .map(|provider| provider.capabilities())
+            .unwrap_or_default();
+        Capabilities {
+            source: self.check.source,
+            ..capabilities
+        }
+    }
 }
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::isolation::TempDir;
+
+/// Points Gemini at a system settings file, which outranks user and
+/// workspace settings.
+const GEMINI_SYSTEM_SETTINGS_VAR: &str = "GEMINI_CLI_SYSTEM_SETTINGS_PATH";
+
+/// Environment that switches off a provider's startup update check for one
+/// session, and the temp files it refers to (keep them for the session's
+/// lifetime).  The user's own settings files are never modified.
+pub(crate) struct UpdateDeferral {
+    pub env: Vec<(&'static str, OsString)>,
+    pub files: Option<TempDir>,
+}
+
+pub(crate) fn defer(provider: &str) -> Result<UpdateDeferral> {
+    let mut deferral = UpdateDeferral {
+        env: Vec::new(),
+        files: None,
+    };
+    match provider {
+        "claude" => deferral.env.push(("DISABLE_AUTOUPDATER", "1".into())),
+        "gemini" => {
+            let existing = match std::fs::read_to_string(gemini_system_settings_path()) {
+                Ok(text) => text,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(e).context("Failed to read Gemini system settings"),
+            };
+            let dir = TempDir::create("settings")?;
+            let path = dir.path().join("settings.json");
+            std::fs::write(&path, gemini_settings(&existing)?)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            deferral
+                .env
+                .push((GEMINI_SYSTEM_SETTINGS_VAR, path.into_os_string()));
+            deferral.files = Some(dir);
+        }
+        // Codex takes a `-c` override on the command line instead.
+        _ => {}
+    }
+    Ok(deferral)
+}
+
+/// Where Gemini reads system settings from today, so they can be carried
+/// into the replacement file.
+fn gemini_system_settings_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(GEMINI_SYSTEM_SETTINGS_VAR).filter(|p| !p.is_empty()) {
+        return PathBuf::from(path);
+    }
+    if cfg!(target_os = "macos") {
+        PathBuf::from("/Library/Application Support/GeminiCli/settings.json")
+    } else {
+        PathBuf::from("/etc/gemini-cli/settings.json")
+    }
+}
+
+/// `existing` system settings with auto-update and the update nag disabled.
+fn gemini_settings(existing: &str) -> Result<String> {
+    let mut root = if existing.trim().is_empty() {
+        Map::new()
+    } else {
+        match serde_json::from_str(existing).context("Gemini system settings are not JSON")? {
+            Value::Object(map) => map,
+            _ => anyhow::bail!("Gemini system settings are not a JSON object"),
+        }
+    };
+    let general = root
+        .entry("general")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .context("Gemini \"general\" settings are not an object")?;
+    general.insert("disableAutoUpdate".into(), Value::Bool(true));
+    general.insert("disableUpdateNag".into(), Value::Bool(true));
+    Ok(serde_json::to_string_pretty(&root)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gemini_settings_keep_existing_policy() {
+        let existing =
+            r#"{"general": {"vimMode": true}, "security": {"auth": {"enforcedType": "oauth"}}}"#;
+        let value: Value = serde_json::from_str(&gemini_settings(existing).unwrap()).unwrap();
+        assert_eq!(value["general"]["vimMode"], true);
+        assert_eq!(value["general"]["disableAutoUpdate"], true);
+        assert_eq!(value["general"]["disableUpdateNag"], true);
+        assert_eq!(value["security"]["auth"]["enforcedType"], "oauth");
+        assert!(gemini_settings("[]").is_err());
+    }
+
+    #[test]
+    fn test_defer_claude_uses_env_only() {
+        let deferral = defer("claude").unwrap();
+        assert_eq!(deferral.env, vec![("DISABLE_AUTOUPDATER", "1".into())]);
+        assert!(deferral.files.is_none());
+        assert!(defer("codex").unwrap().env.is_empty());
+    }
+}
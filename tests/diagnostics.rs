@@ -0,0 +1,72 @@
+#![cfg(unix)]
+
+use std::path::Path;
+use std::process::Command;
+
+/// Two providers from providers.toml: one that answers with a usage row,
+/// and one that exits before showing its prompt.
+const PROVIDERS: &str = r#"
+[[provider]]
+name = "acme"
+binary = "/bin/sh"
+args = ["-c", "printf 'acme> '; read line; echo 'Session quota: 42% used'; read line"]
+prompt = '^acme> '
+command = "/quota"
+row = '^(?P<label>\w+ quota):\s*(?P<percent>\d+)% used'
+
+[[provider]]
+name = "gone"
+binary = "/bin/sh"
+args = ["-c", "echo 'not signed in'"]
+prompt = '^gone> '
+command = "/quota"
+row = '^(?P<label>\w+ quota):\s*(?P<percent>\d+)% used'
+"#;
+
+fn run_verbose_json(home: &Path) -> String {
+    let config = home.join("config");
+    std::fs::create_dir_all(config.join("agentusage")).unwrap();
+    std::fs::create_dir_all(home.join("bin")).unwrap();
+    std::fs::write(config.join("agentusage/providers.toml"), PROVIDERS).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_agentusage"))
+        .args(["--json", "--verbose", "--diagnostics", "json"])
+        .args(["--no-plugins", "--no-auto-tune"])
+        .env_clear()
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", &config)
+        .env("XDG_STATE_HOME", home.join("state"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        // No built-in provider CLI to find.
+        .env("PATH", home.join("bin"))
+        .output()
+        .unwrap();
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn test_verbose_json_diagnostics_are_ndjson() {
+    let home = std::env::temp_dir().join(format!(
+        "agentusage-diagnostics-test-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&home);
+    let stderr = run_verbose_json(&home);
+    std::fs::remove_dir_all(&home).unwrap();
+
+    let events: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("stderr line is not JSON ({}): {:?}", e, line))
+        })
+        .collect();
+    let has = |provider: &str, key: &str, value: &str| {
+        events
+            .iter()
+            .any(|event| event["provider"] == provider && event[key] == value)
+    };
+    assert!(has("acme", "wait", "stable"), "{}", stderr);
+    assert!(has("acme", "screen", "final"), "{}", stderr);
+    assert!(has("gone", "screen", "exit"), "{}", stderr);
+}
@@ -0,0 +1,48 @@
+#![deny(warnings)]
+
+use agentusage::parser;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Parse a captured usage screen from `provider` ("claude", "codex" or
+/// "gemini") into the library's `UsageData` as a JSON string.  Reset times
+/// are computed against the current time in the browser's time zone.
+#[wasm_bindgen(js_name = parseUsage)]
+pub fn parse_usage(provider: &str, text: &str) -> Result<String, String> {
+    let data = parser::parse_output(provider, text).map_err(|e| format!("{:#}", e))?;
+    serde_json::to_string(&data).map_err(|e| e.to_string())
+}
+
+/// Minutes until the reset described by `reset_info` (a `reset_info` field
+/// or the provider's own wording), or `undefined` if it can't be read.
+#[wasm_bindgen(js_name = parseResetMinutes)]
+pub fn parse_reset_minutes(reset_info: &str, provider: &str) -> Option<f64> {
+    parser::parse_reset_minutes(reset_info, provider).map(|minutes| minutes as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_usage_returns_usage_data_json() {
+        let capture = "\
+│  Model Usage                 Requests today             Daily quota
+│  gemini-2.5-pro            120 / 1,500 requests   (Resets in 11h 20m)
+";
+        let json = parse_usage("gemini", capture).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["provider"], "gemini");
+        assert_eq!(value["entries"][0]["window"], "daily");
+        assert_eq!(value["entries"][0]["reset_minutes"], 680);
+        assert!(parse_usage("bogus", "").unwrap_err().contains("bogus"));
+    }
+
+    #[test]
+    fn test_parse_reset_minutes() {
+        assert_eq!(
+            parse_reset_minutes("Resets in 3h 3m", "gemini"),
+            Some(183.0)
+        );
+        assert_eq!(parse_reset_minutes("soon", "gemini"), None);
+    }
+}